@@ -1,10 +1,16 @@
 use anyhow::{anyhow, Result};
-use clap::{Parser, Subcommand};
+use base64::{engine::general_purpose, Engine as _};
+use clap::{Args, Parser, Subcommand};
+use chrono::{DateTime, Utc};
+use guard_core::event_log::{EventQuery, EventSeverity};
+use guard_core::event_replay::{check_artifacts, decode_device_key, replay_log};
 use guard_core::ipc::{
     AuthOk, ClientAuth, ClientHello, IpcEnvelope, IpcRequest, IpcResponse, RequestEnvelope,
     ResponseEnvelope, IPC_PROTOCOL_VERSION,
 };
-use guard_core::paths::{ipc_socket_path, status_socket_path};
+use guard_core::instances::{load_registry, set_instance};
+use guard_core::ipc_client::{send_remote_request, RemoteClientConfig};
+use guard_core::paths::{data_dir, ipc_socket_path, log_dir, status_socket_path};
 use guard_core::secure_storage::get_ipc_secret;
 use hmac::{Hmac, Mac};
 use rand::RngCore;
@@ -13,6 +19,8 @@ use std::path::PathBuf;
 use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
 use tokio::net::UnixStream;
 
+mod tui;
+
 async fn get_device_id() -> Result<String> {
     let socket_path = status_socket_path()?;
     let mut stream = UnixStream::connect(&socket_path).await?;
@@ -31,15 +39,125 @@ async fn get_device_id() -> Result<String> {
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    #[command(flatten)]
+    remote: RemoteArgs,
+
+    /// Named service instance to talk to (default: GUARD_INSTANCE or the default instance)
+    #[arg(long, global = true)]
+    instance: Option<String>,
+}
+
+/// Remote administration over TLS. The IPC secret is read from
+/// `GUARD_IPC_SECRET` (base64, as stored in the remote vault).
+#[derive(Args)]
+struct RemoteArgs {
+    /// Remote guard service address (host:port)
+    #[arg(long, global = true)]
+    remote: Option<String>,
+
+    /// Name on the server certificate (defaults to the host part of --remote)
+    #[arg(long, global = true, requires = "remote")]
+    server_name: Option<String>,
+
+    /// CA certificate that signed the server certificate
+    #[arg(long, global = true, requires = "remote")]
+    ca: Option<PathBuf>,
+
+    /// Client certificate, when the server requires one
+    #[arg(long, global = true, requires_all = ["remote", "client_key"])]
+    client_cert: Option<PathBuf>,
+
+    /// Private key for --client-cert
+    #[arg(long, global = true, requires_all = ["remote", "client_cert"])]
+    client_key: Option<PathBuf>,
+}
+
+impl RemoteArgs {
+    async fn send(&self, addr: &str, request: IpcRequest) -> Result<IpcResponse> {
+        let secret = std::env::var("GUARD_IPC_SECRET")
+            .map_err(|_| anyhow!("GUARD_IPC_SECRET must be set for --remote"))?;
+        let secret = general_purpose::STANDARD
+            .decode(secret.trim())
+            .map_err(|e| anyhow!("GUARD_IPC_SECRET is not valid base64: {e}"))?;
+        let server_name = match &self.server_name {
+            Some(name) => name.clone(),
+            None => addr
+                .rsplit_once(':')
+                .map(|(host, _)| host.trim_matches(['[', ']']).to_string())
+                .ok_or_else(|| anyhow!("--remote must be host:port"))?,
+        };
+        let config = RemoteClientConfig {
+            addr: addr.to_string(),
+            server_name,
+            ca_path: self
+                .ca
+                .clone()
+                .ok_or_else(|| anyhow!("--ca is required with --remote"))?,
+            client_cert_path: self.client_cert.clone(),
+            client_key_path: self.client_key.clone(),
+        };
+        send_remote_request(&config, &secret, request).await
+    }
+}
+
+/// Where requests go: the local service socket or a remote service.
+enum Connection<'a> {
+    Local(IpcClient),
+    Remote { args: &'a RemoteArgs, addr: &'a str },
+}
+
+impl<'a> Connection<'a> {
+    async fn open(remote: &'a RemoteArgs) -> Result<Self> {
+        Ok(match &remote.remote {
+            Some(addr) => Connection::Remote { args: remote, addr },
+            None => Connection::Local(IpcClient::connect().await?),
+        })
+    }
+
+    async fn send(&mut self, request: IpcRequest) -> Result<IpcResponse> {
+        match self {
+            Connection::Local(client) => client.send_request(request).await,
+            Connection::Remote { args, addr } => args.send(addr, request).await,
+        }
+    }
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Get service status
     Status,
+
+    /// Live terminal dashboard: status, recent events and quick actions
+    Tui,
+
+    /// Show per-path file counts, violations and restore success rates
+    PathStats,
+
+    /// Estimate file count, baseline time and watch usage for a path before protecting it
+    EstimatePath {
+        path: String,
+    },
     
     /// Get current settings
     GetSettings,
+
+    /// List recorded settings versions with who changed what, newest first
+    SettingsHistory {
+        /// Maximum number of versions
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+
+    /// Restore the settings of an earlier version
+    SettingsRollback {
+        version: u64,
+
+        /// Note kept with the new version, e.g. who asked for it (defaults
+        /// to $USER)
+        #[arg(long)]
+        note: Option<String>,
+    },
     
     /// Set protected paths
     SetPaths {
@@ -50,6 +168,33 @@ enum Commands {
     
     /// Create baseline from protected paths
     CreateBaseline,
+
+    /// Require baselines to be countersigned by an operator key; omit the
+    /// key to turn dual control off
+    BaselineOperatorKey {
+        /// base64 ed25519 public key
+        public_key: Option<String>,
+
+        /// Hex signature by the current operator key approving the change
+        #[arg(long)]
+        authorization: Option<String>,
+    },
+
+    /// Print the message the current operator key signs to approve a key
+    /// change; omit the key for turning dual control off
+    OperatorKeyChangeRequest {
+        /// base64 ed25519 public key
+        public_key: Option<String>,
+    },
+
+    /// Print the message to sign with the operator key
+    BaselineSigningRequest,
+
+    /// Attach the operator's countersignature to the current baseline
+    BaselineCountersign {
+        /// Hex ed25519 signature over the signing request message
+        signature: String,
+    },
     
     /// Trigger a manual scan
     Scan,
@@ -73,6 +218,217 @@ enum Commands {
         #[arg(short, long, default_value = "50")]
         limit: usize,
     },
+
+    /// Tag baseline entries (files or directory prefixes)
+    Tag {
+        /// Baseline paths to tag
+        #[arg(required = true)]
+        paths: Vec<String>,
+
+        /// Tag to add (repeatable)
+        #[arg(long)]
+        add: Vec<String>,
+
+        /// Tag to remove (repeatable)
+        #[arg(long)]
+        remove: Vec<String>,
+
+        /// Metadata as key=value; an empty value removes the key (repeatable)
+        #[arg(long = "meta", value_parser = parse_key_value)]
+        metadata: Vec<(String, String)>,
+    },
+
+    /// Scan against the baseline, optionally reporting only one tag
+    ScanReport {
+        #[arg(long)]
+        tag: Option<String>,
+    },
+
+    /// Restore every modified or missing file carrying a tag
+    RestoreTag {
+        tag: String,
+    },
+
+    /// Search the event log (newest first)
+    SearchEvents {
+        /// Only these event types (repeatable)
+        #[arg(long = "type")]
+        event_types: Vec<String>,
+
+        /// Minimum severity: info, warn, error or critical
+        #[arg(long, value_parser = parse_severity)]
+        severity: Option<EventSeverity>,
+
+        /// Only events about paths under this prefix
+        #[arg(long)]
+        path: Option<String>,
+
+        /// Case-insensitive text to look for in event data
+        #[arg(long)]
+        text: Option<String>,
+
+        /// Only events at or after this RFC 3339 timestamp
+        #[arg(long)]
+        since: Option<DateTime<Utc>>,
+
+        /// Only events at or before this RFC 3339 timestamp
+        #[arg(long)]
+        until: Option<DateTime<Utc>>,
+
+        /// Continue from a previous page's next_cursor
+        #[arg(long)]
+        cursor: Option<String>,
+
+        /// Page size
+        #[arg(short, long, default_value = "50")]
+        limit: usize,
+    },
+
+    /// Attach a note to an event, e.g. "expected — deploy #123"
+    Note {
+        /// Sequence number of the event
+        seq: u64,
+
+        note: String,
+
+        /// Name to sign the note with, kept in the note text. The service
+        /// records the authenticated client as the note's author.
+        #[arg(long)]
+        author: Option<String>,
+    },
+
+    /// Configure the organization key policy bundles must be signed with
+    PolicySetKey {
+        /// base64 ed25519 public key
+        public_key: String,
+    },
+
+    /// Import a signed organization policy bundle
+    PolicyImport {
+        /// Path to the signed bundle (JSON)
+        file: PathBuf,
+    },
+
+    /// Show the active organization policy
+    PolicyShow,
+
+    /// Replay the local event log and check it against the data directory.
+    /// Runs without the service; exits non-zero on any inconsistency.
+    AuditLog {
+        /// Event log to replay (defaults to the service's log)
+        #[arg(long)]
+        log: Option<PathBuf>,
+
+        /// Data directory holding baseline.json (defaults to the service's)
+        #[arg(long)]
+        data_dir: Option<PathBuf>,
+
+        /// base64 device public key; without it signatures are not checked
+        #[arg(long)]
+        public_key: Option<String>,
+    },
+
+    /// Bind a vendor-signed file manifest to a protected application directory
+    SbomImport {
+        /// Application directory the manifest describes
+        root: String,
+
+        /// Path to the signed manifest (JSON)
+        file: PathBuf,
+
+        /// base64 ed25519 public key of the vendor
+        #[arg(long)]
+        vendor_key: String,
+    },
+
+    /// Check application directories against their vendor manifests
+    SbomVerify {
+        /// Only check this directory
+        root: Option<String>,
+    },
+
+    /// Temporarily stop enforcing a path inside a protected directory
+    Exclude {
+        path: String,
+
+        /// How long the exclusion lasts, in minutes
+        #[arg(long, default_value_t = 30)]
+        minutes: u64,
+
+        /// Why the path is being edited (recorded in the event log)
+        #[arg(long)]
+        reason: String,
+    },
+
+    /// End a temporary exclusion; the path's current contents become baseline
+    Unexclude {
+        path: String,
+    },
+
+    /// List active temporary exclusions
+    Exclusions,
+
+    /// Release the write freeze applied after a suspected ransomware burst
+    ReleaseFreeze,
+
+    /// List copy-on-write snapshots of protected directories
+    Snapshots,
+
+    /// Restore a protected directory from its latest snapshot
+    SnapshotRestore {
+        path: String,
+    },
+
+    /// List service instances on this host and whether each is running
+    Instances,
+
+    /// Show backup store size, orphaned blobs and the last GC run
+    BackupStats,
+
+    /// Simulate modify/delete/rename in a sandboxed copy of a protected path
+    /// and report each stage of the response
+    SelfTest {
+        /// Protected path to copy (default: the first one)
+        path: Option<String>,
+    },
+
+    /// Show signed receipts for remote commands, newest first
+    Receipts {
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+
+    /// List quarantined files with their hashes and original paths
+    Quarantine,
+
+    /// Export quarantined files into an encrypted evidence archive
+    QuarantineExport {
+        /// Archive file to create
+        destination: PathBuf,
+        /// Quarantined file name to include (repeatable; default: all)
+        #[arg(long = "item")]
+        items: Vec<String>,
+        /// Name recorded in the chain-of-custody manifest
+        #[arg(long)]
+        examiner: String,
+        /// Archive password
+        #[arg(long)]
+        password: String,
+    },
+
+    /// Verify and import an evidence archive from another instance
+    EvidenceImport {
+        archive: PathBuf,
+        /// Name recorded in the chain-of-custody manifest
+        #[arg(long)]
+        examiner: String,
+        /// Archive password
+        #[arg(long)]
+        password: String,
+        /// base64 device key the archive must have been exported with
+        #[arg(long)]
+        exporter_key: Option<String>,
+    },
 }
 
 struct IpcClient {
@@ -195,73 +551,209 @@ impl IpcClient {
     }
 }
 
+fn parse_key_value(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .ok_or_else(|| format!("expected key=value, got '{s}'"))
+}
+
+fn parse_severity(s: &str) -> Result<EventSeverity, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "info" => Ok(EventSeverity::Info),
+        "warn" => Ok(EventSeverity::Warn),
+        "error" => Ok(EventSeverity::Error),
+        "critical" => Ok(EventSeverity::Critical),
+        other => Err(format!("unknown severity '{other}'")),
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
-    
-    // Connect to IPC
-    let mut client = IpcClient::connect().await?;
-    
-    match cli.command {
-        Commands::Status => {
-            let response = client.send_request(IpcRequest::GetStatus).await?;
-            println!("{}", serde_json::to_string_pretty(&response)?);
-        }
-        
-        Commands::GetSettings => {
-            let response = client.send_request(IpcRequest::GetSettings).await?;
-            println!("{}", serde_json::to_string_pretty(&response)?);
-        }
-        
-        Commands::SetPaths { paths } => {
-            // Convert to strings
-            let path_strings: Vec<String> = paths
+    set_instance(cli.instance.as_deref())?;
+
+    let request = match cli.command {
+        Commands::Status => IpcRequest::GetStatus,
+        Commands::Tui => return tui::run(&mut Connection::open(&cli.remote).await?).await,
+        Commands::PathStats => IpcRequest::GetPathStats,
+        Commands::EstimatePath { path } => IpcRequest::EstimatePath { path },
+        Commands::GetSettings => IpcRequest::GetSettings,
+        Commands::SettingsHistory { limit } => IpcRequest::GetSettingsHistory { limit },
+        Commands::SettingsRollback { version, note } => IpcRequest::RollbackSettings {
+            version,
+            note: note.or_else(|| std::env::var("USER").ok()),
+        },
+        Commands::SetPaths { paths } => IpcRequest::SetProtectedPaths {
+            paths: paths
                 .into_iter()
                 .map(|p| p.to_string_lossy().into_owned())
-                .collect();
-            
-            let response = client
-                .send_request(IpcRequest::SetProtectedPaths {
-                    paths: path_strings,
-                })
-                .await?;
-            println!("{}", serde_json::to_string_pretty(&response)?);
-        }
-        
-        Commands::CreateBaseline => {
-            let response = client.send_request(IpcRequest::BaselineCreate).await?;
-            println!("{}", serde_json::to_string_pretty(&response)?);
+                .collect(),
+        },
+        Commands::CreateBaseline => IpcRequest::BaselineCreate,
+        Commands::BaselineOperatorKey {
+            public_key,
+            authorization,
+        } => IpcRequest::SetBaselineOperatorKey {
+            public_key,
+            authorization,
+        },
+        Commands::OperatorKeyChangeRequest { public_key } => {
+            IpcRequest::GetOperatorKeyChangeRequest { public_key }
         }
-        
-        Commands::Scan => {
-            let response = client.send_request(IpcRequest::TriggerScan).await?;
-            println!("{}", serde_json::to_string_pretty(&response)?);
-        }
-        
-        Commands::SafeModeEnter { reason } => {
-            let response = client
-                .send_request(IpcRequest::EnterSafeMode { reason })
-                .await?;
-            println!("{}", serde_json::to_string_pretty(&response)?);
-        }
-        
-        Commands::SafeModeExit { password } => {
-            let response = client
-                .send_request(IpcRequest::ExitSafeMode { password })
-                .await?;
-            println!("{}", serde_json::to_string_pretty(&response)?);
-        }
-        
-        Commands::GetEvents { limit } => {
-            let response = client
-                .send_request(IpcRequest::GetEvents {
-                    since: None,
-                    limit: Some(limit),
-                })
-                .await?;
-            println!("{}", serde_json::to_string_pretty(&response)?);
+        Commands::BaselineSigningRequest => IpcRequest::GetBaselineSigningRequest,
+        Commands::BaselineCountersign { signature } => IpcRequest::CountersignBaseline { signature },
+        Commands::Scan => IpcRequest::TriggerScan,
+        Commands::SafeModeEnter { reason } => IpcRequest::EnterSafeMode { reason },
+        Commands::SafeModeExit { password } => IpcRequest::ExitSafeMode { password },
+        Commands::GetEvents { limit } => IpcRequest::GetEvents {
+            since: None,
+            limit: Some(limit),
+        },
+        Commands::Tag {
+            paths,
+            add,
+            remove,
+            metadata,
+        } => IpcRequest::TagBaseline {
+            paths,
+            add,
+            remove,
+            metadata: metadata.into_iter().collect(),
+        },
+        Commands::ScanReport { tag } => IpcRequest::ScanReport { tag },
+        Commands::RestoreTag { tag } => IpcRequest::RestoreByTag { tag },
+        Commands::SearchEvents {
+            event_types,
+            severity,
+            path,
+            text,
+            since,
+            until,
+            cursor,
+            limit,
+        } => IpcRequest::SearchEvents {
+            query: EventQuery {
+                event_types,
+                min_severity: severity,
+                path_prefix: path,
+                text,
+                since,
+                until,
+                cursor,
+                limit: Some(limit),
+            },
+        },
+        Commands::Note { seq, note, author } => IpcRequest::AnnotateEvent {
+            seq,
+            note: match author {
+                Some(author) => format!("{} — {author}", note.trim()),
+                None => note,
+            },
+        },
+        Commands::PolicySetKey { public_key } => IpcRequest::SetPolicyOrgKey { public_key },
+        Commands::PolicyImport { file } => IpcRequest::ImportPolicy {
+            bundle: serde_json::from_slice(&std::fs::read(&file)?)
+                .map_err(|e| anyhow!("invalid policy bundle {}: {e}", file.display()))?,
+        },
+        Commands::PolicyShow => IpcRequest::GetPolicy,
+        Commands::AuditLog {
+            log,
+            data_dir,
+            public_key,
+        } => return audit_log(log, data_dir, public_key),
+        Commands::Instances => return list_instances().await,
+        Commands::BackupStats => IpcRequest::GetBackupStoreStats,
+        Commands::SelfTest { path } => IpcRequest::SelfTest { path },
+        Commands::Receipts { limit } => IpcRequest::GetCommandReceipts { limit: Some(limit) },
+        Commands::Quarantine => IpcRequest::ListQuarantine,
+        Commands::QuarantineExport {
+            destination,
+            items,
+            examiner,
+            password,
+        } => IpcRequest::ExportQuarantine {
+            destination: std::path::absolute(&destination)?.display().to_string(),
+            items,
+            examiner,
+            password,
+        },
+        Commands::EvidenceImport {
+            archive,
+            examiner,
+            password,
+            exporter_key,
+        } => IpcRequest::ImportEvidence {
+            archive: std::path::absolute(&archive)?.display().to_string(),
+            examiner,
+            password,
+            exporter_key,
+        },
+        Commands::SbomImport {
+            root,
+            file,
+            vendor_key,
+        } => IpcRequest::ImportSbom {
+            root,
+            vendor_key,
+            manifest: serde_json::from_slice(&std::fs::read(&file)?)
+                .map_err(|e| anyhow!("invalid manifest {}: {e}", file.display()))?,
+        },
+        Commands::SbomVerify { root } => IpcRequest::VerifySbom { root },
+        Commands::Exclude {
+            path,
+            minutes,
+            reason,
+        } => IpcRequest::AddExclusion {
+            path,
+            duration_secs: minutes.saturating_mul(60),
+            reason,
+        },
+        Commands::Unexclude { path } => IpcRequest::RemoveExclusion { path },
+        Commands::Exclusions => IpcRequest::ListExclusions,
+        Commands::ReleaseFreeze => IpcRequest::ReleaseWriteFreeze,
+        Commands::Snapshots => IpcRequest::ListSnapshots,
+        Commands::SnapshotRestore { path } => IpcRequest::SnapshotRestore { path },
+    };
+
+    let response = Connection::open(&cli.remote).await?.send(request).await?;
+    println!("{}", serde_json::to_string_pretty(&response)?);
+
+    Ok(())
+}
+
+/// Read the instance registry directly; a stopped instance has no socket to
+/// ask. An instance recorded as running whose status socket no longer answers
+/// exited without deregistering, and is reported as stopped.
+async fn list_instances() -> Result<()> {
+    let mut registry = load_registry()?;
+    for record in &mut registry.instances {
+        if record.running {
+            record.running = UnixStream::connect(&record.status_socket).await.is_ok();
         }
     }
-    
+    println!("{}", serde_json::to_string_pretty(&registry.instances)?);
+    Ok(())
+}
+
+fn audit_log(
+    log: Option<PathBuf>,
+    data: Option<PathBuf>,
+    public_key: Option<String>,
+) -> Result<()> {
+    let data = match data {
+        Some(d) => d,
+        None => data_dir()?,
+    };
+    let log = match log {
+        Some(l) => l,
+        None => log_dir()?.join("events.log"),
+    };
+    let key = public_key.as_deref().map(decode_device_key).transpose()?;
+    let mut report = replay_log(&log, key.as_ref())?;
+    check_artifacts(&mut report, &data)?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    if !report.is_consistent() {
+        std::process::exit(1);
+    }
     Ok(())
 }
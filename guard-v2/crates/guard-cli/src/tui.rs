@@ -0,0 +1,468 @@
+//! `guard-cli tui`: a terminal dashboard for servers without the desktop app.
+//!
+//! The service has no push channel for events, so the dashboard polls it:
+//! health and the newest events every `REFRESH` and after each action. It
+//! draws with plain ANSI escapes on the alternate screen and reads keys in raw
+//! mode, so it needs nothing beyond a VT100-compatible terminal.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local, Utc};
+use guard_core::event_log::{EventEntry, EventQuery, EventSeverity};
+use guard_core::health::{HealthState, ServiceHealth};
+use guard_core::ipc::{IpcRequest, IpcResponse};
+use std::io::{Read, Write};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::Connection;
+
+const REFRESH: Duration = Duration::from_secs(2);
+/// Maintenance entered from the dashboard ends on its own after this long.
+const MAINTENANCE_TIMEOUT_SECS: u64 = 30 * 60;
+const MAX_EVENTS: usize = 50;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const RED: &str = "\x1b[31m";
+const YELLOW: &str = "\x1b[33m";
+const GREEN: &str = "\x1b[32m";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Quit,
+    Scan,
+    ToggleMaintenance,
+    Refresh,
+}
+
+fn action_for(key: u8) -> Option<Action> {
+    match key {
+        b'q' | b'Q' | 0x03 => Some(Action::Quit),
+        b's' | b'S' => Some(Action::Scan),
+        b'm' | b'M' => Some(Action::ToggleMaintenance),
+        b'r' | b'R' => Some(Action::Refresh),
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+struct Dashboard {
+    health: Option<ServiceHealth>,
+    /// Newest first.
+    events: Vec<EventEntry>,
+    updated: Option<DateTime<Utc>>,
+    /// Result of the last action or refresh error.
+    message: String,
+}
+
+pub(crate) async fn run(conn: &mut Connection<'_>) -> Result<()> {
+    let _terminal = RawTerminal::enter()?;
+    let (key_tx, mut key_rx) = mpsc::channel(16);
+    // Blocking stdin reader; it is left behind when the dashboard exits.
+    std::thread::spawn(move || {
+        let mut byte = [0u8; 1];
+        while let Ok(1) = std::io::stdin().read(&mut byte) {
+            if key_tx.blocking_send(byte[0]).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut dash = Dashboard::default();
+    let mut ticker = tokio::time::interval(REFRESH);
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => refresh(conn, &mut dash).await,
+            key = key_rx.recv() => {
+                let Some(action) = key.and_then(action_for) else {
+                    if key.is_none() {
+                        return Ok(());
+                    }
+                    continue;
+                };
+                match action {
+                    Action::Quit => return Ok(()),
+                    Action::Refresh => {}
+                    Action::Scan => dash.message = act(conn, IpcRequest::TriggerScan).await,
+                    Action::ToggleMaintenance => {
+                        let request = if dash.health.as_ref().is_some_and(|h| h.mode == "Maintenance") {
+                            IpcRequest::MaintenanceExit { rebaseline: false, report: false }
+                        } else {
+                            IpcRequest::MaintenanceEnter {
+                                reason: "guard-cli tui".into(),
+                                timeout_secs: MAINTENANCE_TIMEOUT_SECS,
+                            }
+                        };
+                        dash.message = act(conn, request).await;
+                    }
+                }
+                refresh(conn, &mut dash).await;
+            }
+        }
+        let (width, height) = terminal_size();
+        draw(&render(&dash, width, height))?;
+    }
+}
+
+async fn refresh(conn: &mut Connection<'_>, dash: &mut Dashboard) {
+    match fetch(conn).await {
+        Ok((health, events)) => {
+            dash.health = health;
+            dash.events = events;
+            dash.updated = Some(Utc::now());
+        }
+        Err(e) => dash.message = format!("refresh failed: {e}"),
+    }
+}
+
+async fn fetch(conn: &mut Connection<'_>) -> Result<(Option<ServiceHealth>, Vec<EventEntry>)> {
+    let health = match conn.send(IpcRequest::GetStatus).await? {
+        IpcResponse::Status { health, .. } => health,
+        other => return Err(anyhow!("unexpected status response: {other:?}")),
+    };
+    let query = EventQuery {
+        limit: Some(MAX_EVENTS),
+        ..Default::default()
+    };
+    let events = match conn.send(IpcRequest::SearchEvents { query }).await? {
+        IpcResponse::EventPage { events, .. } => events
+            .into_iter()
+            .filter_map(|e| serde_json::from_value(e).ok())
+            .collect(),
+        other => return Err(anyhow!("unexpected events response: {other:?}")),
+    };
+    Ok((health, events))
+}
+
+/// Run an action and describe the outcome for the status line.
+async fn act(conn: &mut Connection<'_>, request: IpcRequest) -> String {
+    match conn.send(request).await {
+        Ok(IpcResponse::ScanComplete { result }) => format!(
+            "scan complete: {} modified, {} removed, {} added",
+            count(&result["modified"]),
+            count(&result["removed"]),
+            count(&result["added"]),
+        ),
+        Ok(IpcResponse::MaintenanceEntered) => format!(
+            "maintenance on for up to {} min; press m to end it",
+            MAINTENANCE_TIMEOUT_SECS / 60
+        ),
+        Ok(IpcResponse::MaintenanceExited { changes, .. }) => {
+            format!("maintenance off; {changes} paths changed during the window")
+        }
+        Ok(other) => format!("{other:?}"),
+        Err(e) => format!("failed: {e}"),
+    }
+}
+
+fn count(value: &serde_json::Value) -> usize {
+    value
+        .as_array()
+        .map(Vec::len)
+        .or_else(|| value.as_u64().map(|n| n as usize))
+        .unwrap_or(0)
+}
+
+// ── Rendering ───────────────────────────────────────────────────────────────
+
+fn render(dash: &Dashboard, width: usize, height: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let rule = format!("{DIM}{}{RESET}", "─".repeat(width));
+
+    let updated = dash
+        .updated
+        .map(|t| t.with_timezone(&Local).format("%H:%M:%S").to_string())
+        .unwrap_or_else(|| "never".into());
+    match &dash.health {
+        Some(h) => {
+            let mode = match h.mode.as_str() {
+                "Active" => format!("{GREEN}{}{RESET}", h.mode),
+                "SafeMode" => format!("{RED}{}{RESET}", h.mode),
+                _ => format!("{YELLOW}{}{RESET}", h.mode),
+            };
+            lines.push(format!(
+                "{BOLD}Darklock Guard{RESET}  mode {mode}  up {}  updated {updated}",
+                format_uptime(h.uptime_secs)
+            ));
+            lines.push(rule.clone());
+
+            let b = &h.baseline;
+            lines.push(if b.present {
+                format!(
+                    "Baseline   {} entries  signature {}  countersignature {}",
+                    b.entries,
+                    check_mark(b.signature_valid),
+                    check_mark(b.operator_signature_valid),
+                )
+            } else {
+                format!("Baseline   {RED}none{RESET}")
+            });
+            lines.push(match &h.last_scan {
+                Some(s) => format!(
+                    "Last scan  {}  {} files  {}",
+                    s.at.with_timezone(&Local).format("%H:%M:%S"),
+                    s.files,
+                    match s.violations {
+                        0 => format!("{GREEN}clean{RESET}"),
+                        n => format!("{RED}{n} violations{RESET}"),
+                    }
+                ),
+                None => "Last scan  none".into(),
+            });
+            lines.push(format!(
+                "Queues     tamper {}  maintenance {}  restoring {}",
+                h.queues.tamper_events, h.queues.maintenance_queued, h.queues.restores_in_progress
+            ));
+            let subsystems: Vec<String> = h
+                .subsystems
+                .iter()
+                .map(|s| {
+                    let colour = match s.state {
+                        HealthState::Ok => GREEN,
+                        HealthState::Degraded => YELLOW,
+                        HealthState::Failed => RED,
+                        HealthState::Disabled => DIM,
+                    };
+                    format!("{colour}{}{RESET}", s.name)
+                })
+                .collect();
+            lines.push(format!("Subsystems {}", subsystems.join(" ")));
+        }
+        None => {
+            lines.push(format!("{BOLD}Darklock Guard{RESET}  updated {updated}"));
+            lines.push(rule.clone());
+            lines.push("No health report from the service".into());
+        }
+    }
+
+    lines.push(format!("{DIM}── Recent events {}{RESET}", "─".repeat(width.saturating_sub(17))));
+    // Header, footer and their rules take the rest.
+    let room = height.saturating_sub(lines.len() + 2);
+    for event in dash.events.iter().take(room) {
+        let colour = match event.severity {
+            EventSeverity::Critical | EventSeverity::Error => RED,
+            EventSeverity::Warn => YELLOW,
+            EventSeverity::Info => "",
+        };
+        lines.push(format!(
+            "{} {colour}{:<24}{RESET} {}",
+            event.timestamp.with_timezone(&Local).format("%H:%M:%S"),
+            event.event_type,
+            event_summary(&event.data),
+        ));
+    }
+    while lines.len() + 2 < height {
+        lines.push(String::new());
+    }
+
+    lines.push(rule);
+    lines.push(format!(
+        "{BOLD}[s]{RESET} scan  {BOLD}[m]{RESET} maintenance on/off  {BOLD}[r]{RESET} refresh  {BOLD}[q]{RESET} quit  {}",
+        dash.message
+    ));
+    lines.into_iter().map(|l| truncate_visible(&l, width)).collect()
+}
+
+fn check_mark(valid: Option<bool>) -> String {
+    match valid {
+        Some(true) => format!("{GREEN}ok{RESET}"),
+        Some(false) => format!("{RED}INVALID{RESET}"),
+        None => "n/a".into(),
+    }
+}
+
+fn format_uptime(secs: u64) -> String {
+    match secs {
+        s if s < 3600 => format!("{}m", s / 60),
+        s if s < 86_400 => format!("{}h{:02}m", s / 3600, (s % 3600) / 60),
+        s => format!("{}d{}h", s / 86_400, (s % 86_400) / 3600),
+    }
+}
+
+/// One-line gist of an event payload: its path if it has one, otherwise
+/// its scalar fields.
+fn event_summary(data: &serde_json::Value) -> String {
+    let Some(obj) = data.as_object() else {
+        return String::new();
+    };
+    if let Some(path) = obj.get("path").and_then(|p| p.as_str()) {
+        return match obj.get("kind").and_then(|k| k.as_str()) {
+            Some(kind) => format!("{path} ({kind})"),
+            None => path.to_string(),
+        };
+    }
+    obj.iter()
+        .filter(|(k, v)| *k != "schema" && !v.is_object() && !v.is_array() && !v.is_null())
+        .map(|(k, v)| match v.as_str() {
+            Some(s) => format!("{k}={s}"),
+            None => format!("{k}={v}"),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Cut `line` to `width` visible characters, keeping escape sequences intact.
+fn truncate_visible(line: &str, width: usize) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut visible = 0;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            out.push(c);
+            for c in chars.by_ref() {
+                out.push(c);
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+            continue;
+        }
+        if visible == width {
+            out.push_str(RESET);
+            break;
+        }
+        out.push(c);
+        visible += 1;
+    }
+    out
+}
+
+// ── Terminal ────────────────────────────────────────────────────────────────
+
+fn draw(lines: &[String]) -> Result<()> {
+    let mut out = std::io::stdout().lock();
+    write!(out, "\x1b[H")?;
+    for (i, line) in lines.iter().enumerate() {
+        if i > 0 {
+            write!(out, "\r\n")?;
+        }
+        write!(out, "{line}\x1b[K")?;
+    }
+    write!(out, "\x1b[J")?;
+    out.flush()?;
+    Ok(())
+}
+
+fn terminal_size() -> (usize, usize) {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let ok = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0;
+    if ok && size.ws_col > 0 && size.ws_row > 0 {
+        (size.ws_col as usize, size.ws_row as usize)
+    } else {
+        (80, 24)
+    }
+}
+
+/// Raw mode on the alternate screen for as long as it lives.
+struct RawTerminal {
+    original: libc::termios,
+}
+
+impl RawTerminal {
+    fn enter() -> Result<Self> {
+        let mut original: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut original) } != 0 {
+            return Err(anyhow!("tui needs an interactive terminal"));
+        }
+        let mut raw = original;
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN);
+        raw.c_iflag &= !(libc::IXON | libc::ICRNL);
+        raw.c_cc[libc::VMIN] = 1;
+        raw.c_cc[libc::VTIME] = 0;
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) } != 0 {
+            return Err(anyhow!("cannot switch terminal to raw mode"));
+        }
+        print!("\x1b[?1049h\x1b[?25l");
+        std::io::stdout().flush()?;
+        Ok(Self { original })
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        print!("\x1b[?25h\x1b[?1049l");
+        let _ = std::io::stdout().flush();
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use guard_core::health::{BaselineHealth, QueueDepths, SubsystemStatus};
+
+    fn strip(line: &str) -> String {
+        truncate_visible(line, usize::MAX)
+            .split('\x1b')
+            .enumerate()
+            .map(|(i, part)| {
+                if i == 0 {
+                    part
+                } else {
+                    part.split_once(|c: char| c.is_ascii_alphabetic())
+                        .map_or("", |(_, rest)| rest)
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn render_fits_the_terminal() {
+        let dash = Dashboard {
+            health: Some(ServiceHealth {
+                uptime_secs: 7_260,
+                mode: "Maintenance".into(),
+                safe_mode_reason: None,
+                subsystems: vec![SubsystemStatus {
+                    name: "watcher".into(),
+                    state: HealthState::Ok,
+                    restarts: 0,
+                    heartbeat_age_secs: None,
+                    detail: None,
+                }],
+                queues: QueueDepths::default(),
+                last_scan: None,
+                baseline: BaselineHealth {
+                    present: true,
+                    entries: 42,
+                    signature_valid: Some(true),
+                    ..Default::default()
+                },
+                resources: None,
+            }),
+            events: (0..40)
+                .map(|i| EventEntry {
+                    seq: i,
+                    timestamp: Utc::now(),
+                    event_type: "TAMPER_DETECTED".into(),
+                    severity: EventSeverity::Critical,
+                    data: serde_json::json!({"path": format!("/srv/app/{i}.conf"), "kind": "modified"}),
+                    prev_hash: String::new(),
+                    hash: String::new(),
+                    signature: String::new(),
+                })
+                .collect(),
+            updated: Some(Utc::now()),
+            message: String::new(),
+        };
+
+        let lines = render(&dash, 60, 20);
+        assert_eq!(lines.len(), 20);
+        assert!(lines.iter().all(|l| strip(l).chars().count() <= 60));
+        assert!(strip(&lines[0]).contains("mode Maintenance  up 2h01m"));
+        assert!(strip(&lines[2]).contains("42 entries  signature ok  countersignature n/a"));
+        assert!(lines.iter().any(|l| strip(l).contains("/srv/app/0.conf (modified)")));
+        assert!(strip(&lines[19]).starts_with("[s] scan"));
+    }
+
+    #[test]
+    fn keys_map_to_actions() {
+        assert_eq!(action_for(b'q'), Some(Action::Quit));
+        assert_eq!(action_for(0x03), Some(Action::Quit));
+        assert_eq!(action_for(b'm'), Some(Action::ToggleMaintenance));
+        assert_eq!(action_for(b'x'), None);
+    }
+}
@@ -10,6 +10,10 @@
 //!  - Added `has_entry()`, `entry_for()`, `manifest()` accessors
 //!  - Added `ensure_from_bytes()` for programmatic population
 //!  - Added `blake3_hex()` public helper
+//!  - Reads verify blobs against the manifest; a damaged blob is re-fetched
+//!    from its source file when that still matches (`read_path_repairing()`)
+//!  - Added `run_gc()`: prunes entries, repairs blobs and sweeps orphans
+//!  - Added `stats()` for `GetBackupStoreStats`
 
 use anyhow::{anyhow, Context, Result};
 use blake3::Hasher;
@@ -17,7 +21,7 @@ use chrono::{DateTime, Utc};
 use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::{self, File, OpenOptions};
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
@@ -66,6 +70,35 @@ pub struct BackupManifest {
     pub signature: String,
 }
 
+/// Outcome of a garbage collection pass.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GcReport {
+    pub at: DateTime<Utc>,
+    /// Manifest entries dropped because nothing protects their path any more.
+    pub entries_pruned: usize,
+    /// Blob files no manifest entry referenced, and their size on disk.
+    pub blobs_removed: usize,
+    pub bytes_freed: u64,
+    /// Paths whose damaged blob was re-fetched from the intact source.
+    pub blobs_repaired: Vec<String>,
+    /// Paths whose blob is damaged and whose source no longer matches.
+    pub blobs_unrecoverable: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupStoreStats {
+    pub entries: usize,
+    /// Blob files on disk, and how many of them no entry references.
+    pub blobs: usize,
+    pub orphaned_blobs: usize,
+    pub disk_bytes: u64,
+    /// Sizes as recorded in the manifest.
+    pub stored_bytes: u64,
+    pub original_bytes: u64,
+    #[serde(default)]
+    pub last_gc: Option<GcReport>,
+}
+
 // ── Store ───────────────────────────────────────────────────────────────────
 
 pub struct BackupStore {
@@ -76,6 +109,7 @@ pub struct BackupStore {
     manifest: BackupManifest,
     signing_key: SigningKey,
     verifying_key: VerifyingKey,
+    last_gc: Option<GcReport>,
 }
 
 impl BackupStore {
@@ -131,6 +165,7 @@ impl BackupStore {
             manifest,
             signing_key,
             verifying_key,
+            last_gc: None,
         })
     }
 
@@ -144,6 +179,12 @@ impl BackupStore {
         self.manifest.entries.get(path)
     }
 
+    /// Whether `path` is backed by a stored blob of `hash`.
+    pub fn has_blob(&self, path: &str, hash: &str) -> bool {
+        self.entry_for(path)
+            .is_some_and(|e| e.blob_hash == hash && self.blob_path(hash).exists())
+    }
+
     pub fn manifest(&self) -> &BackupManifest {
         &self.manifest
     }
@@ -207,7 +248,48 @@ impl BackupStore {
             .entries
             .get(path)
             .ok_or_else(|| BackupStoreError::PathNotFound(path.to_string()))?;
-        self.read_blob_by_entry(entry)
+        self.read_entry_checked(entry)
+    }
+
+    /// `read_path`, but a missing or corrupted blob is re-fetched from the
+    /// source file first if that still has the recorded content.
+    pub fn read_path_repairing(&mut self, path: &str) -> Result<Vec<u8>> {
+        match self.read_path(path) {
+            Ok(data) => Ok(data),
+            Err(e) if is_blob_damage(&e) => {
+                if !self.refetch_from_source(path)? {
+                    return Err(e);
+                }
+                warn!(path, "backup blob damaged; re-fetched from intact source");
+                self.read_path(path)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Rewrite the blob for `path` from the file at `path`, if the file still
+    /// hashes to the recorded blob hash. Returns whether it did.
+    pub fn refetch_from_source(&mut self, path: &str) -> Result<bool> {
+        let entry = self
+            .manifest
+            .entries
+            .get(path)
+            .cloned()
+            .ok_or_else(|| BackupStoreError::PathNotFound(path.to_string()))?;
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(_) => return Ok(false),
+        };
+        if blake3_hex(&data) != entry.blob_hash {
+            return Ok(false);
+        }
+        let stored_bytes = if entry.compressed {
+            zstd::encode_all(&data[..], 3)?
+        } else {
+            data
+        };
+        self.write_blob_atomic(&self.blob_path(&entry.blob_hash), &stored_bytes)?;
+        Ok(true)
     }
 
     /// Same as `read_path` but additionally asserts the blob hash equals
@@ -267,6 +349,81 @@ impl BackupStore {
         Ok(())
     }
 
+    pub fn stats(&self) -> BackupStoreStats {
+        let referenced = self.referenced_blobs();
+        let blobs = self.blob_files();
+        BackupStoreStats {
+            entries: self.manifest.entries.len(),
+            blobs: blobs.len(),
+            orphaned_blobs: blobs
+                .iter()
+                .filter(|(hash, _, _)| !referenced.contains(hash.as_str()))
+                .count(),
+            disk_bytes: blobs.iter().map(|(_, _, size)| size).sum(),
+            stored_bytes: self.manifest.total_size,
+            original_bytes: self.manifest.entries.values().map(|e| e.original_size).sum(),
+            last_gc: self.last_gc.clone(),
+        }
+    }
+
+    // ── Garbage collection ──────────────────────────────────────────────────
+
+    /// Drop entries whose path `keep` rejects, check every remaining blob
+    /// (re-fetching damaged ones from intact sources), then delete blob
+    /// files no entry references.
+    pub fn run_gc(&mut self, keep: impl Fn(&str) -> bool) -> Result<GcReport> {
+        let before = self.manifest.entries.len();
+        self.manifest.entries.retain(|path, _| keep(path));
+        let entries_pruned = before - self.manifest.entries.len();
+        if entries_pruned > 0 {
+            self.manifest.total_size = self.manifest.entries.values().map(|e| e.stored_size).sum();
+            self.manifest.updated_at = Utc::now();
+            Self::sign_manifest(&mut self.manifest, &self.signing_key)?;
+            self.persist_manifest()?;
+        }
+
+        let mut blobs_repaired = Vec::new();
+        let mut blobs_unrecoverable = Vec::new();
+        let mut paths: Vec<String> = self.manifest.entries.keys().cloned().collect();
+        paths.sort();
+        for path in paths {
+            if self.read_path(&path).is_ok() {
+                continue;
+            }
+            if self.refetch_from_source(&path)? {
+                blobs_repaired.push(path);
+            } else {
+                blobs_unrecoverable.push(path);
+            }
+        }
+
+        let referenced = self.referenced_blobs();
+        let mut blobs_removed = 0;
+        let mut bytes_freed = 0;
+        for (hash, path, size) in self.blob_files() {
+            if !referenced.contains(hash.as_str()) && fs::remove_file(&path).is_ok() {
+                blobs_removed += 1;
+                bytes_freed += size;
+                if let Some(parent) = path.parent() {
+                    // Only succeeds once the prefix directory is empty.
+                    let _ = fs::remove_dir(parent);
+                }
+            }
+        }
+        Self::cleanup_staging_dir(&self.staging_root);
+
+        let report = GcReport {
+            at: Utc::now(),
+            entries_pruned,
+            blobs_removed,
+            bytes_freed,
+            blobs_repaired,
+            blobs_unrecoverable,
+        };
+        self.last_gc = Some(report.clone());
+        Ok(report)
+    }
+
     // ── Removal ─────────────────────────────────────────────────────────────
 
     pub fn remove_entry(&mut self, path: &str) {
@@ -297,8 +454,9 @@ impl BackupStore {
         };
         let stored_size = stored_bytes.len() as u64;
 
+        // Rewrite a damaged blob rather than dedupe against it.
         let blob_path = self.blob_path(hash);
-        if !blob_path.exists() {
+        if !self.blob_intact(hash, compressed) {
             self.write_blob_atomic(&blob_path, &stored_bytes)?;
         }
 
@@ -343,6 +501,77 @@ impl BackupStore {
         }
     }
 
+    /// `read_blob_by_entry` plus a hash check against the manifest.
+    fn read_entry_checked(&self, entry: &BackupEntry) -> Result<Vec<u8>> {
+        let data = self.read_blob_by_entry(entry).map_err(|e| {
+            if is_blob_damage(&e) {
+                e
+            } else {
+                // Undecodable compressed data is corruption too.
+                anyhow!(BackupStoreError::BlobCorrupted {
+                    expected: entry.blob_hash.clone(),
+                    actual: format!("unreadable: {e}"),
+                })
+            }
+        })?;
+        let actual = blake3_hex(&data);
+        if actual != entry.blob_hash {
+            return Err(anyhow!(BackupStoreError::BlobCorrupted {
+                expected: entry.blob_hash.clone(),
+                actual,
+            }));
+        }
+        Ok(data)
+    }
+
+    fn blob_intact(&self, hash: &str, compressed: bool) -> bool {
+        let probe = BackupEntry {
+            path: String::new(),
+            blob_hash: hash.to_string(),
+            original_size: 0,
+            stored_size: 0,
+            permissions: 0,
+            owner: None,
+            compressed,
+            stored_at: Utc::now(),
+        };
+        self.read_entry_checked(&probe).is_ok()
+    }
+
+    fn referenced_blobs(&self) -> HashSet<&str> {
+        self.manifest
+            .entries
+            .values()
+            .map(|e| e.blob_hash.as_str())
+            .collect()
+    }
+
+    /// Every blob file on disk: (hash, path, size).
+    fn blob_files(&self) -> Vec<(String, PathBuf, u64)> {
+        let mut blobs = Vec::new();
+        let Ok(prefixes) = fs::read_dir(&self.blobs_root) else {
+            return blobs;
+        };
+        for prefix in prefixes.flatten() {
+            let Ok(files) = fs::read_dir(prefix.path()) else {
+                continue;
+            };
+            for file in files.flatten() {
+                let path = file.path();
+                let Some(hash) = path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .and_then(|n| n.strip_suffix(".blob"))
+                else {
+                    continue;
+                };
+                let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+                blobs.push((hash.to_string(), path.clone(), size));
+            }
+        }
+        blobs
+    }
+
     fn blob_path(&self, hash: &str) -> PathBuf {
         let prefix = &hash[0..2.min(hash.len())];
         self.blobs_root.join(prefix).join(format!("{}.blob", hash))
@@ -448,6 +677,13 @@ impl BackupStore {
 
 // ── Utility ────────────────────────────────────────────────────────────────
 
+fn is_blob_damage(e: &anyhow::Error) -> bool {
+    matches!(
+        e.downcast_ref::<BackupStoreError>(),
+        Some(BackupStoreError::BlobMissing(_) | BackupStoreError::BlobCorrupted { .. })
+    )
+}
+
 /// Compute the BLAKE3 hex digest of `data`.
 pub fn blake3_hex(data: &[u8]) -> String {
     let mut hasher = Hasher::new();
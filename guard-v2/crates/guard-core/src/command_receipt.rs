@@ -0,0 +1,113 @@
+//! Signed receipts for remote commands.
+//!
+//! Every command received in connected mode — executed, failed or rejected —
+//! yields a receipt signed with the device key. The service keeps its own
+//! copy and sends one to the platform, so either side can later prove which
+//! commands the device acted on and with what outcome.
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Domain separator so a receipt signature can't be replayed as any other
+/// device-signed message.
+const RECEIPT_SIGNING_CONTEXT: &[u8] = b"darklock-guard-command-receipt-v1\0";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CommandReceipt {
+    pub device_id: String,
+    pub command_id: String,
+    pub command: String,
+    pub nonce: String,
+    /// `succeeded`, `failed` or `rejected`.
+    pub status: String,
+    pub result: Value,
+    #[serde(default)]
+    pub error: Option<String>,
+    pub received_at: DateTime<Utc>,
+    pub completed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedCommandReceipt {
+    pub receipt: CommandReceipt,
+    /// base64 ed25519 signature over
+    /// `RECEIPT_SIGNING_CONTEXT || json(receipt)`.
+    pub signature: String,
+    /// When the platform acknowledged the receipt. Local bookkeeping, not
+    /// covered by the signature.
+    #[serde(default)]
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+fn signing_message(receipt: &CommandReceipt) -> Result<Vec<u8>> {
+    let mut msg = RECEIPT_SIGNING_CONTEXT.to_vec();
+    msg.extend_from_slice(&serde_json::to_vec(receipt)?);
+    Ok(msg)
+}
+
+impl SignedCommandReceipt {
+    pub fn sign(receipt: CommandReceipt, device_key: &SigningKey) -> Result<Self> {
+        let signature = device_key.sign(&signing_message(&receipt)?);
+        Ok(Self {
+            receipt,
+            signature: general_purpose::STANDARD.encode(signature.to_bytes()),
+            delivered_at: None,
+        })
+    }
+
+    pub fn verify(&self, device_key: &VerifyingKey) -> Result<()> {
+        let sig_bytes = general_purpose::STANDARD
+            .decode(&self.signature)
+            .map_err(|e| anyhow!("decode receipt signature: {e}"))?;
+        let arr: [u8; 64] = sig_bytes
+            .try_into()
+            .map_err(|_| anyhow!("receipt signature length"))?;
+        device_key
+            .verify_strict(
+                &signing_message(&self.receipt)?,
+                &Signature::from_bytes(&arr),
+            )
+            .map_err(|e| anyhow!("receipt signature invalid: {e}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn signed_receipt_verifies_and_detects_tampering() {
+        let device = SigningKey::generate(&mut OsRng);
+        let now = Utc::now();
+        let receipt = CommandReceipt {
+            device_id: "device-1".into(),
+            command_id: "cmd-1".into(),
+            command: "ENTER_SAFE_MODE".into(),
+            nonce: "n1".into(),
+            status: "succeeded".into(),
+            result: serde_json::json!({"safe_mode": true}),
+            error: None,
+            received_at: now,
+            completed_at: now,
+        };
+        let mut signed = SignedCommandReceipt::sign(receipt, &device).unwrap();
+        signed.verify(&device.verifying_key()).unwrap();
+
+        // Delivery bookkeeping doesn't invalidate the receipt.
+        signed.delivered_at = Some(Utc::now());
+        let round_trip: SignedCommandReceipt =
+            serde_json::from_slice(&serde_json::to_vec(&signed).unwrap()).unwrap();
+        round_trip.verify(&device.verifying_key()).unwrap();
+
+        let other = SigningKey::generate(&mut OsRng);
+        assert!(signed.verify(&other.verifying_key()).is_err());
+
+        signed.receipt.status = "failed".into();
+        assert!(signed.verify(&device.verifying_key()).is_err());
+    }
+}
@@ -1,18 +1,22 @@
 use crate::crypto::sign_bytes;
-use anyhow::Result;
+use crate::events::GuardEvent;
+use anyhow::{anyhow, Result};
 use base64::{engine::general_purpose, Engine as _};
 use chrono::{DateTime, Utc};
 use ed25519_dalek::SigningKey;
 use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
 use std::fs::{self, File, OpenOptions};
-use std::io::{BufRead, BufReader, Write};
+use std::io::{BufRead, BufReader, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 
-const MAX_ROTATIONS: usize = 5;
+pub(crate) const MAX_ROTATIONS: usize = 5;
+const DEFAULT_PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 1000;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum EventSeverity {
     Info,
@@ -46,10 +50,117 @@ pub struct LogAnchor {
     pub hash: String,
 }
 
+/// Server-side event filter. Every set field must match.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EventQuery {
+    #[serde(default)]
+    pub event_types: Vec<String>,
+    /// Minimum severity (inclusive).
+    #[serde(default)]
+    pub min_severity: Option<EventSeverity>,
+    /// Matches events whose `data.path` / `data.paths` start with this prefix.
+    #[serde(default)]
+    pub path_prefix: Option<String>,
+    /// Case-insensitive substring match over the event's `data`.
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub until: Option<DateTime<Utc>>,
+    /// Opaque cursor from a previous page's `next_cursor`.
+    #[serde(default)]
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// One page of search results, newest first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventPage {
+    pub events: Vec<EventEntry>,
+    pub next_cursor: Option<String>,
+}
+
+/// Index record: the filterable fields of an entry plus where to read it.
+#[derive(Debug)]
+struct IndexedEvent {
+    seq: u64,
+    timestamp: DateTime<Utc>,
+    event_type: String,
+    severity: EventSeverity,
+    paths: Vec<String>,
+    segment: u64,
+    offset: u64,
+}
+
+impl IndexedEvent {
+    fn new(entry: &EventEntry, segment: u64, offset: u64) -> Self {
+        let mut paths = Vec::new();
+        if let Some(p) = entry.data.get("path").and_then(|v| v.as_str()) {
+            paths.push(p.to_string());
+        }
+        if let Some(list) = entry.data.get("paths").and_then(|v| v.as_array()) {
+            paths.extend(list.iter().filter_map(|v| v.as_str()).map(String::from));
+        }
+        Self {
+            seq: entry.seq,
+            timestamp: entry.timestamp,
+            event_type: entry.event_type.clone(),
+            severity: entry.severity.clone(),
+            paths,
+            segment,
+            offset,
+        }
+    }
+
+    fn matches(&self, query: &EventQuery, before_seq: Option<u64>) -> bool {
+        if before_seq.is_some_and(|b| self.seq >= b) {
+            return false;
+        }
+        if !query.event_types.is_empty() && !query.event_types.contains(&self.event_type) {
+            return false;
+        }
+        if query.min_severity.as_ref().is_some_and(|min| self.severity < *min) {
+            return false;
+        }
+        if query.since.is_some_and(|t| self.timestamp < t) {
+            return false;
+        }
+        if query.until.is_some_and(|t| self.timestamp > t) {
+            return false;
+        }
+        if let Some(prefix) = &query.path_prefix {
+            if !self.paths.iter().any(|p| p.starts_with(prefix.as_str())) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// In-memory index over the active log and its rotations. Segment ids grow
+/// by one on every rotation; the active file is always `active_segment`.
+#[derive(Debug)]
+struct EventIndex {
+    active_segment: u64,
+    entries: Vec<IndexedEvent>,
+}
+
+impl EventIndex {
+    fn rotate(&mut self) {
+        self.active_segment += 1;
+        let oldest = self.active_segment - MAX_ROTATIONS as u64;
+        self.entries.retain(|e| e.segment >= oldest);
+    }
+}
+
 #[derive(Debug)]
 struct LogState {
     last_seq: u64,
     last_hash: String,
+    /// Built on first search, then maintained by `append`.
+    index: Option<EventIndex>,
 }
 
 impl EventLog {
@@ -62,6 +173,7 @@ impl EventLog {
             inner: Mutex::new(LogState {
                 last_seq,
                 last_hash,
+                index: None,
             }),
             max_bytes,
         })
@@ -69,6 +181,15 @@ impl EventLog {
 
     fn load_state(path: &Path) -> Result<(u64, String)> {
         if !path.exists() {
+            // Just rotated: the chain restarts, but seq (used as the search
+            // cursor) must keep counting from the newest rotation.
+            let mut rotated = path.as_os_str().to_owned();
+            rotated.push(".1");
+            let rotated = PathBuf::from(rotated);
+            if rotated.exists() {
+                let (last_seq, _) = Self::load_state(&rotated)?;
+                return Ok((last_seq, "CHAIN_START".to_string()));
+            }
             return Ok((0, "CHAIN_START".to_string()));
         }
         let file = File::open(path)?;
@@ -87,12 +208,17 @@ impl EventLog {
         Ok((last_seq, last_hash))
     }
 
-    fn compute_hash(entry_without_sig: &serde_json::Value) -> Result<String> {
+    pub(crate) fn compute_hash(entry_without_sig: &serde_json::Value) -> Result<String> {
         let mut hasher = Sha256::new();
         hasher.update(entry_without_sig.to_string().as_bytes());
         Ok(hex::encode(hasher.finalize()))
     }
 
+    /// Append a typed event; its payload carries the schema version.
+    pub fn append_event(&self, severity: EventSeverity, event: &GuardEvent) -> Result<EventEntry> {
+        self.append(event.event_type(), severity, event.to_data()?)
+    }
+
     pub fn append(
         &self,
         event_type: &str,
@@ -118,21 +244,27 @@ impl EventLog {
         entry_value["signature"] = serde_json::Value::String(signature.clone());
 
         let entry: EventEntry = serde_json::from_value(entry_value.clone())?;
-        self.write_entry(&entry)?;
+        let offset = self.write_entry(&entry)?;
         state.last_seq = seq;
         state.last_hash = hash;
+        if let Some(index) = state.index.as_mut() {
+            let segment = index.active_segment;
+            index.entries.push(IndexedEvent::new(&entry, segment, offset));
+        }
         Ok(entry)
     }
 
-    fn write_entry(&self, entry: &EventEntry) -> Result<()> {
+    /// Append `entry` as one line; returns the byte offset it was written at.
+    fn write_entry(&self, entry: &EventEntry) -> Result<u64> {
         let mut file = OpenOptions::new()
             .create(true)
             .append(true)
             .open(&self.path)?;
+        let offset = file.metadata()?.len();
         let line = serde_json::to_string(entry)?;
         writeln!(file, "{}", line)?;
         file.flush()?;
-        Ok(())
+        Ok(offset)
     }
 
     fn rotate_if_needed(&self) -> Result<()> {
@@ -160,6 +292,9 @@ impl EventLog {
         }
         // reset chain on new file
         state.last_hash = "CHAIN_START".to_string();
+        if let Some(index) = state.index.as_mut() {
+            index.rotate();
+        }
         // keep sequence monotonic across rotations
         Ok(())
     }
@@ -216,6 +351,171 @@ impl EventLog {
         Ok(entries)
     }
 
+    /// Search the active log and its rotations, newest first.
+    pub fn search(&self, query: &EventQuery) -> Result<EventPage> {
+        let before_seq = query
+            .cursor
+            .as_deref()
+            .map(|c| c.parse::<u64>().map_err(|_| anyhow!("invalid cursor")))
+            .transpose()?;
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE);
+        let text = query.text.as_ref().map(|t| t.to_lowercase());
+
+        let mut state = self.inner.lock();
+        if state.index.is_none() {
+            state.index = Some(self.build_index()?);
+        }
+        let index = state.index.as_ref().expect("index built above");
+
+        let mut readers: HashMap<u64, BufReader<File>> = HashMap::new();
+        let mut events = Vec::new();
+        let mut more = false;
+        for item in index.entries.iter().rev() {
+            if !item.matches(query, before_seq) {
+                continue;
+            }
+            let entry = self.read_indexed(&mut readers, index.active_segment, item)?;
+            if let Some(text) = &text {
+                if !entry.data.to_string().to_lowercase().contains(text.as_str()) {
+                    continue;
+                }
+            }
+            if events.len() == limit {
+                more = true;
+                break;
+            }
+            events.push(entry);
+        }
+        let next_cursor = if more {
+            events.last().map(|e| e.seq.to_string())
+        } else {
+            None
+        };
+        Ok(EventPage {
+            events,
+            next_cursor,
+        })
+    }
+
+    /// The entry with sequence number `seq`, if it is still retained.
+    pub fn get(&self, seq: u64) -> Result<Option<EventEntry>> {
+        Ok(self.read_where(|e| e.seq == seq)?.pop())
+    }
+
+    /// Log an operator note on entry `seq`. The note records the entry's
+    /// hash, binding it to that exact entry.
+    pub fn annotate(&self, seq: u64, note: &str, author: &str) -> Result<EventEntry> {
+        if note.trim().is_empty() {
+            return Err(anyhow!("note is empty"));
+        }
+        let target = self
+            .get(seq)?
+            .ok_or_else(|| anyhow!("no event with seq {seq}"))?;
+        self.append_event(
+            EventSeverity::Info,
+            &GuardEvent::EventNote {
+                seq,
+                hash: target.hash,
+                note: note.trim().to_string(),
+                author: author.to_string(),
+            },
+        )
+    }
+
+    /// Notes logged on each of `seqs`, oldest first.
+    pub fn notes_for(&self, seqs: &[u64]) -> Result<BTreeMap<u64, Vec<EventEntry>>> {
+        let mut notes: BTreeMap<u64, Vec<EventEntry>> = BTreeMap::new();
+        if seqs.is_empty() {
+            return Ok(notes);
+        }
+        for note in self.read_where(|e| e.event_type == "EVENT_NOTE")? {
+            match note.data.get("seq").and_then(|v| v.as_u64()) {
+                Some(seq) if seqs.contains(&seq) => notes.entry(seq).or_default().push(note),
+                _ => {}
+            }
+        }
+        Ok(notes)
+    }
+
+    /// Indexed entries matching `want`, oldest first.
+    fn read_where(&self, want: impl Fn(&IndexedEvent) -> bool) -> Result<Vec<EventEntry>> {
+        let mut state = self.inner.lock();
+        if state.index.is_none() {
+            state.index = Some(self.build_index()?);
+        }
+        let index = state.index.as_ref().expect("index built above");
+        let mut readers = HashMap::new();
+        index
+            .entries
+            .iter()
+            .filter(|item| want(item))
+            .map(|item| self.read_indexed(&mut readers, index.active_segment, item))
+            .collect()
+    }
+
+    fn build_index(&self) -> Result<EventIndex> {
+        let active_segment = MAX_ROTATIONS as u64;
+        let mut entries = Vec::new();
+        for age in (0..=MAX_ROTATIONS).rev() {
+            let path = self.segment_path(active_segment, active_segment - age as u64);
+            if !path.exists() {
+                continue;
+            }
+            let mut reader = BufReader::new(File::open(&path)?);
+            let mut offset = 0u64;
+            let mut line = String::new();
+            loop {
+                line.clear();
+                let n = reader.read_line(&mut line)?;
+                if n == 0 {
+                    break;
+                }
+                if !line.trim().is_empty() {
+                    let entry: EventEntry = serde_json::from_str(line.trim_end())?;
+                    entries.push(IndexedEvent::new(
+                        &entry,
+                        active_segment - age as u64,
+                        offset,
+                    ));
+                }
+                offset += n as u64;
+            }
+        }
+        Ok(EventIndex {
+            active_segment,
+            entries,
+        })
+    }
+
+    fn read_indexed(
+        &self,
+        readers: &mut HashMap<u64, BufReader<File>>,
+        active_segment: u64,
+        item: &IndexedEvent,
+    ) -> Result<EventEntry> {
+        let reader = match readers.entry(item.segment) {
+            std::collections::hash_map::Entry::Occupied(e) => e.into_mut(),
+            std::collections::hash_map::Entry::Vacant(e) => {
+                let path = self.segment_path(active_segment, item.segment);
+                e.insert(BufReader::new(File::open(path)?))
+            }
+        };
+        reader.seek(SeekFrom::Start(item.offset))?;
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        Ok(serde_json::from_str(line.trim_end())?)
+    }
+
+    fn segment_path(&self, active_segment: u64, segment: u64) -> PathBuf {
+        match active_segment - segment {
+            0 => self.path.clone(),
+            age => self.path_with_suffix(age as usize),
+        }
+    }
+
     fn path_with_suffix(&self, index: usize) -> PathBuf {
         let mut p = self.path.clone();
         let filename = p.file_name().unwrap().to_string_lossy().to_string();
@@ -261,4 +561,122 @@ mod tests {
         assert!(anchor_path.exists());
         assert!(!anchor.hash.is_empty());
     }
+
+    #[test]
+    fn search_filters_and_paginates_across_rotations() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("events.log");
+        let signer = SigningKey::generate(&mut rand::rngs::OsRng);
+        let log = EventLog::new(path.clone(), signer.clone(), 2048).unwrap();
+        for i in 0..20 {
+            log.append(
+                "FILE_RESTORED",
+                EventSeverity::Warn,
+                serde_json::json!({"path": format!("/etc/app/{i}.conf"), "note": "alpha"}),
+            )
+            .unwrap();
+            log.append("HEARTBEAT", EventSeverity::Info, serde_json::json!({"i": i}))
+                .unwrap();
+        }
+        assert!(path.with_file_name("events.log.1").exists());
+
+        // First search builds the index from disk; later appends extend it.
+        let query = EventQuery {
+            event_types: vec!["FILE_RESTORED".into()],
+            path_prefix: Some("/etc/app/".into()),
+            limit: Some(7),
+            ..Default::default()
+        };
+        let first = log.search(&query).unwrap();
+        assert_eq!(first.events.len(), 7);
+        assert!(first.events.windows(2).all(|w| w[0].seq > w[1].seq));
+        log.append(
+            "FILE_RESTORED",
+            EventSeverity::Critical,
+            serde_json::json!({"path": "/etc/app/late.conf", "note": "Omega"}),
+        )
+        .unwrap();
+
+        let mut seen = 0;
+        let mut cursor = None;
+        loop {
+            let page = log
+                .search(&EventQuery {
+                    cursor: cursor.clone(),
+                    ..query.clone()
+                })
+                .unwrap();
+            seen += page.events.len();
+            match page.next_cursor {
+                Some(c) => cursor = Some(c),
+                None => break,
+            }
+        }
+        let all = log
+            .search(&EventQuery {
+                limit: Some(1000),
+                ..Default::default()
+            })
+            .unwrap()
+            .events
+            .len();
+        let restored_total = log
+            .search(&EventQuery {
+                event_types: vec!["FILE_RESTORED".into()],
+                limit: Some(1000),
+                ..Default::default()
+            })
+            .unwrap()
+            .events
+            .len();
+        assert_eq!(seen, restored_total);
+        assert!(all > restored_total);
+
+        let hits = log
+            .search(&EventQuery {
+                text: Some("omega".into()),
+                min_severity: Some(EventSeverity::Error),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(hits.events.len(), 1);
+        assert_eq!(hits.events[0].data["path"], "/etc/app/late.conf");
+
+        // A fresh handle rebuilds the same index from disk.
+        let reopened = EventLog::new(path, signer, 2048).unwrap();
+        let again = reopened
+            .search(&EventQuery {
+                event_types: vec!["FILE_RESTORED".into()],
+                limit: Some(1000),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(again.events.len(), restored_total);
+    }
+
+    #[test]
+    fn notes_link_to_the_annotated_entry() {
+        let dir = tempdir().unwrap();
+        let signer = SigningKey::generate(&mut rand::rngs::OsRng);
+        let log = EventLog::new(dir.path().join("events.log"), signer, 1024 * 1024).unwrap();
+        let target = log
+            .append("TAMPER_DETECTED", EventSeverity::Warn, serde_json::json!({"path": "/a"}))
+            .unwrap();
+        let other = log
+            .append("HEARTBEAT", EventSeverity::Info, serde_json::json!({}))
+            .unwrap();
+
+        assert!(log.annotate(target.seq, "  ", "ops").is_err());
+        assert!(log.annotate(999, "expected", "ops").is_err());
+        let note = log
+            .annotate(target.seq, "expected — deploy #123", "ops")
+            .unwrap();
+        assert_eq!(note.event_type, "EVENT_NOTE");
+        assert_eq!(note.data["hash"], target.hash);
+
+        let notes = log.notes_for(&[target.seq, other.seq]).unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[&target.seq][0].data["note"], "expected — deploy #123");
+        assert_eq!(log.get(other.seq).unwrap().unwrap().hash, other.hash);
+    }
 }
@@ -0,0 +1,550 @@
+//! Deterministic replay of the signed event log.
+//!
+//! `replay_log` reads the active log and its rotations oldest first, checks
+//! every entry's hash chain, signature and sequence number, and folds the
+//! events into the state the service should be in: engine mode, baseline
+//! versions, restore counts. `check_artifacts` then compares that state with
+//! what is actually in the data directory, so a baseline swapped or a write
+//! freeze lifted behind the service's back shows up as an inconsistency.
+//!
+//! Mode tracking assumes the service records `SERVICE_STOP` on shutdown;
+//! maintenance never survives a restart and safe mode only survives one when
+//! it was entered by remote command (the only reason persisted to the vault).
+
+use crate::crypto::verify_signature;
+use crate::event_log::{EventEntry, EventLog};
+use anyhow::Result;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Duration, Utc};
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+const CHAIN_START: &str = "CHAIN_START";
+
+/// How far a baseline's `created_at` may trail the event recording it.
+const BASELINE_CLOCK_SLACK_SECS: i64 = 2;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReplayMode {
+    Active,
+    Maintenance,
+    SafeMode,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum IssueKind {
+    Unparseable,
+    BadHash,
+    BadSignature,
+    BrokenChain,
+    SequenceGap,
+    ImpossibleTransition,
+    ArtifactMismatch,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayIssue {
+    #[serde(default)]
+    pub seq: Option<u64>,
+    pub kind: IssueKind,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModeTransition {
+    pub seq: u64,
+    pub at: DateTime<Utc>,
+    pub event_type: String,
+    pub from: ReplayMode,
+    pub to: ReplayMode,
+}
+
+/// A full baseline (re)generation recorded in the log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BaselineVersion {
+    pub seq: u64,
+    pub at: DateTime<Utc>,
+    pub entries: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RestoreCounts {
+    pub restored: u64,
+    pub failed: u64,
+    pub quarantined: u64,
+    /// Files brought back by snapshot restores.
+    pub snapshot_restored: u64,
+    /// Restores put off because the target was locked.
+    #[serde(default)]
+    pub deferred: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayedState {
+    pub mode: ReplayMode,
+    #[serde(default)]
+    pub safe_mode_reason: Option<String>,
+    pub transitions: Vec<ModeTransition>,
+    pub baselines: Vec<BaselineVersion>,
+    /// Partial baseline refreshes since the last full version.
+    pub baseline_refreshes_since_last: u64,
+    pub restores: RestoreCounts,
+    pub write_freeze_active: bool,
+    pub service_starts: u64,
+    /// `SERVICE_START` not preceded by a `SERVICE_STOP`.
+    pub unclean_restarts: u64,
+    pub event_counts: BTreeMap<String, u64>,
+}
+
+impl Default for ReplayedState {
+    fn default() -> Self {
+        Self {
+            mode: ReplayMode::Active,
+            safe_mode_reason: None,
+            transitions: Vec::new(),
+            baselines: Vec::new(),
+            baseline_refreshes_since_last: 0,
+            restores: RestoreCounts::default(),
+            write_freeze_active: false,
+            service_starts: 0,
+            unclean_restarts: 0,
+            event_counts: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayReport {
+    pub segments: Vec<PathBuf>,
+    pub events: u64,
+    #[serde(default)]
+    pub first_seq: Option<u64>,
+    #[serde(default)]
+    pub last_seq: Option<u64>,
+    pub signatures_checked: bool,
+    pub state: ReplayedState,
+    pub issues: Vec<ReplayIssue>,
+}
+
+impl ReplayReport {
+    pub fn is_consistent(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// The log reaches back to the first event ever written, so absence of an
+    /// event is meaningful.
+    fn is_complete(&self) -> bool {
+        self.first_seq.is_none_or(|s| s == 1)
+    }
+
+    fn issue(&mut self, seq: Option<u64>, kind: IssueKind, detail: impl Into<String>) {
+        self.issues.push(ReplayIssue {
+            seq,
+            kind,
+            detail: detail.into(),
+        });
+    }
+}
+
+/// Parse the device public key (base64, as stored in the vault).
+pub fn decode_device_key(b64: &str) -> Result<VerifyingKey> {
+    crate::policy::decode_org_key(b64).map_err(|e| anyhow::anyhow!("device key: {e}"))
+}
+
+/// Existing log files for `log_path`, oldest rotation first.
+pub fn log_segments(log_path: &Path) -> Vec<PathBuf> {
+    let mut segments: Vec<PathBuf> = (1..=crate::event_log::MAX_ROTATIONS)
+        .rev()
+        .map(|i| {
+            let mut p = log_path.as_os_str().to_owned();
+            p.push(format!(".{i}"));
+            PathBuf::from(p)
+        })
+        .filter(|p| p.exists())
+        .collect();
+    if log_path.exists() {
+        segments.push(log_path.to_path_buf());
+    }
+    segments
+}
+
+/// Recompute `entry`'s hash and check its signature when a key is given.
+fn check_entry(entry: &EventEntry, key: Option<&VerifyingKey>) -> Result<(), (IssueKind, String)> {
+    let mut value = serde_json::json!({
+        "seq": entry.seq,
+        "timestamp": entry.timestamp,
+        "event_type": entry.event_type,
+        "severity": entry.severity,
+        "data": entry.data,
+        "prev_hash": entry.prev_hash,
+    });
+    let hash = EventLog::compute_hash(&value).map_err(|e| (IssueKind::BadHash, e.to_string()))?;
+    if hash != entry.hash {
+        return Err((IssueKind::BadHash, "entry hash does not match its contents".into()));
+    }
+    let Some(key) = key else {
+        return Ok(());
+    };
+    value["hash"] = serde_json::Value::String(entry.hash.clone());
+    let sig = general_purpose::STANDARD
+        .decode(&entry.signature)
+        .ok()
+        .and_then(|b| <[u8; 64]>::try_from(b).ok())
+        .map(|b| Signature::from_bytes(&b))
+        .ok_or((IssueKind::BadSignature, "malformed signature".to_string()))?;
+    verify_signature(key, value.to_string().as_bytes(), &sig)
+        .map_err(|_| (IssueKind::BadSignature, "signature does not verify".to_string()))
+}
+
+fn transition(report: &mut ReplayReport, entry: &EventEntry, to: ReplayMode) {
+    let from = report.state.mode;
+    if from != to {
+        report.state.transitions.push(ModeTransition {
+            seq: entry.seq,
+            at: entry.timestamp,
+            event_type: entry.event_type.clone(),
+            from,
+            to,
+        });
+    }
+    report.state.mode = to;
+}
+
+fn apply(report: &mut ReplayReport, entry: &EventEntry, stopped: &mut bool) {
+    *report
+        .state
+        .event_counts
+        .entry(entry.event_type.clone())
+        .or_default() += 1;
+    let data = &entry.data;
+    let seq = Some(entry.seq);
+    match entry.event_type.as_str() {
+        "SERVICE_START" => {
+            report.state.service_starts += 1;
+            if !*stopped && report.state.service_starts > 1 {
+                report.state.unclean_restarts += 1;
+            }
+            *stopped = false;
+        }
+        "SERVICE_STOP" => {
+            *stopped = true;
+            let persisted = report.state.safe_mode_reason.as_deref() == Some("REMOTE_COMMAND");
+            if report.state.mode == ReplayMode::Maintenance
+                || (report.state.mode == ReplayMode::SafeMode && !persisted)
+            {
+                report.state.safe_mode_reason = None;
+                transition(report, entry, ReplayMode::Active);
+            }
+        }
+        "SAFE_MODE_ENTERED" => {
+            report.state.safe_mode_reason =
+                data.get("reason").and_then(|r| r.as_str()).map(String::from);
+            transition(report, entry, ReplayMode::SafeMode);
+        }
+        "SAFE_MODE_EXITED" => {
+            if report.state.mode != ReplayMode::SafeMode && report.is_complete() {
+                report.issue(seq, IssueKind::ImpossibleTransition, "safe mode exited but was not entered");
+            }
+            report.state.safe_mode_reason = None;
+            transition(report, entry, ReplayMode::Active);
+        }
+        "MAINTENANCE_ENTER" => {
+            if report.state.mode != ReplayMode::Active {
+                report.issue(
+                    seq,
+                    IssueKind::ImpossibleTransition,
+                    format!("maintenance entered from {:?}", report.state.mode),
+                );
+            }
+            transition(report, entry, ReplayMode::Maintenance);
+        }
+        "MAINTENANCE_EXIT" | "MAINTENANCE_TIMEOUT" => {
+            if report.state.mode != ReplayMode::Maintenance && report.is_complete() {
+                report.issue(seq, IssueKind::ImpossibleTransition, "maintenance ended but was not entered");
+            }
+            transition(report, entry, ReplayMode::Active);
+        }
+        "BASELINE_CREATED" | "BASELINE_UPDATED" => {
+            // Exclusion refreshes touch a subset of entries; everything else
+            // regenerates the whole baseline.
+            if data.get("source").is_some() {
+                report.state.baseline_refreshes_since_last += 1;
+            } else {
+                let entries = data
+                    .get("files")
+                    .or_else(|| data.get("entries"))
+                    .and_then(|v| v.as_u64());
+                report.state.baselines.push(BaselineVersion {
+                    seq: entry.seq,
+                    at: entry.timestamp,
+                    entries,
+                });
+                report.state.baseline_refreshes_since_last = 0;
+            }
+        }
+        "RESTORE_SUCCESS" => report.state.restores.restored += 1,
+        "RESTORE_FAILURE" => {
+            if data.get("quarantined").is_some_and(|q| !q.is_null()) {
+                report.state.restores.quarantined += 1;
+            } else {
+                report.state.restores.failed += 1;
+            }
+        }
+        "RESTORE_DEFERRED" => report.state.restores.deferred += 1,
+        "SNAPSHOT_RESTORE" => {
+            report.state.restores.snapshot_restored +=
+                data.get("restored").and_then(|v| v.as_u64()).unwrap_or(0);
+        }
+        "RANSOMWARE_SUSPECTED"
+            if data
+                .get("frozen_directories")
+                .and_then(|v| v.as_u64())
+                .is_some_and(|n| n > 0) =>
+        {
+            report.state.write_freeze_active = true;
+        }
+        "WRITE_FREEZE_RELEASED" => report.state.write_freeze_active = false,
+        _ => {}
+    }
+}
+
+/// Replay every segment of the log at `log_path`. Signatures are checked
+/// when `key` (the device's public key) is given; hashes, chain links and
+/// sequence numbers always are.
+pub fn replay_log(log_path: &Path, key: Option<&VerifyingKey>) -> Result<ReplayReport> {
+    let mut report = ReplayReport {
+        segments: log_segments(log_path),
+        events: 0,
+        first_seq: None,
+        last_seq: None,
+        signatures_checked: key.is_some(),
+        state: ReplayedState::default(),
+        issues: Vec::new(),
+    };
+    let mut stopped = true;
+    for segment in report.segments.clone() {
+        let reader = BufReader::new(File::open(&segment)?);
+        let mut prev_hash: Option<String> = None;
+        for (line_no, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: EventEntry = match serde_json::from_str(&line) {
+                Ok(e) => e,
+                Err(e) => {
+                    report.issue(
+                        None,
+                        IssueKind::Unparseable,
+                        format!("{}:{}: {e}", segment.display(), line_no + 1),
+                    );
+                    prev_hash = None;
+                    continue;
+                }
+            };
+            let seq = Some(entry.seq);
+
+            if let Err((kind, detail)) = check_entry(&entry, key) {
+                report.issue(seq, kind, detail);
+            }
+            // Each segment starts a fresh chain.
+            let expected_prev = prev_hash.as_deref().unwrap_or(CHAIN_START);
+            if entry.prev_hash != expected_prev {
+                report.issue(seq, IssueKind::BrokenChain, "prev_hash does not link to the previous entry");
+            }
+            if let Some(last) = report.last_seq {
+                if entry.seq != last + 1 {
+                    report.issue(seq, IssueKind::SequenceGap, format!("expected seq {}", last + 1));
+                }
+            }
+
+            report.first_seq.get_or_insert(entry.seq);
+            report.last_seq = Some(entry.seq);
+            report.events += 1;
+            prev_hash = Some(entry.hash.clone());
+            apply(&mut report, &entry, &mut stopped);
+        }
+    }
+    Ok(report)
+}
+
+/// Compare the replayed state with the baseline and write-freeze files in
+/// `data_dir`, appending any mismatch to `report.issues`.
+pub fn check_artifacts(report: &mut ReplayReport, data_dir: &Path) -> Result<()> {
+    #[derive(Deserialize)]
+    struct BaselineHeader {
+        created_at: DateTime<Utc>,
+        entries: serde_json::Map<String, serde_json::Value>,
+    }
+
+    let baseline_path = data_dir.join("baseline.json");
+    let on_disk: Option<BaselineHeader> = if baseline_path.exists() {
+        Some(serde_json::from_slice(&std::fs::read(&baseline_path)?)?)
+    } else {
+        None
+    };
+    let versions = &report.state.baselines;
+    let last = versions.last().cloned();
+    let previous_at = versions.iter().rev().nth(1).map(|v| v.at);
+    let refreshed = report.state.baseline_refreshes_since_last > 0;
+
+    match (&on_disk, last) {
+        (None, Some(v)) => report.issue(
+            Some(v.seq),
+            IssueKind::ArtifactMismatch,
+            "log records a baseline but baseline.json is missing",
+        ),
+        (Some(_), None) if report.is_complete() => report.issue(
+            None,
+            IssueKind::ArtifactMismatch,
+            "baseline.json exists but the log never records one being created",
+        ),
+        (Some(b), Some(v)) => {
+            if b.created_at > v.at + Duration::seconds(BASELINE_CLOCK_SLACK_SECS) {
+                report.issue(
+                    Some(v.seq),
+                    IssueKind::ArtifactMismatch,
+                    format!("baseline.json was created at {} after the last logged baseline", b.created_at),
+                );
+            } else if previous_at.is_some_and(|p| b.created_at < p) {
+                report.issue(
+                    Some(v.seq),
+                    IssueKind::ArtifactMismatch,
+                    format!("baseline.json ({}) predates the last logged baseline", b.created_at),
+                );
+            }
+            if let Some(expected) = v.entries.filter(|_| !refreshed) {
+                if b.entries.len() as u64 != expected {
+                    report.issue(
+                        Some(v.seq),
+                        IssueKind::ArtifactMismatch,
+                        format!(
+                            "baseline.json has {} entries, log recorded {expected}",
+                            b.entries.len()
+                        ),
+                    );
+                }
+            }
+        }
+        _ => {}
+    }
+
+    let freeze_on_disk = data_dir.join("write_freeze.json").exists();
+    if freeze_on_disk != report.state.write_freeze_active
+        && (report.is_complete() || !freeze_on_disk)
+    {
+        let detail = if freeze_on_disk {
+            "write freeze is in place but the log never records one"
+        } else {
+            "log records an active write freeze but write_freeze.json is missing"
+        };
+        report.issue(None, IssueKind::ArtifactMismatch, detail);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_log::EventSeverity;
+    use ed25519_dalek::SigningKey;
+    use rand::rngs::OsRng;
+    use serde_json::json;
+    use std::fs;
+
+    fn log_with(dir: &Path, key: &SigningKey) -> EventLog {
+        EventLog::new(dir.join("events.log"), key.clone(), 1 << 20).unwrap()
+    }
+
+    #[test]
+    fn replays_mode_and_restore_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = SigningKey::generate(&mut OsRng);
+        let log = log_with(dir.path(), &key);
+        log.append("BASELINE_CREATED", EventSeverity::Info, json!({"files": 2})).unwrap();
+        log.append("SERVICE_START", EventSeverity::Info, json!({})).unwrap();
+        log.append("MAINTENANCE_ENTER", EventSeverity::Info, json!({})).unwrap();
+        log.append("MAINTENANCE_EXIT", EventSeverity::Info, json!({})).unwrap();
+        log.append("RESTORE_SUCCESS", EventSeverity::Warn, json!({"path": "/a"})).unwrap();
+        log.append("RESTORE_FAILURE", EventSeverity::Critical, json!({"path": "/b", "quarantined": "/q/b"}))
+            .unwrap();
+        log.append("SAFE_MODE_ENTERED", EventSeverity::Critical, json!({"reason": "MANUAL"})).unwrap();
+
+        let report = replay_log(&dir.path().join("events.log"), Some(&key.verifying_key())).unwrap();
+        assert!(report.is_consistent(), "{:?}", report.issues);
+        assert_eq!(report.events, 7);
+        assert_eq!(report.state.mode, ReplayMode::SafeMode);
+        assert_eq!(report.state.transitions.len(), 3);
+        assert_eq!(report.state.baselines.len(), 1);
+        assert_eq!(report.state.restores.restored, 1);
+        assert_eq!(report.state.restores.quarantined, 1);
+
+        // A forged key fails every signature.
+        let other = SigningKey::generate(&mut OsRng);
+        let forged = replay_log(&dir.path().join("events.log"), Some(&other.verifying_key())).unwrap();
+        assert_eq!(forged.issues.len(), 7);
+        assert!(forged.issues.iter().all(|i| i.kind == IssueKind::BadSignature));
+    }
+
+    #[test]
+    fn edited_or_removed_entries_are_flagged() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = SigningKey::generate(&mut OsRng);
+        let log = log_with(dir.path(), &key);
+        for i in 0..4 {
+            log.append("TEST", EventSeverity::Info, serde_json::json!({"i": i})).unwrap();
+        }
+        let path = dir.path().join("events.log");
+        let text = fs::read_to_string(&path).unwrap();
+        let mut lines: Vec<String> = text.lines().map(String::from).collect();
+        lines.remove(1);
+        lines[2] = lines[2].replace("\"i\":3", "\"i\":9");
+        fs::write(&path, lines.join("\n") + "\n").unwrap();
+
+        let report = replay_log(&path, None).unwrap();
+        let kinds: Vec<IssueKind> = report.issues.iter().map(|i| i.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![IssueKind::BrokenChain, IssueKind::SequenceGap, IssueKind::BadHash]
+        );
+    }
+
+    #[test]
+    fn artifacts_are_compared_with_the_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = SigningKey::generate(&mut OsRng);
+        let log = log_with(dir.path(), &key);
+        let created_at = Utc::now();
+        log.append("BASELINE_CREATED", EventSeverity::Info, serde_json::json!({"files": 1}))
+            .unwrap();
+        let baseline = |at: DateTime<Utc>, n: usize| {
+            let entries: serde_json::Map<_, _> = (0..n)
+                .map(|i| (format!("/p/{i}"), serde_json::json!({})))
+                .collect();
+            serde_json::json!({"created_at": at, "entries": entries}).to_string()
+        };
+
+        fs::write(dir.path().join("baseline.json"), baseline(created_at, 1)).unwrap();
+        let mut report = replay_log(&dir.path().join("events.log"), None).unwrap();
+        check_artifacts(&mut report, dir.path()).unwrap();
+        assert!(report.is_consistent(), "{:?}", report.issues);
+
+        // Swapped in later with different contents, and a stray freeze file.
+        fs::write(
+            dir.path().join("baseline.json"),
+            baseline(created_at + Duration::minutes(5), 3),
+        )
+        .unwrap();
+        fs::write(dir.path().join("write_freeze.json"), "{}").unwrap();
+        let mut report = replay_log(&dir.path().join("events.log"), None).unwrap();
+        check_artifacts(&mut report, dir.path()).unwrap();
+        assert_eq!(report.issues.len(), 3);
+        assert!(report.issues.iter().all(|i| i.kind == IssueKind::ArtifactMismatch));
+    }
+}
@@ -0,0 +1,336 @@
+//! Typed payloads for the core event types.
+//!
+//! `GuardEvent` pins down the payload of the events other components read
+//! back (replay, path statistics, the desktop timeline). Each type has a
+//! schema version in `EVENT_SCHEMAS`, written into the payload as `schema`.
+//! Entries logged before typed events existed carry no `schema` and are read
+//! as version 0 through `upgrade_legacy`.
+//!
+//! Field names match the untyped payloads so existing readers of the raw
+//! `data` keep working.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::event_log::EventEntry;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+pub struct EventSchema {
+    pub event_type: &'static str,
+    pub version: u32,
+}
+
+/// Current schema version of every typed event type.
+pub const EVENT_SCHEMAS: &[EventSchema] = &[
+    // v2: `symlink_swap` kind. v3: `attributes_changed` kind.
+    EventSchema { event_type: "TAMPER_DETECTED", version: 3 },
+    EventSchema { event_type: "PERMISSIONS_RESTORED", version: 1 },
+    EventSchema { event_type: "ATTRIBUTES_RESTORED", version: 1 },
+    EventSchema { event_type: "UNAUTHORIZED_FILE", version: 1 },
+    EventSchema { event_type: "RESTORE_SUCCESS", version: 1 },
+    EventSchema { event_type: "RESTORE_FAILURE", version: 1 },
+    EventSchema { event_type: "RESTORE_DEFERRED", version: 1 },
+    EventSchema { event_type: "BASELINE_CREATED", version: 1 },
+    EventSchema { event_type: "BASELINE_UPDATED", version: 1 },
+    // v2: `attributes`.
+    EventSchema { event_type: "INTEGRITY_VIOLATION", version: 2 },
+    EventSchema { event_type: "SERVICE_START", version: 1 },
+    EventSchema { event_type: "SERVICE_STOP", version: 1 },
+    EventSchema { event_type: "EVENT_NOTE", version: 1 },
+];
+
+/// Schema version of `event_type`, or `None` if it is not a typed event.
+pub fn schema_version(event_type: &str) -> Option<u32> {
+    EVENT_SCHEMAS
+        .iter()
+        .find(|s| s.event_type == event_type)
+        .map(|s| s.version)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TamperKind {
+    Modified {
+        expected_hash: String,
+        actual_hash: String,
+    },
+    Deleted {
+        expected_hash: String,
+    },
+    PermissionChanged {
+        expected: u32,
+        actual: u32,
+    },
+    Renamed {
+        new_path: String,
+    },
+    /// `path` is a link where the baseline has a file, directory or a link
+    /// to somewhere else.
+    SymlinkSwap {
+        target: String,
+    },
+    /// Ownership, security descriptor or tracked extended attributes differ
+    /// from the baseline; `attributes` names each one, e.g. `uid` or
+    /// `xattr:security.capability`.
+    AttributesChanged {
+        attributes: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event_type", content = "data", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum GuardEvent {
+    TamperDetected {
+        path: String,
+        #[serde(flatten)]
+        kind: TamperKind,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        tags: Vec<String>,
+    },
+    PermissionsRestored {
+        path: String,
+        restored_perms: u32,
+    },
+    AttributesRestored {
+        path: String,
+        attributes: Vec<String>,
+    },
+    UnauthorizedFile {
+        path: String,
+        file_hash: String,
+        file_size: u64,
+        suspicious: bool,
+        #[serde(default)]
+        reasons: Vec<String>,
+    },
+    RestoreSuccess {
+        path: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        tags: Vec<String>,
+    },
+    RestoreFailure {
+        path: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        quarantined: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        tags: Vec<String>,
+    },
+    /// Target locked by another process; `queued` if the restore will be
+    /// retried at the next service start.
+    RestoreDeferred {
+        path: String,
+        error: String,
+        attempts: usize,
+        queued: bool,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        tags: Vec<String>,
+    },
+    BaselineCreated {
+        files: usize,
+    },
+    BaselineUpdated {
+        entries: usize,
+        /// Pending watcher events dropped by a maintenance rebaseline.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        drained_events: Option<usize>,
+        /// Set for partial refreshes, e.g. `"exclusion"`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        source: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        paths: Vec<String>,
+    },
+    IntegrityViolation {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        source: Option<String>,
+        modified: usize,
+        removed: usize,
+        added: usize,
+        /// Files whose ownership or tracked attributes changed.
+        #[serde(default)]
+        attributes: usize,
+        #[serde(default)]
+        tags: BTreeMap<String, usize>,
+        #[serde(default)]
+        roots: BTreeMap<String, usize>,
+    },
+    ServiceStart {},
+    ServiceStop {},
+    /// Operator note on the earlier entry `seq`, whose hash was `hash`.
+    /// `author` is the label of the authenticated client that wrote it.
+    EventNote {
+        seq: u64,
+        hash: String,
+        note: String,
+        author: String,
+    },
+}
+
+impl GuardEvent {
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            GuardEvent::TamperDetected { .. } => "TAMPER_DETECTED",
+            GuardEvent::PermissionsRestored { .. } => "PERMISSIONS_RESTORED",
+            GuardEvent::AttributesRestored { .. } => "ATTRIBUTES_RESTORED",
+            GuardEvent::UnauthorizedFile { .. } => "UNAUTHORIZED_FILE",
+            GuardEvent::RestoreSuccess { .. } => "RESTORE_SUCCESS",
+            GuardEvent::RestoreFailure { .. } => "RESTORE_FAILURE",
+            GuardEvent::RestoreDeferred { .. } => "RESTORE_DEFERRED",
+            GuardEvent::BaselineCreated { .. } => "BASELINE_CREATED",
+            GuardEvent::BaselineUpdated { .. } => "BASELINE_UPDATED",
+            GuardEvent::IntegrityViolation { .. } => "INTEGRITY_VIOLATION",
+            GuardEvent::ServiceStart {} => "SERVICE_START",
+            GuardEvent::ServiceStop {} => "SERVICE_STOP",
+            GuardEvent::EventNote { .. } => "EVENT_NOTE",
+        }
+    }
+
+    /// Payload as stored in `EventEntry::data`, stamped with its schema
+    /// version.
+    pub fn to_data(&self) -> Result<Value> {
+        let mut value = serde_json::to_value(self)?;
+        let mut data = value
+            .get_mut("data")
+            .map(Value::take)
+            .unwrap_or_else(|| Value::Object(Default::default()));
+        let version = schema_version(self.event_type())
+            .ok_or_else(|| anyhow!("no schema registered for {}", self.event_type()))?;
+        data["schema"] = Value::from(version);
+        Ok(data)
+    }
+
+    /// Parse a logged entry. Returns `None` for event types without a typed
+    /// schema; fails if the payload is malformed or was written by a newer
+    /// schema than this build knows.
+    pub fn from_entry(entry: &EventEntry) -> Result<Option<GuardEvent>> {
+        let Some(current) = schema_version(&entry.event_type) else {
+            return Ok(None);
+        };
+        let mut data = entry.data.clone();
+        let version = match data.as_object_mut().and_then(|o| o.remove("schema")) {
+            Some(v) => v
+                .as_u64()
+                .ok_or_else(|| anyhow!("seq {}: schema is not a number", entry.seq))?
+                as u32,
+            None => 0,
+        };
+        if version > current {
+            return Err(anyhow!(
+                "seq {}: {} schema v{} is newer than supported v{}",
+                entry.seq,
+                entry.event_type,
+                version,
+                current
+            ));
+        }
+        if version == 0 {
+            upgrade_legacy(&entry.event_type, &mut data);
+        }
+        let event = serde_json::from_value(serde_json::json!({
+            "event_type": entry.event_type,
+            "data": data,
+        }))
+        .map_err(|e| anyhow!("seq {}: invalid {} payload: {e}", entry.seq, entry.event_type))?;
+        Ok(Some(event))
+    }
+}
+
+/// Bring an untyped payload into the v1 shape. Untyped writers were not held
+/// to one shape: baseline counts appear as `entries` as well as `files`
+/// (replay accepts both) and a missing quarantine path was written as `null`.
+fn upgrade_legacy(event_type: &str, data: &mut Value) {
+    let Some(obj) = data.as_object_mut() else {
+        *data = Value::Object(Default::default());
+        return;
+    };
+    match event_type {
+        "BASELINE_CREATED" if !obj.contains_key("files") => {
+            if let Some(entries) = obj.remove("entries") {
+                obj.insert("files".into(), entries);
+            }
+        }
+        "RESTORE_FAILURE" => {
+            obj.retain(|k, v| !(k == "quarantined" && v.is_null()));
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_log::EventSeverity;
+    use chrono::Utc;
+
+    fn entry(event_type: &str, data: Value) -> EventEntry {
+        EventEntry {
+            seq: 1,
+            timestamp: Utc::now(),
+            event_type: event_type.into(),
+            severity: EventSeverity::Info,
+            data,
+            prev_hash: String::new(),
+            hash: String::new(),
+            signature: String::new(),
+        }
+    }
+
+    #[test]
+    fn typed_payload_keeps_untyped_field_names() {
+        let event = GuardEvent::TamperDetected {
+            path: "/srv/app.bin".into(),
+            kind: TamperKind::PermissionChanged { expected: 0o644, actual: 0o777 },
+            tags: vec![],
+        };
+        let data = event.to_data().unwrap();
+        assert_eq!(
+            data,
+            serde_json::json!({
+                "path": "/srv/app.bin",
+                "kind": "permission_changed",
+                "expected": 0o644,
+                "actual": 0o777,
+                "schema": 3,
+            })
+        );
+        let parsed = GuardEvent::from_entry(&entry("TAMPER_DETECTED", data)).unwrap();
+        assert_eq!(parsed, Some(event));
+    }
+
+    #[test]
+    fn legacy_entries_are_upgraded() {
+        let parsed = GuardEvent::from_entry(&entry(
+            "BASELINE_CREATED",
+            serde_json::json!({"entries": 12}),
+        ))
+        .unwrap();
+        assert_eq!(parsed, Some(GuardEvent::BaselineCreated { files: 12 }));
+
+        let parsed = GuardEvent::from_entry(&entry(
+            "RESTORE_FAILURE",
+            serde_json::json!({"path": "/a", "quarantined": null}),
+        ))
+        .unwrap();
+        assert_eq!(
+            parsed,
+            Some(GuardEvent::RestoreFailure {
+                path: "/a".into(),
+                error: None,
+                quarantined: None,
+                tags: vec![],
+            })
+        );
+
+        assert!(GuardEvent::from_entry(&entry("HEARTBEAT_SENT", serde_json::json!({})))
+            .unwrap()
+            .is_none());
+        assert!(GuardEvent::from_entry(&entry(
+            "SERVICE_START",
+            serde_json::json!({"schema": 99}),
+        ))
+        .is_err());
+    }
+}
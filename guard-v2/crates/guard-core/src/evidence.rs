@@ -0,0 +1,301 @@
+//! Evidence archives for quarantined files.
+//!
+//! An export packs quarantined files with a chain-of-custody manifest into a
+//! single archive, encrypted under an operator-chosen password (Argon2id +
+//! XChaCha20-Poly1305, the same primitives as the vault). The manifest lists
+//! each file's SHA-256 and size, and every device that handles the archive
+//! appends a custody record — who, where, when, what — signed with its device
+//! key over the item list and all earlier records. Records can't be dropped,
+//! reordered or edited without breaking a later signature.
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+use crate::crypto::{decrypt, derive_key, encrypt, generate_nonce, generate_salt};
+use crate::policy::decode_org_key;
+
+pub const EVIDENCE_FORMAT: &str = "darklock-evidence-v1";
+
+/// Domain separator so a custody signature can't be replayed as any other
+/// device-signed message.
+const CUSTODY_SIGNING_CONTEXT: &[u8] = b"darklock-guard-custody-v1\0";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EvidenceItem {
+    /// File name in the quarantine zone; a single path component.
+    pub name: String,
+    /// Where the file was quarantined from, when the event log still says.
+    #[serde(default)]
+    pub original_path: Option<String>,
+    pub size: u64,
+    /// Lowercase hex SHA-256, for checking with standard forensic tools.
+    pub sha256: String,
+}
+
+impl EvidenceItem {
+    pub fn new(name: String, original_path: Option<String>, data: &[u8]) -> Self {
+        Self {
+            name,
+            original_path,
+            size: data.len() as u64,
+            sha256: hex::encode(Sha256::digest(data)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CustodyAction {
+    Exported,
+    Imported,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CustodyRecord {
+    pub action: CustodyAction,
+    pub device_id: String,
+    /// Operator-supplied name of the person handling the evidence.
+    pub examiner: String,
+    pub at: DateTime<Utc>,
+    /// base64 device public key the record is signed with.
+    pub public_key: String,
+    /// base64 ed25519 signature; see `CustodyManifest::record_message`.
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CustodyManifest {
+    pub archive_id: String,
+    pub items: Vec<EvidenceItem>,
+    pub custody: Vec<CustodyRecord>,
+}
+
+impl CustodyManifest {
+    pub fn new(items: Vec<EvidenceItem>) -> Self {
+        Self {
+            archive_id: uuid::Uuid::new_v4().to_string(),
+            items,
+            custody: Vec::new(),
+        }
+    }
+
+    /// `CUSTODY_SIGNING_CONTEXT || json(archive, items, earlier records,
+    /// record without its signature)` for the record at `index`.
+    fn record_message(&self, index: usize, record: &CustodyRecord) -> Result<Vec<u8>> {
+        let mut msg = CUSTODY_SIGNING_CONTEXT.to_vec();
+        msg.extend_from_slice(&serde_json::to_vec(&serde_json::json!({
+            "archive_id": self.archive_id,
+            "items": self.items,
+            "prior": &self.custody[..index],
+            "action": record.action,
+            "device_id": record.device_id,
+            "examiner": record.examiner,
+            "at": record.at,
+            "public_key": record.public_key,
+        }))?);
+        Ok(msg)
+    }
+
+    /// Sign and append a custody record for `action` by this device.
+    pub fn append(
+        &mut self,
+        action: CustodyAction,
+        device_id: &str,
+        examiner: &str,
+        device_key: &SigningKey,
+    ) -> Result<()> {
+        let mut record = CustodyRecord {
+            action,
+            device_id: device_id.to_string(),
+            examiner: examiner.to_string(),
+            at: Utc::now(),
+            public_key: general_purpose::STANDARD.encode(device_key.verifying_key().to_bytes()),
+            signature: String::new(),
+        };
+        let signature = device_key.sign(&self.record_message(self.custody.len(), &record)?);
+        record.signature = general_purpose::STANDARD.encode(signature.to_bytes());
+        self.custody.push(record);
+        Ok(())
+    }
+
+    /// Check the chain: it starts with an export and every record verifies
+    /// against the key it names.
+    pub fn verify(&self) -> Result<()> {
+        if self.custody.first().map(|r| r.action) != Some(CustodyAction::Exported) {
+            return Err(anyhow!("custody chain does not start with an export"));
+        }
+        for (index, record) in self.custody.iter().enumerate() {
+            let key = decode_org_key(&record.public_key)
+                .map_err(|e| anyhow!("custody record {index}: {e}"))?;
+            let sig_bytes = general_purpose::STANDARD
+                .decode(&record.signature)
+                .map_err(|e| anyhow!("custody record {index}: decode signature: {e}"))?;
+            let arr: [u8; 64] = sig_bytes
+                .try_into()
+                .map_err(|_| anyhow!("custody record {index}: signature length"))?;
+            key.verify_strict(
+                &self.record_message(index, record)?,
+                &Signature::from_bytes(&arr),
+            )
+            .map_err(|e| anyhow!("custody record {index} invalid: {e}"))?;
+        }
+        Ok(())
+    }
+
+    /// Key of the device that exported the archive.
+    pub fn exporter_key(&self) -> Result<VerifyingKey> {
+        let first = self
+            .custody
+            .first()
+            .ok_or_else(|| anyhow!("custody chain is empty"))?;
+        decode_org_key(&first.public_key)
+    }
+}
+
+/// Decrypted archive contents.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvidencePayload {
+    pub manifest: CustodyManifest,
+    /// base64 file contents by item name.
+    pub files: BTreeMap<String, String>,
+}
+
+impl EvidencePayload {
+    /// Contents of `item`, checked against its manifest size and hash.
+    pub fn file(&self, item: &EvidenceItem) -> Result<Vec<u8>> {
+        let data = general_purpose::STANDARD
+            .decode(
+                self.files
+                    .get(&item.name)
+                    .ok_or_else(|| anyhow!("{} missing from archive", item.name))?,
+            )
+            .map_err(|e| anyhow!("{}: {e}", item.name))?;
+        if data.len() as u64 != item.size || hex::encode(Sha256::digest(&data)) != item.sha256 {
+            return Err(anyhow!("{} does not match its manifest hash", item.name));
+        }
+        Ok(data)
+    }
+}
+
+/// On-disk archive: the payload as zstd-compressed JSON, encrypted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvidenceArchive {
+    pub format: String,
+    /// hex Argon2id salt.
+    pub salt: String,
+    /// hex XChaCha20 nonce.
+    pub nonce: String,
+    /// base64 ciphertext.
+    pub ciphertext: String,
+}
+
+impl EvidenceArchive {
+    pub fn seal(payload: &EvidencePayload, password: &str) -> Result<Self> {
+        if password.is_empty() {
+            return Err(anyhow!("an evidence archive needs a password"));
+        }
+        let salt = generate_salt();
+        let nonce = generate_nonce();
+        let key = derive_key(password, &salt)?;
+        let compressed = zstd::encode_all(serde_json::to_vec(payload)?.as_slice(), 3)?;
+        Ok(Self {
+            format: EVIDENCE_FORMAT.to_string(),
+            salt: hex::encode(salt),
+            nonce: hex::encode(nonce),
+            ciphertext: general_purpose::STANDARD.encode(encrypt(&key, &nonce, &compressed)?),
+        })
+    }
+
+    pub fn open(&self, password: &str) -> Result<EvidencePayload> {
+        if self.format != EVIDENCE_FORMAT {
+            return Err(anyhow!("unsupported evidence format {:?}", self.format));
+        }
+        let salt = hex::decode(&self.salt).map_err(|e| anyhow!("decode salt: {e}"))?;
+        let nonce: [u8; 24] = hex::decode(&self.nonce)
+            .map_err(|e| anyhow!("decode nonce: {e}"))?
+            .try_into()
+            .map_err(|_| anyhow!("nonce length"))?;
+        let ciphertext = general_purpose::STANDARD
+            .decode(&self.ciphertext)
+            .map_err(|e| anyhow!("decode ciphertext: {e}"))?;
+        let key = derive_key(password, &salt)?;
+        let compressed = decrypt(&key, &nonce, &ciphertext)
+            .map_err(|_| anyhow!("wrong password or corrupted archive"))?;
+        Ok(serde_json::from_slice(&zstd::decode_all(
+            compressed.as_slice(),
+        )?)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    fn payload(device: &SigningKey) -> EvidencePayload {
+        let data = b"dropped payload";
+        let mut manifest = CustodyManifest::new(vec![EvidenceItem::new(
+            "20260101T000000.000_evil.so".into(),
+            Some("/usr/lib/evil.so".into()),
+            data,
+        )]);
+        manifest
+            .append(CustodyAction::Exported, "device-a", "alice", device)
+            .unwrap();
+        let files = manifest
+            .items
+            .iter()
+            .map(|i| (i.name.clone(), general_purpose::STANDARD.encode(data)))
+            .collect();
+        EvidencePayload { manifest, files }
+    }
+
+    #[test]
+    fn custody_chain_verifies_and_detects_tampering() {
+        let exporter = SigningKey::generate(&mut OsRng);
+        let importer = SigningKey::generate(&mut OsRng);
+        let mut manifest = payload(&exporter).manifest;
+        manifest
+            .append(CustodyAction::Imported, "device-b", "bob", &importer)
+            .unwrap();
+        manifest.verify().unwrap();
+        assert_eq!(manifest.exporter_key().unwrap(), exporter.verifying_key());
+
+        let mut edited = manifest.clone();
+        edited.items[0].sha256 = "00".repeat(32);
+        assert!(edited.verify().is_err());
+
+        let mut reworded = manifest.clone();
+        reworded.custody[0].examiner = "mallory".into();
+        assert!(reworded.verify().is_err());
+
+        let mut truncated = manifest;
+        truncated.custody.remove(0);
+        assert!(truncated.verify().is_err());
+    }
+
+    #[test]
+    fn archive_round_trips_under_its_password_only() {
+        let device = SigningKey::generate(&mut OsRng);
+        let payload = payload(&device);
+        let archive = EvidenceArchive::seal(&payload, "correct horse").unwrap();
+        assert!(archive.open("wrong").is_err());
+
+        let opened = archive.open("correct horse").unwrap();
+        assert_eq!(opened.manifest, payload.manifest);
+        let item = &opened.manifest.items[0].clone();
+        assert_eq!(opened.file(item).unwrap(), b"dropped payload");
+
+        let mut swapped = opened;
+        swapped.files.insert(
+            item.name.clone(),
+            general_purpose::STANDARD.encode(b"other"),
+        );
+        assert!(swapped.file(item).is_err());
+    }
+}
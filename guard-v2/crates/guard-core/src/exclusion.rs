@@ -0,0 +1,32 @@
+//! Time-boxed exclusions of protected paths.
+//!
+//! An exclusion lets an operator edit a protected file (or everything under
+//! a protected subdirectory) without entering maintenance mode. While it is
+//! active neither the watcher nor the audit loop enforces the path; when it
+//! ends the path's current contents become its baseline.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Longest exclusion an operator may request.
+pub const MAX_EXCLUSION_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PathExclusion {
+    /// Canonical path; covers everything beneath it.
+    pub path: String,
+    pub reason: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl PathExclusion {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at <= now
+    }
+
+    pub fn covers(&self, path: &Path) -> bool {
+        path.starts_with(&self.path)
+    }
+}
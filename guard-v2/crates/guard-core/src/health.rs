@@ -0,0 +1,198 @@
+//! Service health reported alongside `IpcResponse::Status`.
+
+use crate::safe_mode::SafeModeReason;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum HealthState {
+    Ok,
+    Degraded,
+    Failed,
+    Disabled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubsystemStatus {
+    pub name: String,
+    pub state: HealthState,
+    #[serde(default)]
+    pub restarts: u32,
+    /// Seconds since a supervised task last beat its heartbeat.
+    #[serde(default)]
+    pub heartbeat_age_secs: Option<u64>,
+    /// Why the subsystem isn't `Ok`, or a short summary when it is.
+    #[serde(default)]
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QueueDepths {
+    /// Verified tamper events waiting for the enforcement consumer.
+    pub tamper_events: usize,
+    /// Events held back while in maintenance mode.
+    pub maintenance_queued: usize,
+    pub restores_in_progress: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanSummary {
+    pub at: DateTime<Utc>,
+    pub files: usize,
+    pub violations: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BaselineHealth {
+    pub present: bool,
+    pub entries: usize,
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+    /// `None` when there is no baseline to check.
+    #[serde(default)]
+    pub signature_valid: Option<bool>,
+    /// Operator countersignature check; `None` unless dual control is on.
+    #[serde(default)]
+    pub operator_signature_valid: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceHealth {
+    pub uptime_secs: u64,
+    /// Engine mode: `Active`, `Maintenance` or `SafeMode`.
+    pub mode: String,
+    #[serde(default)]
+    pub safe_mode_reason: Option<SafeModeReason>,
+    pub subsystems: Vec<SubsystemStatus>,
+    pub queues: QueueDepths,
+    #[serde(default)]
+    pub last_scan: Option<ScanSummary>,
+    pub baseline: BaselineHealth,
+    /// Latest resource sample; `None` until the first check has run.
+    #[serde(default)]
+    pub resources: Option<ResourceUsage>,
+}
+
+/// The service's own resource use, sampled against `settings.resources`.
+/// Process figures are `None` where the platform doesn't report them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    pub sampled_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub rss_bytes: Option<u64>,
+    #[serde(default)]
+    pub open_fds: Option<u64>,
+    #[serde(default)]
+    pub fd_limit: Option<u64>,
+    /// Estimated OS watch descriptors held by the realtime watcher.
+    pub watches: u64,
+    pub watch_budget: u64,
+    /// Protected paths polled because they didn't fit the watch budget.
+    #[serde(default)]
+    pub polled_paths: Vec<String>,
+    pub baseline_entries: usize,
+    /// Estimated in-memory size of the live baseline.
+    pub baseline_bytes: u64,
+    pub backup_store_bytes: u64,
+    /// Resources at or over their warning threshold.
+    #[serde(default)]
+    pub over_budget: Vec<String>,
+}
+
+/// Violations counted over trailing windows.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ViolationWindows {
+    pub last_hour: u64,
+    pub last_day: u64,
+    pub last_week: u64,
+}
+
+/// Health of one protected path, for `GetPathStats`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathStats {
+    pub path: String,
+    pub exists: bool,
+    /// Files and bytes under the path in the current baseline.
+    pub files: usize,
+    pub total_bytes: u64,
+    #[serde(default)]
+    pub last_scan_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub last_scan_duration_ms: Option<u64>,
+    pub violations: ViolationWindows,
+    /// Restore outcomes over the last week.
+    pub restores_succeeded: u64,
+    pub restores_failed: u64,
+    /// `None` when nothing was restored.
+    #[serde(default)]
+    pub restore_success_rate: Option<f64>,
+}
+
+/// One stage of a self-test scenario: `watcher`, `pipeline`, `engine` or
+/// `restore`. `duration_ms` runs from the simulated change for the first
+/// two stages and from the stage's start for the others.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelfTestStage {
+    pub stage: String,
+    pub passed: bool,
+    pub duration_ms: u64,
+    #[serde(default)]
+    pub detail: Option<String>,
+}
+
+/// A simulated `modify`, `delete` or `rename` of one sandboxed file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelfTestScenario {
+    pub scenario: String,
+    pub file: String,
+    pub passed: bool,
+    pub stages: Vec<SelfTestStage>,
+}
+
+/// Result of `SelfTest`: the protection chain exercised against a sandboxed
+/// copy of `source`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelfTestReport {
+    pub source: String,
+    pub passed: bool,
+    pub duration_ms: u64,
+    pub scenarios: Vec<SelfTestScenario>,
+}
+
+/// A directory inside an estimated path that is usually better left out of
+/// protection, with what it accounts for.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SuggestedExclusion {
+    pub path: String,
+    pub reason: String,
+    pub files: u64,
+    pub total_bytes: u64,
+}
+
+/// Result of `EstimatePath`: what protecting `path` would cost, measured
+/// without touching the baseline or the watcher.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathEstimate {
+    pub path: String,
+    pub files: u64,
+    pub directories: u64,
+    pub total_bytes: u64,
+    /// Entries the walk could not read.
+    pub unreadable: u64,
+    /// Hash throughput measured on a sample of the path's files, and the
+    /// baseline time it implies.
+    pub hash_rate_bytes_per_sec: u64,
+    pub sampled_bytes: u64,
+    pub estimated_baseline_secs: f64,
+    /// Native watches the path needs, next to those already in use and the
+    /// budget. Past the budget some tree falls back to polling.
+    pub watches: u64,
+    pub watches_in_use: u64,
+    pub watch_budget: u64,
+    pub exceeds_watch_budget: bool,
+    /// The protected path that already covers this one, if any.
+    #[serde(default)]
+    pub covered_by: Option<String>,
+    pub suggested_exclusions: Vec<SuggestedExclusion>,
+}
@@ -0,0 +1,252 @@
+//! Named service instances.
+//!
+//! A host can run several guard services side by side — one per customer,
+//! say — each with its own vault, event log, sockets and protected paths. A
+//! process picks its instance with [`set_instance`] (the `--instance` flag)
+//! or the `GUARD_INSTANCE` environment variable, and every path in
+//! [`crate::paths`] then resolves under `<data dir>/instances/<name>`.
+//! Without one, the default instance keeps the original layout.
+//!
+//! Services record themselves in `instances.json` in the base data dir when
+//! they start and stop, so tools can list every instance and find its
+//! status socket. Updates hold a lock on `instances.json.lock`, so services
+//! starting or stopping together don't drop each other's records.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use crate::paths::{base_data_dir, ipc_socket_path, status_socket_path};
+
+pub const INSTANCE_ENV: &str = "GUARD_INSTANCE";
+/// Registry name of the unnamed instance.
+pub const DEFAULT_INSTANCE: &str = "default";
+const REGISTRY_FILE: &str = "instances.json";
+
+static INSTANCE: RwLock<Option<String>> = RwLock::new(None);
+
+/// Instance names become directory and pipe names: 1–32 lowercase ASCII
+/// letters, digits, `-` or `_`.
+pub fn validate_instance_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= 32
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-' || b == b'_');
+    if !valid {
+        return Err(anyhow!(
+            "invalid instance name '{name}': use 1-32 of a-z, 0-9, '-' and '_'"
+        ));
+    }
+    Ok(())
+}
+
+/// Select the instance for this process; `None` (or `default`) selects the
+/// default instance. Takes precedence over `GUARD_INSTANCE`.
+pub fn set_instance(name: Option<&str>) -> Result<()> {
+    let name = name.filter(|n| *n != DEFAULT_INSTANCE);
+    if let Some(name) = name {
+        validate_instance_name(name)?;
+    }
+    *INSTANCE.write().unwrap() = name.map(str::to_string);
+    Ok(())
+}
+
+/// The selected named instance, or `None` for the default one.
+pub fn current_instance() -> Result<Option<String>> {
+    if let Some(name) = INSTANCE.read().unwrap().clone() {
+        return Ok(Some(name));
+    }
+    match std::env::var(INSTANCE_ENV) {
+        Ok(name) if !name.is_empty() && name != DEFAULT_INSTANCE => {
+            validate_instance_name(&name)?;
+            Ok(Some(name))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// One instance as last recorded by its service.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct InstanceRecord {
+    pub name: String,
+    pub data_dir: String,
+    pub ipc_socket: String,
+    pub status_socket: String,
+    pub pid: u32,
+    pub started_at: DateTime<Utc>,
+    pub running: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InstanceRegistry {
+    pub instances: Vec<InstanceRecord>,
+}
+
+impl InstanceRegistry {
+    pub fn load(path: &Path) -> Result<Self> {
+        match std::fs::read(path) {
+            Ok(data) => Ok(serde_json::from_slice(&data)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension(format!("json.{}.tmp", std::process::id()));
+        std::fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Insert `record`, replacing any entry with the same name.
+    pub fn upsert(&mut self, record: InstanceRecord) {
+        self.instances.retain(|r| r.name != record.name);
+        self.instances.push(record);
+        self.instances.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&InstanceRecord> {
+        self.instances.iter().find(|r| r.name == name)
+    }
+}
+
+pub fn registry_path() -> Result<PathBuf> {
+    Ok(base_data_dir()?.join(REGISTRY_FILE))
+}
+
+pub fn load_registry() -> Result<InstanceRegistry> {
+    InstanceRegistry::load(&registry_path()?)
+}
+
+/// Load the registry at `path`, apply `update` and save it if `update`
+/// returns true, all under the registry lock.
+fn update_registry(path: &Path, update: impl FnOnce(&mut InstanceRegistry) -> bool) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let lock = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path.with_extension("json.lock"))?;
+    lock.lock()?;
+    let mut registry = InstanceRegistry::load(path)?;
+    if update(&mut registry) {
+        registry.save(path)?;
+    }
+    Ok(())
+}
+
+/// Record the current instance as running in this process, with `data` as
+/// its data dir.
+pub fn register_running(data: &Path) -> Result<InstanceRecord> {
+    let record = InstanceRecord {
+        name: current_instance()?.unwrap_or_else(|| DEFAULT_INSTANCE.into()),
+        data_dir: data.display().to_string(),
+        ipc_socket: ipc_socket_path()?.display().to_string(),
+        status_socket: status_socket_path()?.display().to_string(),
+        pid: std::process::id(),
+        started_at: Utc::now(),
+        running: true,
+    };
+    update_registry(&registry_path()?, |registry| {
+        registry.upsert(record.clone());
+        true
+    })?;
+    Ok(record)
+}
+
+/// Record the current instance as stopped, if this process registered it.
+pub fn register_stopped() -> Result<()> {
+    let name = current_instance()?.unwrap_or_else(|| DEFAULT_INSTANCE.into());
+    update_registry(&registry_path()?, |registry| {
+        let Some(mut record) = registry.get(&name).cloned() else {
+            return false;
+        };
+        if record.pid != std::process::id() {
+            return false;
+        }
+        record.running = false;
+        registry.upsert(record);
+        true
+    })
+}
+
+/// Where named instances keep their data dirs.
+pub fn instances_root() -> Result<PathBuf> {
+    Ok(base_data_dir()?.join("instances"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn record(name: &str, pid: u32) -> InstanceRecord {
+        InstanceRecord {
+            name: name.into(),
+            data_dir: format!("/data/{name}"),
+            ipc_socket: format!("/data/{name}/guard.ipc"),
+            status_socket: format!("/data/{name}/guard-status.ipc"),
+            pid,
+            started_at: Utc::now(),
+            running: true,
+        }
+    }
+
+    #[test]
+    fn instance_names_are_path_safe() {
+        for ok in ["acme", "customer-2", "eu_west"] {
+            validate_instance_name(ok).unwrap();
+        }
+        for bad in ["", "Acme", "../etc", "a/b", "x".repeat(33).as_str()] {
+            assert!(validate_instance_name(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn registry_upserts_by_name() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("instances.json");
+        let mut registry = InstanceRegistry::load(&path).unwrap();
+        assert!(registry.instances.is_empty());
+        registry.upsert(record("beta", 1));
+        registry.upsert(record("alpha", 2));
+        registry.upsert(record("beta", 3));
+        registry.save(&path).unwrap();
+
+        let loaded = InstanceRegistry::load(&path).unwrap();
+        let names: Vec<_> = loaded.instances.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["alpha", "beta"]);
+        assert_eq!(loaded.get("beta").unwrap().pid, 3);
+    }
+
+    #[test]
+    fn concurrent_updates_keep_every_record() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("instances.json");
+        let threads: Vec<_> = (0..8)
+            .map(|i| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    update_registry(&path, |registry| {
+                        registry.upsert(record(&format!("instance-{i}"), i));
+                        true
+                    })
+                    .unwrap();
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(InstanceRegistry::load(&path).unwrap().instances.len(), 8);
+    }
+}
@@ -1,10 +1,23 @@
 use anyhow::{anyhow, Result};
+use crate::backup_store::BackupStoreStats;
+use crate::command_receipt::SignedCommandReceipt;
+use crate::event_log::EventQuery;
+use crate::evidence::{CustodyManifest, EvidenceItem};
+use crate::exclusion::PathExclusion;
+use crate::health::{PathEstimate, PathStats, SelfTestReport, ServiceHealth};
+use crate::ipc_audit::{
+    is_read_only, redact_request, request_name, ClientIdentity, RateLimiter, RateLimits,
+    RequestAudit, RequestResult,
+};
+use crate::policy::{PolicyBundle, SignedPolicyBundle};
+use crate::sbom::{SbomReport, SignedSbomManifest};
 use crate::settings::GuardSettings;
+use crate::settings_history::{SettingChange, SettingsVersion};
 use hmac::{Hmac, Mac};
 use rand::RngCore;
 use serde::{Deserialize, Serialize};
 use sha2::Sha256;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::sync::Mutex;
@@ -61,14 +74,39 @@ pub struct ResponseEnvelope {
     pub response: IpcResponse,
 }
 
+// As with `IpcResponse`, only the settings variant is large and requests
+// are short-lived.
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "request", content = "data")]
 pub enum IpcRequest {
     Ping,
     GetStatus,
+    /// Per-path file counts, scan timing, violations and restore rates.
+    GetPathStats,
+    /// Walk a path that is not protected yet and estimate what protecting
+    /// it would cost.
+    EstimatePath {
+        path: String,
+    },
     GetSettings,
     UpdateSettings {
         settings: GuardSettings,
+        /// Kept as a note in the settings history, which records the
+        /// authenticated client as the one who made the change.
+        #[serde(default, alias = "changed_by")]
+        note: Option<String>,
+    },
+    /// Recorded settings versions, newest first.
+    GetSettingsHistory {
+        limit: Option<usize>,
+    },
+    /// Restore the settings of an earlier version.
+    RollbackSettings {
+        version: u64,
+        /// As for `UpdateSettings`.
+        #[serde(default, alias = "changed_by")]
+        note: Option<String>,
     },
     EnterSafeMode {
         reason: String,
@@ -93,6 +131,15 @@ pub enum IpcRequest {
         since: Option<String>,  // ISO 8601 timestamp
         limit: Option<usize>,
     },
+    SearchEvents {
+        query: EventQuery,
+    },
+    /// Attach an operator note to event `seq`. The note records the
+    /// authenticated client as its author.
+    AnnotateEvent {
+        seq: u64,
+        note: String,
+    },
     TriggerScan,
     // ── New commands per architecture spec ───────────────────────────────
     MaintenanceEnter {
@@ -101,29 +148,194 @@ pub enum IpcRequest {
     },
     MaintenanceExit {
         rebaseline: bool,
+        /// Also write the change journal to the data directory for review.
+        #[serde(default)]
+        report: bool,
     },
     SetProtectedPaths {
         paths: Vec<String>,
     },
     BaselineCreate,
     BaselineVerify,
+    // ── Dual-control baselines ──────────────────────────────────────────
+    /// Require an operator countersignature on baselines, rotate the
+    /// operator key, or drop the requirement (`public_key` = `None`).
+    SetBaselineOperatorKey {
+        #[serde(default)]
+        public_key: Option<String>,
+        /// Current operator key's signature approving the change; required
+        /// once a key is configured.
+        #[serde(default)]
+        authorization: Option<String>,
+    },
+    /// The message the current operator key signs to approve changing it to
+    /// `public_key`.
+    GetOperatorKeyChangeRequest {
+        #[serde(default)]
+        public_key: Option<String>,
+    },
+    /// The message the operator signs to countersign the current baseline.
+    GetBaselineSigningRequest,
+    CountersignBaseline {
+        /// Hex ed25519 signature by the operator key.
+        signature: String,
+    },
     RestoreNow {
         path: String,
     },
     GetEngineMode,
+    // ── Baseline tags ───────────────────────────────────────────────────
+    TagBaseline {
+        /// Exact file paths or directory prefixes.
+        paths: Vec<String>,
+        #[serde(default)]
+        add: Vec<String>,
+        #[serde(default)]
+        remove: Vec<String>,
+        /// Key/value metadata; an empty value deletes the key.
+        #[serde(default)]
+        metadata: BTreeMap<String, String>,
+    },
+    ScanReport {
+        tag: Option<String>,
+    },
+    RestoreByTag {
+        tag: String,
+    },
+    // ── Organization policy ─────────────────────────────────────────────
+    SetPolicyOrgKey {
+        public_key: String,
+    },
+    ImportPolicy {
+        bundle: SignedPolicyBundle,
+    },
+    GetPolicy,
+    // ── Vendor manifests ────────────────────────────────────────────────
+    /// Bind a vendor-signed manifest to a protected application directory.
+    ImportSbom {
+        root: String,
+        /// base64 ed25519 public key of the vendor.
+        vendor_key: String,
+        manifest: SignedSbomManifest,
+    },
+    /// Check one bound directory, or all of them when `root` is `None`.
+    VerifySbom {
+        #[serde(default)]
+        root: Option<String>,
+    },
+    // ── Temporary exclusions ────────────────────────────────────────────
+    AddExclusion {
+        path: String,
+        duration_secs: u64,
+        reason: String,
+    },
+    /// End an exclusion early; the path's current contents become baseline.
+    RemoveExclusion {
+        path: String,
+    },
+    ListExclusions,
+    /// Undo the write freeze applied on a suspected ransomware burst.
+    ReleaseWriteFreeze,
+    // ── Copy-on-write snapshots ─────────────────────────────────────────
+    ListSnapshots,
+    /// Restore a protected directory from its latest snapshot.
+    SnapshotRestore {
+        path: String,
+    },
+    // ── Backup store ────────────────────────────────────────────────────
+    GetBackupStoreStats,
+    /// Simulate tampering in a sandboxed copy of a protected path (the
+    /// first one by default) and time each stage of the response.
+    SelfTest {
+        #[serde(default)]
+        path: Option<String>,
+    },
+    // ── Connected mode ──────────────────────────────────────────────────
+    /// Signed receipts for remote commands, newest first.
+    GetCommandReceipts {
+        #[serde(default)]
+        limit: Option<usize>,
+    },
+    // ── Quarantine evidence ─────────────────────────────────────────────
+    ListQuarantine,
+    /// Pack quarantined files (all by default) into an encrypted archive
+    /// with a custody manifest signed by this device.
+    ExportQuarantine {
+        destination: String,
+        #[serde(default)]
+        items: Vec<String>,
+        examiner: String,
+        password: String,
+    },
+    /// Verify and unpack an archive exported by another instance.
+    ImportEvidence {
+        archive: String,
+        examiner: String,
+        password: String,
+        /// base64 key the exporting device must have signed with.
+        #[serde(default)]
+        exporter_key: Option<String>,
+    },
+}
+
+impl IpcRequest {
+    /// Whether the request names a filesystem path the service reads,
+    /// writes or restores as root. These are served to local clients only,
+    /// whatever the handler does with them.
+    pub fn requires_local_client(&self) -> bool {
+        matches!(
+            self,
+            IpcRequest::EstimatePath { .. }
+                | IpcRequest::CheckUpdate { .. }
+                | IpcRequest::StageUpdate { .. }
+                | IpcRequest::InstallUpdate { .. }
+                | IpcRequest::RollbackUpdate { .. }
+                | IpcRequest::SetProtectedPaths { .. }
+                | IpcRequest::RestoreNow { .. }
+                | IpcRequest::TagBaseline { .. }
+                | IpcRequest::ImportSbom { .. }
+                | IpcRequest::VerifySbom { root: Some(_) }
+                | IpcRequest::AddExclusion { .. }
+                | IpcRequest::RemoveExclusion { .. }
+                | IpcRequest::SnapshotRestore { .. }
+                | IpcRequest::SelfTest { path: Some(_) }
+                | IpcRequest::ExportQuarantine { .. }
+                | IpcRequest::ImportEvidence { .. }
+        )
+    }
 }
 
+// Responses are built once and serialized straight away; boxing the
+// settings variant would only complicate every match on it.
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "response", content = "data")]
 pub enum IpcResponse {
     Pong,
     Status {
         ok: bool,
+        #[serde(default)]
+        health: Option<ServiceHealth>,
+    },
+    PathStatistics {
+        paths: Vec<PathStats>,
+    },
+    PathEstimate {
+        estimate: PathEstimate,
     },
     Settings {
         settings: GuardSettings,
     },
     SettingsUpdated,
+    SettingsHistory {
+        versions: Vec<SettingsVersion>,
+    },
+    /// `version` is the new version the rollback was recorded as; `None`
+    /// if the settings already matched.
+    SettingsRolledBack {
+        version: Option<u64>,
+        changes: Vec<SettingChange>,
+    },
     SafeModeEntered,
     SafeModeExited,
     UpdateChecked {
@@ -140,6 +352,13 @@ pub enum IpcResponse {
     Events {
         events: Vec<serde_json::Value>,
     },
+    EventPage {
+        events: Vec<serde_json::Value>,
+        next_cursor: Option<String>,
+    },
+    EventAnnotated {
+        note: serde_json::Value,
+    },
     ScanComplete {
         result: serde_json::Value,
     },
@@ -147,15 +366,48 @@ pub enum IpcResponse {
     MaintenanceEntered,
     MaintenanceExited {
         rebaselined: bool,
+        /// Paths the maintenance window changed.
+        #[serde(default)]
+        changes: usize,
+        #[serde(default)]
+        report_path: Option<String>,
     },
     ProtectedPathsUpdated,
     BaselineCreated {
         entries: usize,
+        /// Held until an operator countersigns it (dual control); the
+        /// previous baseline stays in force meanwhile.
+        #[serde(default)]
+        pending_countersign: bool,
     },
     BaselineVerified {
         valid: bool,
         detail: serde_json::Value,
     },
+    BaselineOperatorKeySet {
+        dual_control: bool,
+    },
+    OperatorKeyChangeRequest {
+        /// Hex bytes to sign; `None` when no key is set and none is needed.
+        message: Option<String>,
+    },
+    BaselineSigningRequest {
+        /// Hex bytes to sign.
+        message: String,
+        entries: usize,
+        created_at: chrono::DateTime<chrono::Utc>,
+        countersigned: bool,
+        /// A held rebaseline rather than the baseline in force.
+        #[serde(default)]
+        pending: bool,
+    },
+    BaselineCountersigned {
+        entries: usize,
+        /// False in maintenance mode, where exiting with a rebaseline
+        /// commits it.
+        #[serde(default)]
+        in_force: bool,
+    },
     RestoreResult {
         path: String,
         outcome: String,
@@ -163,6 +415,81 @@ pub enum IpcResponse {
     EngineModeInfo {
         mode: serde_json::Value,
     },
+    BaselineTagged {
+        entries: usize,
+    },
+    RestoreBatch {
+        tag: String,
+        results: Vec<RestoreItem>,
+    },
+    PolicyOrgKeySet,
+    PolicyApplied {
+        policy_id: String,
+        version: u64,
+        locked: Vec<String>,
+    },
+    PolicyInfo {
+        org_key: Option<String>,
+        policy: Option<PolicyBundle>,
+        locked: Vec<String>,
+    },
+    SbomImported {
+        root: String,
+        vendor: String,
+        product: String,
+        version: String,
+        files: usize,
+    },
+    SbomReports {
+        reports: Vec<SbomReport>,
+    },
+    ExclusionAdded {
+        exclusion: PathExclusion,
+    },
+    ExclusionRemoved {
+        path: String,
+        rebaselined: usize,
+    },
+    Exclusions {
+        exclusions: Vec<PathExclusion>,
+    },
+    WriteFreezeReleased {
+        directories: usize,
+    },
+    Snapshots {
+        snapshots: Vec<serde_json::Value>,
+    },
+    SnapshotRestored {
+        report: serde_json::Value,
+    },
+    BackupStoreStats {
+        stats: BackupStoreStats,
+    },
+    SelfTestCompleted {
+        report: SelfTestReport,
+    },
+    CommandReceipts {
+        receipts: Vec<SignedCommandReceipt>,
+        /// base64 device key the receipts are signed with.
+        device_public_key: String,
+    },
+    QuarantineItems {
+        items: Vec<EvidenceItem>,
+    },
+    QuarantineExported {
+        archive: String,
+        manifest: CustodyManifest,
+    },
+    EvidenceImported {
+        directory: String,
+        manifest: CustodyManifest,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreItem {
+    pub path: String,
+    pub outcome: String,
 }
 
 #[derive(Debug, Clone)]
@@ -183,12 +510,13 @@ impl IpcAuthContext {
         }
     }
 
-    fn compute_proof(&self, server_nonce: &str, client_nonce: &str) -> Result<String> {
-        let mut mac = Hmac::<Sha256>::new_from_slice(&self.shared_secret)
-            .map_err(|e| anyhow!("mac init: {e}"))?;
-        mac.update(server_nonce.as_bytes());
-        mac.update(client_nonce.as_bytes());
-        Ok(hex::encode(mac.finalize().into_bytes()))
+    fn compute_proof(
+        &self,
+        server_nonce: &str,
+        client_nonce: &str,
+        channel_binding: Option<&[u8]>,
+    ) -> Result<String> {
+        compute_proof(&self.shared_secret, server_nonce, client_nonce, channel_binding)
     }
 
     pub async fn register_session(&self, session_id: String) {
@@ -209,9 +537,28 @@ impl IpcAuthContext {
     }
 }
 
+/// HMAC proof over both nonces. Remote (TLS) connections also mix in the
+/// channel binding so a proof cannot be relayed onto another TLS session.
+pub(crate) fn compute_proof(
+    secret: &[u8],
+    server_nonce: &str,
+    client_nonce: &str,
+    channel_binding: Option<&[u8]>,
+) -> Result<String> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret).map_err(|e| anyhow!("mac init: {e}"))?;
+    mac.update(server_nonce.as_bytes());
+    mac.update(client_nonce.as_bytes());
+    if let Some(binding) = channel_binding {
+        mac.update(binding);
+    }
+    Ok(hex::encode(mac.finalize().into_bytes()))
+}
+
 pub struct IpcServer {
     auth: Arc<IpcAuthContext>,
     socket_path: std::path::PathBuf,
+    limiter: Arc<RateLimiter>,
 }
 
 impl IpcServer {
@@ -219,9 +566,24 @@ impl IpcServer {
         Self {
             auth: Arc::new(IpcAuthContext::new(auth_secret)),
             socket_path,
+            limiter: Arc::new(RateLimiter::default()),
         }
     }
 
+    pub(crate) fn auth(&self) -> Arc<IpcAuthContext> {
+        self.auth.clone()
+    }
+
+    pub(crate) fn limiter(&self) -> Arc<RateLimiter> {
+        self.limiter.clone()
+    }
+
+    /// Per-client request budgets, for handlers that don't supply their own
+    /// through [`IpcHandler::rate_limits`].
+    pub fn set_rate_limits(&self, limits: RateLimits) {
+        self.limiter.set_limits(limits);
+    }
+
     #[cfg(unix)]
     pub async fn start(self: Arc<Self>, handler: Arc<dyn IpcHandler + Send + Sync>) -> Result<()> {
         use tokio::net::UnixListener;
@@ -231,10 +593,16 @@ impl IpcServer {
         let listener = UnixListener::bind(&self.socket_path)?;
         loop {
             let (stream, _addr) = listener.accept().await?;
+            let pid = stream
+                .peer_cred()
+                .ok()
+                .and_then(|cred| cred.pid())
+                .map(|pid| pid as u32);
             let auth = self.auth.clone();
+            let limiter = self.limiter.clone();
             let handler = handler.clone();
             tokio::spawn(async move {
-                if let Err(e) = handle_connection(stream, auth, handler).await {
+                if let Err(e) = handle_connection(stream, pid, auth, limiter, handler).await {
                     eprintln!("ipc connection error: {e}");
                 }
             });
@@ -250,9 +618,10 @@ impl IpcServer {
                 .create(&self.socket_path)?;
             server.connect().await?;
             let auth = self.auth.clone();
+            let limiter = self.limiter.clone();
             let handler = handler.clone();
             tokio::spawn(async move {
-                if let Err(e) = handle_connection(server, auth, handler).await {
+                if let Err(e) = handle_connection(server, None, auth, limiter, handler).await {
                     eprintln!("ipc connection error: {e}");
                 }
             });
@@ -262,14 +631,52 @@ impl IpcServer {
 
 #[async_trait::async_trait]
 pub trait IpcHandler {
-    async fn handle(&self, req: IpcRequest) -> Result<IpcResponse>;
+    /// Answer `req` from the authenticated `client`.
+    async fn handle(&self, req: IpcRequest, client: &ClientIdentity) -> Result<IpcResponse>;
     async fn enter_safe_mode(&self, reason: String) -> Result<IpcResponse>;
     async fn exit_safe_mode(&self, password: String) -> Result<IpcResponse>;
+
+    /// Audit hook for remote (TCP) connections. Local socket connections are
+    /// not reported.
+    async fn audit_remote(&self, _event: RemoteConnectionEvent) {}
+
+    /// Audit hook called for every request after it is answered or refused,
+    /// on any transport.
+    async fn audit_request(&self, _audit: RequestAudit) {}
+
+    /// Current per-client budgets, consulted before every request so
+    /// settings changes apply immediately. `None` keeps the server's own.
+    fn rate_limits(&self) -> Option<RateLimits> {
+        None
+    }
+}
+
+/// Lifecycle of a remote IPC connection, reported through
+/// [`IpcHandler::audit_remote`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "stage", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RemoteConnectionEvent {
+    Authenticated {
+        peer: String,
+        /// blake3 fingerprint of the client certificate, if one was presented.
+        client_cert: Option<String>,
+    },
+    Rejected {
+        peer: String,
+        reason: String,
+    },
+    Closed {
+        peer: String,
+        requests: u64,
+        error: Option<String>,
+    },
 }
 
 async fn handle_connection<S>(
     stream: S,
+    pid: Option<u32>,
     auth: Arc<IpcAuthContext>,
+    limiter: Arc<RateLimiter>,
     handler: Arc<dyn IpcHandler + Send + Sync>,
 ) -> Result<()>
 where
@@ -277,7 +684,34 @@ where
 {
     let (read_half, mut writer) = tokio::io::split(stream);
     let mut reader = BufReader::new(read_half);
+    let (session_id, client_id) = authenticate(&mut reader, &mut writer, &auth, None).await?;
+    let client = ClientIdentity::local(client_id, pid);
+    let mut served = 0;
+    serve_requests(
+        &mut reader,
+        &mut writer,
+        &auth,
+        &limiter,
+        &handler,
+        &client,
+        &session_id,
+        &mut served,
+    )
+    .await
+}
 
+/// Run the hello / challenge / proof handshake and return the session id and
+/// the client id from the hello.
+pub(crate) async fn authenticate<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    auth: &IpcAuthContext,
+    channel_binding: Option<&[u8]>,
+) -> Result<(String, String)>
+where
+    R: tokio::io::AsyncBufRead + Unpin,
+    W: tokio::io::AsyncWrite + Unpin,
+{
     // Expect ClientHello
     let mut line = String::new();
     let n = reader.read_line(&mut line).await?;
@@ -335,7 +769,7 @@ where
     if auth_msg.session_id != session_id {
         return Err(anyhow!("session id mismatch"));
     }
-    let expected = auth.compute_proof(&server_nonce, &auth_msg.client_nonce)?;
+    let expected = auth.compute_proof(&server_nonce, &auth_msg.client_nonce, channel_binding)?;
     if expected != auth_msg.proof {
         return Err(anyhow!("invalid proof"));
     }
@@ -349,8 +783,28 @@ where
         .await?;
     writer.write_all(b"\n").await?;
     writer.flush().await?;
+    Ok((session_id, hello.client_id))
+}
 
-    // Process requests
+/// Serve requests for an authenticated session until EOF, counting each
+/// answered request in `served`. Requests over `client`'s budget are refused
+/// with an error envelope; the connection stays open.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn serve_requests<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    auth: &IpcAuthContext,
+    limiter: &RateLimiter,
+    handler: &Arc<dyn IpcHandler + Send + Sync>,
+    client: &ClientIdentity,
+    session_id: &str,
+    served: &mut u64,
+) -> Result<()>
+where
+    R: tokio::io::AsyncBufRead + Unpin,
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    let mut line = String::new();
     loop {
         line.clear();
         let n = reader.read_line(&mut line).await?;
@@ -365,17 +819,57 @@ where
         if req_env.session_id != session_id {
             return Err(anyhow!("session mismatch"));
         }
-        auth.verify_and_update_nonce(&session_id, req_env.nonce)
+        auth.verify_and_update_nonce(session_id, req_env.nonce)
             .await?;
-        let resp = match req_env.request {
-            IpcRequest::Ping => IpcResponse::Pong,
-            IpcRequest::GetStatus => handler.handle(IpcRequest::GetStatus).await?,
-            IpcRequest::EnterSafeMode { reason } => handler.enter_safe_mode(reason).await?,
-            IpcRequest::ExitSafeMode { password } => handler.exit_safe_mode(password).await?,
-            other => handler.handle(other).await?,
+        let started = std::time::Instant::now();
+        let audit = |result: RequestResult| RequestAudit {
+            client: client.clone(),
+            session_id: session_id.to_string(),
+            request: request_name(&req_env.request),
+            params: redact_request(&req_env.request),
+            read_only: is_read_only(&req_env.request),
+            result,
+            duration_ms: started.elapsed().as_millis() as u64,
         };
+        if let Some(limits) = handler.rate_limits() {
+            limiter.set_limits(limits);
+        }
+        if let Err(wait) = limiter.check(&client.key(), &req_env.request) {
+            let retry_after_ms = wait.as_millis() as u64;
+            handler
+                .audit_request(audit(RequestResult::RateLimited { retry_after_ms }))
+                .await;
+            let refusal = IpcEnvelope::Error {
+                message: format!("rate limited: retry in {retry_after_ms}ms"),
+            };
+            writer
+                .write_all(serde_json::to_string(&refusal)?.as_bytes())
+                .await?;
+            writer.write_all(b"\n").await?;
+            writer.flush().await?;
+            continue;
+        }
+        let result = match req_env.request.clone() {
+            request if request.requires_local_client() && client.transport != "local" => Err(
+                anyhow!("{} is only served to local clients", request_name(&request)),
+            ),
+            IpcRequest::Ping => Ok(IpcResponse::Pong),
+            IpcRequest::GetStatus => handler.handle(IpcRequest::GetStatus, client).await,
+            IpcRequest::EnterSafeMode { reason } => handler.enter_safe_mode(reason).await,
+            IpcRequest::ExitSafeMode { password } => handler.exit_safe_mode(password).await,
+            other => handler.handle(other, client).await,
+        };
+        handler
+            .audit_request(audit(match &result {
+                Ok(_) => RequestResult::Ok,
+                Err(e) => RequestResult::Error {
+                    message: e.to_string(),
+                },
+            }))
+            .await;
+        let resp = result?;
         let response_env = IpcEnvelope::Response(ResponseEnvelope {
-            session_id: session_id.clone(),
+            session_id: session_id.to_string(),
             nonce: req_env.nonce,
             response: resp,
         });
@@ -384,6 +878,7 @@ where
             .await?;
         writer.write_all(b"\n").await?;
         writer.flush().await?;
+        *served += 1;
     }
     Ok(())
 }
@@ -395,11 +890,21 @@ mod tests {
     #[tokio::test]
     async fn proof_changes_with_nonce() {
         let ctx = IpcAuthContext::new(vec![1, 2, 3, 4]);
-        let p1 = ctx.compute_proof("abc", "def").unwrap();
-        let p2 = ctx.compute_proof("abc", "xyz").unwrap();
+        let p1 = ctx.compute_proof("abc", "def", None).unwrap();
+        let p2 = ctx.compute_proof("abc", "xyz", None).unwrap();
         assert_ne!(p1, p2);
     }
 
+    #[tokio::test]
+    async fn proof_is_bound_to_channel() {
+        let ctx = IpcAuthContext::new(vec![1, 2, 3, 4]);
+        let plain = ctx.compute_proof("abc", "def", None).unwrap();
+        let a = ctx.compute_proof("abc", "def", Some(b"session-a")).unwrap();
+        let b = ctx.compute_proof("abc", "def", Some(b"session-b")).unwrap();
+        assert_ne!(plain, a);
+        assert_ne!(a, b);
+    }
+
     #[tokio::test]
     async fn nonce_replay_rejected() {
         let ctx = IpcAuthContext::new(vec![1, 2, 3, 4]);
@@ -407,4 +912,13 @@ mod tests {
         ctx.verify_and_update_nonce("s1", 1).await.unwrap();
         assert!(ctx.verify_and_update_nonce("s1", 1).await.is_err());
     }
+
+    #[test]
+    fn status_without_health_still_parses() {
+        let legacy = r#"{"response":"Status","data":{"ok":true}}"#;
+        match serde_json::from_str::<IpcResponse>(legacy).unwrap() {
+            IpcResponse::Status { ok, health } => assert!(ok && health.is_none()),
+            other => panic!("unexpected response {other:?}"),
+        }
+    }
 }
@@ -0,0 +1,369 @@
+//! Auditing and rate limiting of IPC requests.
+//!
+//! Every request served is reported to [`IpcHandler::audit_request`] with the
+//! identity of the client that sent it, its redacted parameters and its
+//! result. Clients are rate limited per identity across connections, since
+//! the UI and CLI open a fresh connection for each request. Expensive
+//! requests (scans, baseline builds) draw from a second, smaller budget so a
+//! misbehaving client can't keep the service hashing.
+//!
+//! Served requests go to the event log at the severity
+//! [`RequestAudit::log_severity`] gives. The one exclusion is read-only
+//! requests from local clients, which the UI polls continuously; those stay
+//! in the trace log. Reads by remote clients are logged like any other
+//! request, since they disclose device state off the machine.
+//!
+//! [`IpcHandler::audit_request`]: crate::ipc::IpcHandler::audit_request
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::event_log::EventSeverity;
+use crate::ipc::IpcRequest;
+
+/// Replaced in audited parameters, at any depth.
+const REDACTED_KEYS: &[&str] = &["password", "secret", "token", "private_key"];
+
+/// Who sent a request.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ClientIdentity {
+    pub client_id: String,
+    /// `local` for the socket / named pipe, `remote` for TLS.
+    pub transport: String,
+    /// Remote peer address.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peer: Option<String>,
+    /// Local peer process, where the platform reports it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    /// blake3 fingerprint of the remote client certificate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_cert: Option<String>,
+}
+
+impl ClientIdentity {
+    pub fn local(client_id: String, pid: Option<u32>) -> Self {
+        Self {
+            client_id,
+            transport: "local".into(),
+            pid,
+            ..Default::default()
+        }
+    }
+
+    pub fn remote(client_id: String, peer: String, client_cert: Option<String>) -> Self {
+        Self {
+            client_id,
+            transport: "remote".into(),
+            peer: Some(peer),
+            client_cert,
+            ..Default::default()
+        }
+    }
+
+    /// Who, for records such as the settings history: transport and client
+    /// id, then the peer and certificate or the local pid.
+    pub fn label(&self) -> String {
+        let mut label = format!("{}:{}", self.transport, self.client_id);
+        if let Some(peer) = &self.peer {
+            label.push_str(&format!("@{peer}"));
+        }
+        if let Some(cert) = &self.client_cert {
+            label.push_str(&format!(" cert:{cert}"));
+        } else if let Some(pid) = self.pid {
+            label.push_str(&format!(" pid:{pid}"));
+        }
+        label
+    }
+
+    /// Rate-limit key: the most specific identity available. Remote peers
+    /// are keyed by host, not port, so reconnecting doesn't reset the budget.
+    pub fn key(&self) -> String {
+        if let Some(cert) = &self.client_cert {
+            return format!("cert:{cert}");
+        }
+        if let Some(peer) = &self.peer {
+            let host = peer
+                .rsplit_once(':')
+                .map_or(peer.as_str(), |(host, _)| host);
+            return format!("peer:{host}");
+        }
+        match self.pid {
+            Some(pid) => format!("{}:{}:{pid}", self.transport, self.client_id),
+            None => format!("{}:{}", self.transport, self.client_id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum RequestResult {
+    Ok,
+    Error { message: String },
+    RateLimited { retry_after_ms: u64 },
+}
+
+/// One served (or refused) request, as reported to the handler.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestAudit {
+    pub client: ClientIdentity,
+    pub session_id: String,
+    pub request: String,
+    /// Request parameters with secrets redacted.
+    pub params: Value,
+    /// See [`is_read_only`].
+    pub read_only: bool,
+    pub result: RequestResult,
+    pub duration_ms: u64,
+}
+
+impl RequestAudit {
+    /// Severity to log this request at, or `None` for a local read that
+    /// only belongs in the trace log.
+    pub fn log_severity(&self) -> Option<EventSeverity> {
+        match self.result {
+            RequestResult::RateLimited { .. } => Some(EventSeverity::Warn),
+            _ if self.read_only && self.client.transport == "local" => None,
+            RequestResult::Error { .. } => Some(EventSeverity::Warn),
+            RequestResult::Ok => Some(EventSeverity::Info),
+        }
+    }
+}
+
+/// Name of the request variant, e.g. `TriggerScan`.
+pub fn request_name(request: &IpcRequest) -> String {
+    serde_json::to_value(request)
+        .ok()
+        .and_then(|v| v.get("request").and_then(Value::as_str).map(str::to_string))
+        .unwrap_or_else(|| "Unknown".into())
+}
+
+/// Parameters of `request` with every secret-bearing field replaced.
+pub fn redact_request(request: &IpcRequest) -> Value {
+    let mut data = serde_json::to_value(request)
+        .ok()
+        .and_then(|mut v| v.get_mut("data").map(Value::take))
+        .unwrap_or(Value::Null);
+    redact(&mut data);
+    data
+}
+
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, v) in map.iter_mut() {
+                let lower = key.to_ascii_lowercase();
+                if REDACTED_KEYS.iter().any(|k| lower.contains(k)) && !v.is_null() {
+                    *v = Value::String("[redacted]".into());
+                } else {
+                    redact(v);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+/// Whether `request` only reads state. Read-only requests are polled by the
+/// UI and are not written to the event log when they come from a local
+/// client. `EstimatePath` is left out: it walks and reads any path the
+/// caller names, so every use is logged.
+pub fn is_read_only(request: &IpcRequest) -> bool {
+    matches!(
+        request,
+        IpcRequest::Ping
+            | IpcRequest::GetStatus
+            | IpcRequest::GetPathStats
+            | IpcRequest::GetSettings
+            | IpcRequest::GetSettingsHistory { .. }
+            | IpcRequest::GetEvents { .. }
+            | IpcRequest::SearchEvents { .. }
+            | IpcRequest::GetEngineMode
+            | IpcRequest::GetBaselineSigningRequest
+            | IpcRequest::GetOperatorKeyChangeRequest { .. }
+            | IpcRequest::GetPolicy
+            | IpcRequest::ListExclusions
+            | IpcRequest::ListSnapshots
+            | IpcRequest::GetBackupStoreStats
+            | IpcRequest::GetCommandReceipts { .. }
+            | IpcRequest::ListQuarantine
+    )
+}
+
+/// Requests that walk and hash the protected paths.
+fn is_expensive(request: &IpcRequest) -> bool {
+    matches!(
+        request,
+        IpcRequest::TriggerScan
+            | IpcRequest::ScanReport { .. }
+            | IpcRequest::BaselineCreate
+            | IpcRequest::BaselineVerify
+            | IpcRequest::VerifySbom { .. }
+            | IpcRequest::SelfTest { .. }
+            | IpcRequest::EstimatePath { .. }
+    )
+}
+
+/// Per-client budgets; `0` means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimits {
+    pub requests_per_minute: u32,
+    pub scans_per_minute: u32,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    /// Take a token from a bucket holding up to `per_minute`, refilled
+    /// continuously. Returns the wait until one is available otherwise.
+    fn take(&mut self, per_minute: u32, now: Instant) -> Result<(), Duration> {
+        let capacity = per_minute as f64;
+        let rate = capacity / 60.0;
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(capacity);
+        self.updated = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / rate))
+        }
+    }
+}
+
+/// Token buckets per client key, shared by every connection.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    limits: Mutex<RateLimits>,
+    buckets: Mutex<HashMap<(String, bool), Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: RateLimits) -> Self {
+        Self {
+            limits: Mutex::new(limits),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn set_limits(&self, limits: RateLimits) {
+        *self.limits.lock() = limits;
+    }
+
+    /// Charge `request` to `client`; on refusal returns how long to wait.
+    pub fn check(&self, client: &str, request: &IpcRequest) -> Result<(), Duration> {
+        self.check_at(client, request, Instant::now())
+    }
+
+    fn check_at(&self, client: &str, request: &IpcRequest, now: Instant) -> Result<(), Duration> {
+        let limits = *self.limits.lock();
+        let mut buckets = self.buckets.lock();
+        let mut charge = |expensive: bool, per_minute: u32| {
+            if per_minute == 0 {
+                return Ok(());
+            }
+            buckets
+                .entry((client.to_string(), expensive))
+                .or_insert(Bucket {
+                    tokens: per_minute as f64,
+                    updated: now,
+                })
+                .take(per_minute, now)
+        };
+        if is_expensive(request) {
+            charge(true, limits.scans_per_minute)?;
+        }
+        charge(false, limits.requests_per_minute)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secrets_are_redacted() {
+        let params = redact_request(&IpcRequest::ExitSafeMode {
+            password: "hunter2".into(),
+        });
+        assert_eq!(params, serde_json::json!({"password": "[redacted]"}));
+        // Public material is kept for the audit trail.
+        let params = redact_request(&IpcRequest::SetBaselineOperatorKey {
+            public_key: Some("pk".into()),
+            authorization: Some("sig".into()),
+        });
+        assert_eq!(params["public_key"], "pk");
+        assert_eq!(params["authorization"], "sig");
+        assert_eq!(request_name(&IpcRequest::TriggerScan), "TriggerScan");
+    }
+
+    #[test]
+    fn path_estimates_are_logged() {
+        assert!(is_read_only(&IpcRequest::GetPathStats));
+        assert!(!is_read_only(&IpcRequest::EstimatePath {
+            path: "/etc".into()
+        }));
+    }
+
+    #[test]
+    fn remote_reads_are_logged() {
+        let audit = |client: ClientIdentity, result: RequestResult| RequestAudit {
+            client,
+            session_id: "s".into(),
+            request: "GetStatus".into(),
+            params: Value::Null,
+            read_only: true,
+            result,
+            duration_ms: 0,
+        };
+        let local = ClientIdentity::local("ui".into(), Some(42));
+        let remote = ClientIdentity::remote("ops".into(), "10.0.0.5:4431".into(), None);
+
+        assert_eq!(audit(local.clone(), RequestResult::Ok).log_severity(), None);
+        assert_eq!(
+            audit(local, RequestResult::RateLimited { retry_after_ms: 1 }).log_severity(),
+            Some(EventSeverity::Warn)
+        );
+        assert_eq!(
+            audit(remote.clone(), RequestResult::Ok).log_severity(),
+            Some(EventSeverity::Info)
+        );
+        assert_eq!(
+            audit(remote, RequestResult::Error { message: "no".into() }).log_severity(),
+            Some(EventSeverity::Warn)
+        );
+    }
+
+    #[test]
+    fn scans_have_their_own_budget() {
+        let limiter = RateLimiter::new(RateLimits {
+            requests_per_minute: 60,
+            scans_per_minute: 2,
+        });
+        let t0 = Instant::now();
+        assert!(limiter.check_at("ui", &IpcRequest::TriggerScan, t0).is_ok());
+        assert!(limiter.check_at("ui", &IpcRequest::TriggerScan, t0).is_ok());
+        let wait = limiter
+            .check_at("ui", &IpcRequest::TriggerScan, t0)
+            .unwrap_err();
+        assert!(wait <= Duration::from_secs(30));
+        // Cheap requests and other clients are unaffected.
+        assert!(limiter.check_at("ui", &IpcRequest::GetStatus, t0).is_ok());
+        assert!(limiter
+            .check_at("other", &IpcRequest::TriggerScan, t0)
+            .is_ok());
+        // The budget refills over time.
+        let later = t0 + Duration::from_secs(30);
+        assert!(limiter
+            .check_at("ui", &IpcRequest::TriggerScan, later)
+            .is_ok());
+    }
+}
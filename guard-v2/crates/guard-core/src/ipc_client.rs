@@ -1,9 +1,12 @@
-use crate::ipc::{AuthOk, ClientAuth, ClientHello, IpcEnvelope, IpcRequest, IpcResponse, RequestEnvelope, ServerChallenge, IPC_PROTOCOL_VERSION};
+use crate::ipc::{compute_proof, AuthOk, ClientAuth, ClientHello, IpcEnvelope, IpcRequest, IpcResponse, RequestEnvelope, ServerChallenge, IPC_PROTOCOL_VERSION};
+use crate::ipc_tls::{load_certs, load_private_key, load_roots, EXPORTER_LABEL};
 use anyhow::{anyhow, Result};
-use hmac::{Hmac, Mac};
 use rand::RngCore;
-use sha2::Sha256;
+use std::path::PathBuf;
+use std::sync::Arc;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
 
 #[cfg(unix)]
 use tokio::net::UnixStream;
@@ -11,14 +14,6 @@ use tokio::net::UnixStream;
 #[cfg(windows)]
 use tokio::net::windows::named_pipe::ClientOptions;
 
-fn compute_proof(secret: &[u8], server_nonce: &str, client_nonce: &str) -> Result<String> {
-    let mut mac = Hmac::<Sha256>::new_from_slice(secret)
-        .map_err(|e| anyhow!("mac init: {e}"))?;
-    mac.update(server_nonce.as_bytes());
-    mac.update(client_nonce.as_bytes());
-    Ok(hex::encode(mac.finalize().into_bytes()))
-}
-
 pub async fn send_request(
     socket_path: std::path::PathBuf,
     secret: &[u8],
@@ -32,6 +27,63 @@ pub async fn send_request(
         .open(socket_path)
         .map_err(|e| anyhow!("ipc connect: {e}"))?;
 
+    exchange(stream, secret, None, request).await
+}
+
+/// Connection parameters for a remote (TLS over TCP) guard service.
+#[derive(Debug, Clone)]
+pub struct RemoteClientConfig {
+    /// `host:port` of the remote listener.
+    pub addr: String,
+    /// Name the server certificate must be valid for.
+    pub server_name: String,
+    /// CA that signed the server certificate.
+    pub ca_path: PathBuf,
+    /// Client certificate and key, required when the server has a client CA.
+    pub client_cert_path: Option<PathBuf>,
+    pub client_key_path: Option<PathBuf>,
+}
+
+pub async fn send_remote_request(
+    config: &RemoteClientConfig,
+    secret: &[u8],
+    request: IpcRequest,
+) -> Result<IpcResponse> {
+    let builder = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(load_roots(&config.ca_path)?);
+    let tls_config = match (&config.client_cert_path, &config.client_key_path) {
+        (Some(cert), Some(key)) => builder
+            .with_client_auth_cert(load_certs(cert)?, load_private_key(key)?)
+            .map_err(|e| anyhow!("client certificate: {e}"))?,
+        (None, None) => builder.with_no_client_auth(),
+        _ => return Err(anyhow!("client certificate and key must be given together")),
+    };
+    let server_name = rustls::ServerName::try_from(config.server_name.as_str())
+        .map_err(|e| anyhow!("invalid server name: {e}"))?;
+
+    let tcp = TcpStream::connect(&config.addr).await?;
+    let stream = TlsConnector::from(Arc::new(tls_config))
+        .connect(server_name, tcp)
+        .await?;
+    let binding = stream
+        .get_ref()
+        .1
+        .export_keying_material([0u8; 32], EXPORTER_LABEL, None)
+        .map_err(|e| anyhow!("channel binding: {e}"))?;
+
+    exchange(stream, secret, Some(&binding), request).await
+}
+
+async fn exchange<S>(
+    stream: S,
+    secret: &[u8],
+    channel_binding: Option<&[u8]>,
+    request: IpcRequest,
+) -> Result<IpcResponse>
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
     let (read_half, mut writer) = tokio::io::split(stream);
     let mut reader = BufReader::new(read_half);
 
@@ -44,6 +96,7 @@ pub async fn send_request(
         .write_all(serde_json::to_string(&hello)?.as_bytes())
         .await?;
     writer.write_all(b"\n").await?;
+    writer.flush().await?;
 
     let mut line = String::new();
     reader.read_line(&mut line).await?;
@@ -60,7 +113,7 @@ pub async fn send_request(
     let mut nonce_bytes = [0u8; 16];
     rand::rngs::OsRng.fill_bytes(&mut nonce_bytes);
     let client_nonce = hex::encode(nonce_bytes);
-    let proof = compute_proof(secret, &server_nonce, &client_nonce)?;
+    let proof = compute_proof(secret, &server_nonce, &client_nonce, channel_binding)?;
 
     let auth = IpcEnvelope::ClientAuth(ClientAuth {
         session_id: session_id.clone(),
@@ -71,6 +124,7 @@ pub async fn send_request(
         .write_all(serde_json::to_string(&auth)?.as_bytes())
         .await?;
     writer.write_all(b"\n").await?;
+    writer.flush().await?;
 
     line.clear();
     reader.read_line(&mut line).await?;
@@ -90,6 +144,7 @@ pub async fn send_request(
         .write_all(serde_json::to_string(&request_envelope)?.as_bytes())
         .await?;
     writer.write_all(b"\n").await?;
+    writer.flush().await?;
 
     line.clear();
     reader.read_line(&mut line).await?;
@@ -0,0 +1,193 @@
+//! TLS-wrapped TCP transport for remote administration.
+//!
+//! The remote listener runs the same hello / challenge / proof handshake as
+//! the local socket, but the HMAC proof additionally covers a TLS exporter
+//! secret, so it only validates on the TLS session it was computed for. When
+//! a client CA is configured, peers must also present a certificate chained
+//! to it.
+
+use crate::ipc::{authenticate, serve_requests, IpcHandler, IpcServer, RemoteConnectionEvent};
+use crate::ipc_audit::ClientIdentity;
+use crate::settings::RemoteIpcSettings;
+use anyhow::{anyhow, Context, Result};
+use rustls::server::{AllowAnyAuthenticatedClient, NoClientAuth};
+use rustls::{Certificate, PrivateKey, RootCertStore, ServerConfig};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::BufReader;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::TlsAcceptor;
+
+/// Label for the RFC 5705 exporter secret used as channel binding.
+pub(crate) const EXPORTER_LABEL: &[u8] = b"EXPORTER-darklock-guard-ipc";
+
+/// TLS handshake plus IPC authentication must finish within this window.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub fn load_certs(path: &Path) -> Result<Vec<Certificate>> {
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("open certificate {}", path.display()))?;
+    let certs = rustls_pemfile::certs(&mut std::io::BufReader::new(file))?;
+    if certs.is_empty() {
+        return Err(anyhow!("no certificates in {}", path.display()));
+    }
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+/// Load the first PKCS#8, SEC1 or PKCS#1 private key found in `path`.
+pub fn load_private_key(path: &Path) -> Result<PrivateKey> {
+    let pem = std::fs::read(path).with_context(|| format!("open key {}", path.display()))?;
+    type Parser = fn(&mut dyn std::io::BufRead) -> std::io::Result<Vec<Vec<u8>>>;
+    let parsers: [Parser; 3] = [
+        rustls_pemfile::pkcs8_private_keys,
+        rustls_pemfile::ec_private_keys,
+        rustls_pemfile::rsa_private_keys,
+    ];
+    for parse in parsers {
+        if let Some(key) = parse(&mut pem.as_slice())?.into_iter().next() {
+            return Ok(PrivateKey(key));
+        }
+    }
+    Err(anyhow!("no private key in {}", path.display()))
+}
+
+pub fn load_roots(path: &Path) -> Result<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+    for cert in load_certs(path)? {
+        roots
+            .add(&cert)
+            .map_err(|e| anyhow!("invalid CA certificate {}: {e}", path.display()))?;
+    }
+    Ok(roots)
+}
+
+pub fn server_config(settings: &RemoteIpcSettings) -> Result<Arc<ServerConfig>> {
+    let cert_path = settings
+        .cert_path
+        .as_deref()
+        .ok_or_else(|| anyhow!("remote IPC requires cert_path"))?;
+    let key_path = settings
+        .key_path
+        .as_deref()
+        .ok_or_else(|| anyhow!("remote IPC requires key_path"))?;
+    let verifier = match settings.client_ca_path.as_deref() {
+        Some(ca) => AllowAnyAuthenticatedClient::new(load_roots(Path::new(ca))?).boxed(),
+        None => NoClientAuth::boxed(),
+    };
+    let config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(
+            load_certs(Path::new(cert_path))?,
+            load_private_key(Path::new(key_path))?,
+        )
+        .map_err(|e| anyhow!("remote IPC certificate: {e}"))?;
+    Ok(Arc::new(config))
+}
+
+impl IpcServer {
+    /// Accept remote administration connections on `settings.bind_addr`.
+    pub async fn start_remote(
+        self: Arc<Self>,
+        settings: RemoteIpcSettings,
+        handler: Arc<dyn IpcHandler + Send + Sync>,
+    ) -> Result<()> {
+        let acceptor = TlsAcceptor::from(server_config(&settings)?);
+        let listener = TcpListener::bind(&settings.bind_addr)
+            .await
+            .with_context(|| format!("bind remote IPC on {}", settings.bind_addr))?;
+        loop {
+            let (tcp, peer) = listener.accept().await?;
+            let acceptor = acceptor.clone();
+            let server = self.clone();
+            let handler = handler.clone();
+            tokio::spawn(async move {
+                server.handle_remote(acceptor, tcp, peer, handler).await;
+            });
+        }
+    }
+
+    async fn handle_remote(
+        &self,
+        acceptor: TlsAcceptor,
+        tcp: TcpStream,
+        peer: SocketAddr,
+        handler: Arc<dyn IpcHandler + Send + Sync>,
+    ) {
+        let peer = peer.to_string();
+        let auth = self.auth();
+
+        let handshake = async {
+            let tls = acceptor
+                .accept(tcp)
+                .await
+                .map_err(|e| anyhow!("tls handshake: {e}"))?;
+            let conn = tls.get_ref().1;
+            let binding = conn
+                .export_keying_material([0u8; 32], EXPORTER_LABEL, None)
+                .map_err(|e| anyhow!("channel binding: {e}"))?;
+            let client_cert = conn
+                .peer_certificates()
+                .and_then(|chain| chain.first())
+                .map(|cert| blake3::hash(&cert.0).to_hex().to_string());
+            let (read_half, mut writer) = tokio::io::split(tls);
+            let mut reader = BufReader::new(read_half);
+            let (session_id, client_id) =
+                authenticate(&mut reader, &mut writer, &auth, Some(&binding)).await?;
+            Ok::<_, anyhow::Error>((reader, writer, session_id, client_id, client_cert))
+        };
+
+        let (mut reader, mut writer, session_id, client_id, client_cert) =
+            match tokio::time::timeout(HANDSHAKE_TIMEOUT, handshake).await {
+                Ok(Ok(parts)) => parts,
+                Ok(Err(e)) => {
+                    handler
+                        .audit_remote(RemoteConnectionEvent::Rejected {
+                            peer,
+                            reason: e.to_string(),
+                        })
+                        .await;
+                    return;
+                }
+                Err(_) => {
+                    handler
+                        .audit_remote(RemoteConnectionEvent::Rejected {
+                            peer,
+                            reason: "handshake timed out".to_string(),
+                        })
+                        .await;
+                    return;
+                }
+            };
+
+        handler
+            .audit_remote(RemoteConnectionEvent::Authenticated {
+                peer: peer.clone(),
+                client_cert: client_cert.clone(),
+            })
+            .await;
+        let client = ClientIdentity::remote(client_id, peer.clone(), client_cert);
+        let limiter = self.limiter();
+        let mut requests = 0;
+        let result = serve_requests(
+            &mut reader,
+            &mut writer,
+            &auth,
+            &limiter,
+            &handler,
+            &client,
+            &session_id,
+            &mut requests,
+        )
+        .await;
+        handler
+            .audit_remote(RemoteConnectionEvent::Closed {
+                peer,
+                requests,
+                error: result.err().map(|e| e.to_string()),
+            })
+            .await;
+    }
+}
@@ -1,25 +1,53 @@
 pub mod crypto;
 pub mod device_state;
 pub mod event_log;
+pub mod event_replay;
+pub mod events;
+pub mod evidence;
+pub mod exclusion;
+pub mod health;
+pub mod instances;
 pub mod backup_store;
+pub mod command_receipt;
 pub mod ipc;
+pub mod ipc_audit;
 pub mod ipc_client;
+pub mod ipc_tls;
+pub mod maintenance;
 pub mod paths;
+pub mod policy;
+pub mod privileged;
 pub mod safe_mode;
+pub mod sbom;
 pub mod secure_storage;
 pub mod settings;
+pub mod settings_history;
 pub mod storage;
 pub mod vault;
 
 pub use crypto::*;
 pub use device_state::*;
 pub use event_log::*;
+pub use event_replay::*;
+pub use events::*;
+pub use evidence::*;
+pub use exclusion::*;
+pub use health::*;
+pub use instances::*;
 pub use backup_store::*;
+pub use command_receipt::*;
 pub use ipc::*;
+pub use ipc_audit::*;
 pub use ipc_client::*;
+pub use ipc_tls::*;
+pub use maintenance::*;
 pub use paths::*;
+pub use policy::*;
+pub use privileged::*;
 pub use safe_mode::*;
+pub use sbom::*;
 pub use secure_storage::*;
 pub use settings::*;
+pub use settings_history::*;
 pub use storage::*;
 pub use vault::*;
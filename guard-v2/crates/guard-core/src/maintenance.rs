@@ -0,0 +1,143 @@
+//! Change journal kept while the engine is in maintenance mode.
+//!
+//! Enforcement is paused during maintenance, so the journal is the record of
+//! what the window actually changed: every path the watcher saw touched, and
+//! on a rebaseline the old and new baseline hashes. It is attached to the
+//! `MAINTENANCE_EXIT` event and can be written out as a report for review.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Journal entries carried inline in a `MAINTENANCE_EXIT` event; the full
+/// journal goes in the report.
+pub const JOURNAL_EVENT_LIMIT: usize = 200;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Modified,
+    Added,
+    Removed,
+    PermissionChanged,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct JournalEntry {
+    pub path: String,
+    pub kind: ChangeKind,
+    /// Baseline hash before the window; `None` for added files.
+    #[serde(default)]
+    pub old_hash: Option<String>,
+    /// Hash at the end of the window; `None` for removed files.
+    #[serde(default)]
+    pub new_hash: Option<String>,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MaintenanceJournal {
+    pub reason: String,
+    #[serde(default)]
+    pub entered_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub exited_at: Option<DateTime<Utc>>,
+    pub rebaselined: bool,
+    /// Keyed by path; one entry per path however often it was touched.
+    pub entries: BTreeMap<String, JournalEntry>,
+}
+
+impl MaintenanceJournal {
+    pub fn new(reason: String, entered_at: DateTime<Utc>) -> Self {
+        Self {
+            reason,
+            entered_at: Some(entered_at),
+            ..Default::default()
+        }
+    }
+
+    /// Record a change. A path already in the journal keeps its original
+    /// `old_hash` and `first_seen`; the latest kind and hash win.
+    pub fn record(
+        &mut self,
+        path: String,
+        kind: ChangeKind,
+        old_hash: Option<String>,
+        new_hash: Option<String>,
+        at: DateTime<Utc>,
+    ) {
+        match self.entries.get_mut(&path) {
+            Some(entry) => {
+                if entry.old_hash.is_none() && entry.kind != ChangeKind::Added {
+                    entry.old_hash = old_hash;
+                }
+                // A file created inside the window stays "added" until it
+                // is removed again.
+                entry.kind = match (entry.kind, kind) {
+                    (ChangeKind::Added, ChangeKind::Removed) => ChangeKind::Removed,
+                    (ChangeKind::Added, _) => ChangeKind::Added,
+                    (_, k) => k,
+                };
+                if new_hash.is_some() || kind == ChangeKind::Removed {
+                    entry.new_hash = new_hash;
+                }
+                entry.last_seen = at;
+            }
+            None => {
+                self.entries.insert(
+                    path.clone(),
+                    JournalEntry {
+                        path,
+                        kind,
+                        old_hash,
+                        new_hash,
+                        first_seen: at,
+                        last_seen: at,
+                    },
+                );
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Number of entries per change kind.
+    pub fn counts(&self) -> BTreeMap<ChangeKind, usize> {
+        let mut counts = BTreeMap::new();
+        for entry in self.entries.values() {
+            *counts.entry(entry.kind).or_insert(0) += 1;
+        }
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_changes_collapse_to_one_entry() {
+        let t0 = Utc::now();
+        let mut journal = MaintenanceJournal::new("upgrade".into(), t0);
+        journal.record("/etc/app.conf".into(), ChangeKind::Modified, Some("a".into()), Some("b".into()), t0);
+        journal.record("/etc/app.conf".into(), ChangeKind::Modified, Some("b".into()), Some("c".into()), t0);
+        journal.record("/etc/new".into(), ChangeKind::Added, None, Some("n".into()), t0);
+        journal.record("/etc/new".into(), ChangeKind::Modified, Some("n".into()), Some("m".into()), t0);
+
+        let conf = &journal.entries["/etc/app.conf"];
+        assert_eq!(conf.old_hash.as_deref(), Some("a"));
+        assert_eq!(conf.new_hash.as_deref(), Some("c"));
+        let added = &journal.entries["/etc/new"];
+        assert_eq!(added.kind, ChangeKind::Added);
+        assert_eq!(added.old_hash, None);
+        assert_eq!(added.new_hash.as_deref(), Some("m"));
+        assert_eq!(journal.len(), 2);
+    }
+}
@@ -1,16 +1,27 @@
 use directories::ProjectDirs;
 use std::path::PathBuf;
 
+use crate::instances::{current_instance, instances_root};
+
 pub const APP_QUALIFIER: &str = "com";
 pub const APP_ORG: &str = "darklock";
 pub const APP_NAME: &str = "guard";
 
-pub fn data_dir() -> anyhow::Result<PathBuf> {
+/// Data dir of the default instance; named instances live beneath it.
+pub fn base_data_dir() -> anyhow::Result<PathBuf> {
     let dirs = ProjectDirs::from(APP_QUALIFIER, APP_ORG, APP_NAME)
         .ok_or_else(|| anyhow::anyhow!("cannot determine data directory"))?;
     Ok(dirs.data_dir().to_path_buf())
 }
 
+/// Data dir of the selected instance.
+pub fn data_dir() -> anyhow::Result<PathBuf> {
+    match current_instance()? {
+        Some(name) => Ok(instances_root()?.join(name)),
+        None => base_data_dir(),
+    }
+}
+
 pub fn log_dir() -> anyhow::Result<PathBuf> {
     Ok(data_dir()?.join("logs"))
 }
@@ -22,7 +33,7 @@ pub fn ipc_socket_path() -> anyhow::Result<PathBuf> {
     }
     #[cfg(windows)]
     {
-        Ok(PathBuf::from(r"\\.\pipe\DarklockGuardIpc"))
+        Ok(PathBuf::from(pipe_name("DarklockGuardIpc")?))
     }
 }
 
@@ -36,6 +47,15 @@ pub fn status_socket_path() -> anyhow::Result<PathBuf> {
     }
     #[cfg(windows)]
     {
-        Ok(PathBuf::from(r"\\.\pipe\DarklockGuardStatus"))
+        Ok(PathBuf::from(pipe_name("DarklockGuardStatus")?))
     }
 }
+
+/// Named pipes are global, so named instances suffix theirs.
+#[cfg(windows)]
+fn pipe_name(base: &str) -> anyhow::Result<String> {
+    Ok(match current_instance()? {
+        Some(name) => format!(r"\\.\pipe\{base}-{name}"),
+        None => format!(r"\\.\pipe\{base}"),
+    })
+}
@@ -0,0 +1,256 @@
+//! Organization policy bundles.
+//!
+//! A bundle pins a subset of `GuardSettings` (protected paths, enforcement
+//! mode, update channel, telemetry). It is signed with the organization's
+//! ed25519 key; the service verifies it against the configured org public key,
+//! overlays it onto local settings and refuses local edits to any field the
+//! bundle pins while it is active.
+
+use crate::settings::{GuardSettings, SecurityMode};
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+
+/// Domain separator so a policy signature can't be replayed as any other
+/// org-signed message.
+const POLICY_SIGNING_CONTEXT: &[u8] = b"darklock-guard-policy-v1\0";
+
+/// Settings pinned by a bundle. `None` leaves the local value untouched.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct PolicySettings {
+    #[serde(default)]
+    pub protected_paths: Option<Vec<String>>,
+    #[serde(default)]
+    pub security_mode: Option<SecurityMode>,
+    #[serde(default)]
+    pub realtime_enabled: Option<bool>,
+    #[serde(default)]
+    pub baseline_locked: Option<bool>,
+    #[serde(default)]
+    pub quarantine_enabled: Option<bool>,
+    #[serde(default)]
+    pub update_channel: Option<String>,
+    #[serde(default)]
+    pub auto_update: Option<bool>,
+    #[serde(default)]
+    pub telemetry_enabled: Option<bool>,
+    #[serde(default)]
+    pub crash_reports: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PolicyBundle {
+    pub policy_id: String,
+    /// Monotonic; a bundle is accepted only with a higher version than any
+    /// bundle accepted before it, expired ones included.
+    pub version: u64,
+    pub issued_at: DateTime<Utc>,
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    pub settings: PolicySettings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedPolicyBundle {
+    pub bundle: PolicyBundle,
+    /// base64 ed25519 signature over `POLICY_SIGNING_CONTEXT || json(bundle)`.
+    pub signature: String,
+}
+
+fn signing_message(bundle: &PolicyBundle) -> Result<Vec<u8>> {
+    let mut msg = POLICY_SIGNING_CONTEXT.to_vec();
+    msg.extend_from_slice(&serde_json::to_vec(bundle)?);
+    Ok(msg)
+}
+
+pub fn decode_org_key(b64: &str) -> Result<VerifyingKey> {
+    let bytes = general_purpose::STANDARD
+        .decode(b64.trim())
+        .map_err(|e| anyhow!("decode org key: {e}"))?;
+    let arr: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| anyhow!("org key must be 32 bytes"))?;
+    VerifyingKey::from_bytes(&arr).map_err(|e| anyhow!("org key parse: {e}"))
+}
+
+impl SignedPolicyBundle {
+    pub fn sign(bundle: PolicyBundle, org_key: &SigningKey) -> Result<Self> {
+        let signature = org_key.sign(&signing_message(&bundle)?);
+        Ok(Self {
+            bundle,
+            signature: general_purpose::STANDARD.encode(signature.to_bytes()),
+        })
+    }
+
+    /// Check the signature against `org_key`. Expiry is checked separately
+    /// so an expired bundle can still be inspected.
+    pub fn verify(&self, org_key: &VerifyingKey) -> Result<()> {
+        let sig_bytes = general_purpose::STANDARD
+            .decode(&self.signature)
+            .map_err(|e| anyhow!("decode policy signature: {e}"))?;
+        let arr: [u8; 64] = sig_bytes
+            .try_into()
+            .map_err(|_| anyhow!("policy signature length"))?;
+        org_key
+            .verify_strict(&signing_message(&self.bundle)?, &Signature::from_bytes(&arr))
+            .map_err(|e| anyhow!("policy signature invalid: {e}"))
+    }
+}
+
+impl PolicyBundle {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|exp| exp <= now)
+    }
+
+    /// Overlay the pinned fields onto `settings`.
+    pub fn apply(&self, settings: &GuardSettings) -> GuardSettings {
+        let p = &self.settings;
+        let mut out = settings.clone();
+        if let Some(paths) = &p.protected_paths {
+            out.protection.protected_paths = paths.clone();
+        }
+        if let Some(mode) = &p.security_mode {
+            out.security_mode = mode.clone();
+        }
+        if let Some(v) = p.realtime_enabled {
+            out.protection.realtime_enabled = v;
+        }
+        if let Some(v) = p.baseline_locked {
+            out.protection.baseline_locked = v;
+        }
+        if let Some(v) = p.quarantine_enabled {
+            out.protection.quarantine_enabled = v;
+        }
+        if let Some(channel) = &p.update_channel {
+            out.updates.channel = channel.clone();
+        }
+        if let Some(v) = p.auto_update {
+            out.updates.auto_update = v;
+        }
+        if let Some(v) = p.telemetry_enabled {
+            out.privacy.telemetry_enabled = v;
+        }
+        if let Some(v) = p.crash_reports {
+            out.privacy.crash_reports = v;
+        }
+        out
+    }
+
+    /// Names of the settings this bundle pins.
+    pub fn locked_fields(&self) -> Vec<&'static str> {
+        let p = &self.settings;
+        [
+            ("protection.protected_paths", p.protected_paths.is_some()),
+            ("security_mode", p.security_mode.is_some()),
+            ("protection.realtime_enabled", p.realtime_enabled.is_some()),
+            ("protection.baseline_locked", p.baseline_locked.is_some()),
+            ("protection.quarantine_enabled", p.quarantine_enabled.is_some()),
+            ("updates.channel", p.update_channel.is_some()),
+            ("updates.auto_update", p.auto_update.is_some()),
+            ("privacy.telemetry_enabled", p.telemetry_enabled.is_some()),
+            ("privacy.crash_reports", p.crash_reports.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(name, locked)| locked.then_some(name))
+        .collect()
+    }
+
+    /// Locked fields whose value in `proposed` differs from the policy.
+    pub fn violations(&self, proposed: &GuardSettings) -> Vec<&'static str> {
+        let enforced = self.apply(proposed);
+        let mut out = Vec::new();
+        if enforced.protection.protected_paths != proposed.protection.protected_paths {
+            out.push("protection.protected_paths");
+        }
+        if enforced.security_mode != proposed.security_mode {
+            out.push("security_mode");
+        }
+        if enforced.protection.realtime_enabled != proposed.protection.realtime_enabled {
+            out.push("protection.realtime_enabled");
+        }
+        if enforced.protection.baseline_locked != proposed.protection.baseline_locked {
+            out.push("protection.baseline_locked");
+        }
+        if enforced.protection.quarantine_enabled != proposed.protection.quarantine_enabled {
+            out.push("protection.quarantine_enabled");
+        }
+        if enforced.updates.channel != proposed.updates.channel {
+            out.push("updates.channel");
+        }
+        if enforced.updates.auto_update != proposed.updates.auto_update {
+            out.push("updates.auto_update");
+        }
+        if enforced.privacy.telemetry_enabled != proposed.privacy.telemetry_enabled {
+            out.push("privacy.telemetry_enabled");
+        }
+        if enforced.privacy.crash_reports != proposed.privacy.crash_reports {
+            out.push("privacy.crash_reports");
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    fn bundle() -> PolicyBundle {
+        PolicyBundle {
+            policy_id: "corp-baseline".into(),
+            version: 1,
+            issued_at: Utc::now(),
+            expires_at: None,
+            settings: PolicySettings {
+                protected_paths: Some(vec!["/etc".into()]),
+                update_channel: Some("stable".into()),
+                telemetry_enabled: Some(false),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn signed_bundle_verifies_and_detects_tampering() {
+        let org = SigningKey::generate(&mut OsRng);
+        let mut signed = SignedPolicyBundle::sign(bundle(), &org).unwrap();
+        signed.verify(&org.verifying_key()).unwrap();
+
+        let other = SigningKey::generate(&mut OsRng);
+        assert!(signed.verify(&other.verifying_key()).is_err());
+
+        signed.bundle.settings.telemetry_enabled = Some(true);
+        assert!(signed.verify(&org.verifying_key()).is_err());
+    }
+
+    #[test]
+    fn apply_overlays_only_pinned_fields() {
+        let local = GuardSettings::default();
+        let applied = bundle().apply(&local);
+        assert_eq!(applied.protection.protected_paths, vec!["/etc".to_string()]);
+        assert_eq!(applied.performance.max_cpu_percent, local.performance.max_cpu_percent);
+        assert_eq!(
+            bundle().locked_fields(),
+            vec!["protection.protected_paths", "updates.channel", "privacy.telemetry_enabled"]
+        );
+    }
+
+    #[test]
+    fn violations_report_changed_locked_fields() {
+        let policy = bundle();
+        let mut proposed = policy.apply(&GuardSettings::default());
+        assert!(policy.violations(&proposed).is_empty());
+
+        proposed.performance.max_cpu_percent = 50;
+        assert!(policy.violations(&proposed).is_empty());
+
+        proposed.protection.protected_paths.push("/home".into());
+        proposed.updates.channel = "beta".into();
+        assert_eq!(
+            policy.violations(&proposed),
+            vec!["protection.protected_paths", "updates.channel"]
+        );
+    }
+}
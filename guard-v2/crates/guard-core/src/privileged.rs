@@ -0,0 +1,185 @@
+//! Requests to the privileged restore helper.
+//!
+//! When the service runs unprivileged it can't put back root-owned files.
+//! The helper (`darklock-restore-helper`) is started elevated per request —
+//! through polkit on Linux, a sudoers rule on macOS — reads one signed
+//! request on stdin and answers on stdout. It only restores or quarantines
+//! paths under the prefixes in its own root-owned config, only for requests
+//! signed with the service's device key and issued within
+//! `MAX_REQUEST_AGE_SECS`, and logs every call.
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Duration, Utc};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+
+/// Domain separator so a helper request signature can't be replayed as any
+/// other device-signed message.
+const HELPER_SIGNING_CONTEXT: &[u8] = b"darklock-guard-privileged-helper-v1\0";
+
+/// How long a signed request stays valid.
+pub const MAX_REQUEST_AGE_SECS: i64 = 60;
+
+/// How the service starts the helper: the elevation wrapper and the
+/// helper's install path. Fixed at build time, so nothing a settings writer
+/// controls decides what runs elevated.
+#[cfg(target_os = "macos")]
+pub const HELPER_COMMAND: &[&str] = &[
+    "/usr/bin/sudo",
+    "-n",
+    "/Library/PrivilegedHelperTools/darklock-restore-helper",
+];
+#[cfg(target_os = "linux")]
+pub const HELPER_COMMAND: &[&str] = &[
+    "/usr/bin/pkexec",
+    "/usr/libexec/darklock/darklock-restore-helper",
+];
+/// No elevation wrapper is shipped elsewhere; the helper is unavailable.
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+pub const HELPER_COMMAND: &[&str] = &[];
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum HelperOp {
+    /// Replace `path` with `content` (base64), which must have BLAKE3 hash
+    /// `hash`, then apply `mode` (Unix; 0 leaves it) and ownership.
+    Restore {
+        path: String,
+        hash: String,
+        content: String,
+        mode: u32,
+        #[serde(default)]
+        uid: Option<u32>,
+        #[serde(default)]
+        gid: Option<u32>,
+    },
+    /// Move `path` into the helper's quarantine directory.
+    Quarantine { path: String },
+}
+
+impl HelperOp {
+    pub fn path(&self) -> &str {
+        match self {
+            HelperOp::Restore { path, .. } | HelperOp::Quarantine { path } => path,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            HelperOp::Restore { .. } => "restore",
+            HelperOp::Quarantine { .. } => "quarantine",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HelperRequest {
+    pub op: HelperOp,
+    /// Random per request; the helper refuses a nonce it has seen.
+    pub nonce: String,
+    pub issued_at: DateTime<Utc>,
+}
+
+impl HelperRequest {
+    pub fn new(op: HelperOp) -> Self {
+        Self {
+            op,
+            nonce: uuid::Uuid::new_v4().to_string(),
+            issued_at: Utc::now(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedHelperRequest {
+    pub request: HelperRequest,
+    /// base64 ed25519 signature over
+    /// `HELPER_SIGNING_CONTEXT || json(request)`.
+    pub signature: String,
+}
+
+fn signing_message(request: &HelperRequest) -> Result<Vec<u8>> {
+    let mut msg = HELPER_SIGNING_CONTEXT.to_vec();
+    msg.extend_from_slice(&serde_json::to_vec(request)?);
+    Ok(msg)
+}
+
+impl SignedHelperRequest {
+    pub fn sign(request: HelperRequest, device_key: &SigningKey) -> Result<Self> {
+        let signature = device_key.sign(&signing_message(&request)?);
+        Ok(Self {
+            request,
+            signature: general_purpose::STANDARD.encode(signature.to_bytes()),
+        })
+    }
+
+    /// Check the signature and that the request was issued within
+    /// `MAX_REQUEST_AGE_SECS` of `now`.
+    pub fn verify(&self, service_key: &VerifyingKey, now: DateTime<Utc>) -> Result<()> {
+        let sig_bytes = general_purpose::STANDARD
+            .decode(&self.signature)
+            .map_err(|e| anyhow!("decode request signature: {e}"))?;
+        let arr: [u8; 64] = sig_bytes
+            .try_into()
+            .map_err(|_| anyhow!("request signature length"))?;
+        service_key
+            .verify_strict(
+                &signing_message(&self.request)?,
+                &Signature::from_bytes(&arr),
+            )
+            .map_err(|e| anyhow!("request signature invalid: {e}"))?;
+        let age = now - self.request.issued_at;
+        if age > Duration::seconds(MAX_REQUEST_AGE_SECS)
+            || age < -Duration::seconds(MAX_REQUEST_AGE_SECS)
+        {
+            return Err(anyhow!("request is stale"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HelperResponse {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Where a quarantined file was moved to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quarantine_path: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn signed_request_verifies_only_while_fresh() {
+        let service = SigningKey::generate(&mut OsRng);
+        let signed = SignedHelperRequest::sign(
+            HelperRequest::new(HelperOp::Quarantine {
+                path: "/etc/app.conf".into(),
+            }),
+            &service,
+        )
+        .unwrap();
+        let now = signed.request.issued_at;
+        signed.verify(&service.verifying_key(), now).unwrap();
+        assert!(signed
+            .verify(
+                &service.verifying_key(),
+                now + Duration::seconds(MAX_REQUEST_AGE_SECS + 1)
+            )
+            .is_err());
+
+        let other = SigningKey::generate(&mut OsRng);
+        assert!(signed.verify(&other.verifying_key(), now).is_err());
+
+        let mut retargeted = signed;
+        retargeted.request.op = HelperOp::Quarantine {
+            path: "/etc/shadow".into(),
+        };
+        assert!(retargeted.verify(&service.verifying_key(), now).is_err());
+    }
+}
@@ -11,6 +11,11 @@ pub enum SafeModeReason {
     IntegrityFailure,
     IpcFailure,
     RemoteCommand,
+    SubsystemFailure,
+    RansomwareSuspected,
+    PlatformUnreachable,
+    EnforcementAction,
+    VaultTamperSuspected,
     Unknown,
 }
 
@@ -19,6 +24,11 @@ pub struct SafeModeState {
     pub active: bool,
     pub reason: Option<SafeModeReason>,
     pub entered_at: Option<DateTime<Utc>>,
+    /// Set when the state files failed cold-start attestation. Entering safe
+    /// mode again for another reason keeps it; only `exit` clears it, so the
+    /// changed files aren't attested as trusted without an operator.
+    #[serde(default)]
+    pub attestation_mismatch: bool,
 }
 
 impl Default for SafeModeState {
@@ -27,6 +37,7 @@ impl Default for SafeModeState {
             active: false,
             reason: None,
             entered_at: None,
+            attestation_mismatch: false,
         }
     }
 }
@@ -34,6 +45,7 @@ impl Default for SafeModeState {
 impl SafeModeState {
     pub fn enter(&mut self, reason: SafeModeReason) {
         self.active = true;
+        self.attestation_mismatch |= reason == SafeModeReason::VaultTamperSuspected;
         self.reason = Some(reason);
         self.entered_at = Some(Utc::now());
     }
@@ -42,5 +54,24 @@ impl SafeModeState {
         self.active = false;
         self.reason = None;
         self.entered_at = None;
+        self.attestation_mismatch = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attestation_mismatch_survives_later_entries() {
+        let mut state = SafeModeState::default();
+        state.enter(SafeModeReason::VaultTamperSuspected);
+        state.enter(SafeModeReason::Manual);
+        state.enter(SafeModeReason::RansomwareSuspected);
+        assert_eq!(state.reason, Some(SafeModeReason::RansomwareSuspected));
+        assert!(state.attestation_mismatch);
+
+        state.exit();
+        assert!(!state.attestation_mismatch);
     }
 }
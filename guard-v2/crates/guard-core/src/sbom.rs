@@ -0,0 +1,169 @@
+//! Vendor-signed software manifests (SBOM file lists).
+//!
+//! A vendor publishes the files that make up a release (relative path +
+//! SHA-256) and signs the list with its ed25519 key. The operator imports it
+//! for a protected application directory, supplying the vendor's public key
+//! obtained out of band; the service then reports files that differ from what
+//! the vendor shipped, independently of the local baseline.
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+
+use crate::policy::decode_org_key;
+
+/// Domain separator so a manifest signature can't be replayed as any other
+/// vendor-signed message.
+const SBOM_SIGNING_CONTEXT: &[u8] = b"darklock-guard-sbom-v1\0";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SbomFile {
+    /// Path relative to the application directory, `/`-separated.
+    pub path: String,
+    /// Lowercase hex SHA-256.
+    pub sha256: String,
+    #[serde(default)]
+    pub size: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SbomManifest {
+    pub vendor: String,
+    pub product: String,
+    pub version: String,
+    pub issued_at: DateTime<Utc>,
+    pub files: Vec<SbomFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedSbomManifest {
+    pub manifest: SbomManifest,
+    /// base64 ed25519 signature over `SBOM_SIGNING_CONTEXT || json(manifest)`.
+    pub signature: String,
+}
+
+/// An imported manifest bound to the directory it describes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SbomBinding {
+    pub root: String,
+    /// base64 vendor public key the manifest was verified against.
+    pub vendor_key: String,
+    pub signed: SignedSbomManifest,
+    pub imported_at: DateTime<Utc>,
+}
+
+/// Result of checking a directory against its manifest.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SbomReport {
+    pub root: String,
+    pub vendor: String,
+    pub product: String,
+    pub version: String,
+    pub checked_at: DateTime<Utc>,
+    pub matched: usize,
+    /// Listed files whose hash differs.
+    pub modified: Vec<String>,
+    /// Listed files that are absent.
+    pub missing: Vec<String>,
+    /// Files present on disk that the vendor didn't ship.
+    pub unexpected: Vec<String>,
+    /// Files that couldn't be read.
+    pub errors: Vec<String>,
+}
+
+impl SbomReport {
+    pub fn is_clean(&self) -> bool {
+        self.modified.is_empty()
+            && self.missing.is_empty()
+            && self.unexpected.is_empty()
+            && self.errors.is_empty()
+    }
+}
+
+fn signing_message(manifest: &SbomManifest) -> Result<Vec<u8>> {
+    let mut msg = SBOM_SIGNING_CONTEXT.to_vec();
+    msg.extend_from_slice(&serde_json::to_vec(manifest)?);
+    Ok(msg)
+}
+
+pub fn decode_vendor_key(b64: &str) -> Result<VerifyingKey> {
+    decode_org_key(b64).map_err(|e| anyhow!("vendor key: {e}"))
+}
+
+impl SignedSbomManifest {
+    pub fn sign(manifest: SbomManifest, vendor_key: &SigningKey) -> Result<Self> {
+        let signature = vendor_key.sign(&signing_message(&manifest)?);
+        Ok(Self {
+            manifest,
+            signature: general_purpose::STANDARD.encode(signature.to_bytes()),
+        })
+    }
+
+    /// Check the signature and that every listed path is a plain relative
+    /// path, so a manifest can't point outside the directory it describes.
+    pub fn verify(&self, vendor_key: &VerifyingKey) -> Result<()> {
+        let sig_bytes = general_purpose::STANDARD
+            .decode(&self.signature)
+            .map_err(|e| anyhow!("decode manifest signature: {e}"))?;
+        let arr: [u8; 64] = sig_bytes
+            .try_into()
+            .map_err(|_| anyhow!("manifest signature length"))?;
+        vendor_key
+            .verify_strict(&signing_message(&self.manifest)?, &Signature::from_bytes(&arr))
+            .map_err(|e| anyhow!("manifest signature invalid: {e}"))?;
+        for file in &self.manifest.files {
+            let unsafe_path = file.path.is_empty()
+                || file.path.starts_with('/')
+                || file.path.contains('\\')
+                || file.path.split('/').any(|c| c.is_empty() || c == "." || c == "..");
+            if unsafe_path {
+                return Err(anyhow!("manifest lists invalid path {:?}", file.path));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    fn manifest(path: &str) -> SbomManifest {
+        SbomManifest {
+            vendor: "Acme".into(),
+            product: "acmed".into(),
+            version: "2.1.0".into(),
+            issued_at: Utc::now(),
+            files: vec![SbomFile {
+                path: path.into(),
+                sha256: "00".repeat(32),
+                size: Some(4),
+            }],
+        }
+    }
+
+    #[test]
+    fn signed_manifest_verifies_and_detects_tampering() {
+        let vendor = SigningKey::generate(&mut OsRng);
+        let mut signed = SignedSbomManifest::sign(manifest("bin/acmed"), &vendor).unwrap();
+        signed.verify(&vendor.verifying_key()).unwrap();
+
+        let other = SigningKey::generate(&mut OsRng);
+        assert!(signed.verify(&other.verifying_key()).is_err());
+
+        signed.manifest.files[0].sha256 = "11".repeat(32);
+        assert!(signed.verify(&vendor.verifying_key()).is_err());
+    }
+
+    #[test]
+    fn paths_escaping_the_directory_are_rejected() {
+        let vendor = SigningKey::generate(&mut OsRng);
+        for bad in ["../etc/passwd", "/etc/passwd", "bin//x", "bin\\x"] {
+            let signed = SignedSbomManifest::sign(manifest(bad), &vendor).unwrap();
+            assert!(signed.verify(&vendor.verifying_key()).is_err(), "{bad}");
+        }
+    }
+}
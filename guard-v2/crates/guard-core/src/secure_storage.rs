@@ -5,6 +5,8 @@ use keyring::Entry;
 const SERVICE_NAME: &str = "DarklockGuard";
 const TOKEN_KEY: &str = "device_token";
 const IPC_SECRET_KEY: &str = "ipc_secret";
+const STATE_ATTESTATION_KEY: &str = "state_attestation";
+const STATE_ATTESTED_KEY: &str = "state_attested";
 
 pub fn store_device_token(device_id: &str, token: &str) -> Result<()> {
     let entry = Entry::new(SERVICE_NAME, &format!("{}:{}", TOKEN_KEY, device_id))
@@ -63,3 +65,51 @@ pub fn delete_ipc_secret(device_id: &str) -> Result<()> {
         .map_err(|e| anyhow!("delete ipc secret: {e}"))?;
     Ok(())
 }
+
+/// Digests of the service's state files, recorded at a clean shutdown.
+pub fn store_state_attestation(device_id: &str, record: &str) -> Result<()> {
+    let entry = Entry::new(SERVICE_NAME, &format!("{}:{}", STATE_ATTESTATION_KEY, device_id))
+        .map_err(|e| anyhow!("keyring init: {e}"))?;
+    entry
+        .set_password(record)
+        .map_err(|e| anyhow!("store state attestation: {e}"))?;
+    Ok(())
+}
+
+/// Remove and return the recorded digests, so a record is only ever
+/// compared once; `None` if there is none.
+pub fn take_state_attestation(device_id: &str) -> Result<Option<String>> {
+    let entry = Entry::new(SERVICE_NAME, &format!("{}:{}", STATE_ATTESTATION_KEY, device_id))
+        .map_err(|e| anyhow!("keyring init: {e}"))?;
+    let record = match entry.get_password() {
+        Ok(record) => record,
+        Err(keyring::Error::NoEntry) => return Ok(None),
+        Err(e) => return Err(anyhow!("load state attestation: {e}")),
+    };
+    entry
+        .delete_password()
+        .map_err(|e| anyhow!("delete state attestation: {e}"))?;
+    Ok(Some(record))
+}
+
+/// Note that a state attestation has been stored for `device_id`. Never
+/// cleared, so a missing record afterwards can't pass for a first start.
+pub fn mark_state_attested(device_id: &str) -> Result<()> {
+    let entry = Entry::new(SERVICE_NAME, &format!("{}:{}", STATE_ATTESTED_KEY, device_id))
+        .map_err(|e| anyhow!("keyring init: {e}"))?;
+    entry
+        .set_password("1")
+        .map_err(|e| anyhow!("store state attestation marker: {e}"))?;
+    Ok(())
+}
+
+/// Whether a state attestation has ever been stored for `device_id`.
+pub fn was_state_attested(device_id: &str) -> Result<bool> {
+    let entry = Entry::new(SERVICE_NAME, &format!("{}:{}", STATE_ATTESTED_KEY, device_id))
+        .map_err(|e| anyhow!("keyring init: {e}"))?;
+    match entry.get_password() {
+        Ok(_) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(anyhow!("load state attestation marker: {e}")),
+    }
+}
@@ -1,6 +1,9 @@
+use chrono::{DateTime, Timelike, Utc};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::event_log::EventSeverity;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum SecurityMode {
     Normal,
     Strict,
@@ -12,6 +15,24 @@ pub struct PerformanceLimits {
     pub max_memory_mb: u32,
 }
 
+/// How symbolic links inside protected paths are treated. A protected root
+/// that is itself a link is always followed.
+///
+/// The policy is recorded in each baseline and scans use the baseline's, so
+/// a change applies from the next baseline. Whatever the policy, a baselined
+/// file or directory that turns into a link is reported as a symlink swap.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SymlinkPolicy {
+    /// Protect the files links point to, keyed by their resolved path.
+    Follow,
+    /// Ignore links.
+    #[default]
+    NoFollow,
+    /// Protect the link itself: its target may not change.
+    ProtectLink,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProtectionSettings {
     pub realtime_enabled: bool,
@@ -20,12 +41,54 @@ pub struct ProtectionSettings {
     pub protected_paths: Vec<String>,
     #[serde(default)]
     pub quarantine_enabled: bool,
+    #[serde(default)]
+    pub symlink_policy: SymlinkPolicy,
 }
 
+/// Service-side updates.
+///
+/// With `auto_update` on, the service checks `platform_url` (or
+/// `DARKLOCK_PLATFORM_URL`) every `check_interval_hours` and stages newer
+/// releases. A staged release is installed inside `install_window`, or right
+/// away if the platform marks it forced; without a window it waits for an
+/// operator to install it.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateSettings {
     pub channel: String,
     pub auto_update: bool,
+    #[serde(default)]
+    pub platform_url: Option<String>,
+    #[serde(default = "default_check_interval_hours")]
+    pub check_interval_hours: u32,
+    #[serde(default)]
+    pub install_window: Option<MaintenanceWindow>,
+    /// Run by the service after an install to restart itself on the new
+    /// binaries, e.g. `systemctl restart darklock-guard`.
+    #[serde(default)]
+    pub restart_cmd: Option<String>,
+}
+
+fn default_check_interval_hours() -> u32 {
+    6
+}
+
+/// Daily window in UTC hours, `start_hour` inclusive to `end_hour`
+/// exclusive; may wrap past midnight (22 → 4).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MaintenanceWindow {
+    pub start_hour: u8,
+    pub end_hour: u8,
+}
+
+impl MaintenanceWindow {
+    pub fn contains(&self, now: DateTime<Utc>) -> bool {
+        let hour = now.hour() as u8;
+        if self.start_hour <= self.end_hour {
+            (self.start_hour..self.end_hour).contains(&hour)
+        } else {
+            hour >= self.start_hour || hour < self.end_hour
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,6 +98,401 @@ pub struct PrivacySettings {
     pub crash_reports: bool,
 }
 
+/// Optional TLS-wrapped TCP listener for remote administration.
+///
+/// Disabled by default, and bound to loopback until `bind_addr` is changed
+/// to an address other hosts can reach. The server always presents
+/// `cert_path`; clients must also prove knowledge of the vault's IPC secret
+/// (bound to the TLS session). When `client_ca_path` is set, clients must
+/// additionally present a certificate signed by that CA.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteIpcSettings {
+    pub enabled: bool,
+    pub bind_addr: String,
+    #[serde(default)]
+    pub cert_path: Option<String>,
+    #[serde(default)]
+    pub key_path: Option<String>,
+    #[serde(default)]
+    pub client_ca_path: Option<String>,
+}
+
+impl Default for RemoteIpcSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind_addr: "127.0.0.1:7443".into(),
+            cert_path: None,
+            key_path: None,
+            client_ca_path: None,
+        }
+    }
+}
+
+/// Copy-on-write snapshots of protected directories (btrfs, ZFS, APFS).
+///
+/// Disabled by default. When enabled, a snapshot is taken each time a
+/// baseline is created; if a scan finds at least `widespread_threshold`
+/// violations under one protected directory, the whole directory is restored
+/// from its latest snapshot instead of file by file.
+///
+/// On btrfs the snapshot is of the entire subvolume enclosing the directory,
+/// which may be the root filesystem `/`; keep that in mind for disk usage.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotSettings {
+    pub enabled: bool,
+    /// Snapshots retained per protected directory.
+    pub keep: usize,
+    pub widespread_threshold: usize,
+}
+
+impl Default for SnapshotSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            keep: 3,
+            widespread_threshold: 20,
+        }
+    }
+}
+
+/// Ransomware burst detection on the watcher stream.
+///
+/// A burst is `file_threshold` distinct protected files changed, or
+/// `rename_threshold` files given a new extension, within `window_secs`.
+/// On a burst the service records `RANSOMWARE_SUSPECTED`, freezes writes to
+/// the protected directories if `freeze_writes` is set, and with
+/// `safe_mode_on_detect` restores what it can and enters safe mode.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RansomwareSettings {
+    pub enabled: bool,
+    pub window_secs: u64,
+    pub file_threshold: usize,
+    pub rename_threshold: usize,
+    pub freeze_writes: bool,
+    pub safe_mode_on_detect: bool,
+}
+
+impl Default for RansomwareSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window_secs: 10,
+            file_threshold: 40,
+            rename_threshold: 8,
+            freeze_writes: true,
+            safe_mode_on_detect: false,
+        }
+    }
+}
+
+/// Flap suppression for realtime tamper events.
+///
+/// A path that raises `flap_threshold` identical events (same path, same
+/// kind) within `dedup_window_secs` is flapping: the event that trips it is
+/// enforced, and its further events are counted instead of logged and
+/// enforced, first for one window, then for twice as long each time it
+/// starts flapping again, up to `max_suppress_secs`. A period starts with
+/// `FLAPPING_DETECTED` and ends with an `ALERTS_SUPPRESSED` summary and a
+/// re-check of the path, which restores a file left changed. From the
+/// `suggest_after`th period on, the summary suggests excluding or
+/// allowlisting the path.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlapSettings {
+    pub enabled: bool,
+    pub dedup_window_secs: u64,
+    pub flap_threshold: usize,
+    pub max_suppress_secs: u64,
+    pub suggest_after: u32,
+}
+
+impl Default for FlapSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dedup_window_secs: 30,
+            flap_threshold: 5,
+            max_suppress_secs: 3600,
+            suggest_after: 3,
+        }
+    }
+}
+
+/// Elevated restores through the privileged helper.
+///
+/// With `enabled`, a restore or quarantine refused for lack of permission is
+/// handed to `darklock-restore-helper`, started through the elevation
+/// wrapper in `privileged::HELPER_COMMAND`; which program runs is not a
+/// setting. The helper acts only on the paths approved in its own config
+/// and only for requests signed with this device's key; every call is
+/// logged as `PRIVILEGED_HELPER_CALL`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PrivilegedHelperSettings {
+    pub enabled: bool,
+}
+
+/// Hashing in a separate, confined worker process.
+///
+/// With `enabled`, the service streams file contents to a `scan-worker`
+/// child and reads back their BLAKE3 digests, for scans, baselines and
+/// watcher checks. When the service runs as root the worker switches to
+/// `user`; on Linux it then enters strict seccomp, leaving it able to read
+/// from and write to its pipes and nothing else. A file crafted to exploit
+/// the hashing code can then at worst falsify digests, not reach the vault
+/// key. Takes effect at the next service start.
+///
+/// Anything less is logged at start but not refused: a service not running
+/// as root keeps its own user for the worker, other Unix platforms get no
+/// syscall filter, and on Windows the worker is only a separate process.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScannerSandboxSettings {
+    pub enabled: bool,
+    pub user: String,
+}
+
+impl Default for ScannerSandboxSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            user: "nobody".into(),
+        }
+    }
+}
+
+/// Restores blocked by a lock on the target file (another process holding it
+/// open on Windows, a busy executable on Unix).
+///
+/// Locked restores are retried in the background, backing off exponentially
+/// for up to `locked_retry_attempts` retries, doubling from 250ms up to
+/// `locked_retry_max_delay_ms` (checked once a second). If the file
+/// is still locked the restore is deferred (`RESTORE_DEFERRED`) and, with
+/// `queue_on_restart`, queued to be applied at the next service start; on
+/// Windows it is also scheduled as a replace-on-reboot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreSettings {
+    pub locked_retry_attempts: u32,
+    pub locked_retry_max_delay_ms: u64,
+    pub queue_on_restart: bool,
+}
+
+impl Default for RestoreSettings {
+    fn default() -> Self {
+        Self {
+            locked_retry_attempts: 5,
+            locked_retry_max_delay_ms: 4_000,
+            queue_on_restart: true,
+        }
+    }
+}
+
+/// How protected files are hashed.
+///
+/// Files are hashed as a stream; each file of at least
+/// `progress_interval_mb` reports progress every that many MiB (`0` turns
+/// reporting off). Files at or beyond a `quick_check` rule's size under its
+/// path are quick-checked on audit scans: size, mtime and the first and last
+/// MiB are compared against the baseline and only a mismatch escalates to a
+/// full hash. A quick check misses edits confined to the middle of a file
+/// that keep its size and mtime, so reserve it for files too large to hash on
+/// every scan. Baselines always hash in full.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HashingSettings {
+    pub progress_interval_mb: u64,
+    #[serde(default)]
+    pub quick_check: Vec<QuickCheckRule>,
+}
+
+impl Default for HashingSettings {
+    fn default() -> Self {
+        Self {
+            progress_interval_mb: 256,
+            quick_check: Vec::new(),
+        }
+    }
+}
+
+/// Quick-check files of at least `min_size_mb` at or beneath `path`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct QuickCheckRule {
+    pub path: String,
+    pub min_size_mb: u64,
+}
+
+/// Per-client IPC request budgets, shared by the local socket and remote
+/// IPC. Scans, baseline builds and manifest verification also count against
+/// `scans_per_minute`. `0` disables a limit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IpcSettings {
+    pub requests_per_minute: u32,
+    pub scans_per_minute: u32,
+}
+
+impl Default for IpcSettings {
+    fn default() -> Self {
+        Self {
+            requests_per_minute: 600,
+            scans_per_minute: 6,
+        }
+    }
+}
+
+/// Background maintenance of the backup store. Every `gc_interval_hours`
+/// entries for paths the baseline no longer covers are dropped, damaged
+/// blobs are re-fetched from intact sources, and unreferenced blobs are
+/// deleted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupStoreSettings {
+    pub gc_interval_hours: u32,
+}
+
+impl Default for BackupStoreSettings {
+    fn default() -> Self {
+        Self {
+            gc_interval_hours: 24,
+        }
+    }
+}
+
+/// File attributes recorded in baselines besides content and mode bits.
+///
+/// With `track_ownership` the owner and group are recorded (uid/gid on Unix;
+/// owner, group and DACL on Windows). `xattrs` names the extended attributes
+/// recorded on Unix; a tracked attribute that appears on a file where the
+/// baseline had none counts as a change, so `security.capability` catches
+/// capabilities granted to a protected binary. Changes are restored like
+/// permission changes; ownership can only be put back when the service runs
+/// as root / SYSTEM.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttributeSettings {
+    pub track_ownership: bool,
+    #[serde(default)]
+    pub xattrs: Vec<String>,
+}
+
+impl Default for AttributeSettings {
+    fn default() -> Self {
+        Self {
+            track_ownership: true,
+            xattrs: vec!["security.capability".into()],
+        }
+    }
+}
+
+/// Budgets for the service's own resource use, checked every
+/// `check_interval_secs`. Memory is budgeted by `performance.max_memory_mb`
+/// and open descriptors by the process limit. Reaching `warn_percent` of a
+/// budget is logged as `RESOURCE_BUDGET_EXCEEDED`.
+///
+/// Protected trees that would take the realtime watcher past `max_watches`
+/// descriptors are polled instead; `0` uses half the OS watch limit. `0`
+/// disables the baseline and backup store budgets.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceBudgets {
+    pub max_watches: u64,
+    pub max_baseline_mb: u64,
+    pub max_backup_store_mb: u64,
+    pub warn_percent: u8,
+    pub check_interval_secs: u64,
+}
+
+impl Default for ResourceBudgets {
+    fn default() -> Self {
+        Self {
+            max_watches: 0,
+            max_baseline_mb: 256,
+            max_backup_store_mb: 4096,
+            warn_percent: 80,
+            check_interval_secs: 300,
+        }
+    }
+}
+
+/// One step of the response to a tamper event.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EnforcementAction {
+    /// Nothing beyond the detection event, which is always logged.
+    Log,
+    /// Raise a `TAMPER_NOTIFICATION` for the UI and platform.
+    Notify,
+    /// Put the baselined state back: content, permissions, a rename or a
+    /// swapped symlink.
+    Restore,
+    /// Move the offending file aside for inspection.
+    Quarantine,
+    /// Kill processes holding the file open for writing (Linux only).
+    KillWriter,
+    /// Pause enforcement by entering safe mode.
+    SafeMode,
+}
+
+/// How the engine responds to tamper events.
+///
+/// The first rule whose `paths` cover the event (empty covers everything)
+/// and whose `min_severity` it reaches decides the actions, run in order —
+/// so put `kill_writer` before `restore` and `quarantine` before `restore`
+/// to keep the evidence. Events no rule matches get the built-in response:
+/// restore baselined files and quarantine suspicious unknown files. With
+/// `dry_run` nothing is acted on; the planned actions are logged as
+/// `ENFORCEMENT_DRY_RUN` instead.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EnforcementSettings {
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default)]
+    pub rules: Vec<EnforcementRule>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EnforcementRule {
+    #[serde(default)]
+    pub paths: Vec<String>,
+    #[serde(default)]
+    pub min_severity: Option<EventSeverity>,
+    pub actions: Vec<EnforcementAction>,
+}
+
+impl EnforcementSettings {
+    /// Actions of the first rule matching an event at `path`, if any.
+    pub fn actions_for(
+        &self,
+        path: &std::path::Path,
+        severity: &EventSeverity,
+    ) -> Option<&[EnforcementAction]> {
+        self.rules
+            .iter()
+            .find(|rule| {
+                (rule.paths.is_empty() || rule.paths.iter().any(|p| path.starts_with(p)))
+                    && rule.min_severity.as_ref().is_none_or(|min| severity >= min)
+            })
+            .map(|rule| rule.actions.as_slice())
+    }
+}
+
+/// What a Connected-mode device does while it can't reach the platform.
+///
+/// Each threshold counts hours since the last successful heartbeat; `0`
+/// disables that step. Escalated logging records every failed heartbeat in
+/// the event log, a settings lock refuses local settings changes, and safe
+/// mode is only entered under the ZeroTrust profile. All but safe mode lift
+/// once the device reconnects and has uploaded its offline event backlog.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OfflinePolicySettings {
+    pub escalate_logging_after_hours: u32,
+    pub lock_settings_after_hours: u32,
+    pub safe_mode_after_hours: u32,
+}
+
+impl Default for OfflinePolicySettings {
+    fn default() -> Self {
+        Self {
+            escalate_logging_after_hours: 1,
+            lock_settings_after_hours: 12,
+            safe_mode_after_hours: 24,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GuardSettings {
     pub security_mode: SecurityMode,
@@ -42,6 +500,34 @@ pub struct GuardSettings {
     pub performance: PerformanceLimits,
     pub updates: UpdateSettings,
     pub privacy: PrivacySettings,
+    #[serde(default)]
+    pub remote_ipc: RemoteIpcSettings,
+    #[serde(default)]
+    pub snapshots: SnapshotSettings,
+    #[serde(default)]
+    pub ransomware: RansomwareSettings,
+    #[serde(default)]
+    pub offline: OfflinePolicySettings,
+    #[serde(default)]
+    pub restore: RestoreSettings,
+    #[serde(default)]
+    pub hashing: HashingSettings,
+    #[serde(default)]
+    pub ipc: IpcSettings,
+    #[serde(default)]
+    pub enforcement: EnforcementSettings,
+    #[serde(default)]
+    pub resources: ResourceBudgets,
+    #[serde(default)]
+    pub backup_store: BackupStoreSettings,
+    #[serde(default)]
+    pub attributes: AttributeSettings,
+    #[serde(default)]
+    pub flapping: FlapSettings,
+    #[serde(default)]
+    pub privileged_helper: PrivilegedHelperSettings,
+    #[serde(default)]
+    pub scanner_sandbox: ScannerSandboxSettings,
 }
 
 impl Default for GuardSettings {
@@ -53,6 +539,7 @@ impl Default for GuardSettings {
                 baseline_locked: true,
                 protected_paths: vec![],
                 quarantine_enabled: true,
+                symlink_policy: SymlinkPolicy::default(),
             },
             performance: PerformanceLimits {
                 max_cpu_percent: 30,
@@ -61,11 +548,29 @@ impl Default for GuardSettings {
             updates: UpdateSettings {
                 channel: "stable".into(),
                 auto_update: true,
+                platform_url: None,
+                check_interval_hours: default_check_interval_hours(),
+                install_window: None,
+                restart_cmd: None,
             },
             privacy: PrivacySettings {
                 telemetry_enabled: false,
                 crash_reports: true,
             },
+            remote_ipc: RemoteIpcSettings::default(),
+            snapshots: SnapshotSettings::default(),
+            ransomware: RansomwareSettings::default(),
+            offline: OfflinePolicySettings::default(),
+            restore: RestoreSettings::default(),
+            hashing: HashingSettings::default(),
+            ipc: IpcSettings::default(),
+            enforcement: EnforcementSettings::default(),
+            resources: ResourceBudgets::default(),
+            backup_store: BackupStoreSettings::default(),
+            attributes: AttributeSettings::default(),
+            flapping: FlapSettings::default(),
+            privileged_helper: PrivilegedHelperSettings::default(),
+            scanner_sandbox: ScannerSandboxSettings::default(),
         }
     }
 }
@@ -0,0 +1,171 @@
+//! Versioned history of `GuardSettings`.
+//!
+//! Every accepted settings change is kept in the vault as a full copy of the
+//! resulting settings, together with who made it, when, and a field-level
+//! diff against the version before. Any retained version can be restored;
+//! the rollback is itself recorded as a new version.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::settings::GuardSettings;
+
+/// Versions kept; older ones are dropped.
+pub const MAX_SETTINGS_VERSIONS: usize = 50;
+
+/// One changed leaf, addressed by its dotted path, e.g.
+/// `protection.protected_paths`. Arrays are compared whole.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SettingChange {
+    pub field: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsVersion {
+    pub version: u64,
+    pub changed_at: DateTime<Utc>,
+    /// The authenticated client that made the change.
+    pub changed_by: String,
+    /// Free text supplied by the caller, e.g. an operator name or ticket.
+    /// Not authenticated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// The version this one restored, for rollbacks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rollback_of: Option<u64>,
+    /// Difference from the previous version.
+    pub changes: Vec<SettingChange>,
+    pub settings: GuardSettings,
+}
+
+/// Oldest version first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SettingsHistory {
+    pub versions: Vec<SettingsVersion>,
+}
+
+impl SettingsHistory {
+    pub fn get(&self, version: u64) -> Option<&SettingsVersion> {
+        self.versions.iter().find(|v| v.version == version)
+    }
+
+    /// Record `new` replacing `old`. The first record also keeps `old`, so
+    /// the settings from before any recorded change can be restored.
+    /// Returns `None`, recording nothing, if the two are the same.
+    pub fn record(
+        &mut self,
+        old: &GuardSettings,
+        new: &GuardSettings,
+        changed_by: &str,
+        note: Option<&str>,
+        rollback_of: Option<u64>,
+        now: DateTime<Utc>,
+    ) -> Option<&SettingsVersion> {
+        let changes = diff_settings(old, new);
+        if changes.is_empty() {
+            return None;
+        }
+        if self.versions.is_empty() {
+            self.versions.push(SettingsVersion {
+                version: 1,
+                changed_at: now,
+                changed_by: "initial".into(),
+                note: None,
+                rollback_of: None,
+                changes: Vec::new(),
+                settings: old.clone(),
+            });
+        }
+        let version = self.versions.last().map_or(1, |v| v.version + 1);
+        self.versions.push(SettingsVersion {
+            version,
+            changed_at: now,
+            changed_by: changed_by.to_string(),
+            note: note.map(str::to_string),
+            rollback_of,
+            changes,
+            settings: new.clone(),
+        });
+        if self.versions.len() > MAX_SETTINGS_VERSIONS {
+            let excess = self.versions.len() - MAX_SETTINGS_VERSIONS;
+            self.versions.drain(..excess);
+        }
+        self.versions.last()
+    }
+}
+
+/// Field-level differences between two settings.
+pub fn diff_settings(old: &GuardSettings, new: &GuardSettings) -> Vec<SettingChange> {
+    let mut changes = Vec::new();
+    diff_values(
+        "",
+        &serde_json::to_value(old).unwrap_or_default(),
+        &serde_json::to_value(new).unwrap_or_default(),
+        &mut changes,
+    );
+    changes
+}
+
+fn diff_values(prefix: &str, old: &Value, new: &Value, changes: &mut Vec<SettingChange>) {
+    match (old, new) {
+        (Value::Object(a), Value::Object(b)) => {
+            let mut keys: Vec<&String> = a.keys().chain(b.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let field = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                diff_values(
+                    &field,
+                    a.get(key).unwrap_or(&Value::Null),
+                    b.get(key).unwrap_or(&Value::Null),
+                    changes,
+                );
+            }
+        }
+        _ if old != new => changes.push(SettingChange {
+            field: prefix.to_string(),
+            old: old.clone(),
+            new: new.clone(),
+        }),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_changed_fields_and_keeps_the_original() {
+        let original = GuardSettings::default();
+        let mut edited = original.clone();
+        edited.protection.protected_paths = vec!["/etc/app".into()];
+        edited.flapping.flap_threshold += 1;
+
+        let mut history = SettingsHistory::default();
+        assert!(history
+            .record(&original, &original, "local:ui", None, None, Utc::now())
+            .is_none());
+
+        let version = history
+            .record(&original, &edited, "local:ui", Some("tuning"), None, Utc::now())
+            .unwrap();
+        assert_eq!(version.version, 2);
+        let fields: Vec<&str> = version.changes.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            ["flapping.flap_threshold", "protection.protected_paths"]
+        );
+
+        let initial = history.get(1).unwrap();
+        assert_eq!(initial.changed_by, "initial");
+        assert!(diff_settings(&initial.settings, &original).is_empty());
+    }
+}
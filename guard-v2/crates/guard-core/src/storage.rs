@@ -1,7 +1,19 @@
+use crate::exclusion::PathExclusion;
+use crate::policy::SignedPolicyBundle;
+use crate::sbom::SbomBinding;
 use crate::settings::GuardSettings;
+use crate::settings_history::SettingsHistory;
 use crate::vault::Vault;
 
 const SETTINGS_KEY: &str = "guard.settings";
+const SETTINGS_HISTORY_KEY: &str = "guard.settings.history";
+const POLICY_ORG_KEY: &str = "guard.policy.org_key";
+const POLICY_BUNDLE_KEY: &str = "guard.policy.bundle";
+const POLICY_HIGHEST_VERSION_KEY: &str = "guard.policy.highest_version";
+const EXCLUSIONS_KEY: &str = "guard.exclusions";
+const SBOM_KEY: &str = "guard.sbom";
+const BASELINE_OPERATOR_KEY: &str = "guard.baseline.operator_key";
+const BASELINE_OPERATOR_KEY_CHANGES: &str = "guard.baseline.operator_key_changes";
 
 pub fn load_settings(vault: &Vault) -> anyhow::Result<GuardSettings> {
     if let Some(bytes) = vault.get(SETTINGS_KEY)? {
@@ -16,3 +28,101 @@ pub fn save_settings(vault: &mut Vault, settings: &GuardSettings) -> anyhow::Res
     vault.set(SETTINGS_KEY, &data)?;
     Ok(())
 }
+
+pub fn load_settings_history(vault: &Vault) -> anyhow::Result<SettingsHistory> {
+    match vault.get(SETTINGS_HISTORY_KEY)? {
+        Some(bytes) => Ok(serde_json::from_slice(&bytes)?),
+        None => Ok(SettingsHistory::default()),
+    }
+}
+
+pub fn save_settings_history(vault: &mut Vault, history: &SettingsHistory) -> anyhow::Result<()> {
+    let data = serde_json::to_vec(history)?;
+    vault.set(SETTINGS_HISTORY_KEY, &data)
+}
+
+/// base64 ed25519 public key that policy bundles must be signed with.
+pub fn load_policy_org_key(vault: &Vault) -> anyhow::Result<Option<String>> {
+    Ok(vault
+        .get(POLICY_ORG_KEY)?
+        .map(|b| String::from_utf8_lossy(&b).into_owned()))
+}
+
+pub fn save_policy_org_key(vault: &mut Vault, key_b64: &str) -> anyhow::Result<()> {
+    vault.set(POLICY_ORG_KEY, key_b64.as_bytes())
+}
+
+pub fn load_policy_bundle(vault: &Vault) -> anyhow::Result<Option<SignedPolicyBundle>> {
+    match vault.get(POLICY_BUNDLE_KEY)? {
+        Some(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+        None => Ok(None),
+    }
+}
+
+pub fn save_policy_bundle(vault: &mut Vault, bundle: &SignedPolicyBundle) -> anyhow::Result<()> {
+    let data = serde_json::to_vec(bundle)?;
+    vault.set(POLICY_BUNDLE_KEY, &data)
+}
+
+/// Highest policy bundle version ever accepted, so an older bundle can't be
+/// replayed once the current one expires.
+pub fn load_policy_highest_version(vault: &Vault) -> anyhow::Result<Option<u64>> {
+    match vault.get(POLICY_HIGHEST_VERSION_KEY)? {
+        Some(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+        None => Ok(None),
+    }
+}
+
+pub fn save_policy_highest_version(vault: &mut Vault, version: u64) -> anyhow::Result<()> {
+    vault.set(POLICY_HIGHEST_VERSION_KEY, &serde_json::to_vec(&version)?)
+}
+
+pub fn load_exclusions(vault: &Vault) -> anyhow::Result<Vec<PathExclusion>> {
+    match vault.get(EXCLUSIONS_KEY)? {
+        Some(bytes) => Ok(serde_json::from_slice(&bytes)?),
+        None => Ok(Vec::new()),
+    }
+}
+
+pub fn save_exclusions(vault: &mut Vault, exclusions: &[PathExclusion]) -> anyhow::Result<()> {
+    let data = serde_json::to_vec(exclusions)?;
+    vault.set(EXCLUSIONS_KEY, &data)
+}
+
+pub fn load_sbom_bindings(vault: &Vault) -> anyhow::Result<Vec<SbomBinding>> {
+    match vault.get(SBOM_KEY)? {
+        Some(bytes) => Ok(serde_json::from_slice(&bytes)?),
+        None => Ok(Vec::new()),
+    }
+}
+
+pub fn save_sbom_bindings(vault: &mut Vault, bindings: &[SbomBinding]) -> anyhow::Result<()> {
+    let data = serde_json::to_vec(bindings)?;
+    vault.set(SBOM_KEY, &data)
+}
+
+/// Operator public key (base64) required to countersign baselines; `None`
+/// when dual control is off.
+pub fn load_baseline_operator_key(vault: &Vault) -> anyhow::Result<Option<String>> {
+    Ok(vault
+        .get(BASELINE_OPERATOR_KEY)?
+        .filter(|b| !b.is_empty())
+        .map(|b| String::from_utf8_lossy(&b).into_owned()))
+}
+
+pub fn save_baseline_operator_key(vault: &mut Vault, key_b64: Option<&str>) -> anyhow::Result<()> {
+    vault.set(BASELINE_OPERATOR_KEY, key_b64.unwrap_or_default().as_bytes())
+}
+
+/// How many times the operator key has been set, rotated or cleared. Key
+/// change authorizations sign it, so each one is good for a single change.
+pub fn load_baseline_operator_key_changes(vault: &Vault) -> anyhow::Result<u64> {
+    match vault.get(BASELINE_OPERATOR_KEY_CHANGES)? {
+        Some(bytes) => Ok(serde_json::from_slice(&bytes)?),
+        None => Ok(0),
+    }
+}
+
+pub fn save_baseline_operator_key_changes(vault: &mut Vault, changes: u64) -> anyhow::Result<()> {
+    vault.set(BASELINE_OPERATOR_KEY_CHANGES, &serde_json::to_vec(&changes)?)
+}
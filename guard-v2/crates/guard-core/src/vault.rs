@@ -67,6 +67,12 @@ pub struct VaultState {
     pub tour_completed: bool,
     pub last_update_check: Option<DateTime<Utc>>,
     pub installed_version: String,
+    /// Last successful platform heartbeat in Connected mode.
+    #[serde(default)]
+    pub last_heartbeat: Option<DateTime<Utc>>,
+    /// Start of the current offline period; `None` while the platform is reachable.
+    #[serde(default)]
+    pub offline_since: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -173,6 +179,8 @@ impl Vault {
                 tour_completed: false,
                 last_update_check: None,
                 installed_version: "0.0.0".to_string(),
+                last_heartbeat: None,
+                offline_since: None,
             },
             nonce_cache: vec![],
             ipc_shared_secret: ipc_secret,
@@ -392,6 +400,8 @@ fn default_state() -> VaultState {
         tour_completed: false,
         last_update_check: None,
         installed_version: "0.0.0".to_string(),
+        last_heartbeat: None,
+        offline_since: None,
     }
 }
 
@@ -0,0 +1,230 @@
+use anyhow::Result;
+use guard_core::ipc::{IpcHandler, IpcRequest, IpcResponse, IpcServer, RemoteConnectionEvent};
+use guard_core::ipc_audit::{ClientIdentity, RateLimits, RequestAudit, RequestResult};
+use guard_core::ipc_client::{send_remote_request, RemoteClientConfig};
+use guard_core::settings::RemoteIpcSettings;
+use parking_lot::Mutex;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+const SECRET: &[u8] = b"remote-ipc-test-secret";
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/remote_ipc")
+        .join(name)
+}
+
+#[derive(Default)]
+struct RecordingHandler {
+    audit: Mutex<Vec<RemoteConnectionEvent>>,
+    requests: Mutex<Vec<RequestAudit>>,
+    handled: Mutex<Vec<ClientIdentity>>,
+    limits: Option<RateLimits>,
+}
+
+#[async_trait::async_trait]
+impl IpcHandler for RecordingHandler {
+    async fn handle(&self, req: IpcRequest, client: &ClientIdentity) -> Result<IpcResponse> {
+        self.handled.lock().push(client.clone());
+        match req {
+            IpcRequest::GetStatus => Ok(IpcResponse::Status {
+                ok: true,
+                health: None,
+            }),
+            _ => Err(anyhow::anyhow!("unsupported request")),
+        }
+    }
+
+    async fn enter_safe_mode(&self, _reason: String) -> Result<IpcResponse> {
+        Ok(IpcResponse::SafeModeEntered)
+    }
+
+    async fn exit_safe_mode(&self, _password: String) -> Result<IpcResponse> {
+        Ok(IpcResponse::SafeModeExited)
+    }
+
+    async fn audit_remote(&self, event: RemoteConnectionEvent) {
+        self.audit.lock().push(event);
+    }
+
+    async fn audit_request(&self, audit: RequestAudit) {
+        self.requests.lock().push(audit);
+    }
+
+    fn rate_limits(&self) -> Option<RateLimits> {
+        self.limits
+    }
+}
+
+fn free_addr() -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().to_string()
+}
+
+async fn start_server(require_client_cert: bool) -> (String, Arc<RecordingHandler>) {
+    start_server_with(require_client_cert, RecordingHandler::default()).await
+}
+
+async fn start_server_with(
+    require_client_cert: bool,
+    handler: RecordingHandler,
+) -> (String, Arc<RecordingHandler>) {
+    let addr = free_addr();
+    let settings = RemoteIpcSettings {
+        enabled: true,
+        bind_addr: addr.clone(),
+        cert_path: Some(fixture("server.pem").to_string_lossy().into_owned()),
+        key_path: Some(fixture("server.key").to_string_lossy().into_owned()),
+        client_ca_path: require_client_cert
+            .then(|| fixture("ca.pem").to_string_lossy().into_owned()),
+    };
+    let handler = Arc::new(handler);
+    let server = Arc::new(IpcServer::new(SECRET.to_vec(), PathBuf::from("unused.sock")));
+    let h: Arc<dyn IpcHandler + Send + Sync> = handler.clone();
+    tokio::spawn(server.start_remote(settings, h));
+    for _ in 0..50 {
+        if tokio::net::TcpStream::connect(&addr).await.is_ok() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    (addr, handler)
+}
+
+fn client(addr: &str, with_cert: bool) -> RemoteClientConfig {
+    RemoteClientConfig {
+        addr: addr.to_string(),
+        server_name: "guard.local".to_string(),
+        ca_path: fixture("ca.pem"),
+        client_cert_path: with_cert.then(|| fixture("client.pem")),
+        client_key_path: with_cert.then(|| fixture("client.key")),
+    }
+}
+
+async fn wait_for_audit(handler: &RecordingHandler, count: usize) -> Vec<RemoteConnectionEvent> {
+    for _ in 0..100 {
+        let events = handler.audit.lock().clone();
+        if events.len() >= count {
+            return events;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    handler.audit.lock().clone()
+}
+
+#[tokio::test]
+async fn mutual_tls_request_round_trip_is_audited() {
+    let (addr, handler) = start_server(true).await;
+    let resp = send_remote_request(&client(&addr, true), SECRET, IpcRequest::GetStatus)
+        .await
+        .unwrap();
+    assert!(matches!(resp, IpcResponse::Status { ok: true, .. }));
+
+    // The readiness probe in start_server shows up as a rejected handshake;
+    // only look at what follows it.
+    let events = wait_for_audit(&handler, 3).await;
+    let tail: Vec<_> = events
+        .iter()
+        .skip_while(|e| matches!(e, RemoteConnectionEvent::Rejected { .. }))
+        .collect();
+    assert!(matches!(
+        tail.first(),
+        Some(RemoteConnectionEvent::Authenticated { client_cert: Some(_), .. })
+    ));
+    assert!(matches!(
+        tail.get(1),
+        Some(RemoteConnectionEvent::Closed { requests: 1, .. })
+    ));
+}
+
+#[tokio::test]
+async fn wrong_secret_is_rejected() {
+    let (addr, handler) = start_server(true).await;
+    let result =
+        send_remote_request(&client(&addr, true), b"wrong-secret", IpcRequest::GetStatus).await;
+    assert!(result.is_err());
+
+    let events = wait_for_audit(&handler, 2).await;
+    assert!(events.iter().any(|e| matches!(
+        e,
+        RemoteConnectionEvent::Rejected { reason, .. } if reason.contains("invalid proof")
+    )));
+    assert!(!events
+        .iter()
+        .any(|e| matches!(e, RemoteConnectionEvent::Authenticated { .. })));
+}
+
+#[tokio::test]
+async fn missing_client_certificate_is_rejected_when_ca_configured() {
+    let (addr, _handler) = start_server(true).await;
+    let result = send_remote_request(&client(&addr, false), SECRET, IpcRequest::GetStatus).await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn secret_alone_authenticates_without_client_ca() {
+    let (addr, _handler) = start_server(false).await;
+    let resp = send_remote_request(&client(&addr, false), SECRET, IpcRequest::GetStatus)
+        .await
+        .unwrap();
+    assert!(matches!(resp, IpcResponse::Status { ok: true, .. }));
+}
+
+#[tokio::test]
+async fn requests_over_budget_are_refused_and_audited() {
+    let handler = RecordingHandler {
+        limits: Some(RateLimits {
+            requests_per_minute: 2,
+            scans_per_minute: 0,
+        }),
+        ..Default::default()
+    };
+    let (addr, handler) = start_server_with(true, handler).await;
+    let config = client(&addr, true);
+    // Each request is a new connection; the budget follows the certificate.
+    for _ in 0..2 {
+        send_remote_request(&config, SECRET, IpcRequest::GetStatus)
+            .await
+            .unwrap();
+    }
+    let err = send_remote_request(&config, SECRET, IpcRequest::GetStatus)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("rate limited"), "{err}");
+
+    let audits = handler.requests.lock().clone();
+    assert_eq!(audits.len(), 3);
+    assert!(audits.iter().all(|a| a.request == "GetStatus" && a.read_only));
+    assert!(audits[0].client.client_cert.is_some());
+    assert_eq!(audits[0].client.transport, "remote");
+    assert!(matches!(audits[1].result, RequestResult::Ok));
+    assert!(matches!(audits[2].result, RequestResult::RateLimited { .. }));
+    // The handler sees the same identity the request is audited under.
+    assert_eq!(handler.handled.lock()[..], [audits[0].client.clone(), audits[1].client.clone()]);
+}
+
+#[tokio::test]
+async fn path_requests_are_refused_to_remote_clients() {
+    let (addr, handler) = start_server(true).await;
+    let config = client(&addr, true);
+    let requests = [
+        IpcRequest::RestoreNow {
+            path: "/etc/passwd".into(),
+        },
+        IpcRequest::SnapshotRestore { path: "/etc".into() },
+        IpcRequest::EstimatePath { path: "/root".into() },
+    ];
+    for request in requests {
+        assert!(send_remote_request(&config, SECRET, request).await.is_err());
+    }
+    // Refused before reaching the handler, and audited with the reason.
+    assert!(handler.handled.lock().is_empty());
+    let audits = handler.requests.lock().clone();
+    assert_eq!(audits.len(), 3);
+    assert!(audits.iter().all(|a| matches!(
+        &a.result,
+        RequestResult::Error { message } if message.contains("only served to local clients")
+    )));
+}
@@ -0,0 +1,221 @@
+//! Cold-start attestation of the service's own state files.
+//!
+//! On a clean shutdown the service hashes the vault and the baseline and
+//! stores the digests in the OS keyring. The next start takes that record
+//! out of the keyring before anything writes to either file and compares.
+//! A mismatch means a file changed while the service wasn't running — an
+//! older vault copied back, or a baseline edited to bless a tampered file —
+//! so `VAULT_TAMPER_SUSPECTED` is logged and the service starts in safe
+//! mode. The record is kept until safe mode is exited; only then does a
+//! clean shutdown replace it. Without a record — first start, a crash, or
+//! no keyring — `VAULT_ATTESTATION_UNAVAILABLE` is logged, unless one is
+//! expected: once a record has been stored (a marker in the keyring that is
+//! never removed) or under the Zero-Trust profile, a missing record counts
+//! as a mismatch. Otherwise `kill -9`, offline edits and a restart would
+//! skip attestation altogether.
+//!
+//! The keyring (Keychain, Credential Manager, Secret Service) is the only
+//! anchor; nothing is sealed to a TPM.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use guard_core::secure_storage::{
+    mark_state_attested, store_state_attestation, take_state_attestation, was_state_attested,
+};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct StateDigests {
+    pub vault: String,
+    /// `None` when there was no baseline.
+    pub baseline: Option<String>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+impl StateDigests {
+    pub fn measure(vault_path: &Path, baseline_path: &Path) -> Result<Self> {
+        let digest = |p: &Path| -> Result<String> {
+            Ok(blake3::hash(&std::fs::read(p)?).to_hex().to_string())
+        };
+        Ok(Self {
+            vault: digest(vault_path)?,
+            baseline: baseline_path
+                .exists()
+                .then(|| digest(baseline_path))
+                .transpose()?,
+            recorded_at: Utc::now(),
+        })
+    }
+
+    /// Files whose digest differs from `recorded`.
+    pub fn mismatches(&self, recorded: &StateDigests) -> Vec<&'static str> {
+        let mut files = Vec::new();
+        if self.vault != recorded.vault {
+            files.push("vault");
+        }
+        if self.baseline != recorded.baseline {
+            files.push("baseline");
+        }
+        files
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Attestation {
+    Verified {
+        recorded_at: DateTime<Utc>,
+    },
+    /// Nothing to compare against: first start, a crash, or no keyring.
+    Unavailable {
+        reason: String,
+    },
+    Mismatch {
+        files: Vec<&'static str>,
+        /// `None` when the record itself couldn't be read.
+        recorded_at: Option<DateTime<Utc>>,
+    },
+}
+
+impl Attestation {
+    /// `record_expected`: a missing record is itself suspect.
+    fn compare(
+        current: &StateDigests,
+        recorded: Option<&StateDigests>,
+        record_expected: bool,
+    ) -> Self {
+        let Some(recorded) = recorded else {
+            if record_expected {
+                return Attestation::Mismatch {
+                    files: vec!["attestation record"],
+                    recorded_at: None,
+                };
+            }
+            return Attestation::Unavailable {
+                reason: "no record from a clean shutdown".into(),
+            };
+        };
+        let files = current.mismatches(recorded);
+        if files.is_empty() {
+            Attestation::Verified {
+                recorded_at: recorded.recorded_at,
+            }
+        } else {
+            Attestation::Mismatch {
+                files,
+                recorded_at: Some(recorded.recorded_at),
+            }
+        }
+    }
+}
+
+/// Compare the state files against the record of the last clean shutdown,
+/// consuming the record. `zero_trust` makes a missing record a mismatch even
+/// before one has ever been stored.
+pub(crate) fn check_at_start(
+    device_id: &str,
+    vault_path: &Path,
+    baseline_path: &Path,
+    zero_trust: bool,
+) -> Attestation {
+    let recorded = match take_state_attestation(device_id) {
+        Ok(record) => record,
+        Err(e) => {
+            return Attestation::Unavailable {
+                reason: format!("{e:#}"),
+            }
+        }
+    };
+    let Ok(recorded) = recorded
+        .as_deref()
+        .map(serde_json::from_str::<StateDigests>)
+        .transpose()
+    else {
+        // An unreadable record is as suspect as a mismatching one.
+        return Attestation::Mismatch {
+            files: vec!["attestation record"],
+            recorded_at: None,
+        };
+    };
+    // The keyring just answered, so a failure here is as suspect as a set marker.
+    let record_expected = zero_trust || was_state_attested(device_id).unwrap_or(true);
+    let attestation = match StateDigests::measure(vault_path, baseline_path) {
+        Ok(current) => Attestation::compare(&current, recorded.as_ref(), record_expected),
+        Err(e) => Attestation::Unavailable {
+            reason: format!("measure state files: {e:#}"),
+        },
+    };
+    // Keep the record until the operator has dealt with the mismatch, so a
+    // restart doesn't quietly accept the changed files.
+    if let (Attestation::Mismatch { .. }, Some(recorded)) = (&attestation, &recorded) {
+        if let Ok(record) = serde_json::to_string(recorded) {
+            let _ = store_state_attestation(device_id, &record);
+        }
+    }
+    attestation
+}
+
+/// Record the state files' digests for the next start. Not called until
+/// safe mode is exited after a mismatch (`SafeModeState::attestation_mismatch`);
+/// see `check_at_start`.
+pub(crate) fn record_at_shutdown(
+    device_id: &str,
+    vault_path: &Path,
+    baseline_path: &Path,
+) -> Result<()> {
+    let digests = StateDigests::measure(vault_path, baseline_path)?;
+    store_state_attestation(device_id, &serde_json::to_string(&digests)?)?;
+    mark_state_attested(device_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offline_changes_are_reported_per_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let vault = dir.path().join("vault.dat");
+        let baseline = dir.path().join("baseline.json");
+        std::fs::write(&vault, b"vault v2").unwrap();
+        std::fs::write(&baseline, b"{}").unwrap();
+        let recorded = StateDigests::measure(&vault, &baseline).unwrap();
+
+        let current = StateDigests::measure(&vault, &baseline).unwrap();
+        assert!(matches!(
+            Attestation::compare(&current, Some(&recorded), true),
+            Attestation::Verified { .. }
+        ));
+        assert!(matches!(
+            Attestation::compare(&current, None, false),
+            Attestation::Unavailable { .. }
+        ));
+
+        // An older vault copied back and the baseline removed.
+        std::fs::write(&vault, b"vault v1").unwrap();
+        std::fs::remove_file(&baseline).unwrap();
+        let current = StateDigests::measure(&vault, &baseline).unwrap();
+        match Attestation::compare(&current, Some(&recorded), true) {
+            Attestation::Mismatch { files, .. } => assert_eq!(files, ["vault", "baseline"]),
+            other => panic!("expected mismatch, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn missing_record_is_a_mismatch_once_one_is_expected() {
+        let dir = tempfile::tempdir().unwrap();
+        let vault = dir.path().join("vault.dat");
+        std::fs::write(&vault, b"vault").unwrap();
+        let current = StateDigests::measure(&vault, &dir.path().join("baseline.json")).unwrap();
+
+        // Killed before the shutdown record was written, after one was
+        // stored before (or under Zero-Trust): the files can't be vouched for.
+        match Attestation::compare(&current, None, true) {
+            Attestation::Mismatch { files, recorded_at } => {
+                assert_eq!(files, ["attestation record"]);
+                assert_eq!(recorded_at, None);
+            }
+            other => panic!("expected mismatch, got {other:?}"),
+        }
+    }
+}
@@ -1,5 +1,8 @@
 use crate::connected::commands::ServerCommand;
 use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use guard_core::command_receipt::SignedCommandReceipt;
+use guard_core::event_log::EventEntry;
 use reqwest::StatusCode;
 use serde_json::Value;
 
@@ -38,6 +41,30 @@ impl ApiClient {
         Err(anyhow!("heartbeat failed with status {}", res.status()))
     }
 
+    /// Upload events recorded while the platform was unreachable.
+    pub async fn upload_events(
+        &self,
+        device_id: &str,
+        offline_since: DateTime<Utc>,
+        events: &[EventEntry],
+    ) -> Result<()> {
+        let url = format!("{}/api/devices/{}/events", self.base_url, device_id);
+        let res = self
+            .client
+            .post(url)
+            .bearer_auth(&self.token)
+            .json(&serde_json::json!({
+                "offline_since": offline_since,
+                "events": events,
+            }))
+            .send()
+            .await?;
+        if res.status().is_success() {
+            return Ok(());
+        }
+        Err(anyhow!("event upload failed: {}", res.status()))
+    }
+
     pub async fn fetch_pending_commands(&self, device_id: &str) -> Result<Vec<ServerCommand>> {
         let url = format!(
             "{}/api/devices/{}/pending-commands",
@@ -74,6 +101,7 @@ impl ApiClient {
         signature: Option<String>,
         result: Option<Value>,
         error: Option<String>,
+        receipt: Option<&SignedCommandReceipt>,
     ) -> Result<()> {
         let url = format!(
             "{}/api/devices/{}/commands/{}/result",
@@ -88,7 +116,8 @@ impl ApiClient {
                 "nonce": nonce,
                 "signature": signature.unwrap_or_default(),
                 "result": result,
-                "error": error
+                "error": error,
+                "receipt": receipt,
             }))
             .send()
             .await?;
@@ -97,4 +126,27 @@ impl ApiClient {
         }
         Err(anyhow!("submit result failed: {}", res.status()))
     }
+
+    /// Deliver receipts the platform didn't acknowledge with their result.
+    pub async fn submit_receipts(
+        &self,
+        device_id: &str,
+        receipts: &[SignedCommandReceipt],
+    ) -> Result<()> {
+        let url = format!(
+            "{}/api/devices/{}/command-receipts",
+            self.base_url, device_id
+        );
+        let res = self
+            .client
+            .post(url)
+            .bearer_auth(&self.token)
+            .json(&serde_json::json!({ "receipts": receipts }))
+            .send()
+            .await?;
+        if res.status().is_success() {
+            return Ok(());
+        }
+        Err(anyhow!("receipt upload failed: {}", res.status()))
+    }
 }
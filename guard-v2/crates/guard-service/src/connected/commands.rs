@@ -3,6 +3,7 @@ use crate::connected::verifier::{canonical_result_message, Verifier};
 use crate::service_state::ServiceState;
 use anyhow::Result;
 use chrono::{DateTime, Utc};
+use guard_core::command_receipt::{CommandReceipt, SignedCommandReceipt};
 use guard_core::device_state::RemoteActivityStatus;
 use guard_core::event_log::EventSeverity;
 use guard_core::safe_mode::SafeModeReason;
@@ -103,16 +104,19 @@ pub fn spawn_command_loop(
         let mut ticker = time::interval(Duration::from_secs(15));
         loop {
             ticker.tick().await;
+            resend_receipts(&client, &device_id, &state).await;
             match client.fetch_pending_commands(&device_id).await {
                 Ok(commands) => {
                     for cmd in commands {
+                        let received_at = Utc::now();
                         match validate_command(&cmd, &security_profile, &mut nonce_book, &verifier)
                         {
                             Ok(_) => {
                                 info!(id = %cmd.id, cmd = %cmd.command, "command validated");
                                 record_remote_activity(&state, &cmd, RemoteActivityStatus::Pending);
                                 if let Err(err) =
-                                    execute_command(&client, &device_id, &cmd, &state).await
+                                    execute_command(&client, &device_id, &cmd, &state, received_at)
+                                        .await
                                 {
                                     warn!(error = %err, "failed to execute command");
                                 }
@@ -124,8 +128,15 @@ pub fn spawn_command_loop(
                                     &cmd,
                                     RemoteActivityStatus::Rejected,
                                 );
-                                let _ = reject_command(&client, &device_id, &cmd, &state, &reason)
-                                    .await;
+                                let _ = reject_command(
+                                    &client,
+                                    &device_id,
+                                    &cmd,
+                                    &state,
+                                    received_at,
+                                    &reason,
+                                )
+                                .await;
                                 record_command_event(&state, "COMMAND_REJECTED", &cmd.id, &reason);
                             }
                         }
@@ -163,21 +174,17 @@ async fn reject_command(
     device_id: &str,
     cmd: &ServerCommand,
     state: &Arc<Mutex<ServiceState>>,
+    received_at: DateTime<Utc>,
     reason: &CommandValidationError,
 ) -> Result<()> {
     let payload = serde_json::json!({"error": format!("rejected: {:?}", reason)});
-    let signature = sign_result(device_id, cmd, "rejected", &payload, state)?;
-    client
-        .submit_result(
-            device_id,
-            &cmd.id,
-            "rejected",
-            &cmd.nonce,
-            Some(signature),
-            Some(payload),
-            Some(format!("rejected: {:?}", reason)),
-        )
-        .await
+    let outcome = Outcome {
+        status: "rejected",
+        payload,
+        error: Some(format!("rejected: {:?}", reason)),
+        received_at,
+    };
+    finish_command(client, device_id, cmd, state, outcome).await
 }
 
 async fn execute_command(
@@ -185,11 +192,16 @@ async fn execute_command(
     device_id: &str,
     cmd: &ServerCommand,
     state: &Arc<Mutex<ServiceState>>,
+    received_at: DateTime<Utc>,
 ) -> Result<()> {
     let result = match cmd.command.as_str() {
-        "ENTER_SAFE_MODE" => execute_enter_safe_mode(client, device_id, cmd, state).await,
-        "REQUEST_LOGS" => reply_execution_not_implemented(client, device_id, cmd, state).await,
-        _ => reply_execution_not_implemented(client, device_id, cmd, state).await,
+        "ENTER_SAFE_MODE" => {
+            execute_enter_safe_mode(client, device_id, cmd, state, received_at).await
+        }
+        "REQUEST_LOGS" => {
+            reply_execution_not_implemented(client, device_id, cmd, state, received_at).await
+        }
+        _ => reply_execution_not_implemented(client, device_id, cmd, state, received_at).await,
     };
 
     match result {
@@ -205,6 +217,7 @@ async fn execute_enter_safe_mode(
     device_id: &str,
     cmd: &ServerCommand,
     state: &Arc<Mutex<ServiceState>>,
+    received_at: DateTime<Utc>,
 ) -> Result<()> {
     {
         let mut guard = state.lock();
@@ -222,19 +235,13 @@ async fn execute_enter_safe_mode(
     }
 
     info!(command_id = %cmd.id, "safe mode entered via remote command");
-    let payload = serde_json::json!({"safe_mode": true, "reason": "REMOTE_COMMAND"});
-    let signature = sign_result(device_id, cmd, "succeeded", &payload, state)?;
-    client
-        .submit_result(
-            device_id,
-            &cmd.id,
-            "succeeded",
-            &cmd.nonce,
-            Some(signature),
-            Some(payload),
-            None,
-        )
-        .await
+    let outcome = Outcome {
+        status: "succeeded",
+        payload: serde_json::json!({"safe_mode": true, "reason": "REMOTE_COMMAND"}),
+        error: None,
+        received_at,
+    };
+    finish_command(client, device_id, cmd, state, outcome).await
 }
 
 async fn reply_execution_not_implemented(
@@ -242,20 +249,94 @@ async fn reply_execution_not_implemented(
     device_id: &str,
     cmd: &ServerCommand,
     state: &Arc<Mutex<ServiceState>>,
+    received_at: DateTime<Utc>,
 ) -> Result<()> {
-    let payload = serde_json::json!({"error": "execution_not_implemented"});
-    let signature = sign_result(device_id, cmd, "failed", &payload, state)?;
+    let outcome = Outcome {
+        status: "failed",
+        payload: serde_json::json!({"error": "execution_not_implemented"}),
+        error: None,
+        received_at,
+    };
+    finish_command(client, device_id, cmd, state, outcome).await
+}
+
+/// How a received command ended.
+struct Outcome {
+    status: &'static str,
+    payload: Value,
+    error: Option<String>,
+    received_at: DateTime<Utc>,
+}
+
+/// Sign the result and a receipt for `cmd`, record the receipt locally and
+/// submit both to the platform. A receipt the platform doesn't acknowledge
+/// stays undelivered and is resent on the next poll.
+async fn finish_command(
+    client: &ApiClient,
+    device_id: &str,
+    cmd: &ServerCommand,
+    state: &Arc<Mutex<ServiceState>>,
+    outcome: Outcome,
+) -> Result<()> {
+    let signature = sign_result(device_id, cmd, outcome.status, &outcome.payload, state)?;
+    let receipt = CommandReceipt {
+        device_id: device_id.to_string(),
+        command_id: cmd.id.clone(),
+        command: cmd.command.clone(),
+        nonce: cmd.nonce.clone(),
+        status: outcome.status.to_string(),
+        result: outcome.payload.clone(),
+        error: outcome.error.clone(),
+        received_at: outcome.received_at,
+        completed_at: Utc::now(),
+    };
+    let (log, receipt) = {
+        let guard = state.lock();
+        let receipt = SignedCommandReceipt::sign(receipt, &guard.signing_key)?;
+        let _ = guard.event_log.append(
+            "COMMAND_RECEIPT",
+            EventSeverity::Info,
+            serde_json::json!({
+                "command_id": cmd.id,
+                "command": cmd.command,
+                "status": outcome.status,
+                "signature": receipt.signature,
+            }),
+        );
+        guard.command_receipts.record(receipt.clone())?;
+        (guard.command_receipts.clone(), receipt)
+    };
     client
         .submit_result(
             device_id,
             &cmd.id,
-            "failed",
+            outcome.status,
             &cmd.nonce,
             Some(signature),
-            Some(payload),
-            None,
+            Some(outcome.payload),
+            outcome.error,
+            Some(&receipt),
         )
-        .await
+        .await?;
+    log.mark_delivered(std::slice::from_ref(&cmd.id))
+}
+
+/// Resend receipts whose delivery failed earlier.
+async fn resend_receipts(client: &ApiClient, device_id: &str, state: &Arc<Mutex<ServiceState>>) {
+    let log = state.lock().command_receipts.clone();
+    let pending = log.undelivered();
+    if pending.is_empty() {
+        return;
+    }
+    match client.submit_receipts(device_id, &pending).await {
+        Ok(()) => {
+            let ids: Vec<String> = pending.into_iter().map(|r| r.receipt.command_id).collect();
+            if let Err(err) = log.mark_delivered(&ids) {
+                warn!(error = %err, "failed to record receipt delivery");
+            }
+        }
+        Err(err) => warn!(error = %err, count = pending.len(), "receipt resend failed"),
+    }
 }
 
 fn sign_result(
@@ -1,15 +1,22 @@
 use chrono::Utc;
+use guard_core::event_log::EventSeverity;
+use guard_core::safe_mode::SafeModeReason;
 use parking_lot::Mutex;
 use std::sync::Arc;
 use tokio::{
     task::JoinHandle,
     time::{self, Duration},
 };
-use tracing::warn;
+use tracing::{info, warn};
 
 use super::api_client::ApiClient;
+use super::offline::{upload_backlog, OfflineStatus, OfflineStep};
 use crate::service_state::ServiceState;
 
+/// How often a successful heartbeat is written to the vault. The offline
+/// policy counts in hours, so a restored heartbeat this stale is harmless.
+const PERSIST_HEARTBEAT_EVERY_MINS: i64 = 5;
+
 pub fn spawn_heartbeat_loop(
     client: ApiClient,
     device_id: String,
@@ -21,24 +28,168 @@ pub fn spawn_heartbeat_loop(
             ticker.tick().await;
             match client.send_heartbeat(&device_id).await {
                 Ok(_) => {
-                    let mut guard = state.lock();
-                    guard.connected = true;
-                    guard.last_heartbeat = Some(Utc::now());
-                    guard
-                        .event_log
-                        .append(
-                            "HEARTBEAT_SENT",
-                            guard_core::event_log::EventSeverity::Info,
-                            serde_json::json!({"device_id": device_id}),
-                        )
-                        .ok();
+                    let (offline_since, event_log) = {
+                        let mut guard = state.lock();
+                        let now = Utc::now();
+                        guard.connected = true;
+                        guard.last_heartbeat = Some(now);
+                        let persisted = guard.vault.payload.state.last_heartbeat;
+                        if persisted.is_none_or(|at| {
+                            now - at >= chrono::Duration::minutes(PERSIST_HEARTBEAT_EVERY_MINS)
+                        }) {
+                            persist_offline_clock(&mut guard);
+                        }
+                        guard
+                            .event_log
+                            .append(
+                                "HEARTBEAT_SENT",
+                                EventSeverity::Info,
+                                serde_json::json!({"device_id": device_id}),
+                            )
+                            .ok();
+                        (guard.offline.since, guard.event_log.clone())
+                    };
+                    if let Some(since) = offline_since {
+                        reconnect(&client, &device_id, &state, since, &event_log).await;
+                    }
                 }
                 Err(err) => {
                     warn!(error = %err, "heartbeat failed");
                     let mut guard = state.lock();
                     guard.connected = false;
+                    apply_offline_policy(&mut guard, &err.to_string());
                 }
             }
         }
     })
 }
+
+/// Restore the offline clock persisted before a restart and re-apply the
+/// policy steps it has already passed.
+pub(super) fn resume_offline_clock(st: &mut ServiceState) {
+    let now = Utc::now();
+    st.last_heartbeat = st.vault.payload.state.last_heartbeat;
+    let policy = st.engine.settings().offline;
+    let profile = st.vault.payload.security_profile.clone();
+    let (offline, steps) = OfflineStatus::resume(&st.vault.payload.state, &policy, &profile, now);
+    st.offline = offline;
+    let Some(since) = st.offline.since else {
+        return;
+    };
+    let offline_secs = (now - since).num_seconds();
+    st.event_log
+        .append(
+            "CONNECTED_OFFLINE_RESUMED",
+            EventSeverity::Warn,
+            serde_json::json!({"since": since, "offline_secs": offline_secs}),
+        )
+        .ok();
+    apply_steps(st, steps, offline_secs);
+}
+
+/// Start or continue the offline clock and apply any policy steps now due.
+fn apply_offline_policy(st: &mut ServiceState, error: &str) {
+    let now = Utc::now();
+    if st.offline.since.is_none() {
+        let since = st.last_heartbeat.unwrap_or(now);
+        st.offline.since = Some(since);
+        persist_offline_clock(st);
+        st.event_log
+            .append(
+                "CONNECTED_OFFLINE",
+                EventSeverity::Warn,
+                serde_json::json!({"since": since, "error": error}),
+            )
+            .ok();
+    }
+
+    let policy = st.engine.settings().offline;
+    let profile = st.vault.payload.security_profile.clone();
+    let offline_secs = st.offline.since.map(|s| (now - s).num_seconds()).unwrap_or(0);
+    let steps = st.offline.advance(&policy, &profile, now);
+    apply_steps(st, steps, offline_secs);
+
+    if st.offline.logging_escalated {
+        st.event_log
+            .append(
+                "HEARTBEAT_FAILED",
+                EventSeverity::Warn,
+                serde_json::json!({"error": error, "offline_secs": offline_secs}),
+            )
+            .ok();
+    }
+}
+
+fn apply_steps(st: &mut ServiceState, steps: Vec<OfflineStep>, offline_secs: i64) {
+    for step in steps {
+        let severity = match step {
+            OfflineStep::SafeMode => EventSeverity::Critical,
+            _ => EventSeverity::Warn,
+        };
+        st.event_log
+            .append(
+                "OFFLINE_POLICY_APPLIED",
+                severity,
+                serde_json::json!({"step": step, "offline_secs": offline_secs}),
+            )
+            .ok();
+        if step == OfflineStep::SafeMode {
+            st.safe_mode.enter(SafeModeReason::PlatformUnreachable);
+            st.engine.enter_safe_mode();
+            st.event_log
+                .append(
+                    "SAFE_MODE_ENTERED",
+                    EventSeverity::Critical,
+                    serde_json::json!({"reason": "PLATFORM_UNREACHABLE"}),
+                )
+                .ok();
+        }
+    }
+}
+
+/// Write the offline clock to the vault so a restart resumes it rather than
+/// starting over.
+fn persist_offline_clock(st: &mut ServiceState) {
+    st.vault.payload.state.last_heartbeat = st.last_heartbeat;
+    st.vault.payload.state.offline_since = st.offline.since;
+    if let Err(err) = st.vault.save_with_key() {
+        warn!(error = %err, "failed to persist offline clock");
+    }
+}
+
+/// Upload the offline backlog, then lift the offline restrictions. On
+/// failure the device stays in its offline state and the next successful
+/// heartbeat retries.
+async fn reconnect(
+    client: &ApiClient,
+    device_id: &str,
+    state: &Arc<Mutex<ServiceState>>,
+    since: chrono::DateTime<Utc>,
+    event_log: &guard_core::event_log::EventLog,
+) {
+    match upload_backlog(client, device_id, event_log, since).await {
+        Ok(uploaded) => {
+            let mut guard = state.lock();
+            let ended = std::mem::take(&mut guard.offline);
+            persist_offline_clock(&mut guard);
+            let offline_secs = (Utc::now() - since).num_seconds();
+            info!(uploaded, offline_secs, "reconnected to platform");
+            guard
+                .event_log
+                .append(
+                    "CONNECTED_RESTORED",
+                    EventSeverity::Info,
+                    serde_json::json!({
+                        "offline_since": since,
+                        "offline_secs": offline_secs,
+                        "uploaded_events": uploaded,
+                        "settings_unlocked": ended.settings_locked,
+                        // Safe mode is left for an operator to exit.
+                        "safe_mode_entered": ended.safe_mode_entered,
+                    }),
+                )
+                .ok();
+        }
+        Err(err) => warn!(error = %err, "offline event upload failed; will retry"),
+    }
+}
@@ -10,6 +10,8 @@ use crate::service_state::ServiceState;
 mod api_client;
 pub mod commands;
 mod heartbeat;
+pub mod offline;
+pub mod receipts;
 pub mod state;
 mod telemetry;
 pub mod verifier;
@@ -36,7 +38,8 @@ pub fn maybe_start_connected(state: Arc<Mutex<ServiceState>>) -> Result<Option<J
         }
     }
 
-    let guard = state.lock();
+    let mut guard = state.lock();
+    heartbeat::resume_offline_clock(&mut guard);
     let device_id = guard.vault.payload.device_id.clone();
     let security_profile = guard.vault.payload.security_profile.clone();
     let server_public_key = guard.vault.payload.connection.server_public_key.clone();
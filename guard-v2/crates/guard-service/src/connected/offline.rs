@@ -0,0 +1,219 @@
+//! Offline grace policy for Connected mode.
+//!
+//! The heartbeat loop tracks how long the platform has been unreachable and
+//! applies `OfflinePolicySettings` as each threshold passes. The offline
+//! start and the last heartbeat are kept in vault state, so a restart
+//! resumes the offline clock instead of resetting it. On reconnection the
+//! events recorded while offline are uploaded before the offline
+//! restrictions lift.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use guard_core::event_log::{EventLog, EventQuery};
+use guard_core::settings::OfflinePolicySettings;
+use guard_core::vault::{SecurityProfile, VaultState};
+use serde::Serialize;
+
+use super::api_client::ApiClient;
+
+/// Events per upload request.
+const UPLOAD_BATCH: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OfflineStep {
+    EscalateLogging,
+    LockSettings,
+    SafeMode,
+}
+
+/// Offline bookkeeping kept in `ServiceState`.
+#[derive(Debug, Clone, Default)]
+pub struct OfflineStatus {
+    /// Last moment the platform was known reachable; `None` while online.
+    pub since: Option<DateTime<Utc>>,
+    pub logging_escalated: bool,
+    pub settings_locked: bool,
+    pub safe_mode_entered: bool,
+}
+
+impl OfflineStatus {
+    /// Rebuild the status from the offline start persisted in `state`.
+    /// Returns it with the steps already due, which the caller re-applies.
+    pub fn resume(
+        state: &VaultState,
+        policy: &OfflinePolicySettings,
+        profile: &SecurityProfile,
+        now: DateTime<Utc>,
+    ) -> (Self, Vec<OfflineStep>) {
+        let mut status = Self {
+            since: state.offline_since,
+            ..Default::default()
+        };
+        let steps = status.advance(policy, profile, now);
+        (status, steps)
+    }
+
+    /// Apply `policy` for the time spent offline at `now`. Returns the steps
+    /// newly reached, in escalating order.
+    pub fn advance(
+        &mut self,
+        policy: &OfflinePolicySettings,
+        profile: &SecurityProfile,
+        now: DateTime<Utc>,
+    ) -> Vec<OfflineStep> {
+        let Some(since) = self.since else {
+            return Vec::new();
+        };
+        let hours = (now - since).num_hours();
+        let reached = |threshold: u32| threshold > 0 && hours >= i64::from(threshold);
+
+        let mut steps = Vec::new();
+        if !self.logging_escalated && reached(policy.escalate_logging_after_hours) {
+            self.logging_escalated = true;
+            steps.push(OfflineStep::EscalateLogging);
+        }
+        if !self.settings_locked && reached(policy.lock_settings_after_hours) {
+            self.settings_locked = true;
+            steps.push(OfflineStep::LockSettings);
+        }
+        if !self.safe_mode_entered
+            && *profile == SecurityProfile::ZeroTrust
+            && reached(policy.safe_mode_after_hours)
+        {
+            self.safe_mode_entered = true;
+            steps.push(OfflineStep::SafeMode);
+        }
+        steps
+    }
+}
+
+/// Upload every event recorded since `since`, oldest first. Returns the
+/// number uploaded.
+pub async fn upload_backlog(
+    client: &ApiClient,
+    device_id: &str,
+    event_log: &EventLog,
+    since: DateTime<Utc>,
+) -> Result<usize> {
+    let mut events = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = event_log.search(&EventQuery {
+            since: Some(since),
+            cursor,
+            limit: Some(UPLOAD_BATCH),
+            ..Default::default()
+        })?;
+        events.extend(page.events);
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+    events.reverse();
+    for batch in events.chunks(UPLOAD_BATCH) {
+        client.upload_events(device_id, since, batch).await?;
+    }
+    Ok(events.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn steps_fire_once_and_safe_mode_needs_zero_trust() {
+        let policy = OfflinePolicySettings::default();
+        let t0 = Utc::now();
+        let mut status = OfflineStatus {
+            since: Some(t0),
+            ..Default::default()
+        };
+
+        assert!(status.advance(&policy, &SecurityProfile::Normal, t0).is_empty());
+        assert_eq!(
+            status.advance(&policy, &SecurityProfile::Normal, t0 + Duration::hours(1)),
+            vec![OfflineStep::EscalateLogging]
+        );
+        assert_eq!(
+            status.advance(&policy, &SecurityProfile::Normal, t0 + Duration::hours(48)),
+            vec![OfflineStep::LockSettings]
+        );
+        assert!(status.settings_locked && !status.safe_mode_entered);
+
+        let mut zero_trust = OfflineStatus {
+            since: Some(t0),
+            ..Default::default()
+        };
+        assert_eq!(
+            zero_trust.advance(&policy, &SecurityProfile::ZeroTrust, t0 + Duration::hours(24)),
+            vec![
+                OfflineStep::EscalateLogging,
+                OfflineStep::LockSettings,
+                OfflineStep::SafeMode
+            ]
+        );
+        assert!(zero_trust
+            .advance(&policy, &SecurityProfile::ZeroTrust, t0 + Duration::hours(30))
+            .is_empty());
+    }
+
+    #[test]
+    fn restart_while_offline_resumes_the_clock() {
+        let policy = OfflinePolicySettings::default();
+        let now = Utc::now();
+        let state: VaultState = serde_json::from_value(serde_json::json!({
+            "safe_mode": false,
+            "safe_mode_reason": null,
+            "tour_completed": false,
+            "last_update_check": null,
+            "installed_version": "0.0.0",
+            "last_heartbeat": now - Duration::hours(30),
+            "offline_since": now - Duration::hours(30),
+        }))
+        .unwrap();
+
+        let (mut status, steps) =
+            OfflineStatus::resume(&state, &policy, &SecurityProfile::ZeroTrust, now);
+        assert_eq!(status.since, state.offline_since);
+        assert_eq!(
+            steps,
+            vec![
+                OfflineStep::EscalateLogging,
+                OfflineStep::LockSettings,
+                OfflineStep::SafeMode
+            ]
+        );
+        assert!(status.settings_locked && status.safe_mode_entered);
+        assert!(status
+            .advance(&policy, &SecurityProfile::ZeroTrust, now + Duration::hours(1))
+            .is_empty());
+
+        let online = VaultState {
+            offline_since: None,
+            ..state
+        };
+        let (status, steps) =
+            OfflineStatus::resume(&online, &policy, &SecurityProfile::ZeroTrust, now);
+        assert!(status.since.is_none() && steps.is_empty());
+    }
+
+    #[test]
+    fn zero_threshold_disables_a_step() {
+        let policy = OfflinePolicySettings {
+            escalate_logging_after_hours: 0,
+            lock_settings_after_hours: 0,
+            safe_mode_after_hours: 0,
+        };
+        let t0 = Utc::now();
+        let mut status = OfflineStatus {
+            since: Some(t0),
+            ..Default::default()
+        };
+        assert!(status
+            .advance(&policy, &SecurityProfile::ZeroTrust, t0 + Duration::days(30))
+            .is_empty());
+    }
+}
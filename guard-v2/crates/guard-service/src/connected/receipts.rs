@@ -0,0 +1,146 @@
+//! Local record of signed command receipts.
+//!
+//! Receipts are persisted to `<data_dir>/command_receipts.json` in the order
+//! they were issued. A receipt the platform hasn't acknowledged is kept until
+//! it has been delivered; only the oldest delivered receipts are dropped once
+//! the log grows past `MAX_DELIVERED`.
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use guard_core::command_receipt::SignedCommandReceipt;
+use parking_lot::Mutex;
+use std::fs;
+use std::path::PathBuf;
+
+const MAX_DELIVERED: usize = 1000;
+
+pub struct ReceiptLog {
+    state_path: PathBuf,
+    receipts: Mutex<Vec<SignedCommandReceipt>>,
+}
+
+impl ReceiptLog {
+    pub fn load(state_path: PathBuf) -> Result<Self> {
+        let receipts = if state_path.exists() {
+            let data =
+                fs::read(&state_path).with_context(|| format!("read {}", state_path.display()))?;
+            serde_json::from_slice(&data).context("parse command receipts")?
+        } else {
+            Vec::new()
+        };
+        Ok(Self {
+            state_path,
+            receipts: Mutex::new(receipts),
+        })
+    }
+
+    pub fn record(&self, receipt: SignedCommandReceipt) -> Result<()> {
+        let mut receipts = self.receipts.lock();
+        receipts.push(receipt);
+        self.save(&receipts)
+    }
+
+    /// Note that the platform has acknowledged the receipts for
+    /// `command_ids`, dropping the oldest delivered ones over the cap.
+    pub fn mark_delivered(&self, command_ids: &[String]) -> Result<()> {
+        let mut receipts = self.receipts.lock();
+        let now = Utc::now();
+        for receipt in receipts.iter_mut() {
+            if receipt.delivered_at.is_none() && command_ids.contains(&receipt.receipt.command_id) {
+                receipt.delivered_at = Some(now);
+            }
+        }
+        let delivered = receipts.iter().filter(|r| r.delivered_at.is_some()).count();
+        let mut excess = delivered.saturating_sub(MAX_DELIVERED);
+        receipts.retain(|r| {
+            let drop = excess > 0 && r.delivered_at.is_some();
+            excess -= drop as usize;
+            !drop
+        });
+        self.save(&receipts)
+    }
+
+    /// Receipts still waiting for the platform, oldest first.
+    pub fn undelivered(&self) -> Vec<SignedCommandReceipt> {
+        self.receipts
+            .lock()
+            .iter()
+            .filter(|r| r.delivered_at.is_none())
+            .cloned()
+            .collect()
+    }
+
+    /// The `limit` most recent receipts, newest first.
+    pub fn recent(&self, limit: usize) -> Vec<SignedCommandReceipt> {
+        self.receipts
+            .lock()
+            .iter()
+            .rev()
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    fn save(&self, receipts: &[SignedCommandReceipt]) -> Result<()> {
+        let tmp = self.state_path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(receipts)?)?;
+        fs::rename(&tmp, &self.state_path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::SigningKey;
+    use guard_core::command_receipt::CommandReceipt;
+    use rand::rngs::OsRng;
+
+    fn receipt(key: &SigningKey, id: &str) -> SignedCommandReceipt {
+        let now = Utc::now();
+        SignedCommandReceipt::sign(
+            CommandReceipt {
+                device_id: "device-1".into(),
+                command_id: id.into(),
+                command: "ENTER_SAFE_MODE".into(),
+                nonce: format!("nonce-{id}"),
+                status: "succeeded".into(),
+                result: serde_json::json!({}),
+                error: None,
+                received_at: now,
+                completed_at: now,
+            },
+            key,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn undelivered_receipts_survive_restart_and_trimming() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("command_receipts.json");
+        let key = SigningKey::generate(&mut OsRng);
+
+        let log = ReceiptLog::load(path.clone()).unwrap();
+        log.record(receipt(&key, "pending")).unwrap();
+        for i in 0..MAX_DELIVERED + 5 {
+            let id = format!("cmd-{i}");
+            log.record(receipt(&key, &id)).unwrap();
+            log.mark_delivered(&[id]).unwrap();
+        }
+
+        let log = ReceiptLog::load(path).unwrap();
+        let pending = log.undelivered();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].receipt.command_id, "pending");
+        pending[0].verify(&key.verifying_key()).unwrap();
+
+        let recent = log.recent(usize::MAX);
+        assert_eq!(recent.len(), MAX_DELIVERED + 1);
+        assert_eq!(
+            recent[0].receipt.command_id,
+            format!("cmd-{}", MAX_DELIVERED + 4)
+        );
+        assert!(recent.iter().all(|r| r.receipt.command_id != "cmd-0"));
+    }
+}
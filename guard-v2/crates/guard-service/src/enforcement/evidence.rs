@@ -0,0 +1,208 @@
+//! Evidence export and import for quarantined files.
+//!
+//! Export packs files from the quarantine zone into an encrypted archive
+//! (`guard_core::evidence`) whose custody manifest is signed by this device.
+//! Import opens an archive from another instance, checks the custody chain
+//! and every file hash, appends this device's own custody record and unpacks
+//! it under `{data_dir}/evidence/{archive_id}/`:
+//!
+//! * `custody.json` — the manifest, including the import record;
+//! * `files/` — the quarantined files, never executed or restored.
+
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use guard_core::event_log::{EventLog, EventQuery};
+use guard_core::evidence::{
+    CustodyAction, CustodyManifest, EvidenceArchive, EvidenceItem, EvidencePayload,
+};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+
+/// Who is handling the evidence on this device.
+pub struct Custodian<'a> {
+    pub device_id: &'a str,
+    pub examiner: &'a str,
+    pub device_key: &'a SigningKey,
+}
+
+/// Where the file now at `quarantined` was moved from, per the
+/// `FILE_QUARANTINED` or `RESTORE_FAILURE` event that put it there.
+pub fn quarantined_from(event_log: &EventLog, quarantined: &str) -> Option<String> {
+    let name = Path::new(quarantined)
+        .file_name()?
+        .to_string_lossy()
+        .to_string();
+    let page = event_log
+        .search(&EventQuery {
+            event_types: vec!["FILE_QUARANTINED".into(), "RESTORE_FAILURE".into()],
+            text: Some(name),
+            ..Default::default()
+        })
+        .ok()?;
+    page.events.iter().find_map(|event| {
+        let data = &event.data;
+        let (moved_to, from) = match event.event_type.as_str() {
+            "FILE_QUARANTINED" => (&data["quarantine_path"], &data["original_path"]),
+            _ => (&data["quarantined"], &data["path"]),
+        };
+        // The engine names its quarantine dir via the backup store root
+        // (`backups/../quarantine`), so compare file names within it.
+        let same = moved_to
+            .as_str()
+            .is_some_and(|p| Path::new(p).file_name() == Path::new(quarantined).file_name());
+        same.then(|| from.as_str().map(str::to_string)).flatten()
+    })
+}
+
+/// Every file in the quarantine zone, with where it came from according to
+/// `origin`.
+pub fn quarantine_items(
+    root: &Path,
+    origin: impl Fn(&str) -> Option<String>,
+) -> Result<Vec<EvidenceItem>> {
+    Ok(read_quarantine(root, &[], origin)?
+        .into_iter()
+        .map(|(item, _)| item)
+        .collect())
+}
+
+/// Write the quarantined files named in `names` (all when empty) to a new
+/// archive at `destination`.
+pub fn export_quarantine(
+    root: &Path,
+    names: &[String],
+    origin: impl Fn(&str) -> Option<String>,
+    destination: &Path,
+    password: &str,
+    custodian: &Custodian,
+) -> Result<CustodyManifest> {
+    let files = read_quarantine(root, names, origin)?;
+    if files.is_empty() {
+        return Err(anyhow!("nothing in quarantine to export"));
+    }
+    let mut manifest = CustodyManifest::new(files.iter().map(|(i, _)| i.clone()).collect());
+    manifest.append(
+        CustodyAction::Exported,
+        custodian.device_id,
+        custodian.examiner,
+        custodian.device_key,
+    )?;
+    let payload = EvidencePayload {
+        manifest: manifest.clone(),
+        files: files
+            .into_iter()
+            .map(|(item, data)| (item.name, general_purpose::STANDARD.encode(data)))
+            .collect(),
+    };
+    let archive = EvidenceArchive::seal(&payload, password)?;
+    fs::File::options()
+        .write(true)
+        .create_new(true)
+        .open(destination)
+        .with_context(|| format!("create {}", destination.display()))?
+        .write_all(&serde_json::to_vec_pretty(&archive)?)?;
+    Ok(manifest)
+}
+
+/// Verify and unpack `archive` under `evidence_root`. With `exporter_key`,
+/// the archive must also have been exported by that device.
+pub fn import_evidence(
+    archive: &Path,
+    password: &str,
+    evidence_root: &Path,
+    exporter_key: Option<&VerifyingKey>,
+    custodian: &Custodian,
+) -> Result<(CustodyManifest, PathBuf)> {
+    let sealed: EvidenceArchive = serde_json::from_slice(
+        &fs::read(archive).with_context(|| format!("read {}", archive.display()))?,
+    )
+    .context("parse evidence archive")?;
+    let payload = sealed.open(password)?;
+    let mut manifest = payload.manifest.clone();
+    manifest.verify()?;
+    // The id names the import directory. Without `exporter_key` the manifest
+    // vouches only for itself, so it gets no say in where files land.
+    if uuid::Uuid::parse_str(&manifest.archive_id).is_err() {
+        return Err(anyhow!("archive has invalid id {:?}", manifest.archive_id));
+    }
+    if let Some(expected) = exporter_key {
+        if manifest.exporter_key()? != *expected {
+            return Err(anyhow!("archive was not exported by the expected device"));
+        }
+    }
+    let mut files = Vec::new();
+    for item in &manifest.items {
+        if !is_plain_name(&item.name) {
+            return Err(anyhow!("archive lists invalid file name {:?}", item.name));
+        }
+        files.push((item.name.clone(), payload.file(item)?));
+    }
+
+    let dir = evidence_root.join(&manifest.archive_id);
+    if dir.exists() {
+        return Err(anyhow!(
+            "archive {} is already imported at {}",
+            manifest.archive_id,
+            dir.display()
+        ));
+    }
+    manifest.append(
+        CustodyAction::Imported,
+        custodian.device_id,
+        custodian.examiner,
+        custodian.device_key,
+    )?;
+    fs::create_dir_all(dir.join("files"))
+        .with_context(|| format!("create evidence dir {}", dir.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = fs::set_permissions(evidence_root, fs::Permissions::from_mode(0o700));
+    }
+    for (name, data) in files {
+        fs::write(dir.join("files").join(name), data)?;
+    }
+    fs::write(
+        dir.join("custody.json"),
+        serde_json::to_vec_pretty(&manifest)?,
+    )?;
+    Ok((manifest, dir))
+}
+
+/// Read the top-level files of the quarantine zone, restricted to `names`
+/// when given. Every requested name must exist.
+fn read_quarantine(
+    root: &Path,
+    names: &[String],
+    origin: impl Fn(&str) -> Option<String>,
+) -> Result<Vec<(EvidenceItem, Vec<u8>)>> {
+    let mut found = BTreeMap::new();
+    for entry in fs::read_dir(root).with_context(|| format!("read {}", root.display()))? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if entry.file_type()?.is_file() && (names.is_empty() || names.contains(&name)) {
+            found.insert(name, entry.path());
+        }
+    }
+    if let Some(missing) = names.iter().find(|n| !found.contains_key(*n)) {
+        return Err(anyhow!("{missing} is not in quarantine"));
+    }
+    found
+        .into_iter()
+        .map(|(name, path)| {
+            let data = fs::read(&path).with_context(|| format!("read {}", path.display()))?;
+            let original = origin(&path.display().to_string());
+            Ok((EvidenceItem::new(name, original, &data), data))
+        })
+        .collect()
+}
+
+fn is_plain_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    matches!(components.next(), Some(Component::Normal(_)))
+        && components.next().is_none()
+        && !name.contains(['/', '\\'])
+}
@@ -1,2 +1,8 @@
+pub mod evidence;
 pub mod restore;
+pub mod pending;
+pub mod privileged;
 pub mod quarantine;
+pub mod snapshot;
+pub mod write_freeze;
+pub mod writers;
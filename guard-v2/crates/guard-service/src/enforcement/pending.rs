@@ -0,0 +1,71 @@
+//! Restores deferred until the next service start.
+//!
+//! A restore whose target stays locked through every retry is recorded here
+//! and persisted to `<data_dir>/pending_restores.json`. On startup the service
+//! takes the queue and restores each path against the current baseline; paths
+//! already back at their baseline hash (e.g. replaced at boot on Windows) are
+//! counted as restored without touching them.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PendingRestore {
+    pub path: String,
+    /// Baseline hash the restore was targeting.
+    pub hash: String,
+    pub queued_at: DateTime<Utc>,
+    pub error: String,
+    /// Set when a replace-on-reboot was registered with the OS.
+    #[serde(default)]
+    pub boot_scheduled: bool,
+}
+
+pub struct PendingRestores {
+    state_path: PathBuf,
+    queue: Mutex<BTreeMap<String, PendingRestore>>,
+}
+
+impl PendingRestores {
+    pub fn load(state_path: PathBuf) -> Result<Self> {
+        let queue = if state_path.exists() {
+            let data = fs::read(&state_path)
+                .with_context(|| format!("read {}", state_path.display()))?;
+            serde_json::from_slice(&data).context("parse pending restore queue")?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Self {
+            state_path,
+            queue: Mutex::new(queue),
+        })
+    }
+
+    /// Queue `pending`, replacing any earlier entry for the same path.
+    pub fn enqueue(&self, pending: PendingRestore) -> Result<()> {
+        let mut queue = self.queue.lock();
+        queue.insert(pending.path.clone(), pending);
+        self.save(&queue)
+    }
+
+    /// Drain the queue, oldest first.
+    pub fn take_all(&self) -> Result<Vec<PendingRestore>> {
+        let mut queue = self.queue.lock();
+        let mut drained: Vec<_> = std::mem::take(&mut *queue).into_values().collect();
+        self.save(&queue)?;
+        drained.sort_by_key(|p| p.queued_at);
+        Ok(drained)
+    }
+
+    fn save(&self, queue: &BTreeMap<String, PendingRestore>) -> Result<()> {
+        let tmp = self.state_path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(queue)?)?;
+        fs::rename(&tmp, &self.state_path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+//! Client for the privileged restore helper (`guard_core::privileged`).
+//!
+//! Each call starts `HELPER_COMMAND`, writes one signed request to
+//! its stdin and reads the response from its stdout. The call and its
+//! outcome are logged as `PRIVILEGED_HELPER_CALL`.
+
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use ed25519_dalek::SigningKey;
+use guard_core::event_log::{EventLog, EventSeverity};
+use guard_core::privileged::{
+    HelperOp, HelperRequest, HelperResponse, SignedHelperRequest, HELPER_COMMAND,
+};
+use guard_core::settings::PrivilegedHelperSettings;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+
+use crate::integrity::scanner::BaselineEntry;
+
+pub struct PrivilegedHelper {
+    device_key: SigningKey,
+    event_log: Arc<EventLog>,
+}
+
+impl PrivilegedHelper {
+    /// `None` unless the helper is enabled.
+    pub fn from_settings(
+        settings: &PrivilegedHelperSettings,
+        device_key: &SigningKey,
+        event_log: Arc<EventLog>,
+    ) -> Option<Arc<Self>> {
+        settings.enabled.then(|| {
+            Arc::new(Self {
+                device_key: device_key.clone(),
+                event_log,
+            })
+        })
+    }
+
+    /// Put `data`, the verified backup of `entry`, back at `path`.
+    pub fn restore(&self, path: &Path, entry: &BaselineEntry, data: &[u8]) -> Result<()> {
+        let ownership = entry.ownership.as_ref();
+        self.call(HelperOp::Restore {
+            path: path.display().to_string(),
+            hash: entry.hash.clone(),
+            content: general_purpose::STANDARD.encode(data),
+            mode: entry.permissions,
+            uid: ownership.and_then(|o| o.uid),
+            gid: ownership.and_then(|o| o.gid),
+        })
+        .map(|_| ())
+    }
+
+    /// Move `path` into the helper's quarantine directory.
+    pub fn quarantine(&self, path: &Path) -> Result<Option<PathBuf>> {
+        let response = self.call(HelperOp::Quarantine {
+            path: path.display().to_string(),
+        })?;
+        Ok(response.quarantine_path.map(PathBuf::from))
+    }
+
+    fn call(&self, op: HelperOp) -> Result<HelperResponse> {
+        let (op_name, path) = (op.name(), op.path().to_string());
+        let result = self.run(op);
+        let error = match &result {
+            Ok(response) if response.ok => None,
+            Ok(response) => Some(
+                response
+                    .error
+                    .clone()
+                    .unwrap_or_else(|| "helper refused the request".into()),
+            ),
+            Err(e) => Some(format!("{e:#}")),
+        };
+        let _ = self.event_log.append(
+            "PRIVILEGED_HELPER_CALL",
+            if error.is_some() {
+                EventSeverity::Error
+            } else {
+                EventSeverity::Info
+            },
+            serde_json::json!({
+                "op": op_name,
+                "path": path,
+                "ok": error.is_none(),
+                "error": error,
+            }),
+        );
+        match error {
+            None => result,
+            Some(e) => Err(anyhow!("privileged helper {op_name} of {path} failed: {e}")),
+        }
+    }
+
+    fn run(&self, op: HelperOp) -> Result<HelperResponse> {
+        let request = SignedHelperRequest::sign(HelperRequest::new(op), &self.device_key)?;
+        let (program, args) = HELPER_COMMAND
+            .split_first()
+            .ok_or_else(|| anyhow!("no helper command for this platform"))?;
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| format!("start {program}"))?;
+        // Dropped after writing so the helper sees end of input.
+        child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow!("helper stdin unavailable"))?
+            .write_all(&serde_json::to_vec(&request)?)?;
+        let output = child.wait_with_output()?;
+        serde_json::from_slice(&output.stdout)
+            .with_context(|| format!("unreadable helper response (exit status {})", output.status))
+    }
+}
+
+/// Whether `err` is the OS refusing access, which the helper can get past.
+pub(crate) fn is_permission_error(err: &anyhow::Error) -> bool {
+    err.chain()
+        .filter_map(|e| e.downcast_ref::<std::io::Error>())
+        .any(|e| e.kind() == std::io::ErrorKind::PermissionDenied)
+}
@@ -71,7 +71,6 @@ impl QuarantineZone {
         }
     }
 
-    #[allow(dead_code)]
     pub fn root(&self) -> &Path {
         &self.root
     }
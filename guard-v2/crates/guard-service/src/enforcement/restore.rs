@@ -11,30 +11,58 @@
 //! 7. Verify final hash
 //! 8. Retry 3× (100ms, 500ms, 2s) then quarantine
 //!
+//! A target locked by another process is not a tamper we can fix by
+//! quarantining, so locked failures are retried by `retry_locked` with
+//! exponential back-off per `RestoreSettings` — the caller, which holds the
+//! baseline and backup store, never waits — and end as `Deferred`,
+//! optionally queued for the next service start (and on Windows for
+//! replace-on-reboot).
+//!
+//! A restore refused for lack of permission (a root-owned file, the service
+//! running unprivileged) is handed to the privileged helper when one is
+//! configured, and so is quarantining the file if that fails too.
+//!
+//! Link entries (`SymlinkPolicy::ProtectLink`) have no backup blob: the link
+//! is recreated at the staging path and renamed into place the same way.
+//!
 //! Restore-loop suppression: A `HashSet<PathBuf>` of paths currently being
 //! restored. The watcher pipeline must check this set and skip events for
 //! paths undergoing restore.
 
 use anyhow::{anyhow, Context, Result};
 use blake3::Hasher;
+use chrono::Utc;
 use guard_core::backup_store::BackupStore;
-use parking_lot::Mutex;
+use guard_core::settings::RestoreSettings;
+use parking_lot::{Mutex, RwLock};
 use std::collections::{HashMap, HashSet};
 use std::fs::{self, File, OpenOptions};
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tracing::{error, info, warn};
 
+use crate::enforcement::pending::{PendingRestore, PendingRestores};
+use crate::enforcement::privileged::{is_permission_error, PrivilegedHelper};
 use crate::enforcement::quarantine::QuarantineZone;
-use crate::integrity::scanner::BaselineEntry;
+use crate::integrity::attributes;
+use crate::integrity::scanner::{Baseline, BaselineEntry};
+use crate::integrity::symlink::{find_swapped_link, link_hash};
 
 /// Minimum free space required before writing a restored file (bytes).
 const MIN_FREE_SPACE_BYTES: u64 = 10 * 1024 * 1024; // 10 MiB
 
 /// Staging file prefix used so we can clean up orphans on startup.
-const STAGING_PREFIX: &str = ".darklock_restore_";
+pub(crate) const STAGING_PREFIX: &str = ".darklock_restore_";
+/// Prefix of a file staged for replace-on-reboot. Not a `STAGING_PREFIX`,
+/// so startup cleanup leaves it for the boot that moves it into place.
+pub(crate) const BOOT_STAGING_PREFIX: &str = ".darklock_boot_";
+
+/// Whether `name` is one of our staging files rather than a protected file.
+pub(crate) fn is_staging_name(name: &str) -> bool {
+    name.starts_with(STAGING_PREFIX) || name.starts_with(BOOT_STAGING_PREFIX)
+}
 
 /// Result of a single restore attempt.
 #[derive(Debug, Clone)]
@@ -45,6 +73,18 @@ pub enum RestoreOutcome {
     BackupCorrupted { path: String },
     Quarantined { quarantine_path: Option<PathBuf> },
     Failed { error: String },
+    /// Target still locked after backing off; `queued` if it will be retried
+    /// at the next service start.
+    Deferred { error: String, attempts: usize, queued: bool },
+    /// Target locked; `retry_locked` tries again after `delay_ms`.
+    Retrying { error: String, retry: u32, delay_ms: u64 },
+}
+
+/// A locked target waiting for its next attempt.
+struct LockedRetry {
+    entry: BaselineEntry,
+    retries: u32,
+    due: Instant,
 }
 
 /// The restore engine.
@@ -54,10 +94,16 @@ pub struct RestoreEngine {
     /// Paths currently undergoing atomic restore — the watcher must skip these.
     pub restoring: Arc<Mutex<HashSet<PathBuf>>>,
     quarantine: QuarantineZone,
+    settings: RwLock<RestoreSettings>,
+    pending: Option<PendingRestores>,
+    helper: RwLock<Option<Arc<PrivilegedHelper>>>,
+    locked: Mutex<HashMap<PathBuf, LockedRetry>>,
 }
 
 const MAX_RETRIES: usize = 3;
 const RETRY_DELAYS_MS: [u64; MAX_RETRIES] = [100, 500, 2000];
+/// First back-off delay for a locked target; doubles per retry.
+const LOCKED_BASE_DELAY_MS: u64 = 250;
 
 impl RestoreEngine {
     pub fn new(quarantine: QuarantineZone) -> Self {
@@ -65,9 +111,63 @@ impl RestoreEngine {
             locks: Mutex::new(HashMap::new()),
             restoring: Arc::new(Mutex::new(HashSet::new())),
             quarantine,
+            settings: RwLock::new(RestoreSettings::default()),
+            pending: None,
+            helper: RwLock::new(None),
+            locked: Mutex::new(HashMap::new()),
         }
     }
 
+    /// Queue restores that stay locked for the next service start.
+    pub fn with_pending_queue(mut self, pending: PendingRestores) -> Self {
+        self.pending = Some(pending);
+        self
+    }
+
+    pub fn configure(&self, settings: &RestoreSettings) {
+        *self.settings.write() = settings.clone();
+    }
+
+    /// Use `helper` for restores and quarantines that need elevation.
+    pub fn set_privileged_helper(&self, helper: Option<Arc<PrivilegedHelper>>) {
+        *self.helper.write() = helper;
+    }
+
+    /// Retry restores deferred by a previous run. Returns one outcome per
+    /// queued path still in `baseline`; paths still locked are queued again.
+    pub fn apply_pending(
+        &self,
+        baseline: &Baseline,
+        store: &BackupStore,
+    ) -> Vec<(String, RestoreOutcome)> {
+        let Some(pending) = &self.pending else {
+            return Vec::new();
+        };
+        let queued = match pending.take_all() {
+            Ok(queued) => queued,
+            Err(e) => {
+                error!(error = %e, "cannot read pending restore queue");
+                return Vec::new();
+            }
+        };
+        let mut outcomes = Vec::new();
+        for item in queued {
+            let Some(entry) = baseline.entries.get(&item.path) else {
+                info!(path = %item.path, "dropping pending restore; path left the baseline");
+                continue;
+            };
+            let path = PathBuf::from(&item.path);
+            // Already replaced at boot, or put back by someone else.
+            if current_hash(&path, entry).is_ok_and(|h| h == entry.hash) {
+                outcomes.push((item.path, RestoreOutcome::Restored));
+                continue;
+            }
+            let outcome = self.restore_file(&path, entry, store);
+            outcomes.push((item.path, outcome));
+        }
+        outcomes
+    }
+
     /// Core public entry point.  Attempts to atomically restore `path` from
     /// the backup store, verifying against `baseline_entry`.  Retries up to 3
     /// times on transient failures; quarantines the tampered file on permanent
@@ -97,6 +197,9 @@ impl RestoreEngine {
         self.restoring.lock().insert(path.to_path_buf());
 
         let outcome = self.restore_with_retries(path, baseline_entry, backup_store);
+        if !matches!(outcome, RestoreOutcome::Retrying { .. }) {
+            self.locked.lock().remove(path);
+        }
 
         // Unmark regardless of outcome.
         self.restoring.lock().remove(path);
@@ -105,6 +208,37 @@ impl RestoreEngine {
         outcome
     }
 
+    /// Retry locked targets whose back-off has elapsed, against their
+    /// current `baseline` entry. Returns one outcome per retried path.
+    pub fn retry_locked(
+        &self,
+        baseline: &Baseline,
+        store: &BackupStore,
+    ) -> Vec<(String, RestoreOutcome)> {
+        let now = Instant::now();
+        let due: Vec<PathBuf> = {
+            let mut locked = self.locked.lock();
+            locked.retain(|path, retry| {
+                baseline
+                    .entries
+                    .get(&path.display().to_string())
+                    .is_some_and(|e| e.hash == retry.entry.hash)
+            });
+            locked
+                .iter()
+                .filter(|(_, retry)| retry.due <= now)
+                .map(|(path, _)| path.clone())
+                .collect()
+        };
+        due.into_iter()
+            .filter_map(|path| {
+                let key = path.display().to_string();
+                let entry = baseline.entries.get(&key)?;
+                Some((key, self.restore_file(&path, entry, store)))
+            })
+            .collect()
+    }
+
     /// Check whether a path is currently being restored (for loop suppression).
     #[allow(dead_code)]
     pub fn is_restoring(&self, path: &Path) -> bool {
@@ -112,7 +246,6 @@ impl RestoreEngine {
     }
 
     /// Borrow the quarantine zone (e.g. for logging its root).
-    #[allow(dead_code)]
     pub fn quarantine(&self) -> &QuarantineZone {
         &self.quarantine
     }
@@ -139,6 +272,7 @@ impl RestoreEngine {
             for entry in entries.flatten() {
                 let name = entry.file_name();
                 let name_str = name.to_string_lossy();
+                // Boot-staged files are left for the reboot that applies them.
                 if name_str.starts_with(STAGING_PREFIX) {
                     warn!(path = %entry.path().display(), "removing orphaned staging file");
                     let _ = fs::remove_file(entry.path());
@@ -159,7 +293,9 @@ impl RestoreEngine {
         entry: &BaselineEntry,
         store: &BackupStore,
     ) -> RestoreOutcome {
-        for attempt in 0..MAX_RETRIES {
+        let settings = self.settings.read().clone();
+        let mut failures = 0;
+        while failures < MAX_RETRIES {
             match self.try_restore_once(path, entry, store) {
                 Ok(()) => return RestoreOutcome::Restored,
                 Err(e) => {
@@ -175,15 +311,30 @@ impl RestoreEngine {
                             path: path.display().to_string(),
                         };
                     }
+                    if is_locked_error(&e) {
+                        return self.back_off_locked(path, entry, store, &settings, &e, failures);
+                    }
+                    if is_permission_error(&e) {
+                        if let Some(helper) = self.helper.read().clone() {
+                            match restore_elevated(&helper, path, entry, store) {
+                                Ok(()) => return RestoreOutcome::Restored,
+                                Err(e) => {
+                                    warn!(path = %path.display(), error = %e, "elevated restore failed");
+                                    break;
+                                }
+                            }
+                        }
+                    }
                     warn!(
                         path = %path.display(),
-                        attempt = attempt + 1,
+                        attempt = failures + 1,
                         error = %e,
                         "restore attempt failed"
                     );
-                    if attempt + 1 < MAX_RETRIES {
-                        std::thread::sleep(Duration::from_millis(RETRY_DELAYS_MS[attempt]));
+                    if failures + 1 < MAX_RETRIES {
+                        std::thread::sleep(Duration::from_millis(RETRY_DELAYS_MS[failures]));
                     }
+                    failures += 1;
                 }
             }
         }
@@ -193,12 +344,102 @@ impl RestoreEngine {
             path = %path.display(),
             "all restore attempts failed – quarantining tampered file"
         );
-        let q = self.quarantine.quarantine_file(path);
+        let q = match self.quarantine.quarantine_file(path) {
+            Err(e) if is_permission_error(&e) => match self.helper.read().clone() {
+                Some(helper) => helper.quarantine(path),
+                None => Err(e),
+            },
+            other => other,
+        };
         RestoreOutcome::Quarantined {
             quarantine_path: q.ok().flatten(),
         }
     }
 
+    /// Schedule the next attempt at a locked target, or defer it once the
+    /// retries are used up.
+    fn back_off_locked(
+        &self,
+        path: &Path,
+        entry: &BaselineEntry,
+        store: &BackupStore,
+        settings: &RestoreSettings,
+        err: &anyhow::Error,
+        failures: usize,
+    ) -> RestoreOutcome {
+        let retries = self.locked.lock().remove(path).map_or(0, |r| r.retries);
+        if retries >= settings.locked_retry_attempts {
+            let attempts = failures + retries as usize + 1;
+            return self.defer(path, entry, store, settings, err, attempts);
+        }
+        let delay = locked_backoff(retries, settings.locked_retry_max_delay_ms);
+        warn!(
+            path = %path.display(),
+            retry = retries + 1,
+            delay_ms = delay.as_millis() as u64,
+            error = %err,
+            "restore target locked; backing off"
+        );
+        self.locked.lock().insert(
+            path.to_path_buf(),
+            LockedRetry {
+                entry: entry.clone(),
+                retries: retries + 1,
+                due: Instant::now() + delay,
+            },
+        );
+        RestoreOutcome::Retrying {
+            error: format!("{err:#}"),
+            retry: retries + 1,
+            delay_ms: delay.as_millis() as u64,
+        }
+    }
+
+    /// Give up on a locked target for now: queue it for the next start if
+    /// configured, and on Windows stage the blob for replace-on-reboot.
+    fn defer(
+        &self,
+        path: &Path,
+        entry: &BaselineEntry,
+        store: &BackupStore,
+        settings: &RestoreSettings,
+        err: &anyhow::Error,
+        attempts: usize,
+    ) -> RestoreOutcome {
+        let error = format!("{err:#}");
+        let queued = match &self.pending {
+            Some(pending) if settings.queue_on_restart => {
+                let boot_scheduled = match schedule_boot_replace(path, entry, store) {
+                    Ok(scheduled) => scheduled,
+                    Err(e) => {
+                        warn!(path = %path.display(), error = %e, "cannot schedule replace-on-reboot");
+                        false
+                    }
+                };
+                match pending.enqueue(PendingRestore {
+                    path: path.display().to_string(),
+                    hash: entry.hash.clone(),
+                    queued_at: Utc::now(),
+                    error: error.clone(),
+                    boot_scheduled,
+                }) {
+                    Ok(()) => true,
+                    Err(e) => {
+                        error!(path = %path.display(), error = %e, "cannot queue deferred restore");
+                        false
+                    }
+                }
+            }
+            _ => false,
+        };
+        warn!(path = %path.display(), attempts, queued, "restore deferred; target is locked");
+        RestoreOutcome::Deferred {
+            error,
+            attempts,
+            queued,
+        }
+    }
+
     fn try_restore_once(
         &self,
         target_path: &Path,
@@ -209,6 +450,10 @@ impl RestoreEngine {
         // Ensure the target path doesn't resolve outside its parent via symlinks.
         validate_no_symlink_escape(target_path)?;
 
+        if let Some(link_target) = &entry.link_target {
+            return restore_link(target_path, Path::new(link_target), entry);
+        }
+
         // ── Step 2: validate backup integrity ───────────────────────────
         let blob_data = store
             .read_blob_verified(&entry.path, &entry.hash)
@@ -252,6 +497,11 @@ impl RestoreEngine {
 
         // ── Step 6: restore permissions ─────────────────────────────────
         restore_permissions(target_path, entry.permissions)?;
+        // The content is back even if ownership can't be (service not
+        // running as root), so that only warns.
+        if let Err(e) = attributes::restore(target_path, entry, &[]) {
+            warn!(path = %target_path.display(), error = %e, "restored file without its recorded attributes");
+        }
 
         // ── Step 7: verify final hash ───────────────────────────────────
         let final_hash = hash_file(target_path)?;
@@ -272,9 +522,153 @@ impl RestoreEngine {
     }
 }
 
+/// Restore `target` through the privileged helper and verify the result.
+fn restore_elevated(
+    helper: &PrivilegedHelper,
+    target: &Path,
+    entry: &BaselineEntry,
+    store: &BackupStore,
+) -> Result<()> {
+    if entry.link_target.is_some() {
+        return Err(anyhow!("the privileged helper does not restore links"));
+    }
+    let data = store
+        .read_blob_verified(&entry.path, &entry.hash)
+        .context("backup blob verification failed")?;
+    helper.restore(target, entry, &data)?;
+    let final_hash = hash_file(target)?;
+    if final_hash != entry.hash {
+        return Err(anyhow!(
+            "post-restore verification failed: expected {}, got {}",
+            entry.hash,
+            final_hash
+        ));
+    }
+    info!(path = %target.display(), "file restored through the privileged helper");
+    Ok(())
+}
+
+/// Recreate the link `target_path -> link_target` atomically.
+fn restore_link(target_path: &Path, link_target: &Path, entry: &BaselineEntry) -> Result<()> {
+    let parent = target_path
+        .parent()
+        .ok_or_else(|| anyhow!("no parent dir for {}", target_path.display()))?;
+    fs::create_dir_all(parent)?;
+    let staging_path = parent.join(format!("{}{:08x}", STAGING_PREFIX, rand::random::<u32>()));
+    create_symlink(link_target, &staging_path)
+        .with_context(|| format!("create staging link {}", staging_path.display()))?;
+    atomic_rename(&staging_path, target_path).with_context(|| {
+        format!("atomic rename {} -> {}", staging_path.display(), target_path.display())
+    })?;
+
+    let final_hash = current_hash(target_path, entry)?;
+    if final_hash != entry.hash {
+        return Err(anyhow!(
+            "post-restore verification failed: expected {}, got {}",
+            entry.hash,
+            final_hash
+        ));
+    }
+    info!(
+        path = %target_path.display(),
+        target = %link_target.display(),
+        "link restored successfully"
+    );
+    Ok(())
+}
+
+fn create_symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(target, link)
+    }
+    #[cfg(windows)]
+    {
+        let resolved = link.parent().map(|p| p.join(target)).unwrap_or_default();
+        if resolved.is_dir() {
+            std::os::windows::fs::symlink_dir(target, link)
+        } else {
+            std::os::windows::fs::symlink_file(target, link)
+        }
+    }
+}
+
+/// Hash of what is at `path` now, in the form `entry` was recorded in.
+fn current_hash(path: &Path, entry: &BaselineEntry) -> Result<String> {
+    if entry.link_target.is_some() {
+        return Ok(link_hash(&fs::read_link(path)?));
+    }
+    hash_file(path)
+}
+
 // ── Platform helpers ────────────────────────────────────────────────────────
 
-fn atomic_rename(from: &Path, to: &Path) -> Result<()> {
+/// Whether `err` comes from the target being locked or in use rather than
+/// from anything a retry of the restore itself could fix.
+pub(crate) fn is_locked_error(err: &anyhow::Error) -> bool {
+    err.chain()
+        .filter_map(|e| e.downcast_ref::<std::io::Error>())
+        .filter_map(std::io::Error::raw_os_error)
+        .any(|code| {
+            #[cfg(unix)]
+            {
+                code == libc::ETXTBSY || code == libc::EBUSY
+            }
+            #[cfg(windows)]
+            {
+                // ERROR_SHARING_VIOLATION, ERROR_LOCK_VIOLATION
+                code == 32 || code == 33
+            }
+        })
+}
+
+/// Delay before locked retry `retry` (0-based).
+pub(crate) fn locked_backoff(retry: u32, max_delay_ms: u64) -> Duration {
+    let delay = LOCKED_BASE_DELAY_MS.saturating_mul(1 << retry.min(16));
+    Duration::from_millis(delay.min(max_delay_ms))
+}
+
+/// Stage the backup next to `target` and ask Windows to move it into place
+/// at the next boot, before anything can reopen the file. Returns whether a
+/// replacement was scheduled; always `false` elsewhere, where the queue is
+/// applied at service start.
+fn schedule_boot_replace(target: &Path, entry: &BaselineEntry, store: &BackupStore) -> Result<bool> {
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::OsStrExt;
+        if entry.link_target.is_some() {
+            return Ok(false);
+        }
+        let blob = store.read_blob_verified(&entry.path, &entry.hash)?;
+        let parent = target
+            .parent()
+            .ok_or_else(|| anyhow!("no parent dir for {}", target.display()))?;
+        let staging = parent.join(format!("{}{:08x}", BOOT_STAGING_PREFIX, rand::random::<u32>()));
+        fs::write(&staging, &blob)?;
+        let wide_from: Vec<u16> = staging.as_os_str().encode_wide().chain(Some(0)).collect();
+        let wide_to: Vec<u16> = target.as_os_str().encode_wide().chain(Some(0)).collect();
+        let ret = unsafe {
+            windows_sys::Win32::Storage::FileSystem::MoveFileExW(
+                wide_from.as_ptr(),
+                wide_to.as_ptr(),
+                windows_sys::Win32::Storage::FileSystem::MOVEFILE_REPLACE_EXISTING
+                    | windows_sys::Win32::Storage::FileSystem::MOVEFILE_DELAY_UNTIL_REBOOT,
+            )
+        };
+        if ret == 0 {
+            let _ = fs::remove_file(&staging);
+            return Err(anyhow!("MoveFileExW failed: {}", std::io::Error::last_os_error()));
+        }
+        Ok(true)
+    }
+    #[cfg(not(windows))]
+    {
+        let _ = (target, entry, store);
+        Ok(false)
+    }
+}
+
+pub(crate) fn atomic_rename(from: &Path, to: &Path) -> Result<()> {
     #[cfg(unix)]
     {
         fs::rename(from, to)?;
@@ -310,13 +704,14 @@ fn restore_permissions(path: &Path, mode: u32) -> Result<()> {
     }
     #[cfg(windows)]
     {
-        // On Windows we don't store Unix mode bits; ACL restore is future work.
+        // On Windows we don't store Unix mode bits; the ACL is restored with
+        // the other recorded attributes.
         let _ = (path, mode);
     }
     Ok(())
 }
 
-fn hash_file(path: &Path) -> Result<String> {
+pub(crate) fn hash_file(path: &Path) -> Result<String> {
     let mut f = File::open(path)?;
     let mut hasher = Hasher::new();
     let mut buf = vec![0u8; 64 * 1024];
@@ -334,12 +729,23 @@ fn hash_file(path: &Path) -> Result<String> {
 
 /// Ensure the target path doesn't escape its parent directory via symlinks.
 /// The parent directory must exist and the canonical parent of target must
-/// match the canonical form of target's lexical parent.
+/// match the canonical form of target's lexical parent. A link at the target
+/// itself is fine: the rename replaces the link rather than writing through
+/// it.
 fn validate_no_symlink_escape(target: &Path) -> Result<()> {
     let parent = target
         .parent()
         .ok_or_else(|| anyhow!("no parent directory for {}", target.display()))?;
 
+    // No directory above the target may be a symlink pointing elsewhere,
+    // dangling or not.
+    if let Some((link, _)) = find_swapped_link(target, true) {
+        return Err(anyhow!(
+            "parent directory {} is a symlink — refusing restore to prevent escape",
+            link.display()
+        ));
+    }
+
     // If parent doesn't exist yet, we'll create it in the caller — no symlink risk.
     if !parent.exists() {
         return Ok(());
@@ -350,7 +756,8 @@ fn validate_no_symlink_escape(target: &Path) -> Result<()> {
         .with_context(|| format!("canonicalize parent {}", parent.display()))?;
 
     // If the target file exists, canonicalize it and verify its parent matches.
-    if target.exists() {
+    let leaf_is_link = fs::symlink_metadata(target).is_ok_and(|m| m.file_type().is_symlink());
+    if target.exists() && !leaf_is_link {
         let canonical_target = target
             .canonicalize()
             .with_context(|| format!("canonicalize {}", target.display()))?;
@@ -366,14 +773,6 @@ fn validate_no_symlink_escape(target: &Path) -> Result<()> {
         }
     }
 
-    // Also verify the parent itself isn't a symlink pointing elsewhere.
-    if parent.is_symlink() {
-        return Err(anyhow!(
-            "parent directory {} is a symlink — refusing restore to prevent escape",
-            parent.display()
-        ));
-    }
-
     Ok(())
 }
 
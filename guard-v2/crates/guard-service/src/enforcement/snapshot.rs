@@ -0,0 +1,615 @@
+//! Copy-on-write snapshot protection.
+//!
+//! On btrfs, ZFS and APFS the service can take a filesystem snapshot of each
+//! protected directory whenever a baseline is created. When a scan finds
+//! widespread tampering under one protected directory (the ransomware case)
+//! the whole directory is restored from its latest snapshot in one pass
+//! instead of file-by-file from the backup store. Paths on any other
+//! filesystem have no backend and keep the per-file restore path.
+//!
+//! Layout:
+//! ```text
+//! <data_dir>/snapshots.json              — index of snapshots taken
+//! <btrfs subvolume>/.darklock-snapshots/ — read-only btrfs snapshots
+//! ```
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use tracing::{info, warn};
+use walkdir::WalkDir;
+
+use crate::enforcement::restore::{atomic_rename, hash_file, STAGING_PREFIX};
+use crate::integrity::symlink::find_swapped_link;
+
+/// Directory holding btrfs snapshots inside the snapshotted subvolume. The
+/// scanner and watcher skip it so snapshots never show up as protected files.
+pub const SNAPSHOT_DIR_NAME: &str = ".darklock-snapshots";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SnapshotKind {
+    Btrfs,
+    Zfs,
+    Apfs,
+    /// Plain directory copy; only used by tests.
+    Copy,
+}
+
+/// One snapshot of one protected directory.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SnapshotRecord {
+    pub kind: SnapshotKind,
+    /// Protected directory the snapshot covers.
+    pub source: PathBuf,
+    /// Backend-specific handle (subvolume path, `dataset@name`, APFS name).
+    pub id: String,
+    /// Where `source` lives inside the snapshot once it is opened. Absolute
+    /// for btrfs/ZFS; relative to the mount point for APFS.
+    pub view_path: PathBuf,
+    pub label: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A readable view of a snapshot. Backends that need to mount the snapshot
+/// unmount it when the view is dropped.
+pub struct SnapshotView {
+    root: PathBuf,
+    cleanup: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl SnapshotView {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root, cleanup: None }
+    }
+
+    pub fn with_cleanup(root: PathBuf, cleanup: impl FnOnce() + Send + 'static) -> Self {
+        Self {
+            root,
+            cleanup: Some(Box::new(cleanup)),
+        }
+    }
+
+    /// The snapshotted copy of the record's `source` directory.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl Drop for SnapshotView {
+    fn drop(&mut self) {
+        if let Some(cleanup) = self.cleanup.take() {
+            cleanup();
+        }
+    }
+}
+
+/// A filesystem that can snapshot a directory and hand the snapshot back.
+pub trait SnapshotBackend: Send + Sync {
+    fn kind(&self) -> SnapshotKind;
+    /// Snapshot the filesystem containing `source`; returns the record to index.
+    fn create(&self, source: &Path, label: &str) -> Result<SnapshotRecord>;
+    fn open(&self, record: &SnapshotRecord) -> Result<SnapshotView>;
+    fn delete(&self, record: &SnapshotRecord) -> Result<()>;
+}
+
+/// Outcome of restoring a directory from a snapshot.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SnapshotRestoreReport {
+    pub path: String,
+    pub snapshot_id: String,
+    pub snapshot_created_at: Option<DateTime<Utc>>,
+    pub restored: usize,
+    pub unchanged: usize,
+    pub failed: Vec<SnapshotRestoreFailure>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotRestoreFailure {
+    pub path: String,
+    pub error: String,
+}
+
+fn run(cmd: &mut Command) -> Result<String> {
+    let output = cmd
+        .output()
+        .with_context(|| format!("spawn {:?}", cmd.get_program()))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{:?} failed: {}",
+            cmd.get_program(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// ── Filesystem detection ────────────────────────────────────────────────────
+
+#[cfg(target_os = "linux")]
+const BTRFS_SUPER_MAGIC: i64 = 0x9123_683E;
+#[cfg(target_os = "linux")]
+const ZFS_SUPER_MAGIC: i64 = 0x2FC1_2FC1;
+
+/// Which snapshot backend, if any, serves the filesystem holding `path`.
+pub fn detect_kind(path: &Path) -> Option<SnapshotKind> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::ffi::CString;
+        use std::mem::MaybeUninit;
+        use std::os::unix::ffi::OsStrExt;
+
+        let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+        let mut stat = MaybeUninit::<libc::statfs>::uninit();
+        // SAFETY: c_path is NUL-terminated and stat is a valid out-pointer.
+        if unsafe { libc::statfs(c_path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+            return None;
+        }
+        // SAFETY: statfs returned 0, so the struct is initialised.
+        let stat = unsafe { stat.assume_init() };
+        #[allow(clippy::unnecessary_cast)]
+        match stat.f_type as i64 {
+            BTRFS_SUPER_MAGIC => Some(SnapshotKind::Btrfs),
+            ZFS_SUPER_MAGIC => Some(SnapshotKind::Zfs),
+            _ => None,
+        }
+    }
+    #[cfg(target_os = "macos")]
+    {
+        use std::ffi::{CStr, CString};
+        use std::mem::MaybeUninit;
+        use std::os::unix::ffi::OsStrExt;
+
+        let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+        let mut stat = MaybeUninit::<libc::statfs>::uninit();
+        // SAFETY: c_path is NUL-terminated and stat is a valid out-pointer.
+        if unsafe { libc::statfs(c_path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+            return None;
+        }
+        // SAFETY: statfs returned 0, so the struct is initialised.
+        let stat = unsafe { stat.assume_init() };
+        // SAFETY: f_fstypename is a NUL-terminated C string.
+        let name = unsafe { CStr::from_ptr(stat.f_fstypename.as_ptr()) };
+        (name.to_bytes() == b"apfs").then_some(SnapshotKind::Apfs)
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+/// Default backend resolver: pick by filesystem type.
+pub fn system_backend(path: &Path) -> Option<Arc<dyn SnapshotBackend>> {
+    match detect_kind(path)? {
+        SnapshotKind::Btrfs => Some(Arc::new(BtrfsBackend)),
+        SnapshotKind::Zfs => Some(Arc::new(ZfsBackend)),
+        SnapshotKind::Apfs => Some(Arc::new(ApfsBackend)),
+        SnapshotKind::Copy => None,
+    }
+}
+
+// ── btrfs ───────────────────────────────────────────────────────────────────
+
+/// Read-only `btrfs subvolume snapshot` of the subvolume containing the path.
+/// That is the whole enclosing subvolume, which for a directory that is not
+/// a subvolume of its own may be `/`: the snapshot pins everything on it
+/// until it is pruned.
+pub struct BtrfsBackend;
+
+impl BtrfsBackend {
+    /// A btrfs subvolume root always has inode 256; walk up until we find it.
+    fn subvolume_root(path: &Path) -> Result<PathBuf> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            let mut cur = path.canonicalize()?;
+            loop {
+                if fs::metadata(&cur)?.ino() == 256 {
+                    return Ok(cur);
+                }
+                if !cur.pop() {
+                    return Err(anyhow!("no btrfs subvolume above {}", path.display()));
+                }
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            Err(anyhow!("btrfs unsupported for {}", path.display()))
+        }
+    }
+}
+
+impl SnapshotBackend for BtrfsBackend {
+    fn kind(&self) -> SnapshotKind {
+        SnapshotKind::Btrfs
+    }
+
+    fn create(&self, source: &Path, label: &str) -> Result<SnapshotRecord> {
+        let subvol = Self::subvolume_root(source)?;
+        let dir = subvol.join(SNAPSHOT_DIR_NAME);
+        fs::create_dir_all(&dir)?;
+        let dest = dir.join(label);
+        run(Command::new("btrfs")
+            .args(["subvolume", "snapshot", "-r"])
+            .arg(&subvol)
+            .arg(&dest))?;
+        let rel = source.canonicalize()?.strip_prefix(&subvol)?.to_path_buf();
+        Ok(SnapshotRecord {
+            kind: self.kind(),
+            source: source.to_path_buf(),
+            id: dest.display().to_string(),
+            view_path: dest.join(rel),
+            label: label.to_string(),
+            created_at: Utc::now(),
+        })
+    }
+
+    fn open(&self, record: &SnapshotRecord) -> Result<SnapshotView> {
+        Ok(SnapshotView::new(record.view_path.clone()))
+    }
+
+    fn delete(&self, record: &SnapshotRecord) -> Result<()> {
+        run(Command::new("btrfs")
+            .args(["subvolume", "delete"])
+            .arg(&record.id))
+        .map(|_| ())
+    }
+}
+
+// ── ZFS ─────────────────────────────────────────────────────────────────────
+
+/// `zfs snapshot` of the dataset containing the path, read back through the
+/// dataset's `.zfs/snapshot` directory.
+pub struct ZfsBackend;
+
+impl ZfsBackend {
+    /// The mounted dataset with the longest mountpoint prefix of `path`.
+    fn dataset_for(path: &Path) -> Result<(String, PathBuf)> {
+        let path = path.canonicalize()?;
+        let listing = run(Command::new("zfs").args([
+            "list", "-H", "-t", "filesystem", "-o", "name,mountpoint",
+        ]))?;
+        listing
+            .lines()
+            .filter_map(|line| {
+                let (name, mount) = line.split_once('\t')?;
+                let mount = PathBuf::from(mount);
+                path.starts_with(&mount).then(|| (name.to_string(), mount))
+            })
+            .max_by_key(|(_, mount)| mount.components().count())
+            .ok_or_else(|| anyhow!("no ZFS dataset mounted above {}", path.display()))
+    }
+}
+
+impl SnapshotBackend for ZfsBackend {
+    fn kind(&self) -> SnapshotKind {
+        SnapshotKind::Zfs
+    }
+
+    fn create(&self, source: &Path, label: &str) -> Result<SnapshotRecord> {
+        let (dataset, mount) = Self::dataset_for(source)?;
+        let id = format!("{dataset}@{label}");
+        run(Command::new("zfs").arg("snapshot").arg(&id))?;
+        let rel = source.canonicalize()?.strip_prefix(&mount)?.to_path_buf();
+        Ok(SnapshotRecord {
+            kind: self.kind(),
+            source: source.to_path_buf(),
+            id,
+            view_path: mount.join(".zfs/snapshot").join(label).join(rel),
+            label: label.to_string(),
+            created_at: Utc::now(),
+        })
+    }
+
+    fn open(&self, record: &SnapshotRecord) -> Result<SnapshotView> {
+        Ok(SnapshotView::new(record.view_path.clone()))
+    }
+
+    fn delete(&self, record: &SnapshotRecord) -> Result<()> {
+        run(Command::new("zfs").arg("destroy").arg(&record.id)).map(|_| ())
+    }
+}
+
+// ── APFS ────────────────────────────────────────────────────────────────────
+
+/// Local Time Machine snapshot of the data volume, mounted read-only on
+/// demand for restores.
+pub struct ApfsBackend;
+
+impl ApfsBackend {
+    fn volume() -> &'static Path {
+        let data = Path::new("/System/Volumes/Data");
+        if data.is_dir() {
+            data
+        } else {
+            Path::new("/")
+        }
+    }
+}
+
+impl SnapshotBackend for ApfsBackend {
+    fn kind(&self) -> SnapshotKind {
+        SnapshotKind::Apfs
+    }
+
+    fn create(&self, source: &Path, label: &str) -> Result<SnapshotRecord> {
+        // "Created local snapshot with date: 2024-05-01-101500"
+        let out = run(Command::new("tmutil").arg("localsnapshot"))?;
+        let date = out
+            .rsplit(':')
+            .next()
+            .map(str::trim)
+            .filter(|d| !d.is_empty())
+            .ok_or_else(|| anyhow!("unexpected tmutil output: {out}"))?;
+        let rel = source
+            .canonicalize()?
+            .strip_prefix("/")
+            .map(Path::to_path_buf)?;
+        Ok(SnapshotRecord {
+            kind: self.kind(),
+            source: source.to_path_buf(),
+            id: format!("com.apple.TimeMachine.{date}.local"),
+            view_path: rel,
+            label: label.to_string(),
+            created_at: Utc::now(),
+        })
+    }
+
+    fn open(&self, record: &SnapshotRecord) -> Result<SnapshotView> {
+        let mount = std::env::temp_dir().join(format!("darklock-snap-{}", record.label));
+        fs::create_dir_all(&mount)?;
+        run(Command::new("mount_apfs")
+            .args(["-o", "rdonly", "-s"])
+            .arg(&record.id)
+            .arg(Self::volume())
+            .arg(&mount))?;
+        let root = mount.join(&record.view_path);
+        Ok(SnapshotView::with_cleanup(root, move || {
+            let _ = Command::new("umount").arg(&mount).status();
+            let _ = fs::remove_dir(&mount);
+        }))
+    }
+
+    fn delete(&self, record: &SnapshotRecord) -> Result<()> {
+        let date = record
+            .id
+            .trim_start_matches("com.apple.TimeMachine.")
+            .trim_end_matches(".local");
+        run(Command::new("tmutil").arg("deletelocalsnapshots").arg(date)).map(|_| ())
+    }
+}
+
+// ── Manager ─────────────────────────────────────────────────────────────────
+
+type Resolver = Box<dyn Fn(&Path) -> Option<Arc<dyn SnapshotBackend>> + Send + Sync>;
+
+/// Takes, indexes, prunes and restores from snapshots of protected paths.
+pub struct SnapshotManager {
+    index_path: PathBuf,
+    keep: usize,
+    records: Mutex<Vec<SnapshotRecord>>,
+    resolver: Resolver,
+}
+
+impl SnapshotManager {
+    /// Load the index at `index_path`, resolving backends by filesystem type.
+    pub fn new(index_path: PathBuf, keep: usize) -> Result<Self> {
+        Self::with_resolver(index_path, keep, system_backend)
+    }
+
+    pub fn with_resolver(
+        index_path: PathBuf,
+        keep: usize,
+        resolver: impl Fn(&Path) -> Option<Arc<dyn SnapshotBackend>> + Send + Sync + 'static,
+    ) -> Result<Self> {
+        let records = if index_path.exists() {
+            let data = fs::read(&index_path)
+                .with_context(|| format!("read {}", index_path.display()))?;
+            serde_json::from_slice(&data).context("parse snapshot index")?
+        } else {
+            Vec::new()
+        };
+        Ok(Self {
+            index_path,
+            keep: keep.max(1),
+            records: Mutex::new(records),
+            resolver: Box::new(resolver),
+        })
+    }
+
+    pub fn records(&self) -> Vec<SnapshotRecord> {
+        self.records.lock().clone()
+    }
+
+    /// Newest snapshot whose source is `dir`.
+    pub fn latest_for(&self, dir: &Path) -> Option<SnapshotRecord> {
+        self.records
+            .lock()
+            .iter()
+            .filter(|r| r.source == dir)
+            .max_by_key(|r| r.created_at)
+            .cloned()
+    }
+
+    /// Snapshot every directory in `paths` that has a backend. Paths without
+    /// one are skipped; per-path failures are returned, not fatal.
+    pub fn snapshot_paths(
+        &self,
+        paths: &[PathBuf],
+        label: &str,
+    ) -> Vec<(PathBuf, Result<SnapshotRecord>)> {
+        let mut out = Vec::new();
+        for path in paths.iter().filter(|p| p.is_dir()) {
+            let Some(backend) = (self.resolver)(path) else {
+                continue;
+            };
+            let result = backend.create(path, label);
+            if let Ok(record) = &result {
+                info!(path = %path.display(), id = %record.id, "snapshot created");
+                self.records.lock().push(record.clone());
+                self.prune(path, backend.as_ref());
+            }
+            out.push((path.clone(), result));
+        }
+        if let Err(e) = self.save() {
+            warn!(error = %e, "failed to persist snapshot index");
+        }
+        out
+    }
+
+    /// Drop the oldest snapshots of `source` beyond `keep`.
+    fn prune(&self, source: &Path, backend: &dyn SnapshotBackend) {
+        let mut records = self.records.lock();
+        let mut mine: Vec<SnapshotRecord> = records
+            .iter()
+            .filter(|r| r.source == source && r.kind == backend.kind())
+            .cloned()
+            .collect();
+        if mine.len() <= self.keep {
+            return;
+        }
+        mine.sort_by_key(|r| r.created_at);
+        let excess = mine.len() - self.keep;
+        for old in &mine[..excess] {
+            if let Err(e) = backend.delete(old) {
+                warn!(id = %old.id, error = %e, "failed to delete old snapshot");
+            }
+            records.retain(|r| r.id != old.id || r.source != old.source);
+        }
+    }
+
+    fn save(&self) -> Result<()> {
+        let data = serde_json::to_vec_pretty(&*self.records.lock())?;
+        let tmp = self.index_path.with_extension("json.tmp");
+        fs::write(&tmp, data)?;
+        fs::rename(&tmp, &self.index_path)?;
+        Ok(())
+    }
+
+    /// Bring `dir` back to its latest snapshot: files that differ or are
+    /// missing are rewritten atomically; files added since the snapshot are
+    /// left for the scanner to report. Paths being written are added to
+    /// `restoring` so the watcher doesn't treat them as tampering.
+    pub fn restore_dir(
+        &self,
+        dir: &Path,
+        restoring: &Mutex<HashSet<PathBuf>>,
+    ) -> Result<SnapshotRestoreReport> {
+        let record = self
+            .latest_for(dir)
+            .ok_or_else(|| anyhow!("no snapshot of {}", dir.display()))?;
+        let backend = (self.resolver)(dir)
+            .filter(|b| b.kind() == record.kind)
+            .ok_or_else(|| anyhow!("{:?} snapshots unavailable for {}", record.kind, dir.display()))?;
+        let view = backend.open(&record)?;
+        if !view.root().is_dir() {
+            return Err(anyhow!("snapshot view {} missing", view.root().display()));
+        }
+
+        let mut report = SnapshotRestoreReport {
+            path: dir.display().to_string(),
+            snapshot_id: record.id.clone(),
+            snapshot_created_at: Some(record.created_at),
+            ..Default::default()
+        };
+        let walker = WalkDir::new(view.root())
+            .follow_links(false)
+            .into_iter()
+            .filter_entry(|e| e.file_name() != SNAPSHOT_DIR_NAME);
+        for entry in walker {
+            let entry = match entry {
+                Ok(e) => e,
+                Err(e) => {
+                    report.failed.push(SnapshotRestoreFailure {
+                        path: dir.display().to_string(),
+                        error: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let rel = entry.path().strip_prefix(view.root())?;
+            let target = dir.join(rel);
+            match restore_one(entry.path(), &target, restoring) {
+                Ok(true) => report.restored += 1,
+                Ok(false) => report.unchanged += 1,
+                Err(e) => report.failed.push(SnapshotRestoreFailure {
+                    path: target.display().to_string(),
+                    error: e.to_string(),
+                }),
+            }
+        }
+        info!(
+            path = %dir.display(),
+            restored = report.restored,
+            failed = report.failed.len(),
+            "directory restored from snapshot"
+        );
+        Ok(report)
+    }
+}
+
+/// Copy `from` over `to` unless it already matches. Returns whether it wrote.
+/// Refuses when `to` or a directory above it is a symlink, and writes
+/// through a freshly created staging file, so a planted link can't redirect
+/// the write.
+fn restore_one(from: &Path, to: &Path, restoring: &Mutex<HashSet<PathBuf>>) -> Result<bool> {
+    if let Some((link, _)) = find_swapped_link(to, false) {
+        return Err(anyhow!(
+            "{} is a symlink — refusing restore to prevent escape",
+            link.display()
+        ));
+    }
+    if to.is_file() && hash_file(to)? == hash_file(from)? {
+        return Ok(false);
+    }
+    let parent = to
+        .parent()
+        .ok_or_else(|| anyhow!("no parent directory for {}", to.display()))?;
+    fs::create_dir_all(parent)?;
+    let staging = parent.join(format!("{}{:08x}", STAGING_PREFIX, rand::random::<u32>()));
+
+    restoring.lock().insert(to.to_path_buf());
+    let mut created = false;
+    let result = (|| {
+        let mut source = File::open(from)?;
+        let mut file = no_follow(OpenOptions::new().write(true).create_new(true))
+            .open(&staging)
+            .with_context(|| format!("create staging {}", staging.display()))?;
+        created = true;
+        io::copy(&mut source, &mut file)?;
+        file.set_permissions(source.metadata()?.permissions())?;
+        file.sync_all()?;
+        drop(file);
+        atomic_rename(&staging, to)
+    })();
+    if result.is_err() && created {
+        let _ = fs::remove_file(&staging);
+    }
+    restoring.lock().remove(to);
+    result.map(|_| true)
+}
+
+fn no_follow(options: &mut OpenOptions) -> &mut OpenOptions {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.custom_flags(libc::O_NOFOLLOW);
+    }
+    options
+}
+
+/// Snapshot label for a baseline taken at `at`.
+pub fn baseline_label(at: DateTime<Utc>) -> String {
+    format!("darklock-baseline-{}", at.format("%Y%m%dT%H%M%SZ"))
+}
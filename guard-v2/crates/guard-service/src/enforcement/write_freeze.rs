@@ -0,0 +1,193 @@
+//! Write freeze for protected directories.
+//!
+//! When a ransomware burst is suspected the service strips the write bits
+//! from every directory under the affected protected paths. That stops
+//! unprivileged processes creating, renaming or deleting entries (the
+//! "write `x.locked`, unlink `x`" pattern) until an operator releases the
+//! freeze. It cannot stop in-place overwrites of existing files or a process
+//! running as root; restores cover those. The service itself runs as root, so
+//! restores still work inside a frozen tree.
+//!
+//! Original modes are persisted to `<data_dir>/write_freeze.json` so a freeze
+//! survives a restart and can always be undone.
+
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use walkdir::WalkDir;
+
+use crate::enforcement::snapshot::SNAPSHOT_DIR_NAME;
+
+/// Directories whose modes are saved together during a freeze.
+const FREEZE_BATCH: usize = 64;
+
+pub struct WriteFreeze {
+    state_path: PathBuf,
+    /// Frozen directory → mode before the freeze.
+    frozen: Mutex<BTreeMap<PathBuf, u32>>,
+}
+
+impl WriteFreeze {
+    pub fn load(state_path: PathBuf) -> Result<Self> {
+        let frozen = if state_path.exists() {
+            let data = fs::read(&state_path)
+                .with_context(|| format!("read {}", state_path.display()))?;
+            serde_json::from_slice(&data).context("parse write freeze state")?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Self {
+            state_path,
+            frozen: Mutex::new(frozen),
+        })
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.frozen.lock().is_empty()
+    }
+
+    pub fn frozen_dirs(&self) -> Vec<PathBuf> {
+        self.frozen.lock().keys().cloned().collect()
+    }
+
+    /// Remove write permission from every directory under `roots`. Returns
+    /// the number of directories newly frozen.
+    pub fn freeze(&self, roots: &[PathBuf]) -> Result<usize> {
+        let mut frozen = self.frozen.lock();
+        let mut count = 0;
+        let mut batch = Vec::with_capacity(FREEZE_BATCH);
+        for root in roots.iter().filter(|r| r.is_dir()) {
+            let walker = WalkDir::new(root)
+                .follow_links(false)
+                .into_iter()
+                .filter_entry(|e| e.file_name() != SNAPSHOT_DIR_NAME);
+            for entry in walker.flatten().filter(|e| e.file_type().is_dir()) {
+                let dir = entry.path().to_path_buf();
+                if frozen.contains_key(&dir) {
+                    continue;
+                }
+                batch.push(dir);
+                if batch.len() == FREEZE_BATCH {
+                    count += self.freeze_batch(&mut frozen, &mut batch)?;
+                }
+            }
+        }
+        count += self.freeze_batch(&mut frozen, &mut batch)?;
+        self.save(&frozen)?;
+        info!(directories = count, "write freeze applied");
+        Ok(count)
+    }
+
+    /// Freeze the directories in `batch`, emptying it. Their original modes
+    /// are saved before any is changed, so a crash mid-walk can't lose them.
+    fn freeze_batch(
+        &self,
+        frozen: &mut BTreeMap<PathBuf, u32>,
+        batch: &mut Vec<PathBuf>,
+    ) -> Result<usize> {
+        let mut recorded = Vec::with_capacity(batch.len());
+        for dir in batch.drain(..) {
+            match current_mode(&dir) {
+                Ok(mode) => {
+                    frozen.insert(dir.clone(), mode);
+                    recorded.push(dir);
+                }
+                Err(e) => warn!(path = %dir.display(), error = %e, "cannot freeze directory"),
+            }
+        }
+        if recorded.is_empty() {
+            return Ok(0);
+        }
+        self.save(frozen)?;
+        let mut count = 0;
+        for dir in recorded {
+            match strip_write_bits(&dir, frozen[&dir]) {
+                Ok(()) => count += 1,
+                Err(e) => {
+                    frozen.remove(&dir);
+                    warn!(path = %dir.display(), error = %e, "cannot freeze directory");
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    /// Restore every frozen directory's original mode. Returns how many were
+    /// restored. Directories that can't be restored stay frozen, and on
+    /// disk, for the next release.
+    pub fn release(&self) -> Result<usize> {
+        let mut frozen = self.frozen.lock();
+        let mut restored = 0;
+        frozen.retain(|dir, mode| match restore_mode(dir, *mode) {
+            Ok(()) => {
+                restored += 1;
+                false
+            }
+            // Nothing left to unfreeze.
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => false,
+            Err(e) => {
+                warn!(path = %dir.display(), error = %e, "cannot release directory");
+                true
+            }
+        });
+        if frozen.is_empty() {
+            if self.state_path.exists() {
+                fs::remove_file(&self.state_path)?;
+            }
+        } else {
+            self.save(&frozen)?;
+            warn!(directories = frozen.len(), "write freeze only partly released");
+        }
+        info!(directories = restored, "write freeze released");
+        Ok(restored)
+    }
+
+    fn save(&self, frozen: &BTreeMap<PathBuf, u32>) -> Result<()> {
+        let tmp = self.state_path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(frozen)?)?;
+        fs::rename(&tmp, &self.state_path)?;
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn current_mode(dir: &Path) -> std::io::Result<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Ok(fs::metadata(dir)?.permissions().mode())
+}
+
+/// Clear the write bits of `mode` on `dir`.
+#[cfg(unix)]
+fn strip_write_bits(dir: &Path, mode: u32) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(dir, fs::Permissions::from_mode(mode & !0o222))
+}
+
+#[cfg(unix)]
+fn restore_mode(dir: &Path, mode: u32) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(dir, fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+fn current_mode(dir: &Path) -> std::io::Result<u32> {
+    let readonly = fs::metadata(dir)?.permissions().readonly();
+    Ok(if readonly { 0o555 } else { 0o755 })
+}
+
+#[cfg(not(unix))]
+fn strip_write_bits(dir: &Path, _mode: u32) -> std::io::Result<()> {
+    let mut perms = fs::metadata(dir)?.permissions();
+    perms.set_readonly(true);
+    fs::set_permissions(dir, perms)
+}
+
+#[cfg(not(unix))]
+fn restore_mode(dir: &Path, mode: u32) -> std::io::Result<()> {
+    let mut perms = fs::metadata(dir)?.permissions();
+    perms.set_readonly(mode & 0o200 == 0);
+    fs::set_permissions(dir, perms)
+}
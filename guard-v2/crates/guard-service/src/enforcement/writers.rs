@@ -0,0 +1,99 @@
+//! Finding and stopping the processes writing to a protected file.
+//!
+//! On Linux a process counts as a writer when one of its descriptors under
+//! `/proc/<pid>/fd` resolves to the file and its `fdinfo` flags show it was
+//! opened for writing. A writer may well have closed the file by the time the
+//! event reaches enforcement, so finding none is normal. Other platforms
+//! report no writers.
+
+use std::path::Path;
+
+/// Pids with `path` open for writing, excluding this process.
+#[cfg(target_os = "linux")]
+pub fn find_writers(path: &Path) -> Vec<u32> {
+    let own = std::process::id();
+    let Ok(procs) = std::fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    let mut writers: Vec<u32> = procs
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .filter(|&pid| pid != own && pid > 1)
+        .filter(|&pid| holds_for_writing(pid, path))
+        .collect();
+    writers.sort_unstable();
+    writers
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn find_writers(_path: &Path) -> Vec<u32> {
+    Vec::new()
+}
+
+#[cfg(target_os = "linux")]
+fn holds_for_writing(pid: u32, path: &Path) -> bool {
+    let Ok(fds) = std::fs::read_dir(format!("/proc/{pid}/fd")) else {
+        return false;
+    };
+    fds.flatten().any(|fd| {
+        std::fs::read_link(fd.path()).is_ok_and(|target| target == path)
+            && std::fs::read_to_string(format!(
+                "/proc/{pid}/fdinfo/{}",
+                fd.file_name().to_string_lossy()
+            ))
+            .ok()
+            .and_then(|info| {
+                info.lines()
+                    .find_map(|line| line.strip_prefix("flags:"))
+                    .and_then(|flags| u32::from_str_radix(flags.trim(), 8).ok())
+            })
+            .is_some_and(|flags| flags & libc::O_ACCMODE as u32 != libc::O_RDONLY as u32)
+    })
+}
+
+/// Kill `pid` outright; a writer caught tampering gets no chance to clean up.
+#[cfg(unix)]
+pub fn kill_process(pid: u32) -> std::io::Result<()> {
+    if pid <= 1 || pid == std::process::id() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            format!("refusing to kill pid {pid}"),
+        ));
+    }
+    // SAFETY: kill(2) has no memory-safety preconditions.
+    if unsafe { libc::kill(pid as libc::pid_t, libc::SIGKILL) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(unix))]
+pub fn kill_process(pid: u32) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        format!("killing pid {pid} is not supported on this platform"),
+    ))
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::process::Command;
+    use tempfile::tempdir;
+
+    #[test]
+    fn child_writing_to_file_is_found_and_killed() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().canonicalize().unwrap().join("target.log");
+        let out = std::fs::File::create(&path).unwrap();
+        let mut child = Command::new("sleep").arg("30").stdout(out).spawn().unwrap();
+
+        let writers = find_writers(&path);
+        assert_eq!(writers, vec![child.id()]);
+        kill_process(child.id()).unwrap();
+        assert!(!child.wait().unwrap().success());
+        assert!(find_writers(&path).is_empty());
+        assert!(kill_process(std::process::id()).is_err());
+    }
+}
@@ -11,24 +11,44 @@
 
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Duration as ChronoDuration, Utc};
-use ed25519_dalek::SigningKey;
+use ed25519_dalek::{SigningKey, VerifyingKey};
 use guard_core::backup_store::BackupStore;
 use guard_core::event_log::{EventLog, EventSeverity};
-use guard_core::settings::{GuardSettings, SecurityMode};
-use guard_core::storage::{load_settings, save_settings};
-use guard_core::vault::Vault;
+use guard_core::events::{GuardEvent, TamperKind};
+use guard_core::exclusion::{PathExclusion, MAX_EXCLUSION_SECS};
+use guard_core::health::ScanSummary;
+use guard_core::maintenance::{ChangeKind, MaintenanceJournal, JOURNAL_EVENT_LIMIT};
+use guard_core::policy::{decode_org_key, PolicyBundle, SignedPolicyBundle};
+use guard_core::sbom::{decode_vendor_key, SbomBinding, SignedSbomManifest};
+use guard_core::settings::{EnforcementAction, GuardSettings, SecurityMode};
+use guard_core::settings_history::SettingsVersion;
+use guard_core::storage::{
+    load_baseline_operator_key, load_baseline_operator_key_changes, load_exclusions, load_policy_bundle, load_policy_highest_version,
+    load_policy_org_key, load_sbom_bindings, load_settings, load_settings_history,
+    save_baseline_operator_key, save_baseline_operator_key_changes, save_exclusions, save_policy_bundle, save_policy_highest_version,
+    save_policy_org_key, save_sbom_bindings, save_settings, save_settings_history,
+};
+use guard_core::vault::{SecurityProfile, Vault};
 use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::{broadcast, watch};
 use tracing::{error, info, warn};
 
 use crate::enforcement::restore::{RestoreEngine, RestoreOutcome};
-use crate::integrity::pipeline::TamperEvent;
-use crate::integrity::scanner::{Baseline, IntegrityScanner};
+use crate::enforcement::snapshot::{baseline_label, SnapshotManager, SnapshotRestoreReport};
+use crate::enforcement::write_freeze::WriteFreeze;
+use crate::enforcement::writers::{find_writers, kill_process};
+use crate::integrity::attributes;
+use crate::integrity::burst::BurstReport;
+use crate::integrity::flap::{FlapSummary, FlapTracker, FlapVerdict};
+use crate::integrity::pipeline::{recheck_path, TamperEvent};
+use crate::integrity::sandbox::ScanWorker;
+use crate::integrity::scanner::{Baseline, BaselineEntry, HashProgress, IntegrityScanner};
+use crate::integrity::symlink::{is_symlink, normalize_path, remove_link};
 
 // ── Engine mode ─────────────────────────────────────────────────────────────
 
@@ -61,6 +81,9 @@ pub enum EngineEvent {
     BaselineUpdated { entries: usize },
     RestoreAttempt { path: String, outcome: String },
     ScanCompleted { violations: usize },
+    HashProgress { path: String, bytes_hashed: u64, total_bytes: u64 },
+    RansomwareSuspected { files_changed: usize },
+    TamperNotification { path: String, event: String },
 }
 
 // ── Settings validation (preserved) ─────────────────────────────────────────
@@ -83,17 +106,348 @@ fn validate_settings(settings: &GuardSettings) -> Result<()> {
     if settings.updates.channel != "stable" && settings.updates.channel != "beta" {
         anyhow::bail!("Update channel must be 'stable' or 'beta'");
     }
+    if settings.updates.check_interval_hours == 0 {
+        anyhow::bail!("Update check interval must be at least 1 hour");
+    }
+    if let Some(window) = settings.updates.install_window {
+        if window.start_hour > 23 || window.end_hour > 23 || window.start_hour == window.end_hour {
+            anyhow::bail!("Update install window hours must be 0-23 and differ");
+        }
+    }
+    if settings.restore.locked_retry_attempts > 10 {
+        anyhow::bail!("Locked restore retries must be at most 10");
+    }
+    for rule in &settings.hashing.quick_check {
+        if rule.path.trim().is_empty() || rule.min_size_mb == 0 {
+            anyhow::bail!("Quick-check rules need a path and a minimum size of at least 1 MB");
+        }
+    }
+    for rule in &settings.enforcement.rules {
+        if rule.actions.is_empty() {
+            anyhow::bail!("Enforcement rules need at least one action");
+        }
+        if rule.paths.iter().any(|p| p.trim().is_empty()) {
+            anyhow::bail!("Enforcement rule paths must not be empty");
+        }
+    }
+    if !(1..=100).contains(&settings.resources.warn_percent) {
+        anyhow::bail!("Resource warning threshold must be between 1 and 100 percent");
+    }
+    if settings.resources.check_interval_secs < 10 {
+        anyhow::bail!("Resource check interval must be at least 10 seconds");
+    }
+    if settings.backup_store.gc_interval_hours == 0 {
+        anyhow::bail!("Backup store GC interval must be at least 1 hour");
+    }
+    for name in &settings.attributes.xattrs {
+        if name.trim().is_empty() || !name.contains('.') {
+            anyhow::bail!("Tracked extended attributes need a namespace, e.g. user.origin");
+        }
+    }
+    let flapping = &settings.flapping;
+    if flapping.dedup_window_secs == 0 || flapping.flap_threshold < 2 {
+        anyhow::bail!("Flap suppression needs a window of at least 1 second and a threshold of at least 2");
+    }
+    if flapping.max_suppress_secs < flapping.dedup_window_secs {
+        anyhow::bail!("Longest flap suppression must be at least the dedup window");
+    }
+    if settings.scanner_sandbox.enabled && settings.scanner_sandbox.user.trim().is_empty() {
+        anyhow::bail!("The scan worker needs a user to run as");
+    }
     Ok(())
 }
 
+/// Load the stored policy bundle, ignoring it (with a warning) if it no longer
+/// verifies against the stored org key.
+fn load_verified_policy(vault: &Vault) -> Option<PolicyBundle> {
+    let signed = match load_policy_bundle(vault) {
+        Ok(Some(signed)) => signed,
+        Ok(None) => return None,
+        Err(e) => {
+            warn!("stored policy bundle unreadable: {e}");
+            return None;
+        }
+    };
+    let verified = load_policy_org_key(vault)
+        .and_then(|k| k.ok_or_else(|| anyhow!("no organization key configured")))
+        .and_then(|k| decode_org_key(&k))
+        .and_then(|key| signed.verify(&key));
+    match verified {
+        Ok(()) => Some(signed.bundle),
+        Err(e) => {
+            warn!("stored policy bundle rejected: {e}");
+            None
+        }
+    }
+}
+
+/// `TamperDetected` for `path`, carrying its baseline tags.
+fn tamper_detected(path: &Path, kind: TamperKind, baseline: &Baseline) -> GuardEvent {
+    let path = path.display().to_string();
+    GuardEvent::TamperDetected {
+        tags: baseline.tags_for(&path),
+        path,
+        kind,
+    }
+}
+
+/// Attach baseline tags to an event payload when there are any.
+fn with_tags(mut data: serde_json::Value, tags: &[String]) -> serde_json::Value {
+    if !tags.is_empty() {
+        data["tags"] = serde_json::json!(tags);
+    }
+    data
+}
+
+// ── Enforcement actions ─────────────────────────────────────────────────────
+
+/// Severity a tamper event is logged at and matched against rules with.
+fn tamper_severity(event: &TamperEvent) -> EventSeverity {
+    match event {
+        TamperEvent::PermissionChanged { .. } | TamperEvent::AttributesChanged { .. } => {
+            EventSeverity::Warn
+        }
+        TamperEvent::UnauthorizedFile {
+            suspicious_reasons, ..
+        } if suspicious_reasons.is_empty() => EventSeverity::Warn,
+        _ => EventSeverity::Critical,
+    }
+}
+
+/// Response when no enforcement rule matches: restore what the baseline
+/// covers, quarantine suspicious unknown files, leave other unknown files be.
+fn default_actions(event: &TamperEvent) -> Vec<EnforcementAction> {
+    match event {
+        TamperEvent::UnauthorizedFile {
+            suspicious_reasons, ..
+        } if suspicious_reasons.is_empty() => vec![EnforcementAction::Log],
+        TamperEvent::UnauthorizedFile { .. } => vec![EnforcementAction::Quarantine],
+        _ => vec![EnforcementAction::Restore],
+    }
+}
+
+/// Move `path` into the quarantine directory next to the backup store,
+/// deleting it if it can't be moved.
+fn quarantine_offender(
+    path: &Path,
+    reasons: &[String],
+    backup_store: &BackupStore,
+    event_log: &EventLog,
+) {
+    if !path.exists() {
+        return;
+    }
+    let quarantine_dir = backup_store.root().join("../quarantine");
+    let _ = std::fs::create_dir_all(&quarantine_dir);
+
+    let quarantine_name = format!(
+        "{}_{}",
+        Utc::now().format("%Y%m%d_%H%M%S"),
+        path.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "unknown".to_string())
+    );
+    let quarantine_path = quarantine_dir.join(&quarantine_name);
+
+    match std::fs::rename(path, &quarantine_path) {
+        Ok(_) => {
+            let _ = event_log.append(
+                "FILE_QUARANTINED",
+                EventSeverity::Warn,
+                serde_json::json!({
+                    "original_path": path.display().to_string(),
+                    "quarantine_path": quarantine_path.display().to_string(),
+                    "reasons": reasons,
+                }),
+            );
+            info!(
+                path = %path.display(),
+                quarantine = %quarantine_path.display(),
+                "file quarantined"
+            );
+        }
+        Err(e) => {
+            // Try delete as fallback
+            if std::fs::remove_file(path).is_ok() {
+                let _ = event_log.append(
+                    "FILE_REMOVED",
+                    EventSeverity::Warn,
+                    serde_json::json!({
+                        "path": path.display().to_string(),
+                        "reason": "quarantine failed, file removed",
+                        "error": e.to_string(),
+                    }),
+                );
+            } else {
+                let _ = event_log.append(
+                    "QUARANTINE_FAILED",
+                    EventSeverity::Critical,
+                    serde_json::json!({
+                        "path": path.display().to_string(),
+                        "error": e.to_string(),
+                    }),
+                );
+            }
+        }
+    }
+}
+
+/// Kill every process holding `path` open for writing.
+fn kill_writers(path: &Path, event_log: &EventLog) {
+    for pid in find_writers(path) {
+        let (event_type, error) = match kill_process(pid) {
+            Ok(()) => ("WRITER_KILLED", None),
+            Err(e) => ("WRITER_KILL_FAILED", Some(e.to_string())),
+        };
+        warn!(path = %path.display(), pid, ?error, "stopping writer of protected file");
+        let _ = event_log.append(
+            event_type,
+            EventSeverity::Critical,
+            serde_json::json!({
+                "path": path.display().to_string(),
+                "pid": pid,
+                "error": error,
+            }),
+        );
+    }
+}
+
 // ── Baseline helpers ────────────────────────────────────────────────────────
 
 const MAX_BASELINE_ARCHIVES: usize = 10;
 
+fn log_flap_summaries(summaries: &[FlapSummary], event_log: &EventLog) {
+    for summary in summaries {
+        let path = summary.path.display().to_string();
+        let mut data = serde_json::json!({
+            "path": path,
+            "kind": summary.kind,
+            "suppressed": summary.suppressed,
+            "suppressed_secs": summary.suppressed_secs,
+            "period": summary.period,
+            "message": format!("{} identical events suppressed", summary.suppressed),
+        });
+        if let Some(suggestion) = summary.suggestion {
+            data["suggestion"] = suggestion.into();
+            data["hint"] = match suggestion {
+                "allowlist" => format!(
+                    "{path} keeps appearing; add it to the baseline with a maintenance rebaseline"
+                ),
+                _ => format!(
+                    "{path} keeps changing; exclude it with `guard-cli exclude {path} --reason ...` or remove it from the protected paths"
+                ),
+            }
+            .into();
+        }
+        let _ = event_log.append("ALERTS_SUPPRESSED", EventSeverity::Info, data);
+    }
+}
+
 fn baselines_dir(data_dir: &Path) -> PathBuf {
     data_dir.join("baselines")
 }
 
+/// Generate the rebaseline candidate, back up its files and write it as the
+/// pending baseline, then validate it. A candidate that fails validation is
+/// discarded and `BASELINE_VALIDATION_FAILED` logged. Returns the candidate
+/// and the baseline it will replace.
+fn stage_rebaseline(
+    scanner: &IntegrityScanner,
+    signing_key: &SigningKey,
+    baseline_path: &Path,
+    backup_store: &mut BackupStore,
+    event_log: &EventLog,
+) -> Result<(Baseline, Option<Baseline>)> {
+    let previous = baseline_path
+        .exists()
+        .then(|| IntegrityScanner::load_baseline(baseline_path).ok())
+        .flatten();
+    let baseline = scanner.generate_baseline_with(signing_key, previous.as_ref())?;
+    stage_candidate(scanner, &baseline, signing_key, baseline_path, backup_store, event_log)?;
+    Ok((baseline, previous))
+}
+
+/// Under dual control, stage the candidate an operator has countersigned,
+/// held at `countersign_baseline_path`. Without one, a new candidate is
+/// generated and held there for countersigning instead, the active baseline
+/// stays in force and `BASELINE_COUNTERSIGN_REQUIRED` is logged.
+fn stage_countersigned_rebaseline(
+    scanner: &IntegrityScanner,
+    signing_key: &SigningKey,
+    operator_key: &VerifyingKey,
+    baseline_path: &Path,
+    backup_store: &mut BackupStore,
+    event_log: &EventLog,
+) -> Result<(Baseline, Option<Baseline>)> {
+    let held = IntegrityScanner::countersign_baseline_path(baseline_path);
+    let countersigned = held
+        .exists()
+        .then(|| IntegrityScanner::load_baseline(&held).ok())
+        .flatten()
+        .filter(|b| IntegrityScanner::verify_operator_signature(b, operator_key));
+    if let Some(baseline) = countersigned {
+        let previous = baseline_path
+            .exists()
+            .then(|| IntegrityScanner::load_baseline(baseline_path).ok())
+            .flatten();
+        stage_candidate(scanner, &baseline, signing_key, baseline_path, backup_store, event_log)?;
+        std::fs::remove_file(&held)?;
+        return Ok((baseline, previous));
+    }
+
+    let (baseline, _) =
+        stage_rebaseline(scanner, signing_key, baseline_path, backup_store, event_log)?;
+    std::fs::remove_file(IntegrityScanner::pending_baseline_path(baseline_path))?;
+    hold_for_countersign(&baseline, baseline_path, event_log, "maintenance")?;
+    Err(anyhow!(
+        "new baseline awaits an operator countersignature; countersign it and exit maintenance again"
+    ))
+}
+
+/// Write `baseline` as the pending baseline, back up its files and validate
+/// it. A candidate that fails validation is discarded and
+/// `BASELINE_VALIDATION_FAILED` logged.
+fn stage_candidate(
+    scanner: &IntegrityScanner,
+    baseline: &Baseline,
+    signing_key: &SigningKey,
+    baseline_path: &Path,
+    backup_store: &mut BackupStore,
+    event_log: &EventLog,
+) -> Result<()> {
+    let pending = IntegrityScanner::write_pending(baseline, baseline_path)?;
+
+    // Links are restored from their baseline entry and need no blob.
+    for entry in baseline.entries.values().filter(|e| e.link_target.is_none()) {
+        let p = PathBuf::from(&entry.path);
+        if let Err(e) = backup_store.ensure_from_disk(&p, &entry.hash, entry.permissions, None) {
+            warn!(path = %entry.path, error = %e, "backup update failed during rebaseline");
+        }
+    }
+
+    let problems =
+        scanner.validate_candidate(baseline, &signing_key.verifying_key(), backup_store);
+    if problems.is_empty() {
+        return Ok(());
+    }
+    let _ = std::fs::remove_file(&pending);
+    warn!(problems = problems.len(), "rebaseline candidate failed validation");
+    event_log.append(
+        "BASELINE_VALIDATION_FAILED",
+        EventSeverity::Warn,
+        serde_json::json!({
+            "entries": baseline.entries.len(),
+            "problems": problems.len(),
+            "first_problems": problems.iter().take(20).collect::<Vec<_>>(),
+        }),
+    )?;
+    Err(anyhow!(
+        "new baseline failed validation ({} problems, first: {}); still in maintenance mode",
+        problems.len(),
+        problems[0]
+    ))
+}
+
 fn archive_baseline(data_dir: &Path, current_path: &Path) -> Result<()> {
     let dir = baselines_dir(data_dir);
     std::fs::create_dir_all(&dir)?;
@@ -123,45 +477,480 @@ fn archive_baseline(data_dir: &Path, current_path: &Path) -> Result<()> {
     Ok(())
 }
 
+// ── Dual-control baselines ──────────────────────────────────────────────────
+
+const OPERATOR_KEY_CHANGE_CONTEXT: &[u8] = b"darklock-guard-operator-key-change-v2\0";
+
+/// The bytes `current_key` signs to authorize replacing itself with
+/// `new_key`, or turning dual control off when `None`, on `device_id` after
+/// `changes` earlier key changes. An authorization is therefore good for one
+/// change on one device and can't be replayed once the key has moved on.
+pub fn operator_key_change_message(
+    device_id: &str,
+    current_key: &str,
+    changes: u64,
+    new_key: Option<&str>,
+) -> Vec<u8> {
+    let mut msg = OPERATOR_KEY_CHANGE_CONTEXT.to_vec();
+    msg.extend_from_slice(device_id.as_bytes());
+    msg.push(0);
+    msg.extend_from_slice(current_key.trim().as_bytes());
+    msg.push(0);
+    msg.extend_from_slice(&changes.to_be_bytes());
+    msg.extend_from_slice(new_key.unwrap_or_default().trim().as_bytes());
+    msg
+}
+
+/// Hold `baseline` at `countersign_baseline_path` until an operator
+/// countersigns it, replacing any baseline already held. The active baseline
+/// stays in force; `BASELINE_COUNTERSIGN_REQUIRED` is logged.
+pub fn hold_for_countersign(
+    baseline: &Baseline,
+    baseline_path: &Path,
+    event_log: &EventLog,
+    source: &str,
+) -> Result<()> {
+    IntegrityScanner::save_baseline(
+        baseline,
+        &IntegrityScanner::countersign_baseline_path(baseline_path),
+    )?;
+    warn!(source, entries = baseline.entries.len(), "baseline held for operator countersignature");
+    event_log.append(
+        "BASELINE_COUNTERSIGN_REQUIRED",
+        EventSeverity::Critical,
+        serde_json::json!({
+            "source": source,
+            "entries": baseline.entries.len(),
+        }),
+    )?;
+    Ok(())
+}
+
+/// The operator key baselines must be countersigned with, `None` while dual
+/// control is off.
+pub fn baseline_operator_key(vault: &Vault) -> Result<Option<VerifyingKey>> {
+    load_baseline_operator_key(vault)?
+        .map(|key| decode_org_key(&key))
+        .transpose()
+}
+
+// ── Maintenance journal helpers ─────────────────────────────────────────────
+
+/// Outcome of `Engine::exit_maintenance`.
+pub struct MaintenanceExit {
+    pub baseline: Option<Baseline>,
+    pub journal: MaintenanceJournal,
+    pub report_path: Option<PathBuf>,
+}
+
+fn journal_tamper_event(journal: &mut MaintenanceJournal, event: &TamperEvent) {
+    let now = Utc::now();
+    let path = |p: &Path| p.display().to_string();
+    match event {
+        TamperEvent::Modified {
+            path: p,
+            expected_hash,
+            actual_hash,
+        } => journal.record(
+            path(p),
+            ChangeKind::Modified,
+            Some(expected_hash.clone()),
+            Some(actual_hash.clone()),
+            now,
+        ),
+        TamperEvent::Deleted {
+            path: p,
+            expected_hash,
+        } => journal.record(
+            path(p),
+            ChangeKind::Removed,
+            Some(expected_hash.clone()),
+            None,
+            now,
+        ),
+        TamperEvent::PermissionChanged { path: p, .. }
+        | TamperEvent::AttributesChanged { path: p, .. } => {
+            journal.record(path(p), ChangeKind::PermissionChanged, None, None, now)
+        }
+        TamperEvent::Renamed { from, to } => {
+            journal.record(path(from), ChangeKind::Removed, None, None, now);
+            journal.record(path(to), ChangeKind::Added, None, None, now);
+        }
+        TamperEvent::UnauthorizedFile {
+            path: p, file_hash, ..
+        } => journal.record(path(p), ChangeKind::Added, None, Some(file_hash.clone()), now),
+        TamperEvent::SymlinkSwap { link, .. } => {
+            journal.record(path(link), ChangeKind::Modified, None, None, now)
+        }
+    }
+}
+
+/// Ownership or attributes both entries recorded and that differ.
+fn attributes_differ(old: &BaselineEntry, new: &BaselineEntry) -> bool {
+    let ownership = matches!((&old.ownership, &new.ownership), (Some(a), Some(b)) if a != b);
+    let xattrs = matches!((&old.xattrs, &new.xattrs), (Some(a), Some(b)) if a != b);
+    ownership || xattrs
+}
+
+/// Record every difference between the baseline replaced by a rebaseline
+/// and its successor. These are authoritative for old / new hashes.
+fn journal_baseline_diff(journal: &mut MaintenanceJournal, old: &Baseline, new: &Baseline) {
+    let now = Utc::now();
+    for (path, entry) in &new.entries {
+        match old.entries.get(path) {
+            None => journal.record(
+                path.clone(),
+                ChangeKind::Added,
+                None,
+                Some(entry.hash.clone()),
+                now,
+            ),
+            Some(prev) if prev.hash != entry.hash => journal.record(
+                path.clone(),
+                ChangeKind::Modified,
+                Some(prev.hash.clone()),
+                Some(entry.hash.clone()),
+                now,
+            ),
+            Some(prev) if prev.permissions != entry.permissions || attributes_differ(prev, entry) => journal.record(
+                path.clone(),
+                ChangeKind::PermissionChanged,
+                Some(prev.hash.clone()),
+                Some(entry.hash.clone()),
+                now,
+            ),
+            Some(_) => {}
+        }
+    }
+    for (path, prev) in &old.entries {
+        if !new.entries.contains_key(path) {
+            journal.record(
+                path.clone(),
+                ChangeKind::Removed,
+                Some(prev.hash.clone()),
+                None,
+                now,
+            );
+        }
+    }
+}
+
+/// Add the change count, per-kind counts and the first
+/// `JOURNAL_EVENT_LIMIT` entries of `journal` to an event payload.
+fn merge_journal_summary(data: &mut serde_json::Value, journal: &MaintenanceJournal) {
+    let entries: Vec<_> = journal.entries.values().take(JOURNAL_EVENT_LIMIT).collect();
+    data["changes"] = serde_json::json!(journal.len());
+    data["change_counts"] = serde_json::json!(journal.counts());
+    data["journal"] = serde_json::json!(entries);
+    data["journal_truncated"] = serde_json::json!(journal.len() > JOURNAL_EVENT_LIMIT);
+}
+
+fn write_journal_report(data_dir: &Path, journal: &MaintenanceJournal) -> Result<PathBuf> {
+    let dir = data_dir.join("maintenance");
+    std::fs::create_dir_all(&dir)?;
+    let ts = journal.exited_at.unwrap_or_else(Utc::now).format("%Y%m%dT%H%M%S");
+    let dest = dir.join(format!("journal_{ts}.json"));
+    std::fs::write(&dest, serde_json::to_string_pretty(journal)?)?;
+    info!(path = %dest.display(), changes = journal.len(), "maintenance journal written");
+    Ok(dest)
+}
+
 // ── Engine ──────────────────────────────────────────────────────────────────
 
 pub struct Engine {
     settings: Arc<RwLock<GuardSettings>>,
+    policy: Arc<RwLock<Option<PolicyBundle>>>,
     mode: Arc<RwLock<EngineMode>>,
     queued_events: Arc<Mutex<VecDeque<TamperEvent>>>,
+    /// What the current maintenance window has changed.
+    journal: Arc<Mutex<Option<MaintenanceJournal>>>,
     event_tx: broadcast::Sender<EngineEvent>,
     last_daily_anchor: Arc<Mutex<DateTime<Utc>>>,
+    snapshots: Arc<RwLock<Option<Arc<SnapshotManager>>>>,
+    last_scan: Arc<Mutex<Option<ScanSummary>>>,
+    /// Per-path walk time of the last full scan.
+    last_scan_durations: Arc<Mutex<BTreeMap<String, u64>>>,
+    exclusions: Arc<RwLock<Vec<PathExclusion>>>,
+    flaps: Arc<Mutex<FlapTracker>>,
+    /// Paths whose suppression period ended with events dropped, to be
+    /// re-checked against the baseline.
+    flap_rechecks: Arc<Mutex<BTreeSet<PathBuf>>>,
 }
 
 impl Engine {
     /// Load settings from the vault, start in Active mode.
     pub fn load_from_vault(vault: &Vault) -> Result<Self> {
-        let settings = load_settings(vault)?;
+        let mut settings = load_settings(vault)?;
+        let policy = load_verified_policy(vault);
+        if let Some(bundle) = policy.as_ref().filter(|p| !p.is_expired(Utc::now())) {
+            settings = bundle.apply(&settings);
+        }
+        let exclusions = load_exclusions(vault)?;
         let (event_tx, _) = broadcast::channel(256);
         Ok(Self {
             settings: Arc::new(RwLock::new(settings)),
+            policy: Arc::new(RwLock::new(policy)),
             mode: Arc::new(RwLock::new(EngineMode::Active)),
             queued_events: Arc::new(Mutex::new(VecDeque::new())),
+            journal: Arc::new(Mutex::new(None)),
             event_tx,
             last_daily_anchor: Arc::new(Mutex::new(Utc::now())),
+            snapshots: Arc::new(RwLock::new(None)),
+            last_scan: Arc::new(Mutex::new(None)),
+            last_scan_durations: Arc::new(Mutex::new(BTreeMap::new())),
+            exclusions: Arc::new(RwLock::new(exclusions)),
+            flaps: Arc::new(Mutex::new(FlapTracker::new())),
+            flap_rechecks: Arc::new(Mutex::new(BTreeSet::new())),
         })
     }
 
+    /// An engine with this one's settings and none of its state: Active,
+    /// with no policy, exclusions or snapshots. Enforcement rules that pause
+    /// or alert then act on the copy, so the self-test can't change the
+    /// live engine's mode.
+    pub fn sandboxed(&self) -> Self {
+        let (event_tx, _) = broadcast::channel(256);
+        Self {
+            settings: Arc::new(RwLock::new(self.settings())),
+            policy: Arc::new(RwLock::new(None)),
+            mode: Arc::new(RwLock::new(EngineMode::Active)),
+            queued_events: Arc::new(Mutex::new(VecDeque::new())),
+            journal: Arc::new(Mutex::new(None)),
+            event_tx,
+            last_daily_anchor: Arc::new(Mutex::new(Utc::now())),
+            snapshots: Arc::new(RwLock::new(None)),
+            last_scan: Arc::new(Mutex::new(None)),
+            last_scan_durations: Arc::new(Mutex::new(BTreeMap::new())),
+            exclusions: Arc::new(RwLock::new(Vec::new())),
+            flaps: Arc::new(Mutex::new(FlapTracker::new())),
+            flap_rechecks: Arc::new(Mutex::new(BTreeSet::new())),
+        }
+    }
+
     // ── Settings ────────────────────────────────────────────────────────
 
     pub fn settings(&self) -> GuardSettings {
         self.settings.read().clone()
     }
 
+    /// `update_settings_by` on behalf of the local user.
+    #[allow(dead_code)]
     pub fn update_settings(
         &self,
         vault: &mut Vault,
         new_settings: GuardSettings,
     ) -> Result<()> {
+        self.update_settings_by(vault, new_settings, "local", None, None)
+            .map(|_| ())
+    }
+
+    /// Apply `new_settings` and add them to the settings history as made by
+    /// `changed_by`, with the caller's `note`, restoring version
+    /// `rollback_of` if set. Returns the new version, or `None` if nothing
+    /// changed.
+    pub fn update_settings_by(
+        &self,
+        vault: &mut Vault,
+        new_settings: GuardSettings,
+        changed_by: &str,
+        note: Option<&str>,
+        rollback_of: Option<u64>,
+    ) -> Result<Option<SettingsVersion>> {
+        if let Some(policy) = self.active_policy() {
+            let locked = policy.violations(&new_settings);
+            if !locked.is_empty() {
+                return Err(anyhow!(
+                    "settings locked by organization policy '{}': {}",
+                    policy.policy_id,
+                    locked.join(", ")
+                ));
+            }
+        }
         validate_settings(&new_settings)?;
+        let mut history = load_settings_history(vault)?;
+        let version = history
+            .record(
+                &self.settings(),
+                &new_settings,
+                changed_by,
+                note,
+                rollback_of,
+                Utc::now(),
+            )
+            .cloned();
         save_settings(vault, &new_settings)?;
+        if version.is_some() {
+            save_settings_history(vault, &history)?;
+        }
         *self.settings.write() = new_settings;
-        Ok(())
+        Ok(version)
+    }
+
+    /// Settings as they were at `version`, for a rollback.
+    pub fn settings_version(&self, vault: &Vault, version: u64) -> Result<GuardSettings> {
+        load_settings_history(vault)?
+            .get(version)
+            .map(|v| v.settings.clone())
+            .ok_or_else(|| anyhow!("no settings version {version} in history"))
+    }
+
+    // ── Organization policy ─────────────────────────────────────────────
+
+    /// The installed policy bundle, unless it has expired.
+    pub fn active_policy(&self) -> Option<PolicyBundle> {
+        self.policy
+            .read()
+            .clone()
+            .filter(|p| !p.is_expired(Utc::now()))
+    }
+
+    /// Configure the org public key. Refused while a bundle is active, so a
+    /// local user can't swap the key to install their own policy.
+    pub fn set_policy_org_key(&self, vault: &mut Vault, key_b64: &str) -> Result<()> {
+        if let Some(policy) = self.active_policy() {
+            return Err(anyhow!(
+                "org key cannot change while policy '{}' is active",
+                policy.policy_id
+            ));
+        }
+        decode_org_key(key_b64)?;
+        save_policy_org_key(vault, key_b64.trim())
+    }
+
+    /// What the current operator key signs to approve changing it to
+    /// `new_key`; `None` while no key is configured and no approval is needed.
+    pub fn operator_key_change_request(
+        &self,
+        vault: &Vault,
+        new_key: Option<&str>,
+    ) -> Result<Option<Vec<u8>>> {
+        let Some(current) = load_baseline_operator_key(vault)? else {
+            return Ok(None);
+        };
+        Ok(Some(operator_key_change_message(
+            &vault.payload.device_id,
+            &current,
+            load_baseline_operator_key_changes(vault)?,
+            new_key.map(str::trim),
+        )))
+    }
+
+    /// Turn dual-control baselines on, rotate the operator key, or turn them
+    /// off (`new_key` = `None`). Once a key is configured, changing it needs
+    /// `authorization`: the current operator key's hex signature over
+    /// `operator_key_change_request(new_key)`. Otherwise whoever controls
+    /// the service could swap in their own key.
+    pub fn set_baseline_operator_key(
+        &self,
+        vault: &mut Vault,
+        new_key: Option<&str>,
+        authorization: Option<&str>,
+    ) -> Result<()> {
+        let new_key = new_key.map(str::trim);
+        if let Some(key) = new_key {
+            decode_org_key(key).map_err(|e| anyhow!("operator key: {e}"))?;
+        }
+        let changes = load_baseline_operator_key_changes(vault)?;
+        if let Some(current) = load_baseline_operator_key(vault)? {
+            let message =
+                operator_key_change_message(&vault.payload.device_id, &current, changes, new_key);
+            let current = decode_org_key(&current)?;
+            let sig_hex =
+                authorization.ok_or_else(|| anyhow!("changing the operator key requires authorization from the current key"))?;
+            let sig_bytes = hex::decode(sig_hex.trim())
+                .map_err(|e| anyhow!("invalid authorization hex: {e}"))?;
+            let arr: [u8; 64] = sig_bytes
+                .try_into()
+                .map_err(|_| anyhow!("authorization signature length"))?;
+            current
+                .verify_strict(&message, &ed25519_dalek::Signature::from_bytes(&arr))
+                .map_err(|_| {
+                    anyhow!("authorization is not the current operator key's approval of this change")
+                })?;
+        }
+        save_baseline_operator_key(vault, new_key)?;
+        save_baseline_operator_key_changes(vault, changes + 1)
+    }
+
+    /// Verify `signed` against the configured org key and apply it.
+    pub fn apply_policy(
+        &self,
+        vault: &mut Vault,
+        signed: SignedPolicyBundle,
+    ) -> Result<PolicyBundle> {
+        let org_key = load_policy_org_key(vault)?
+            .ok_or_else(|| anyhow!("no organization key configured"))?;
+        signed.verify(&decode_org_key(&org_key)?)?;
+        let bundle = signed.bundle.clone();
+        if bundle.is_expired(Utc::now()) {
+            return Err(anyhow!("policy bundle has expired"));
+        }
+        // The stored bundle covers vaults from before the version was kept.
+        let highest = load_policy_highest_version(vault)?
+            .into_iter()
+            .chain(self.policy.read().as_ref().map(|p| p.version))
+            .max();
+        if let Some(highest) = highest {
+            if bundle.version <= highest {
+                return Err(anyhow!(
+                    "policy version {} does not supersede accepted version {}",
+                    bundle.version,
+                    highest
+                ));
+            }
+        }
+
+        let new_settings = bundle.apply(&self.settings());
+        validate_settings(&new_settings)?;
+        save_policy_bundle(vault, &signed)?;
+        save_policy_highest_version(vault, bundle.version)?;
+        save_settings(vault, &new_settings)?;
+        *self.settings.write() = new_settings;
+        *self.policy.write() = Some(bundle.clone());
+        Ok(bundle)
+    }
+
+    // ── Vendor manifests ────────────────────────────────────────────────
+
+    /// Verify `signed` against `vendor_key` and bind it to `root`, which must
+    /// be, or lie inside, a protected path. Replaces any manifest already
+    /// bound to the same directory.
+    pub fn import_sbom(
+        &self,
+        vault: &mut Vault,
+        root: &Path,
+        vendor_key: &str,
+        signed: SignedSbomManifest,
+    ) -> Result<SbomBinding> {
+        signed.verify(&decode_vendor_key(vendor_key)?)?;
+        let canonical = root
+            .canonicalize()
+            .map_err(|e| anyhow!("{}: {e}", root.display()))?;
+        if !canonical.is_dir() {
+            return Err(anyhow!("{} is not a directory", canonical.display()));
+        }
+        let protected = self
+            .settings()
+            .protection
+            .protected_paths
+            .iter()
+            .filter_map(|p| Path::new(p).canonicalize().ok())
+            .any(|p| canonical.starts_with(&p));
+        if !protected {
+            return Err(anyhow!("{} is not a protected directory", canonical.display()));
+        }
+
+        let binding = SbomBinding {
+            root: canonical.display().to_string(),
+            vendor_key: vendor_key.trim().to_string(),
+            signed,
+            imported_at: Utc::now(),
+        };
+        let mut bindings = load_sbom_bindings(vault)?;
+        bindings.retain(|b| b.root != binding.root);
+        bindings.push(binding.clone());
+        save_sbom_bindings(vault, &bindings)?;
+        Ok(binding)
     }
 
     // ── Mode queries ────────────────────────────────────────────────────
@@ -178,6 +967,65 @@ impl Engine {
         matches!(*self.mode.read(), EngineMode::Maintenance { .. })
     }
 
+    /// Tamper events held back while in maintenance mode.
+    pub fn queued_event_count(&self) -> usize {
+        self.queued_events.lock().len()
+    }
+
+    pub fn last_scan(&self) -> Option<ScanSummary> {
+        self.last_scan.lock().clone()
+    }
+
+    /// Remember the outcome of a full scan for status reporting.
+    pub fn record_scan(&self, result: &crate::integrity::scanner::ScanResult) {
+        *self.last_scan.lock() = Some(ScanSummary {
+            at: result.scanned_at,
+            files: result.total_files,
+            violations: result.modified.len()
+                + result.removed.len()
+                + result.attribute_paths().len(),
+        });
+        *self.last_scan_durations.lock() = result.root_durations_ms.clone();
+    }
+
+    pub fn last_scan_durations(&self) -> BTreeMap<String, u64> {
+        self.last_scan_durations.lock().clone()
+    }
+
+    /// Modified and removed files per protected path, keyed as configured.
+    pub fn violations_by_root(
+        &self,
+        result: &crate::integrity::scanner::ScanResult,
+    ) -> BTreeMap<String, usize> {
+        self.settings()
+            .protection
+            .protected_paths
+            .iter()
+            .map(|root| {
+                let canonical = normalize_path(Path::new(root), true);
+                let count = result
+                    .modified
+                    .iter()
+                    .map(|m| m.path.as_str())
+                    .chain(result.removed.iter().map(String::as_str))
+                    .chain(result.attribute_paths())
+                    .filter(|p| Path::new(p).starts_with(&canonical))
+                    .count();
+                (root.clone(), count)
+            })
+            .filter(|(_, n)| *n > 0)
+            .collect()
+    }
+
+    /// Publish progress through a large file being hashed.
+    pub fn publish_hash_progress(&self, progress: &HashProgress) {
+        let _ = self.event_tx.send(EngineEvent::HashProgress {
+            path: progress.path.display().to_string(),
+            bytes_hashed: progress.bytes_hashed,
+            total_bytes: progress.total_bytes,
+        });
+    }
+
     #[allow(dead_code)]
     pub fn subscribe(&self) -> broadcast::Receiver<EngineEvent> {
         self.event_tx.subscribe()
@@ -204,6 +1052,7 @@ impl Engine {
         };
         *self.mode.write() = mode.clone();
         self.queued_events.lock().clear();
+        *self.journal.lock() = Some(MaintenanceJournal::new(reason.clone(), now));
 
         event_log.append(
             "MAINTENANCE_ENTER",
@@ -228,68 +1077,98 @@ impl Engine {
         backup_store: &mut BackupStore,
         event_log: &EventLog,
         data_dir: &Path,
-    ) -> Result<Option<Baseline>> {
+        write_report: bool,
+        operator_key: Option<&VerifyingKey>,
+    ) -> Result<MaintenanceExit> {
         if !self.is_maintenance() {
             return Err(anyhow!("not in maintenance mode"));
         }
+
+        // Stage and validate the new baseline before leaving maintenance; on
+        // failure the active baseline and the maintenance session are kept.
+        // Under dual control (`operator_key`) only a countersigned one is.
+        let candidate = match (scanner.filter(|_| rebaseline), operator_key) {
+            (Some(scanner), None) => Some(stage_rebaseline(
+                scanner,
+                signing_key,
+                baseline_path,
+                backup_store,
+                event_log,
+            )?),
+            (Some(scanner), Some(operator_key)) => Some(stage_countersigned_rebaseline(
+                scanner,
+                signing_key,
+                operator_key,
+                baseline_path,
+                backup_store,
+                event_log,
+            )?),
+            (None, _) => None,
+        };
+
         let drained = self.queued_events.lock().len();
         self.queued_events.lock().clear();
+        let mut journal = self.journal.lock().take().unwrap_or_default();
 
-        let new_baseline = if rebaseline {
-            if let Some(scanner) = scanner {
-                // Archive current baseline before overwriting.
+        let new_baseline = match candidate {
+            Some((baseline, previous)) => {
+                // Archive current baseline before overwriting; its tags carry over.
                 if baseline_path.exists() {
                     archive_baseline(data_dir, baseline_path)?;
                 }
-                let baseline = scanner.generate_baseline(signing_key)?;
-                IntegrityScanner::save_baseline(&baseline, baseline_path)?;
-
-                // Update backup store for all files in new baseline.
-                for (_key, entry) in &baseline.entries {
-                    let p = PathBuf::from(&entry.path);
-                    if p.exists() {
-                        let perms = entry.permissions;
-                        if let Err(e) =
-                            backup_store.ensure_from_disk(&p, &entry.hash, perms, None)
-                        {
-                            warn!(path = %entry.path, error = %e, "backup update failed during rebaseline");
-                        }
-                    }
+                IntegrityScanner::commit_pending(baseline_path)?;
+                if let Some(previous) = &previous {
+                    journal_baseline_diff(&mut journal, previous, &baseline);
                 }
 
-                event_log.append(
-                    "BASELINE_UPDATED",
+                event_log.append_event(
                     EventSeverity::Info,
-                    serde_json::json!({
-                        "entries": baseline.entries.len(),
-                        "drained_events": drained,
-                    }),
+                    &GuardEvent::BaselineUpdated {
+                        entries: baseline.entries.len(),
+                        drained_events: Some(drained),
+                        source: None,
+                        paths: Vec::new(),
+                    },
                 )?;
                 let _ = self.event_tx.send(EngineEvent::BaselineUpdated {
                     entries: baseline.entries.len(),
                 });
+                self.snapshot_protected_paths(event_log);
                 Some(baseline)
-            } else {
-                None
             }
+            None => None,
+        };
+
+        journal.exited_at = Some(Utc::now());
+        journal.rebaselined = new_baseline.is_some();
+        let report_path = if write_report {
+            Some(write_journal_report(data_dir, &journal)?)
         } else {
             None
         };
 
         *self.mode.write() = EngineMode::Active;
-        event_log.append(
-            "MAINTENANCE_EXIT",
-            EventSeverity::Info,
-            serde_json::json!({
-                "rebaselined": rebaseline,
-                "drained_events": drained,
-            }),
-        )?;
+        let mut data = serde_json::json!({
+            "rebaselined": rebaseline,
+            "drained_events": drained,
+            "report": report_path.as_ref().map(|p| p.display().to_string()),
+        });
+        merge_journal_summary(&mut data, &journal);
+        event_log.append("MAINTENANCE_EXIT", EventSeverity::Info, data)?;
         let _ = self
             .event_tx
             .send(EngineEvent::ModeChanged(EngineMode::Active));
-        info!(rebaselined = rebaseline, drained = drained, "exited maintenance mode");
-        Ok(new_baseline)
+        info!(
+            rebaselined = rebaseline,
+            drained = drained,
+            changes = journal.len(),
+            "exited maintenance mode"
+        );
+        Ok(MaintenanceExit {
+            baseline: new_baseline,
+            journal,
+            report_path,
+        })
     }
 
     /// Force-exit maintenance after timeout — NO rebaseline.
@@ -298,12 +1177,11 @@ impl Engine {
             return Ok(());
         }
         self.queued_events.lock().clear();
+        let journal = self.journal.lock().take().unwrap_or_default();
         *self.mode.write() = EngineMode::Active;
-        event_log.append(
-            "MAINTENANCE_TIMEOUT",
-            EventSeverity::Warn,
-            serde_json::json!({}),
-        )?;
+        let mut data = serde_json::json!({});
+        merge_journal_summary(&mut data, &journal);
+        event_log.append("MAINTENANCE_TIMEOUT", EventSeverity::Warn, data)?;
         let _ = self
             .event_tx
             .send(EngineEvent::ModeChanged(EngineMode::Active));
@@ -327,75 +1205,530 @@ impl Engine {
             .send(EngineEvent::ModeChanged(EngineMode::Active));
     }
 
-    // ── Event routing ───────────────────────────────────────────────────
+    // ── Temporary exclusions ────────────────────────────────────────────
 
-    /// Process a `TamperEvent` from the watcher pipeline.
-    /// In Active mode → enforce immediately.
-    /// In Maintenance mode → queue (don't enforce).
-    /// In SafeMode → drop.
-    pub fn handle_tamper_event(
+    pub fn exclusions(&self) -> Vec<PathExclusion> {
+        self.exclusions.read().clone()
+    }
+
+    /// Whether an unexpired exclusion covers `path`.
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        let now = Utc::now();
+        self.exclusions
+            .read()
+            .iter()
+            .any(|e| !e.is_expired(now) && e.covers(path))
+    }
+
+    /// Stop enforcing `path` for `duration_secs`. The path must lie inside
+    /// (not be) a protected path. Refused under the ZeroTrust profile.
+    pub fn add_exclusion(
         &self,
-        event: &TamperEvent,
-        restore_engine: &RestoreEngine,
-        backup_store: &BackupStore,
-        baseline: &Baseline,
+        vault: &mut Vault,
+        path: &Path,
+        duration_secs: u64,
+        reason: String,
         event_log: &EventLog,
-    ) {
-        match *self.mode.read() {
-            EngineMode::Active => {
-                self.enforce_tamper(event, restore_engine, backup_store, baseline, event_log);
-            }
-            EngineMode::Maintenance { .. } => {
-                self.queued_events.lock().push_back(event.clone());
-            }
-            EngineMode::SafeMode => {
-                // Drop silently — safe mode means enforcement is paused.
-            }
+    ) -> Result<PathExclusion> {
+        let denied = |why: &str| {
+            let _ = event_log.append(
+                "EXCLUSION_DENIED",
+                EventSeverity::Warn,
+                serde_json::json!({"path": path.display().to_string(), "reason": why}),
+            );
+            anyhow!("exclusion refused: {why}")
+        };
+        if vault.payload.security_profile == SecurityProfile::ZeroTrust {
+            return Err(denied("temporary exclusions are disabled under the ZeroTrust profile"));
+        }
+        if reason.trim().is_empty() {
+            return Err(anyhow!("an exclusion needs a reason"));
         }
+        if duration_secs == 0 || duration_secs > MAX_EXCLUSION_SECS {
+            return Err(anyhow!(
+                "exclusion duration must be between 1 and {MAX_EXCLUSION_SECS} seconds"
+            ));
+        }
+        let canonical = path
+            .canonicalize()
+            .map_err(|e| anyhow!("{}: {e}", path.display()))?;
+        let inside_protected = self
+            .settings()
+            .protection
+            .protected_paths
+            .iter()
+            .filter_map(|p| Path::new(p).canonicalize().ok())
+            .any(|root| canonical.starts_with(&root) && canonical != root);
+        if !inside_protected {
+            return Err(denied("path is not inside a protected directory"));
+        }
+
+        let now = Utc::now();
+        let exclusion = PathExclusion {
+            path: canonical.display().to_string(),
+            reason,
+            created_at: now,
+            expires_at: now + ChronoDuration::seconds(duration_secs as i64),
+        };
+        let mut updated = self.exclusions();
+        updated.retain(|e| e.path != exclusion.path);
+        updated.push(exclusion.clone());
+        save_exclusions(vault, &updated)?;
+        *self.exclusions.write() = updated;
+
+        event_log.append(
+            "EXCLUSION_ADDED",
+            EventSeverity::Warn,
+            serde_json::to_value(&exclusion)?,
+        )?;
+        info!(path = %exclusion.path, until = %exclusion.expires_at, "temporary exclusion added");
+        Ok(exclusion)
     }
 
-    /// Process scan results from the audit loop.
-    pub fn handle_scan_result(
+    /// End the exclusion for `path` early. The caller re-baselines the path.
+    pub fn remove_exclusion(
         &self,
-        result: &crate::integrity::scanner::ScanResult,
-        restore_engine: &RestoreEngine,
-        backup_store: &BackupStore,
-        baseline: &Baseline,
+        vault: &mut Vault,
+        path: &str,
         event_log: &EventLog,
+    ) -> Result<Option<PathExclusion>> {
+        let canonical = Path::new(path)
+            .canonicalize()
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|_| path.to_string());
+        let mut updated = self.exclusions();
+        let Some(pos) = updated.iter().position(|e| e.path == canonical) else {
+            return Ok(None);
+        };
+        let removed = updated.remove(pos);
+        save_exclusions(vault, &updated)?;
+        *self.exclusions.write() = updated;
+        event_log.append(
+            "EXCLUSION_REMOVED",
+            EventSeverity::Info,
+            serde_json::json!({"path": removed.path, "reason": removed.reason}),
+        )?;
+        Ok(Some(removed))
+    }
+
+    /// Drop and return exclusions that have run out. The caller re-baselines
+    /// their paths.
+    pub fn take_expired_exclusions(
+        &self,
+        vault: &mut Vault,
+        event_log: &EventLog,
+    ) -> Result<Vec<PathExclusion>> {
+        let now = Utc::now();
+        let (expired, remaining): (Vec<_>, Vec<_>) =
+            self.exclusions().into_iter().partition(|e| e.is_expired(now));
+        if expired.is_empty() {
+            return Ok(expired);
+        }
+        save_exclusions(vault, &remaining)?;
+        *self.exclusions.write() = remaining;
+        for e in &expired {
+            event_log.append(
+                "EXCLUSION_EXPIRED",
+                EventSeverity::Info,
+                serde_json::json!({"path": e.path, "reason": e.reason}),
+            )?;
+        }
+        Ok(expired)
+    }
+
+    fn tamper_event_excluded(&self, event: &TamperEvent) -> bool {
+        self.is_excluded(event.path())
+    }
+
+    // ── Snapshots ───────────────────────────────────────────────────────
+
+    pub fn set_snapshot_manager(&self, manager: Option<Arc<SnapshotManager>>) {
+        *self.snapshots.write() = manager;
+    }
+
+    pub fn snapshot_manager(&self) -> Option<Arc<SnapshotManager>> {
+        self.snapshots.read().clone()
+    }
+
+    /// Snapshot the protected directories after a baseline was written.
+    /// A no-op without a snapshot manager or on filesystems without a backend.
+    pub fn snapshot_protected_paths(&self, event_log: &EventLog) {
+        let Some(manager) = self.snapshot_manager() else {
+            return;
+        };
+        let paths: Vec<PathBuf> = self
+            .settings()
+            .protection
+            .protected_paths
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        for (path, result) in manager.snapshot_paths(&paths, &baseline_label(Utc::now())) {
+            let path = path.display().to_string();
+            match result {
+                Ok(record) => {
+                    let _ = event_log.append(
+                        "SNAPSHOT_CREATED",
+                        EventSeverity::Info,
+                        serde_json::json!({"path": path, "kind": record.kind, "id": record.id}),
+                    );
+                }
+                Err(e) => {
+                    warn!(path = %path, error = %e, "snapshot failed");
+                    let _ = event_log.append(
+                        "SNAPSHOT_FAILED",
+                        EventSeverity::Warn,
+                        serde_json::json!({"path": path, "error": e.to_string()}),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Protected directories with at least the configured number of
+    /// violations in `result` and a snapshot to restore from.
+    fn widespread_roots(&self, result: &crate::integrity::scanner::ScanResult) -> Vec<PathBuf> {
+        let Some(manager) = self.snapshot_manager() else {
+            return Vec::new();
+        };
+        let settings = self.settings();
+        let threshold = settings.snapshots.widespread_threshold.max(1);
+        let violated: Vec<&str> = result
+            .modified
+            .iter()
+            .map(|m| m.path.as_str())
+            .chain(result.removed.iter().map(String::as_str))
+            .collect();
+        settings
+            .protection
+            .protected_paths
+            .iter()
+            .map(PathBuf::from)
+            .filter(|root| {
+                let canonical = normalize_path(root, true);
+                violated
+                    .iter()
+                    .filter(|p| Path::new(p).starts_with(&canonical))
+                    .count()
+                    >= threshold
+            })
+            .filter(|root| manager.latest_for(root).is_some())
+            .collect()
+    }
+
+    /// Restore `root` wholesale from its latest snapshot.
+    pub fn restore_from_snapshot(
+        &self,
+        root: &Path,
+        restore_engine: &RestoreEngine,
+        event_log: &EventLog,
+    ) -> Result<SnapshotRestoreReport> {
+        let manager = self
+            .snapshot_manager()
+            .ok_or_else(|| anyhow!("snapshot protection is disabled"))?;
+        match manager.restore_dir(root, &restore_engine.restoring) {
+            Ok(report) => {
+                let _ = event_log.append(
+                    "SNAPSHOT_RESTORE",
+                    EventSeverity::Warn,
+                    serde_json::json!({
+                        "path": report.path,
+                        "snapshot": report.snapshot_id,
+                        "restored": report.restored,
+                        "unchanged": report.unchanged,
+                        "failed": report.failed.len(),
+                    }),
+                );
+                let _ = self.event_tx.send(EngineEvent::RestoreAttempt {
+                    path: report.path.clone(),
+                    outcome: "snapshot_restored".into(),
+                });
+                Ok(report)
+            }
+            Err(e) => {
+                let _ = event_log.append(
+                    "SNAPSHOT_RESTORE_FAILURE",
+                    EventSeverity::Critical,
+                    serde_json::json!({"path": root.display().to_string(), "error": e.to_string()}),
+                );
+                Err(e)
+            }
+        }
+    }
+
+    // ── Ransomware response ─────────────────────────────────────────────
+
+    /// Record a suspected ransomware burst and, in Active mode, freeze writes
+    /// to the protected directories when `freeze` is given. Returns whether
+    /// the caller should continue with the protective response (restores,
+    /// safe mode); false in Maintenance and SafeMode.
+    pub fn handle_ransomware_suspected(
+        &self,
+        report: &BurstReport,
+        freeze: Option<&WriteFreeze>,
+        event_log: &EventLog,
+    ) -> bool {
+        let active = self.is_active();
+        let frozen = match freeze.filter(|_| active) {
+            Some(freeze) => {
+                let roots: Vec<PathBuf> = self
+                    .settings()
+                    .protection
+                    .protected_paths
+                    .iter()
+                    .map(PathBuf::from)
+                    .collect();
+                match freeze.freeze(&roots) {
+                    Ok(count) => Some(count),
+                    Err(e) => {
+                        error!(error = %e, "write freeze failed");
+                        None
+                    }
+                }
+            }
+            None => None,
+        };
+        let _ = event_log.append(
+            "RANSOMWARE_SUSPECTED",
+            EventSeverity::Critical,
+            serde_json::json!({
+                "files_changed": report.files_changed,
+                "extension_changes": report.extension_changes,
+                "dominant_extension": report.dominant_extension,
+                "window_secs": report.window_secs,
+                "paths": report.sample_paths,
+                "mode": self.mode(),
+                "frozen_directories": frozen,
+            }),
+        );
+        let _ = self.event_tx.send(EngineEvent::RansomwareSuspected {
+            files_changed: report.files_changed,
+        });
+        active
+    }
+
+    // ── Event routing ───────────────────────────────────────────────────
+
+    /// Process a `TamperEvent` from the watcher pipeline.
+    /// In Active mode → enforce immediately, unless the path is flapping.
+    /// In Maintenance mode → queue (don't enforce).
+    /// In SafeMode → drop.
+    pub fn handle_tamper_event(
+        &self,
+        event: &TamperEvent,
+        restore_engine: &RestoreEngine,
+        backup_store: &BackupStore,
+        baseline: &Baseline,
+        event_log: &EventLog,
+    ) {
+        if self.tamper_event_excluded(event) {
+            return;
+        }
+        // Not `match *self.mode.read()`: the guard would still be held when
+        // an enforcement action enters safe mode.
+        let mode = self.mode();
+        match mode {
+            EngineMode::Active => {
+                if self.admit_flapping(event, event_log) {
+                    self.enforce_tamper(event, restore_engine, backup_store, baseline, event_log);
+                }
+            }
+            EngineMode::Maintenance { .. } => {
+                self.queued_events.lock().push_back(event.clone());
+                if let Some(journal) = self.journal.lock().as_mut() {
+                    journal_tamper_event(journal, event);
+                }
+            }
+            EngineMode::SafeMode => {
+                // Drop silently — safe mode means enforcement is paused.
+            }
+        }
+    }
+
+    /// Run `event` through flap suppression; `false` if it is dropped.
+    fn admit_flapping(&self, event: &TamperEvent, event_log: &EventLog) -> bool {
+        let settings = self.settings.read().flapping.clone();
+        if !settings.enabled {
+            return true;
+        }
+        let now = Instant::now();
+        let (verdict, summaries) = {
+            let mut flaps = self.flaps.lock();
+            let verdict = flaps.observe(event.path(), event.kind(), &settings, now);
+            (verdict, flaps.take_summaries(&settings, now))
+        };
+        self.finish_flap_periods(&summaries, event_log);
+        match verdict {
+            FlapVerdict::Handle => true,
+            FlapVerdict::Suppress => false,
+            FlapVerdict::StartSuppressing {
+                events,
+                suppress_secs,
+            } => {
+                warn!(path = %event.path().display(), events, suppress_secs, "path is flapping");
+                let _ = event_log.append(
+                    "FLAPPING_DETECTED",
+                    EventSeverity::Warn,
+                    serde_json::json!({
+                        "path": event.path().display().to_string(),
+                        "kind": event.kind(),
+                        "events": events,
+                        "window_secs": settings.dedup_window_secs,
+                        "suppress_secs": suppress_secs,
+                    }),
+                );
+                true
+            }
+        }
+    }
+
+    /// Log summaries of flap suppression periods that have ended.
+    pub fn flush_flap_summaries(&self, event_log: &EventLog) {
+        let settings = self.settings.read().flapping.clone();
+        let summaries = self.flaps.lock().take_summaries(&settings, Instant::now());
+        self.finish_flap_periods(&summaries, event_log);
+    }
+
+    fn finish_flap_periods(&self, summaries: &[FlapSummary], event_log: &EventLog) {
+        log_flap_summaries(summaries, event_log);
+        self.flap_rechecks.lock().extend(
+            summaries
+                .iter()
+                .filter(|s| s.suppressed > 0)
+                .map(|s| s.path.clone()),
+        );
+    }
+
+    /// Re-check paths whose suppression period has ended, enforcing any
+    /// that were left different from the baseline.
+    pub fn recheck_flapped_paths(
+        &self,
+        restore_engine: &RestoreEngine,
+        backup_store: &BackupStore,
+        baseline: &Baseline,
+        event_log: &EventLog,
+        sandbox: Option<&ScanWorker>,
     ) {
+        let paths = std::mem::take(&mut *self.flap_rechecks.lock());
+        for path in paths {
+            if let Some(event) = recheck_path(&path, baseline, sandbox) {
+                self.handle_tamper_event(&event, restore_engine, backup_store, baseline, event_log);
+            }
+        }
+    }
+
+    /// Retry restores of locked targets whose back-off has elapsed.
+    pub fn retry_locked_restores(
+        &self,
+        restore_engine: &RestoreEngine,
+        backup_store: &BackupStore,
+        baseline: &Baseline,
+        event_log: &EventLog,
+    ) {
+        if !self.is_active() {
+            return;
+        }
+        for (path, outcome) in restore_engine.retry_locked(baseline, backup_store) {
+            self.log_restore(&path, &outcome, &baseline.tags_for(&path), event_log);
+        }
+    }
+
+    /// Process scan results from the audit loop.
+    pub fn handle_scan_result(
+        &self,
+        result: &crate::integrity::scanner::ScanResult,
+        restore_engine: &RestoreEngine,
+        backup_store: &BackupStore,
+        baseline: &Baseline,
+        event_log: &EventLog,
+    ) {
+        let filtered;
+        let result = if self.exclusions.read().is_empty() {
+            result
+        } else {
+            filtered = self.without_excluded(result);
+            &filtered
+        };
+        self.record_scan(result);
         if !self.is_active() {
             return;
         }
 
-        let violations = result.modified.len() + result.removed.len();
+        let attribute_paths = result.attribute_paths();
+        let violations = result.modified.len() + result.removed.len() + attribute_paths.len();
         if violations > 0 {
             // Log scan event
-            let _ = event_log.append(
-                "INTEGRITY_VIOLATION",
+            let _ = event_log.append_event(
                 EventSeverity::Critical,
-                serde_json::json!({
-                    "source": "audit_loop",
-                    "modified": result.modified.len(),
-                    "removed": result.removed.len(),
-                    "added": result.added.len(),
-                }),
+                &GuardEvent::IntegrityViolation {
+                    source: Some("audit_loop".into()),
+                    modified: result.modified.len(),
+                    removed: result.removed.len(),
+                    added: result.added.len(),
+                    attributes: attribute_paths.len(),
+                    tags: result.tag_counts(),
+                    roots: self.violations_by_root(result),
+                },
             );
 
-            // Enforce each violation
-            for mf in &result.modified {
-                let path = PathBuf::from(&mf.path);
-                if let Some(entry) = baseline.entries.get(&mf.path) {
-                    let outcome = restore_engine.restore_file(&path, entry, backup_store);
-                    self.log_restore(&mf.path, &outcome, event_log);
-                }
+            // Links go first: restoring through a swapped link would write
+            // wherever it points.
+            let mut swapped: Vec<PathBuf> = Vec::new();
+            for swap in &result.symlink_swaps {
+                let event = TamperEvent::SymlinkSwap {
+                    link: PathBuf::from(&swap.link),
+                    target: PathBuf::from(&swap.target),
+                };
+                self.enforce_tamper(&event, restore_engine, backup_store, baseline, event_log);
+                swapped.push(PathBuf::from(&swap.link));
             }
-            for removed_path in &result.removed {
-                if let Some(entry) = baseline.entries.get(removed_path) {
-                    let path = PathBuf::from(removed_path);
-                    let outcome = restore_engine.restore_file(&path, entry, backup_store);
-                    self.log_restore(removed_path, &outcome, event_log);
+
+            // Widespread damage under one root (e.g. ransomware) is undone
+            // from a snapshot in one pass; anything left falls through to
+            // per-file restore.
+            let mut snapshot_restored: Vec<PathBuf> = swapped;
+            for root in self.widespread_roots(result) {
+                if let Ok(report) = self.restore_from_snapshot(&root, restore_engine, event_log) {
+                    if report.failed.is_empty() {
+                        snapshot_restored.push(root.canonicalize().unwrap_or(root));
+                    }
                 }
             }
+            let covered = |p: &str| snapshot_restored.iter().any(|r| Path::new(p).starts_with(r));
+
+            // Enforce each violation; INTEGRITY_VIOLATION above stands in
+            // for the per-file detection events.
+            for mf in result.modified.iter().filter(|m| !covered(&m.path)) {
+                let event = TamperEvent::Modified {
+                    path: PathBuf::from(&mf.path),
+                    expected_hash: mf.expected_hash.clone(),
+                    actual_hash: mf.actual_hash.clone(),
+                };
+                self.run_enforcement(&event, restore_engine, backup_store, baseline, event_log);
+            }
+            for removed_path in result.removed.iter().filter(|p| !covered(p)) {
+                let event = TamperEvent::Deleted {
+                    path: PathBuf::from(removed_path),
+                    expected_hash: baseline
+                        .entries
+                        .get(removed_path)
+                        .map(|e| e.hash.clone())
+                        .unwrap_or_default(),
+                };
+                self.run_enforcement(&event, restore_engine, backup_store, baseline, event_log);
+            }
+            for path in attribute_paths.into_iter().filter(|p| !covered(p)) {
+                let event = TamperEvent::AttributesChanged {
+                    path: PathBuf::from(path),
+                    changes: result
+                        .attribute_changes
+                        .iter()
+                        .filter(|c| c.path == path)
+                        .cloned()
+                        .collect(),
+                };
+                self.run_enforcement(&event, restore_engine, backup_store, baseline, event_log);
+            }
         }
 
         let _ = self
@@ -403,6 +1736,23 @@ impl Engine {
             .send(EngineEvent::ScanCompleted { violations });
     }
 
+    fn without_excluded(
+        &self,
+        result: &crate::integrity::scanner::ScanResult,
+    ) -> crate::integrity::scanner::ScanResult {
+        let mut out = result.clone();
+        out.modified.retain(|m| !self.is_excluded(Path::new(&m.path)));
+        out.removed.retain(|p| !self.is_excluded(Path::new(p)));
+        out.added.retain(|p| !self.is_excluded(Path::new(p)));
+        out.tags.retain(|p, _| !self.is_excluded(Path::new(p)));
+        out.symlink_swaps.retain(|s| !self.is_excluded(Path::new(&s.link)));
+        out.attribute_changes.retain(|c| !self.is_excluded(Path::new(&c.path)));
+        out.valid = out.modified.is_empty()
+            && out.removed.is_empty()
+            && out.attribute_changes.is_empty();
+        out
+    }
+
     /// Check if daily anchor is due and fire it.
     pub fn maybe_daily_anchor(&self, event_log: &EventLog, data_dir: &Path) {
         let now = Utc::now();
@@ -448,93 +1798,248 @@ impl Engine {
         baseline: &Baseline,
         event_log: &EventLog,
     ) {
-        match event {
+        self.log_tamper(event, baseline, event_log);
+        self.run_enforcement(event, restore_engine, backup_store, baseline, event_log);
+    }
+
+    /// Record the detection itself; this happens whatever the actions are.
+    fn log_tamper(&self, event: &TamperEvent, baseline: &Baseline, event_log: &EventLog) {
+        let severity = tamper_severity(event);
+        let _ = match event {
             TamperEvent::Modified {
                 path,
                 expected_hash,
                 actual_hash,
-            } => {
-                let _ = event_log.append(
-                    "TAMPER_DETECTED",
-                    EventSeverity::Critical,
-                    serde_json::json!({
-                        "path": path.display().to_string(),
-                        "kind": "modified",
-                        "expected_hash": expected_hash,
-                        "actual_hash": actual_hash,
-                    }),
-                );
-                let key = path.display().to_string();
-                if let Some(entry) = baseline.entries.get(&key) {
-                    let outcome = restore_engine.restore_file(path, entry, backup_store);
-                    self.log_restore(&key, &outcome, event_log);
-                }
-            }
+            } => event_log.append_event(
+                severity,
+                &tamper_detected(
+                    path,
+                    TamperKind::Modified {
+                        expected_hash: expected_hash.clone(),
+                        actual_hash: actual_hash.clone(),
+                    },
+                    baseline,
+                ),
+            ),
             TamperEvent::Deleted {
                 path,
                 expected_hash,
-            } => {
-                let _ = event_log.append(
-                    "TAMPER_DETECTED",
-                    EventSeverity::Critical,
-                    serde_json::json!({
-                        "path": path.display().to_string(),
-                        "kind": "deleted",
-                        "expected_hash": expected_hash,
-                    }),
-                );
+            } => event_log.append_event(
+                severity,
+                &tamper_detected(
+                    path,
+                    TamperKind::Deleted {
+                        expected_hash: expected_hash.clone(),
+                    },
+                    baseline,
+                ),
+            ),
+            TamperEvent::PermissionChanged {
+                path,
+                expected_perms,
+                actual_perms,
+            } => event_log.append_event(
+                severity,
+                &tamper_detected(
+                    path,
+                    TamperKind::PermissionChanged {
+                        expected: *expected_perms,
+                        actual: *actual_perms,
+                    },
+                    baseline,
+                ),
+            ),
+            TamperEvent::AttributesChanged { path, changes } => event_log.append_event(
+                severity,
+                &tamper_detected(
+                    path,
+                    TamperKind::AttributesChanged {
+                        attributes: changes.iter().map(|c| c.attribute.clone()).collect(),
+                    },
+                    baseline,
+                ),
+            ),
+            TamperEvent::Renamed { from, to } => event_log.append_event(
+                severity,
+                &tamper_detected(
+                    from,
+                    TamperKind::Renamed {
+                        new_path: to.display().to_string(),
+                    },
+                    baseline,
+                ),
+            ),
+            TamperEvent::UnauthorizedFile {
+                path,
+                file_hash,
+                file_size,
+                suspicious_reasons,
+            } => event_log.append_event(
+                severity,
+                &GuardEvent::UnauthorizedFile {
+                    path: path.display().to_string(),
+                    file_hash: file_hash.clone(),
+                    file_size: *file_size,
+                    suspicious: !suspicious_reasons.is_empty(),
+                    reasons: suspicious_reasons.clone(),
+                },
+            ),
+            TamperEvent::SymlinkSwap { link, target } => event_log.append_event(
+                severity,
+                &tamper_detected(
+                    link,
+                    TamperKind::SymlinkSwap {
+                        target: target.display().to_string(),
+                    },
+                    baseline,
+                ),
+            ),
+        };
+    }
+
+    /// Run the actions configured for `event` in order, or only log them
+    /// under dry-run.
+    fn run_enforcement(
+        &self,
+        event: &TamperEvent,
+        restore_engine: &RestoreEngine,
+        backup_store: &BackupStore,
+        baseline: &Baseline,
+        event_log: &EventLog,
+    ) {
+        let severity = tamper_severity(event);
+        let path = event.path();
+        let settings = self.settings();
+        let actions = settings
+            .enforcement
+            .actions_for(path, &severity)
+            .map(<[EnforcementAction]>::to_vec)
+            .unwrap_or_else(|| default_actions(event));
+        if settings.enforcement.dry_run {
+            let _ = event_log.append(
+                "ENFORCEMENT_DRY_RUN",
+                EventSeverity::Info,
+                serde_json::json!({
+                    "path": path.display().to_string(),
+                    "event": event.kind(),
+                    "severity": severity,
+                    "actions": actions,
+                }),
+            );
+            return;
+        }
+        for action in actions {
+            match action {
+                EnforcementAction::Log => {}
+                EnforcementAction::Notify => {
+                    let _ = event_log.append(
+                        "TAMPER_NOTIFICATION",
+                        severity.clone(),
+                        serde_json::json!({
+                            "path": path.display().to_string(),
+                            "event": event.kind(),
+                        }),
+                    );
+                    let _ = self.event_tx.send(EngineEvent::TamperNotification {
+                        path: path.display().to_string(),
+                        event: event.kind().to_string(),
+                    });
+                }
+                EnforcementAction::Restore => {
+                    self.restore_tampered(event, restore_engine, backup_store, baseline, event_log)
+                }
+                EnforcementAction::Quarantine if !settings.protection.quarantine_enabled => {
+                    info!(path = %path.display(), "quarantine disabled; skipping quarantine action");
+                }
+                EnforcementAction::Quarantine => match event {
+                    TamperEvent::Modified { .. } => {
+                        quarantine_offender(path, &[], backup_store, event_log)
+                    }
+                    TamperEvent::UnauthorizedFile {
+                        suspicious_reasons, ..
+                    } => quarantine_offender(path, suspicious_reasons, backup_store, event_log),
+                    _ => {}
+                },
+                EnforcementAction::KillWriter => kill_writers(path, event_log),
+                EnforcementAction::SafeMode => {
+                    if self.mode() != EngineMode::SafeMode {
+                        self.enter_safe_mode();
+                        let _ = event_log.append(
+                            "SAFE_MODE_ENTERED",
+                            EventSeverity::Critical,
+                            serde_json::json!({
+                                "reason": "ENFORCEMENT_ACTION",
+                                "path": path.display().to_string(),
+                            }),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Put back the baselined state `event` disturbed.
+    fn restore_tampered(
+        &self,
+        event: &TamperEvent,
+        restore_engine: &RestoreEngine,
+        backup_store: &BackupStore,
+        baseline: &Baseline,
+        event_log: &EventLog,
+    ) {
+        match event {
+            TamperEvent::Modified { path, .. } | TamperEvent::Deleted { path, .. } => {
                 let key = path.display().to_string();
                 if let Some(entry) = baseline.entries.get(&key) {
                     let outcome = restore_engine.restore_file(path, entry, backup_store);
-                    self.log_restore(&key, &outcome, event_log);
+                    self.log_restore(&key, &outcome, &baseline.tags_for(&key), event_log);
                 }
             }
+            #[cfg(unix)]
             TamperEvent::PermissionChanged {
                 path,
                 expected_perms,
-                actual_perms,
+                ..
             } => {
-                let _ = event_log.append(
-                    "TAMPER_DETECTED",
-                    EventSeverity::Warn,
-                    serde_json::json!({
-                        "path": path.display().to_string(),
-                        "kind": "permission_changed",
-                        "expected": expected_perms,
-                        "actual": actual_perms,
-                    }),
-                );
-                // Restore permissions directly
-                #[cfg(unix)]
+                use std::os::unix::fs::PermissionsExt;
+                if let Err(e) =
+                    std::fs::set_permissions(path, std::fs::Permissions::from_mode(*expected_perms))
                 {
-                    use std::os::unix::fs::PermissionsExt;
-                    if let Err(e) = std::fs::set_permissions(
-                        path,
-                        std::fs::Permissions::from_mode(*expected_perms),
-                    ) {
-                        error!(path = %path.display(), error = %e, "failed to restore permissions");
-                    } else {
-                        let _ = event_log.append(
-                            "PERMISSIONS_RESTORED",
+                    error!(path = %path.display(), error = %e, "failed to restore permissions");
+                } else {
+                    let _ = event_log.append_event(
+                        EventSeverity::Warn,
+                        &GuardEvent::PermissionsRestored {
+                            path: path.display().to_string(),
+                            restored_perms: *expected_perms,
+                        },
+                    );
+                }
+            }
+            #[cfg(not(unix))]
+            TamperEvent::PermissionChanged { .. } => {}
+            TamperEvent::AttributesChanged { path, .. } => {
+                let key = path.display().to_string();
+                let Some(entry) = baseline.entries.get(&key) else {
+                    return;
+                };
+                match attributes::restore(path, entry, &baseline.tracked_xattrs) {
+                    Ok(restored) if !restored.is_empty() => {
+                        let _ = event_log.append_event(
                             EventSeverity::Warn,
-                            serde_json::json!({
-                                "path": path.display().to_string(),
-                                "restored_perms": expected_perms,
-                            }),
+                            &GuardEvent::AttributesRestored {
+                                path: key,
+                                attributes: restored,
+                            },
                         );
                     }
+                    Ok(_) => {}
+                    Err(e) => {
+                        error!(path = %path.display(), error = %e, "failed to restore attributes")
+                    }
                 }
             }
             TamperEvent::Renamed { from, to } => {
-                let _ = event_log.append(
-                    "TAMPER_DETECTED",
-                    EventSeverity::Critical,
-                    serde_json::json!({
-                        "path": from.display().to_string(),
-                        "kind": "renamed",
-                        "new_path": to.display().to_string(),
-                    }),
-                );
                 // Try to reverse the rename.
                 if to.exists() && !from.exists() {
                     if std::fs::rename(to, from).is_ok() {
@@ -551,110 +2056,73 @@ impl Engine {
                         let key = from.display().to_string();
                         if let Some(entry) = baseline.entries.get(&key) {
                             let outcome = restore_engine.restore_file(from, entry, backup_store);
-                            self.log_restore(&key, &outcome, event_log);
+                            self.log_restore(&key, &outcome, &baseline.tags_for(&key), event_log);
                         }
                     }
                 }
             }
-            TamperEvent::UnauthorizedFile {
-                path,
-                file_hash,
-                file_size,
-                suspicious_reasons,
-            } => {
-                let is_suspicious = !suspicious_reasons.is_empty();
-                let severity = if is_suspicious {
-                    EventSeverity::Critical
-                } else {
-                    EventSeverity::Warn
-                };
-                
-                let _ = event_log.append(
-                    "UNAUTHORIZED_FILE",
-                    severity,
-                    serde_json::json!({
-                        "path": path.display().to_string(),
-                        "kind": "unauthorized_new_file",
-                        "file_hash": file_hash,
-                        "file_size": file_size,
-                        "suspicious": is_suspicious,
-                        "reasons": suspicious_reasons,
-                    }),
+            TamperEvent::UnauthorizedFile { .. } => {
+                // Nothing in the baseline to put back.
+            }
+            TamperEvent::SymlinkSwap { link, .. } => {
+                self.undo_symlink_swap(link, restore_engine, backup_store, baseline, event_log);
+            }
+        }
+    }
+
+    /// Remove the swapped-in link at `link` and restore every baseline entry
+    /// at or beneath it.
+    fn undo_symlink_swap(
+        &self,
+        link: &Path,
+        restore_engine: &RestoreEngine,
+        backup_store: &BackupStore,
+        baseline: &Baseline,
+        event_log: &EventLog,
+    ) {
+        if is_symlink(link) {
+            if let Err(e) = remove_link(link) {
+                error!(link = %link.display(), error = %e, "failed to remove swapped symlink");
+                let key = link.display().to_string();
+                self.log_restore(
+                    &key,
+                    &RestoreOutcome::Failed {
+                        error: format!("remove symlink: {e}"),
+                    },
+                    &baseline.tags_for(&key),
+                    event_log,
                 );
-                
-                // Quarantine suspicious files automatically
-                if is_suspicious && path.exists() {
-                    let quarantine_dir = backup_store.root().join("../quarantine");
-                    let _ = std::fs::create_dir_all(&quarantine_dir);
-                    
-                    let quarantine_name = format!(
-                        "{}_{}",
-                        chrono::Utc::now().format("%Y%m%d_%H%M%S"),
-                        path.file_name()
-                            .map(|n| n.to_string_lossy().to_string())
-                            .unwrap_or_else(|| "unknown".to_string())
-                    );
-                    let quarantine_path = quarantine_dir.join(&quarantine_name);
-                    
-                    match std::fs::rename(path, &quarantine_path) {
-                        Ok(_) => {
-                            let _ = event_log.append(
-                                "FILE_QUARANTINED",
-                                EventSeverity::Warn,
-                                serde_json::json!({
-                                    "original_path": path.display().to_string(),
-                                    "quarantine_path": quarantine_path.display().to_string(),
-                                    "reasons": suspicious_reasons,
-                                }),
-                            );
-                            info!(
-                                path = %path.display(),
-                                quarantine = %quarantine_path.display(),
-                                "suspicious file quarantined"
-                            );
-                        }
-                        Err(e) => {
-                            // Try delete as fallback
-                            if std::fs::remove_file(path).is_ok() {
-                                let _ = event_log.append(
-                                    "FILE_REMOVED",
-                                    EventSeverity::Warn,
-                                    serde_json::json!({
-                                        "path": path.display().to_string(),
-                                        "reason": "quarantine failed, file removed",
-                                        "error": e.to_string(),
-                                    }),
-                                );
-                            } else {
-                                let _ = event_log.append(
-                                    "QUARANTINE_FAILED",
-                                    EventSeverity::Critical,
-                                    serde_json::json!({
-                                        "path": path.display().to_string(),
-                                        "error": e.to_string(),
-                                    }),
-                                );
-                            }
-                        }
-                    }
-                } else if !is_suspicious {
-                    // Non-suspicious unauthorized file — just log warning, don't quarantine
-                    info!(
-                        path = %path.display(),
-                        "non-suspicious unauthorized file detected (not quarantined)"
-                    );
-                }
+                return;
             }
         }
+        let mut affected: Vec<_> = baseline
+            .entries
+            .iter()
+            .filter(|(key, _)| Path::new(key).starts_with(link))
+            .collect();
+        affected.sort_by(|a, b| a.0.cmp(b.0));
+        for (key, entry) in affected {
+            let outcome = restore_engine.restore_file(Path::new(key), entry, backup_store);
+            self.log_restore(key, &outcome, &baseline.tags_for(key), event_log);
+        }
     }
 
-    fn log_restore(&self, path: &str, outcome: &RestoreOutcome, event_log: &EventLog) {
+    /// Log the outcome of a restore; `tags` are the baseline tags of `path`.
+    pub fn log_restore(
+        &self,
+        path: &str,
+        outcome: &RestoreOutcome,
+        tags: &[String],
+        event_log: &EventLog,
+    ) {
         match outcome {
             RestoreOutcome::Restored => {
-                let _ = event_log.append(
-                    "RESTORE_SUCCESS",
+                let _ = event_log.append_event(
                     EventSeverity::Warn,
-                    serde_json::json!({"path": path}),
+                    &GuardEvent::RestoreSuccess {
+                        path: path.to_string(),
+                        tags: tags.to_vec(),
+                    },
                 );
                 let _ = self.event_tx.send(EngineEvent::RestoreAttempt {
                     path: path.to_string(),
@@ -668,33 +2136,67 @@ impl Engine {
                 let _ = event_log.append(
                     "BACKUP_STORE_CORRUPTION",
                     EventSeverity::Critical,
-                    serde_json::json!({"path": p}),
+                    with_tags(serde_json::json!({"path": p}), tags),
                 );
             }
             RestoreOutcome::Quarantined { quarantine_path } => {
-                let _ = event_log.append(
-                    "RESTORE_FAILURE",
+                let _ = event_log.append_event(
                     EventSeverity::Critical,
-                    serde_json::json!({
-                        "path": path,
-                        "quarantined": quarantine_path.as_ref().map(|p| p.display().to_string()),
-                    }),
+                    &GuardEvent::RestoreFailure {
+                        path: path.to_string(),
+                        error: None,
+                        quarantined: quarantine_path.as_ref().map(|p| p.display().to_string()),
+                        tags: tags.to_vec(),
+                    },
                 );
             }
             RestoreOutcome::Failed { error } => {
-                let _ = event_log.append(
-                    "RESTORE_FAILURE",
+                let _ = event_log.append_event(
                     EventSeverity::Critical,
-                    serde_json::json!({"path": path, "error": error}),
+                    &GuardEvent::RestoreFailure {
+                        path: path.to_string(),
+                        error: Some(error.clone()),
+                        quarantined: None,
+                        tags: tags.to_vec(),
+                    },
                 );
             }
+            RestoreOutcome::Deferred {
+                error,
+                attempts,
+                queued,
+            } => {
+                let _ = event_log.append_event(
+                    EventSeverity::Warn,
+                    &GuardEvent::RestoreDeferred {
+                        path: path.to_string(),
+                        error: error.clone(),
+                        attempts: *attempts,
+                        queued: *queued,
+                        tags: tags.to_vec(),
+                    },
+                );
+                let _ = self.event_tx.send(EngineEvent::RestoreAttempt {
+                    path: path.to_string(),
+                    outcome: "deferred".into(),
+                });
+            }
+            RestoreOutcome::Retrying { .. } => {
+                // Logged by the restore engine; the last retry ends as
+                // Restored or Deferred.
+                let _ = self.event_tx.send(EngineEvent::RestoreAttempt {
+                    path: path.to_string(),
+                    outcome: "retrying".into(),
+                });
+            }
         }
     }
 }
 
 // ── Maintenance timeout watcher task ────────────────────────────────────────
 
-/// Spawns a tokio task that checks for maintenance timeout every 15 s.
+/// Spawns a tokio task that checks for maintenance timeout and ended flap
+/// suppression periods every 15 s.
 pub fn spawn_maintenance_watcher(
     engine: Arc<Engine>,
     event_log: Arc<EventLog>,
@@ -705,6 +2207,7 @@ pub fn spawn_maintenance_watcher(
             tokio::select! {
                 _ = tokio::time::sleep(Duration::from_secs(15)) => {
                     engine.check_maintenance_timeout(&event_log);
+                    engine.flush_flap_summaries(&event_log);
                 }
                 _ = shutdown.changed() => {
                     if *shutdown.borrow() { return; }
@@ -0,0 +1,483 @@
+//! File ownership and extended attributes recorded in baselines.
+//!
+//! On Unix a baseline entry records the owner uid/gid and the extended
+//! attributes the baseline tracks (`Baseline::tracked_xattrs`), values
+//! hex-encoded; on Windows the owner, group and DACL as one SDDL string.
+//! `current_changes` compares a file against its entry and `restore` puts
+//! the recorded values back. Attributes an entry has no record of (entries
+//! from older baselines, links) are never compared.
+
+use crate::integrity::scanner::{Baseline, BaselineEntry};
+use anyhow::{anyhow, Result};
+use guard_core::settings::AttributeSettings;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+/// Owner and group of a file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Ownership {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gid: Option<u32>,
+    /// Owner, group and DACL in SDDL form (Windows).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sddl: Option<String>,
+}
+
+/// Attributes recorded besides content and mode bits.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AttributeConfig {
+    pub ownership: bool,
+    /// Sorted and deduplicated.
+    pub xattrs: Vec<String>,
+}
+
+impl AttributeConfig {
+    pub fn from_settings(settings: &AttributeSettings) -> Self {
+        let xattrs: BTreeSet<&String> = settings.xattrs.iter().collect();
+        Self {
+            ownership: settings.track_ownership,
+            xattrs: xattrs.into_iter().cloned().collect(),
+        }
+    }
+
+    /// What `baseline` recorded, so refreshed entries match the rest.
+    pub fn of_baseline(baseline: &Baseline) -> Self {
+        Self {
+            ownership: baseline.entries.values().any(|e| e.ownership.is_some()),
+            xattrs: baseline.tracked_xattrs.clone(),
+        }
+    }
+}
+
+/// One attribute of a file that differs from its baseline entry.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AttributeChange {
+    pub path: String,
+    /// `uid`, `gid`, `sddl` or `xattr:<name>`.
+    pub attribute: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actual: Option<String>,
+}
+
+/// Tracked extended attribute values, hex-encoded, by name.
+pub type Xattrs = BTreeMap<String, String>;
+
+/// Ownership and tracked attributes of `path` per `config`; `None` for
+/// whatever `config` does not record.
+pub fn read(
+    path: &Path,
+    metadata: &fs::Metadata,
+    config: &AttributeConfig,
+) -> Result<(Option<Ownership>, Option<Xattrs>)> {
+    let ownership = if config.ownership {
+        Some(read_ownership(path, metadata)?)
+    } else {
+        None
+    };
+    let xattrs = if config.xattrs.is_empty() {
+        None
+    } else {
+        Some(read_xattrs(path, &config.xattrs)?)
+    };
+    Ok((ownership, xattrs))
+}
+
+#[cfg(unix)]
+fn read_ownership(_path: &Path, metadata: &fs::Metadata) -> Result<Ownership> {
+    use std::os::unix::fs::MetadataExt;
+    Ok(Ownership {
+        uid: Some(metadata.uid()),
+        gid: Some(metadata.gid()),
+        sddl: None,
+    })
+}
+
+#[cfg(windows)]
+fn read_ownership(path: &Path, _metadata: &fs::Metadata) -> Result<Ownership> {
+    Ok(Ownership {
+        sddl: Some(sddl::read(path)?),
+        ..Default::default()
+    })
+}
+
+#[cfg(not(any(unix, windows)))]
+fn read_ownership(_path: &Path, _metadata: &fs::Metadata) -> Result<Ownership> {
+    Ok(Ownership::default())
+}
+
+/// Values of the `names` present on `path`, hex-encoded. A file system
+/// without extended attributes has none.
+#[cfg(unix)]
+fn read_xattrs(path: &Path, names: &[String]) -> Result<BTreeMap<String, String>> {
+    let mut values = BTreeMap::new();
+    for name in names {
+        match xattr::get(path, name) {
+            Ok(Some(value)) => {
+                values.insert(name.clone(), hex::encode(value));
+            }
+            Ok(None) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::Unsupported => break,
+            Err(e) => return Err(anyhow!("read xattr {name} of {}: {e}", path.display())),
+        }
+    }
+    Ok(values)
+}
+
+#[cfg(not(unix))]
+fn read_xattrs(_path: &Path, _names: &[String]) -> Result<BTreeMap<String, String>> {
+    Ok(BTreeMap::new())
+}
+
+/// Differences between what `entry` recorded and `ownership` / `xattrs`
+/// as read now. `tracked` are the baseline's tracked attribute names.
+pub fn compare(
+    entry: &BaselineEntry,
+    ownership: Option<&Ownership>,
+    xattrs: Option<&BTreeMap<String, String>>,
+    tracked: &[String],
+) -> Vec<AttributeChange> {
+    let mut changes = Vec::new();
+    let mut push = |attribute: String, expected: Option<String>, actual: Option<String>| {
+        if expected != actual {
+            changes.push(AttributeChange {
+                path: entry.path.clone(),
+                attribute,
+                expected,
+                actual,
+            });
+        }
+    };
+    if let (Some(expected), Some(actual)) = (&entry.ownership, ownership) {
+        if expected.uid.is_some() {
+            push(
+                "uid".into(),
+                expected.uid.map(|u| u.to_string()),
+                actual.uid.map(|u| u.to_string()),
+            );
+        }
+        if expected.gid.is_some() {
+            push(
+                "gid".into(),
+                expected.gid.map(|g| g.to_string()),
+                actual.gid.map(|g| g.to_string()),
+            );
+        }
+        if expected.sddl.is_some() {
+            push("sddl".into(), expected.sddl.clone(), actual.sddl.clone());
+        }
+    }
+    if let (Some(expected), Some(actual)) = (&entry.xattrs, xattrs) {
+        for name in tracked {
+            push(
+                format!("xattr:{name}"),
+                expected.get(name).cloned(),
+                actual.get(name).cloned(),
+            );
+        }
+    }
+    changes
+}
+
+/// Attributes of `path` that differ from `entry` right now.
+pub fn current_changes(
+    path: &Path,
+    entry: &BaselineEntry,
+    tracked: &[String],
+) -> Result<Vec<AttributeChange>> {
+    if entry.link_target.is_some() || (entry.ownership.is_none() && entry.xattrs.is_none()) {
+        return Ok(Vec::new());
+    }
+    let config = AttributeConfig {
+        ownership: entry.ownership.is_some(),
+        xattrs: if entry.xattrs.is_some() {
+            tracked.to_vec()
+        } else {
+            Vec::new()
+        },
+    };
+    let metadata = fs::metadata(path)?;
+    let (ownership, xattrs) = read(path, &metadata, &config)?;
+    Ok(compare(entry, ownership.as_ref(), xattrs.as_ref(), tracked))
+}
+
+/// Put back the ownership and attributes `entry` recorded. Tracked
+/// attributes the entry has no value for are removed. Returns the
+/// attributes that were changed.
+pub fn restore(path: &Path, entry: &BaselineEntry, tracked: &[String]) -> Result<Vec<String>> {
+    // After a content restore the file is new, so every recorded attribute
+    // needs setting even if the baseline tracks none.
+    let mut names: Vec<String> = tracked.to_vec();
+    if let Some(recorded) = &entry.xattrs {
+        names.extend(recorded.keys().filter(|k| !tracked.contains(k)).cloned());
+    }
+    let changes = current_changes(path, entry, &names)?;
+    let (owner, xattrs): (Vec<_>, Vec<_>) = changes
+        .iter()
+        .partition(|c| !c.attribute.starts_with("xattr:"));
+    let mut restored = Vec::new();
+    let mut failed = Vec::new();
+    if let (false, Some(ownership)) = (owner.is_empty(), &entry.ownership) {
+        match set_ownership(path, ownership) {
+            Ok(()) => restored.extend(owner.iter().map(|c| c.attribute.clone())),
+            Err(e) => failed.push(format!("ownership: {e}")),
+        }
+    }
+    for change in xattrs {
+        let name = change.attribute.trim_start_matches("xattr:");
+        match set_xattr(path, name, change.expected.as_deref()) {
+            Ok(()) => restored.push(change.attribute.clone()),
+            Err(e) => failed.push(format!("{}: {e}", change.attribute)),
+        }
+    }
+    if !failed.is_empty() {
+        return Err(anyhow!(
+            "restore attributes of {}: {}",
+            path.display(),
+            failed.join("; ")
+        ));
+    }
+    Ok(restored)
+}
+
+#[cfg(unix)]
+fn set_ownership(path: &Path, ownership: &Ownership) -> Result<()> {
+    std::os::unix::fs::chown(path, ownership.uid, ownership.gid)?;
+    Ok(())
+}
+
+#[cfg(windows)]
+fn set_ownership(path: &Path, ownership: &Ownership) -> Result<()> {
+    match &ownership.sddl {
+        Some(text) => sddl::write(path, text),
+        None => Ok(()),
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn set_ownership(_path: &Path, _ownership: &Ownership) -> Result<()> {
+    Ok(())
+}
+
+/// Set `name` to the hex-encoded `value`, or remove it for `None`.
+#[cfg(unix)]
+fn set_xattr(path: &Path, name: &str, value: Option<&str>) -> Result<()> {
+    match value {
+        Some(value) => xattr::set(path, name, &hex::decode(value)?)?,
+        None => match xattr::remove(path, name) {
+            Ok(()) => {}
+            // Already gone.
+            Err(e) if e.raw_os_error() == Some(libc::ENODATA) => {}
+            Err(e) => return Err(e.into()),
+        },
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_xattr(_path: &Path, _name: &str, _value: Option<&str>) -> Result<()> {
+    Ok(())
+}
+
+/// Owner, group and DACL of a file as an SDDL string.
+#[cfg(windows)]
+mod sddl {
+    use anyhow::{anyhow, Result};
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::Path;
+    use windows_sys::Win32::Foundation::LocalFree;
+    use windows_sys::Win32::Security::Authorization::{
+        ConvertSecurityDescriptorToStringSecurityDescriptorW,
+        ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1,
+    };
+    use windows_sys::Win32::Security::{
+        GetFileSecurityW, SetFileSecurityW, DACL_SECURITY_INFORMATION, GROUP_SECURITY_INFORMATION,
+        OWNER_SECURITY_INFORMATION,
+    };
+
+    const INFO: u32 =
+        OWNER_SECURITY_INFORMATION | GROUP_SECURITY_INFORMATION | DACL_SECURITY_INFORMATION;
+
+    fn wide(path: &Path) -> Vec<u16> {
+        path.as_os_str().encode_wide().chain(Some(0)).collect()
+    }
+
+    pub fn read(path: &Path) -> Result<String> {
+        let name = wide(path);
+        let mut needed = 0u32;
+        // SAFETY: a null buffer of length 0 only asks for the size needed.
+        unsafe {
+            GetFileSecurityW(name.as_ptr(), INFO, std::ptr::null_mut(), 0, &mut needed);
+        }
+        if needed == 0 {
+            return Err(anyhow!(
+                "GetFileSecurityW failed: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+        let mut buf = vec![0u8; needed as usize];
+        // SAFETY: `buf` is `needed` bytes long.
+        let ok = unsafe {
+            GetFileSecurityW(
+                name.as_ptr(),
+                INFO,
+                buf.as_mut_ptr().cast(),
+                needed,
+                &mut needed,
+            )
+        };
+        if ok == 0 {
+            return Err(anyhow!(
+                "GetFileSecurityW failed: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+        let mut text: *mut u16 = std::ptr::null_mut();
+        // SAFETY: `buf` holds the descriptor written above; the returned
+        // string is freed below.
+        let ok = unsafe {
+            ConvertSecurityDescriptorToStringSecurityDescriptorW(
+                buf.as_mut_ptr().cast(),
+                SDDL_REVISION_1,
+                INFO,
+                &mut text,
+                std::ptr::null_mut(),
+            )
+        };
+        if ok == 0 || text.is_null() {
+            return Err(anyhow!(
+                "SDDL conversion failed: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+        // SAFETY: `text` is a NUL-terminated UTF-16 string owned by us.
+        let sddl = unsafe {
+            let len = (0..).take_while(|&i| *text.add(i) != 0).count();
+            let sddl = String::from_utf16_lossy(std::slice::from_raw_parts(text, len));
+            LocalFree(text as _);
+            sddl
+        };
+        Ok(sddl)
+    }
+
+    pub fn write(path: &Path, sddl: &str) -> Result<()> {
+        let name = wide(path);
+        let text: Vec<u16> = sddl.encode_utf16().chain(Some(0)).collect();
+        let mut descriptor = std::ptr::null_mut();
+        // SAFETY: `text` is NUL-terminated; the descriptor is freed below.
+        let ok = unsafe {
+            ConvertStringSecurityDescriptorToSecurityDescriptorW(
+                text.as_ptr(),
+                SDDL_REVISION_1,
+                &mut descriptor,
+                std::ptr::null_mut(),
+            )
+        };
+        if ok == 0 {
+            return Err(anyhow!("invalid SDDL: {}", std::io::Error::last_os_error()));
+        }
+        // SAFETY: `descriptor` came from the conversion above.
+        let ok = unsafe {
+            let ok = SetFileSecurityW(name.as_ptr(), INFO, descriptor);
+            LocalFree(descriptor as _);
+            ok
+        };
+        if ok == 0 {
+            return Err(anyhow!(
+                "SetFileSecurityW failed: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn entry(
+        ownership: Option<Ownership>,
+        xattrs: Option<BTreeMap<String, String>>,
+    ) -> BaselineEntry {
+        BaselineEntry {
+            path: "/srv/app".into(),
+            hash: String::new(),
+            size: 0,
+            modified: Utc::now(),
+            permissions: 0o755,
+            link_target: None,
+            quick_hash: None,
+            ownership,
+            xattrs,
+        }
+    }
+
+    #[test]
+    fn compares_only_what_the_entry_recorded() {
+        let owner = |uid| Ownership {
+            uid: Some(uid),
+            gid: Some(0),
+            sddl: None,
+        };
+        let tracked = ["security.capability".to_string()];
+        let caps = BTreeMap::from([("security.capability".to_string(), "01".to_string())]);
+
+        let recorded = entry(Some(owner(0)), Some(BTreeMap::new()));
+        assert!(compare(&recorded, Some(&owner(0)), Some(&BTreeMap::new()), &tracked).is_empty());
+
+        let changes = compare(&recorded, Some(&owner(1000)), Some(&caps), &tracked);
+        let attributes: Vec<_> = changes.iter().map(|c| c.attribute.as_str()).collect();
+        assert_eq!(attributes, ["uid", "xattr:security.capability"]);
+        assert_eq!(changes[1].expected, None);
+        assert_eq!(changes[1].actual.as_deref(), Some("01"));
+
+        // Entries from before attributes were recorded are never flagged.
+        let legacy = entry(None, None);
+        assert!(compare(&legacy, Some(&owner(1000)), Some(&caps), &tracked).is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn restores_user_xattrs() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("app");
+        fs::write(&file, b"x").unwrap();
+        if xattr::set(&file, "user.guard-test", b"v1").is_err() {
+            return; // file system without user xattrs
+        }
+        let tracked = [
+            "user.guard-test".to_string(),
+            "user.guard-extra".to_string(),
+        ];
+        let config = AttributeConfig {
+            ownership: true,
+            xattrs: tracked.to_vec(),
+        };
+        let (ownership, xattrs) = read(&file, &fs::metadata(&file).unwrap(), &config).unwrap();
+        let mut recorded = entry(ownership, xattrs);
+        recorded.path = file.display().to_string();
+
+        xattr::set(&file, "user.guard-test", b"v2").unwrap();
+        xattr::set(&file, "user.guard-extra", b"added").unwrap();
+        let changes = current_changes(&file, &recorded, &tracked).unwrap();
+        assert_eq!(changes.len(), 2);
+
+        let restored = restore(&file, &recorded, &tracked).unwrap();
+        assert_eq!(restored.len(), 2);
+        assert!(current_changes(&file, &recorded, &tracked)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            xattr::get(&file, "user.guard-test").unwrap().unwrap(),
+            b"v1"
+        );
+        assert!(xattr::get(&file, "user.guard-extra").unwrap().is_none());
+    }
+}
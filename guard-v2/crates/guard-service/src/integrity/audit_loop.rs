@@ -4,15 +4,20 @@
 //! configurable interval (default 5 minutes). This is the "belt" to the
 //! watcher's "suspenders" – it catches anything the watcher missed (restarts,
 //! NFS, event overflow, etc.).
+//!
+//! Scans run on the blocking pool so hashing large files doesn't stall the
+//! runtime; hashing progress counts as a heartbeat.
 
-use crate::integrity::scanner::{Baseline, IntegrityScanner, ScanResult};
+use crate::integrity::scanner::{Baseline, HashProgress, IntegrityScanner, ScanResult};
+use crate::supervisor::Heartbeat;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::{watch, Notify};
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
-/// Handle returned to the caller so it can request an immediate scan or shut
-/// the loop down.
+/// Control handle for the audit loop. Created by the caller and shared across
+/// restarts so the wake/shutdown channels survive a respawned task.
+#[derive(Clone)]
 pub struct AuditLoopHandle {
     /// Notify to wake the loop early (e.g. after maintenance exit).
     pub wake: Arc<Notify>,
@@ -20,32 +25,59 @@ pub struct AuditLoopHandle {
     pub shutdown_tx: watch::Sender<bool>,
 }
 
-/// Spawn the audit loop as a tokio task.  Returns a `JoinHandle` and an
-/// `AuditLoopHandle` for control.
+impl AuditLoopHandle {
+    pub fn new() -> Self {
+        let (shutdown_tx, _) = watch::channel(false);
+        Self {
+            wake: Arc::new(Notify::new()),
+            shutdown_tx,
+        }
+    }
+}
+
+impl Default for AuditLoopHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Spawn the audit loop as a tokio task.
 ///
 /// `on_result` is called after every scan with the `ScanResult`. The caller
-/// (the orchestrator) decides what to enforce.
+/// (the orchestrator) decides what to enforce. `heartbeat` is beaten on every
+/// wakeup so the supervisor can detect a hung loop.
 pub fn spawn_audit_loop<F>(
     scanner: Arc<IntegrityScanner>,
     interval: Duration,
     baseline_fn: Arc<dyn Fn() -> Option<Baseline> + Send + Sync>,
     on_result: F,
-) -> (tokio::task::JoinHandle<()>, AuditLoopHandle)
+    control: &AuditLoopHandle,
+    heartbeat: Heartbeat,
+) -> tokio::task::JoinHandle<()>
 where
     F: Fn(ScanResult) + Send + Sync + 'static,
 {
-    let wake = Arc::new(Notify::new());
-    let (shutdown_tx, mut shutdown_rx) = watch::channel(false);
-
-    let wake_clone = wake.clone();
+    let wake_clone = control.wake.clone();
+    let mut shutdown_rx = control.shutdown_tx.subscribe();
+    let scanner = {
+        let beat = heartbeat.clone();
+        let inner = scanner.progress();
+        Arc::new(scanner.as_ref().clone().with_progress(Arc::new(move |p: &HashProgress| {
+            beat.beat();
+            if let Some(inner) = &inner {
+                inner(p);
+            }
+        })))
+    };
 
-    let handle = tokio::spawn(async move {
+    tokio::spawn(async move {
         info!(
             interval_secs = interval.as_secs(),
             "audit loop started"
         );
 
         loop {
+            heartbeat.beat();
             tokio::select! {
                 _ = tokio::time::sleep(interval) => {}
                 _ = wake_clone.notified() => {
@@ -58,6 +90,7 @@ where
                     }
                 }
             }
+            heartbeat.beat();
 
             // Check shutdown again after wakeup.
             if *shutdown_rx.borrow() {
@@ -77,16 +110,13 @@ where
                 "audit loop: running periodic scan"
             );
 
-            let result = scanner.scan_against_baseline(&baseline);
-            on_result(result);
+            let scan = scanner.clone();
+            let result =
+                tokio::task::spawn_blocking(move || scan.scan_against_baseline(&baseline)).await;
+            match result {
+                Ok(result) => on_result(result),
+                Err(e) => warn!(error = %e, "audit scan task failed"),
+            }
         }
-    });
-
-    (
-        handle,
-        AuditLoopHandle {
-            wake,
-            shutdown_tx,
-        },
-    )
+    })
 }
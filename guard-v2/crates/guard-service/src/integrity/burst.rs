@@ -0,0 +1,321 @@
+//! Ransomware burst detection.
+//!
+//! Watches the raw watcher stream (before debouncing and baseline checks) for
+//! the shape of mass encryption: many distinct protected files changed within
+//! a few seconds, or files being renamed / rewritten under a new extension
+//! (`report.docx` → `report.docx.locked`). Either pattern crossing its
+//! threshold yields one `BurstReport`; the detector then stays quiet until a
+//! full window has passed so a single attack raises a single alert.
+
+use crate::enforcement::restore::is_staging_name;
+use crate::enforcement::snapshot::SNAPSHOT_DIR_NAME;
+use crate::integrity::watcher::FileChange;
+use crate::supervisor::Heartbeat;
+use guard_core::settings::RansomwareSettings;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tracing::{debug, warn};
+
+/// Paths included in a report, so the event stays small.
+const SAMPLE_PATHS: usize = 10;
+
+/// What tripped the detector.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BurstReport {
+    pub window_secs: u64,
+    /// Distinct files changed in the window.
+    pub files_changed: usize,
+    /// Files that reappeared under a different extension in the window.
+    pub extension_changes: usize,
+    /// Most common extension those files were given, if any.
+    pub dominant_extension: Option<String>,
+    pub sample_paths: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+struct Observation {
+    at: Instant,
+    path: PathBuf,
+    /// New extension if this change moved a file to a different extension.
+    new_extension: Option<String>,
+}
+
+/// Sliding-window burst detector. Pure logic; time is passed in.
+pub struct BurstDetector {
+    window: Duration,
+    file_threshold: usize,
+    rename_threshold: usize,
+    observations: VecDeque<Observation>,
+    /// File names removed or modified recently, for spotting
+    /// "write `x.locked`, delete `x`" without an actual rename.
+    recent_names: HashMap<String, Instant>,
+    quiet_until: Option<Instant>,
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension().map(|e| e.to_string_lossy().to_lowercase())
+}
+
+fn file_name(path: &Path) -> Option<String> {
+    path.file_name().map(|n| n.to_string_lossy().into_owned())
+}
+
+impl BurstDetector {
+    pub fn new(settings: &RansomwareSettings) -> Self {
+        Self {
+            window: Duration::from_secs(settings.window_secs.max(1)),
+            file_threshold: settings.file_threshold.max(1),
+            rename_threshold: settings.rename_threshold.max(1),
+            observations: VecDeque::new(),
+            recent_names: HashMap::new(),
+            quiet_until: None,
+        }
+    }
+
+    /// `to` took over `from`'s content under a new extension, e.g.
+    /// `a.docx` → `a.docx.enc` or `a.docx` → `a.enc`.
+    fn new_extension_of(from: &Path, to: &Path) -> Option<String> {
+        let new_ext = extension(to)?;
+        (extension(from).as_deref() != Some(new_ext.as_str())).then_some(new_ext)
+    }
+
+    /// Feed one raw change. Returns a report when a burst is recognised.
+    pub fn observe(&mut self, change: &FileChange, now: Instant) -> Option<BurstReport> {
+        let (path, new_extension) = match change {
+            FileChange::Modified(p) => {
+                if let Some(name) = file_name(p) {
+                    self.recent_names.insert(name, now);
+                }
+                (p.clone(), None)
+            }
+            FileChange::Removed(p) => {
+                if let Some(name) = file_name(p) {
+                    self.recent_names.insert(name, now);
+                }
+                (p.clone(), None)
+            }
+            FileChange::Created(p) => {
+                // `x.locked` created while `x` was just touched.
+                let stem = p.file_stem().map(|s| s.to_string_lossy().into_owned());
+                let derived = stem
+                    .as_ref()
+                    .filter(|s| self.recent_names.contains_key(s.as_str()))
+                    .and_then(|_| extension(p));
+                (p.clone(), derived)
+            }
+            FileChange::Renamed { from, to } => (to.clone(), Self::new_extension_of(from, to)),
+            FileChange::PermissionChanged(_) => return None,
+        };
+
+        self.observations.push_back(Observation {
+            at: now,
+            path,
+            new_extension,
+        });
+        self.expire(now);
+
+        if self.quiet_until.is_some_and(|until| now < until) {
+            return None;
+        }
+        let report = self.evaluate();
+        if report.is_some() {
+            self.quiet_until = Some(now + self.window);
+            self.observations.clear();
+            self.recent_names.clear();
+        }
+        report
+    }
+
+    fn expire(&mut self, now: Instant) {
+        while self
+            .observations
+            .front()
+            .is_some_and(|o| now.duration_since(o.at) > self.window)
+        {
+            self.observations.pop_front();
+        }
+        let window = self.window;
+        self.recent_names
+            .retain(|_, at| now.duration_since(*at) <= window);
+    }
+
+    fn evaluate(&self) -> Option<BurstReport> {
+        let distinct: HashSet<&PathBuf> = self.observations.iter().map(|o| &o.path).collect();
+        let mut extensions: BTreeMap<&str, usize> = BTreeMap::new();
+        for ext in self.observations.iter().filter_map(|o| o.new_extension.as_deref()) {
+            *extensions.entry(ext).or_default() += 1;
+        }
+        let extension_changes: usize = extensions.values().sum();
+
+        if distinct.len() < self.file_threshold && extension_changes < self.rename_threshold {
+            return None;
+        }
+        let dominant_extension = extensions
+            .iter()
+            .max_by_key(|(_, n)| **n)
+            .map(|(ext, _)| ext.to_string());
+        let mut sample_paths: Vec<String> = Vec::new();
+        for o in &self.observations {
+            let p = o.path.display().to_string();
+            if !sample_paths.contains(&p) {
+                sample_paths.push(p);
+            }
+            if sample_paths.len() == SAMPLE_PATHS {
+                break;
+            }
+        }
+        Some(BurstReport {
+            window_secs: self.window.as_secs(),
+            files_changed: distinct.len(),
+            extension_changes,
+            dominant_extension,
+            sample_paths,
+        })
+    }
+}
+
+/// Changes the service causes itself: restores and snapshot bookkeeping.
+fn is_own_activity(change: &FileChange, restoring: &parking_lot::Mutex<HashSet<PathBuf>>) -> bool {
+    let paths: Vec<&PathBuf> = match change {
+        FileChange::Renamed { from, to } => vec![from, to],
+        FileChange::Modified(p)
+        | FileChange::Created(p)
+        | FileChange::Removed(p)
+        | FileChange::PermissionChanged(p) => vec![p],
+    };
+    let restoring = restoring.lock();
+    paths.into_iter().any(|p| {
+        restoring.contains(p)
+            || p.components().any(|c| c.as_os_str() == SNAPSHOT_DIR_NAME)
+            || file_name(p).is_some_and(|n| is_staging_name(&n))
+    })
+}
+
+/// Spawn the burst detector on the raw watcher stream. `on_burst` runs on
+/// the detector task for every report.
+pub fn spawn_burst_detector<F>(
+    mut raw_rx: broadcast::Receiver<FileChange>,
+    settings: RansomwareSettings,
+    restoring: Arc<parking_lot::Mutex<HashSet<PathBuf>>>,
+    on_burst: F,
+    heartbeat: Heartbeat,
+    shutdown: tokio::sync::watch::Receiver<bool>,
+) -> tokio::task::JoinHandle<()>
+where
+    F: Fn(BurstReport) + Send + Sync + 'static,
+{
+    let mut shutdown = shutdown;
+    tokio::spawn(async move {
+        let mut detector = BurstDetector::new(&settings);
+        loop {
+            heartbeat.beat();
+            tokio::select! {
+                result = raw_rx.recv() => match result {
+                    Ok(change) => {
+                        if is_own_activity(&change, &restoring) {
+                            continue;
+                        }
+                        if let Some(report) = detector.observe(&change, Instant::now()) {
+                            warn!(
+                                files = report.files_changed,
+                                extension_changes = report.extension_changes,
+                                "ransomware burst suspected"
+                            );
+                            on_burst(report);
+                        }
+                    }
+                    // Dropping events under load is itself a burst signal, but
+                    // we can't attribute it; the audit loop will catch up.
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        warn!(missed = n, "burst detector lagged");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        debug!("watcher channel closed, burst detector exiting");
+                        return;
+                    }
+                },
+                _ = tokio::time::sleep(Duration::from_secs(5)) => {}
+                _ = shutdown.changed() => {
+                    if *shutdown.borrow() { return; }
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> RansomwareSettings {
+        RansomwareSettings {
+            window_secs: 5,
+            file_threshold: 10,
+            rename_threshold: 3,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn mass_modification_triggers_once_per_window() {
+        let mut d = BurstDetector::new(&settings());
+        let t0 = Instant::now();
+        let mut reports = 0;
+        for i in 0..25 {
+            let change = FileChange::Modified(PathBuf::from(format!("/p/f{i}.txt")));
+            if d.observe(&change, t0 + Duration::from_millis(i * 10)).is_some() {
+                reports += 1;
+            }
+        }
+        assert_eq!(reports, 1);
+
+        // Same rate spread well beyond the window is normal activity.
+        let mut d = BurstDetector::new(&settings());
+        for i in 0..25 {
+            let change = FileChange::Modified(PathBuf::from(format!("/p/f{i}.txt")));
+            assert!(d.observe(&change, t0 + Duration::from_secs(i)).is_none());
+        }
+    }
+
+    #[test]
+    fn extension_renames_and_rewrites_trigger() {
+        let mut d = BurstDetector::new(&settings());
+        let t0 = Instant::now();
+        let rename = |i: u64| FileChange::Renamed {
+            from: PathBuf::from(format!("/p/doc{i}.docx")),
+            to: PathBuf::from(format!("/p/doc{i}.docx.locked")),
+        };
+        assert!(d.observe(&rename(0), t0).is_none());
+        assert!(d.observe(&rename(1), t0).is_none());
+        let report = d.observe(&rename(2), t0).unwrap();
+        assert_eq!(report.extension_changes, 3);
+        assert_eq!(report.dominant_extension.as_deref(), Some("locked"));
+
+        // Encrypt-to-new-file then delete the original.
+        let mut d = BurstDetector::new(&settings());
+        let mut hit = None;
+        for i in 0..3 {
+            let _ = d.observe(&FileChange::Removed(PathBuf::from(format!("/p/a{i}.pdf"))), t0);
+            hit = d.observe(
+                &FileChange::Created(PathBuf::from(format!("/p/a{i}.pdf.crypt"))),
+                t0,
+            );
+        }
+        assert_eq!(hit.unwrap().dominant_extension.as_deref(), Some("crypt"));
+    }
+
+    #[test]
+    fn own_restores_are_ignored() {
+        let restoring = parking_lot::Mutex::new(HashSet::from([PathBuf::from("/p/a.txt")]));
+        assert!(is_own_activity(&FileChange::Modified(PathBuf::from("/p/a.txt")), &restoring));
+        assert!(is_own_activity(
+            &FileChange::Created(PathBuf::from("/p/.darklock_restore_b.txt")),
+            &restoring
+        ));
+        assert!(!is_own_activity(&FileChange::Modified(PathBuf::from("/p/b.txt")), &restoring));
+    }
+}
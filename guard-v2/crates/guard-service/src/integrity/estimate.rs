@@ -0,0 +1,218 @@
+//! Pre-scan estimate for `EstimatePath`, used by onboarding to show what
+//! protecting a directory would cost before it is added.
+//!
+//! One walk counts files, bytes and directories (one native watch each) and
+//! hashes the first `SAMPLE_BYTES` it meets to measure throughput on this
+//! disk. Directories that are usually regenerated or churn constantly —
+//! dependency trees, build output, caches, VCS metadata — are reported as
+//! suggested exclusions with their share of the total. Nothing is written.
+
+use anyhow::{anyhow, Result};
+use guard_core::health::{PathEstimate, SuggestedExclusion};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use walkdir::WalkDir;
+
+/// Bytes hashed to measure throughput.
+pub const SAMPLE_BYTES: u64 = 32 * 1024 * 1024;
+/// Below this much sample the measurement is noise; `DEFAULT_HASH_RATE`
+/// is used instead.
+const MIN_SAMPLE_BYTES: u64 = 1024 * 1024;
+const DEFAULT_HASH_RATE: u64 = 200 * 1024 * 1024;
+/// Open, stat and record cost per file on top of hashing.
+const PER_FILE_SECS: f64 = 0.000_05;
+
+/// Directory names suggested for exclusion, and why.
+const SUGGESTED: &[(&str, &str)] = &[
+    (
+        "node_modules",
+        "package dependencies, reinstalled on demand",
+    ),
+    (".venv", "Python virtual environment, recreated on demand"),
+    ("venv", "Python virtual environment, recreated on demand"),
+    (
+        "__pycache__",
+        "compiled Python bytecode, regenerated on import",
+    ),
+    ("target", "build output"),
+    ("build", "build output"),
+    ("dist", "build output"),
+    (".git", "version control metadata, changes on every commit"),
+    (".cache", "cache, rewritten constantly"),
+    ("cache", "cache, rewritten constantly"),
+    ("tmp", "temporary files"),
+    ("temp", "temporary files"),
+    ("logs", "logs, appended constantly"),
+];
+
+/// Estimate protecting `path`. `watches_in_use` and `watch_budget` come
+/// from the running watcher; `protected` is the current protected paths.
+pub fn estimate_path(
+    path: &Path,
+    watches_in_use: u64,
+    watch_budget: u64,
+    protected: &[String],
+) -> Result<PathEstimate> {
+    if !path.exists() {
+        return Err(anyhow!("{} does not exist", path.display()));
+    }
+    let mut estimate = PathEstimate {
+        path: path.display().to_string(),
+        files: 0,
+        directories: 0,
+        total_bytes: 0,
+        unreadable: 0,
+        hash_rate_bytes_per_sec: DEFAULT_HASH_RATE,
+        sampled_bytes: 0,
+        estimated_baseline_secs: 0.0,
+        watches: 0,
+        watches_in_use,
+        watch_budget,
+        exceeds_watch_budget: false,
+        covered_by: protected.iter().find(|p| path.starts_with(p)).cloned(),
+        suggested_exclusions: Vec::new(),
+    };
+    let mut sample_time = Duration::ZERO;
+    // The suggestion the walk is inside; the walk is depth-first, so it
+    // ends at the first entry outside it. Suggestions nested in it are not
+    // reported separately.
+    let mut current: Option<(PathBuf, usize)> = None;
+
+    for entry in WalkDir::new(path).follow_links(false) {
+        let Ok(entry) = entry else {
+            estimate.unreadable += 1;
+            continue;
+        };
+        if current
+            .as_ref()
+            .is_some_and(|(dir, _)| !entry.path().starts_with(dir))
+        {
+            current = None;
+        }
+        if entry.file_type().is_dir() {
+            estimate.directories += 1;
+            if current.is_none() && entry.depth() > 0 {
+                let name = entry.file_name().to_string_lossy();
+                if let Some((_, reason)) = SUGGESTED.iter().find(|(n, _)| *n == name) {
+                    estimate.suggested_exclusions.push(SuggestedExclusion {
+                        path: entry.path().display().to_string(),
+                        reason: (*reason).to_string(),
+                        files: 0,
+                        total_bytes: 0,
+                    });
+                    current = Some((
+                        entry.path().to_path_buf(),
+                        estimate.suggested_exclusions.len() - 1,
+                    ));
+                }
+            }
+            continue;
+        }
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else {
+            estimate.unreadable += 1;
+            continue;
+        };
+        estimate.files += 1;
+        estimate.total_bytes += metadata.len();
+        if let Some((_, index)) = &current {
+            let suggestion = &mut estimate.suggested_exclusions[*index];
+            suggestion.files += 1;
+            suggestion.total_bytes += metadata.len();
+        }
+        if estimate.sampled_bytes < SAMPLE_BYTES {
+            let started = Instant::now();
+            match hash_prefix(entry.path(), SAMPLE_BYTES - estimate.sampled_bytes) {
+                Ok(hashed) => {
+                    estimate.sampled_bytes += hashed;
+                    sample_time += started.elapsed();
+                }
+                Err(_) => estimate.unreadable += 1,
+            }
+        }
+    }
+
+    if estimate.sampled_bytes >= MIN_SAMPLE_BYTES && !sample_time.is_zero() {
+        estimate.hash_rate_bytes_per_sec =
+            (estimate.sampled_bytes as f64 / sample_time.as_secs_f64()) as u64;
+    }
+    estimate.estimated_baseline_secs = estimate.total_bytes as f64
+        / estimate.hash_rate_bytes_per_sec.max(1) as f64
+        + estimate.files as f64 * PER_FILE_SECS;
+    // Same count as `watcher::estimate_watches`, without a second walk.
+    estimate.watches = if path.is_dir() {
+        estimate.directories
+    } else {
+        1
+    };
+    estimate.exceeds_watch_budget = watches_in_use.saturating_add(estimate.watches) > watch_budget;
+    estimate
+        .suggested_exclusions
+        .sort_by_key(|s| std::cmp::Reverse(s.total_bytes));
+    Ok(estimate)
+}
+
+/// Hash up to `limit` bytes of `path`, returning how many were read.
+fn hash_prefix(path: &Path, limit: u64) -> std::io::Result<u64> {
+    let mut file = std::fs::File::open(path)?.take(limit);
+    let mut hasher = blake3::Hasher::new();
+    let mut buf = [0u8; 64 * 1024];
+    let mut read = 0;
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        read += n as u64;
+    }
+    let _ = hasher.finalize();
+    Ok(read)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_tree_and_suggests_generated_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for (file, size) in [
+            ("src/main.rs", 100),
+            ("node_modules/left-pad/index.js", 4000),
+            ("node_modules/left-pad/.cache/x", 10),
+            (".git/HEAD", 20),
+        ] {
+            let path = root.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, vec![0u8; size]).unwrap();
+        }
+
+        let protected = vec![root.display().to_string()];
+        let estimate = estimate_path(&root.join("src"), 10, 1000, &protected).unwrap();
+        assert_eq!(estimate.covered_by.as_deref(), Some(protected[0].as_str()));
+
+        let estimate = estimate_path(root, 10, 14, &[]).unwrap();
+        assert_eq!(estimate.files, 4);
+        assert_eq!(estimate.total_bytes, 4130);
+        assert_eq!(estimate.sampled_bytes, 4130);
+        // root, src, node_modules, left-pad, .cache, .git
+        assert_eq!(estimate.watches, 6);
+        assert!(estimate.exceeds_watch_budget);
+        assert!(estimate.covered_by.is_none());
+
+        let suggested: Vec<(&str, u64, u64)> = estimate
+            .suggested_exclusions
+            .iter()
+            .map(|s| {
+                let name = Path::new(&s.path).file_name().unwrap().to_str().unwrap();
+                (name, s.files, s.total_bytes)
+            })
+            .collect();
+        assert_eq!(suggested, [("node_modules", 2, 4010), (".git", 1, 20)]);
+    }
+}
@@ -0,0 +1,269 @@
+//! Flap suppression for realtime tamper events.
+//!
+//! A file rewritten every second by a misconfigured application would
+//! otherwise log a detection and run a restore on every write. The tracker
+//! counts identical events (same path, same kind) per dedup window; a path
+//! that reaches the threshold is flapping. The event that trips it is still
+//! enforced; later ones are only counted for a suppression period — one
+//! window the first time, doubling each time it flaps again, up to the
+//! configured maximum. When a period ends the tracker yields a
+//! `FlapSummary` and the engine re-checks the path, restoring it if it was
+//! left changed. A path quiet for the longest suppression period is
+//! forgotten and starts over.
+
+use guard_core::settings::FlapSettings;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FlapVerdict {
+    /// Log and enforce the event as usual.
+    Handle,
+    /// The path is flapping; drop the event.
+    Suppress,
+    /// This event made the path flap: enforce it and record the start of a
+    /// suppression period.
+    StartSuppressing { events: usize, suppress_secs: u64 },
+}
+
+/// End of one suppression period.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlapSummary {
+    pub path: PathBuf,
+    pub kind: &'static str,
+    /// Events dropped during the period.
+    pub suppressed: usize,
+    pub suppressed_secs: u64,
+    /// 1 for the path's first suppression period, 2 for the next...
+    pub period: u32,
+    /// `exclude` or `allowlist`, once the path has flapped `suggest_after`
+    /// times. Made once per path.
+    pub suggestion: Option<&'static str>,
+}
+
+struct Flap {
+    window_start: Instant,
+    in_window: usize,
+    last_seen: Instant,
+    /// Start and end of the current suppression period.
+    suppressing: Option<(Instant, Instant)>,
+    suppressed: usize,
+    periods: u32,
+    suggested: bool,
+}
+
+/// Per-path flap state. Pure logic; time is passed in.
+#[derive(Default)]
+pub struct FlapTracker {
+    flaps: HashMap<(PathBuf, &'static str), Flap>,
+    finished: Vec<FlapSummary>,
+}
+
+impl FlapTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn observe(
+        &mut self,
+        path: &Path,
+        kind: &'static str,
+        settings: &FlapSettings,
+        now: Instant,
+    ) -> FlapVerdict {
+        let window = Duration::from_secs(settings.dedup_window_secs.max(1));
+        let key = (path.to_path_buf(), kind);
+        let flap = self.flaps.entry(key.clone()).or_insert(Flap {
+            window_start: now,
+            in_window: 0,
+            last_seen: now,
+            suppressing: None,
+            suppressed: 0,
+            periods: 0,
+            suggested: false,
+        });
+        flap.last_seen = now;
+        if let Some((_, until)) = flap.suppressing {
+            if now < until {
+                flap.suppressed += 1;
+                return FlapVerdict::Suppress;
+            }
+            let summary = close_period(&key, flap, settings);
+            self.finished.push(summary);
+        }
+
+        if now.duration_since(flap.window_start) > window {
+            flap.window_start = now;
+            flap.in_window = 0;
+        }
+        flap.in_window += 1;
+        if flap.in_window < settings.flap_threshold.max(2) {
+            return FlapVerdict::Handle;
+        }
+        let suppress_secs = window
+            .as_secs()
+            .saturating_mul(1 << flap.periods.min(20))
+            .min(settings.max_suppress_secs.max(window.as_secs()));
+        flap.periods += 1;
+        flap.suppressing = Some((now, now + Duration::from_secs(suppress_secs)));
+        flap.suppressed = 0;
+        FlapVerdict::StartSuppressing {
+            events: flap.in_window,
+            suppress_secs,
+        }
+    }
+
+    /// Summaries of the suppression periods that have ended, oldest first.
+    pub fn take_summaries(&mut self, settings: &FlapSettings, now: Instant) -> Vec<FlapSummary> {
+        let forget_after = Duration::from_secs(settings.max_suppress_secs);
+        let mut finished = std::mem::take(&mut self.finished);
+        self.flaps.retain(|key, flap| match flap.suppressing {
+            Some((_, until)) if now >= until => {
+                finished.push(close_period(key, flap, settings));
+                true
+            }
+            Some(_) => true,
+            None => now.duration_since(flap.last_seen) <= forget_after,
+        });
+        finished
+    }
+}
+
+fn close_period(
+    (path, kind): &(PathBuf, &'static str),
+    flap: &mut Flap,
+    settings: &FlapSettings,
+) -> FlapSummary {
+    let (from, until) = flap
+        .suppressing
+        .take()
+        .expect("closing a suppression period");
+    let suggestion = (flap.periods >= settings.suggest_after && !flap.suggested).then(|| {
+        flap.suggested = true;
+        // A file that isn't baselined can be allowed by adding it; a
+        // baselined one that keeps changing wants excluding.
+        if *kind == "unauthorized_file" {
+            "allowlist"
+        } else {
+            "exclude"
+        }
+    });
+    flap.window_start = until;
+    flap.in_window = 0;
+    FlapSummary {
+        path: path.clone(),
+        kind,
+        suppressed: std::mem::take(&mut flap.suppressed),
+        suppressed_secs: until.duration_since(from).as_secs(),
+        period: flap.periods,
+        suggestion,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> FlapSettings {
+        FlapSettings {
+            enabled: true,
+            dedup_window_secs: 10,
+            flap_threshold: 3,
+            max_suppress_secs: 60,
+            suggest_after: 2,
+        }
+    }
+
+    #[test]
+    fn flapping_path_is_suppressed_for_doubling_periods() {
+        let settings = settings();
+        let mut tracker = FlapTracker::new();
+        let path = Path::new("/srv/app.conf");
+        let t0 = Instant::now();
+        let at = |secs: u64| t0 + Duration::from_secs(secs);
+
+        assert_eq!(
+            tracker.observe(path, "modified", &settings, at(0)),
+            FlapVerdict::Handle
+        );
+        assert_eq!(
+            tracker.observe(path, "modified", &settings, at(1)),
+            FlapVerdict::Handle
+        );
+        // Another kind on the same path is counted separately.
+        assert_eq!(
+            tracker.observe(path, "deleted", &settings, at(1)),
+            FlapVerdict::Handle
+        );
+        assert_eq!(
+            tracker.observe(path, "modified", &settings, at(2)),
+            FlapVerdict::StartSuppressing {
+                events: 3,
+                suppress_secs: 10
+            }
+        );
+        for s in 3..12 {
+            assert_eq!(
+                tracker.observe(path, "modified", &settings, at(s)),
+                FlapVerdict::Suppress
+            );
+        }
+        let summaries = tracker.take_summaries(&settings, at(12));
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(summaries[0].suppressed, 9);
+        assert_eq!(summaries[0].suppressed_secs, 10);
+        assert_eq!(summaries[0].suggestion, None);
+
+        // Flapping again right away doubles the period and earns a suggestion.
+        tracker.observe(path, "modified", &settings, at(12));
+        tracker.observe(path, "modified", &settings, at(13));
+        assert_eq!(
+            tracker.observe(path, "modified", &settings, at(14)),
+            FlapVerdict::StartSuppressing {
+                events: 3,
+                suppress_secs: 20
+            }
+        );
+        assert_eq!(
+            tracker.observe(path, "modified", &settings, at(20)),
+            FlapVerdict::Suppress
+        );
+        // The period also closes when the next event arrives after it.
+        tracker.observe(path, "modified", &settings, at(40));
+        let summaries = tracker.take_summaries(&settings, at(40));
+        assert_eq!(summaries[0].period, 2);
+        assert_eq!(summaries[0].suppressed, 1);
+        assert_eq!(summaries[0].suggestion, Some("exclude"));
+
+        // Quiet for the longest period: forgotten, back to one window.
+        assert!(tracker.take_summaries(&settings, at(200)).is_empty());
+        tracker.observe(path, "modified", &settings, at(200));
+        tracker.observe(path, "modified", &settings, at(201));
+        assert_eq!(
+            tracker.observe(path, "modified", &settings, at(202)),
+            FlapVerdict::StartSuppressing {
+                events: 3,
+                suppress_secs: 10
+            }
+        );
+    }
+
+    #[test]
+    fn events_spread_beyond_the_window_are_handled() {
+        let settings = settings();
+        let mut tracker = FlapTracker::new();
+        let t0 = Instant::now();
+        for i in 0..10 {
+            assert_eq!(
+                tracker.observe(
+                    Path::new("/srv/new.bin"),
+                    "unauthorized_file",
+                    &settings,
+                    t0 + Duration::from_secs(i * 6)
+                ),
+                FlapVerdict::Handle
+            );
+        }
+    }
+}
@@ -1,4 +1,11 @@
+pub mod attributes;
 pub mod audit_loop;
+pub mod burst;
+pub mod estimate;
+pub mod flap;
 pub mod pipeline;
+pub mod sandbox;
+pub mod sbom;
 pub mod scanner;
+pub mod symlink;
 pub mod watcher;
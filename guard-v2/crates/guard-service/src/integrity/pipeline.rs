@@ -10,13 +10,24 @@
 //! - Suspicious file extensions flagged (.php, .sh, .exe, etc.)
 //! - High-entropy files flagged (potential encrypted/packed payloads)
 //! - Permission changes detected and reversed
+//! - Ownership and tracked extended-attribute changes detected and reversed
+//! - Symlink swaps (a protected file or directory replaced by a link, or a
+//!   protected link retargeted) reported before anything follows the link
+//!
+//! Hashing happens in the confined scan worker when one is configured
+//! (`integrity::sandbox`).
 //!
 //! **Restore-loop suppression**: Events for paths currently in the
 //! `RestoreEngine::restoring` set are silently discarded.
 
+use crate::enforcement::snapshot::SNAPSHOT_DIR_NAME;
+use crate::integrity::attributes::{self, AttributeChange};
+use crate::integrity::sandbox::{ScanWorker, StreamDigest};
 use crate::integrity::scanner::Baseline;
+use crate::integrity::symlink::{is_symlink, link_hash, normalize_path};
 use crate::integrity::watcher::FileChange;
-use blake3::Hasher;
+use crate::supervisor::Heartbeat;
+use guard_core::settings::SymlinkPolicy;
 use std::collections::HashMap;
 use std::fs;
 use std::io::Read;
@@ -45,6 +56,11 @@ pub enum TamperEvent {
         expected_perms: u32,
         actual_perms: u32,
     },
+    /// Ownership or tracked extended attributes differ from the baseline.
+    AttributesChanged {
+        path: PathBuf,
+        changes: Vec<AttributeChange>,
+    },
     Renamed {
         from: PathBuf,
         to: PathBuf,
@@ -56,6 +72,40 @@ pub enum TamperEvent {
         file_size: u64,
         suspicious_reasons: Vec<String>,
     },
+    /// `link` replaced a baselined file or directory, or a protected link
+    /// now points at `target` instead of its baseline target.
+    SymlinkSwap {
+        link: PathBuf,
+        target: PathBuf,
+    },
+}
+
+impl TamperEvent {
+    /// The protected path the event is about: the original location for a
+    /// rename, the link for a swap.
+    pub fn path(&self) -> &Path {
+        match self {
+            TamperEvent::Modified { path, .. }
+            | TamperEvent::Deleted { path, .. }
+            | TamperEvent::PermissionChanged { path, .. }
+            | TamperEvent::AttributesChanged { path, .. }
+            | TamperEvent::UnauthorizedFile { path, .. } => path,
+            TamperEvent::Renamed { from, .. } => from,
+            TamperEvent::SymlinkSwap { link, .. } => link,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            TamperEvent::Modified { .. } => "modified",
+            TamperEvent::Deleted { .. } => "deleted",
+            TamperEvent::PermissionChanged { .. } => "permission_changed",
+            TamperEvent::AttributesChanged { .. } => "attributes_changed",
+            TamperEvent::Renamed { .. } => "renamed",
+            TamperEvent::UnauthorizedFile { .. } => "unauthorized_file",
+            TamperEvent::SymlinkSwap { .. } => "symlink_swap",
+        }
+    }
 }
 
 // ── Suspicious file detection ───────────────────────────────────────────────
@@ -179,26 +229,26 @@ fn analyze_file_suspicion(path: &Path, data: &[u8]) -> Vec<String> {
 
 // ── spawn_watcher_pipeline ──────────────────────────────────────────────────
 
-/// Spawn the debounced watcher pipeline.  Returns a `JoinHandle` and a
-/// `broadcast::Receiver<TamperEvent>` the orchestrator subscribes to.
+/// Spawn the debounced watcher pipeline. Verified violations are published on
+/// `tamper_tx`, which the caller owns so it outlives pipeline restarts.
 pub fn spawn_watcher_pipeline(
     mut raw_rx: broadcast::Receiver<FileChange>,
     baseline_fn: Arc<dyn Fn() -> Option<Baseline> + Send + Sync>,
     restoring: Arc<parking_lot::Mutex<std::collections::HashSet<PathBuf>>>,
+    tamper_tx: broadcast::Sender<TamperEvent>,
+    sandbox: Option<Arc<ScanWorker>>,
+    heartbeat: Heartbeat,
     shutdown: tokio::sync::watch::Receiver<bool>,
-) -> (
-    tokio::task::JoinHandle<()>,
-    broadcast::Sender<TamperEvent>,
-) {
-    let (tamper_tx, _) = broadcast::channel::<TamperEvent>(512);
-    let tx = tamper_tx.clone();
+) -> tokio::task::JoinHandle<()> {
+    let tx = tamper_tx;
     let mut shutdown = shutdown;
 
-    let handle = tokio::spawn(async move {
+    tokio::spawn(async move {
         let debounce_window = Duration::from_millis(100);
         let mut pending: HashMap<PathBuf, (FileChange, Instant)> = HashMap::new();
 
         loop {
+            heartbeat.beat();
             tokio::select! {
                 result = raw_rx.recv() => {
                     match result {
@@ -234,6 +284,11 @@ pub fn spawn_watcher_pipeline(
             for (path, change) in ready {
                 pending.remove(&path);
 
+                // Snapshots live inside the protected tree on btrfs.
+                if path.components().any(|c| c.as_os_str() == SNAPSHOT_DIR_NAME) {
+                    continue;
+                }
+
                 // Restore-loop suppression
                 if restoring.lock().contains(&path) {
                     trace!(path = %path.display(), "suppressed event – path being restored");
@@ -245,14 +300,12 @@ pub fn spawn_watcher_pipeline(
                     None => continue,
                 };
 
-                if let Some(event) = classify_change(&change, &baseline) {
+                if let Some(event) = classify_change(&change, &baseline, sandbox.as_deref()) {
                     let _ = tx.send(event);
                 }
             }
         }
-    });
-
-    (handle, tamper_tx)
+    })
 }
 
 // ── helpers ─────────────────────────────────────────────────────────────────
@@ -267,21 +320,78 @@ fn change_path(change: &FileChange) -> PathBuf {
     }
 }
 
-fn classify_change(change: &FileChange, baseline: &Baseline) -> Option<TamperEvent> {
+/// Classify a link that appeared or changed at `path`. `None` means the
+/// change is not about the link itself and should be classified as usual
+/// (only under `SymlinkPolicy::Follow`).
+fn classify_symlink(path: &Path, baseline: &Baseline) -> Option<Option<TamperEvent>> {
+    let link = normalize_path(path, false);
+    let target = fs::read_link(path).ok()?;
+    let key = link.display().to_string();
+    let swap = || Some(Some(TamperEvent::SymlinkSwap {
+        link: link.clone(),
+        target: target.clone(),
+    }));
+    match baseline.entries.get(&key) {
+        Some(entry) => match &entry.link_target {
+            Some(expected) if Path::new(expected) == target => Some(None),
+            _ => swap(),
+        },
+        // A link in place of a baselined directory.
+        None if baseline.entries.keys().any(|k| Path::new(k).starts_with(&link)) => swap(),
+        None => match baseline.symlink_policy {
+            SymlinkPolicy::Follow => None,
+            SymlinkPolicy::NoFollow => Some(None),
+            SymlinkPolicy::ProtectLink => Some(Some(TamperEvent::UnauthorizedFile {
+                file_hash: link_hash(&target),
+                file_size: 0,
+                suspicious_reasons: vec![format!("Symbolic link to {}", target.display())],
+                path: link,
+            })),
+        },
+    }
+}
+
+/// Classify `path` as it is now against the baseline, as if the watcher had
+/// just reported it. Used to re-check a path after flap suppression.
+pub fn recheck_path(
+    path: &Path,
+    baseline: &Baseline,
+    sandbox: Option<&ScanWorker>,
+) -> Option<TamperEvent> {
+    let change = if path.symlink_metadata().is_ok() {
+        FileChange::Modified(path.to_path_buf())
+    } else {
+        FileChange::Removed(path.to_path_buf())
+    };
+    classify_change(&change, baseline, sandbox)
+}
+
+fn classify_change(
+    change: &FileChange,
+    baseline: &Baseline,
+    sandbox: Option<&ScanWorker>,
+) -> Option<TamperEvent> {
+    let follow = baseline.symlink_policy == SymlinkPolicy::Follow;
     match change {
         FileChange::Modified(path) | FileChange::Created(path) => {
+            if is_symlink(path) {
+                if let Some(event) = classify_symlink(path, baseline) {
+                    return event;
+                }
+            }
+
             // Skip directories
             if path.is_dir() {
                 return None;
             }
             
-            let canonical = path.canonicalize().ok().unwrap_or_else(|| path.clone());
+            let canonical = normalize_path(path, follow);
             let key = canonical.display().to_string();
             
             // Check if file is in baseline
             if let Some(entry) = baseline.entries.get(&key) {
                 // Known file — check for modification
-                match hash_file_quick(&canonical) {
+                match hash_file_quick(&canonical, sandbox) {
                     Ok(actual_hash) => {
                         if actual_hash != entry.hash {
                             Some(TamperEvent::Modified {
@@ -304,11 +414,10 @@ fn classify_change(change: &FileChange, baseline: &Baseline) -> Option<TamperEve
                 match fs::read(&canonical) {
                     Ok(data) => {
                         let suspicious_reasons = analyze_file_suspicion(&canonical, &data);
-                        let file_hash = {
-                            let mut hasher = Hasher::new();
-                            hasher.update(&data);
-                            hasher.finalize().to_hex().to_string()
-                        };
+                        let file_hash = hash_bytes(&data, sandbox).unwrap_or_else(|e| {
+                            warn!(path = %canonical.display(), error = %e, "cannot hash unauthorized file");
+                            "unhashable".to_string()
+                        });
                         let file_size = data.len() as u64;
                         
                         info!(
@@ -339,18 +448,18 @@ fn classify_change(change: &FileChange, baseline: &Baseline) -> Option<TamperEve
             }
         }
         FileChange::Removed(path) => {
-            let key = path.display().to_string();
-            // Also try canonical form if path itself is in baseline
-            let entry = baseline.entries.get(&key).or_else(|| {
-                // Path is gone so we can't canonicalize; try as-is
-                baseline.entries.get(&key)
-            })?;
+            // The path is gone, so only its directories can be resolved.
+            let normalized = normalize_path(path, false);
+            let entry = baseline.entries.get(&normalized.display().to_string())?;
             Some(TamperEvent::Deleted {
-                path: path.clone(),
+                path: normalized,
                 expected_hash: entry.hash.clone(),
             })
         }
         FileChange::PermissionChanged(path) => {
+            if is_symlink(path) && !follow {
+                return None;
+            }
             let canonical = path.canonicalize().ok()?;
             let key = canonical.display().to_string();
             let entry = baseline.entries.get(&key)?;
@@ -369,14 +478,24 @@ fn classify_change(change: &FileChange, baseline: &Baseline) -> Option<TamperEve
                     }
                 }
             }
-            None
+            match attributes::current_changes(&canonical, entry, &baseline.tracked_xattrs) {
+                Ok(changes) if !changes.is_empty() => Some(TamperEvent::AttributesChanged {
+                    path: canonical,
+                    changes,
+                }),
+                Ok(_) => None,
+                Err(e) => {
+                    warn!(path = %canonical.display(), error = %e, "cannot read file attributes during classify");
+                    None
+                }
+            }
         }
         FileChange::Renamed { from, to } => {
-            let from_key = from.display().to_string();
-            if baseline.entries.contains_key(&from_key) {
+            let from = normalize_path(from, false);
+            if baseline.entries.contains_key(&from.display().to_string()) {
                 Some(TamperEvent::Renamed {
-                    from: from.clone(),
-                    to: to.clone(),
+                    from,
+                    to: normalize_path(to, false),
                 })
             } else {
                 None
@@ -385,16 +504,22 @@ fn classify_change(change: &FileChange, baseline: &Baseline) -> Option<TamperEve
     }
 }
 
-fn hash_file_quick(path: &Path) -> anyhow::Result<String> {
+fn hash_bytes(data: &[u8], sandbox: Option<&ScanWorker>) -> anyhow::Result<String> {
+    let mut hasher = StreamDigest::new(sandbox)?;
+    hasher.update(data)?;
+    hasher.finalize()
+}
+
+fn hash_file_quick(path: &Path, sandbox: Option<&ScanWorker>) -> anyhow::Result<String> {
     let mut f = fs::File::open(path)?;
-    let mut hasher = Hasher::new();
+    let mut hasher = StreamDigest::new(sandbox)?;
     let mut buf = vec![0u8; 64 * 1024];
     loop {
         let n = f.read(&mut buf)?;
         if n == 0 {
             break;
         }
-        hasher.update(&buf[..n]);
+        hasher.update(&buf[..n])?;
     }
-    Ok(hasher.finalize().to_hex().to_string())
+    hasher.finalize()
 }
@@ -0,0 +1,346 @@
+//! Hashing in a confined worker process (`settings.scanner_sandbox`).
+//!
+//! The worker is this executable run as `guard-service scan-worker`. It
+//! never opens a file: the service reads each file and streams its bytes
+//! over the worker's stdin as frames of a little-endian `u32` length and
+//! that many bytes, at most `FRAME_MAX`. A zero-length frame ends the file
+//! and the worker answers with the 32-byte BLAKE3 digest on stdout. Before
+//! reading its first frame the worker drops root for its configured user
+//! and, on Linux, enters strict seccomp (only `read`, `write`, `exit` and
+//! `sigreturn`), so from then on it must not allocate.
+//!
+//! A worker that dies or answers short fails that hash; it is restarted for
+//! the next one. There is no fallback to hashing in-process.
+
+use anyhow::{anyhow, Context, Result};
+use blake3::Hasher;
+use guard_core::settings::ScannerSandboxSettings;
+use parking_lot::{Mutex, MutexGuard};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::Arc;
+use tracing::warn;
+
+/// Largest frame the worker accepts.
+pub const FRAME_MAX: usize = 64 * 1024;
+
+struct WorkerProcess {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: ChildStdout,
+}
+
+impl Drop for WorkerProcess {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Handle on the worker, started on first use. One file is hashed at a
+/// time.
+pub struct ScanWorker {
+    program: PathBuf,
+    args: Vec<String>,
+    process: Mutex<Option<WorkerProcess>>,
+}
+
+impl ScanWorker {
+    /// Run `program` with `args` as the worker.
+    pub fn new(program: PathBuf, args: Vec<String>) -> Self {
+        Self {
+            program,
+            args,
+            process: Mutex::new(None),
+        }
+    }
+
+    /// `None` unless the sandbox is enabled. Warns when the worker will be
+    /// less confined than configured.
+    pub fn from_settings(settings: &ScannerSandboxSettings) -> Result<Option<Arc<Self>>> {
+        if !settings.enabled {
+            return Ok(None);
+        }
+        #[cfg(unix)]
+        if unsafe { libc::geteuid() } != 0 {
+            warn!(
+                user = %settings.user,
+                "service not running as root; the scan worker keeps the service's user"
+            );
+        }
+        #[cfg(not(target_os = "linux"))]
+        warn!("no syscall filter on this platform; the scan worker is only a separate process");
+        Ok(Some(Arc::new(Self::new(
+            std::env::current_exe()?,
+            vec!["scan-worker".into(), "--user".into(), settings.user.clone()],
+        ))))
+    }
+
+    /// Process id of the running worker, if one is.
+    pub fn pid(&self) -> Option<u32> {
+        self.process.lock().as_ref().map(|p| p.child.id())
+    }
+
+    /// Start hashing one stream.
+    pub fn session(&self) -> Result<WorkerSession<'_>> {
+        let mut process = self.process.lock();
+        if process.is_none() {
+            let mut child = Command::new(&self.program)
+                .args(&self.args)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .with_context(|| format!("start scan worker {}", self.program.display()))?;
+            let stdin = child.stdin.take().ok_or_else(|| anyhow!("scan worker stdin"))?;
+            let stdout = child.stdout.take().ok_or_else(|| anyhow!("scan worker stdout"))?;
+            *process = Some(WorkerProcess {
+                child,
+                stdin,
+                stdout,
+            });
+        }
+        Ok(WorkerSession { process })
+    }
+}
+
+pub struct WorkerSession<'a> {
+    process: MutexGuard<'a, Option<WorkerProcess>>,
+}
+
+impl WorkerSession<'_> {
+    pub fn update(&mut self, data: &[u8]) -> Result<()> {
+        for chunk in data.chunks(FRAME_MAX) {
+            self.send(chunk)?;
+        }
+        Ok(())
+    }
+
+    pub fn finalize(mut self) -> Result<String> {
+        self.send(&[])?;
+        let mut digest = [0u8; 32];
+        let read = self
+            .process
+            .as_mut()
+            .ok_or_else(|| anyhow!("scan worker not running"))?
+            .stdout
+            .read_exact(&mut digest);
+        if let Err(e) = read {
+            return Err(self.fail(e));
+        }
+        Ok(hex::encode(digest))
+    }
+
+    fn send(&mut self, frame: &[u8]) -> Result<()> {
+        let process = self
+            .process
+            .as_mut()
+            .ok_or_else(|| anyhow!("scan worker not running"))?;
+        let written = process
+            .stdin
+            .write_all(&(frame.len() as u32).to_le_bytes())
+            .and_then(|_| process.stdin.write_all(frame));
+        written.map_err(|e| self.fail(e))
+    }
+
+    /// Drop the worker so the next session starts a fresh one.
+    fn fail(&mut self, e: std::io::Error) -> anyhow::Error {
+        let status = self.process.take().and_then(|mut p| {
+            let _ = p.child.kill();
+            p.child.wait().ok()
+        });
+        match status {
+            Some(status) => anyhow!("scan worker failed ({status}): {e}"),
+            None => anyhow!("scan worker failed: {e}"),
+        }
+    }
+}
+
+/// BLAKE3 of a stream, in the worker when there is one.
+pub enum StreamDigest<'a> {
+    Local(Box<Hasher>),
+    Worker(WorkerSession<'a>),
+}
+
+impl<'a> StreamDigest<'a> {
+    pub fn new(worker: Option<&'a ScanWorker>) -> Result<Self> {
+        Ok(match worker {
+            Some(worker) => StreamDigest::Worker(worker.session()?),
+            None => StreamDigest::Local(Box::new(Hasher::new())),
+        })
+    }
+
+    pub fn update(&mut self, data: &[u8]) -> Result<()> {
+        match self {
+            StreamDigest::Local(hasher) => {
+                hasher.update(data);
+                Ok(())
+            }
+            StreamDigest::Worker(session) => session.update(data),
+        }
+    }
+
+    pub fn finalize(self) -> Result<String> {
+        match self {
+            StreamDigest::Local(hasher) => Ok(hasher.finalize().to_hex().to_string()),
+            StreamDigest::Worker(session) => session.finalize(),
+        }
+    }
+}
+
+// ── Worker side ─────────────────────────────────────────────────────────────
+
+/// Body of `guard-service scan-worker`. Must run before any other thread
+/// exists: seccomp confines only the calling thread.
+pub fn run_worker(user: &str) -> Result<()> {
+    drop_privileges(user)?;
+    // Settle blake3's CPU feature detection while syscalls are still allowed.
+    let _ = blake3::hash(&[0u8; 4096]);
+    confine()?;
+    serve()
+}
+
+/// Switch to `user` when running as root. Otherwise there is nothing to
+/// drop; the service warned about that when it set the worker up.
+#[cfg(unix)]
+fn drop_privileges(user: &str) -> Result<()> {
+    if unsafe { libc::geteuid() } != 0 {
+        return Ok(());
+    }
+    let name = std::ffi::CString::new(user)?;
+    let pw = unsafe { libc::getpwnam(name.as_ptr()) };
+    if pw.is_null() {
+        return Err(anyhow!("unknown scan worker user {user}"));
+    }
+    let (uid, gid) = unsafe { ((*pw).pw_uid, (*pw).pw_gid) };
+    if uid == 0 {
+        return Err(anyhow!("the scan worker must not run as root"));
+    }
+    unsafe {
+        if libc::setgroups(0, std::ptr::null()) != 0
+            || libc::setgid(gid) != 0
+            || libc::setuid(uid) != 0
+        {
+            return Err(anyhow!("drop privileges: {}", std::io::Error::last_os_error()));
+        }
+        if libc::setuid(0) == 0 {
+            return Err(anyhow!("root privileges could not be dropped"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn drop_privileges(_user: &str) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn confine() -> Result<()> {
+    unsafe {
+        if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0
+            || libc::prctl(libc::PR_SET_SECCOMP, libc::SECCOMP_MODE_STRICT, 0, 0, 0) != 0
+        {
+            return Err(anyhow!("enter seccomp: {}", std::io::Error::last_os_error()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn confine() -> Result<()> {
+    Ok(())
+}
+
+/// Frame loop. Uses raw `read`/`write` on fds 0 and 1 and a fixed buffer:
+/// no allocation, no other syscalls.
+#[cfg(unix)]
+fn serve() -> Result<()> {
+    fn read_exact(buf: &mut [u8]) -> bool {
+        let mut filled = 0;
+        while filled < buf.len() {
+            let rest = &mut buf[filled..];
+            let n = unsafe { libc::read(0, rest.as_mut_ptr().cast(), rest.len()) };
+            if n <= 0 {
+                return false;
+            }
+            filled += n as usize;
+        }
+        true
+    }
+    fn write_all(buf: &[u8]) -> bool {
+        let mut sent = 0;
+        while sent < buf.len() {
+            let rest = &buf[sent..];
+            let n = unsafe { libc::write(1, rest.as_ptr().cast(), rest.len()) };
+            if n <= 0 {
+                return false;
+            }
+            sent += n as usize;
+        }
+        true
+    }
+
+    let mut buf = [0u8; FRAME_MAX];
+    let mut hasher = Hasher::new();
+    let code = loop {
+        let mut header = [0u8; 4];
+        if !read_exact(&mut header) {
+            break 0;
+        }
+        let len = u32::from_le_bytes(header) as usize;
+        if len == 0 {
+            if !write_all(hasher.finalize().as_bytes()) {
+                break 1;
+            }
+            hasher.reset();
+            continue;
+        }
+        if len > FRAME_MAX || !read_exact(&mut buf[..len]) {
+            break 1;
+        }
+        hasher.update(&buf[..len]);
+    };
+    exit(code)
+}
+
+#[cfg(not(unix))]
+fn serve() -> Result<()> {
+    let (mut stdin, mut stdout) = (std::io::stdin().lock(), std::io::stdout().lock());
+    let mut buf = vec![0u8; FRAME_MAX];
+    let mut hasher = Hasher::new();
+    loop {
+        let mut header = [0u8; 4];
+        if stdin.read_exact(&mut header).is_err() {
+            return Ok(());
+        }
+        let len = u32::from_le_bytes(header) as usize;
+        if len == 0 {
+            stdout.write_all(hasher.finalize().as_bytes())?;
+            stdout.flush()?;
+            hasher.reset();
+            continue;
+        }
+        if len > FRAME_MAX {
+            return Err(anyhow!("frame of {len} bytes"));
+        }
+        stdin.read_exact(&mut buf[..len])?;
+        hasher.update(&buf[..len]);
+    }
+}
+
+/// `exit_group`, which Rust's own exit uses, is not allowed under strict
+/// seccomp; the worker's only thread leaves with `exit`.
+#[cfg(target_os = "linux")]
+fn exit(code: i32) -> ! {
+    unsafe {
+        libc::syscall(libc::SYS_exit, code);
+    }
+    unreachable!()
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn exit(code: i32) -> ! {
+    std::process::exit(code)
+}
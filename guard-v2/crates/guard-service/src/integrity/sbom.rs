@@ -0,0 +1,134 @@
+//! Checks an application directory against a vendor manifest.
+//!
+//! Unlike the baseline, which records whatever was on disk when it was taken,
+//! a manifest says what the vendor shipped. Differences are reported in their
+//! own categories and never trigger restores.
+
+use chrono::Utc;
+use guard_core::sbom::{SbomBinding, SbomReport};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use walkdir::WalkDir;
+
+use crate::enforcement::restore::is_staging_name;
+use crate::enforcement::snapshot::SNAPSHOT_DIR_NAME;
+
+fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Relative `/`-separated paths of the regular files under `root`.
+fn files_on_disk(root: &Path) -> BTreeSet<String> {
+    WalkDir::new(root)
+        .follow_links(false)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != SNAPSHOT_DIR_NAME)
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| !is_staging_name(&e.file_name().to_string_lossy()))
+        .filter_map(|e| {
+            let rel = e.path().strip_prefix(root).ok()?;
+            let parts: Vec<String> = rel
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                .collect();
+            Some(parts.join("/"))
+        })
+        .collect()
+}
+
+/// Compare the directory bound in `binding` with its manifest.
+pub fn verify_binding(binding: &SbomBinding) -> SbomReport {
+    let manifest = &binding.signed.manifest;
+    let root = Path::new(&binding.root);
+    let mut report = SbomReport {
+        root: binding.root.clone(),
+        vendor: manifest.vendor.clone(),
+        product: manifest.product.clone(),
+        version: manifest.version.clone(),
+        checked_at: Utc::now(),
+        ..Default::default()
+    };
+
+    let mut on_disk = files_on_disk(root);
+    let listed: BTreeMap<&str, &str> = manifest
+        .files
+        .iter()
+        .map(|f| (f.path.as_str(), f.sha256.as_str()))
+        .collect();
+    for (rel, expected) in listed {
+        if !on_disk.remove(rel) {
+            report.missing.push(rel.to_string());
+            continue;
+        }
+        match sha256_file(&root.join(rel)) {
+            Ok(actual) if actual.eq_ignore_ascii_case(expected) => report.matched += 1,
+            Ok(_) => report.modified.push(rel.to_string()),
+            Err(e) => report.errors.push(format!("{rel}: {e}")),
+        }
+    }
+    report.unexpected = on_disk.into_iter().collect();
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::SigningKey;
+    use guard_core::sbom::{SbomFile, SbomManifest, SignedSbomManifest};
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn reports_each_category_separately() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("bin")).unwrap();
+        fs::write(root.join("bin/app"), b"app v2").unwrap();
+        fs::write(root.join("lib.so"), b"patched").unwrap();
+        fs::write(root.join("dropper.sh"), b"#!/bin/sh").unwrap();
+
+        let file = |path: &str, content: &[u8]| SbomFile {
+            path: path.into(),
+            sha256: hex::encode(Sha256::digest(content)),
+            size: Some(content.len() as u64),
+        };
+        let manifest = SbomManifest {
+            vendor: "Acme".into(),
+            product: "app".into(),
+            version: "2.0".into(),
+            issued_at: Utc::now(),
+            files: vec![
+                file("bin/app", b"app v2"),
+                file("lib.so", b"original"),
+                file("share/readme", b"readme"),
+            ],
+        };
+        let vendor = SigningKey::generate(&mut OsRng);
+        let binding = SbomBinding {
+            root: root.display().to_string(),
+            vendor_key: String::new(),
+            signed: SignedSbomManifest::sign(manifest, &vendor).unwrap(),
+            imported_at: Utc::now(),
+        };
+
+        let report = verify_binding(&binding);
+        assert_eq!(report.matched, 1);
+        assert_eq!(report.modified, vec!["lib.so"]);
+        assert_eq!(report.missing, vec!["share/readme"]);
+        assert_eq!(report.unexpected, vec!["dropper.sh"]);
+        assert!(!report.is_clean());
+    }
+}
@@ -3,20 +3,116 @@
 //! The scanner walks a set of protected paths, hashes every file with BLAKE3,
 //! and produces a baseline manifest. The manifest is signed with the device's
 //! Ed25519 key so attackers cannot forge a clean baseline.
+//!
+//! Files are hashed as a stream with progress reported through an optional
+//! callback, in the confined scan worker when one is configured
+//! (`integrity::sandbox`). Large files under a quick-check rule (`HashingSettings`) are
+//! compared on audit scans by size, mtime and their first and last MiB, and
+//! fully hashed only when that differs from the baseline.
+//!
+//! Symbolic links are handled per `SymlinkPolicy`, recorded in the baseline
+//! so later scans walk the tree the same way; see `integrity::symlink`.
+//!
+//! Under dual control an operator key held off the device (YubiKey,
+//! ssh-agent) countersigns each baseline as well, so the device key alone
+//! can't make a rebaseline pass verification.
+//!
+//! Entries also record ownership and selected extended attributes
+//! (`integrity::attributes`); a scan reports files whose content is intact
+//! but whose attributes changed. Baselines carry a format version and older
+//! ones are brought forward by `migrate_baseline`.
+//!
+//! A rebaseline is first written beside the active baseline as a pending
+//! candidate (`write_pending`), checked by `validate_candidate`, and only then
+//! renamed over the active one (`commit_pending`), so a crash mid-rebaseline
+//! leaves the previous baseline in place.
 
 use anyhow::{Context, Result};
-use blake3::Hasher;
 use chrono::{DateTime, Utc};
 use ed25519_dalek::{Signer, SigningKey, VerifyingKey, Verifier, Signature};
+use guard_core::backup_store::BackupStore;
+use guard_core::settings::{AttributeSettings, HashingSettings};
 use serde::{Deserialize, Serialize};
 use sha2::{Sha256, Digest};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs;
-use std::io::Read;
+use std::io::{Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use guard_core::settings::SymlinkPolicy;
 use tracing::{info, warn, error, debug};
 use walkdir::WalkDir;
 
+use crate::enforcement::snapshot::SNAPSHOT_DIR_NAME;
+use crate::integrity::attributes::{self, AttributeChange, AttributeConfig, Ownership};
+use crate::integrity::sandbox::{ScanWorker, StreamDigest};
+use crate::integrity::symlink::{find_swapped_link, link_hash, normalize_path, SymlinkSwap};
+
+/// Domain separator for operator countersignatures, so one can't be replayed
+/// as the device signature or any other operator-signed message.
+const OPERATOR_SIGNING_CONTEXT: &[u8] = b"darklock-guard-baseline-operator-v1\0";
+
+/// Bytes read from each end of a file for a quick check.
+const QUICK_CHECK_SPAN: u64 = 1024 * 1024;
+
+const MIB: u64 = 1024 * 1024;
+
+/// Baseline format written by this build. v2 added ownership and extended
+/// attributes.
+pub const BASELINE_VERSION: u32 = 2;
+
+/// Progress through one file being hashed.
+#[derive(Debug, Clone, Serialize)]
+pub struct HashProgress {
+    pub path: PathBuf,
+    pub bytes_hashed: u64,
+    pub total_bytes: u64,
+}
+
+pub type ProgressFn = Arc<dyn Fn(&HashProgress) + Send + Sync>;
+
+/// Hashing behaviour resolved from `HashingSettings`.
+#[derive(Clone, Default)]
+struct HashConfig {
+    /// Report progress every this many bytes; 0 disables.
+    progress_interval: u64,
+    /// Normalized rule path and minimum size in bytes.
+    quick_check: Vec<(PathBuf, u64)>,
+    progress: Option<ProgressFn>,
+    sandbox: Option<Arc<ScanWorker>>,
+}
+
+impl HashConfig {
+    /// Minimum size for a quick check of `path`, from the most specific rule.
+    fn quick_check_min(&self, path: &Path) -> Option<u64> {
+        self.quick_check
+            .iter()
+            .filter(|(root, _)| path.starts_with(root))
+            .max_by_key(|(root, _)| root.components().count())
+            .map(|(_, min)| *min)
+    }
+}
+
+/// Files settled by a quick check and files it escalated to a full hash.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct QuickCheckStats {
+    pub verified: usize,
+    pub escalated: usize,
+}
+
+impl QuickCheckStats {
+    pub fn is_empty(&self) -> bool {
+        self.verified == 0 && self.escalated == 0
+    }
+}
+
+/// Everything a walk of the protected paths produced.
+struct Collected {
+    entries: HashMap<String, BaselineEntry>,
+    errors: Vec<ScanError>,
+    quick_check: QuickCheckStats,
+}
+
 /// A single file entry in the baseline
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BaselineEntry {
@@ -25,6 +121,36 @@ pub struct BaselineEntry {
     pub size: u64,
     pub modified: DateTime<Utc>,
     pub permissions: u32,
+    /// Set for links recorded under `SymlinkPolicy::ProtectLink`; `hash` is
+    /// then the link hash of this target.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_target: Option<String>,
+    /// Quick-check digest over size, mtime and the first and last MiB, for
+    /// files under a quick-check rule.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quick_hash: Option<String>,
+    /// `None` when ownership was not recorded (links, v1 baselines).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ownership: Option<Ownership>,
+    /// Values of the baseline's tracked extended attributes present on the
+    /// file, hex-encoded; `None` when they were not recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub xattrs: Option<BTreeMap<String, String>>,
+}
+
+/// Operator-supplied tags and metadata for a baseline entry.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FileAnnotation {
+    #[serde(default)]
+    pub tags: BTreeSet<String>,
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl FileAnnotation {
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.metadata.is_empty()
+    }
 }
 
 /// The full integrity baseline manifest
@@ -34,7 +160,79 @@ pub struct Baseline {
     pub created_at: DateTime<Utc>,
     pub device_id: String,
     pub entries: HashMap<String, BaselineEntry>,
+    /// Policy the baseline was walked with; covered by the signature.
+    #[serde(default, skip_serializing_if = "is_default_policy")]
+    pub symlink_policy: SymlinkPolicy,
+    /// Tags / metadata keyed by entry path; covered by the signature.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub annotations: BTreeMap<String, FileAnnotation>,
+    /// Extended attributes recorded per entry, sorted; covered by the
+    /// signature.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tracked_xattrs: Vec<String>,
     pub signature: String,  // Ed25519 signature over the canonical entry data
+    /// Operator countersignature (hex) over `operator_signing_message`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operator_signature: Option<String>,
+}
+
+fn is_default_policy(policy: &SymlinkPolicy) -> bool {
+    *policy == SymlinkPolicy::default()
+}
+
+impl Baseline {
+    pub fn tags_for(&self, path: &str) -> Vec<String> {
+        self.annotations
+            .get(path)
+            .map(|a| a.tags.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn has_tag(&self, path: &str, tag: &str) -> bool {
+        self.annotations
+            .get(path)
+            .is_some_and(|a| a.tags.contains(tag))
+    }
+
+    /// Add/remove tags and set metadata on every entry matching `selectors`
+    /// (exact file paths or directory prefixes). An empty metadata value
+    /// deletes the key. Returns the number of entries touched. The caller
+    /// must re-sign the baseline afterwards.
+    pub fn annotate(
+        &mut self,
+        selectors: &[String],
+        add: &[String],
+        remove: &[String],
+        metadata: &BTreeMap<String, String>,
+    ) -> usize {
+        let matches = |path: &str| {
+            selectors.iter().any(|sel| {
+                let sel = sel.trim_end_matches(['/', '\\']);
+                path == sel
+                    || path
+                        .strip_prefix(sel)
+                        .is_some_and(|rest| rest.starts_with(['/', '\\']))
+            })
+        };
+        let mut touched = 0;
+        for path in self.entries.keys().filter(|p| matches(p)) {
+            let annotation = self.annotations.entry(path.clone()).or_default();
+            annotation.tags.extend(add.iter().cloned());
+            for tag in remove {
+                annotation.tags.remove(tag);
+            }
+            for (k, v) in metadata {
+                if v.is_empty() {
+                    annotation.metadata.remove(k);
+                } else {
+                    annotation.metadata.insert(k.clone(), v.clone());
+                }
+            }
+            touched += 1;
+        }
+        self.annotations.retain(|_, a| !a.is_empty());
+        touched
+    }
 }
 
 /// Result of comparing current state against baseline
@@ -47,6 +245,95 @@ pub struct ScanResult {
     pub removed: Vec<String>,
     pub errors: Vec<ScanError>,
     pub valid: bool,
+    /// Baseline tags of the modified / removed paths that have any.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, Vec<String>>,
+    /// Time spent walking and hashing each protected path.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub root_durations_ms: BTreeMap<String, u64>,
+    /// Links behind modified / removed entries, one per link.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub symlink_swaps: Vec<SymlinkSwap>,
+    #[serde(default, skip_serializing_if = "QuickCheckStats::is_empty")]
+    pub quick_check: QuickCheckStats,
+    /// Ownership / attribute changes of files whose content is intact.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attribute_changes: Vec<AttributeChange>,
+}
+
+impl ScanResult {
+    /// Files with attribute changes, each once.
+    pub fn attribute_paths(&self) -> BTreeSet<&str> {
+        self.attribute_changes.iter().map(|c| c.path.as_str()).collect()
+    }
+
+    /// Restrict the report to baseline entries carrying `tag`. Added files
+    /// have no baseline entry and are dropped.
+    pub fn filter_by_tag(&self, baseline: &Baseline, tag: &str) -> ScanResult {
+        let modified: Vec<ModifiedFile> = self
+            .modified
+            .iter()
+            .filter(|m| baseline.has_tag(&m.path, tag))
+            .cloned()
+            .collect();
+        let removed: Vec<String> = self
+            .removed
+            .iter()
+            .filter(|p| baseline.has_tag(p, tag))
+            .cloned()
+            .collect();
+        let attribute_changes: Vec<AttributeChange> = self
+            .attribute_changes
+            .iter()
+            .filter(|c| baseline.has_tag(&c.path, tag))
+            .cloned()
+            .collect();
+        let tags = self
+            .tags
+            .iter()
+            .filter(|(path, _)| baseline.has_tag(path, tag))
+            .map(|(p, t)| (p.clone(), t.clone()))
+            .collect();
+        ScanResult {
+            scanned_at: self.scanned_at,
+            total_files: baseline
+                .entries
+                .keys()
+                .filter(|p| baseline.has_tag(p, tag))
+                .count(),
+            valid: modified.is_empty() && removed.is_empty() && attribute_changes.is_empty(),
+            modified,
+            added: Vec::new(),
+            removed,
+            errors: self.errors.clone(),
+            tags,
+            root_durations_ms: self.root_durations_ms.clone(),
+            symlink_swaps: self
+                .symlink_swaps
+                .iter()
+                .filter(|s| {
+                    baseline
+                        .entries
+                        .keys()
+                        .any(|p| Path::new(p).starts_with(&s.link) && baseline.has_tag(p, tag))
+                })
+                .cloned()
+                .collect(),
+            quick_check: self.quick_check,
+            attribute_changes,
+        }
+    }
+
+    /// Number of violations per tag.
+    pub fn tag_counts(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for tags in self.tags.values() {
+            for tag in tags {
+                *counts.entry(tag.clone()).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -64,10 +351,24 @@ pub struct ScanError {
     pub error: String,
 }
 
+/// What `migrate_baseline` did.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct BaselineMigration {
+    pub from_version: u32,
+    pub to_version: u32,
+    /// Entries whose attributes were recorded.
+    pub adopted: usize,
+    /// Entries left without attributes until the next rebaseline.
+    pub skipped: usize,
+}
+
 #[derive(Clone)]
 pub struct IntegrityScanner {
     protected_paths: Vec<PathBuf>,
     device_id: String,
+    symlink_policy: SymlinkPolicy,
+    hashing: HashConfig,
+    attributes: AttributeConfig,
 }
 
 impl IntegrityScanner {
@@ -75,45 +376,159 @@ impl IntegrityScanner {
         Self {
             protected_paths,
             device_id,
+            symlink_policy: SymlinkPolicy::default(),
+            hashing: HashConfig {
+                progress_interval: HashingSettings::default().progress_interval_mb * MIB,
+                ..Default::default()
+            },
+            attributes: AttributeConfig::from_settings(&AttributeSettings::default()),
         }
     }
 
-    /// Hash a single file using BLAKE3
-    fn hash_file(path: &Path) -> Result<(String, u64)> {
+    /// Attributes recorded in new baselines. Scans compare what the
+    /// baseline recorded.
+    pub fn with_attributes(mut self, settings: &AttributeSettings) -> Self {
+        self.attributes = AttributeConfig::from_settings(settings);
+        self
+    }
+
+    /// Policy for new baselines. Scans always use the baseline's own.
+    pub fn with_symlink_policy(mut self, policy: SymlinkPolicy) -> Self {
+        self.symlink_policy = policy;
+        self
+    }
+
+    pub fn with_hashing(mut self, settings: &HashingSettings) -> Self {
+        self.hashing.progress_interval = settings.progress_interval_mb * MIB;
+        self.hashing.quick_check = settings
+            .quick_check
+            .iter()
+            .map(|rule| (normalize_path(Path::new(&rule.path), true), rule.min_size_mb * MIB))
+            .collect();
+        self
+    }
+
+    /// Called as large files are hashed, every `progress_interval_mb`.
+    pub fn with_progress(mut self, progress: ProgressFn) -> Self {
+        self.hashing.progress = Some(progress);
+        self
+    }
+
+    /// Hash in `worker` rather than in this process.
+    pub fn with_sandbox(mut self, worker: Option<Arc<ScanWorker>>) -> Self {
+        self.hashing.sandbox = worker;
+        self
+    }
+
+    pub fn progress(&self) -> Option<ProgressFn> {
+        self.hashing.progress.clone()
+    }
+
+    /// Hash a single file using BLAKE3, in 64KB chunks, reporting progress
+    /// per `hashing`.
+    fn hash_file(path: &Path, hashing: &HashConfig) -> Result<(String, u64)> {
         let mut file = fs::File::open(path)
             .with_context(|| format!("Failed to open {}", path.display()))?;
         let metadata = file.metadata()?;
         let size = metadata.len();
+        let report = hashing
+            .progress
+            .as_ref()
+            .filter(|_| hashing.progress_interval > 0 && size >= hashing.progress_interval);
 
-        let mut hasher = Hasher::new();
+        let mut hasher = StreamDigest::new(hashing.sandbox.as_deref())?;
         let mut buffer = vec![0u8; 64 * 1024]; // 64KB buffer
+        let mut hashed = 0u64;
+        let mut next_report = hashing.progress_interval;
         loop {
             let n = file.read(&mut buffer)?;
             if n == 0 { break; }
-            hasher.update(&buffer[..n]);
+            hasher.update(&buffer[..n])?;
+            hashed += n as u64;
+            if let Some(report) = report {
+                if hashed >= next_report {
+                    report(&HashProgress {
+                        path: path.to_path_buf(),
+                        bytes_hashed: hashed,
+                        total_bytes: size,
+                    });
+                    next_report = hashed + hashing.progress_interval;
+                }
+            }
         }
 
-        Ok((hasher.finalize().to_hex().to_string(), size))
+        Ok((hasher.finalize()?, size))
+    }
+
+    /// Digest of `size`, `modified` and the first and last MiB of `path`.
+    fn quick_hash(
+        path: &Path,
+        size: u64,
+        modified: DateTime<Utc>,
+        hashing: &HashConfig,
+    ) -> Result<String> {
+        let mut file = fs::File::open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        let mut hasher = StreamDigest::new(hashing.sandbox.as_deref())?;
+        hasher.update(b"quick\0")?;
+        hasher.update(&size.to_le_bytes())?;
+        hasher.update(modified.to_rfc3339().as_bytes())?;
+        let mut head = Vec::new();
+        (&mut file).take(QUICK_CHECK_SPAN).read_to_end(&mut head)?;
+        hasher.update(&head)?;
+        if size > QUICK_CHECK_SPAN {
+            let tail_start = size.saturating_sub(QUICK_CHECK_SPAN).max(QUICK_CHECK_SPAN);
+            file.seek(SeekFrom::Start(tail_start))?;
+            let mut tail = Vec::new();
+            file.take(QUICK_CHECK_SPAN).read_to_end(&mut tail)?;
+            hasher.update(&tail)?;
+        }
+        hasher.finalize()
     }
 
     /// Walk all protected paths and collect file entries
-    fn collect_entries(&self) -> (HashMap<String, BaselineEntry>, Vec<ScanError>) {
+    fn collect_entries(&self) -> Collected {
+        Self::collect_entries_under(
+            &self.protected_paths,
+            self.symlink_policy,
+            &self.hashing,
+            None,
+            Some(&self.attributes),
+        )
+    }
+
+    /// Walk `roots` (files or directories) and collect file entries. A root
+    /// that is a link is always followed; links below it per `policy`. Files
+    /// whose quick check matches their entry in `previous` keep its hash.
+    /// Entries record the attributes in `attributes`, if given.
+    fn collect_entries_under(
+        roots: &[PathBuf],
+        policy: SymlinkPolicy,
+        hashing: &HashConfig,
+        previous: Option<&Baseline>,
+        attributes: Option<&AttributeConfig>,
+    ) -> Collected {
         let mut entries = HashMap::new();
         let mut errors = Vec::new();
+        let mut quick_check = QuickCheckStats::default();
 
-        for root in &self.protected_paths {
+        for root in roots {
             if !root.exists() {
                 warn!("Protected path does not exist: {}", root.display());
                 continue;
             }
+            let root = normalize_path(root, true);
 
             let walker = if root.is_file() {
-                WalkDir::new(root).max_depth(0)
+                WalkDir::new(&root).max_depth(0)
             } else {
-                WalkDir::new(root).follow_links(false)
+                WalkDir::new(&root).follow_links(policy == SymlinkPolicy::Follow)
             };
 
-            for entry in walker.into_iter() {
+            let walker = walker
+                .into_iter()
+                .filter_entry(|e| e.file_name() != SNAPSHOT_DIR_NAME);
+            for entry in walker {
                 let entry = match entry {
                     Ok(e) => e,
                     Err(e) => {
@@ -125,6 +540,18 @@ impl IntegrityScanner {
                     }
                 };
 
+                if entry.file_type().is_symlink() && policy == SymlinkPolicy::ProtectLink {
+                    match Self::link_entry(entry.path()) {
+                        Ok(link) => {
+                            entries.insert(link.path.clone(), link);
+                        }
+                        Err(e) => errors.push(ScanError {
+                            path: entry.path().display().to_string(),
+                            error: e.to_string(),
+                        }),
+                    }
+                    continue;
+                }
                 if !entry.file_type().is_file() {
                     continue;
                 }
@@ -141,30 +568,85 @@ impl IntegrityScanner {
                     }
                 };
 
-                match Self::hash_file(&canonical) {
-                    Ok((hash, size)) => {
-                        let modified = entry
-                            .metadata()
-                            .ok()
-                            .and_then(|m| m.modified().ok())
-                            .map(DateTime::<Utc>::from)
-                            .unwrap_or_else(Utc::now);
+                let metadata = entry.metadata().ok();
+                let modified = metadata
+                    .as_ref()
+                    .and_then(|m| m.modified().ok())
+                    .map(DateTime::<Utc>::from)
+                    .unwrap_or_else(Utc::now);
+                let key = canonical.display().to_string();
+
+                let size = metadata.as_ref().map_or(0, |m| m.len());
+                let quick_hash = match hashing.quick_check_min(&canonical) {
+                    Some(min) if size >= min => {
+                        match Self::quick_hash(&canonical, size, modified, hashing) {
+                            Ok(q) => Some(q),
+                            Err(e) => {
+                                errors.push(ScanError {
+                                    path: key,
+                                    error: e.to_string(),
+                                });
+                                continue;
+                            }
+                        }
+                    }
+                    _ => None,
+                };
+                let known = previous.and_then(|b| b.entries.get(&key));
+                let unchanged = quick_hash.as_ref().and_then(|q| {
+                    known
+                        .filter(|e| e.size == size && e.quick_hash.as_ref() == Some(q))
+                        .map(|e| (e.hash.clone(), size))
+                });
+                let hashed = match unchanged {
+                    Some(hashed) => {
+                        quick_check.verified += 1;
+                        Ok(hashed)
+                    }
+                    None => {
+                        if quick_hash.is_some() && known.is_some() {
+                            debug!(path = %key, "quick check mismatch, hashing in full");
+                            quick_check.escalated += 1;
+                        }
+                        Self::hash_file(&canonical, hashing)
+                    }
+                };
 
+                match hashed {
+                    Ok((hash, size)) => {
                         #[cfg(unix)]
                         let permissions = {
                             use std::os::unix::fs::PermissionsExt;
-                            entry.metadata().map(|m| m.permissions().mode()).unwrap_or(0)
+                            metadata.as_ref().map(|m| m.permissions().mode()).unwrap_or(0)
                         };
                         #[cfg(not(unix))]
                         let permissions = 0u32;
 
-                        let key = canonical.display().to_string();
+                        let (ownership, xattrs) = match (attributes, metadata.as_ref()) {
+                            (Some(config), Some(meta)) => {
+                                match attributes::read(&canonical, meta, config) {
+                                    Ok(read) => read,
+                                    Err(e) => {
+                                        errors.push(ScanError {
+                                            path: key.clone(),
+                                            error: e.to_string(),
+                                        });
+                                        (None, None)
+                                    }
+                                }
+                            }
+                            _ => (None, None),
+                        };
                         entries.insert(key.clone(), BaselineEntry {
                             path: key,
                             hash,
                             size,
                             modified,
                             permissions,
+                            link_target: None,
+                            quick_hash,
+                            ownership,
+                            xattrs,
                         });
                     }
                     Err(e) => {
@@ -177,11 +659,49 @@ impl IntegrityScanner {
             }
         }
 
-        (entries, errors)
+        Collected {
+            entries,
+            errors,
+            quick_check,
+        }
+    }
+
+    /// Baseline entry for the link at `path` itself.
+    fn link_entry(path: &Path) -> Result<BaselineEntry> {
+        let target = fs::read_link(path)
+            .with_context(|| format!("Failed to read link {}", path.display()))?;
+        let metadata = fs::symlink_metadata(path)?;
+        #[cfg(unix)]
+        let permissions = {
+            use std::os::unix::fs::PermissionsExt;
+            metadata.permissions().mode()
+        };
+        #[cfg(not(unix))]
+        let permissions = 0u32;
+        let key = normalize_path(path, false).display().to_string();
+        Ok(BaselineEntry {
+            path: key,
+            hash: link_hash(&target),
+            size: metadata.len(),
+            modified: metadata
+                .modified()
+                .map(DateTime::<Utc>::from)
+                .unwrap_or_else(|_| Utc::now()),
+            permissions,
+            link_target: Some(target.display().to_string()),
+            quick_hash: None,
+            ownership: None,
+            xattrs: None,
+        })
     }
 
     /// Create a canonical bytes representation of entries for signing
-    fn canonical_bytes(entries: &HashMap<String, BaselineEntry>) -> Vec<u8> {
+    fn canonical_bytes(
+        entries: &HashMap<String, BaselineEntry>,
+        policy: SymlinkPolicy,
+        annotations: &BTreeMap<String, FileAnnotation>,
+        tracked_xattrs: &[String],
+    ) -> Vec<u8> {
         let mut keys: Vec<&String> = entries.keys().collect();
         keys.sort();
 
@@ -193,15 +713,90 @@ impl IntegrityScanner {
             hasher.update(entry.hash.as_bytes());
             hasher.update(b":");
             hasher.update(entry.size.to_le_bytes());
+            // Only hashed when present so existing baselines keep their
+            // signature.
+            if let Some(quick_hash) = &entry.quick_hash {
+                hasher.update(b":q:");
+                hasher.update(quick_hash.as_bytes());
+            }
+            // Likewise for attributes, absent from v1 baselines.
+            if let Some(ownership) = &entry.ownership {
+                hasher.update(b":o:");
+                hasher.update(serde_json::to_vec(ownership).unwrap_or_default());
+            }
+            if let Some(xattrs) = &entry.xattrs {
+                hasher.update(b":x:");
+                for (name, value) in xattrs {
+                    hasher.update(name.as_bytes());
+                    hasher.update(b"=");
+                    hasher.update(value.as_bytes());
+                    hasher.update(b"\0");
+                }
+            }
+            hasher.update(b"\n");
+        }
+        // Only hashed when not the default so existing baselines keep their
+        // signature.
+        if policy != SymlinkPolicy::default() {
+            hasher.update(b"symlink_policy:");
+            hasher.update(serde_json::to_vec(&policy).unwrap_or_default());
+            hasher.update(b"\n");
+        }
+        // Only hashed when present so untagged baselines keep their signature.
+        if !annotations.is_empty() {
+            hasher.update(b"annotations\n");
+            for (path, annotation) in annotations {
+                hasher.update(path.as_bytes());
+                for tag in &annotation.tags {
+                    hasher.update(b"\0t:");
+                    hasher.update(tag.as_bytes());
+                }
+                for (k, v) in &annotation.metadata {
+                    hasher.update(b"\0m:");
+                    hasher.update(k.as_bytes());
+                    hasher.update(b"=");
+                    hasher.update(v.as_bytes());
+                }
+                hasher.update(b"\n");
+            }
+        }
+        if !tracked_xattrs.is_empty() {
+            hasher.update(b"tracked_xattrs:");
+            hasher.update(tracked_xattrs.join("\0").as_bytes());
             hasher.update(b"\n");
         }
         hasher.finalize().to_vec()
     }
 
+    fn baseline_bytes(baseline: &Baseline) -> Vec<u8> {
+        Self::canonical_bytes(
+            &baseline.entries,
+            baseline.symlink_policy,
+            &baseline.annotations,
+            &baseline.tracked_xattrs,
+        )
+    }
+
+    /// Re-sign a baseline after its annotations changed.
+    pub fn sign_baseline(baseline: &mut Baseline, signing_key: &SigningKey) {
+        let canonical = Self::baseline_bytes(baseline);
+        baseline.signature = hex::encode(signing_key.sign(&canonical).to_bytes());
+    }
+
     /// Generate a new baseline, signed with the device's Ed25519 key
     pub fn generate_baseline(&self, signing_key: &SigningKey) -> Result<Baseline> {
+        self.generate_baseline_with(signing_key, None)
+    }
+
+    /// Generate a new baseline, keeping annotations from `previous` for
+    /// paths that are still present.
+    pub fn generate_baseline_with(
+        &self,
+        signing_key: &SigningKey,
+        previous: Option<&Baseline>,
+    ) -> Result<Baseline> {
         info!("Generating integrity baseline for {} protected paths", self.protected_paths.len());
-        let (entries, errors) = self.collect_entries();
+        let Collected { entries, errors, .. } = self.collect_entries();
 
         if !errors.is_empty() {
             warn!("{} errors during baseline generation", errors.len());
@@ -210,23 +805,175 @@ impl IntegrityScanner {
             }
         }
 
-        let canonical = Self::canonical_bytes(&entries);
-        let signature = signing_key.sign(&canonical);
+        let annotations: BTreeMap<String, FileAnnotation> = previous
+            .map(|prev| {
+                prev.annotations
+                    .iter()
+                    .filter(|(path, _)| entries.contains_key(*path))
+                    .map(|(p, a)| (p.clone(), a.clone()))
+                    .collect()
+            })
+            .unwrap_or_default();
 
         info!("Baseline generated: {} files", entries.len());
 
-        Ok(Baseline {
-            version: 1,
+        let mut baseline = Baseline {
+            version: BASELINE_VERSION,
             created_at: Utc::now(),
             device_id: self.device_id.clone(),
             entries,
-            signature: hex::encode(signature.to_bytes()),
-        })
+            symlink_policy: self.symlink_policy,
+            annotations,
+            tracked_xattrs: self.attributes.xattrs.clone(),
+            signature: String::new(),
+            operator_signature: None,
+        };
+        Self::sign_baseline(&mut baseline, signing_key);
+        Ok(baseline)
+    }
+
+    /// Re-read `paths` from disk into `baseline` and re-sign it. Entries at or
+    /// beneath each path are replaced with what is there now; paths that no
+    /// longer exist drop out. Files are hashed as a scan would, quick-check
+    /// rules and sandbox included. Returns the number of entries refreshed.
+    pub fn refresh_paths(
+        &self,
+        baseline: &mut Baseline,
+        paths: &[PathBuf],
+        signing_key: &SigningKey,
+    ) -> usize {
+        baseline
+            .entries
+            .retain(|key, _| !paths.iter().any(|p| Path::new(key).starts_with(p)));
+        let attributes = AttributeConfig::of_baseline(baseline);
+        let Collected {
+            entries: fresh,
+            errors,
+            ..
+        } = Self::collect_entries_under(
+            paths,
+            baseline.symlink_policy,
+            &HashConfig {
+                progress: None,
+                ..self.hashing.clone()
+            },
+            None,
+            Some(&attributes),
+        );
+        for e in errors {
+            warn!(path = %e.path, error = %e.error, "refresh skipped path");
+        }
+        let refreshed = fresh.len();
+        baseline.entries.extend(fresh);
+        let entries = &baseline.entries;
+        baseline.annotations.retain(|key, _| entries.contains_key(key));
+        Self::sign_baseline(baseline, signing_key);
+        refreshed
+    }
+
+    /// Bring a baseline written by an older build up to `BASELINE_VERSION`.
+    /// Returns `None` if it is already current.
+    ///
+    /// v1 → v2 records ownership and tracked attributes for entries whose
+    /// content still matches the baseline; changed files stay without them
+    /// and pick them up at the next rebaseline. The baseline must verify
+    /// against `signing_key`, which re-signs it. A countersigned baseline
+    /// only has its version raised, since re-signing would drop the operator
+    /// signature.
+    pub fn migrate_baseline(
+        &self,
+        baseline: &mut Baseline,
+        signing_key: &SigningKey,
+    ) -> Result<Option<BaselineMigration>> {
+        let from_version = baseline.version;
+        if from_version > BASELINE_VERSION {
+            anyhow::bail!(
+                "baseline format v{from_version} is newer than the supported v{BASELINE_VERSION}"
+            );
+        }
+        if from_version == BASELINE_VERSION {
+            return Ok(None);
+        }
+        if !Self::verify_baseline_signature(baseline, &signing_key.verifying_key())? {
+            anyhow::bail!("baseline signature does not verify; not migrating");
+        }
+
+        let mut adopted = 0;
+        if baseline.operator_signature.is_none() {
+            baseline.tracked_xattrs = self.attributes.xattrs.clone();
+            let hashing = HashConfig {
+                sandbox: self.hashing.sandbox.clone(),
+                ..Default::default()
+            };
+            for entry in baseline.entries.values_mut().filter(|e| e.link_target.is_none()) {
+                let path = Path::new(&entry.path);
+                let intact = Self::hash_file(path, &hashing)
+                    .is_ok_and(|(hash, _)| hash == entry.hash);
+                let Some(metadata) = intact.then(|| fs::metadata(path).ok()).flatten() else {
+                    continue;
+                };
+                if let Ok((ownership, xattrs)) = attributes::read(path, &metadata, &self.attributes)
+                {
+                    entry.ownership = ownership;
+                    entry.xattrs = xattrs;
+                    adopted += 1;
+                }
+            }
+            Self::sign_baseline(baseline, signing_key);
+        }
+        baseline.version = BASELINE_VERSION;
+        Ok(Some(BaselineMigration {
+            from_version,
+            to_version: BASELINE_VERSION,
+            adopted,
+            skipped: baseline.entries.len() - adopted,
+        }))
+    }
+
+    /// The bytes an operator signs to countersign `baseline`.
+    pub fn operator_signing_message(baseline: &Baseline) -> Vec<u8> {
+        let mut msg = OPERATOR_SIGNING_CONTEXT.to_vec();
+        msg.extend(Self::baseline_bytes(baseline));
+        msg
+    }
+
+    /// Attach an operator countersignature after checking it.
+    pub fn countersign_baseline(
+        baseline: &mut Baseline,
+        signature_hex: &str,
+        operator_key: &VerifyingKey,
+    ) -> Result<()> {
+        let sig_bytes = hex::decode(signature_hex.trim())
+            .context("Invalid operator signature hex")?;
+        let signature = Signature::from_bytes(
+            sig_bytes.as_slice().try_into().map_err(|_| anyhow::anyhow!("Invalid signature length"))?
+        );
+        operator_key
+            .verify_strict(&Self::operator_signing_message(baseline), &signature)
+            .map_err(|_| anyhow::anyhow!("operator signature does not match this baseline"))?;
+        baseline.operator_signature = Some(hex::encode(signature.to_bytes()));
+        Ok(())
+    }
+
+    /// False when the countersignature is missing, malformed or stale.
+    pub fn verify_operator_signature(baseline: &Baseline, operator_key: &VerifyingKey) -> bool {
+        let Some(sig_hex) = &baseline.operator_signature else {
+            return false;
+        };
+        let Ok(sig_bytes) = hex::decode(sig_hex) else {
+            return false;
+        };
+        let Ok(arr) = <[u8; 64]>::try_from(sig_bytes.as_slice()) else {
+            return false;
+        };
+        operator_key
+            .verify_strict(&Self::operator_signing_message(baseline), &Signature::from_bytes(&arr))
+            .is_ok()
     }
 
     /// Verify a baseline's signature
     pub fn verify_baseline_signature(baseline: &Baseline, verifying_key: &VerifyingKey) -> Result<bool> {
-        let canonical = Self::canonical_bytes(&baseline.entries);
+        let canonical = Self::baseline_bytes(baseline);
         let sig_bytes = hex::decode(&baseline.signature)
             .context("Invalid baseline signature hex")?;
         let signature = Signature::from_bytes(
@@ -242,11 +989,33 @@ impl IntegrityScanner {
     /// Scan current state and compare against a baseline
     pub fn scan_against_baseline(&self, baseline: &Baseline) -> ScanResult {
         info!("Running integrity scan against baseline ({} entries)", baseline.entries.len());
-        let (current_entries, errors) = self.collect_entries();
+        let mut current_entries = HashMap::new();
+        let mut errors = Vec::new();
+        let mut root_durations_ms = BTreeMap::new();
+        let mut quick_check = QuickCheckStats::default();
+        for root in &self.protected_paths {
+            let started = std::time::Instant::now();
+            let collected = Self::collect_entries_under(
+                std::slice::from_ref(root),
+                baseline.symlink_policy,
+                &self.hashing,
+                Some(baseline),
+                None,
+            );
+            root_durations_ms.insert(
+                root.display().to_string(),
+                started.elapsed().as_millis() as u64,
+            );
+            current_entries.extend(collected.entries);
+            errors.extend(collected.errors);
+            quick_check.verified += collected.quick_check.verified;
+            quick_check.escalated += collected.quick_check.escalated;
+        }
 
         let mut modified = Vec::new();
         let mut added = Vec::new();
         let mut removed = Vec::new();
+        let mut attribute_changes = Vec::new();
 
         // Check for modified and removed files
         for (path, expected) in &baseline.entries {
@@ -260,6 +1029,18 @@ impl IntegrityScanner {
                             expected_size: expected.size,
                             actual_size: actual.size,
                         });
+                    } else {
+                        match attributes::current_changes(
+                            Path::new(path),
+                            expected,
+                            &baseline.tracked_xattrs,
+                        ) {
+                            Ok(changes) => attribute_changes.extend(changes),
+                            Err(e) => errors.push(ScanError {
+                                path: path.clone(),
+                                error: e.to_string(),
+                            }),
+                        }
                     }
                 }
                 None => {
@@ -275,15 +1056,33 @@ impl IntegrityScanner {
             }
         }
 
-        let valid = modified.is_empty() && removed.is_empty();
+        let symlink_swaps = Self::find_symlink_swaps(baseline, &modified, &removed);
+
+        attribute_changes.sort_by(|a: &AttributeChange, b| {
+            (&a.path, &a.attribute).cmp(&(&b.path, &b.attribute))
+        });
+        let valid = modified.is_empty() && removed.is_empty() && attribute_changes.is_empty();
         let total_files = current_entries.len();
+        let tags = modified
+            .iter()
+            .map(|m| &m.path)
+            .chain(removed.iter())
+            .chain(attribute_changes.iter().map(|c| &c.path))
+            .filter_map(|path| {
+                let tags = baseline.tags_for(path);
+                (!tags.is_empty()).then(|| (path.clone(), tags))
+            })
+            .collect();
 
         if valid {
             info!("Integrity scan passed: {} files verified", total_files);
         } else {
+            for swap in &symlink_swaps {
+                error!("SYMLINK SWAP: {} -> {}", swap.link, swap.target);
+            }
             error!(
-                "INTEGRITY VIOLATION: {} modified, {} removed, {} added",
-                modified.len(), removed.len(), added.len()
+                "INTEGRITY VIOLATION: {} modified, {} removed, {} added, {} attribute changes",
+                modified.len(), removed.len(), added.len(), attribute_changes.len()
             );
         }
 
@@ -295,9 +1094,43 @@ impl IntegrityScanner {
             removed,
             errors,
             valid,
+            tags,
+            root_durations_ms,
+            symlink_swaps,
+            quick_check,
+            attribute_changes,
         }
     }
 
+    /// Links responsible for modified / removed entries: a baselined file or
+    /// directory that is now a link, or a protected link that was retargeted.
+    fn find_symlink_swaps(
+        baseline: &Baseline,
+        modified: &[ModifiedFile],
+        removed: &[String],
+    ) -> Vec<SymlinkSwap> {
+        let mut swaps: BTreeMap<String, String> = BTreeMap::new();
+        for path in modified.iter().map(|m| &m.path).chain(removed) {
+            let Some(entry) = baseline.entries.get(path) else {
+                continue;
+            };
+            let is_link = entry.link_target.is_some();
+            let found = find_swapped_link(Path::new(path), is_link).or_else(|| {
+                is_link
+                    .then(|| fs::read_link(path).ok())
+                    .flatten()
+                    .map(|target| (PathBuf::from(path), target))
+            });
+            if let Some((link, target)) = found {
+                swaps.insert(link.display().to_string(), target.display().to_string());
+            }
+        }
+        swaps
+            .into_iter()
+            .map(|(link, target)| SymlinkSwap { link, target })
+            .collect()
+    }
+
     /// Save baseline to disk as JSON
     pub fn save_baseline(baseline: &Baseline, path: &Path) -> Result<()> {
         let json = serde_json::to_string_pretty(baseline)?;
@@ -306,13 +1139,94 @@ impl IntegrityScanner {
         Ok(())
     }
 
-    /// Load baseline from disk
+    /// Load baseline from disk. Fails for formats newer than this build.
     pub fn load_baseline(path: &Path) -> Result<Baseline> {
         let json = fs::read_to_string(path)?;
         let baseline: Baseline = serde_json::from_str(&json)?;
+        if baseline.version > BASELINE_VERSION {
+            anyhow::bail!(
+                "baseline {} is format v{}, newer than the supported v{}",
+                path.display(),
+                baseline.version,
+                BASELINE_VERSION
+            );
+        }
         debug!("Baseline loaded from {} ({} entries)", path.display(), baseline.entries.len());
         Ok(baseline)
     }
+
+    /// Where a rebaseline candidate for `baseline_path` is staged.
+    pub fn pending_baseline_path(baseline_path: &Path) -> PathBuf {
+        let mut name = baseline_path.as_os_str().to_owned();
+        name.push(".pending");
+        PathBuf::from(name)
+    }
+
+    /// Where a rebaseline waits for an operator countersignature under dual
+    /// control. Unlike a pending candidate it survives a restart.
+    pub fn countersign_baseline_path(baseline_path: &Path) -> PathBuf {
+        let mut name = baseline_path.as_os_str().to_owned();
+        name.push(".countersign");
+        PathBuf::from(name)
+    }
+
+    /// Write `baseline` as the pending candidate for `baseline_path`, synced
+    /// to disk. The active baseline is untouched.
+    pub fn write_pending(baseline: &Baseline, baseline_path: &Path) -> Result<PathBuf> {
+        let pending = Self::pending_baseline_path(baseline_path);
+        let mut file = fs::File::create(&pending)
+            .with_context(|| format!("create {}", pending.display()))?;
+        std::io::Write::write_all(&mut file, serde_json::to_string_pretty(baseline)?.as_bytes())?;
+        file.sync_all()?;
+        debug!("Pending baseline written to {}", pending.display());
+        Ok(pending)
+    }
+
+    /// Check a candidate before it replaces the active baseline: the device
+    /// signature verifies, every entry still matches disk, and every file
+    /// entry has its blob in `store`. Returns the problems found.
+    pub fn validate_candidate(
+        &self,
+        baseline: &Baseline,
+        verifying_key: &VerifyingKey,
+        store: &BackupStore,
+    ) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !Self::verify_baseline_signature(baseline, verifying_key).unwrap_or(false) {
+            problems.push("baseline signature does not verify".to_string());
+        }
+        for (key, entry) in &baseline.entries {
+            let path = Path::new(&entry.path);
+            let on_disk = match &entry.link_target {
+                Some(_) => fs::read_link(path).map(|target| link_hash(&target)).map_err(Into::into),
+                None => Self::hash_file(path, &self.hashing).map(|(hash, _)| hash),
+            };
+            match on_disk {
+                Ok(hash) if hash == entry.hash => {}
+                Ok(_) => problems.push(format!("{key}: changed since the candidate was hashed")),
+                Err(e) => problems.push(format!("{key}: {e}")),
+            }
+            if entry.link_target.is_none() && !store.has_blob(&entry.path, &entry.hash) {
+                problems.push(format!("{key}: no backup blob"));
+            }
+        }
+        problems
+    }
+
+    /// Atomically replace the active baseline with its validated pending
+    /// candidate.
+    pub fn commit_pending(baseline_path: &Path) -> Result<()> {
+        let pending = Self::pending_baseline_path(baseline_path);
+        fs::rename(&pending, baseline_path).with_context(|| {
+            format!("commit {} to {}", pending.display(), baseline_path.display())
+        })?;
+        #[cfg(unix)]
+        if let Some(dir) = baseline_path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::File::open(dir)?.sync_all()?;
+        }
+        debug!("Pending baseline committed to {}", baseline_path.display());
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -330,7 +1244,7 @@ mod tests {
         let file_path = dir.path().join("test.txt");
         File::create(&file_path).unwrap().write_all(b"hello world").unwrap();
 
-        let (hash, size) = IntegrityScanner::hash_file(&file_path).unwrap();
+        let (hash, size) = IntegrityScanner::hash_file(&file_path, &HashConfig::default()).unwrap();
         assert_eq!(size, 11);
         assert!(!hash.is_empty());
     }
@@ -356,6 +1270,9 @@ mod tests {
         assert!(result.valid);
         assert!(result.modified.is_empty());
         assert!(result.removed.is_empty());
+        assert!(result
+            .root_durations_ms
+            .contains_key(&dir.path().display().to_string()));
 
         // Modify a file
         File::create(dir.path().join("a.txt")).unwrap().write_all(b"MODIFIED").unwrap();
@@ -363,4 +1280,137 @@ mod tests {
         assert!(!result.valid);
         assert_eq!(result.modified.len(), 1);
     }
+
+    #[test]
+    fn test_operator_countersignature() {
+        let dir = tempdir().unwrap();
+        File::create(dir.path().join("a.txt")).unwrap().write_all(b"aaa").unwrap();
+        let device = SigningKey::generate(&mut OsRng);
+        let operator = SigningKey::generate(&mut OsRng);
+        let scanner = IntegrityScanner::new(vec![dir.path().to_path_buf()], "test-device".into());
+        let mut baseline = scanner.generate_baseline(&device).unwrap();
+        assert!(!IntegrityScanner::verify_operator_signature(&baseline, &operator.verifying_key()));
+
+        // The device key can't stand in for the operator.
+        let forged = device.sign(&IntegrityScanner::operator_signing_message(&baseline));
+        assert!(IntegrityScanner::countersign_baseline(
+            &mut baseline,
+            &hex::encode(forged.to_bytes()),
+            &operator.verifying_key()
+        )
+        .is_err());
+
+        let sig = operator.sign(&IntegrityScanner::operator_signing_message(&baseline));
+        IntegrityScanner::countersign_baseline(
+            &mut baseline,
+            &hex::encode(sig.to_bytes()),
+            &operator.verifying_key(),
+        )
+        .unwrap();
+        assert!(IntegrityScanner::verify_operator_signature(&baseline, &operator.verifying_key()));
+
+        // A rebaseline re-signed by the device alone loses the countersignature.
+        File::create(dir.path().join("a.txt")).unwrap().write_all(b"evil").unwrap();
+        let paths = [dir.path().canonicalize().unwrap()];
+        IntegrityScanner::new(paths.to_vec(), "test-device".into())
+            .refresh_paths(&mut baseline, &paths, &device);
+        assert!(IntegrityScanner::verify_baseline_signature(&baseline, &device.verifying_key()).unwrap());
+        assert!(!IntegrityScanner::verify_operator_signature(&baseline, &operator.verifying_key()));
+    }
+
+    #[test]
+    fn test_annotations_are_signed_carried_and_reported() {
+        let dir = tempdir().unwrap();
+        let conf = dir.path().join("conf");
+        std::fs::create_dir(&conf).unwrap();
+        File::create(conf.join("app.toml")).unwrap().write_all(b"x=1").unwrap();
+        File::create(dir.path().join("LICENSE")).unwrap().write_all(b"MIT").unwrap();
+
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let verifying_key = signing_key.verifying_key();
+        let scanner = IntegrityScanner::new(vec![dir.path().to_path_buf()], "test-device".into());
+        let mut baseline = scanner.generate_baseline(&signing_key).unwrap();
+
+        let conf_dir = conf.canonicalize().unwrap().display().to_string();
+        let meta = BTreeMap::from([("owner".to_string(), "platform".to_string())]);
+        let touched = baseline.annotate(std::slice::from_ref(&conf_dir), &["config".into()], &[], &meta);
+        assert_eq!(touched, 1);
+
+        // Changing annotations without re-signing breaks the signature.
+        assert!(!IntegrityScanner::verify_baseline_signature(&baseline, &verifying_key).unwrap());
+        IntegrityScanner::sign_baseline(&mut baseline, &signing_key);
+        assert!(IntegrityScanner::verify_baseline_signature(&baseline, &verifying_key).unwrap());
+
+        let app = conf.join("app.toml").canonicalize().unwrap().display().to_string();
+        let rebuilt = scanner
+            .generate_baseline_with(&signing_key, Some(&baseline))
+            .unwrap();
+        assert_eq!(rebuilt.tags_for(&app), vec!["config".to_string()]);
+        assert_eq!(rebuilt.annotations[&app].metadata["owner"], "platform");
+
+        File::create(conf.join("app.toml")).unwrap().write_all(b"x=2").unwrap();
+        File::create(dir.path().join("LICENSE")).unwrap().write_all(b"GPL").unwrap();
+        let result = scanner.scan_against_baseline(&rebuilt);
+        assert_eq!(result.modified.len(), 2);
+        assert_eq!(result.tags[&app], vec!["config".to_string()]);
+        assert_eq!(result.tag_counts()["config"], 1);
+
+        let filtered = result.filter_by_tag(&rebuilt, "config");
+        assert_eq!(filtered.modified.len(), 1);
+        assert_eq!(filtered.modified[0].path, app);
+    }
+
+    #[test]
+    fn test_quick_check_escalates_on_mismatch() {
+        use guard_core::settings::QuickCheckRule;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let dir = tempdir().unwrap();
+        let big = dir.path().join("disk.img");
+        let mut data = vec![7u8; 3 * MIB as usize];
+        std::fs::write(&big, &data).unwrap();
+        File::create(dir.path().join("small.txt")).unwrap().write_all(b"small").unwrap();
+
+        let reports = Arc::new(AtomicUsize::new(0));
+        let counter = reports.clone();
+        let settings = HashingSettings {
+            progress_interval_mb: 1,
+            quick_check: vec![QuickCheckRule {
+                path: dir.path().display().to_string(),
+                min_size_mb: 2,
+            }],
+        };
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let scanner = IntegrityScanner::new(vec![dir.path().to_path_buf()], "test-device".into())
+            .with_hashing(&settings)
+            .with_progress(Arc::new(move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }));
+        let baseline = scanner.generate_baseline(&signing_key).unwrap();
+        assert_eq!(reports.load(Ordering::SeqCst), 3, "one report per MiB");
+        let key = big.canonicalize().unwrap().display().to_string();
+        assert!(baseline.entries[&key].quick_hash.is_some());
+        assert_eq!(baseline.entries.values().filter(|e| e.quick_hash.is_some()).count(), 1);
+
+        // Unchanged: settled without hashing the file again.
+        let result = scanner.scan_against_baseline(&baseline);
+        assert!(result.valid);
+        assert_eq!(result.quick_check, QuickCheckStats { verified: 1, escalated: 0 });
+        assert_eq!(reports.load(Ordering::SeqCst), 3);
+
+        // A changed tail fails the quick check and the full hash confirms it.
+        *data.last_mut().unwrap() = 8;
+        std::fs::write(&big, &data).unwrap();
+        let result = scanner.scan_against_baseline(&baseline);
+        assert_eq!(result.quick_check, QuickCheckStats { verified: 0, escalated: 1 });
+        assert_eq!(result.modified.len(), 1);
+        assert_eq!(result.modified[0].path, key);
+
+        // Refreshing the file keeps it under its quick-check rule.
+        let mut baseline = baseline;
+        scanner.refresh_paths(&mut baseline, &[dir.path().canonicalize().unwrap()], &signing_key);
+        assert!(baseline.entries[&key].quick_hash.is_some());
+        let result = scanner.scan_against_baseline(&baseline);
+        assert_eq!(result.quick_check, QuickCheckStats { verified: 1, escalated: 0 });
+    }
 }
@@ -0,0 +1,173 @@
+//! Path normalization and symlink-swap detection.
+//!
+//! Baseline keys are normalized paths: absolute, with every directory
+//! component resolved. Whether the final component is resolved too depends
+//! on the symlink policy — under `follow` a link is keyed by its target, under
+//! `protect_link` by its own location. Scanner, watcher pipeline and restore
+//! all go through `normalize_path` so a file reached via different spellings
+//! maps to one entry.
+//!
+//! A symlink swap is a baselined file or directory replaced by a link, or a
+//! protected link pointed somewhere else. Writing "through" such a link would
+//! let an attacker redirect restores, so swaps are reported on their own and
+//! the link is removed before anything is restored.
+
+use blake3::Hasher;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// A link found where the baseline expects a regular file or directory, or a
+/// protected link whose target changed.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SymlinkSwap {
+    /// Location of the link.
+    pub link: String,
+    /// Where it points now.
+    pub target: String,
+}
+
+/// Normalize `path` into baseline key form. Directory components are
+/// resolved through the nearest existing ancestor, so paths that no longer
+/// exist still normalize; the leaf is only resolved when `follow_leaf` is
+/// set and it exists.
+pub fn normalize_path(path: &Path, follow_leaf: bool) -> PathBuf {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()
+            .map(|cwd| cwd.join(path))
+            .unwrap_or_else(|_| path.to_path_buf())
+    };
+    let clean = lexical_clean(&absolute);
+    if follow_leaf {
+        if let Ok(resolved) = clean.canonicalize() {
+            return resolved;
+        }
+    }
+    let (Some(parent), Some(name)) = (clean.parent(), clean.file_name()) else {
+        return clean;
+    };
+    // Resolve the deepest ancestor that exists and re-append the rest.
+    let mut existing = parent;
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(resolved) = existing.canonicalize() {
+            let mut out = resolved;
+            out.extend(rest.iter().rev());
+            out.push(name);
+            return out;
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(up), Some(component)) => {
+                rest.push(component);
+                existing = up;
+            }
+            _ => return clean,
+        }
+    }
+}
+
+/// Drop `.` and fold `..` without touching the filesystem.
+fn lexical_clean(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+/// Baseline hash of a link pointing at `target`. Domain-separated from file
+/// hashes so a regular file can never match a link entry.
+pub fn link_hash(target: &Path) -> String {
+    let mut hasher = Hasher::new();
+    hasher.update(b"symlink\0");
+    hasher.update(target.as_os_str().as_encoded_bytes());
+    hasher.finalize().to_hex().to_string()
+}
+
+pub fn is_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink())
+}
+
+/// The outermost link among the ancestors of `path` — and `path` itself
+/// unless `leaf_may_be_link` — with its current target. Baseline keys are
+/// normalized, so any link found here was put in place after the baseline.
+pub fn find_swapped_link(path: &Path, leaf_may_be_link: bool) -> Option<(PathBuf, PathBuf)> {
+    let mut candidates: Vec<&Path> = path.ancestors().skip(1).collect();
+    candidates.reverse();
+    if !leaf_may_be_link {
+        candidates.push(path);
+    }
+    candidates
+        .into_iter()
+        .filter(|p| p.parent().is_some())
+        .find(|p| is_symlink(p))
+        .and_then(|link| fs::read_link(link).ok().map(|t| (link.to_path_buf(), t)))
+}
+
+/// Remove the link at `link` without touching what it points to.
+pub fn remove_link(link: &Path) -> std::io::Result<()> {
+    #[cfg(windows)]
+    {
+        // Directory links must be removed as directories on Windows.
+        if fs::metadata(link).is_ok_and(|m| m.is_dir()) {
+            return fs::remove_dir(link);
+        }
+    }
+    fs::remove_file(link)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+    use tempfile::tempdir;
+
+    #[test]
+    fn normalize_resolves_directories_but_not_the_leaf() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::create_dir(root.join("real")).unwrap();
+        fs::write(root.join("real/app.conf"), b"x").unwrap();
+        symlink(root.join("real"), root.join("alias")).unwrap();
+        symlink(root.join("real/app.conf"), root.join("real/conf-link")).unwrap();
+
+        let via_alias = root.join("alias/./sub/../app.conf");
+        assert_eq!(normalize_path(&via_alias, false), root.join("real/app.conf"));
+        assert_eq!(
+            normalize_path(&root.join("alias/conf-link"), false),
+            root.join("real/conf-link")
+        );
+        assert_eq!(
+            normalize_path(&root.join("alias/conf-link"), true),
+            root.join("real/app.conf")
+        );
+        // Missing paths still resolve their existing ancestors.
+        assert_eq!(
+            normalize_path(&root.join("alias/gone/x"), false),
+            root.join("real/gone/x")
+        );
+    }
+
+    #[test]
+    fn swapped_directory_is_found_above_the_leaf() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::create_dir(root.join("elsewhere")).unwrap();
+        symlink(root.join("elsewhere"), root.join("app")).unwrap();
+
+        let (link, target) = find_swapped_link(&root.join("app/bin/run"), false).unwrap();
+        assert_eq!(link, root.join("app"));
+        assert_eq!(target, root.join("elsewhere"));
+        assert!(find_swapped_link(&root.join("elsewhere/x"), false).is_none());
+        assert!(find_swapped_link(&root.join("app"), true).is_none());
+        assert_ne!(link_hash(&target), link_hash(&root.join("app")));
+    }
+}
@@ -2,10 +2,17 @@
 //!
 //! Watches protected paths for changes and sends events through a channel
 //! to be processed by the integrity scanner.
+//!
+//! Native watchers hold an OS descriptor per watched directory (inotify on
+//! Linux), so a huge protected tree can exhaust the per-user limit. The
+//! watcher is given a descriptor budget; trees that don't fit are polled
+//! instead, largest first, which is slower to notice changes but costs no
+//! descriptors.
 
 use anyhow::Result;
-use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
-use std::path::PathBuf;
+use notify::{Config, Event, EventKind, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
 use std::sync::mpsc;
 use std::time::Duration;
 use tokio::sync::broadcast;
@@ -21,9 +28,17 @@ pub enum FileChange {
     PermissionChanged(PathBuf),
 }
 
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
 /// FileWatcher watches protected directories for changes
 pub struct FileWatcher {
     watcher: RecommendedWatcher,
+    /// Created on first use; most installs never need it.
+    poller: Option<PollWatcher>,
+    event_tx: mpsc::Sender<Result<Event, notify::Error>>,
+    /// Natively watched paths and their estimated descriptor cost.
+    native: BTreeMap<PathBuf, u64>,
+    polled: BTreeSet<PathBuf>,
     change_tx: broadcast::Sender<FileChange>,
 }
 
@@ -35,12 +50,13 @@ impl FileWatcher {
 
         let (sync_tx, sync_rx) = mpsc::channel::<Result<Event, notify::Error>>();
 
+        let event_tx = sync_tx.clone();
         let watcher = RecommendedWatcher::new(
             move |res| {
                 let _ = sync_tx.send(res);
             },
             Config::default()
-                .with_poll_interval(Duration::from_secs(2)),
+                .with_poll_interval(POLL_INTERVAL),
         )?;
 
         // Spawn thread to bridge sync notify events to async broadcast
@@ -73,42 +89,145 @@ impl FileWatcher {
         Ok((
             Self {
                 watcher,
+                poller: None,
+                event_tx,
+                native: BTreeMap::new(),
+                polled: BTreeSet::new(),
                 change_tx: tx,
             },
             change_rx,
         ))
     }
 
-    /// Start watching a list of paths
-    pub fn watch_paths(&mut self, paths: &[PathBuf]) -> Result<()> {
+    /// Start watching a list of paths with at most `budget` native
+    /// descriptors. Returns the paths that had to be polled.
+    pub fn watch_paths(&mut self, paths: &[PathBuf], budget: u64) -> Result<Vec<PathBuf>> {
+        let mut estimates = Vec::new();
         for path in paths {
             if path.exists() {
-                let mode = if path.is_dir() {
-                    RecursiveMode::Recursive
-                } else {
-                    RecursiveMode::NonRecursive
-                };
-                self.watcher.watch(path, mode)?;
-                info!("Watching: {}", path.display());
+                estimates.push((path.clone(), estimate_watches(path)));
             } else {
                 warn!("Path does not exist, cannot watch: {}", path.display());
             }
         }
+        let polled = plan_polling(&estimates, budget);
+        for (path, watches) in estimates {
+            if polled.contains(&path) {
+                self.poll(&path)?;
+            } else {
+                self.watcher.watch(&path, recursive_mode(&path))?;
+                self.native.insert(path.clone(), watches);
+                info!("Watching: {}", path.display());
+            }
+        }
+        Ok(polled)
+    }
+
+    /// Re-estimate the natively watched trees, which grow as files are
+    /// added, and move the largest to polling until they fit `budget`.
+    /// Returns the paths moved.
+    pub fn rebalance(&mut self, budget: u64) -> Result<Vec<PathBuf>> {
+        for (path, watches) in self.native.iter_mut() {
+            *watches = estimate_watches(path);
+        }
+        let estimates: Vec<(PathBuf, u64)> =
+            self.native.iter().map(|(p, w)| (p.clone(), *w)).collect();
+        let moved = plan_polling(&estimates, budget);
+        for path in &moved {
+            self.watcher.unwatch(path)?;
+            self.native.remove(path);
+            self.poll(path)?;
+        }
+        Ok(moved)
+    }
+
+    fn poll(&mut self, path: &Path) -> Result<()> {
+        let poller = match &mut self.poller {
+            Some(poller) => poller,
+            None => {
+                let tx = self.event_tx.clone();
+                self.poller.insert(PollWatcher::new(
+                    move |res| {
+                        let _ = tx.send(res);
+                    },
+                    Config::default().with_poll_interval(POLL_INTERVAL),
+                )?)
+            }
+        };
+        poller.watch(path, recursive_mode(path))?;
+        self.polled.insert(path.to_path_buf());
+        warn!("Polling (over watch budget): {}", path.display());
         Ok(())
     }
 
     /// Stop watching a path
-    pub fn unwatch(&mut self, path: &PathBuf) -> Result<()> {
-        self.watcher.unwatch(path)?;
+    pub fn unwatch(&mut self, path: &Path) -> Result<()> {
+        if self.polled.remove(path) {
+            if let Some(poller) = &mut self.poller {
+                poller.unwatch(path)?;
+            }
+        } else {
+            self.native.remove(path);
+            self.watcher.unwatch(path)?;
+        }
         Ok(())
     }
 
+    /// Estimated native descriptors in use.
+    pub fn watch_count(&self) -> u64 {
+        self.native.values().sum()
+    }
+
+    pub fn polled_paths(&self) -> Vec<PathBuf> {
+        self.polled.iter().cloned().collect()
+    }
+
     /// Get a new receiver for file changes
     pub fn subscribe(&self) -> broadcast::Receiver<FileChange> {
         self.change_tx.subscribe()
     }
 }
 
+fn recursive_mode(path: &Path) -> RecursiveMode {
+    if path.is_dir() {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    }
+}
+
+/// Native descriptors needed to watch `path`: one per directory in the tree,
+/// or one for a single file.
+pub fn estimate_watches(path: &Path) -> u64 {
+    if path.is_dir() {
+        walkdir::WalkDir::new(path)
+            .into_iter()
+            .flatten()
+            .filter(|e| e.file_type().is_dir())
+            .count() as u64
+    } else {
+        1
+    }
+}
+
+/// Which of `estimates` (path, descriptors) to poll so the rest fit
+/// `budget`: the largest go first, so as few trees as possible lose
+/// realtime events.
+pub fn plan_polling(estimates: &[(PathBuf, u64)], budget: u64) -> Vec<PathBuf> {
+    let mut total: u64 = estimates.iter().map(|(_, w)| w).sum();
+    let mut by_size: Vec<&(PathBuf, u64)> = estimates.iter().collect();
+    by_size.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let mut polled = Vec::new();
+    for (path, watches) in by_size {
+        if total <= budget {
+            break;
+        }
+        total -= watches;
+        polled.push(path.clone());
+    }
+    polled
+}
+
 /// Classify a notify event into our FileChange types
 fn classify_event(event: &Event) -> Vec<FileChange> {
     let mut changes = Vec::new();
@@ -150,3 +269,72 @@ fn classify_event(event: &Event) -> Vec<FileChange> {
 
     changes
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn largest_trees_are_polled_first() {
+        let estimates = vec![
+            (PathBuf::from("/etc"), 40),
+            (PathBuf::from("/srv/www"), 900),
+            (PathBuf::from("/opt/app"), 300),
+        ];
+        assert!(plan_polling(&estimates, 2000).is_empty());
+        assert_eq!(plan_polling(&estimates, 500), vec![PathBuf::from("/srv/www")]);
+        assert_eq!(
+            plan_polling(&estimates, 100),
+            vec![PathBuf::from("/srv/www"), PathBuf::from("/opt/app")]
+        );
+        assert_eq!(plan_polling(&estimates, 0).len(), 3);
+    }
+
+    #[test]
+    fn watches_are_counted_per_directory() {
+        let dir = tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        std::fs::create_dir_all(dir.path().join("c")).unwrap();
+        std::fs::write(dir.path().join("a/file"), b"x").unwrap();
+        std::fs::write(dir.path().join("c/file"), b"x").unwrap();
+        assert_eq!(estimate_watches(dir.path()), 4);
+        assert_eq!(estimate_watches(&dir.path().join("a/file")), 1);
+    }
+
+    #[tokio::test]
+    async fn over_budget_tree_is_polled_and_still_reports_changes() {
+        let small = tempdir().unwrap();
+        let large = tempdir().unwrap();
+        for i in 0..5 {
+            std::fs::create_dir(large.path().join(format!("d{i}"))).unwrap();
+        }
+        let (mut fw, mut rx) = FileWatcher::new().unwrap();
+        let polled = fw
+            .watch_paths(&[small.path().to_path_buf(), large.path().to_path_buf()], 3)
+            .unwrap();
+        assert_eq!(polled, vec![large.path().to_path_buf()]);
+        assert_eq!(fw.watch_count(), 1);
+        assert_eq!(fw.polled_paths(), polled);
+
+        let target = large.path().join("d0/new.txt");
+        std::fs::write(&target, b"x").unwrap();
+        let seen = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                match rx.recv().await.unwrap() {
+                    FileChange::Created(p) | FileChange::Modified(p) if p == target => break,
+                    _ => {}
+                }
+            }
+        })
+        .await;
+        assert!(seen.is_ok(), "polled tree reported no change");
+
+        // Growing the natively watched tree past the budget moves it too.
+        for i in 0..3 {
+            std::fs::create_dir(small.path().join(format!("d{i}"))).unwrap();
+        }
+        assert_eq!(fw.rebalance(3).unwrap(), vec![small.path().to_path_buf()]);
+        assert_eq!(fw.watch_count(), 0);
+    }
+}
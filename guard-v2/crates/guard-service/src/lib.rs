@@ -2,4 +2,6 @@ pub mod connected;
 pub mod enforcement;
 pub mod engine;
 pub mod integrity;
+pub mod selftest;
 pub mod service_state;
+pub mod supervisor;
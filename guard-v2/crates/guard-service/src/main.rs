@@ -2,44 +2,83 @@ use anyhow::{anyhow, Result};
 use chrono::Utc;
 use clap::{Parser, Subcommand};
 use guard_core::backup_store::BackupStore;
-use guard_core::event_log::{EventLog, EventSeverity};
-use guard_core::ipc::{IpcHandler, IpcRequest, IpcResponse, IpcServer};
+use guard_core::event_log::{EventEntry, EventLog, EventSeverity};
+use guard_core::events::GuardEvent;
+use guard_core::ipc::{
+    IpcHandler, IpcRequest, IpcResponse, IpcServer, RemoteConnectionEvent, RestoreItem,
+};
+use guard_core::instances::{register_running, register_stopped, set_instance};
+use guard_core::ipc_audit::{ClientIdentity, RateLimiter, RateLimits, RequestAudit};
 use guard_core::paths::{data_dir, ipc_socket_path, log_dir};
+use guard_core::policy::decode_org_key;
 use guard_core::safe_mode::{SafeModeReason, SafeModeState};
 use guard_core::secure_storage::store_ipc_secret;
+use guard_core::settings::GuardSettings;
+use guard_core::settings_history::SettingsVersion;
+use guard_core::storage::{
+    load_baseline_operator_key, load_policy_org_key, load_sbom_bindings, load_settings_history,
+};
 use guard_core::vault::{Vault, CURRENT_CONFIG_VERSION, VAULT_VERSION};
 use parking_lot::Mutex;
 use serde::Deserialize;
-use std::path::PathBuf;
-use std::process::Command;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::signal;
-use tokio::sync::watch;
-use tracing::{info, warn};
+use tokio::sync::{broadcast, watch};
+use tracing::{debug, error, info, warn};
 use zeroize::Zeroizing;
 
+mod attestation;
 mod connected;
 mod enforcement;
 mod engine;
 pub mod integrity;
+mod resources;
+mod selftest;
 mod status;
 mod service_state;
+mod shutdown;
+mod supervisor;
+mod updater;
+#[cfg(windows)]
+mod win_service;
 
+use crate::connected::receipts::ReceiptLog;
+use crate::enforcement::evidence::{self, Custodian};
+use crate::enforcement::pending::PendingRestores;
+use crate::enforcement::privileged::PrivilegedHelper;
 use crate::enforcement::quarantine::QuarantineZone;
 use crate::enforcement::restore::RestoreEngine;
-use crate::engine::Engine;
+use crate::enforcement::snapshot::SnapshotManager;
+use crate::enforcement::write_freeze::WriteFreeze;
+use crate::engine::{
+    baseline_operator_key, hold_for_countersign, Engine, EngineEvent, EngineMode,
+};
 use crate::integrity::audit_loop::{spawn_audit_loop, AuditLoopHandle};
-use crate::integrity::pipeline::spawn_watcher_pipeline;
-use crate::integrity::scanner::{Baseline, IntegrityScanner};
+use crate::integrity::sbom::verify_binding;
+use crate::integrity::burst::{spawn_burst_detector, BurstReport};
+use crate::integrity::estimate::estimate_path;
+use crate::integrity::pipeline::{spawn_watcher_pipeline, TamperEvent};
+use crate::integrity::sandbox::ScanWorker;
+use crate::integrity::scanner::{Baseline, HashProgress, IntegrityScanner};
 use crate::integrity::watcher::FileWatcher;
+use crate::attestation::Attestation;
 use crate::service_state::{CrashTracker, ServiceState};
+use crate::shutdown::ShutdownSignal;
+use crate::supervisor::{RestartPolicy, Supervisor};
+use crate::updater::run_updater;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "Darklock Guard v2 Service", long_about = None)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Named instance to run, with its own data dir and sockets
+    #[arg(long, global = true)]
+    instance: Option<String>,
 }
 
 #[derive(Subcommand, Debug)]
@@ -54,15 +93,36 @@ enum Commands {
         #[arg(long)]
         data_dir: Option<PathBuf>,
     },
+    /// Confined hashing worker, started by the service itself
+    #[command(hide = true)]
+    ScanWorker {
+        #[arg(long, default_value = "nobody")]
+        user: String,
+    },
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    // Before the runtime or logging start any threads: the worker confines
+    // only its own.
+    if let Commands::ScanWorker { user } = &cli.command {
+        return integrity::sandbox::run_worker(user);
+    }
+    #[cfg(windows)]
+    if matches!(cli.command, Commands::Run { .. }) && win_service::run_if_started_by_scm()? {
+        return Ok(());
+    }
+    serve(cli)
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn serve(cli: Cli) -> Result<()> {
     tracing_subscriber::fmt::init();
-    let cli = Cli::parse();
+    set_instance(cli.instance.as_deref())?;
     match cli.command {
         Commands::Init { data_dir } => init_command(data_dir).await,
         Commands::Run { data_dir } => run_command(data_dir).await,
+        Commands::ScanWorker { .. } => unreachable!("handled before the runtime starts"),
     }
 }
 
@@ -85,6 +145,7 @@ async fn init_command(data_dir_override: Option<PathBuf>) -> Result<()> {
 }
 
 async fn run_command(data_dir_override: Option<PathBuf>) -> Result<()> {
+    let started_at = std::time::Instant::now();
     let data = data_dir_override.unwrap_or(data_dir()?);
     std::fs::create_dir_all(&data)?;
     std::fs::create_dir_all(log_dir()?)?;
@@ -98,6 +159,7 @@ async fn run_command(data_dir_override: Option<PathBuf>) -> Result<()> {
     let signing_key_clone = signing_key.clone();
     let log_path = log_dir()?.join("events.log");
     let event_log = Arc::new(EventLog::new(log_path, signing_key, 5 * 1024 * 1024)?);
+    let supervisor = Supervisor::new(event_log.clone(), RestartPolicy::default());
 
     // crash-loop detection for Zero-Trust profile
     let crash_tracker = CrashTracker::new(data.join("crash-tracker.json"));
@@ -119,6 +181,47 @@ async fn run_command(data_dir_override: Option<PathBuf>) -> Result<()> {
         safe_mode.enter(SafeModeReason::Unknown);
     }
 
+    // ── Attest the state files against the last clean shutdown ─────────
+    let baseline_path = data.join("baseline.json");
+    let state_tampered = match attestation::check_at_start(
+        &vault.payload.device_id,
+        &vault_path,
+        &baseline_path,
+        matches!(
+            vault.payload.security_profile,
+            guard_core::vault::SecurityProfile::ZeroTrust
+        ),
+    ) {
+        Attestation::Verified { recorded_at } => {
+            info!(%recorded_at, "vault and baseline match the last clean shutdown");
+            false
+        }
+        Attestation::Unavailable { reason } => {
+            warn!(%reason, "state files could not be attested");
+            event_log.append(
+                "VAULT_ATTESTATION_UNAVAILABLE",
+                EventSeverity::Warn,
+                serde_json::json!({ "reason": reason }),
+            )?;
+            false
+        }
+        Attestation::Mismatch { files, recorded_at } => {
+            error!(?files, "state files changed while the service was stopped");
+            event_log.append(
+                "VAULT_TAMPER_SUSPECTED",
+                EventSeverity::Critical,
+                serde_json::json!({ "files": files, "recorded_at": recorded_at }),
+            )?;
+            safe_mode.enter(SafeModeReason::VaultTamperSuspected);
+            event_log.append(
+                "SAFE_MODE_ENTERED",
+                EventSeverity::Critical,
+                serde_json::json!({"reason": "VAULT_TAMPER_SUSPECTED", "files": files}),
+            )?;
+            true
+        }
+    };
+
     let ipc_secret = vault.ipc_shared_secret()?;
     store_ipc_secret(&vault.payload.device_id, &ipc_secret)?;
     let socket_path = ipc_socket_path()?;
@@ -126,18 +229,69 @@ async fn run_command(data_dir_override: Option<PathBuf>) -> Result<()> {
     let initial_connected = !matches!(vault.payload.mode, guard_core::vault::Mode::Connected);
 
     let engine = Arc::new(Engine::load_from_vault(&vault)?);
+    if state_tampered {
+        engine.enter_safe_mode();
+    }
+
+    // Copy-on-write snapshots of protected directories, where supported.
+    let snapshot_settings = engine.settings().snapshots;
+    if snapshot_settings.enabled {
+        match SnapshotManager::new(data.join("snapshots.json"), snapshot_settings.keep) {
+            Ok(manager) => engine.set_snapshot_manager(Some(Arc::new(manager))),
+            Err(e) => warn!(error = %e, "snapshot protection unavailable"),
+        }
+    }
+
+    // Hashing in a confined worker process, if configured.
+    let scan_worker = ScanWorker::from_settings(&engine.settings().scanner_sandbox)?;
+    if let Some(worker) = &scan_worker {
+        let probe = worker
+            .session()
+            .and_then(|session| session.finalize())
+            .and_then(|digest| {
+                (digest == blake3::hash(b"").to_hex().as_str())
+                    .then_some(())
+                    .ok_or_else(|| anyhow!("scan worker returned a wrong digest"))
+            });
+        match probe {
+            Ok(()) => info!(pid = ?worker.pid(), "scan worker started"),
+            Err(e) => {
+                error!(error = %e, "scan worker unavailable; hashing will fail");
+                event_log.append(
+                    "SCAN_SANDBOX_FAILED",
+                    EventSeverity::Error,
+                    serde_json::json!({ "error": format!("{e:#}") }),
+                )?;
+            }
+        }
+    }
 
     // Initialize integrity scanner with protected paths from settings
     let protected_paths = engine.settings().protection.protected_paths.clone()
         .into_iter().map(PathBuf::from).collect::<Vec<_>>();
     let scanner = if !protected_paths.is_empty() {
-        Some(IntegrityScanner::new(protected_paths.clone(), vault.payload.device_id.clone()))
+        let settings = engine.settings();
+        let progress_engine = engine.clone();
+        Some(
+            IntegrityScanner::new(protected_paths.clone(), vault.payload.device_id.clone())
+                .with_symlink_policy(settings.protection.symlink_policy)
+                .with_hashing(&settings.hashing)
+                .with_attributes(&settings.attributes)
+                .with_sandbox(scan_worker.clone())
+                .with_progress(Arc::new(move |p: &HashProgress| {
+                    debug!(
+                        path = %p.path.display(),
+                        hashed = p.bytes_hashed,
+                        total = p.total_bytes,
+                        "hashing large file"
+                    );
+                    progress_engine.publish_hash_progress(p);
+                })),
+        )
     } else {
         None
     };
 
-    let baseline_path = data.join("baseline.json");
-
     // ── Initialize Backup Store ─────────────────────────────────────────
     let backups_root = data.join("backups");
     let mut backup_store = BackupStore::load_or_create(
@@ -149,25 +303,81 @@ async fn run_command(data_dir_override: Option<PathBuf>) -> Result<()> {
     // ── Initialize Enforcement Engine ───────────────────────────────────
     let quarantine_root = data.join("quarantine");
     let quarantine = QuarantineZone::new(quarantine_root)?;
-    let restore_engine = Arc::new(RestoreEngine::new(quarantine));
+    let restore_engine = Arc::new(
+        RestoreEngine::new(quarantine)
+            .with_pending_queue(PendingRestores::load(data.join("pending_restores.json"))?),
+    );
+    restore_engine.configure(&engine.settings().restore);
+    restore_engine.set_privileged_helper(PrivilegedHelper::from_settings(
+        &engine.settings().privileged_helper,
+        &signing_key_clone,
+        event_log.clone(),
+    ));
+    let write_freeze = Arc::new(WriteFreeze::load(data.join("write_freeze.json"))?);
+    if write_freeze.is_active() {
+        warn!(
+            directories = write_freeze.frozen_dirs().len(),
+            "protected directories are still write-frozen from a suspected ransomware burst"
+        );
+    }
 
     // ── Global shutdown signal ──────────────────────────────────────────
+    let mut stop_signal = ShutdownSignal::listen()?;
     let (shutdown_tx, shutdown_rx) = watch::channel(false);
 
+    // ── Discard a rebaseline interrupted before its commit ──────────────
+    let pending_baseline = IntegrityScanner::pending_baseline_path(&baseline_path);
+    if pending_baseline.exists() {
+        std::fs::remove_file(&pending_baseline)?;
+        warn!(path = %pending_baseline.display(), "discarded uncommitted rebaseline");
+        event_log.append(
+            "BASELINE_PENDING_DISCARDED",
+            EventSeverity::Warn,
+            serde_json::json!({ "path": pending_baseline.display().to_string() }),
+        )?;
+    }
+
     // ── Load or create initial baseline + populate backup store ─────────
     let initial_baseline: Option<Baseline> = if let Some(ref scanner) = scanner {
         if baseline_path.exists() {
-            Some(IntegrityScanner::load_baseline(&baseline_path)?)
+            let mut baseline = IntegrityScanner::load_baseline(&baseline_path)?;
+            match scanner.migrate_baseline(&mut baseline, &signing_key_clone) {
+                Ok(Some(migration)) => {
+                    IntegrityScanner::save_baseline(&baseline, &baseline_path)?;
+                    info!(
+                        from = migration.from_version,
+                        to = migration.to_version,
+                        adopted = migration.adopted,
+                        "baseline format migrated"
+                    );
+                    event_log.append(
+                        "BASELINE_MIGRATED",
+                        EventSeverity::Info,
+                        serde_json::to_value(&migration)?,
+                    )?;
+                }
+                Ok(None) => {}
+                Err(e) => warn!(error = %e, "baseline left in its old format"),
+            }
+            Some(baseline)
+        } else if baseline_operator_key(&vault)?.is_some() {
+            // Under dual control nothing is enforced until an operator
+            // countersigns the first baseline.
+            if !IntegrityScanner::countersign_baseline_path(&baseline_path).exists() {
+                let baseline = scanner.generate_baseline(&signing_key_clone)?;
+                hold_for_countersign(&baseline, &baseline_path, &event_log, "startup")?;
+            }
+            None
         } else {
             let baseline = scanner.generate_baseline(&signing_key_clone)?;
             IntegrityScanner::save_baseline(&baseline, &baseline_path)?;
-            event_log.append(
-                "BASELINE_CREATED",
+            event_log.append_event(
                 EventSeverity::Info,
-                serde_json::json!({"files": baseline.entries.len()}),
+                &GuardEvent::BaselineCreated { files: baseline.entries.len() },
             )?;
-            // Populate backup store from initial baseline
-            for (_key, entry) in &baseline.entries {
+            // Populate backup store from initial baseline. Links are
+            // restored from their baseline entry and need no blob.
+            for entry in baseline.entries.values().filter(|e| e.link_target.is_none()) {
                 let p = PathBuf::from(&entry.path);
                 if p.exists() {
                     if let Err(e) = backup_store.ensure_from_disk(
@@ -180,6 +390,7 @@ async fn run_command(data_dir_override: Option<PathBuf>) -> Result<()> {
                     }
                 }
             }
+            engine.snapshot_protected_paths(&event_log);
             Some(baseline)
         }
     } else {
@@ -192,58 +403,75 @@ async fn run_command(data_dir_override: Option<PathBuf>) -> Result<()> {
         RestoreEngine::cleanup_staging(&protected_paths);
     }
 
-    let mut _file_watcher = None; // Must keep alive for the duration
+    // Retry restores a previous run deferred because the target was locked.
+    if let Some(ref baseline) = initial_baseline {
+        for (path, outcome) in restore_engine.apply_pending(baseline, &backup_store) {
+            engine.log_restore(&path, &outcome, &baseline.tags_for(&path), &event_log);
+        }
+    }
+
+    // Current signed baseline, shared by enforcement tasks and IPC handlers so
+    // rebaselines and tag edits take effect without a restart.
+    let live_baseline = Arc::new(parking_lot::Mutex::new(initial_baseline.clone()));
+
+    let mut file_watcher = None; // Must keep alive for the duration
     let mut watcher_pipeline_handle = None;
-    let mut tamper_rx_opt = None;
+    let mut tamper_tx_opt = None;
+    let mut burst_rx = None;
 
     if !protected_paths.is_empty() && scanner.is_some() {
-        // Check inotify watch limit on Linux before starting watcher.
-        #[cfg(target_os = "linux")]
-        {
-            if let Ok(limit_str) = std::fs::read_to_string("/proc/sys/fs/inotify/max_user_watches") {
-                if let Ok(limit) = limit_str.trim().parse::<u64>() {
-                    // Rough estimate: count files in protected paths
-                    let mut file_count: u64 = 0;
-                    for p in &protected_paths {
-                        if p.is_dir() {
-                            file_count += walkdir::WalkDir::new(p).into_iter().count() as u64;
-                        } else {
-                            file_count += 1;
-                        }
-                    }
-                    if file_count > limit / 2 {
-                        warn!(
-                            file_count,
-                            inotify_limit = limit,
-                            "protected files ({file_count}) exceed 50% of inotify watch limit ({limit}). \
-                            Consider: sysctl fs.inotify.max_user_watches={}",
-                            file_count * 2
-                        );
-                    }
-                }
-            }
-        }
         if let Ok((mut fw, raw_rx)) = FileWatcher::new() {
-            if let Err(e) = fw.watch_paths(&protected_paths) {
-                warn!(error = %e, "failed to start file watcher");
+            // Trees that would take the watcher past its descriptor budget
+            // are polled instead.
+            let budget = resources::watch_budget(&engine.settings());
+            match fw.watch_paths(&protected_paths, budget) {
+                Ok(polled) if !polled.is_empty() => {
+                    event_log.append(
+                        "WATCHER_DEGRADED",
+                        EventSeverity::Warn,
+                        serde_json::json!({
+                            "paths": polled.iter().map(|p| p.display().to_string()).collect::<Vec<_>>(),
+                            "watches": fw.watch_count(),
+                            "budget": budget,
+                        }),
+                    )?;
+                }
+                Ok(_) => {}
+                Err(e) => warn!(error = %e, "failed to start file watcher"),
             }
 
-            let baseline_for_pipeline = initial_baseline.clone();
-            let baseline_arc = Arc::new(parking_lot::Mutex::new(baseline_for_pipeline));
             let baseline_fn = {
-                let b = baseline_arc.clone();
+                let b = live_baseline.clone();
                 Arc::new(move || b.lock().clone()) as Arc<dyn Fn() -> Option<Baseline> + Send + Sync>
             };
 
-            let (handle, tamper_tx) = spawn_watcher_pipeline(
-                raw_rx,
-                baseline_fn,
-                restore_engine.restoring.clone(),
+            burst_rx = Some(raw_rx.resubscribe());
+
+            // The tamper channel is owned here so it survives pipeline restarts.
+            let (tamper_tx, _) = broadcast::channel::<TamperEvent>(512);
+            let restoring = restore_engine.restoring.clone();
+            let pipeline_shutdown = shutdown_rx.clone();
+            let pipeline_tx = tamper_tx.clone();
+            let pipeline_sandbox = scan_worker.clone();
+            let handle = supervisor.supervise(
+                "watcher_pipeline",
+                Some(Duration::from_secs(30)),
+                move |heartbeat| {
+                    spawn_watcher_pipeline(
+                        raw_rx.resubscribe(),
+                        baseline_fn.clone(),
+                        restoring.clone(),
+                        pipeline_tx.clone(),
+                        pipeline_sandbox.clone(),
+                        heartbeat,
+                        pipeline_shutdown.clone(),
+                    )
+                },
                 shutdown_rx.clone(),
             );
             watcher_pipeline_handle = Some(handle);
-            tamper_rx_opt = Some(tamper_tx.subscribe());
-            _file_watcher = Some(fw);
+            tamper_tx_opt = Some(tamper_tx);
+            file_watcher = Some(Arc::new(parking_lot::Mutex::new(fw)));
         }
     }
 
@@ -251,6 +479,7 @@ async fn run_command(data_dir_override: Option<PathBuf>) -> Result<()> {
     // Wrap BackupStore in Arc<Mutex<>> so it can be shared with async tasks.
     let backup_store = Arc::new(parking_lot::Mutex::new(backup_store));
     let mut audit_loop_handle_opt: Option<AuditLoopHandle> = None;
+    let mut audit_task = None;
 
     if let Some(ref scanner) = scanner {
         let scanner_arc = Arc::new(scanner.clone());
@@ -261,10 +490,11 @@ async fn run_command(data_dir_override: Option<PathBuf>) -> Result<()> {
         let engine_for_audit = engine.clone();
         let restore_for_audit = restore_engine.clone();
         let event_log_for_audit = event_log.clone();
-        let bl_for_audit = initial_baseline.clone();
+        let bl_for_audit = live_baseline.clone();
         let backup_for_audit = backup_store.clone();
         let on_result = move |result: crate::integrity::scanner::ScanResult| {
-            if let Some(ref baseline) = bl_for_audit {
+            let baseline = bl_for_audit.lock().clone();
+            if let Some(ref baseline) = baseline {
                 let store_guard = backup_for_audit.lock();
                 engine_for_audit.handle_scan_result(
                     &result,
@@ -276,12 +506,24 @@ async fn run_command(data_dir_override: Option<PathBuf>) -> Result<()> {
             }
         };
 
-        let (_audit_handle, audit_ctl) = spawn_audit_loop(
-            scanner_arc,
-            Duration::from_secs(300), // 5 minutes
-            baseline_loader,
-            on_result,
-        );
+        let audit_interval = Duration::from_secs(300); // 5 minutes
+        let audit_ctl = AuditLoopHandle::new();
+        let ctl = audit_ctl.clone();
+        audit_task = Some(supervisor.supervise(
+            "audit_loop",
+            Some(audit_interval * 3),
+            move |heartbeat| {
+                spawn_audit_loop(
+                    scanner_arc.clone(),
+                    audit_interval,
+                    baseline_loader.clone(),
+                    on_result.clone(),
+                    &ctl,
+                    heartbeat,
+                )
+            },
+            shutdown_rx.clone(),
+        ));
         audit_loop_handle_opt = Some(audit_ctl);
     }
 
@@ -299,36 +541,78 @@ async fn run_command(data_dir_override: Option<PathBuf>) -> Result<()> {
     );
 
     // ── Tamper event consumer task ──────────────────────────────────────
-    let tamper_consumer = if let Some(mut tamper_rx) = tamper_rx_opt {
+    let tamper_tx_for_status = tamper_tx_opt.clone();
+    let tamper_consumer = if let Some(tamper_tx) = tamper_tx_opt {
         let engine_c = engine.clone();
         let restore_c = restore_engine.clone();
         let event_log_c = event_log.clone();
-        let bl = Arc::new(parking_lot::Mutex::new(initial_baseline.clone()));
+        let bl = live_baseline.clone();
         let backup_c = backup_store.clone();
-        let handle = tokio::spawn(async move {
-            loop {
-                match tamper_rx.recv().await {
-                    Ok(event) => {
-                        // Route through the orchestrator for mode-aware enforcement.
-                        let baseline_guard = bl.lock();
-                        if let Some(ref baseline) = *baseline_guard {
-                            let store_guard = backup_c.lock();
-                            engine_c.handle_tamper_event(
-                                &event,
-                                &restore_c,
-                                &store_guard,
-                                baseline,
-                                &event_log_c,
-                            );
+        let sandbox_c = scan_worker.clone();
+        let handle = supervisor.supervise(
+            "tamper_consumer",
+            None,
+            move |_heartbeat| {
+                let mut tamper_rx = tamper_tx.subscribe();
+                let engine_c = engine_c.clone();
+                let restore_c = restore_c.clone();
+                let event_log_c = event_log_c.clone();
+                let bl = bl.clone();
+                let backup_c = backup_c.clone();
+                let sandbox_c = sandbox_c.clone();
+                tokio::spawn(async move {
+                    let mut recheck = tokio::time::interval(Duration::from_secs(1));
+                    loop {
+                        tokio::select! {
+                            received = tamper_rx.recv() => match received {
+                                Ok(event) => {
+                                    // Route through the orchestrator for mode-aware enforcement.
+                                    let baseline_guard = bl.lock();
+                                    if let Some(ref baseline) = *baseline_guard {
+                                        let store_guard = backup_c.lock();
+                                        engine_c.handle_tamper_event(
+                                            &event,
+                                            &restore_c,
+                                            &store_guard,
+                                            baseline,
+                                            &event_log_c,
+                                        );
+                                    }
+                                }
+                                Err(broadcast::error::RecvError::Lagged(n)) => {
+                                    warn!(missed = n, "tamper consumer lagged");
+                                }
+                                Err(broadcast::error::RecvError::Closed) => break,
+                            },
+                            _ = recheck.tick() => {
+                                // Paths whose flap suppression period ended
+                                // may have been left changed, and locked
+                                // restores may be due another attempt.
+                                engine_c.flush_flap_summaries(&event_log_c);
+                                let baseline_guard = bl.lock();
+                                if let Some(ref baseline) = *baseline_guard {
+                                    let store_guard = backup_c.lock();
+                                    engine_c.recheck_flapped_paths(
+                                        &restore_c,
+                                        &store_guard,
+                                        baseline,
+                                        &event_log_c,
+                                        sandbox_c.as_deref(),
+                                    );
+                                    engine_c.retry_locked_restores(
+                                        &restore_c,
+                                        &store_guard,
+                                        baseline,
+                                        &event_log_c,
+                                    );
+                                }
+                            }
                         }
                     }
-                    Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
-                        warn!(missed = n, "tamper consumer lagged");
-                    }
-                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
-                }
-            }
-        });
+                })
+            },
+            shutdown_rx.clone(),
+        );
         Some(handle)
     } else {
         None
@@ -343,7 +627,9 @@ async fn run_command(data_dir_override: Option<PathBuf>) -> Result<()> {
         password: Zeroizing::new(password),
         connected: initial_connected,
         last_heartbeat: None,
+        offline: Default::default(),
         last_remote_command: None,
+        command_receipts: Arc::new(ReceiptLog::load(data.join("command_receipts.json"))?),
         update_available: false,
         _crash_tracker: crash_tracker,
         scanner,
@@ -353,8 +639,129 @@ async fn run_command(data_dir_override: Option<PathBuf>) -> Result<()> {
         backup_store: backup_store.clone(),
         restore_engine: restore_engine.clone(),
         audit_loop_handle: audit_loop_handle_opt,
+        supervisor: supervisor.clone(),
+        live_baseline: live_baseline.clone(),
+        write_freeze: write_freeze.clone(),
+        started_at,
+        tamper_tx: tamper_tx_for_status,
+        file_watcher,
+        resource_usage: None,
     }));
 
+    // A subsystem that keeps dying means enforcement can't be trusted.
+    {
+        let state = state.clone();
+        supervisor.on_give_up(move |subsystem| {
+            let mut st = state.lock();
+            st.safe_mode.enter(SafeModeReason::SubsystemFailure);
+            st.engine.enter_safe_mode();
+            let _ = st.event_log.append(
+                "SAFE_MODE_ENTERED",
+                EventSeverity::Critical,
+                serde_json::json!({"reason": "SUBSYSTEM_FAILURE", "subsystem": subsystem}),
+            );
+        });
+    }
+
+    // Enforcement rules can put the engine into safe mode on their own;
+    // mirror that in the service state so status and IPC report it.
+    {
+        let state = state.clone();
+        let mut engine_rx = engine.subscribe();
+        tokio::spawn(async move {
+            loop {
+                match engine_rx.recv().await {
+                    Ok(EngineEvent::ModeChanged(EngineMode::SafeMode)) => {
+                        let mut st = state.lock();
+                        if !st.safe_mode.active {
+                            st.safe_mode.enter(SafeModeReason::EnforcementAction);
+                        }
+                    }
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    // ── Ransomware burst detector ───────────────────────────────────────
+    let ransomware = engine.settings().ransomware;
+    let burst_task = match burst_rx {
+        Some(raw_rx) if ransomware.enabled => {
+            let restoring = restore_engine.restoring.clone();
+            let on_burst = {
+                let state = state.clone();
+                let safe_mode_on_detect = ransomware.safe_mode_on_detect;
+                let freeze_writes = ransomware.freeze_writes;
+                Arc::new(move |report: BurstReport| {
+                    // Freezes, scans and restores: keep it off the detector's
+                    // task so its heartbeat and the runtime keep going.
+                    let state = state.clone();
+                    tokio::task::spawn_blocking(move || {
+                        respond_to_burst(&state, &report, freeze_writes, safe_mode_on_detect)
+                    });
+                })
+            };
+            let burst_shutdown = shutdown_rx.clone();
+            Some(supervisor.supervise(
+                "burst_detector",
+                Some(Duration::from_secs(30)),
+                move |heartbeat| {
+                    let on_burst = on_burst.clone();
+                    spawn_burst_detector(
+                        raw_rx.resubscribe(),
+                        ransomware.clone(),
+                        restoring.clone(),
+                        move |report| on_burst(report),
+                        heartbeat,
+                        burst_shutdown.clone(),
+                    )
+                },
+                shutdown_rx.clone(),
+            ))
+        }
+        _ => None,
+    };
+
+    // ── Resource budgets ────────────────────────────────────────────────
+    let resource_task = resources::spawn_resource_monitor(state.clone(), shutdown_rx.clone());
+
+    // ── Backup store GC ─────────────────────────────────────────────────
+    let backup_gc_task = {
+        let state = state.clone();
+        let mut shutdown = shutdown_rx.clone();
+        tokio::spawn(async move {
+            loop {
+                let hours = state.lock().engine.settings().backup_store.gc_interval_hours;
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_secs(hours as u64 * 3600)) => {
+                        let state = state.clone();
+                        let _ = tokio::task::spawn_blocking(move || collect_backup_garbage(&state)).await;
+                    }
+                    _ = shutdown.changed() => {
+                        if *shutdown.borrow() { return; }
+                    }
+                }
+            }
+        })
+    };
+
+    // ── Temporary exclusion expiry ──────────────────────────────────────
+    let exclusion_task = {
+        let state = state.clone();
+        let mut shutdown = shutdown_rx.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_secs(15)) => expire_exclusions(&state),
+                    _ = shutdown.changed() => {
+                        if *shutdown.borrow() { return; }
+                    }
+                }
+            }
+        })
+    };
+
     let updater_path = {
         let mut p = install_dir()?;
         #[cfg(windows)]
@@ -367,10 +774,21 @@ async fn run_command(data_dir_override: Option<PathBuf>) -> Result<()> {
         }
         p
     };
+    let update_task = updater::spawn_update_task(
+        state.clone(),
+        updater_path.clone(),
+        install_dir()?,
+        shutdown_rx.clone(),
+    );
 
     let handler = Arc::new(ServiceHandler {
         state: state.clone(),
         updater_path,
+        rejections: RateLimiter::new(RateLimits {
+            requests_per_minute: REMOTE_REJECTIONS_PER_MINUTE,
+            scans_per_minute: 0,
+        }),
+        rejections_dropped: AtomicU64::new(0),
     });
     let server = Arc::new(IpcServer::new(ipc_secret, socket_path));
     #[cfg(unix)]
@@ -392,28 +810,53 @@ async fn run_command(data_dir_override: Option<PathBuf>) -> Result<()> {
         tokio::spawn(async move { server.start(handler).await })
     };
 
+    // Remote administration listener (TLS over TCP), off unless configured.
+    let remote_settings = engine.settings().remote_ipc;
+    let remote_task = if remote_settings.enabled {
+        info!(bind = %remote_settings.bind_addr, "remote IPC listener enabled");
+        event_log.append(
+            "REMOTE_IPC_ENABLED",
+            EventSeverity::Warn,
+            serde_json::json!({
+                "bind_addr": remote_settings.bind_addr,
+                "client_ca": remote_settings.client_ca_path.is_some(),
+            }),
+        )?;
+        let server = server.clone();
+        let handler = handler.clone();
+        Some(tokio::spawn(async move {
+            if let Err(e) = server.start_remote(remote_settings, handler).await {
+                error!("remote IPC listener stopped: {e}");
+            }
+        }))
+    } else {
+        None
+    };
+
     // Log service start
-    event_log.append(
-        "SERVICE_START",
-        EventSeverity::Info,
-        serde_json::json!({}),
-    )?;
+    event_log.append_event(EventSeverity::Info, &GuardEvent::ServiceStart {})?;
+
+    if let Err(e) = register_running(&data) {
+        warn!(error = %e, "failed to record instance in registry");
+    }
 
     info!("service started – all subsystems online");
-    signal::ctrl_c().await?;
-    info!("service stopping");
+    let stopped_by = stop_signal.recv().await;
+    info!(signal = stopped_by, "service stopping");
+    if let Err(e) = register_stopped() {
+        warn!(error = %e, "failed to mark instance stopped in registry");
+    }
 
     // Signal shutdown to all tasks
     let _ = shutdown_tx.send(true);
 
     // Log service stop
-    let _ = event_log.append(
-        "SERVICE_STOP",
-        EventSeverity::Info,
-        serde_json::json!({}),
-    );
+    let _ = event_log.append_event(EventSeverity::Info, &GuardEvent::ServiceStop {});
 
     server_task.abort();
+    if let Some(task) = remote_task {
+        task.abort();
+    }
     maint_handle.abort();
     anchor_handle.abort();
     if let Some(task) = connected_task {
@@ -425,26 +868,255 @@ async fn run_command(data_dir_override: Option<PathBuf>) -> Result<()> {
     if let Some(handle) = tamper_consumer {
         handle.abort();
     }
+    if let Some(handle) = audit_task {
+        handle.abort();
+    }
+    if let Some(handle) = burst_task {
+        handle.abort();
+    }
+    exclusion_task.abort();
+    backup_gc_task.abort();
+    resource_task.abort();
+    update_task.abort();
     #[cfg(unix)]
     status_task.abort();
+
+    // Everything that writes the vault or baseline has stopped.
+    let st = state.lock();
+    if !st.safe_mode.attestation_mismatch {
+        if let Err(e) = attestation::record_at_shutdown(
+            &st.vault.payload.device_id,
+            &st.vault_path,
+            &st.baseline_path,
+        ) {
+            warn!(error = %e, "failed to record state attestation");
+        }
+    }
     Ok(()
     )
 }
 
+/// Make the current contents of `paths` their baseline, e.g. once a
+/// temporary exclusion ends. Returns the number of entries refreshed.
+fn rebaseline_paths(st: &mut ServiceState, paths: &[PathBuf]) -> Result<usize> {
+    let (Some(mut baseline), Some(scanner)) = (baseline_to_edit(st)?, st.scanner.as_ref()) else {
+        return Ok(0);
+    };
+    let refreshed = scanner.refresh_paths(&mut baseline, paths, &st.signing_key);
+    if !adopt_baseline(st, &baseline, "exclusion")? {
+        return Ok(refreshed);
+    }
+    let mut store = st.backup_store.lock();
+    for entry in baseline
+        .entries
+        .values()
+        .filter(|e| e.link_target.is_none())
+        .filter(|e| paths.iter().any(|p| Path::new(&e.path).starts_with(p)))
+    {
+        if let Err(e) =
+            store.ensure_from_disk(Path::new(&entry.path), &entry.hash, entry.permissions, None)
+        {
+            warn!(path = %entry.path, error = %e, "backup update failed after exclusion");
+        }
+    }
+    st.event_log.append_event(
+        EventSeverity::Info,
+        &GuardEvent::BaselineUpdated {
+            entries: refreshed,
+            drained_events: None,
+            source: Some("exclusion".into()),
+            paths: paths.iter().map(|p| p.display().to_string()).collect(),
+        },
+    )?;
+    Ok(refreshed)
+}
+
+/// The baseline a change builds on: under dual control the one held for a
+/// countersignature if there is one, so changes accumulate there.
+fn baseline_to_edit(st: &ServiceState) -> Result<Option<Baseline>> {
+    let held = IntegrityScanner::countersign_baseline_path(&st.baseline_path);
+    if baseline_operator_key(&st.vault)?.is_some() && held.exists() {
+        return IntegrityScanner::load_baseline(&held).map(Some);
+    }
+    Ok(st.live_baseline.lock().clone())
+}
+
+/// Put `baseline` in force. Under dual control one without a valid operator
+/// countersignature is held for one instead and the current baseline stays
+/// in force. Returns whether `baseline` took effect.
+fn adopt_baseline(st: &ServiceState, baseline: &Baseline, source: &str) -> Result<bool> {
+    if let Some(operator_key) = baseline_operator_key(&st.vault)? {
+        if !IntegrityScanner::verify_operator_signature(baseline, &operator_key) {
+            hold_for_countersign(baseline, &st.baseline_path, &st.event_log, source)?;
+            return Ok(false);
+        }
+    }
+    IntegrityScanner::save_baseline(baseline, &st.baseline_path)?;
+    *st.live_baseline.lock() = Some(baseline.clone());
+    Ok(true)
+}
+
+/// Re-baseline and drop exclusions whose time is up.
+fn expire_exclusions(state: &Mutex<ServiceState>) {
+    let mut guard = state.lock();
+    let st = &mut *guard;
+    let expired = match st.engine.take_expired_exclusions(&mut st.vault, &st.event_log) {
+        Ok(expired) => expired,
+        Err(e) => {
+            warn!(error = %e, "failed to expire exclusions");
+            return;
+        }
+    };
+    if expired.is_empty() {
+        return;
+    }
+    let paths: Vec<PathBuf> = expired.iter().map(|e| PathBuf::from(&e.path)).collect();
+    if let Err(e) = rebaseline_paths(st, &paths) {
+        warn!(error = %e, "failed to re-baseline expired exclusions");
+    }
+}
+
+/// Backup store GC: entries for paths the live baseline no longer covers are
+/// dropped (all are kept while there is no baseline), damaged blobs are
+/// re-fetched where the source is intact, and orphaned blobs are deleted.
+fn collect_backup_garbage(state: &Mutex<ServiceState>) {
+    let (store, live_baseline, event_log) = {
+        let st = state.lock();
+        (st.backup_store.clone(), st.live_baseline.clone(), st.event_log.clone())
+    };
+    let baselined: Option<HashSet<String>> = live_baseline
+        .lock()
+        .as_ref()
+        .map(|b| b.entries.values().map(|e| e.path.clone()).collect());
+    let report = store
+        .lock()
+        .run_gc(|path| baselined.as_ref().is_none_or(|paths| paths.contains(path)));
+    let report = match report {
+        Ok(report) => report,
+        Err(e) => {
+            warn!(error = %e, "backup store GC failed");
+            return;
+        }
+    };
+    for path in &report.blobs_repaired {
+        let _ = event_log.append(
+            "BACKUP_BLOB_REPAIRED",
+            EventSeverity::Warn,
+            serde_json::json!({"path": path}),
+        );
+    }
+    for path in &report.blobs_unrecoverable {
+        let _ = event_log.append(
+            "BACKUP_BLOB_UNRECOVERABLE",
+            EventSeverity::Error,
+            serde_json::json!({"path": path}),
+        );
+    }
+    let _ = event_log.append(
+        "BACKUP_GC_COMPLETED",
+        EventSeverity::Info,
+        serde_json::json!({
+            "entries_pruned": report.entries_pruned,
+            "blobs_removed": report.blobs_removed,
+            "bytes_freed": report.bytes_freed,
+            "blobs_repaired": report.blobs_repaired.len(),
+            "blobs_unrecoverable": report.blobs_unrecoverable.len(),
+        }),
+    );
+}
+
+/// Protective response to a suspected ransomware burst: alert and freeze
+/// writes, then optionally restore what was damaged and enter safe mode. The
+/// state lock is only held to collect what the response needs and to record
+/// safe mode, not across the freeze, scan and restores.
+fn respond_to_burst(
+    state: &Mutex<ServiceState>,
+    report: &BurstReport,
+    freeze_writes: bool,
+    safe_mode_on_detect: bool,
+) {
+    let (engine, event_log, write_freeze, scanner, live_baseline, backup_store, restore_engine) = {
+        let st = state.lock();
+        (
+            st.engine.clone(),
+            st.event_log.clone(),
+            st.write_freeze.clone(),
+            st.scanner.clone(),
+            st.live_baseline.clone(),
+            st.backup_store.clone(),
+            st.restore_engine.clone(),
+        )
+    };
+    let freeze = freeze_writes.then_some(&*write_freeze);
+    if !engine.handle_ransomware_suspected(report, freeze, &event_log) {
+        return;
+    }
+    if !safe_mode_on_detect {
+        return;
+    }
+    let baseline = live_baseline.lock().clone();
+    if let (Some(scanner), Some(baseline)) = (scanner, baseline) {
+        let result = scanner.scan_against_baseline(&baseline);
+        let store = backup_store.lock();
+        engine.handle_scan_result(&result, &restore_engine, &store, &baseline, &event_log);
+    }
+    state.lock().safe_mode.enter(SafeModeReason::RansomwareSuspected);
+    engine.enter_safe_mode();
+    let _ = event_log.append(
+        "SAFE_MODE_ENTERED",
+        EventSeverity::Critical,
+        serde_json::json!({"reason": "RANSOMWARE_SUSPECTED"}),
+    );
+}
+
+/// `REMOTE_IPC_REJECTED` entries written per minute. Anyone who can reach
+/// the listener can cause rejections; the rest are only counted, and the
+/// count is reported with the next entry written.
+const REMOTE_REJECTIONS_PER_MINUTE: u32 = 10;
+
 struct ServiceHandler {
     state: Arc<Mutex<ServiceState>>,
     updater_path: PathBuf,
+    rejections: RateLimiter,
+    rejections_dropped: AtomicU64,
 }
 
 #[async_trait::async_trait]
 impl IpcHandler for ServiceHandler {
-    async fn handle(&self, req: IpcRequest) -> Result<IpcResponse> {
+    async fn handle(&self, req: IpcRequest, client: &ClientIdentity) -> Result<IpcResponse> {
         match req {
             IpcRequest::GetStatus => {
                 let state = self.state.lock();
                 Ok(IpcResponse::Status {
                     ok: !state.safe_mode.active,
+                    health: Some(status::service_health(&state)),
+                })
+            }
+            IpcRequest::GetPathStats => {
+                let state = self.state.lock();
+                Ok(IpcResponse::PathStatistics {
+                    paths: status::path_stats(&state)?,
+                })
+            }
+            IpcRequest::EstimatePath { path } => {
+                if !Path::new(&path).is_absolute() {
+                    return Err(anyhow!("{path} is not an absolute path"));
+                }
+                let (settings, watches_in_use) = {
+                    let st = self.state.lock();
+                    let watches = st.file_watcher.as_ref().map_or(0, |fw| fw.lock().watch_count());
+                    (st.engine.settings(), watches)
+                };
+                let estimate = tokio::task::spawn_blocking(move || {
+                    estimate_path(
+                        Path::new(&path),
+                        watches_in_use,
+                        resources::watch_budget(&settings),
+                        &settings.protection.protected_paths,
+                    )
                 })
+                .await??;
+                Ok(IpcResponse::PathEstimate { estimate })
             }
             IpcRequest::GetSettings => {
                 let state = self.state.lock();
@@ -452,14 +1124,29 @@ impl IpcHandler for ServiceHandler {
                     settings: state.engine.settings(),
                 })
             }
-            IpcRequest::UpdateSettings { settings } => {
+            IpcRequest::UpdateSettings { settings, note } => {
                 let mut state = self.state.lock();
-                let st = &mut *state;
-                st.engine
-                    .update_settings(&mut st.vault, settings)
-                    .map_err(|e| anyhow!(e.to_string()))?;
+                update_settings_audited(&mut state, settings, client, note.as_deref(), None)?;
                 Ok(IpcResponse::SettingsUpdated)
             }
+            IpcRequest::GetSettingsHistory { limit } => {
+                let state = self.state.lock();
+                let mut versions = load_settings_history(&state.vault)?.versions;
+                versions.reverse();
+                versions.truncate(limit.unwrap_or(usize::MAX));
+                Ok(IpcResponse::SettingsHistory { versions })
+            }
+            IpcRequest::RollbackSettings { version, note } => {
+                let mut state = self.state.lock();
+                let st = &mut *state;
+                let settings = st.engine.settings_version(&st.vault, version)?;
+                let recorded =
+                    update_settings_audited(st, settings, client, note.as_deref(), Some(version))?;
+                Ok(IpcResponse::SettingsRolledBack {
+                    version: recorded.as_ref().map(|v| v.version),
+                    changes: recorded.map(|v| v.changes).unwrap_or_default(),
+                })
+            }
             IpcRequest::CheckUpdate { manifest_path } => {
                 let manifest = load_manifest(&manifest_path)?;
                 {
@@ -538,12 +1225,25 @@ impl IpcHandler for ServiceHandler {
                 });
                 let entries = state.event_log.read_recent(since_dt, limit)
                     .unwrap_or_default();
-                let events: Vec<serde_json::Value> = entries
-                    .into_iter()
-                    .map(|e| serde_json::to_value(e).unwrap_or_default())
-                    .collect();
+                let events = with_notes(&state.event_log, entries);
                 Ok(IpcResponse::Events { events })
             }
+            IpcRequest::SearchEvents { query } => {
+                let event_log = self.state.lock().event_log.clone();
+                let page = event_log.search(&query)?;
+                let events = with_notes(&event_log, page.events);
+                Ok(IpcResponse::EventPage {
+                    events,
+                    next_cursor: page.next_cursor,
+                })
+            }
+            IpcRequest::AnnotateEvent { seq, note } => {
+                let event_log = self.state.lock().event_log.clone();
+                let note = event_log.annotate(seq, &note, &client.label())?;
+                Ok(IpcResponse::EventAnnotated {
+                    note: serde_json::to_value(note)?,
+                })
+            }
             IpcRequest::TriggerScan => {
                 let state = self.state.lock();
                 if let Some(ref scanner) = state.scanner {
@@ -551,24 +1251,30 @@ impl IpcHandler for ServiceHandler {
                         IntegrityScanner::load_baseline(&state.baseline_path)?
                     } else {
                         let baseline = scanner.generate_baseline(&state.signing_key)?;
-                        IntegrityScanner::save_baseline(&baseline, &state.baseline_path)?;
-                        state.event_log.append(
-                            "BASELINE_CREATED",
+                        if !adopt_baseline(&state, &baseline, "scan")? {
+                            return Err(anyhow!("new baseline awaits an operator countersignature"));
+                        }
+                        state.event_log.append_event(
                             EventSeverity::Info,
-                            serde_json::json!({"files": baseline.entries.len()}),
+                            &GuardEvent::BaselineCreated { files: baseline.entries.len() },
                         )?;
+                        state.engine.snapshot_protected_paths(&state.event_log);
                         baseline
                     };
                     let result = scanner.scan_against_baseline(&baseline);
+                    state.engine.record_scan(&result);
                     if !result.valid {
-                        state.event_log.append(
-                            "INTEGRITY_VIOLATION",
+                        state.event_log.append_event(
                             EventSeverity::Critical,
-                            serde_json::json!({
-                                "modified": result.modified.len(),
-                                "removed": result.removed.len(),
-                                "added": result.added.len()
-                            }),
+                            &GuardEvent::IntegrityViolation {
+                                source: None,
+                                modified: result.modified.len(),
+                                removed: result.removed.len(),
+                                added: result.added.len(),
+                                attributes: result.attribute_paths().len(),
+                                tags: result.tag_counts(),
+                                roots: state.engine.violations_by_root(&result),
+                            },
                         )?;
                     }
                     let result_json = serde_json::to_value(&result)
@@ -596,11 +1302,12 @@ impl IpcHandler for ServiceHandler {
                     .enter_maintenance(reason, timeout_secs, &state.event_log)?;
                 Ok(IpcResponse::MaintenanceEntered)
             }
-            IpcRequest::MaintenanceExit { rebaseline } => {
+            IpcRequest::MaintenanceExit { rebaseline, report } => {
                 let mut state = self.state.lock();
                 let st = &mut *state;
+                let operator_key = baseline_operator_key(&st.vault)?;
                 let mut store_guard = st.backup_store.lock();
-                let new_bl = st.engine.exit_maintenance(
+                let exit = st.engine.exit_maintenance(
                     rebaseline,
                     st.scanner.as_ref(),
                     &st.signing_key,
@@ -608,9 +1315,17 @@ impl IpcHandler for ServiceHandler {
                     &mut *store_guard,
                     &st.event_log,
                     &st.data_dir,
+                    report,
+                    operator_key.as_ref(),
                 )?;
+                let rebaselined = exit.baseline.is_some();
+                if let Some(baseline) = exit.baseline {
+                    *st.live_baseline.lock() = Some(baseline);
+                }
                 Ok(IpcResponse::MaintenanceExited {
-                    rebaselined: new_bl.is_some(),
+                    rebaselined,
+                    changes: exit.journal.len(),
+                    report_path: exit.report_path.map(|p| p.display().to_string()),
                 })
             }
             IpcRequest::SetProtectedPaths { paths } => {
@@ -618,24 +1333,29 @@ impl IpcHandler for ServiceHandler {
                 let st = &mut *state;
                 let mut settings = st.engine.settings();
                 settings.protection.protected_paths = paths;
-                st.engine
-                    .update_settings(&mut st.vault, settings)
-                    .map_err(|e| anyhow!(e.to_string()))?;
+                update_settings_audited(st, settings, client, None, None)?;
                 Ok(IpcResponse::ProtectedPathsUpdated)
             }
             IpcRequest::BaselineCreate => {
                 let mut state = self.state.lock();
                 let st = &mut *state;
                 if let Some(ref scanner) = st.scanner {
-                    let baseline = scanner.generate_baseline(&st.signing_key)?;
-                    IntegrityScanner::save_baseline(&baseline, &st.baseline_path)?;
+                    let previous = baseline_to_edit(st)?;
+                    let baseline =
+                        scanner.generate_baseline_with(&st.signing_key, previous.as_ref())?;
                     let entries = baseline.entries.len();
-                    st.event_log.append(
-                        "BASELINE_CREATED",
-                        EventSeverity::Info,
-                        serde_json::json!({"files": entries}),
-                    )?;
-                    Ok(IpcResponse::BaselineCreated { entries })
+                    let adopted = adopt_baseline(st, &baseline, "create")?;
+                    if adopted {
+                        st.event_log.append_event(
+                            EventSeverity::Info,
+                            &GuardEvent::BaselineCreated { files: entries },
+                        )?;
+                        st.engine.snapshot_protected_paths(&st.event_log);
+                    }
+                    Ok(IpcResponse::BaselineCreated {
+                        entries,
+                        pending_countersign: !adopted,
+                    })
                 } else {
                     Err(anyhow!("no protected paths configured"))
                 }
@@ -650,16 +1370,25 @@ impl IpcHandler for ServiceHandler {
                         let sig_valid =
                             IntegrityScanner::verify_baseline_signature(&baseline, &verifying_key)
                                 .unwrap_or(false);
+                        let operator_valid = load_baseline_operator_key(&state.vault)?
+                            .map(|key| -> Result<bool> {
+                                Ok(IntegrityScanner::verify_operator_signature(
+                                    &baseline,
+                                    &decode_org_key(&key)?,
+                                ))
+                            })
+                            .transpose()?;
                         let result = scanner.scan_against_baseline(&baseline);
                         let detail = serde_json::json!({
                             "signature_valid": sig_valid,
+                            "operator_signature_valid": operator_valid,
                             "total_files": result.total_files,
                             "modified": result.modified.len(),
                             "removed": result.removed.len(),
                             "added": result.added.len(),
                         });
                         Ok(IpcResponse::BaselineVerified {
-                            valid: result.valid && sig_valid,
+                            valid: result.valid && sig_valid && operator_valid.unwrap_or(true),
                             detail,
                         })
                     } else {
@@ -669,6 +1398,168 @@ impl IpcHandler for ServiceHandler {
                     Err(anyhow!("no protected paths configured"))
                 }
             }
+            IpcRequest::SetBaselineOperatorKey {
+                public_key,
+                authorization,
+            } => {
+                let mut state = self.state.lock();
+                let st = &mut *state;
+                let previous = load_baseline_operator_key(&st.vault)?;
+                if let Err(e) = st.engine.set_baseline_operator_key(
+                    &mut st.vault,
+                    public_key.as_deref(),
+                    authorization.as_deref(),
+                ) {
+                    st.event_log.append(
+                        "BASELINE_OPERATOR_KEY_REJECTED",
+                        EventSeverity::Critical,
+                        serde_json::json!({"error": e.to_string()}),
+                    )?;
+                    return Err(e);
+                }
+                if public_key.is_none() {
+                    // Without dual control a held baseline would never be
+                    // countersigned; create a new one instead.
+                    let held = IntegrityScanner::countersign_baseline_path(&st.baseline_path);
+                    if held.exists() {
+                        std::fs::remove_file(&held)?;
+                    }
+                }
+                st.event_log.append(
+                    "BASELINE_OPERATOR_KEY_SET",
+                    EventSeverity::Warn,
+                    serde_json::json!({
+                        "public_key": public_key.as_deref().map(str::trim),
+                        "previous_key": previous,
+                    }),
+                )?;
+                Ok(IpcResponse::BaselineOperatorKeySet {
+                    dual_control: public_key.is_some(),
+                })
+            }
+            IpcRequest::GetOperatorKeyChangeRequest { public_key } => {
+                let state = self.state.lock();
+                let message = state
+                    .engine
+                    .operator_key_change_request(&state.vault, public_key.as_deref())?;
+                Ok(IpcResponse::OperatorKeyChangeRequest {
+                    message: message.map(hex::encode),
+                })
+            }
+            IpcRequest::GetBaselineSigningRequest => {
+                let state = self.state.lock();
+                // A held rebaseline is what waits for the operator.
+                let held = IntegrityScanner::countersign_baseline_path(&state.baseline_path);
+                let operator_key = baseline_operator_key(&state.vault)?;
+                let pending = operator_key.is_some() && held.exists();
+                let baseline = if pending {
+                    IntegrityScanner::load_baseline(&held)?
+                } else {
+                    state
+                        .live_baseline
+                        .lock()
+                        .clone()
+                        .ok_or_else(|| anyhow!("no baseline exists"))?
+                };
+                let countersigned = operator_key.is_some_and(|key| {
+                    IntegrityScanner::verify_operator_signature(&baseline, &key)
+                });
+                Ok(IpcResponse::BaselineSigningRequest {
+                    message: hex::encode(IntegrityScanner::operator_signing_message(&baseline)),
+                    entries: baseline.entries.len(),
+                    created_at: baseline.created_at,
+                    countersigned,
+                    pending,
+                })
+            }
+            IpcRequest::CountersignBaseline { signature } => {
+                let state = self.state.lock();
+                let key = baseline_operator_key(&state.vault)?
+                    .ok_or_else(|| anyhow!("dual-control baselines are not enabled"))?;
+                let held = IntegrityScanner::countersign_baseline_path(&state.baseline_path);
+                if held.exists() {
+                    let mut baseline = IntegrityScanner::load_baseline(&held)?;
+                    IntegrityScanner::countersign_baseline(&mut baseline, &signature, &key)?;
+                    // In maintenance the exit validates and commits it.
+                    let in_force = !state.engine.is_maintenance();
+                    if in_force {
+                        let scanner = state
+                            .scanner
+                            .as_ref()
+                            .ok_or_else(|| anyhow!("no protected paths configured"))?;
+                        // Back up its files only once they are known to match, so
+                        // the backups of the baseline in force stay intact otherwise.
+                        if !scanner.scan_against_baseline(&baseline).valid {
+                            return Err(anyhow!(
+                                "countersigned baseline no longer matches disk; create a new one"
+                            ));
+                        }
+                        let mut store = state.backup_store.lock();
+                        for entry in baseline.entries.values().filter(|e| e.link_target.is_none()) {
+                            if let Err(e) = store.ensure_from_disk(
+                                Path::new(&entry.path),
+                                &entry.hash,
+                                entry.permissions,
+                                None,
+                            ) {
+                                warn!(
+                                    path = %entry.path,
+                                    error = %e,
+                                    "backup failed for countersigned baseline"
+                                );
+                            }
+                        }
+                        let problems = scanner.validate_candidate(
+                            &baseline,
+                            &state.signing_key.verifying_key(),
+                            &store,
+                        );
+                        drop(store);
+                        if !problems.is_empty() {
+                            return Err(anyhow!(
+                                "countersigned baseline failed validation ({} problems)",
+                                problems.len()
+                            ));
+                        }
+                        adopt_baseline(&state, &baseline, "countersign")?;
+                        std::fs::remove_file(&held)?;
+                        state.engine.snapshot_protected_paths(&state.event_log);
+                    } else {
+                        IntegrityScanner::save_baseline(&baseline, &held)?;
+                    }
+                    state.event_log.append(
+                        "BASELINE_COUNTERSIGNED",
+                        EventSeverity::Info,
+                        serde_json::json!({
+                            "entries": baseline.entries.len(),
+                            "created_at": baseline.created_at,
+                            "in_force": in_force,
+                        }),
+                    )?;
+                    return Ok(IpcResponse::BaselineCountersigned {
+                        entries: baseline.entries.len(),
+                        in_force,
+                    });
+                }
+                let mut live = state.live_baseline.lock();
+                let baseline = live.as_mut().ok_or_else(|| anyhow!("no baseline exists"))?;
+                IntegrityScanner::countersign_baseline(baseline, &signature, &key)?;
+                IntegrityScanner::save_baseline(baseline, &state.baseline_path)?;
+                let entries = baseline.entries.len();
+                state.event_log.append(
+                    "BASELINE_COUNTERSIGNED",
+                    EventSeverity::Info,
+                    serde_json::json!({
+                        "entries": entries,
+                        "created_at": baseline.created_at,
+                        "in_force": true,
+                    }),
+                )?;
+                Ok(IpcResponse::BaselineCountersigned {
+                    entries,
+                    in_force: true,
+                })
+            }
             IpcRequest::RestoreNow { path } => {
                 let state = self.state.lock();
                 let target = PathBuf::from(&path);
@@ -698,6 +1589,498 @@ impl IpcHandler for ServiceHandler {
                     .unwrap_or_else(|_| serde_json::json!({"error": "serialization failed"}));
                 Ok(IpcResponse::EngineModeInfo { mode: mode_json })
             }
+            IpcRequest::TagBaseline {
+                paths,
+                add,
+                remove,
+                metadata,
+            } => {
+                let state = self.state.lock();
+                let mut baseline =
+                    baseline_to_edit(&state)?.ok_or_else(|| anyhow!("no baseline exists"))?;
+                // Never re-sign a baseline we can't vouch for.
+                let verifying_key = state.signing_key.verifying_key();
+                if !IntegrityScanner::verify_baseline_signature(&baseline, &verifying_key)? {
+                    return Err(anyhow!("baseline signature invalid; refusing to re-sign"));
+                }
+                let entries = baseline.annotate(&paths, &add, &remove, &metadata);
+                if entries == 0 {
+                    return Err(anyhow!("no baseline entries match the given paths"));
+                }
+                IntegrityScanner::sign_baseline(&mut baseline, &state.signing_key);
+                let adopted = adopt_baseline(&state, &baseline, "tag")?;
+                state.event_log.append(
+                    "BASELINE_TAGGED",
+                    EventSeverity::Info,
+                    serde_json::json!({
+                        "paths": paths,
+                        "added": add,
+                        "removed": remove,
+                        "metadata_keys": metadata.keys().collect::<Vec<_>>(),
+                        "entries": entries,
+                        "pending_countersign": !adopted,
+                    }),
+                )?;
+                Ok(IpcResponse::BaselineTagged { entries })
+            }
+            IpcRequest::ScanReport { tag } => {
+                let state = self.state.lock();
+                let scanner = state
+                    .scanner
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("no protected paths configured"))?;
+                let baseline = state
+                    .live_baseline
+                    .lock()
+                    .clone()
+                    .ok_or_else(|| anyhow!("no baseline exists"))?;
+                let mut result = scanner.scan_against_baseline(&baseline);
+                if let Some(tag) = tag {
+                    result = result.filter_by_tag(&baseline, &tag);
+                }
+                let result_json = serde_json::to_value(&result)
+                    .unwrap_or_else(|_| serde_json::json!({"error": "serialization failed"}));
+                Ok(IpcResponse::ScanComplete { result: result_json })
+            }
+            IpcRequest::RestoreByTag { tag } => {
+                let state = self.state.lock();
+                let scanner = state
+                    .scanner
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("no protected paths configured"))?;
+                let baseline = state
+                    .live_baseline
+                    .lock()
+                    .clone()
+                    .ok_or_else(|| anyhow!("no baseline exists"))?;
+                let result = scanner
+                    .scan_against_baseline(&baseline)
+                    .filter_by_tag(&baseline, &tag);
+                let targets = result
+                    .modified
+                    .iter()
+                    .map(|m| m.path.clone())
+                    .chain(result.removed.iter().cloned());
+                let store_guard = state.backup_store.lock();
+                let mut results = Vec::new();
+                for path in targets {
+                    if let Some(entry) = baseline.entries.get(&path) {
+                        let outcome = state.restore_engine.restore_file(
+                            &PathBuf::from(&path),
+                            entry,
+                            &store_guard,
+                        );
+                        state.engine.log_restore(
+                            &path,
+                            &outcome,
+                            &baseline.tags_for(&path),
+                            &state.event_log,
+                        );
+                        results.push(RestoreItem {
+                            path,
+                            outcome: format!("{:?}", outcome),
+                        });
+                    }
+                }
+                Ok(IpcResponse::RestoreBatch { tag, results })
+            }
+            IpcRequest::SetPolicyOrgKey { public_key } => {
+                let mut state = self.state.lock();
+                let st = &mut *state;
+                st.engine.set_policy_org_key(&mut st.vault, &public_key)?;
+                st.event_log.append(
+                    "POLICY_ORG_KEY_SET",
+                    EventSeverity::Warn,
+                    serde_json::json!({"public_key": public_key.trim()}),
+                )?;
+                Ok(IpcResponse::PolicyOrgKeySet)
+            }
+            IpcRequest::ImportPolicy { bundle } => {
+                let mut state = self.state.lock();
+                let st = &mut *state;
+                let policy_id = bundle.bundle.policy_id.clone();
+                let version = bundle.bundle.version;
+                match st.engine.apply_policy(&mut st.vault, bundle) {
+                    Ok(applied) => {
+                        let locked: Vec<String> =
+                            applied.locked_fields().into_iter().map(String::from).collect();
+                        st.event_log.append(
+                            "POLICY_APPLIED",
+                            EventSeverity::Info,
+                            serde_json::json!({
+                                "policy_id": policy_id,
+                                "version": version,
+                                "locked": locked,
+                            }),
+                        )?;
+                        Ok(IpcResponse::PolicyApplied {
+                            policy_id,
+                            version,
+                            locked,
+                        })
+                    }
+                    Err(e) => {
+                        st.event_log.append(
+                            "POLICY_REJECTED",
+                            EventSeverity::Warn,
+                            serde_json::json!({
+                                "policy_id": policy_id,
+                                "version": version,
+                                "error": e.to_string(),
+                            }),
+                        )?;
+                        Err(e)
+                    }
+                }
+            }
+            IpcRequest::GetPolicy => {
+                let state = self.state.lock();
+                let policy = state.engine.active_policy();
+                let locked = policy
+                    .as_ref()
+                    .map(|p| p.locked_fields().into_iter().map(String::from).collect())
+                    .unwrap_or_default();
+                Ok(IpcResponse::PolicyInfo {
+                    org_key: load_policy_org_key(&state.vault)?,
+                    policy,
+                    locked,
+                })
+            }
+            IpcRequest::ImportSbom {
+                root,
+                vendor_key,
+                manifest,
+            } => {
+                let mut state = self.state.lock();
+                let st = &mut *state;
+                let summary = serde_json::json!({
+                    "root": root,
+                    "vendor": manifest.manifest.vendor,
+                    "product": manifest.manifest.product,
+                    "version": manifest.manifest.version,
+                });
+                match st
+                    .engine
+                    .import_sbom(&mut st.vault, Path::new(&root), &vendor_key, manifest)
+                {
+                    Ok(binding) => {
+                        let m = &binding.signed.manifest;
+                        st.event_log.append(
+                            "SBOM_IMPORTED",
+                            EventSeverity::Info,
+                            serde_json::json!({
+                                "root": binding.root,
+                                "vendor": m.vendor,
+                                "product": m.product,
+                                "version": m.version,
+                                "files": m.files.len(),
+                            }),
+                        )?;
+                        Ok(IpcResponse::SbomImported {
+                            root: binding.root.clone(),
+                            vendor: m.vendor.clone(),
+                            product: m.product.clone(),
+                            version: m.version.clone(),
+                            files: m.files.len(),
+                        })
+                    }
+                    Err(e) => {
+                        let mut data = summary;
+                        data["error"] = e.to_string().into();
+                        st.event_log.append("SBOM_REJECTED", EventSeverity::Warn, data)?;
+                        Err(e)
+                    }
+                }
+            }
+            IpcRequest::VerifySbom { root } => {
+                let (bindings, event_log) = {
+                    let state = self.state.lock();
+                    (load_sbom_bindings(&state.vault)?, state.event_log.clone())
+                };
+                let wanted = root.map(|r| {
+                    Path::new(&r)
+                        .canonicalize()
+                        .map(|p| p.display().to_string())
+                        .unwrap_or(r)
+                });
+                let selected: Vec<_> = bindings
+                    .into_iter()
+                    .filter(|b| wanted.as_ref().is_none_or(|w| &b.root == w))
+                    .collect();
+                if let (Some(w), true) = (&wanted, selected.is_empty()) {
+                    return Err(anyhow!("no manifest is bound to {w}"));
+                }
+                let reports: Vec<_> = tokio::task::spawn_blocking(move || {
+                    selected.iter().map(verify_binding).collect()
+                })
+                .await?;
+                for report in &reports {
+                    let severity = if report.is_clean() {
+                        EventSeverity::Info
+                    } else {
+                        EventSeverity::Warn
+                    };
+                    event_log.append(
+                        "SBOM_VERIFIED",
+                        severity,
+                        serde_json::json!({
+                            "root": report.root,
+                            "product": report.product,
+                            "version": report.version,
+                            "matched": report.matched,
+                            "modified": report.modified,
+                            "missing": report.missing,
+                            "unexpected": report.unexpected,
+                            "errors": report.errors.len(),
+                        }),
+                    )?;
+                }
+                Ok(IpcResponse::SbomReports { reports })
+            }
+            IpcRequest::AddExclusion {
+                path,
+                duration_secs,
+                reason,
+            } => {
+                let mut state = self.state.lock();
+                let st = &mut *state;
+                let exclusion = st.engine.add_exclusion(
+                    &mut st.vault,
+                    Path::new(&path),
+                    duration_secs,
+                    reason,
+                    &st.event_log,
+                )?;
+                Ok(IpcResponse::ExclusionAdded { exclusion })
+            }
+            IpcRequest::RemoveExclusion { path } => {
+                let mut state = self.state.lock();
+                let st = &mut *state;
+                let removed = st
+                    .engine
+                    .remove_exclusion(&mut st.vault, &path, &st.event_log)?
+                    .ok_or_else(|| anyhow!("no exclusion for {path}"))?;
+                let rebaselined = rebaseline_paths(st, &[PathBuf::from(&removed.path)])?;
+                Ok(IpcResponse::ExclusionRemoved {
+                    path: removed.path,
+                    rebaselined,
+                })
+            }
+            IpcRequest::ListExclusions => {
+                let engine = self.state.lock().engine.clone();
+                Ok(IpcResponse::Exclusions {
+                    exclusions: engine.exclusions(),
+                })
+            }
+            IpcRequest::ReleaseWriteFreeze => {
+                let state = self.state.lock();
+                let directories = state.write_freeze.release()?;
+                state.event_log.append(
+                    "WRITE_FREEZE_RELEASED",
+                    EventSeverity::Warn,
+                    serde_json::json!({
+                        "directories": directories,
+                        "still_frozen": state.write_freeze.frozen_dirs().len(),
+                    }),
+                )?;
+                Ok(IpcResponse::WriteFreezeReleased { directories })
+            }
+            IpcRequest::ListSnapshots => {
+                let engine = self.state.lock().engine.clone();
+                let snapshots = engine
+                    .snapshot_manager()
+                    .map(|m| m.records())
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|r| serde_json::to_value(r).unwrap_or_default())
+                    .collect();
+                Ok(IpcResponse::Snapshots { snapshots })
+            }
+            IpcRequest::GetBackupStoreStats => {
+                let store = self.state.lock().backup_store.clone();
+                let stats = store.lock().stats();
+                Ok(IpcResponse::BackupStoreStats { stats })
+            }
+            IpcRequest::SelfTest { path } => {
+                let (engine, signing_key, data_dir, event_log) = {
+                    let st = self.state.lock();
+                    (st.engine.clone(), st.signing_key.clone(), st.data_dir.clone(), st.event_log.clone())
+                };
+                let protected = engine.settings().protection.protected_paths;
+                let source = match path {
+                    Some(path) if protected.contains(&path) => path,
+                    Some(path) => return Err(anyhow!("{path} is not a protected path")),
+                    None => protected
+                        .first()
+                        .cloned()
+                        .ok_or_else(|| anyhow!("no protected paths configured"))?,
+                };
+                let report =
+                    selftest::run_self_test(&engine, Path::new(&source), &data_dir, &signing_key)
+                        .await?;
+                let failed: Vec<&str> = report
+                    .scenarios
+                    .iter()
+                    .filter(|s| !s.passed)
+                    .map(|s| s.scenario.as_str())
+                    .collect();
+                event_log.append(
+                    "SELF_TEST_COMPLETED",
+                    if report.passed { EventSeverity::Info } else { EventSeverity::Warn },
+                    serde_json::json!({
+                        "source": report.source,
+                        "passed": report.passed,
+                        "duration_ms": report.duration_ms,
+                        "failed_scenarios": failed,
+                    }),
+                )?;
+                Ok(IpcResponse::SelfTestCompleted { report })
+            }
+            IpcRequest::ListQuarantine => {
+                let (root, event_log) = {
+                    let st = self.state.lock();
+                    (st.restore_engine.quarantine().root().to_path_buf(), st.event_log.clone())
+                };
+                let items = evidence::quarantine_items(&root, |path| {
+                    evidence::quarantined_from(&event_log, path)
+                })?;
+                Ok(IpcResponse::QuarantineItems { items })
+            }
+            IpcRequest::ExportQuarantine {
+                destination,
+                items,
+                examiner,
+                password,
+            } => {
+                let (root, device_id, signing_key, event_log) = {
+                    let st = self.state.lock();
+                    (
+                        st.restore_engine.quarantine().root().to_path_buf(),
+                        st.vault.payload.device_id.clone(),
+                        st.signing_key.clone(),
+                        st.event_log.clone(),
+                    )
+                };
+                // Key derivation, compression and a log search per item;
+                // don't hold the state lock.
+                let manifest = {
+                    let event_log = event_log.clone();
+                    let destination = destination.clone();
+                    let examiner = examiner.clone();
+                    tokio::task::spawn_blocking(move || {
+                        let custodian = Custodian {
+                            device_id: &device_id,
+                            examiner: &examiner,
+                            device_key: &signing_key,
+                        };
+                        evidence::export_quarantine(
+                            &root,
+                            &items,
+                            |path| evidence::quarantined_from(&event_log, path),
+                            Path::new(&destination),
+                            &password,
+                            &custodian,
+                        )
+                    })
+                    .await??
+                };
+                event_log.append(
+                    "QUARANTINE_EXPORTED",
+                    EventSeverity::Info,
+                    serde_json::json!({
+                        "archive": destination,
+                        "archive_id": manifest.archive_id,
+                        "examiner": examiner,
+                        "items": manifest.items,
+                    }),
+                )?;
+                Ok(IpcResponse::QuarantineExported {
+                    archive: destination,
+                    manifest,
+                })
+            }
+            IpcRequest::ImportEvidence {
+                archive,
+                examiner,
+                password,
+                exporter_key,
+            } => {
+                let exporter_key = exporter_key.as_deref().map(decode_org_key).transpose()?;
+                let (device_id, signing_key, evidence_root, event_log) = {
+                    let st = self.state.lock();
+                    (
+                        st.vault.payload.device_id.clone(),
+                        st.signing_key.clone(),
+                        st.data_dir.join("evidence"),
+                        st.event_log.clone(),
+                    )
+                };
+                let (manifest, directory) = {
+                    let archive = archive.clone();
+                    let examiner = examiner.clone();
+                    tokio::task::spawn_blocking(move || {
+                        let custodian = Custodian {
+                            device_id: &device_id,
+                            examiner: &examiner,
+                            device_key: &signing_key,
+                        };
+                        evidence::import_evidence(
+                            Path::new(&archive),
+                            &password,
+                            &evidence_root,
+                            exporter_key.as_ref(),
+                            &custodian,
+                        )
+                    })
+                    .await??
+                };
+                event_log.append(
+                    "EVIDENCE_IMPORTED",
+                    EventSeverity::Info,
+                    serde_json::json!({
+                        "archive": archive,
+                        "archive_id": manifest.archive_id,
+                        "examiner": examiner,
+                        "exported_by": manifest.custody.first().map(|r| &r.device_id),
+                        "items": manifest.items.len(),
+                        "directory": directory.display().to_string(),
+                    }),
+                )?;
+                Ok(IpcResponse::EvidenceImported {
+                    directory: directory.display().to_string(),
+                    manifest,
+                })
+            }
+            IpcRequest::GetCommandReceipts { limit } => {
+                use base64::{engine::general_purpose, Engine as _};
+                let st = self.state.lock();
+                Ok(IpcResponse::CommandReceipts {
+                    receipts: st.command_receipts.recent(limit.unwrap_or(100)),
+                    device_public_key: general_purpose::STANDARD
+                        .encode(st.signing_key.verifying_key().to_bytes()),
+                })
+            }
+            IpcRequest::SnapshotRestore { path } => {
+                let (engine, restore_engine, event_log) = {
+                    let state = self.state.lock();
+                    if !state.engine.settings().protection.protected_paths.contains(&path) {
+                        return Err(anyhow!("{path} is not a protected path"));
+                    }
+                    (
+                        state.engine.clone(),
+                        state.restore_engine.clone(),
+                        state.event_log.clone(),
+                    )
+                };
+                // Rewrites the whole directory; don't hold the state lock.
+                let report = tokio::task::spawn_blocking(move || {
+                    engine.restore_from_snapshot(Path::new(&path), &restore_engine, &event_log)
+                })
+                .await??;
+                Ok(IpcResponse::SnapshotRestored {
+                    report: serde_json::to_value(report)?,
+                })
+            }
             _ => Err(anyhow!("unsupported request")),
         }
     }
@@ -728,6 +2111,132 @@ impl IpcHandler for ServiceHandler {
         )?;
         Ok(IpcResponse::SafeModeExited)
     }
+
+    async fn audit_remote(&self, event: RemoteConnectionEvent) {
+        let (event_type, severity) = match &event {
+            RemoteConnectionEvent::Authenticated { .. } => {
+                ("REMOTE_IPC_AUTHENTICATED", EventSeverity::Info)
+            }
+            RemoteConnectionEvent::Rejected { .. } => ("REMOTE_IPC_REJECTED", EventSeverity::Warn),
+            RemoteConnectionEvent::Closed { .. } => ("REMOTE_IPC_CLOSED", EventSeverity::Info),
+        };
+        let mut data = serde_json::to_value(&event).unwrap_or_default();
+        if let RemoteConnectionEvent::Rejected { peer, reason } = &event {
+            if self.rejections.check("remote", &IpcRequest::Ping).is_err() {
+                self.rejections_dropped.fetch_add(1, Ordering::Relaxed);
+                debug!(%peer, %reason, "remote IPC connection rejected");
+                return;
+            }
+            data["dropped"] = self.rejections_dropped.swap(0, Ordering::Relaxed).into();
+        }
+        let event_log = self.state.lock().event_log.clone();
+        if let Err(e) = event_log.append(event_type, severity, data) {
+            warn!("failed to log {event_type}: {e}");
+        }
+    }
+
+    async fn audit_request(&self, audit: RequestAudit) {
+        // Local status polling would flood the chain; keep it in the trace log.
+        let Some(severity) = audit.log_severity() else {
+            debug!(
+                request = %audit.request,
+                client = %audit.client.client_id,
+                duration_ms = audit.duration_ms,
+                "ipc request"
+            );
+            return;
+        };
+        let event_log = self.state.lock().event_log.clone();
+        let data = serde_json::to_value(&audit).unwrap_or_default();
+        if let Err(e) = event_log.append("IPC_REQUEST", severity, data) {
+            warn!("failed to log IPC_REQUEST: {e}");
+        }
+    }
+
+    fn rate_limits(&self) -> Option<RateLimits> {
+        let ipc = self.state.lock().engine.settings().ipc;
+        Some(RateLimits {
+            requests_per_minute: ipc.requests_per_minute,
+            scans_per_minute: ipc.scans_per_minute,
+        })
+    }
+}
+
+/// Serialize `entries` for IPC, each with a `notes` array of the operator
+/// notes logged on it.
+fn with_notes(event_log: &EventLog, entries: Vec<EventEntry>) -> Vec<serde_json::Value> {
+    let seqs: Vec<u64> = entries.iter().map(|e| e.seq).collect();
+    let mut notes = event_log.notes_for(&seqs).unwrap_or_default();
+    entries
+        .into_iter()
+        .map(|e| {
+            let seq = e.seq;
+            let mut value = serde_json::to_value(e).unwrap_or_default();
+            value["notes"] = serde_json::to_value(notes.remove(&seq).unwrap_or_default())
+                .unwrap_or_default();
+            value
+        })
+        .collect()
+}
+
+/// Apply `settings` through the engine and log the change, with its diff,
+/// as `SETTINGS_CHANGED`, made by the authenticated `client`. A `note`
+/// from the request is kept alongside but not trusted as the author.
+fn update_settings_audited(
+    st: &mut ServiceState,
+    settings: GuardSettings,
+    client: &ClientIdentity,
+    note: Option<&str>,
+    rollback_of: Option<u64>,
+) -> Result<Option<SettingsVersion>> {
+    if st.offline.settings_locked {
+        st.event_log.append(
+            "SETTINGS_CHANGE_BLOCKED",
+            EventSeverity::Warn,
+            serde_json::json!({
+                "reason": "offline",
+                "offline_since": st.offline.since,
+            }),
+        )?;
+        return Err(anyhow!(
+            "settings are locked until the device reconnects to the platform"
+        ));
+    }
+    if let Some(policy) = st.engine.active_policy() {
+        let locked = policy.violations(&settings);
+        if !locked.is_empty() {
+            st.event_log.append(
+                "SETTINGS_CHANGE_BLOCKED",
+                EventSeverity::Warn,
+                serde_json::json!({"policy_id": policy.policy_id, "fields": locked}),
+            )?;
+        }
+    }
+    let recorded = st
+        .engine
+        .update_settings_by(&mut st.vault, settings, &client.label(), note, rollback_of)
+        .map_err(|e| anyhow!(e.to_string()))?;
+    st.restore_engine.configure(&st.engine.settings().restore);
+    st.restore_engine.set_privileged_helper(PrivilegedHelper::from_settings(
+        &st.engine.settings().privileged_helper,
+        &st.signing_key,
+        st.event_log.clone(),
+    ));
+    if let Some(version) = &recorded {
+        st.event_log.append(
+            "SETTINGS_CHANGED",
+            EventSeverity::Info,
+            serde_json::json!({
+                "version": version.version,
+                "changed_by": version.changed_by,
+                "client": client,
+                "note": version.note,
+                "rollback_of": version.rollback_of,
+                "changes": version.changes,
+            }),
+        )?;
+    }
+    Ok(recorded)
 }
 
 fn prompt_password_once(prompt: &str) -> Result<String> {
@@ -782,11 +2291,3 @@ fn load_manifest(path: &str) -> Result<ManifestView> {
     Ok(m)
 }
 
-fn run_updater(updater_path: &PathBuf, args: &[&str]) -> Result<String> {
-    let output = Command::new(updater_path).args(args).output()?;
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(anyhow!("updater failed: {}", stderr));
-    }
-    Ok(String::from_utf8_lossy(&output.stdout).to_string())
-}
@@ -0,0 +1,377 @@
+//! Resource budgets and self-metrics.
+//!
+//! A periodic check samples the service's own memory, descriptors, watcher
+//! load, baseline size and backup store size, publishes the sample through
+//! `GetStatus`, and compares it against `settings.resources`. Crossing a
+//! budget's warning threshold logs `RESOURCE_BUDGET_EXCEEDED` once, and
+//! dropping back below it `RESOURCE_BUDGET_RECOVERED`. When the watched trees
+//! have grown past the watch budget, the largest are moved to polling and
+//! `WATCHER_DEGRADED` is logged, before the OS limit is hit.
+
+use crate::integrity::scanner::Baseline;
+use crate::service_state::ServiceState;
+use chrono::Utc;
+use guard_core::event_log::EventSeverity;
+use guard_core::health::ResourceUsage;
+use guard_core::settings::GuardSettings;
+use parking_lot::Mutex;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+const MB: u64 = 1024 * 1024;
+
+/// One resource at or over its warning threshold.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BudgetBreach {
+    pub resource: &'static str,
+    pub used: u64,
+    pub limit: u64,
+}
+
+/// Resident set size of this process.
+#[cfg(target_os = "linux")]
+pub(crate) fn rss_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let kb = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn rss_bytes() -> Option<u64> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn open_fds() -> Option<u64> {
+    Some(std::fs::read_dir("/proc/self/fd").ok()?.count() as u64)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn open_fds() -> Option<u64> {
+    None
+}
+
+/// Soft limit on open descriptors for this process.
+#[cfg(unix)]
+pub(crate) fn fd_limit() -> Option<u64> {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: getrlimit only writes to the struct it is given.
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } != 0
+        || limit.rlim_cur == libc::RLIM_INFINITY
+    {
+        return None;
+    }
+    // rlim_t is not u64 on every unix.
+    #[allow(clippy::unnecessary_cast)]
+    Some(limit.rlim_cur as u64)
+}
+
+#[cfg(not(unix))]
+pub(crate) fn fd_limit() -> Option<u64> {
+    None
+}
+
+/// Per-user inotify watch limit.
+#[cfg(target_os = "linux")]
+fn os_watch_limit() -> Option<u64> {
+    std::fs::read_to_string("/proc/sys/fs/inotify/max_user_watches")
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn os_watch_limit() -> Option<u64> {
+    None
+}
+
+/// Native watch descriptors the watcher may use: the configured budget, or
+/// half the OS limit so other programs keep some.
+pub(crate) fn watch_budget(settings: &GuardSettings) -> u64 {
+    match settings.resources.max_watches {
+        0 => os_watch_limit().map_or(u64::MAX, |limit| limit / 2),
+        n => n,
+    }
+}
+
+/// Rough in-memory size of `baseline`: the entries' strings plus per-entry
+/// struct and map overhead.
+pub(crate) fn baseline_bytes(baseline: &Baseline) -> u64 {
+    const ENTRY_OVERHEAD: u64 = 160;
+    baseline
+        .entries
+        .iter()
+        .map(|(key, e)| {
+            ENTRY_OVERHEAD
+                + key.len() as u64
+                + e.path.len() as u64
+                + e.hash.len() as u64
+                + e.link_target.as_ref().map_or(0, |t| t.len() as u64)
+                + e.quick_hash.as_ref().map_or(0, |h| h.len() as u64)
+        })
+        .sum()
+}
+
+/// Resources in `usage` at or over `warn_percent` of their budget.
+pub(crate) fn breaches(usage: &ResourceUsage, settings: &GuardSettings) -> Vec<BudgetBreach> {
+    let budgets = &settings.resources;
+    let candidates = [
+        (
+            "memory",
+            usage.rss_bytes,
+            Some((settings.performance.max_memory_mb as u64).saturating_mul(MB)),
+        ),
+        ("file_descriptors", usage.open_fds, usage.fd_limit),
+        ("watches", Some(usage.watches), Some(usage.watch_budget)),
+        (
+            "baseline",
+            Some(usage.baseline_bytes),
+            Some(budgets.max_baseline_mb.saturating_mul(MB)),
+        ),
+        (
+            "backup_store",
+            Some(usage.backup_store_bytes),
+            Some(budgets.max_backup_store_mb.saturating_mul(MB)),
+        ),
+    ];
+    candidates
+        .into_iter()
+        .filter_map(|(resource, used, limit)| {
+            let (used, limit) = (used?, limit?);
+            let threshold = limit / 100 * budgets.warn_percent as u64;
+            (limit > 0 && limit != u64::MAX && used >= threshold).then_some(BudgetBreach {
+                resource,
+                used,
+                limit,
+            })
+        })
+        .collect()
+}
+
+/// Which resources are over budget, so each crossing is logged once.
+#[derive(Debug, Default)]
+pub(crate) struct BudgetTracker {
+    over: BTreeSet<&'static str>,
+}
+
+impl BudgetTracker {
+    /// Record the current breaches; returns those that are new and the
+    /// resources that have recovered since the last call.
+    pub fn update(&mut self, current: &[BudgetBreach]) -> (Vec<BudgetBreach>, Vec<&'static str>) {
+        let now: BTreeSet<&'static str> = current.iter().map(|b| b.resource).collect();
+        let new = current
+            .iter()
+            .filter(|b| !self.over.contains(b.resource))
+            .cloned()
+            .collect();
+        let recovered = self.over.difference(&now).copied().collect();
+        self.over = now;
+        (new, recovered)
+    }
+}
+
+/// Sample usage, degrade the watcher if it has outgrown its budget, and log
+/// budget crossings. The sample is kept on the service state for `GetStatus`.
+pub(crate) fn check_resources(state: &Arc<Mutex<ServiceState>>, tracker: &mut BudgetTracker) {
+    let (settings, event_log, watcher, live_baseline, backup_store) = {
+        let st = state.lock();
+        (
+            st.engine.settings(),
+            st.event_log.clone(),
+            st.file_watcher.clone(),
+            st.live_baseline.clone(),
+            st.backup_store.clone(),
+        )
+    };
+    let budget = watch_budget(&settings);
+
+    let (watches, polled_paths) = match &watcher {
+        Some(watcher) => {
+            let mut fw = watcher.lock();
+            match fw.rebalance(budget) {
+                Ok(moved) if !moved.is_empty() => {
+                    let _ = event_log.append(
+                        "WATCHER_DEGRADED",
+                        EventSeverity::Warn,
+                        serde_json::json!({
+                            "paths": moved.iter().map(|p| p.display().to_string()).collect::<Vec<_>>(),
+                            "watches": fw.watch_count(),
+                            "budget": budget,
+                        }),
+                    );
+                }
+                Ok(_) => {}
+                Err(e) => warn!(error = %e, "failed to move watched paths to polling"),
+            }
+            (fw.watch_count(), fw.polled_paths())
+        }
+        None => (0, Vec::new()),
+    };
+
+    let (baseline_entries, baseline_size) = live_baseline
+        .lock()
+        .as_ref()
+        .map_or((0, 0), |b| (b.entries.len(), baseline_bytes(b)));
+    let backup_store_bytes = backup_store.lock().manifest().total_size;
+
+    let mut usage = ResourceUsage {
+        sampled_at: Some(Utc::now()),
+        rss_bytes: rss_bytes(),
+        open_fds: open_fds(),
+        fd_limit: fd_limit(),
+        watches,
+        watch_budget: budget,
+        polled_paths: polled_paths
+            .iter()
+            .map(|p| p.display().to_string())
+            .collect(),
+        baseline_entries,
+        baseline_bytes: baseline_size,
+        backup_store_bytes,
+        over_budget: Vec::new(),
+    };
+
+    let current = breaches(&usage, &settings);
+    usage.over_budget = current.iter().map(|b| b.resource.to_string()).collect();
+    let (new, recovered) = tracker.update(&current);
+    for breach in new {
+        warn!(
+            resource = breach.resource,
+            used = breach.used,
+            limit = breach.limit,
+            "resource budget exceeded"
+        );
+        let _ = event_log.append(
+            "RESOURCE_BUDGET_EXCEEDED",
+            EventSeverity::Warn,
+            serde_json::json!({
+                "resource": breach.resource,
+                "used": breach.used,
+                "limit": breach.limit,
+            }),
+        );
+    }
+    for resource in recovered {
+        let _ = event_log.append(
+            "RESOURCE_BUDGET_RECOVERED",
+            EventSeverity::Info,
+            serde_json::json!({ "resource": resource }),
+        );
+    }
+
+    state.lock().resource_usage = Some(usage);
+}
+
+/// Spawn the periodic resource check. The first check runs immediately so
+/// `GetStatus` has figures from startup.
+pub(crate) fn spawn_resource_monitor(
+    state: Arc<Mutex<ServiceState>>,
+    mut shutdown: watch::Receiver<bool>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut tracker = BudgetTracker::default();
+        loop {
+            let state_c = state.clone();
+            tracker = tokio::task::spawn_blocking(move || {
+                check_resources(&state_c, &mut tracker);
+                tracker
+            })
+            .await
+            .unwrap_or_default();
+            let interval = state.lock().engine.settings().resources.check_interval_secs;
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_secs(interval)) => {}
+                _ = shutdown.changed() => {
+                    if *shutdown.borrow() { return; }
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage() -> ResourceUsage {
+        ResourceUsage {
+            rss_bytes: Some(100 * MB),
+            open_fds: Some(100),
+            fd_limit: Some(1024),
+            watches: 1000,
+            watch_budget: 10_000,
+            baseline_bytes: 10 * MB,
+            backup_store_bytes: 100 * MB,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn breaches_start_at_the_warning_threshold() {
+        let settings = GuardSettings::default();
+        assert!(breaches(&usage(), &settings).is_empty());
+
+        let mut heavy = usage();
+        heavy.rss_bytes = Some(450 * MB); // 88% of 512 MB
+        heavy.open_fds = Some(1000);
+        heavy.watches = 7_999;
+        let found: Vec<_> = breaches(&heavy, &settings)
+            .into_iter()
+            .map(|b| b.resource)
+            .collect();
+        assert_eq!(found, ["memory", "file_descriptors"]);
+
+        // Disabled and unknown budgets never breach.
+        let mut settings = settings;
+        settings.resources.max_backup_store_mb = 0;
+        let mut unknown = usage();
+        unknown.backup_store_bytes = u64::MAX;
+        unknown.fd_limit = None;
+        unknown.open_fds = Some(u64::MAX);
+        unknown.watch_budget = u64::MAX;
+        unknown.watches = u64::MAX;
+        assert!(breaches(&unknown, &settings).is_empty());
+    }
+
+    #[test]
+    fn tracker_reports_each_crossing_once() {
+        let breach = |resource| BudgetBreach {
+            resource,
+            used: 1,
+            limit: 1,
+        };
+        let mut tracker = BudgetTracker::default();
+        let (new, recovered) = tracker.update(&[breach("memory")]);
+        assert_eq!(new, [breach("memory")]);
+        assert!(recovered.is_empty());
+
+        let (new, _) = tracker.update(&[breach("memory"), breach("watches")]);
+        assert_eq!(new, [breach("watches")]);
+
+        let (new, recovered) = tracker.update(&[breach("watches")]);
+        assert!(new.is_empty());
+        assert_eq!(recovered, ["memory"]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn process_figures_are_sampled() {
+        assert!(rss_bytes().unwrap() > 0);
+        assert!(open_fds().unwrap() > 0);
+    }
+}
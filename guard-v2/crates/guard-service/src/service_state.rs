@@ -3,17 +3,26 @@ use ed25519_dalek::SigningKey;
 use guard_core::backup_store::BackupStore;
 use guard_core::device_state::RemoteActivityStatus;
 use guard_core::event_log::EventLog;
+use guard_core::health::ResourceUsage;
 use guard_core::safe_mode::SafeModeState;
 use guard_core::vault::Vault;
 use parking_lot::Mutex as ParkMutex;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::broadcast;
 use zeroize::Zeroizing;
 
+use crate::connected::offline::OfflineStatus;
+use crate::connected::receipts::ReceiptLog;
 use crate::enforcement::restore::RestoreEngine;
+use crate::enforcement::write_freeze::WriteFreeze;
 use crate::engine::Engine;
 use crate::integrity::audit_loop::AuditLoopHandle;
-use crate::integrity::scanner::IntegrityScanner;
+use crate::integrity::pipeline::TamperEvent;
+use crate::integrity::scanner::{Baseline, IntegrityScanner};
+use crate::integrity::watcher::FileWatcher;
+use crate::supervisor::Supervisor;
 
 // All fields are accessed through `Arc<Mutex<ServiceState>>` in the IPC handler
 // and connected module. The dead_code lint cannot see through the Mutex.
@@ -27,7 +36,10 @@ pub(crate) struct ServiceState {
     pub(crate) password: Zeroizing<String>,
     pub(crate) connected: bool,
     pub(crate) last_heartbeat: Option<DateTime<Utc>>,
+    pub(crate) offline: OfflineStatus,
     pub(crate) last_remote_command: Option<RemoteCommandRecord>,
+    /// Signed receipts for every remote command received.
+    pub(crate) command_receipts: Arc<ReceiptLog>,
     pub(crate) update_available: bool,
     pub(crate) _crash_tracker: CrashTracker,
     pub(crate) scanner: Option<IntegrityScanner>,
@@ -37,6 +49,16 @@ pub(crate) struct ServiceState {
     pub(crate) backup_store: Arc<ParkMutex<BackupStore>>,
     pub(crate) restore_engine: Arc<RestoreEngine>,
     pub(crate) audit_loop_handle: Option<AuditLoopHandle>,
+    pub(crate) supervisor: Arc<Supervisor>,
+    pub(crate) live_baseline: Arc<ParkMutex<Option<Baseline>>>,
+    pub(crate) write_freeze: Arc<WriteFreeze>,
+    pub(crate) started_at: Instant,
+    /// Sender side of the verified tamper channel; `None` without a watcher.
+    pub(crate) tamper_tx: Option<broadcast::Sender<TamperEvent>>,
+    /// The realtime watcher, rebalanced by the resource check.
+    pub(crate) file_watcher: Option<Arc<ParkMutex<FileWatcher>>>,
+    /// Latest resource sample; `None` until the first check.
+    pub(crate) resource_usage: Option<ResourceUsage>,
 }
 
 #[allow(dead_code)]
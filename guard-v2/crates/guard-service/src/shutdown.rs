@@ -0,0 +1,96 @@
+//! Waiting for the service to be told to stop.
+//!
+//! Interactive runs stop on Ctrl-C; init systems send SIGTERM, and the
+//! Windows service control manager sends a stop control to the handler in
+//! `win_service`, which calls [`request_shutdown`]. Everything after the
+//! wait — marking the instance stopped, the shutdown attestation — only runs
+//! if the wait returns, so every way of stopping has to end it.
+
+use anyhow::Result;
+#[cfg(unix)]
+use tokio::signal::unix::{signal, Signal, SignalKind};
+#[cfg(windows)]
+use tokio::signal::windows::{ctrl_c, ctrl_close, ctrl_shutdown, CtrlC, CtrlClose, CtrlShutdown};
+#[cfg(windows)]
+use tokio::sync::Notify;
+
+#[cfg(windows)]
+static STOP_REQUESTED: Notify = Notify::const_new();
+
+/// Stop the service from outside the async runtime. A request made before
+/// anything waits is kept for the next [`ShutdownSignal::recv`].
+#[cfg(windows)]
+pub fn request_shutdown() {
+    STOP_REQUESTED.notify_one();
+}
+
+/// Handlers for every stop request, installed when created.
+pub struct ShutdownSignal {
+    #[cfg(unix)]
+    interrupt: Signal,
+    #[cfg(unix)]
+    terminate: Signal,
+    #[cfg(windows)]
+    ctrl_c: CtrlC,
+    #[cfg(windows)]
+    close: CtrlClose,
+    #[cfg(windows)]
+    system_shutdown: CtrlShutdown,
+}
+
+impl ShutdownSignal {
+    /// Install the handlers. Done before the service starts its subsystems,
+    /// so a stop sent while it is still starting isn't lost.
+    pub fn listen() -> Result<Self> {
+        Ok(Self {
+            #[cfg(unix)]
+            interrupt: signal(SignalKind::interrupt())?,
+            #[cfg(unix)]
+            terminate: signal(SignalKind::terminate())?,
+            #[cfg(windows)]
+            ctrl_c: ctrl_c()?,
+            #[cfg(windows)]
+            close: ctrl_close()?,
+            #[cfg(windows)]
+            system_shutdown: ctrl_shutdown()?,
+        })
+    }
+
+    /// Wait for the first stop request and name it.
+    pub async fn recv(&mut self) -> &'static str {
+        #[cfg(unix)]
+        {
+            tokio::select! {
+                _ = self.interrupt.recv() => "SIGINT",
+                _ = self.terminate.recv() => "SIGTERM",
+            }
+        }
+        #[cfg(windows)]
+        {
+            tokio::select! {
+                _ = self.ctrl_c.recv() => "Ctrl-C",
+                _ = self.close.recv() => "console close",
+                _ = self.system_shutdown.recv() => "system shutdown",
+                _ = STOP_REQUESTED.notified() => "service stop",
+            }
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn sigterm_ends_the_wait() {
+        let mut signal = ShutdownSignal::listen().unwrap();
+
+        // What systemd sends on `systemctl stop`.
+        unsafe { libc::kill(libc::getpid(), libc::SIGTERM) };
+        let stopped_by = tokio::time::timeout(Duration::from_secs(5), signal.recv())
+            .await
+            .expect("SIGTERM did not end the wait");
+        assert_eq!(stopped_by, "SIGTERM");
+    }
+}
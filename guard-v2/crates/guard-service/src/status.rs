@@ -1,8 +1,18 @@
+use crate::engine::EngineMode;
+use crate::integrity::scanner::IntegrityScanner;
 use crate::service_state::{RemoteCommandRecord, ServiceState};
+use crate::supervisor::SubsystemState;
 use anyhow::{anyhow, Result};
-use chrono::SecondsFormat;
+use chrono::{Duration, SecondsFormat, Utc};
 use guard_core::device_state::{DeviceState, RemoteActivity, UpdateChannel, UpdateState};
+use guard_core::event_log::EventQuery;
+use guard_core::health::{
+    BaselineHealth, HealthState, PathStats, QueueDepths, ServiceHealth, SubsystemStatus,
+    ViolationWindows,
+};
 use guard_core::paths::status_socket_path;
+use guard_core::policy::decode_org_key;
+use guard_core::storage::load_baseline_operator_key;
 use parking_lot::Mutex;
 use std::sync::Arc;
 use tokio::task::JoinHandle;
@@ -121,3 +131,231 @@ pub(crate) fn snapshot_state(state: &Arc<Mutex<ServiceState>>) -> Result<DeviceS
         safe_mode_reason: guard.safe_mode.reason.clone(),
     })
 }
+
+/// Per-subsystem health, queue depths and baseline state for `GetStatus`.
+pub(crate) fn service_health(state: &ServiceState) -> ServiceHealth {
+    let mut subsystems: Vec<SubsystemStatus> = state
+        .supervisor
+        .health()
+        .into_iter()
+        .map(|h| SubsystemStatus {
+            state: match h.state {
+                SubsystemState::Running => HealthState::Ok,
+                SubsystemState::Restarting => HealthState::Degraded,
+                SubsystemState::Failed => HealthState::Failed,
+                SubsystemState::Stopped => HealthState::Disabled,
+            },
+            name: h.name,
+            restarts: h.restarts,
+            heartbeat_age_secs: Some(h.heartbeat_age_secs),
+            detail: h.last_failure,
+        })
+        .collect();
+
+    let watcher = if state.tamper_tx.is_some() {
+        (HealthState::Ok, None)
+    } else {
+        (HealthState::Disabled, Some("no protected paths or watcher unavailable".to_string()))
+    };
+    subsystems.push(synthetic("file_watcher", watcher.0, watcher.1));
+
+    let connected = if !matches!(state.vault.payload.mode, guard_core::vault::Mode::Connected) {
+        (HealthState::Disabled, None)
+    } else if state.connected {
+        (HealthState::Ok, state.last_heartbeat.map(|t| format!("last heartbeat {}", t.to_rfc3339())))
+    } else if let Some(since) = state.offline.since {
+        (
+            HealthState::Degraded,
+            Some(format!("server unreachable since {}", since.to_rfc3339())),
+        )
+    } else {
+        (HealthState::Degraded, Some("server unreachable".to_string()))
+    };
+    subsystems.push(synthetic("connected_mode", connected.0, connected.1));
+
+    let backup = {
+        let store = state.backup_store.lock();
+        match store.verify_manifest_integrity() {
+            Ok(()) => (
+                HealthState::Ok,
+                Some(format!(
+                    "{} blobs, {} bytes",
+                    store.manifest().entries.len(),
+                    store.manifest().total_size
+                )),
+            ),
+            Err(e) => (HealthState::Failed, Some(e.to_string())),
+        }
+    };
+    subsystems.push(synthetic("backup_store", backup.0, backup.1));
+    subsystems.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let baseline = match state.live_baseline.lock().as_ref() {
+        Some(b) => BaselineHealth {
+            present: true,
+            entries: b.entries.len(),
+            created_at: Some(b.created_at),
+            signature_valid: Some(
+                IntegrityScanner::verify_baseline_signature(b, &state.signing_key.verifying_key())
+                    .unwrap_or(false),
+            ),
+            operator_signature_valid: load_baseline_operator_key(&state.vault)
+                .ok()
+                .flatten()
+                .map(|key| {
+                    decode_org_key(&key)
+                        .map(|key| IntegrityScanner::verify_operator_signature(b, &key))
+                        .unwrap_or(false)
+                }),
+        },
+        None => BaselineHealth::default(),
+    };
+
+    ServiceHealth {
+        uptime_secs: state.started_at.elapsed().as_secs(),
+        mode: match state.engine.mode() {
+            EngineMode::Active => "Active",
+            EngineMode::Maintenance { .. } => "Maintenance",
+            EngineMode::SafeMode => "SafeMode",
+        }
+        .to_string(),
+        safe_mode_reason: state.safe_mode.reason.clone(),
+        subsystems,
+        queues: QueueDepths {
+            tamper_events: state.tamper_tx.as_ref().map_or(0, |tx| tx.len()),
+            maintenance_queued: state.engine.queued_event_count(),
+            restores_in_progress: state.restore_engine.restoring.lock().len(),
+        },
+        last_scan: state.engine.last_scan(),
+        baseline,
+        resources: state.resource_usage.clone(),
+    }
+}
+
+/// Event types counted per path by `path_stats`.
+const PATH_STAT_EVENTS: &[&str] = &[
+    "TAMPER_DETECTED",
+    "UNAUTHORIZED_FILE",
+    "INTEGRITY_VIOLATION",
+    "RESTORE_SUCCESS",
+    "PERMISSIONS_RESTORED",
+    "RESTORE_FAILURE",
+];
+
+/// Per protected path: baseline footprint, last scan time, violations over
+/// the last hour / day / week and restore outcomes over the last week.
+pub(crate) fn path_stats(state: &ServiceState) -> Result<Vec<PathStats>> {
+    let now = Utc::now();
+    let settings = state.engine.settings();
+    let last_scan = state.engine.last_scan();
+    let durations = state.engine.last_scan_durations();
+
+    let mut stats: Vec<(std::path::PathBuf, PathStats)> = settings
+        .protection
+        .protected_paths
+        .iter()
+        .map(|root| {
+            let canonical = std::path::Path::new(root)
+                .canonicalize()
+                .unwrap_or_else(|_| root.into());
+            let stat = PathStats {
+                path: root.clone(),
+                exists: canonical.exists(),
+                files: 0,
+                total_bytes: 0,
+                last_scan_at: last_scan.as_ref().map(|s| s.at),
+                last_scan_duration_ms: durations.get(root).copied(),
+                violations: ViolationWindows::default(),
+                restores_succeeded: 0,
+                restores_failed: 0,
+                restore_success_rate: None,
+            };
+            (canonical, stat)
+        })
+        .collect();
+
+    if let Some(baseline) = state.live_baseline.lock().as_ref() {
+        for entry in baseline.entries.values() {
+            if let Some((_, stat)) = stats
+                .iter_mut()
+                .find(|(root, _)| std::path::Path::new(&entry.path).starts_with(root))
+            {
+                stat.files += 1;
+                stat.total_bytes += entry.size;
+            }
+        }
+    }
+
+    let mut cursor = None;
+    loop {
+        let page = state.event_log.search(&EventQuery {
+            event_types: PATH_STAT_EVENTS.iter().map(|t| t.to_string()).collect(),
+            since: Some(now - Duration::days(7)),
+            cursor,
+            limit: Some(1000),
+            ..Default::default()
+        })?;
+        for event in &page.events {
+            let age = now - event.timestamp;
+            let count_violation = |w: &mut ViolationWindows, n: u64| {
+                if age <= Duration::hours(1) {
+                    w.last_hour += n;
+                }
+                if age <= Duration::days(1) {
+                    w.last_day += n;
+                }
+                w.last_week += n;
+            };
+            if event.event_type == "INTEGRITY_VIOLATION" {
+                let Some(roots) = event.data.get("roots").and_then(|r| r.as_object()) else {
+                    continue;
+                };
+                for (_, stat) in stats.iter_mut() {
+                    if let Some(n) = roots.get(&stat.path).and_then(|n| n.as_u64()) {
+                        count_violation(&mut stat.violations, n);
+                    }
+                }
+                continue;
+            }
+            let Some(path) = event.data.get("path").and_then(|p| p.as_str()) else {
+                continue;
+            };
+            let Some((_, stat)) = stats
+                .iter_mut()
+                .find(|(root, _)| std::path::Path::new(path).starts_with(root))
+            else {
+                continue;
+            };
+            match event.event_type.as_str() {
+                "RESTORE_SUCCESS" | "PERMISSIONS_RESTORED" => stat.restores_succeeded += 1,
+                "RESTORE_FAILURE" => stat.restores_failed += 1,
+                _ => count_violation(&mut stat.violations, 1),
+            }
+        }
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+
+    Ok(stats
+        .into_iter()
+        .map(|(_, mut stat)| {
+            let attempts = stat.restores_succeeded + stat.restores_failed;
+            if attempts > 0 {
+                stat.restore_success_rate = Some(stat.restores_succeeded as f64 / attempts as f64);
+            }
+            stat
+        })
+        .collect())
+}
+
+fn synthetic(name: &str, state: HealthState, detail: Option<String>) -> SubsystemStatus {
+    SubsystemStatus {
+        name: name.to_string(),
+        state,
+        restarts: 0,
+        heartbeat_age_secs: None,
+        detail,
+    }
+}
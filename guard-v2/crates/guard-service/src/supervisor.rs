@@ -0,0 +1,400 @@
+//! Subsystem supervisor.
+//!
+//! Long-running tasks (watcher pipeline, audit loop, tamper consumer) are
+//! spawned through the supervisor instead of bare `tokio::spawn`. Each task
+//! receives a `Heartbeat` it must beat while healthy. The supervisor:
+//!
+//!  * restarts a task that exits, panics, or stops beating
+//!  * backs off exponentially between restarts (1 s → 60 s)
+//!  * logs a SUBSYSTEM_RESTARTED event for every restart
+//!  * gives up after too many failures inside the window, logs
+//!    SUBSYSTEM_FAILED and fires the escalation hook (SafeMode)
+
+use chrono::{DateTime, Utc};
+use guard_core::event_log::{EventLog, EventSeverity};
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+// ── Heartbeat ───────────────────────────────────────────────────────────────
+
+/// Liveness token handed to a supervised task.
+#[derive(Clone)]
+pub struct Heartbeat {
+    last: Arc<Mutex<Instant>>,
+}
+
+impl Heartbeat {
+    pub(crate) fn new() -> Self {
+        Self {
+            last: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    pub fn beat(&self) {
+        *self.last.lock() = Instant::now();
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.last.lock().elapsed()
+    }
+}
+
+// ── Policy / health ─────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone)]
+pub struct RestartPolicy {
+    /// Failures tolerated inside `window` before giving up.
+    pub max_restarts: usize,
+    pub window: Duration,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// How often the supervisor checks task liveness.
+    pub check_interval: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_restarts: 5,
+            window: Duration::from_secs(600),
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            check_interval: Duration::from_secs(5),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SubsystemState {
+    Running,
+    Restarting,
+    Failed,
+    Stopped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SubsystemHealth {
+    pub name: String,
+    pub state: SubsystemState,
+    pub restarts: u32,
+    pub started_at: DateTime<Utc>,
+    pub last_failure: Option<String>,
+    /// Seconds since the task last beat its heartbeat.
+    pub heartbeat_age_secs: u64,
+}
+
+struct SubsystemRecord {
+    health: SubsystemHealth,
+    heartbeat: Heartbeat,
+    failures: VecDeque<Instant>,
+}
+
+type EscalationHook = Arc<dyn Fn(&str) + Send + Sync>;
+
+// ── Supervisor ──────────────────────────────────────────────────────────────
+
+pub struct Supervisor {
+    policy: RestartPolicy,
+    event_log: Arc<EventLog>,
+    subsystems: Mutex<HashMap<String, SubsystemRecord>>,
+    on_give_up: RwLock<Option<EscalationHook>>,
+}
+
+impl Supervisor {
+    pub fn new(event_log: Arc<EventLog>, policy: RestartPolicy) -> Arc<Self> {
+        Arc::new(Self {
+            policy,
+            event_log,
+            subsystems: Mutex::new(HashMap::new()),
+            on_give_up: RwLock::new(None),
+        })
+    }
+
+    /// Register the hook fired when a subsystem exhausts its restart budget.
+    pub fn on_give_up<F>(&self, hook: F)
+    where
+        F: Fn(&str) + Send + Sync + 'static,
+    {
+        *self.on_give_up.write() = Some(Arc::new(hook));
+    }
+
+    /// Snapshot of every supervised subsystem, sorted by name.
+    #[allow(dead_code)]
+    pub fn health(&self) -> Vec<SubsystemHealth> {
+        let subsystems = self.subsystems.lock();
+        let mut out: Vec<SubsystemHealth> = subsystems
+            .values()
+            .map(|r| {
+                let mut h = r.health.clone();
+                h.heartbeat_age_secs = r.heartbeat.elapsed().as_secs();
+                h
+            })
+            .collect();
+        out.sort_by(|a, b| a.name.cmp(&b.name));
+        out
+    }
+
+    /// Spawn `spawn_fn` under supervision.
+    ///
+    /// `spawn_fn` is called once per (re)start with a fresh `Heartbeat`. When
+    /// `stale_after` is set, a task that hasn't beaten for that long is
+    /// aborted and treated as failed.
+    pub fn supervise<F>(
+        self: &Arc<Self>,
+        name: &str,
+        stale_after: Option<Duration>,
+        mut spawn_fn: F,
+        mut shutdown: watch::Receiver<bool>,
+    ) -> JoinHandle<()>
+    where
+        F: FnMut(Heartbeat) -> JoinHandle<()> + Send + 'static,
+    {
+        let this = self.clone();
+        let name = name.to_string();
+        tokio::spawn(async move {
+            let mut attempt: u32 = 0;
+            loop {
+                let heartbeat = this.register_start(&name);
+                let mut task = spawn_fn(heartbeat.clone());
+
+                let failure = loop {
+                    tokio::select! {
+                        res = &mut task => {
+                            break match res {
+                                Ok(()) => "task exited".to_string(),
+                                Err(e) if e.is_panic() => "task panicked".to_string(),
+                                Err(e) => format!("task aborted: {e}"),
+                            };
+                        }
+                        _ = tokio::time::sleep(this.policy.check_interval) => {
+                            if let Some(limit) = stale_after {
+                                if heartbeat.elapsed() > limit {
+                                    task.abort();
+                                    break format!(
+                                        "heartbeat stale for {}s",
+                                        heartbeat.elapsed().as_secs()
+                                    );
+                                }
+                            }
+                        }
+                        _ = shutdown.changed() => {
+                            if *shutdown.borrow() {
+                                task.abort();
+                                this.set_state(&name, SubsystemState::Stopped);
+                                return;
+                            }
+                        }
+                    }
+                };
+
+                if *shutdown.borrow() {
+                    this.set_state(&name, SubsystemState::Stopped);
+                    return;
+                }
+
+                attempt += 1;
+                if !this.record_failure(&name, &failure) {
+                    this.give_up(&name, &failure);
+                    return;
+                }
+
+                let backoff = this.backoff_for(attempt);
+                warn!(subsystem = %name, reason = %failure, attempt, "subsystem failed, restarting");
+                let _ = this.event_log.append(
+                    "SUBSYSTEM_RESTARTED",
+                    EventSeverity::Warn,
+                    serde_json::json!({
+                        "subsystem": name,
+                        "reason": failure,
+                        "attempt": attempt,
+                        "backoff_ms": backoff.as_millis() as u64,
+                    }),
+                );
+
+                tokio::select! {
+                    _ = tokio::time::sleep(backoff) => {}
+                    _ = shutdown.changed() => {
+                        if *shutdown.borrow() {
+                            this.set_state(&name, SubsystemState::Stopped);
+                            return;
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    // ── internals ───────────────────────────────────────────────────────
+
+    fn register_start(&self, name: &str) -> Heartbeat {
+        let mut subsystems = self.subsystems.lock();
+        let record = subsystems
+            .entry(name.to_string())
+            .or_insert_with(|| SubsystemRecord {
+                health: SubsystemHealth {
+                    name: name.to_string(),
+                    state: SubsystemState::Running,
+                    restarts: 0,
+                    started_at: Utc::now(),
+                    last_failure: None,
+                    heartbeat_age_secs: 0,
+                },
+                heartbeat: Heartbeat::new(),
+                failures: VecDeque::new(),
+            });
+        record.heartbeat = Heartbeat::new();
+        record.health.state = SubsystemState::Running;
+        record.health.started_at = Utc::now();
+        info!(subsystem = %name, "subsystem started");
+        record.heartbeat.clone()
+    }
+
+    fn set_state(&self, name: &str, state: SubsystemState) {
+        if let Some(record) = self.subsystems.lock().get_mut(name) {
+            record.health.state = state;
+        }
+    }
+
+    /// Record a failure; returns `false` once the restart budget is spent.
+    fn record_failure(&self, name: &str, reason: &str) -> bool {
+        let mut subsystems = self.subsystems.lock();
+        let Some(record) = subsystems.get_mut(name) else {
+            return false;
+        };
+        let now = Instant::now();
+        record.failures.push_back(now);
+        while let Some(first) = record.failures.front() {
+            if now.duration_since(*first) > self.policy.window {
+                record.failures.pop_front();
+            } else {
+                break;
+            }
+        }
+        record.health.last_failure = Some(reason.to_string());
+        if record.failures.len() > self.policy.max_restarts {
+            record.health.state = SubsystemState::Failed;
+            return false;
+        }
+        record.health.state = SubsystemState::Restarting;
+        record.health.restarts += 1;
+        true
+    }
+
+    fn backoff_for(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.policy
+            .initial_backoff
+            .saturating_mul(factor)
+            .min(self.policy.max_backoff)
+    }
+
+    fn give_up(&self, name: &str, reason: &str) {
+        error!(subsystem = %name, reason = %reason, "subsystem exceeded restart budget");
+        let _ = self.event_log.append(
+            "SUBSYSTEM_FAILED",
+            EventSeverity::Critical,
+            serde_json::json!({
+                "subsystem": name,
+                "reason": reason,
+                "max_restarts": self.policy.max_restarts,
+                "window_secs": self.policy.window.as_secs(),
+            }),
+        );
+        let hook = self.on_give_up.read().clone();
+        if let Some(hook) = hook {
+            hook(name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::SigningKey;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tempfile::tempdir;
+
+    fn fast_policy() -> RestartPolicy {
+        RestartPolicy {
+            max_restarts: 2,
+            window: Duration::from_secs(60),
+            initial_backoff: Duration::from_millis(5),
+            max_backoff: Duration::from_millis(20),
+            check_interval: Duration::from_millis(10),
+        }
+    }
+
+    #[tokio::test]
+    async fn crashing_task_is_restarted_then_escalated() {
+        let dir = tempdir().unwrap();
+        let signer = SigningKey::generate(&mut rand::rngs::OsRng);
+        let log = Arc::new(EventLog::new(dir.path().join("events.log"), signer, 1 << 20).unwrap());
+        let supervisor = Supervisor::new(log.clone(), fast_policy());
+
+        let gave_up = Arc::new(AtomicUsize::new(0));
+        let g = gave_up.clone();
+        supervisor.on_give_up(move |_| {
+            g.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let starts = Arc::new(AtomicUsize::new(0));
+        let s = starts.clone();
+        let (_tx, rx) = watch::channel(false);
+        let handle = supervisor.supervise(
+            "crashy",
+            None,
+            move |_hb| {
+                s.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async { panic!("boom") })
+            },
+            rx,
+        );
+        handle.await.unwrap();
+
+        assert_eq!(starts.load(Ordering::SeqCst), 3);
+        assert_eq!(gave_up.load(Ordering::SeqCst), 1);
+        let health = supervisor.health();
+        assert_eq!(health[0].state, SubsystemState::Failed);
+        assert_eq!(health[0].restarts, 2);
+
+        let events = log.read_recent(None, None).unwrap();
+        let restarted = events
+            .iter()
+            .filter(|e| e.event_type == "SUBSYSTEM_RESTARTED")
+            .count();
+        assert_eq!(restarted, 2);
+        assert_eq!(events[0].event_type, "SUBSYSTEM_FAILED");
+    }
+
+    #[tokio::test]
+    async fn stale_heartbeat_triggers_restart() {
+        let dir = tempdir().unwrap();
+        let signer = SigningKey::generate(&mut rand::rngs::OsRng);
+        let log = Arc::new(EventLog::new(dir.path().join("events.log"), signer, 1 << 20).unwrap());
+        let supervisor = Supervisor::new(log.clone(), fast_policy());
+
+        let (tx, rx) = watch::channel(false);
+        let handle = supervisor.supervise(
+            "hung",
+            Some(Duration::from_millis(30)),
+            |_hb| tokio::spawn(std::future::pending::<()>()),
+            rx,
+        );
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        let _ = tx.send(true);
+        handle.await.unwrap();
+
+        let events = log.read_recent(None, None).unwrap();
+        assert!(events
+            .iter()
+            .any(|e| e.event_type == "SUBSYSTEM_RESTARTED"
+                && e.data["reason"].as_str().unwrap().contains("heartbeat stale")));
+    }
+}
@@ -0,0 +1,478 @@
+//! Service-side update orchestration.
+//!
+//! When `updates.auto_update` is on, a background task asks the platform for
+//! the newest release on the configured channel, has updater-helper download
+//! and verify it, and installs it in the configured install window. The
+//! install runs under engine maintenance mode and ends with a rebaseline, so
+//! the new binaries are not restored away. Each step is recorded as an
+//! `UPDATE_*` event; headless devices update without the desktop app.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use guard_core::event_log::EventSeverity;
+use guard_core::settings::UpdateSettings;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::engine::baseline_operator_key;
+use crate::service_state::ServiceState;
+
+/// How often the task wakes to check schedules.
+const TICK: Duration = Duration::from_secs(300);
+
+/// Maintenance timeout while an install runs.
+const INSTALL_MAINTENANCE_SECS: u64 = 30 * 60;
+
+/// A release the platform offers for this platform and channel.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UpdateOffer {
+    pub version: String,
+    pub notes: Option<String>,
+    pub download_url: String,
+    pub sha256: String,
+    /// base64 ed25519 signature checked by updater-helper.
+    pub signature: String,
+    pub force: bool,
+}
+
+#[derive(Debug, Clone)]
+struct StagedUpdate {
+    offer: UpdateOffer,
+    package_path: PathBuf,
+    manifest_path: PathBuf,
+}
+
+pub fn platform_name() -> &'static str {
+    if cfg!(target_os = "windows") {
+        "windows"
+    } else if cfg!(target_os = "macos") {
+        "macos"
+    } else {
+        "linux"
+    }
+}
+
+/// Read the platform's update manifest. `Ok(None)` means no update; an
+/// offer without a hash and signature is refused rather than staged.
+pub fn parse_offer(body: &serde_json::Value, platform: &str) -> Result<Option<UpdateOffer>> {
+    let Some(version) = body.get("version").and_then(|v| v.as_str()) else {
+        return Ok(None);
+    };
+    let entry = body
+        .get("platforms")
+        .and_then(|p| p.get(platform))
+        .ok_or_else(|| anyhow!("release {version} has no {platform} package"))?;
+    let field = |name: &str| {
+        entry
+            .get(name)
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("release {version} is missing {platform}.{name}"))
+    };
+    Ok(Some(UpdateOffer {
+        version: version.to_string(),
+        notes: body.get("notes").and_then(|n| n.as_str()).map(str::to_string),
+        download_url: field("url")?,
+        sha256: field("sha256")?,
+        signature: field("signature")?,
+        force: body.get("force").and_then(|f| f.as_bool()).unwrap_or(false),
+    }))
+}
+
+/// Compare dotted numeric versions, ignoring any `-suffix`.
+pub fn is_newer(candidate: &str, installed: &str) -> bool {
+    let parts = |v: &str| -> Vec<u64> {
+        v.trim_start_matches('v')
+            .split('-')
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|p| p.parse().unwrap_or(0))
+            .collect()
+    };
+    parts(candidate) > parts(installed)
+}
+
+pub fn run_updater(updater_path: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new(updater_path).args(args).output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("updater failed: {}", stderr));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn platform_url(settings: &UpdateSettings) -> Option<String> {
+    settings
+        .platform_url
+        .clone()
+        .or_else(|| std::env::var("DARKLOCK_PLATFORM_URL").ok())
+        .map(|u| u.trim_end_matches('/').to_string())
+        .filter(|u| !u.is_empty())
+}
+
+fn log(state: &Arc<Mutex<ServiceState>>, event: &str, severity: EventSeverity, data: serde_json::Value) {
+    state.lock().event_log.append(event, severity, data).ok();
+}
+
+struct Orchestrator {
+    updater_path: PathBuf,
+    install_dir: PathBuf,
+    last_check: Option<DateTime<Utc>>,
+    staged: Option<StagedUpdate>,
+    /// Version whose install deferral was already logged.
+    deferral_logged: Option<String>,
+}
+
+impl Orchestrator {
+    async fn tick(&mut self, state: &Arc<Mutex<ServiceState>>) {
+        let settings = state.lock().engine.settings().updates;
+        if !settings.auto_update {
+            return;
+        }
+        let now = Utc::now();
+        let due = self.last_check.is_none_or(|last| {
+            now - last >= ChronoDuration::hours(i64::from(settings.check_interval_hours))
+        });
+        if due {
+            self.last_check = Some(now);
+            if let Err(e) = self.check_and_stage(state, &settings).await {
+                warn!(error = %e, "update check failed");
+                log(
+                    state,
+                    "UPDATE_FAILED",
+                    EventSeverity::Warn,
+                    serde_json::json!({"phase": "check", "error": e.to_string()}),
+                );
+            }
+        }
+
+        let Some(staged) = self.staged.clone() else {
+            return;
+        };
+        let in_window = settings.install_window.is_some_and(|w| w.contains(now));
+        if !in_window && !staged.offer.force {
+            return;
+        }
+        if let Err(e) = self.install(state, &settings, &staged).await {
+            warn!(error = %e, version = %staged.offer.version, "update install failed");
+            log(
+                state,
+                "UPDATE_FAILED",
+                EventSeverity::Error,
+                serde_json::json!({
+                    "phase": "install",
+                    "version": staged.offer.version,
+                    "error": e.to_string(),
+                }),
+            );
+            // Re-stage on the next check rather than retrying a bad package.
+            self.staged = None;
+        }
+    }
+
+    async fn check_and_stage(
+        &mut self,
+        state: &Arc<Mutex<ServiceState>>,
+        settings: &UpdateSettings,
+    ) -> Result<()> {
+        let Some(base) = platform_url(settings) else {
+            debug!("auto-update on but no platform URL configured");
+            return Ok(());
+        };
+        let installed = state.lock().vault.payload.state.installed_version.clone();
+        let url = format!(
+            "{base}/platform/api/updates/{}/{installed}?channel={}",
+            platform_name(),
+            settings.channel
+        );
+        let resp = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()?
+            .get(&url)
+            .send()
+            .await?;
+        let offer = if resp.status() == reqwest::StatusCode::NO_CONTENT {
+            None
+        } else {
+            let body: serde_json::Value = resp.error_for_status()?.json().await?;
+            parse_offer(&body, platform_name())?
+        }
+        .filter(|o| is_newer(&o.version, &installed));
+
+        {
+            let mut guard = state.lock();
+            guard.update_available = offer.is_some();
+            guard.vault.payload.state.last_update_check = Some(Utc::now());
+            let password = guard.password.clone();
+            guard.vault.save(&password)?;
+            guard.event_log.append(
+                "UPDATE_CHECKED",
+                EventSeverity::Info,
+                serde_json::json!({
+                    "channel": settings.channel,
+                    "installed": installed,
+                    "available": offer.as_ref().map(|o| &o.version),
+                }),
+            )?;
+        }
+        let Some(offer) = offer else {
+            return Ok(());
+        };
+        if self
+            .staged
+            .as_ref()
+            .is_some_and(|s| s.offer.version == offer.version)
+        {
+            return Ok(());
+        }
+
+        let dir = state.lock().data_dir.join("updates");
+        std::fs::create_dir_all(&dir)?;
+        let manifest_path = dir.join(format!("manifest-{}.json", offer.version));
+        let package_path = dir.join(format!("package-{}.tar.gz", offer.version));
+        std::fs::write(
+            &manifest_path,
+            serde_json::to_vec_pretty(&serde_json::json!({
+                "version": offer.version,
+                "download_url": offer.download_url,
+                "sha256": offer.sha256,
+                "signature": offer.signature,
+            }))?,
+        )?;
+        let updater = self.updater_path.clone();
+        let (manifest_arg, package_arg) = (
+            manifest_path.display().to_string(),
+            package_path.display().to_string(),
+        );
+        tokio::task::spawn_blocking(move || {
+            run_updater(
+                &updater,
+                &["stage", "--manifest", &manifest_arg, "--output", &package_arg],
+            )
+        })
+        .await??;
+
+        info!(version = %offer.version, "update staged");
+        log(
+            state,
+            "UPDATE_STAGED",
+            EventSeverity::Info,
+            serde_json::json!({
+                "version": offer.version,
+                "force": offer.force,
+                "notes": offer.notes,
+                "install_window": settings.install_window,
+            }),
+        );
+        self.staged = Some(StagedUpdate {
+            offer,
+            package_path,
+            manifest_path,
+        });
+        Ok(())
+    }
+
+    async fn install(
+        &mut self,
+        state: &Arc<Mutex<ServiceState>>,
+        settings: &UpdateSettings,
+        staged: &StagedUpdate,
+    ) -> Result<()> {
+        let version = staged.offer.version.clone();
+        {
+            let guard = state.lock();
+            let reason = format!("installing update {version}");
+            if let Err(e) =
+                guard
+                    .engine
+                    .enter_maintenance(reason, INSTALL_MAINTENANCE_SECS, &guard.event_log)
+            {
+                // Safe mode or an operator's maintenance window: try again
+                // next tick, logging the deferral once.
+                if self.deferral_logged.as_deref() != Some(version.as_str()) {
+                    self.deferral_logged = Some(version.clone());
+                    guard.event_log.append(
+                        "UPDATE_INSTALL_DEFERRED",
+                        EventSeverity::Warn,
+                        serde_json::json!({"version": version, "reason": e.to_string()}),
+                    )?;
+                }
+                return Ok(());
+            }
+            guard.event_log.append(
+                "UPDATE_INSTALL_STARTED",
+                EventSeverity::Warn,
+                serde_json::json!({"version": version, "forced": staged.offer.force}),
+            )?;
+        }
+
+        let updater = self.updater_path.clone();
+        let install_dir = self.install_dir.display().to_string();
+        let backup_dir = state.lock().data_dir.join("rollback").display().to_string();
+        let package = staged.package_path.display().to_string();
+        let version_file = self.install_dir.join("version.json").display().to_string();
+        let installed = tokio::task::spawn_blocking(move || {
+            run_updater(
+                &updater,
+                &[
+                    "install",
+                    "--package",
+                    &package,
+                    "--install-dir",
+                    &install_dir,
+                    "--backup-dir",
+                    &backup_dir,
+                    "--version-file",
+                    &version_file,
+                ],
+            )
+        })
+        .await?;
+
+        let mut guard = state.lock();
+        let st = &mut *guard;
+        let backup_manifest = match installed {
+            Ok(out) => out.trim().to_string(),
+            Err(e) => {
+                let mut store = st.backup_store.lock();
+                st.engine.exit_maintenance(
+                    false,
+                    None,
+                    &st.signing_key,
+                    &st.baseline_path,
+                    &mut store,
+                    &st.event_log,
+                    &st.data_dir,
+                    false,
+                    None,
+                )?;
+                return Err(e);
+            }
+        };
+
+        // The new binaries become the baseline before enforcement resumes.
+        let exit = {
+            let operator_key = baseline_operator_key(&st.vault)?;
+            let mut store = st.backup_store.lock();
+            st.engine.exit_maintenance(
+                true,
+                st.scanner.as_ref(),
+                &st.signing_key,
+                &st.baseline_path,
+                &mut store,
+                &st.event_log,
+                &st.data_dir,
+                false,
+                operator_key.as_ref(),
+            )?
+        };
+        if let Some(baseline) = exit.baseline {
+            *st.live_baseline.lock() = Some(baseline);
+        }
+        st.update_available = false;
+        st.vault.payload.state.installed_version = version.clone();
+        let password = st.password.clone();
+        st.vault.save(&password)?;
+        st.event_log.append(
+            "UPDATE_INSTALLED",
+            EventSeverity::Warn,
+            serde_json::json!({
+                "version": version,
+                "backup_manifest": backup_manifest,
+                "changes": exit.journal.len(),
+                "restart": settings.restart_cmd.is_some(),
+            }),
+        )?;
+        info!(version = %version, "update installed");
+        let _ = std::fs::remove_file(&staged.package_path);
+        let _ = std::fs::remove_file(&staged.manifest_path);
+        self.staged = None;
+        drop(guard);
+
+        if let Some(cmd) = &settings.restart_cmd {
+            let mut parts = cmd.split_whitespace();
+            if let Some(prog) = parts.next() {
+                Command::new(prog).args(parts).spawn()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Spawn the auto-update task. It does nothing while `updates.auto_update`
+/// is off or no platform URL is configured.
+pub(crate) fn spawn_update_task(
+    state: Arc<Mutex<ServiceState>>,
+    updater_path: PathBuf,
+    install_dir: PathBuf,
+    mut shutdown: watch::Receiver<bool>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut orchestrator = Orchestrator {
+            updater_path,
+            install_dir,
+            last_check: None,
+            staged: None,
+            deferral_logged: None,
+        };
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(TICK) => orchestrator.tick(&state).await,
+                _ = shutdown.changed() => {
+                    if *shutdown.borrow() { return; }
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use guard_core::settings::MaintenanceWindow;
+
+    #[test]
+    fn offers_need_a_hash_and_signature() {
+        let body = serde_json::json!({
+            "version": "2.1.0",
+            "force": true,
+            "platforms": {"linux": {"url": "https://x/p.tar.gz", "sha256": "ab", "signature": "c2ln"}},
+        });
+        let offer = parse_offer(&body, "linux").unwrap().unwrap();
+        assert_eq!(offer.version, "2.1.0");
+        assert!(offer.force);
+
+        let unsigned = serde_json::json!({
+            "version": "2.1.0",
+            "platforms": {"linux": {"url": "https://x/p.tar.gz"}},
+        });
+        assert!(parse_offer(&unsigned, "linux").is_err());
+        assert!(parse_offer(&body, "windows").is_err());
+        assert!(parse_offer(&serde_json::json!({}), "linux").unwrap().is_none());
+    }
+
+    #[test]
+    fn version_ordering_and_windows() {
+        assert!(is_newer("2.10.0", "2.9.3"));
+        assert!(is_newer("v1.0.1", "1.0.0"));
+        assert!(!is_newer("1.0.0-beta", "1.0.0"));
+        assert!(!is_newer("1.0.0", "1.0.0"));
+
+        let at = |h| Utc.with_ymd_and_hms(2026, 1, 1, h, 30, 0).unwrap();
+        let night = MaintenanceWindow { start_hour: 22, end_hour: 4 };
+        assert!(night.contains(at(23)) && night.contains(at(3)));
+        assert!(!night.contains(at(4)) && !night.contains(at(12)));
+        let day = MaintenanceWindow { start_hour: 2, end_hour: 5 };
+        assert!(day.contains(at(2)) && !day.contains(at(5)));
+    }
+}
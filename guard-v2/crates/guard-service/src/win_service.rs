@@ -0,0 +1,80 @@
+//! Running under the Windows service control manager.
+//!
+//! When the SCM starts `guard-service run`, the process has to connect to
+//! it through the service dispatcher, report itself running and answer the
+//! stop control; a stop then ends the service's wait through
+//! [`request_shutdown`] like a signal would. Started from a console the
+//! dispatcher can't connect and the service runs as usual.
+
+use crate::shutdown::request_shutdown;
+use crate::{serve, Cli};
+use anyhow::Result;
+use clap::Parser;
+use std::ffi::OsString;
+use std::time::Duration;
+use tracing::error;
+use windows_service::service::{
+    ServiceControl, ServiceControlAccept, ServiceExitCode, ServiceState, ServiceStatus,
+    ServiceType,
+};
+use windows_service::service_control_handler::{self, ServiceControlHandlerResult};
+use windows_service::{define_windows_service, service_dispatcher};
+use windows_sys::Win32::Foundation::ERROR_FAILED_SERVICE_CONTROLLER_CONNECT;
+
+/// Ignored by the SCM for a service in its own process.
+const SERVICE_NAME: &str = "DarklockGuard";
+
+define_windows_service!(ffi_service_main, service_main);
+
+/// Hand the process to the SCM if it started us, returning once the service
+/// has stopped. `false` when there is no SCM to connect to.
+pub fn run_if_started_by_scm() -> Result<bool> {
+    match service_dispatcher::start(SERVICE_NAME, ffi_service_main) {
+        Ok(()) => Ok(true),
+        Err(windows_service::Error::Winapi(e))
+            if e.raw_os_error() == Some(ERROR_FAILED_SERVICE_CONTROLLER_CONNECT as i32) =>
+        {
+            Ok(false)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn service_main(_arguments: Vec<OsString>) {
+    if let Err(e) = run_service() {
+        error!("service stopped with an error: {e:#}");
+    }
+}
+
+fn run_service() -> Result<()> {
+    let status = service_control_handler::register(SERVICE_NAME, |control| match control {
+        ServiceControl::Stop | ServiceControl::Shutdown => {
+            request_shutdown();
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+        _ => ServiceControlHandlerResult::NotImplemented,
+    })?;
+    let report = |current_state: ServiceState, exit_code: ServiceExitCode| ServiceStatus {
+        service_type: ServiceType::OWN_PROCESS,
+        current_state,
+        controls_accepted: match current_state {
+            ServiceState::Running => ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+            _ => ServiceControlAccept::empty(),
+        },
+        exit_code,
+        checkpoint: 0,
+        wait_hint: Duration::default(),
+        process_id: None,
+    };
+    status.set_service_status(report(ServiceState::Running, ServiceExitCode::Win32(0)))?;
+    // The SCM passes its own start arguments; the command line is the
+    // service's configured `run` invocation.
+    let result = serve(Cli::parse());
+    let exit_code = match &result {
+        Ok(()) => ServiceExitCode::Win32(0),
+        Err(_) => ServiceExitCode::ServiceSpecific(1),
+    };
+    status.set_service_status(report(ServiceState::Stopped, exit_code))?;
+    result
+}
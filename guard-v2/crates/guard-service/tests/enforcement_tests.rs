@@ -9,17 +9,51 @@
 //!  6. Baseline signature verification
 //!  7. Quarantine on persistent failure
 //!  8. Maintenance mode enter/exit with rebaseline
+//!  9. Compressed blob round-trip
+//! 10. Widespread tampering restored from a copy-on-write snapshot
+//! 11. Ransomware burst response: alert + write freeze
+//! 12. Temporary path exclusions: scoping, enforcement and expiry
+//! 13. Maintenance change journal on exit with rebaseline
+//! 14. Dual-control operator key changes need the current key's approval
+//! 15. Restores deferred by a locked target are applied at the next start
+//! 16. Symlink swaps are detected and undone under the protect-link policy
+//! 17. Enforcement rules: dry-run, then quarantine → restore → safe mode
+//! 18. Backup store GC and verify-on-read re-fetch from intact sources
+//! 19. Attack-simulation self-test over a sandboxed copy of a protected path
+//! 20. Ownership / extended-attribute changes restored; v1 baselines migrated
+//! 21. Rebaseline on maintenance exit is validated before it is committed
+//! 22. Quarantine evidence export / import with a chain-of-custody manifest
+//! 23. Flapping paths are suppressed and summarised
+//! 24. Hashing in the confined scan worker; a dead worker fails closed
 
 use chrono::Utc;
 use ed25519_dalek::SigningKey;
 use guard_core::backup_store::BackupStore;
+use guard_core::event_log::{EventLog, EventQuery};
+use guard_core::event_log::EventSeverity;
+use guard_core::exclusion::PathExclusion;
+use guard_core::maintenance::{ChangeKind, MaintenanceJournal};
+use guard_core::settings::{EnforcementAction, EnforcementRule};
+use guard_core::storage::{load_baseline_operator_key, save_exclusions};
+use guard_core::vault::{SecurityProfile, Vault};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tempfile::tempdir;
 
+use guard_service::enforcement::pending::{PendingRestore, PendingRestores};
 use guard_service::enforcement::quarantine::QuarantineZone;
 use guard_service::enforcement::restore::{RestoreEngine, RestoreOutcome};
+use guard_service::enforcement::snapshot::{
+    SnapshotBackend, SnapshotKind, SnapshotManager, SnapshotRecord, SnapshotView,
+};
+use guard_service::enforcement::write_freeze::WriteFreeze;
+use guard_service::engine::{operator_key_change_message, Engine, EngineMode};
+use guard_service::integrity::burst::BurstReport;
+use guard_service::integrity::pipeline::TamperEvent;
+use guard_service::integrity::sandbox::ScanWorker;
 use guard_service::integrity::scanner::{BaselineEntry, IntegrityScanner};
+use guard_service::selftest::run_self_test;
 
 /// Helper: create a test file and return its (path, blake3 hash, permissions).
 fn create_test_file(dir: &std::path::Path, name: &str, content: &[u8]) -> (PathBuf, String, u32) {
@@ -66,6 +100,10 @@ fn test_deleted_file_is_restored() {
         size: b"critical data".len() as u64,
         modified: Utc::now(),
         permissions: perms,
+        link_target: None,
+        quick_hash: None,
+        ownership: None,
+        xattrs: None,
     };
 
     // Delete the file
@@ -107,6 +145,10 @@ fn test_modified_file_is_restored() {
         size: b"original content".len() as u64,
         modified: Utc::now(),
         permissions: perms,
+        link_target: None,
+        quick_hash: None,
+        ownership: None,
+        xattrs: None,
     };
 
     // Tamper with the file
@@ -147,6 +189,10 @@ fn test_rename_storm_multiple_restores() {
             size: content.len() as u64,
             modified: Utc::now(),
             permissions: perms,
+            link_target: None,
+            quick_hash: None,
+            ownership: None,
+            xattrs: None,
         }));
     }
 
@@ -188,6 +234,10 @@ fn test_restore_loop_suppression() {
         size: b"loop content".len() as u64,
         modified: Utc::now(),
         permissions: perms,
+        link_target: None,
+        quick_hash: None,
+        ownership: None,
+        xattrs: None,
     };
 
     let qz = QuarantineZone::new(dir.path().join("quarantine")).unwrap();
@@ -354,3 +404,1280 @@ fn test_compressed_blob_roundtrip() {
     assert_eq!(data.len(), content.len());
     assert_eq!(String::from_utf8(data).unwrap(), content);
 }
+
+// ─── Test 10: Snapshot restore of widespread tampering ──────────────────────
+
+/// Stand-in for a CoW filesystem: "snapshots" are plain directory copies.
+struct CopyBackend {
+    store: PathBuf,
+}
+
+fn copy_tree(from: &Path, to: &Path) {
+    fs::create_dir_all(to).unwrap();
+    for entry in fs::read_dir(from).unwrap().flatten() {
+        let dest = to.join(entry.file_name());
+        if entry.file_type().unwrap().is_dir() {
+            copy_tree(&entry.path(), &dest);
+        } else {
+            fs::copy(entry.path(), dest).unwrap();
+        }
+    }
+}
+
+impl SnapshotBackend for CopyBackend {
+    fn kind(&self) -> SnapshotKind {
+        SnapshotKind::Copy
+    }
+
+    fn create(&self, source: &Path, label: &str) -> anyhow::Result<SnapshotRecord> {
+        let dest = self.store.join(label);
+        copy_tree(source, &dest);
+        Ok(SnapshotRecord {
+            kind: SnapshotKind::Copy,
+            source: source.to_path_buf(),
+            id: label.to_string(),
+            view_path: dest,
+            label: label.to_string(),
+            created_at: Utc::now(),
+        })
+    }
+
+    fn open(&self, record: &SnapshotRecord) -> anyhow::Result<SnapshotView> {
+        Ok(SnapshotView::new(record.view_path.clone()))
+    }
+
+    fn delete(&self, record: &SnapshotRecord) -> anyhow::Result<()> {
+        fs::remove_dir_all(&record.view_path)?;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_widespread_tampering_restored_from_snapshot() {
+    let dir = tempdir().unwrap();
+    let protected_dir = dir.path().join("protected");
+    fs::create_dir_all(protected_dir.join("sub")).unwrap();
+    for i in 0..5 {
+        create_test_file(&protected_dir, &format!("doc{i}.txt"), format!("doc {i}").as_bytes());
+    }
+    create_test_file(&protected_dir.join("sub"), "nested.txt", b"nested");
+
+    let mut vault = Vault::create_new(dir.path().join("vault.dat"), "pw").unwrap();
+    let engine = Engine::load_from_vault(&vault).unwrap();
+    let mut settings = engine.settings();
+    settings.protection.protected_paths = vec![protected_dir.display().to_string()];
+    settings.snapshots.widespread_threshold = 3;
+    engine.update_settings(&mut vault, settings).unwrap();
+
+    let store = dir.path().join("snapshots");
+    let manager = SnapshotManager::with_resolver(dir.path().join("snapshots.json"), 2, move |_| {
+        Some(Arc::new(CopyBackend { store: store.clone() }) as Arc<dyn SnapshotBackend>)
+    })
+    .unwrap();
+    engine.set_snapshot_manager(Some(Arc::new(manager)));
+
+    let sk = signing_key();
+    let event_log = EventLog::new(dir.path().join("events.log"), sk.clone(), 1 << 20).unwrap();
+    let scanner = IntegrityScanner::new(vec![protected_dir.clone()], "test-device".into());
+    let baseline = scanner.generate_baseline(&sk).unwrap();
+    engine.snapshot_protected_paths(&event_log);
+    assert_eq!(engine.snapshot_manager().unwrap().records().len(), 1);
+
+    // "Encrypt" most of the tree and delete a nested file.
+    for i in 0..4 {
+        fs::write(protected_dir.join(format!("doc{i}.txt")), b"ENCRYPTED").unwrap();
+    }
+    fs::remove_file(protected_dir.join("sub/nested.txt")).unwrap();
+    fs::write(protected_dir.join("README_RANSOM.txt"), b"pay up").unwrap();
+
+    let result = scanner.scan_against_baseline(&baseline);
+    assert_eq!(result.modified.len() + result.removed.len(), 5);
+
+    let quarantine = QuarantineZone::new(dir.path().join("quarantine")).unwrap();
+    let restore_engine = RestoreEngine::new(quarantine);
+    // Empty backup store: only the snapshot can bring the files back.
+    let backups = BackupStore::load_or_create(dir.path().join("backups"), sk, "test-device").unwrap();
+    engine.handle_scan_result(&result, &restore_engine, &backups, &baseline, &event_log);
+
+    for i in 0..5 {
+        let content = fs::read_to_string(protected_dir.join(format!("doc{i}.txt"))).unwrap();
+        assert_eq!(content, format!("doc {i}"));
+    }
+    assert_eq!(fs::read(protected_dir.join("sub/nested.txt")).unwrap(), b"nested");
+    // Files created after the snapshot are left for the scanner to report.
+    assert!(protected_dir.join("README_RANSOM.txt").exists());
+    assert!(scanner.scan_against_baseline(&baseline).modified.is_empty());
+
+    let page = event_log
+        .search(&EventQuery {
+            event_types: vec!["SNAPSHOT_RESTORE".into()],
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(page.events.len(), 1);
+    assert_eq!(page.events[0].data["restored"], 5);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_snapshot_restore_never_writes_through_symlinks() {
+    use std::os::unix::fs::symlink;
+
+    let dir = tempdir().unwrap();
+    let protected_dir = dir.path().join("protected");
+    fs::create_dir_all(&protected_dir).unwrap();
+    create_test_file(&protected_dir, "a.txt", b"a");
+    create_test_file(&protected_dir, "b.txt", b"b");
+    let victim = dir.path().join("victim");
+    fs::write(&victim, b"root-owned").unwrap();
+
+    let store = dir.path().join("snapshots");
+    let manager = SnapshotManager::with_resolver(dir.path().join("snapshots.json"), 2, move |_| {
+        Some(Arc::new(CopyBackend { store: store.clone() }) as Arc<dyn SnapshotBackend>)
+    })
+    .unwrap();
+    let results = manager.snapshot_paths(std::slice::from_ref(&protected_dir), "s1");
+    assert!(results[0].1.is_ok());
+
+    // A link at the target, and one at the staging name restores used to use.
+    fs::remove_file(protected_dir.join("a.txt")).unwrap();
+    symlink(&victim, protected_dir.join("a.txt")).unwrap();
+    fs::write(protected_dir.join("b.txt"), b"ENCRYPTED").unwrap();
+    symlink(&victim, protected_dir.join(".darklock_restore_b.txt")).unwrap();
+
+    let report = manager
+        .restore_dir(&protected_dir, &parking_lot::Mutex::new(Default::default()))
+        .unwrap();
+    assert_eq!(fs::read(&victim).unwrap(), b"root-owned");
+    assert_eq!(report.failed.len(), 1);
+    assert!(report.failed[0].path.ends_with("a.txt"));
+    assert!(fs::symlink_metadata(protected_dir.join("a.txt")).unwrap().file_type().is_symlink());
+    assert_eq!(report.restored, 1);
+    assert_eq!(fs::read(protected_dir.join("b.txt")).unwrap(), b"b");
+}
+
+#[test]
+fn test_snapshots_pruned_to_keep_count() {
+    let dir = tempdir().unwrap();
+    let protected_dir = dir.path().join("protected");
+    fs::create_dir_all(&protected_dir).unwrap();
+    create_test_file(&protected_dir, "a.txt", b"a");
+
+    let store = dir.path().join("snapshots");
+    let index = dir.path().join("snapshots.json");
+    let resolver_store = store.clone();
+    let manager = SnapshotManager::with_resolver(index.clone(), 2, move |_| {
+        Some(Arc::new(CopyBackend { store: resolver_store.clone() }) as Arc<dyn SnapshotBackend>)
+    })
+    .unwrap();
+    for label in ["s1", "s2", "s3"] {
+        let results = manager.snapshot_paths(std::slice::from_ref(&protected_dir), label);
+        assert!(results[0].1.is_ok());
+    }
+
+    let labels: Vec<String> = manager.records().into_iter().map(|r| r.label).collect();
+    assert_eq!(labels, vec!["s2", "s3"]);
+    assert!(!store.join("s1").exists());
+
+    // The index survives a reload.
+    let reloaded = SnapshotManager::with_resolver(index, 2, |_| None).unwrap();
+    assert_eq!(reloaded.records().len(), 2);
+    assert_eq!(reloaded.latest_for(&protected_dir).unwrap().label, "s3");
+}
+
+// ─── Test 11: Ransomware burst response ─────────────────────────────────────
+
+#[cfg(unix)]
+#[test]
+fn test_ransomware_burst_freezes_writes_until_released() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempdir().unwrap();
+    let protected_dir = dir.path().join("protected");
+    fs::create_dir_all(protected_dir.join("sub")).unwrap();
+    fs::set_permissions(&protected_dir, fs::Permissions::from_mode(0o750)).unwrap();
+    let mode = |p: &Path| fs::metadata(p).unwrap().permissions().mode() & 0o777;
+
+    let mut vault = Vault::create_new(dir.path().join("vault.dat"), "pw").unwrap();
+    let engine = Engine::load_from_vault(&vault).unwrap();
+    let mut settings = engine.settings();
+    settings.protection.protected_paths = vec![protected_dir.display().to_string()];
+    engine.update_settings(&mut vault, settings).unwrap();
+
+    let event_log =
+        EventLog::new(dir.path().join("events.log"), signing_key(), 1 << 20).unwrap();
+    let state_path = dir.path().join("write_freeze.json");
+    let freeze = WriteFreeze::load(state_path.clone()).unwrap();
+    let report = BurstReport {
+        window_secs: 10,
+        files_changed: 120,
+        extension_changes: 118,
+        dominant_extension: Some("locked".into()),
+        sample_paths: vec![protected_dir.join("a.docx.locked").display().to_string()],
+    };
+
+    assert!(engine.handle_ransomware_suspected(&report, Some(&freeze), &event_log));
+    assert_eq!(mode(&protected_dir), 0o550);
+    assert_eq!(mode(&protected_dir.join("sub")) & 0o222, 0);
+
+    // The freeze survives a restart and is undone with the original modes.
+    let reloaded = WriteFreeze::load(state_path.clone()).unwrap();
+    assert_eq!(reloaded.frozen_dirs().len(), 2);
+    assert_eq!(reloaded.release().unwrap(), 2);
+    assert_eq!(mode(&protected_dir), 0o750);
+    assert!(!state_path.exists());
+
+    // A directory that can't be restored stays frozen, on disk too, and
+    // one that no longer exists is dropped.
+    fs::write(protected_dir.join("file"), b"").unwrap();
+    let unreachable = protected_dir.join("file/dir");
+    let state = serde_json::json!({
+        protected_dir.join("sub").display().to_string(): 0o750,
+        protected_dir.join("gone").display().to_string(): 0o750,
+        unreachable.display().to_string(): 0o750,
+    });
+    fs::write(&state_path, serde_json::to_vec(&state).unwrap()).unwrap();
+    let partial = WriteFreeze::load(state_path.clone()).unwrap();
+    assert_eq!(partial.release().unwrap(), 1);
+    assert_eq!(partial.frozen_dirs(), vec![unreachable.clone()]);
+    let reloaded = WriteFreeze::load(state_path.clone()).unwrap();
+    assert_eq!(reloaded.frozen_dirs(), vec![unreachable]);
+    fs::remove_file(&state_path).unwrap();
+    fs::remove_file(protected_dir.join("file")).unwrap();
+
+    // Outside Active mode the burst is only recorded.
+    engine.enter_safe_mode();
+    assert!(!engine.handle_ransomware_suspected(&report, Some(&freeze), &event_log));
+    assert_eq!(mode(&protected_dir), 0o750);
+
+    let page = event_log
+        .search(&EventQuery {
+            event_types: vec!["RANSOMWARE_SUSPECTED".into()],
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(page.events.len(), 2);
+    assert_eq!(page.events[1].data["frozen_directories"], 2);
+    assert_eq!(page.events[1].data["dominant_extension"], "locked");
+}
+
+// ─── Test 12: Temporary path exclusions ─────────────────────────────────────
+
+#[test]
+fn test_temporary_exclusion_suspends_enforcement_until_expiry() {
+    let dir = tempdir().unwrap();
+    let protected_dir = dir.path().join("protected");
+    fs::create_dir_all(protected_dir.join("work")).unwrap();
+    create_test_file(&protected_dir, "kept.txt", b"kept");
+    create_test_file(&protected_dir.join("work"), "draft.txt", b"draft v1");
+
+    let mut vault = Vault::create_new(dir.path().join("vault.dat"), "pw").unwrap();
+    let engine = Engine::load_from_vault(&vault).unwrap();
+    let mut settings = engine.settings();
+    settings.protection.protected_paths = vec![protected_dir.display().to_string()];
+    engine.update_settings(&mut vault, settings).unwrap();
+
+    let sk = signing_key();
+    let event_log = EventLog::new(dir.path().join("events.log"), sk.clone(), 1 << 20).unwrap();
+    let reason = || "editing drafts".to_string();
+
+    // Only paths strictly inside a protected directory can be excluded.
+    assert!(engine
+        .add_exclusion(&mut vault, &protected_dir, 60, reason(), &event_log)
+        .is_err());
+    assert!(engine
+        .add_exclusion(&mut vault, dir.path(), 60, reason(), &event_log)
+        .is_err());
+    assert!(engine
+        .add_exclusion(&mut vault, &protected_dir.join("work"), 60, "  ".into(), &event_log)
+        .is_err());
+
+    let work = protected_dir.join("work");
+    let exclusion = engine
+        .add_exclusion(&mut vault, &work, 60, reason(), &event_log)
+        .unwrap();
+    assert!(engine.is_excluded(&work.canonicalize().unwrap().join("draft.txt")));
+
+    // Edits under the exclusion are left alone; the rest is still enforced.
+    let scanner = IntegrityScanner::new(vec![protected_dir.clone()], "test-device".into());
+    let mut baseline = scanner.generate_baseline(&sk).unwrap();
+    let mut backups =
+        BackupStore::load_or_create(dir.path().join("backups"), sk.clone(), "test-device").unwrap();
+    for entry in baseline.entries.values() {
+        backups
+            .ensure_from_disk(Path::new(&entry.path), &entry.hash, entry.permissions, None)
+            .unwrap();
+    }
+    fs::write(work.join("draft.txt"), b"draft v2").unwrap();
+    fs::write(protected_dir.join("kept.txt"), b"tampered").unwrap();
+
+    let quarantine = QuarantineZone::new(dir.path().join("quarantine")).unwrap();
+    let restore_engine = RestoreEngine::new(quarantine);
+    let result = scanner.scan_against_baseline(&baseline);
+    engine.handle_scan_result(&result, &restore_engine, &backups, &baseline, &event_log);
+    assert_eq!(fs::read(protected_dir.join("kept.txt")).unwrap(), b"kept");
+    assert_eq!(fs::read(work.join("draft.txt")).unwrap(), b"draft v2");
+
+    // On expiry the edited contents become the baseline.
+    let expired = PathExclusion {
+        expires_at: Utc::now() - chrono::Duration::seconds(1),
+        ..exclusion
+    };
+    save_exclusions(&mut vault, &[expired]).unwrap();
+    let engine = Engine::load_from_vault(&vault).unwrap();
+    let ended = engine.take_expired_exclusions(&mut vault, &event_log).unwrap();
+    assert_eq!(ended.len(), 1);
+    assert!(engine.exclusions().is_empty());
+    let paths: Vec<PathBuf> = ended.iter().map(|e| PathBuf::from(&e.path)).collect();
+    assert_eq!(scanner.refresh_paths(&mut baseline, &paths, &sk), 1);
+    assert!(IntegrityScanner::verify_baseline_signature(&baseline, &sk.verifying_key()).unwrap());
+    assert!(scanner.scan_against_baseline(&baseline).modified.is_empty());
+
+    // ZeroTrust forbids exclusions outright.
+    vault.payload.security_profile = SecurityProfile::ZeroTrust;
+    assert!(engine
+        .add_exclusion(&mut vault, &work, 60, reason(), &event_log)
+        .is_err());
+
+    let count = |kind: &str| {
+        event_log
+            .search(&EventQuery {
+                event_types: vec![kind.into()],
+                ..Default::default()
+            })
+            .unwrap()
+            .events
+            .len()
+    };
+    assert_eq!(count("EXCLUSION_ADDED"), 1);
+    assert_eq!(count("EXCLUSION_EXPIRED"), 1);
+    assert_eq!(count("EXCLUSION_DENIED"), 3);
+}
+
+// ─── Test 13: Maintenance change journal ────────────────────────────────────
+
+#[test]
+fn test_maintenance_exit_journals_changes() {
+    let dir = tempdir().unwrap();
+    let protected_dir = dir.path().join("protected");
+    fs::create_dir_all(&protected_dir).unwrap();
+    let protected_dir = protected_dir.canonicalize().unwrap();
+    let (conf, conf_hash, _) = create_test_file(&protected_dir, "app.conf", b"v1");
+    let (old_lib, _, _) = create_test_file(&protected_dir, "old.so", b"old");
+
+    let vault = Vault::create_new(dir.path().join("vault.dat"), "pw").unwrap();
+    let engine = Engine::load_from_vault(&vault).unwrap();
+    let sk = signing_key();
+    let event_log = EventLog::new(dir.path().join("events.log"), sk.clone(), 1 << 20).unwrap();
+    let scanner = IntegrityScanner::new(vec![protected_dir.clone()], "test-device".into());
+    let baseline = scanner.generate_baseline(&sk).unwrap();
+    let baseline_path = dir.path().join("baseline.json");
+    IntegrityScanner::save_baseline(&baseline, &baseline_path).unwrap();
+    let mut backups =
+        BackupStore::load_or_create(dir.path().join("backups"), sk.clone(), "test-device").unwrap();
+    let quarantine = QuarantineZone::new(dir.path().join("quarantine")).unwrap();
+    let restore_engine = RestoreEngine::new(quarantine);
+
+    engine
+        .enter_maintenance("package upgrade".into(), 600, &event_log)
+        .unwrap();
+
+    // The watcher reports the config edit; it is journaled, not enforced.
+    fs::write(&conf, b"v2").unwrap();
+    let new_hash = blake3::hash(b"v2").to_hex().to_string();
+    let event = TamperEvent::Modified {
+        path: conf.clone(),
+        expected_hash: conf_hash.clone(),
+        actual_hash: new_hash.clone(),
+    };
+    engine.handle_tamper_event(&event, &restore_engine, &backups, &baseline, &event_log);
+    assert_eq!(fs::read(&conf).unwrap(), b"v2");
+
+    // Changes the watcher missed are picked up from the rebaseline diff.
+    fs::remove_file(&old_lib).unwrap();
+    let (new_lib, _, _) = create_test_file(&protected_dir, "new.so", b"new");
+
+    let exit = engine
+        .exit_maintenance(
+            true,
+            Some(&scanner),
+            &sk,
+            &baseline_path,
+            &mut backups,
+            &event_log,
+            dir.path(),
+            true,
+            None,
+        )
+        .unwrap();
+    assert!(exit.baseline.is_some());
+
+    let journal = &exit.journal;
+    assert_eq!(journal.reason, "package upgrade");
+    assert!(journal.rebaselined);
+    let entry = |p: &Path| journal.entries[&p.display().to_string()].clone();
+    let conf_entry = entry(&conf);
+    assert_eq!(conf_entry.kind, ChangeKind::Modified);
+    assert_eq!(conf_entry.old_hash.as_deref(), Some(conf_hash.as_str()));
+    assert_eq!(conf_entry.new_hash.as_deref(), Some(new_hash.as_str()));
+    assert_eq!(entry(&old_lib).kind, ChangeKind::Removed);
+    assert_eq!(entry(&new_lib).kind, ChangeKind::Added);
+    assert_eq!(journal.len(), 3);
+
+    let report: MaintenanceJournal =
+        serde_json::from_slice(&fs::read(exit.report_path.unwrap()).unwrap()).unwrap();
+    assert_eq!(report.len(), 3);
+
+    let exit_events = event_log
+        .search(&EventQuery {
+            event_types: vec!["MAINTENANCE_EXIT".into()],
+            ..Default::default()
+        })
+        .unwrap()
+        .events;
+    assert_eq!(exit_events[0].data["changes"], 3);
+    assert_eq!(exit_events[0].data["journal"].as_array().unwrap().len(), 3);
+}
+
+// ─── Test 14: Dual-control operator key ─────────────────────────────────────
+
+#[test]
+fn test_operator_key_change_requires_current_key() {
+    use base64::Engine as _;
+    use ed25519_dalek::Signer;
+
+    let dir = tempdir().unwrap();
+    let mut vault = Vault::create_new(dir.path().join("vault.dat"), "pw").unwrap();
+    let engine = Engine::load_from_vault(&vault).unwrap();
+    let b64 = |k: &SigningKey| base64::engine::general_purpose::STANDARD.encode(k.verifying_key().to_bytes());
+    let first = signing_key();
+    let second = signing_key();
+
+    assert!(engine
+        .set_baseline_operator_key(&mut vault, Some("not a key"), None)
+        .is_err());
+    engine
+        .set_baseline_operator_key(&mut vault, Some(&b64(&first)), None)
+        .unwrap();
+    assert_eq!(load_baseline_operator_key(&vault).unwrap(), Some(b64(&first)));
+
+    // Once enabled, rotating or disabling needs the current key's approval.
+    let new_key = b64(&second);
+    assert!(engine
+        .set_baseline_operator_key(&mut vault, Some(&new_key), None)
+        .is_err());
+    let request = |vault: &Vault, key: Option<&str>| {
+        engine.operator_key_change_request(vault, key).unwrap().unwrap()
+    };
+    let approve = |signer: &SigningKey, message: &[u8]| hex::encode(signer.sign(message).to_bytes());
+    let by_wrong_key = approve(&second, &request(&vault, Some(&new_key)));
+    assert!(engine
+        .set_baseline_operator_key(&mut vault, Some(&new_key), Some(&by_wrong_key))
+        .is_err());
+    // Approvals are bound to the device.
+    let other_device = operator_key_change_message("other-device", &b64(&first), 1, Some(&new_key));
+    assert!(engine
+        .set_baseline_operator_key(&mut vault, Some(&new_key), Some(&approve(&first, &other_device)))
+        .is_err());
+    let approval = approve(&first, &request(&vault, Some(&new_key)));
+    engine
+        .set_baseline_operator_key(&mut vault, Some(&new_key), Some(&approval))
+        .unwrap();
+
+    // An approval for one change can't be replayed to disable dual control.
+    assert!(engine
+        .set_baseline_operator_key(&mut vault, None, Some(&approval))
+        .is_err());
+    let disable = approve(&second, &request(&vault, None));
+    engine
+        .set_baseline_operator_key(&mut vault, None, Some(&disable))
+        .unwrap();
+    assert_eq!(load_baseline_operator_key(&vault).unwrap(), None);
+
+    // Nor can an old approval be replayed once the same key is back.
+    engine
+        .set_baseline_operator_key(&mut vault, Some(&new_key), None)
+        .unwrap();
+    assert!(engine
+        .set_baseline_operator_key(&mut vault, None, Some(&disable))
+        .is_err());
+    assert_eq!(load_baseline_operator_key(&vault).unwrap(), Some(new_key));
+}
+
+// ─── Test 15: Pending restores applied at start ─────────────────────────────
+
+#[test]
+fn test_pending_restores_applied_at_start() {
+    let dir = tempdir().unwrap();
+    let protected_dir = dir.path().join("protected");
+    fs::create_dir_all(&protected_dir).unwrap();
+    let (locked, locked_hash, perms) = create_test_file(&protected_dir, "service.exe", b"good binary");
+    let (replaced, replaced_hash, _) = create_test_file(&protected_dir, "helper.dll", b"good library");
+
+    let sk = signing_key();
+    let scanner = IntegrityScanner::new(vec![protected_dir.clone()], "test-device".into());
+    let baseline = scanner.generate_baseline(&sk).unwrap();
+    let mut store = BackupStore::load_or_create(dir.path().join("backups"), sk, "test-device").unwrap();
+    store.ensure_from_disk(&locked, &locked_hash, perms, None).unwrap();
+
+    // Last run: service.exe was tampered and stayed locked; helper.dll was
+    // replaced at boot before the service came up.
+    fs::write(&locked, b"trojaned binary").unwrap();
+    let queue_path = dir.path().join("pending_restores.json");
+    let queue = PendingRestores::load(queue_path.clone()).unwrap();
+    for (path, hash) in [
+        (&locked, &locked_hash),
+        (&replaced, &replaced_hash),
+        (&protected_dir.join("gone.txt"), &locked_hash),
+    ] {
+        queue
+            .enqueue(PendingRestore {
+                path: path.display().to_string(),
+                hash: hash.clone(),
+                queued_at: Utc::now(),
+                error: "sharing violation".into(),
+                boot_scheduled: false,
+            })
+            .unwrap();
+    }
+
+    let engine = RestoreEngine::new(QuarantineZone::new(dir.path().join("quarantine")).unwrap())
+        .with_pending_queue(PendingRestores::load(queue_path.clone()).unwrap());
+    let outcomes = engine.apply_pending(&baseline, &store);
+
+    assert_eq!(outcomes.len(), 2, "path outside the baseline is dropped");
+    assert!(outcomes
+        .iter()
+        .all(|(_, outcome)| matches!(outcome, RestoreOutcome::Restored)));
+    assert_eq!(fs::read(&locked).unwrap(), b"good binary");
+    assert!(PendingRestores::load(queue_path)
+        .unwrap()
+        .take_all()
+        .unwrap()
+        .is_empty());
+
+    // Startup cleanup removes orphaned restore staging files but leaves a
+    // replacement staged for the next reboot.
+    let orphan = protected_dir.join(".darklock_restore_0000beef");
+    let boot_staged = protected_dir.join(".darklock_boot_0000beef");
+    fs::write(&orphan, b"partial").unwrap();
+    fs::write(&boot_staged, b"good binary").unwrap();
+    RestoreEngine::cleanup_staging(std::slice::from_ref(&protected_dir));
+    assert!(!orphan.exists());
+    assert!(boot_staged.exists());
+}
+
+// ─── Test 16: Symlink swaps ─────────────────────────────────────────────────
+
+#[cfg(unix)]
+#[test]
+fn test_symlink_swaps_detected_and_undone() {
+    use guard_core::settings::SymlinkPolicy;
+    use std::os::unix::fs::symlink;
+
+    let dir = tempdir().unwrap();
+    let protected_dir = dir.path().join("protected");
+    fs::create_dir_all(&protected_dir).unwrap();
+    let (config, config_hash, perms) = create_test_file(&protected_dir, "app.toml", b"debug = false");
+    let current = protected_dir.join("current.toml");
+    symlink(&config, &current).unwrap();
+    let (evil, _, _) = create_test_file(dir.path(), "evil.toml", b"debug = true");
+
+    let sk = signing_key();
+    let scanner = IntegrityScanner::new(vec![protected_dir.clone()], "test-device".into())
+        .with_symlink_policy(SymlinkPolicy::ProtectLink);
+    let baseline = scanner.generate_baseline(&sk).unwrap();
+    assert_eq!(baseline.entries.len(), 2, "the link is an entry of its own");
+    assert!(IntegrityScanner::verify_baseline_signature(&baseline, &sk.verifying_key()).unwrap());
+    let mut store =
+        BackupStore::load_or_create(dir.path().join("backups"), sk.clone(), "test-device").unwrap();
+    store.ensure_from_disk(&config, &config_hash, perms, None).unwrap();
+
+    // Swap the file for a link out of the tree and retarget the protected link.
+    let config = config.canonicalize().unwrap();
+    fs::remove_file(&config).unwrap();
+    symlink(&evil, &config).unwrap();
+    fs::remove_file(&current).unwrap();
+    symlink(&evil, &current).unwrap();
+
+    let result = scanner.scan_against_baseline(&baseline);
+    assert!(!result.valid);
+    assert_eq!(result.symlink_swaps.len(), 2);
+
+    let vault = Vault::create_new(dir.path().join("vault.dat"), "pw").unwrap();
+    let engine = Engine::load_from_vault(&vault).unwrap();
+    let event_log = EventLog::new(dir.path().join("events.log"), sk, 1 << 20).unwrap();
+    let restore_engine = RestoreEngine::new(QuarantineZone::new(dir.path().join("quarantine")).unwrap());
+    engine.handle_scan_result(&result, &restore_engine, &store, &baseline, &event_log);
+
+    assert!(!fs::symlink_metadata(&config).unwrap().file_type().is_symlink());
+    assert_eq!(fs::read(&config).unwrap(), b"debug = false");
+    assert_eq!(fs::read_link(&current).unwrap(), config);
+    assert_eq!(fs::read(&evil).unwrap(), b"debug = true", "link target left alone");
+    assert!(scanner.scan_against_baseline(&baseline).valid);
+
+    let swaps = event_log
+        .search(&EventQuery {
+            event_types: vec!["TAMPER_DETECTED".into()],
+            ..Default::default()
+        })
+        .unwrap()
+        .events
+        .into_iter()
+        .filter(|e| e.data["kind"] == "symlink_swap")
+        .count();
+    assert_eq!(swaps, 2);
+}
+
+// ─── Test 17: Enforcement action rules ──────────────────────────────────────
+
+#[test]
+fn test_enforcement_rules_dry_run_then_act() {
+    let dir = tempdir().unwrap();
+    let protected_dir = dir.path().canonicalize().unwrap().join("protected");
+    fs::create_dir_all(&protected_dir).unwrap();
+    let (file_path, _, _) = create_test_file(&protected_dir, "app.conf", b"trusted");
+
+    let sk = signing_key();
+    let scanner = IntegrityScanner::new(vec![protected_dir.clone()], "test-device".into());
+    let baseline = scanner.generate_baseline(&sk).unwrap();
+    let mut backups =
+        BackupStore::load_or_create(dir.path().join("backups"), sk.clone(), "test-device").unwrap();
+    for entry in baseline.entries.values() {
+        backups
+            .ensure_from_disk(Path::new(&entry.path), &entry.hash, entry.permissions, None)
+            .unwrap();
+    }
+    let event_log = EventLog::new(dir.path().join("events.log"), sk.clone(), 1 << 20).unwrap();
+    let restore_engine = RestoreEngine::new(QuarantineZone::new(dir.path().join("q")).unwrap());
+
+    let mut vault = Vault::create_new(dir.path().join("vault.dat"), "pw").unwrap();
+    let engine = Engine::load_from_vault(&vault).unwrap();
+    let mut settings = engine.settings();
+    settings.enforcement.dry_run = true;
+    settings.enforcement.rules = vec![EnforcementRule {
+        paths: vec![protected_dir.display().to_string()],
+        min_severity: Some(EventSeverity::Critical),
+        actions: vec![
+            EnforcementAction::Notify,
+            EnforcementAction::Quarantine,
+            EnforcementAction::Restore,
+            EnforcementAction::SafeMode,
+        ],
+    }];
+    engine.update_settings(&mut vault, settings.clone()).unwrap();
+
+    fs::write(&file_path, b"tampered").unwrap();
+    let event = TamperEvent::Modified {
+        path: file_path.clone(),
+        expected_hash: baseline.entries[&file_path.display().to_string()].hash.clone(),
+        actual_hash: blake3::hash(b"tampered").to_hex().to_string(),
+    };
+
+    // Dry run: the plan is logged, nothing is touched.
+    engine.handle_tamper_event(&event, &restore_engine, &backups, &baseline, &event_log);
+    assert_eq!(fs::read(&file_path).unwrap(), b"tampered");
+    assert_eq!(engine.mode(), EngineMode::Active);
+
+    // Live: the tampered copy is kept aside, the file restored, enforcement paused.
+    settings.enforcement.dry_run = false;
+    engine.update_settings(&mut vault, settings).unwrap();
+    engine.handle_tamper_event(&event, &restore_engine, &backups, &baseline, &event_log);
+    assert_eq!(fs::read(&file_path).unwrap(), b"trusted");
+    assert_eq!(engine.mode(), EngineMode::SafeMode);
+    let quarantined: Vec<_> = fs::read_dir(dir.path().join("quarantine"))
+        .unwrap()
+        .flatten()
+        .collect();
+    assert_eq!(quarantined.len(), 1);
+    assert_eq!(fs::read(quarantined[0].path()).unwrap(), b"tampered");
+
+    let count = |kind: &str| {
+        event_log
+            .search(&EventQuery {
+                event_types: vec![kind.into()],
+                ..Default::default()
+            })
+            .unwrap()
+            .events
+            .len()
+    };
+    assert_eq!(count("ENFORCEMENT_DRY_RUN"), 1);
+    assert_eq!(count("TAMPER_NOTIFICATION"), 1);
+    assert_eq!(count("FILE_QUARANTINED"), 1);
+    assert_eq!(count("SAFE_MODE_ENTERED"), 1);
+}
+
+// ─── Test 18: Backup store GC and verify-on-read ────────────────────────────
+
+#[test]
+fn test_backup_store_gc_and_refetch() {
+    let dir = tempdir().unwrap();
+    let protected_dir = dir.path().join("protected");
+    fs::create_dir_all(&protected_dir).unwrap();
+    let backups_dir = dir.path().join("backups");
+    let sk = signing_key();
+    let mut store = BackupStore::load_or_create(&backups_dir, sk.clone(), "test-device").unwrap();
+    let blob = |hash: &str| backups_dir.join("blobs").join(&hash[0..2]).join(format!("{hash}.blob"));
+
+    let (kept, kept_hash, perms) = create_test_file(&protected_dir, "kept.txt", b"v1");
+    let kept = kept.canonicalize().unwrap();
+    store.ensure_from_disk(&kept, &kept_hash, perms, None).unwrap();
+    let (dropped, dropped_hash, _) = create_test_file(&protected_dir, "dropped.txt", b"gone soon");
+    let dropped = dropped.canonicalize().unwrap();
+    store.ensure_from_disk(&dropped, &dropped_hash, perms, None).unwrap();
+
+    // A new version leaves the old blob orphaned.
+    let (_, v2_hash, _) = create_test_file(&protected_dir, "kept.txt", b"v2");
+    store.ensure_from_disk(&kept, &v2_hash, perms, None).unwrap();
+    let stats = store.stats();
+    assert_eq!((stats.entries, stats.blobs, stats.orphaned_blobs), (2, 3, 1));
+
+    // Verify-on-read: a corrupted blob is re-fetched while the source matches.
+    let kept_key = kept.display().to_string();
+    fs::write(blob(&v2_hash), b"bit rot").unwrap();
+    assert!(store.read_path(&kept_key).is_err());
+    assert_eq!(store.read_path_repairing(&kept_key).unwrap(), b"v2");
+    assert_eq!(store.read_path(&kept_key).unwrap(), b"v2");
+
+    // Once the source has changed too there is nothing to re-fetch from.
+    fs::write(blob(&v2_hash), b"bit rot").unwrap();
+    fs::write(&kept, b"tampered").unwrap();
+    assert!(store.read_path_repairing(&kept_key).is_err());
+    fs::write(&kept, b"v2").unwrap();
+
+    // GC drops entries the baseline no longer covers, repairs, and sweeps.
+    let report = store.run_gc(|path| path == kept_key).unwrap();
+    assert_eq!(report.entries_pruned, 1);
+    assert_eq!(report.blobs_removed, 2);
+    assert_eq!(report.blobs_repaired, vec![kept_key.clone()]);
+    assert!(report.blobs_unrecoverable.is_empty());
+    assert!(!blob(&kept_hash).exists());
+    assert!(!blob(&dropped_hash).exists());
+    assert!(blob(&v2_hash).exists());
+
+    let stats = store.stats();
+    assert_eq!((stats.entries, stats.blobs, stats.orphaned_blobs), (1, 1, 0));
+    assert_eq!(stats.last_gc.unwrap().blobs_removed, 2);
+    store.verify_all().unwrap();
+
+    // The pruned manifest is re-signed and survives a reload.
+    drop(store);
+    let store = BackupStore::load_or_create(&backups_dir, sk, "test-device").unwrap();
+    assert_eq!(store.manifest().entries.len(), 1);
+}
+
+// ─── Test 19: Attack-simulation self-test ───────────────────────────────────
+
+#[tokio::test]
+async fn test_self_test_runs_full_chain_in_sandbox() {
+    let dir = tempdir().unwrap();
+    let protected_dir = dir.path().join("protected");
+    fs::create_dir_all(&protected_dir).unwrap();
+    create_test_file(&protected_dir, "config.toml", b"key = 1");
+    create_test_file(&protected_dir, "app.bin", b"\x7fELF");
+    let work_dir = dir.path().join("data");
+    fs::create_dir_all(&work_dir).unwrap();
+
+    let vault = Vault::create_new(dir.path().join("vault.dat"), "pw").unwrap();
+    let engine = Engine::load_from_vault(&vault).unwrap();
+    let report = run_self_test(&engine, &protected_dir, &work_dir, &signing_key())
+        .await
+        .unwrap();
+
+    assert!(report.passed, "{report:#?}");
+    let scenarios: Vec<_> = report.scenarios.iter().map(|s| s.scenario.as_str()).collect();
+    assert_eq!(scenarios, ["modify", "delete", "rename"]);
+    for scenario in &report.scenarios {
+        let stages: Vec<_> = scenario.stages.iter().map(|s| s.stage.as_str()).collect();
+        assert_eq!(stages, ["watcher", "pipeline", "engine", "restore"]);
+    }
+
+    // The real files are untouched and the sandbox is gone.
+    assert_eq!(fs::read(protected_dir.join("config.toml")).unwrap(), b"key = 1");
+    assert_eq!(fs::read_dir(&work_dir).unwrap().count(), 0);
+}
+
+// ─── Test 20: Ownership and extended attributes ─────────────────────────────
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_attribute_changes_restored_and_baseline_migrated() {
+    use guard_core::settings::AttributeSettings;
+    use guard_service::integrity::scanner::BASELINE_VERSION;
+    use std::os::unix::fs::MetadataExt;
+
+    let dir = tempdir().unwrap();
+    let protected_dir = dir.path().join("protected");
+    fs::create_dir_all(&protected_dir).unwrap();
+    let (tool, _, _) = create_test_file(&protected_dir, "tool", b"#!/bin/sh");
+    let (plain, _, _) = create_test_file(&protected_dir, "plain.txt", b"plain");
+    if xattr::set(&tool, "user.guard-origin", b"vendor").is_err() {
+        return; // file system without user xattrs
+    }
+    let tool = tool.canonicalize().unwrap();
+    let plain = plain.canonicalize().unwrap();
+
+    let sk = signing_key();
+    let scanner = IntegrityScanner::new(vec![protected_dir.clone()], "test-device".into())
+        .with_attributes(&AttributeSettings {
+            track_ownership: true,
+            xattrs: vec!["user.guard-origin".into()],
+        });
+    let baseline = scanner.generate_baseline(&sk).unwrap();
+    assert_eq!(baseline.version, BASELINE_VERSION);
+    let entry = &baseline.entries[&tool.display().to_string()];
+    assert_eq!(entry.ownership.as_ref().unwrap().uid, Some(fs::metadata(&tool).unwrap().uid()));
+    assert_eq!(entry.xattrs.as_ref().unwrap()["user.guard-origin"], hex::encode("vendor"));
+
+    // Content intact, attributes changed: reported apart from modifications.
+    xattr::set(&tool, "user.guard-origin", b"attacker").unwrap();
+    xattr::set(&plain, "user.guard-origin", b"planted").unwrap();
+    let result = scanner.scan_against_baseline(&baseline);
+    assert!(!result.valid);
+    assert!(result.modified.is_empty());
+    assert_eq!(result.attribute_paths().len(), 2);
+
+    let vault = Vault::create_new(dir.path().join("vault.dat"), "pw").unwrap();
+    let engine = Engine::load_from_vault(&vault).unwrap();
+    let event_log = EventLog::new(dir.path().join("events.log"), sk.clone(), 1 << 20).unwrap();
+    let store = BackupStore::load_or_create(dir.path().join("backups"), sk.clone(), "test-device").unwrap();
+    let restore_engine = RestoreEngine::new(QuarantineZone::new(dir.path().join("quarantine")).unwrap());
+    engine.handle_scan_result(&result, &restore_engine, &store, &baseline, &event_log);
+
+    assert_eq!(xattr::get(&tool, "user.guard-origin").unwrap().unwrap(), b"vendor");
+    assert!(xattr::get(&plain, "user.guard-origin").unwrap().is_none());
+    assert!(scanner.scan_against_baseline(&baseline).valid);
+    let restored = event_log
+        .search(&EventQuery {
+            event_types: vec!["ATTRIBUTES_RESTORED".into()],
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(restored.events.len(), 2);
+
+    // A v1 baseline keeps verifying and gains attributes for intact files.
+    let mut legacy = baseline.clone();
+    legacy.version = 1;
+    legacy.tracked_xattrs.clear();
+    for entry in legacy.entries.values_mut() {
+        entry.ownership = None;
+        entry.xattrs = None;
+    }
+    IntegrityScanner::sign_baseline(&mut legacy, &sk);
+    fs::write(&plain, b"changed").unwrap();
+    let migration = scanner.migrate_baseline(&mut legacy, &sk).unwrap().unwrap();
+    assert_eq!((migration.from_version, migration.adopted, migration.skipped), (1, 1, 1));
+    assert_eq!(legacy.version, BASELINE_VERSION);
+    assert!(IntegrityScanner::verify_baseline_signature(&legacy, &sk.verifying_key()).unwrap());
+    assert!(legacy.entries[&tool.display().to_string()].xattrs.is_some());
+    assert!(legacy.entries[&plain.display().to_string()].ownership.is_none());
+    assert!(scanner.migrate_baseline(&mut legacy, &sk).unwrap().is_none());
+
+    // Baselines from a newer build are refused.
+    let baseline_path = dir.path().join("baseline.json");
+    legacy.version = BASELINE_VERSION + 1;
+    IntegrityScanner::save_baseline(&legacy, &baseline_path).unwrap();
+    assert!(IntegrityScanner::load_baseline(&baseline_path).is_err());
+}
+
+// ─── Test 21: Validated rebaseline commit ───────────────────────────────────
+
+#[test]
+fn test_rebaseline_committed_only_after_validation() {
+    let dir = tempdir().unwrap();
+    let protected_dir = dir.path().join("protected");
+    fs::create_dir_all(&protected_dir).unwrap();
+    let protected_dir = protected_dir.canonicalize().unwrap();
+    let (conf, _, _) = create_test_file(&protected_dir, "app.conf", b"v1");
+
+    let vault = Vault::create_new(dir.path().join("vault.dat"), "pw").unwrap();
+    let engine = Engine::load_from_vault(&vault).unwrap();
+    let sk = signing_key();
+    let event_log = EventLog::new(dir.path().join("events.log"), sk.clone(), 1 << 20).unwrap();
+    let scanner = IntegrityScanner::new(vec![protected_dir.clone()], "test-device".into());
+    let baseline_path = dir.path().join("baseline.json");
+    IntegrityScanner::save_baseline(&scanner.generate_baseline(&sk).unwrap(), &baseline_path)
+        .unwrap();
+    let active = fs::read(&baseline_path).unwrap();
+    let backup_root = dir.path().join("backups");
+    let mut backups = BackupStore::load_or_create(&backup_root, sk.clone(), "test-device").unwrap();
+
+    engine
+        .enter_maintenance("package upgrade".into(), 600, &event_log)
+        .unwrap();
+    fs::write(&conf, b"v2").unwrap();
+
+    // Blobs can't be written, so the candidate has no backups and is refused.
+    let blobs = backup_root.join("blobs");
+    fs::remove_dir_all(&blobs).unwrap();
+    fs::write(&blobs, b"not a directory").unwrap();
+    let exit = |backups: &mut BackupStore| {
+        engine.exit_maintenance(
+            true,
+            Some(&scanner),
+            &sk,
+            &baseline_path,
+            backups,
+            &event_log,
+            dir.path(),
+            false,
+            None,
+        )
+    };
+    let err = exit(&mut backups).err().unwrap();
+    assert!(err.to_string().contains("no backup blob"), "{err}");
+    assert!(engine.is_maintenance());
+    assert_eq!(fs::read(&baseline_path).unwrap(), active);
+    assert!(!IntegrityScanner::pending_baseline_path(&baseline_path).exists());
+    let failures = event_log
+        .search(&EventQuery {
+            event_types: vec!["BASELINE_VALIDATION_FAILED".into()],
+            ..Default::default()
+        })
+        .unwrap()
+        .events;
+    assert_eq!(failures.len(), 1);
+
+    // Once backups work again the same exit commits the new baseline.
+    fs::remove_file(&blobs).unwrap();
+    let result = exit(&mut backups).unwrap();
+    assert!(!engine.is_maintenance());
+    assert!(!IntegrityScanner::pending_baseline_path(&baseline_path).exists());
+    let committed = IntegrityScanner::load_baseline(&baseline_path).unwrap();
+    assert_eq!(
+        committed.entries[&conf.display().to_string()].hash,
+        blake3::hash(b"v2").to_hex().to_string()
+    );
+    assert_eq!(result.baseline.unwrap().signature, committed.signature);
+}
+
+// ─── Test 22: Quarantine evidence export / import ───────────────────────────
+
+#[test]
+fn test_quarantine_evidence_round_trip_with_custody_chain() {
+    use base64::{engine::general_purpose, Engine as _};
+    use guard_core::evidence::CustodyAction;
+    use guard_service::enforcement::evidence::{self, Custodian};
+
+    let dir = tempdir().unwrap();
+    let (victim, _, _) = create_test_file(dir.path(), "evil.so", b"dropped payload");
+    let zone = QuarantineZone::new(dir.path().join("quarantine")).unwrap();
+    let moved = zone.quarantine_file(&victim).unwrap().unwrap();
+    let exporter = signing_key();
+    let event_log =
+        EventLog::new(dir.path().join("events.log"), exporter.clone(), 1 << 20).unwrap();
+    event_log
+        .append(
+            "FILE_QUARANTINED",
+            EventSeverity::Warn,
+            serde_json::json!({
+                "original_path": victim.display().to_string(),
+                "quarantine_path": moved.display().to_string(),
+            }),
+        )
+        .unwrap();
+    let origin = |p: &str| evidence::quarantined_from(&event_log, p);
+
+    let items = evidence::quarantine_items(zone.root(), origin).unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].original_path.as_deref(), Some(victim.to_str().unwrap()));
+
+    let archive = dir.path().join("case-42.evidence");
+    let sender = Custodian {
+        device_id: "device-a",
+        examiner: "alice",
+        device_key: &exporter,
+    };
+    assert!(evidence::export_quarantine(
+        zone.root(),
+        &["missing".into()],
+        origin,
+        &archive,
+        "pw",
+        &sender
+    )
+    .is_err());
+    let exported =
+        evidence::export_quarantine(zone.root(), &[], origin, &archive, "pw", &sender).unwrap();
+    assert!(fs::read(&archive)
+        .map(|a| !a.windows(15).any(|w| w == b"dropped payload"))
+        .unwrap());
+    // The file stays in quarantine; exporting copies it.
+    assert!(moved.exists());
+
+    // A second instance imports it with its own key.
+    let analyst = signing_key();
+    let receiver = Custodian {
+        device_id: "device-b",
+        examiner: "bob",
+        device_key: &analyst,
+    };
+    let evidence_root = dir.path().join("lab").join("evidence");
+    assert!(evidence::import_evidence(&archive, "wrong", &evidence_root, None, &receiver).is_err());
+    let stranger = signing_key().verifying_key();
+    assert!(
+        evidence::import_evidence(&archive, "pw", &evidence_root, Some(&stranger), &receiver)
+            .is_err()
+    );
+
+    let (manifest, unpacked) = evidence::import_evidence(
+        &archive,
+        "pw",
+        &evidence_root,
+        Some(&exporter.verifying_key()),
+        &receiver,
+    )
+    .unwrap();
+    assert_eq!(manifest.archive_id, exported.archive_id);
+    manifest.verify().unwrap();
+    let chain: Vec<_> = manifest
+        .custody
+        .iter()
+        .map(|r| (r.action, r.examiner.as_str()))
+        .collect();
+    assert_eq!(
+        chain,
+        [(CustodyAction::Exported, "alice"), (CustodyAction::Imported, "bob")]
+    );
+    let name = &manifest.items[0].name;
+    assert_eq!(
+        fs::read(unpacked.join("files").join(name)).unwrap(),
+        b"dropped payload"
+    );
+    let stored: guard_core::evidence::CustodyManifest =
+        serde_json::from_slice(&fs::read(unpacked.join("custody.json")).unwrap()).unwrap();
+    assert_eq!(stored, manifest);
+
+    // Importing the same archive twice is refused.
+    assert!(evidence::import_evidence(&archive, "pw", &evidence_root, None, &receiver).is_err());
+
+    // A self-signed archive can't choose a directory outside the root.
+    let mut forged = guard_core::evidence::CustodyManifest::new(manifest.items.clone());
+    forged.archive_id = "../../escaped".into();
+    forged
+        .append(CustodyAction::Exported, "device-x", "mallory", &signing_key())
+        .unwrap();
+    let payload = guard_core::evidence::EvidencePayload {
+        manifest: forged,
+        files: [(name.clone(), general_purpose::STANDARD.encode(b"dropped payload"))].into(),
+    };
+    let forged_archive = dir.path().join("forged.evidence");
+    let sealed = guard_core::evidence::EvidenceArchive::seal(&payload, "pw").unwrap();
+    fs::write(&forged_archive, serde_json::to_vec(&sealed).unwrap()).unwrap();
+    assert!(
+        evidence::import_evidence(&forged_archive, "pw", &evidence_root, None, &receiver).is_err()
+    );
+    assert!(!dir.path().join("escaped").exists());
+}
+
+// ─── Test 23: Flap suppression ──────────────────────────────────────────────
+
+#[test]
+fn test_flapping_path_suppressed_and_summarised() {
+    let dir = tempdir().unwrap();
+    let protected_dir = dir.path().canonicalize().unwrap().join("protected");
+    fs::create_dir_all(&protected_dir).unwrap();
+    let (file_path, _, _) = create_test_file(&protected_dir, "flappy.conf", b"trusted");
+
+    let sk = signing_key();
+    let scanner = IntegrityScanner::new(vec![protected_dir.clone()], "test-device".into());
+    let baseline = scanner.generate_baseline(&sk).unwrap();
+    let mut backups =
+        BackupStore::load_or_create(dir.path().join("backups"), sk.clone(), "test-device").unwrap();
+    for entry in baseline.entries.values() {
+        backups
+            .ensure_from_disk(Path::new(&entry.path), &entry.hash, entry.permissions, None)
+            .unwrap();
+    }
+    let event_log = EventLog::new(dir.path().join("events.log"), sk.clone(), 1 << 20).unwrap();
+    let restore_engine = RestoreEngine::new(QuarantineZone::new(dir.path().join("q")).unwrap());
+
+    let mut vault = Vault::create_new(dir.path().join("vault.dat"), "pw").unwrap();
+    let engine = Engine::load_from_vault(&vault).unwrap();
+    let mut settings = engine.settings();
+    settings.flapping.dedup_window_secs = 1;
+    settings.flapping.flap_threshold = 3;
+    settings.flapping.max_suppress_secs = 1;
+    settings.flapping.suggest_after = 1;
+    engine.update_settings(&mut vault, settings).unwrap();
+
+    let event = TamperEvent::Modified {
+        path: file_path.clone(),
+        expected_hash: baseline.entries[&file_path.display().to_string()].hash.clone(),
+        actual_hash: blake3::hash(b"rewritten").to_hex().to_string(),
+    };
+    for _ in 0..6 {
+        fs::write(&file_path, b"rewritten").unwrap();
+        engine.handle_tamper_event(&event, &restore_engine, &backups, &baseline, &event_log);
+    }
+    // The first three are enforced, the third tripping suppression; the rest
+    // are only counted.
+    assert_eq!(fs::read(&file_path).unwrap(), b"rewritten");
+
+    let events = |kind: &str| {
+        event_log
+            .search(&EventQuery {
+                event_types: vec![kind.into()],
+                ..Default::default()
+            })
+            .unwrap()
+            .events
+    };
+    assert_eq!(events("TAMPER_DETECTED").len(), 3);
+    let flapping = events("FLAPPING_DETECTED");
+    assert_eq!(flapping.len(), 1);
+    assert_eq!(flapping[0].data["events"], 3);
+
+    engine.flush_flap_summaries(&event_log);
+    assert!(events("ALERTS_SUPPRESSED").is_empty());
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    engine.flush_flap_summaries(&event_log);
+    let summary = &events("ALERTS_SUPPRESSED")[0];
+    assert_eq!(summary.data["suppressed"], 3);
+    assert_eq!(summary.data["message"], "3 identical events suppressed");
+    assert_eq!(summary.data["suggestion"], "exclude");
+
+    // The end of the period re-checks the path and restores what was left.
+    engine.recheck_flapped_paths(&restore_engine, &backups, &baseline, &event_log, None);
+    assert_eq!(fs::read(&file_path).unwrap(), b"trusted");
+    assert_eq!(events("TAMPER_DETECTED").len(), 4);
+
+    // Once the period is over the path is enforced again.
+    fs::write(&file_path, b"rewritten").unwrap();
+    engine.handle_tamper_event(&event, &restore_engine, &backups, &baseline, &event_log);
+    assert_eq!(fs::read(&file_path).unwrap(), b"trusted");
+}
+
+// ─── Test 24: Sandboxed scan worker ─────────────────────────────────────────
+
+#[test]
+fn test_scan_worker_hashes_and_fails_closed() {
+    let dir = tempdir().unwrap();
+    let protected_dir = dir.path().join("protected");
+    fs::create_dir_all(&protected_dir).unwrap();
+    create_test_file(&protected_dir, "small.conf", b"small");
+    // Spans several worker frames.
+    let large: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+    create_test_file(&protected_dir, "large.bin", &large);
+
+    let sk = signing_key();
+    let local = IntegrityScanner::new(vec![protected_dir.clone()], "test-device".into())
+        .generate_baseline(&sk)
+        .unwrap();
+
+    let worker = Arc::new(ScanWorker::new(
+        env!("CARGO_BIN_EXE_guard-service").into(),
+        vec!["scan-worker".into()],
+    ));
+    let sandboxed = IntegrityScanner::new(vec![protected_dir.clone()], "test-device".into())
+        .with_sandbox(Some(worker.clone()));
+    let baseline = sandboxed.generate_baseline(&sk).unwrap();
+    assert_eq!(baseline.entries.len(), 2);
+    for (path, entry) in &baseline.entries {
+        assert_eq!(entry.hash, local.entries[path].hash);
+    }
+    #[cfg(target_os = "linux")]
+    {
+        let status =
+            fs::read_to_string(format!("/proc/{}/status", worker.pid().unwrap())).unwrap();
+        assert!(status.lines().any(|l| l == "Seccomp:\t1"), "worker not in strict seccomp");
+    }
+
+    fs::write(protected_dir.join("small.conf"), b"changed").unwrap();
+    let result = sandboxed.scan_against_baseline(&baseline);
+    assert_eq!(result.modified.len(), 1);
+    assert!(result.errors.is_empty());
+
+    // A worker that dies hashes nothing; there is no in-process fallback.
+    #[cfg(unix)]
+    {
+        let dead = Arc::new(ScanWorker::new("/bin/true".into(), Vec::new()));
+        let result = IntegrityScanner::new(vec![protected_dir.clone()], "test-device".into())
+            .with_sandbox(Some(dead))
+            .scan_against_baseline(&baseline);
+        assert_eq!(result.errors.len(), 2);
+        assert!(result.modified.is_empty());
+    }
+}
+
+// ─── Test 25: Dual-control rebaseline held for countersigning ───────────────
+
+#[test]
+fn test_dual_control_rebaseline_waits_for_countersignature() {
+    use ed25519_dalek::Signer;
+
+    let dir = tempdir().unwrap();
+    let protected_dir = dir.path().join("protected");
+    fs::create_dir_all(&protected_dir).unwrap();
+    let protected_dir = protected_dir.canonicalize().unwrap();
+    let (conf, _, _) = create_test_file(&protected_dir, "app.conf", b"v1");
+
+    let vault = Vault::create_new(dir.path().join("vault.dat"), "pw").unwrap();
+    let engine = Engine::load_from_vault(&vault).unwrap();
+    let sk = signing_key();
+    let operator = signing_key();
+    let event_log = EventLog::new(dir.path().join("events.log"), sk.clone(), 1 << 20).unwrap();
+    let scanner = IntegrityScanner::new(vec![protected_dir.clone()], "test-device".into());
+    let baseline_path = dir.path().join("baseline.json");
+    IntegrityScanner::save_baseline(&scanner.generate_baseline(&sk).unwrap(), &baseline_path)
+        .unwrap();
+    let active = fs::read(&baseline_path).unwrap();
+    let mut backups =
+        BackupStore::load_or_create(dir.path().join("backups"), sk.clone(), "test-device").unwrap();
+
+    engine
+        .enter_maintenance("package upgrade".into(), 600, &event_log)
+        .unwrap();
+    fs::write(&conf, b"v2").unwrap();
+    let operator_key = operator.verifying_key();
+    let exit = |backups: &mut BackupStore| {
+        engine.exit_maintenance(
+            true,
+            Some(&scanner),
+            &sk,
+            &baseline_path,
+            backups,
+            &event_log,
+            dir.path(),
+            false,
+            Some(&operator_key),
+        )
+    };
+
+    // Uncountersigned, the rebaseline is held and the old one stays active.
+    assert!(exit(&mut backups).is_err());
+    assert!(engine.is_maintenance());
+    assert_eq!(fs::read(&baseline_path).unwrap(), active);
+    let held_path = IntegrityScanner::countersign_baseline_path(&baseline_path);
+    let mut held = IntegrityScanner::load_baseline(&held_path).unwrap();
+    let required = event_log
+        .search(&EventQuery {
+            event_types: vec!["BASELINE_COUNTERSIGN_REQUIRED".into()],
+            ..Default::default()
+        })
+        .unwrap()
+        .events;
+    assert_eq!(required.len(), 1);
+    assert_eq!(required[0].severity, EventSeverity::Critical);
+
+    // Once the operator countersigns it, the next exit commits it.
+    let signature = operator.sign(&IntegrityScanner::operator_signing_message(&held));
+    IntegrityScanner::countersign_baseline(
+        &mut held,
+        &hex::encode(signature.to_bytes()),
+        &operator.verifying_key(),
+    )
+    .unwrap();
+    IntegrityScanner::save_baseline(&held, &held_path).unwrap();
+    let result = exit(&mut backups).unwrap();
+    assert!(!engine.is_maintenance());
+    assert!(!held_path.exists());
+    let committed = IntegrityScanner::load_baseline(&baseline_path).unwrap();
+    assert!(IntegrityScanner::verify_operator_signature(&committed, &operator.verifying_key()));
+    assert_eq!(
+        committed.entries[&conf.display().to_string()].hash,
+        blake3::hash(b"v2").to_hex().to_string()
+    );
+    assert_eq!(result.baseline.unwrap().signature, committed.signature);
+}
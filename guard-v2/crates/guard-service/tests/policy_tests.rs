@@ -0,0 +1,144 @@
+//! Organization policy bundles: verification, application and locking;
+//! settings history and rollback under a policy.
+
+use base64::{engine::general_purpose, Engine as _};
+use chrono::Utc;
+use ed25519_dalek::SigningKey;
+use guard_core::policy::{PolicyBundle, PolicySettings, SignedPolicyBundle};
+use guard_core::storage::load_policy_bundle;
+use guard_core::vault::Vault;
+use guard_service::engine::Engine;
+use tempfile::tempdir;
+
+fn org_key_b64(key: &SigningKey) -> String {
+    general_purpose::STANDARD.encode(key.verifying_key().to_bytes())
+}
+
+fn bundle(version: u64) -> PolicyBundle {
+    PolicyBundle {
+        policy_id: "corp".into(),
+        version,
+        issued_at: Utc::now(),
+        expires_at: None,
+        settings: PolicySettings {
+            protected_paths: Some(vec!["/srv/app".into()]),
+            update_channel: Some("stable".into()),
+            ..Default::default()
+        },
+    }
+}
+
+#[test]
+fn signed_policy_is_applied_locked_and_persisted() {
+    let dir = tempdir().unwrap();
+    let vault_path = dir.path().join("vault.dat");
+    let mut vault = Vault::create_new(&vault_path, "pw").unwrap();
+    let engine = Engine::load_from_vault(&vault).unwrap();
+    let org = SigningKey::generate(&mut rand::rngs::OsRng);
+
+    // No key configured yet.
+    let signed = SignedPolicyBundle::sign(bundle(1), &org).unwrap();
+    assert!(engine.apply_policy(&mut vault, signed.clone()).is_err());
+
+    engine.set_policy_org_key(&mut vault, &org_key_b64(&org)).unwrap();
+
+    // Signed by someone else.
+    let rogue = SigningKey::generate(&mut rand::rngs::OsRng);
+    let forged = SignedPolicyBundle::sign(bundle(1), &rogue).unwrap();
+    assert!(engine.apply_policy(&mut vault, forged).is_err());
+
+    engine.apply_policy(&mut vault, signed.clone()).unwrap();
+    assert_eq!(
+        engine.settings().protection.protected_paths,
+        vec!["/srv/app".to_string()]
+    );
+
+    // Locked fields can't be edited locally; unlocked ones can.
+    let mut edited = engine.settings();
+    edited.protection.protected_paths.push("/home".into());
+    assert!(engine.update_settings(&mut vault, edited).is_err());
+    let mut edited = engine.settings();
+    edited.performance.max_cpu_percent = 50;
+    engine.update_settings(&mut vault, edited).unwrap();
+
+    // Replays / rollbacks and key swaps are refused while active.
+    assert!(engine.apply_policy(&mut vault, signed).is_err());
+    let rogue_key = org_key_b64(&rogue);
+    assert!(engine.set_policy_org_key(&mut vault, &rogue_key).is_err());
+
+    // A newer bundle supersedes the active one.
+    let mut v2 = bundle(2);
+    v2.settings.protected_paths = None;
+    engine
+        .apply_policy(&mut vault, SignedPolicyBundle::sign(v2, &org).unwrap())
+        .unwrap();
+    assert_eq!(engine.active_policy().unwrap().version, 2);
+
+    // Reloading from the vault keeps the policy in force.
+    let mut reopened = Vault::open(&vault_path, "pw").unwrap();
+    let engine = Engine::load_from_vault(&reopened).unwrap();
+    assert_eq!(engine.active_policy().unwrap().version, 2);
+    assert_eq!(engine.settings().performance.max_cpu_percent, 50);
+
+    // Once a newer bundle expires, an older one can't be replayed.
+    let v2_signed = load_policy_bundle(&reopened).unwrap().unwrap();
+    let mut v3 = bundle(3);
+    v3.expires_at = Some(Utc::now() + chrono::Duration::seconds(1));
+    engine
+        .apply_policy(&mut reopened, SignedPolicyBundle::sign(v3, &org).unwrap())
+        .unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    assert!(engine.active_policy().is_none());
+    assert!(engine.apply_policy(&mut reopened, v2_signed).is_err());
+    engine
+        .apply_policy(&mut reopened, SignedPolicyBundle::sign(bundle(4), &org).unwrap())
+        .unwrap();
+}
+
+#[test]
+fn settings_history_rolls_back_except_policy_locked_fields() {
+    let dir = tempdir().unwrap();
+    let mut vault = Vault::create_new(dir.path().join("vault.dat"), "pw").unwrap();
+    let engine = Engine::load_from_vault(&vault).unwrap();
+    let original = engine.settings();
+
+    let mut edited = original.clone();
+    edited.performance.max_cpu_percent = 50;
+    edited.protection.protected_paths = vec!["/srv/old".into()];
+    let recorded = engine
+        .update_settings_by(&mut vault, edited, "local:ui pid:42", Some("alice"), None)
+        .unwrap()
+        .unwrap();
+    assert_eq!(recorded.version, 2);
+    assert_eq!(recorded.changed_by, "local:ui pid:42");
+    assert_eq!(recorded.note.as_deref(), Some("alice"));
+    assert_eq!(recorded.changes.len(), 2);
+    assert!(engine
+        .update_settings_by(&mut vault, engine.settings(), "local:ui pid:42", None, None)
+        .unwrap()
+        .is_none());
+
+    // Version 1 is the settings from before the first recorded change.
+    let restored = engine.settings_version(&vault, 1).unwrap();
+    let rollback = engine
+        .update_settings_by(&mut vault, restored, "local:ui pid:43", None, Some(1))
+        .unwrap()
+        .unwrap();
+    assert_eq!(rollback.rollback_of, Some(1));
+    assert_eq!(
+        engine.settings().performance.max_cpu_percent,
+        original.performance.max_cpu_percent
+    );
+
+    // A rollback that would undo a policy-locked field is refused.
+    let org = SigningKey::generate(&mut rand::rngs::OsRng);
+    engine.set_policy_org_key(&mut vault, &org_key_b64(&org)).unwrap();
+    engine
+        .apply_policy(&mut vault, SignedPolicyBundle::sign(bundle(1), &org).unwrap())
+        .unwrap();
+    let locked = engine.settings_version(&vault, 2).unwrap();
+    assert!(engine
+        .update_settings_by(&mut vault, locked, "local:ui pid:43", None, Some(2))
+        .is_err());
+    assert!(engine.settings_version(&vault, 99).is_err());
+}
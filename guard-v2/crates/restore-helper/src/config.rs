@@ -0,0 +1,177 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use ed25519_dalek::VerifyingKey;
+use guard_core::policy::decode_org_key;
+use guard_core::privileged::{HelperRequest, MAX_REQUEST_AGE_SECS};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+
+/// Installed alongside the helper, owned by root.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HelperConfig {
+    /// base64 device public key of the service allowed to call the helper.
+    pub service_public_key: String,
+    /// The only paths the helper acts on, and everything beneath them.
+    pub approved_paths: Vec<PathBuf>,
+    pub quarantine_dir: PathBuf,
+    /// Call log and seen nonces.
+    pub state_dir: PathBuf,
+}
+
+#[derive(Serialize)]
+struct CallRecord<'a> {
+    at: DateTime<Utc>,
+    op: Option<&'a str>,
+    path: Option<&'a str>,
+    nonce: Option<&'a str>,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a str>,
+}
+
+impl HelperConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        #[cfg(unix)]
+        if unsafe { libc::geteuid() } == 0 {
+            use std::os::unix::fs::MetadataExt;
+            let meta = fs::metadata(path).with_context(|| format!("stat {}", path.display()))?;
+            if meta.uid() != 0 || meta.mode() & 0o022 != 0 {
+                return Err(anyhow!(
+                    "{} must be owned by root and not writable by others",
+                    path.display()
+                ));
+            }
+        }
+        let data = fs::read(path).with_context(|| format!("read {}", path.display()))?;
+        let config: Self = serde_json::from_slice(&data).context("parse helper config")?;
+        if config.approved_paths.iter().any(|p| !p.is_absolute()) {
+            return Err(anyhow!("approved paths must be absolute"));
+        }
+        Ok(config)
+    }
+
+    pub fn service_key(&self) -> Result<VerifyingKey> {
+        decode_org_key(&self.service_public_key)
+    }
+
+    /// `path` if it lies under an approved path without `..` tricks or a
+    /// symlinked directory leading elsewhere, resolved so the caller acts on
+    /// the directory that was checked. A symlink at `path` itself is refused.
+    pub fn approve(&self, path: &str) -> Result<PathBuf> {
+        let target = Path::new(path);
+        if !target.is_absolute()
+            || target
+                .components()
+                .any(|c| matches!(c, Component::ParentDir | Component::CurDir))
+        {
+            return Err(anyhow!("{path} is not a plain absolute path"));
+        }
+        let root = self
+            .approved_paths
+            .iter()
+            .find(|root| target.starts_with(root))
+            .ok_or_else(|| anyhow!("{path} is not under an approved path"))?;
+        let parent = target
+            .parent()
+            .ok_or_else(|| anyhow!("{path} has no parent directory"))?;
+        // The approved path itself may be a file; compare from its directory.
+        let root_dir = if target == root.as_path() {
+            parent
+        } else {
+            root.as_path()
+        };
+        let real_parent = parent
+            .canonicalize()
+            .with_context(|| format!("resolve {}", parent.display()))?;
+        let real_root = root_dir
+            .canonicalize()
+            .with_context(|| format!("resolve {}", root_dir.display()))?;
+        if !real_parent.starts_with(&real_root) {
+            return Err(anyhow!("{path} resolves outside its approved path"));
+        }
+        let file_name = target
+            .file_name()
+            .ok_or_else(|| anyhow!("{path} has no file name"))?;
+        let resolved = real_parent.join(file_name);
+        match fs::symlink_metadata(&resolved) {
+            Ok(meta) if meta.file_type().is_symlink() => {
+                Err(anyhow!("{path} is a symlink"))
+            }
+            Ok(_) => Ok(resolved),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(resolved),
+            Err(e) => Err(e).with_context(|| format!("stat {}", resolved.display())),
+        }
+    }
+
+    /// Record `nonce` as used, refusing one already seen. Nonces older than
+    /// any valid request are forgotten. Concurrent calls are serialised on
+    /// `nonces.lock`; an unreadable `nonces.json` refuses every request
+    /// until it is repaired rather than forgetting the nonces in it.
+    pub fn claim_nonce(&self, request: &HelperRequest) -> Result<()> {
+        fs::create_dir_all(&self.state_dir)?;
+        let _lock = lock_file(&self.state_dir.join("nonces.lock"))?;
+        let path = self.state_dir.join("nonces.json");
+        let mut seen: BTreeMap<String, DateTime<Utc>> = match fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data)
+                .with_context(|| format!("{} is corrupt", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e).with_context(|| format!("read {}", path.display())),
+        };
+        let horizon = Utc::now() - Duration::seconds(2 * MAX_REQUEST_AGE_SECS);
+        seen.retain(|_, at| *at > horizon);
+        if seen.contains_key(&request.nonce) {
+            return Err(anyhow!("request nonce already used"));
+        }
+        seen.insert(request.nonce.clone(), request.issued_at);
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(&seen)?)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    /// Append one line to `helper.log` for a call, accepted or not.
+    pub fn log_call(&self, request: Option<&HelperRequest>, error: Option<&str>) -> Result<()> {
+        fs::create_dir_all(&self.state_dir)?;
+        let record = CallRecord {
+            at: Utc::now(),
+            op: request.map(|r| r.op.name()),
+            path: request.map(|r| r.op.path()),
+            nonce: request.map(|r| r.nonce.as_str()),
+            ok: error.is_none(),
+            error,
+        };
+        let mut line = serde_json::to_vec(&record)?;
+        line.push(b'\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.state_dir.join("helper.log"))?
+            .write_all(&line)?;
+        Ok(())
+    }
+}
+
+/// Open `path` and hold an exclusive lock on it until the file is dropped.
+fn lock_file(path: &Path) -> Result<fs::File> {
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+        .with_context(|| format!("open {}", path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::io::AsRawFd;
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+            return Err(anyhow!(
+                "lock {}: {}",
+                path.display(),
+                std::io::Error::last_os_error()
+            ));
+        }
+    }
+    Ok(file)
+}
@@ -0,0 +1,109 @@
+//! Darklock privileged restore helper.
+//!
+//! Started elevated by the service for a single request (see
+//! `guard_core::privileged`): reads one signed request from stdin, checks
+//! it against the root-owned config, performs the restore or quarantine and
+//! writes a `HelperResponse` to stdout. Every call, refused or not, is
+//! appended to `{state_dir}/helper.log`.
+//!
+//! Install per OS:
+//!  * Linux — polkit action `com.darklock.guard.restore-helper` with a rule
+//!    allowing only the service account (`packaging/linux`);
+//!  * macOS — a sudoers rule letting the service account run the helper
+//!    without a password (`packaging/macos`);
+//!  * Windows — not needed: the service runs as LocalSystem.
+
+mod config;
+mod ops;
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use clap::Parser;
+use guard_core::privileged::{HelperOp, HelperRequest, HelperResponse, SignedHelperRequest};
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use crate::config::HelperConfig;
+
+/// Largest request read from stdin; bounds the size of a restored file.
+const MAX_REQUEST_BYTES: u64 = 256 * 1024 * 1024;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Darklock privileged restore helper", long_about = None)]
+struct Cli {
+    #[arg(long, default_value = "/etc/darklock/restore-helper.json")]
+    config: PathBuf,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let response = match run(&cli) {
+        Ok(response) => response,
+        Err(e) => HelperResponse {
+            ok: false,
+            error: Some(format!("{e:#}")),
+            quarantine_path: None,
+        },
+    };
+    println!(
+        "{}",
+        serde_json::to_string(&response).unwrap_or_else(|_| "{\"ok\":false}".into())
+    );
+    if response.ok {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn run(cli: &Cli) -> Result<HelperResponse> {
+    let config = HelperConfig::load(&cli.config)?;
+    let mut input = String::new();
+    std::io::stdin()
+        .take(MAX_REQUEST_BYTES)
+        .read_to_string(&mut input)?;
+    let signed: SignedHelperRequest = match serde_json::from_str(&input) {
+        Ok(signed) => signed,
+        Err(e) => {
+            let error = format!("malformed request: {e}");
+            config.log_call(None, Some(&error))?;
+            return Err(anyhow!(error));
+        }
+    };
+    let result = handle(&config, &signed);
+    let error = result.as_ref().err().map(|e| format!("{e:#}"));
+    config.log_call(Some(&signed.request), error.as_deref())?;
+    result
+}
+
+fn handle(config: &HelperConfig, signed: &SignedHelperRequest) -> Result<HelperResponse> {
+    signed.verify(&config.service_key()?, Utc::now())?;
+    let HelperRequest { op, .. } = &signed.request;
+    let target = config.approve(op.path())?;
+    config.claim_nonce(&signed.request)?;
+    match op {
+        HelperOp::Restore {
+            hash,
+            content,
+            mode,
+            uid,
+            gid,
+            ..
+        } => {
+            ops::restore(&target, hash, content, *mode, *uid, *gid)?;
+            Ok(HelperResponse {
+                ok: true,
+                ..Default::default()
+            })
+        }
+        HelperOp::Quarantine { .. } => {
+            let moved = ops::quarantine(&target, &config.quarantine_dir)?;
+            Ok(HelperResponse {
+                ok: true,
+                quarantine_path: moved.map(|p| p.display().to_string()),
+                ..Default::default()
+            })
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::Utc;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Same prefix as the service's own staging files, so its startup cleanup
+/// also removes ours.
+const STAGING_PREFIX: &str = ".darklock_restore_";
+
+/// Write `content` next to `target`, give it `mode` and ownership, and
+/// rename it into place.
+pub fn restore(
+    target: &Path,
+    hash: &str,
+    content: &str,
+    mode: u32,
+    uid: Option<u32>,
+    gid: Option<u32>,
+) -> Result<()> {
+    let data = general_purpose::STANDARD
+        .decode(content)
+        .map_err(|e| anyhow!("decode content: {e}"))?;
+    if blake3::hash(&data).to_hex().as_str() != hash {
+        return Err(anyhow!("content does not match hash {hash}"));
+    }
+    let parent = target
+        .parent()
+        .ok_or_else(|| anyhow!("no parent dir for {}", target.display()))?;
+    let staging = parent.join(format!("{STAGING_PREFIX}helper_{}", staging_suffix()));
+    let result = (|| {
+        let mut file = no_follow(OpenOptions::new().write(true).create_new(true))
+            .open(&staging)
+            .with_context(|| format!("create staging {}", staging.display()))?;
+        file.write_all(&data)?;
+        set_attributes(&file, mode, uid, gid)?;
+        file.sync_all()?;
+        fs::rename(&staging, target).with_context(|| format!("rename into {}", target.display()))
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&staging);
+    }
+    result?;
+    #[cfg(unix)]
+    if let Ok(dir) = File::open(parent) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+/// Move `source` into `quarantine_dir`. Returns where it went, or `None`
+/// if it was already gone.
+pub fn quarantine(source: &Path, quarantine_dir: &Path) -> Result<Option<PathBuf>> {
+    if fs::symlink_metadata(source).is_err() {
+        return Ok(None);
+    }
+    fs::create_dir_all(quarantine_dir)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(quarantine_dir, fs::Permissions::from_mode(0o700))?;
+    }
+    let name = source
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "unknown".into());
+    let dest = quarantine_dir.join(format!(
+        "{}_{}",
+        Utc::now().format("%Y%m%dT%H%M%S%.3f"),
+        name
+    ));
+    if fs::rename(source, &dest).is_err() {
+        // Different filesystem. Copy the file itself, never what a link
+        // swapped in since approval points at.
+        if !fs::symlink_metadata(source)?.file_type().is_file() {
+            return Err(anyhow!("{} is not a regular file", source.display()));
+        }
+        let mut from = no_follow(OpenOptions::new().read(true))
+            .open(source)
+            .with_context(|| format!("open {}", source.display()))?;
+        let mut to = no_follow(OpenOptions::new().write(true).create_new(true))
+            .open(&dest)
+            .with_context(|| format!("create {}", dest.display()))?;
+        std::io::copy(&mut from, &mut to)
+            .with_context(|| format!("copy {} to quarantine", source.display()))?;
+        to.sync_all()?;
+        fs::remove_file(source)?;
+    }
+    Ok(Some(dest))
+}
+
+/// Refuse to open through a symlink at the final path component.
+fn no_follow(options: &mut OpenOptions) -> &mut OpenOptions {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.custom_flags(libc::O_NOFOLLOW);
+    }
+    options
+}
+
+fn staging_suffix() -> String {
+    format!("{:x}", Utc::now().timestamp_nanos_opt().unwrap_or_default())
+}
+
+#[cfg(unix)]
+fn set_attributes(file: &File, mode: u32, uid: Option<u32>, gid: Option<u32>) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::io::AsRawFd;
+    if uid.is_some() || gid.is_some() {
+        // -1 leaves that id unchanged.
+        let ret = unsafe {
+            libc::fchown(
+                file.as_raw_fd(),
+                uid.unwrap_or(u32::MAX),
+                gid.unwrap_or(u32::MAX),
+            )
+        };
+        if ret != 0 {
+            return Err(anyhow!("chown: {}", std::io::Error::last_os_error()));
+        }
+    }
+    if mode != 0 {
+        file.set_permissions(fs::Permissions::from_mode(mode & 0o7777))?;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_attributes(_file: &File, _mode: u32, _uid: Option<u32>, _gid: Option<u32>) -> Result<()> {
+    Ok(())
+}
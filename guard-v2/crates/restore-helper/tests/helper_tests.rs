@@ -0,0 +1,169 @@
+//! Drives the helper binary the way the service does: one signed request
+//! on stdin, one response on stdout.
+
+use assert_cmd::Command;
+use base64::{engine::general_purpose, Engine as _};
+use ed25519_dalek::SigningKey;
+use guard_core::privileged::{HelperOp, HelperRequest, HelperResponse, SignedHelperRequest};
+use rand::rngs::OsRng;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::{tempdir, TempDir};
+
+struct Fixture {
+    dir: TempDir,
+    service: SigningKey,
+    approved: PathBuf,
+    config: PathBuf,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let approved = root.join("etc-app");
+        fs::create_dir_all(&approved).unwrap();
+        let service = SigningKey::generate(&mut OsRng);
+        let config = root.join("restore-helper.json");
+        fs::write(
+            &config,
+            serde_json::to_vec(&serde_json::json!({
+                "service_public_key": general_purpose::STANDARD.encode(service.verifying_key().to_bytes()),
+                "approved_paths": [approved],
+                "quarantine_dir": root.join("quarantine"),
+                "state_dir": root.join("state"),
+            }))
+            .unwrap(),
+        )
+        .unwrap();
+        Self {
+            dir,
+            service,
+            approved,
+            config,
+        }
+    }
+
+    fn sign(&self, op: HelperOp) -> SignedHelperRequest {
+        SignedHelperRequest::sign(HelperRequest::new(op), &self.service).unwrap()
+    }
+
+    fn call(&self, request: &SignedHelperRequest) -> HelperResponse {
+        let output = Command::new(assert_cmd::cargo::cargo_bin!("darklock-restore-helper"))
+            .arg("--config")
+            .arg(&self.config)
+            .write_stdin(serde_json::to_vec(request).unwrap())
+            .output()
+            .unwrap();
+        let response: HelperResponse = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(output.status.success(), response.ok);
+        response
+    }
+
+    fn log_lines(&self) -> Vec<serde_json::Value> {
+        fs::read_to_string(self.dir.path().join("state/helper.log"))
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect()
+    }
+}
+
+fn restore_op(path: &Path, content: &[u8]) -> HelperOp {
+    HelperOp::Restore {
+        path: path.display().to_string(),
+        hash: blake3::hash(content).to_hex().to_string(),
+        content: general_purpose::STANDARD.encode(content),
+        mode: 0o640,
+        uid: None,
+        gid: None,
+    }
+}
+
+#[test]
+fn restores_and_quarantines_approved_paths_only() {
+    let fx = Fixture::new();
+    let target = fx.approved.join("app.conf");
+    fs::write(&target, b"tampered").unwrap();
+
+    let request = fx.sign(restore_op(&target, b"trusted"));
+    assert!(fx.call(&request).ok);
+    assert_eq!(fs::read(&target).unwrap(), b"trusted");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(&target).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+    }
+
+    // The same request can't be replayed.
+    let replay = fx.call(&request);
+    assert!(replay.error.unwrap().contains("nonce"));
+
+    // Outside the approved path, or escaping it via `..`.
+    let outside = fx.dir.path().join("outside.conf");
+    assert!(!fx.call(&fx.sign(restore_op(&outside, b"x"))).ok);
+    let dotted = fx.approved.join("../outside.conf");
+    assert!(!fx.call(&fx.sign(restore_op(&dotted, b"x"))).ok);
+    assert!(!outside.exists());
+
+    // A symlinked directory inside the approved path leading out of it.
+    #[cfg(unix)]
+    {
+        let elsewhere = fx.dir.path().join("elsewhere");
+        fs::create_dir_all(&elsewhere).unwrap();
+        std::os::unix::fs::symlink(&elsewhere, fx.approved.join("link")).unwrap();
+        let via_link = fx.approved.join("link/app.conf");
+        assert!(!fx.call(&fx.sign(restore_op(&via_link, b"x"))).ok);
+        assert!(!elsewhere.join("app.conf").exists());
+
+        // Or a symlink in place of the file itself.
+        let outside_file = elsewhere.join("target.conf");
+        fs::write(&outside_file, b"untouched").unwrap();
+        let file_link = fx.approved.join("file-link.conf");
+        std::os::unix::fs::symlink(&outside_file, &file_link).unwrap();
+        assert!(!fx.call(&fx.sign(restore_op(&file_link, b"x"))).ok);
+        assert_eq!(fs::read(&outside_file).unwrap(), b"untouched");
+    }
+
+    // Content that doesn't match its hash.
+    let mut mismatched = restore_op(&target, b"trusted");
+    if let HelperOp::Restore { content, .. } = &mut mismatched {
+        *content = general_purpose::STANDARD.encode(b"evil");
+    }
+    assert!(!fx.call(&fx.sign(mismatched)).ok);
+
+    // Signed by anyone but the service.
+    let forged = SignedHelperRequest::sign(
+        HelperRequest::new(restore_op(&target, b"evil")),
+        &SigningKey::generate(&mut OsRng),
+    )
+    .unwrap();
+    assert!(!fx.call(&forged).ok);
+    assert_eq!(fs::read(&target).unwrap(), b"trusted");
+
+    let quarantined = fx.call(&fx.sign(HelperOp::Quarantine {
+        path: target.display().to_string(),
+    }));
+    assert!(quarantined.ok);
+    assert!(!target.exists());
+    assert_eq!(
+        fs::read(quarantined.quarantine_path.unwrap()).unwrap(),
+        b"trusted"
+    );
+
+    // Every call was logged, refused ones included.
+    let log = fx.log_lines();
+    assert_eq!(log.len(), 9);
+    assert_eq!(log.iter().filter(|l| l["ok"] == true).count(), 2);
+    assert_eq!(log[0]["op"], "restore");
+    assert_eq!(log[8]["op"], "quarantine");
+
+    // A corrupt nonce record refuses requests instead of starting over.
+    let nonces = fx.dir.path().join("state/nonces.json");
+    fs::write(&nonces, b"{not json").unwrap();
+    fs::write(&target, b"tampered").unwrap();
+    assert!(!fx.call(&fx.sign(restore_op(&target, b"trusted"))).ok);
+    assert_eq!(fs::read(&target).unwrap(), b"tampered");
+    assert_eq!(fs::read(&nonces).unwrap(), b"{not json");
+}
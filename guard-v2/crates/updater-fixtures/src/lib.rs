@@ -0,0 +1,275 @@
+//! Test fixtures for driving `updater-helper` end to end.
+//!
+//! [`ReleaseServer`] is a minimal HTTP/1.1 server on a loopback port that
+//! serves whatever has been published to it, so the helper downloads over
+//! real HTTP the same way it does from the release CDN. [`ReleaseFixture`]
+//! pairs a server with a throwaway release signing key and builds signed
+//! packages and manifests against it.
+//!
+//! Only the standard library is used for the server; it answers `GET` and
+//! nothing else, which is all the helper needs.
+
+use base64::{engine::general_purpose, Engine as _};
+use ed25519_dalek::{Signer, SigningKey};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use tempfile::TempDir;
+
+/// Environment variable the helper reads the release public key from.
+pub const RELEASE_PUBKEY_ENV: &str = "DARKLOCK_RELEASE_PUBKEY_B64";
+
+#[derive(Default)]
+struct Shared {
+    files: HashMap<String, Vec<u8>>,
+    requests: Vec<String>,
+}
+
+/// Loopback HTTP server for release manifests and packages. Stops on drop.
+pub struct ReleaseServer {
+    addr: String,
+    shared: Arc<Mutex<Shared>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ReleaseServer {
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind release server");
+        let addr = listener.local_addr().unwrap().to_string();
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let shared = shared.clone();
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    if let Ok(stream) = stream {
+                        let _ = serve(stream, &shared);
+                    }
+                }
+            })
+        };
+        Self {
+            addr,
+            shared,
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    /// Full URL of `path` (which starts with `/`) on this server.
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    /// Serve `body` at `path` from now on, replacing anything there.
+    pub fn publish(&self, path: &str, body: Vec<u8>) {
+        self.shared.lock().unwrap().files.insert(path.into(), body);
+    }
+
+    /// Stop serving `path`; requests for it get a 404.
+    pub fn unpublish(&self, path: &str) {
+        self.shared.lock().unwrap().files.remove(path);
+    }
+
+    /// Paths requested so far, in order.
+    pub fn requests(&self) -> Vec<String> {
+        self.shared.lock().unwrap().requests.clone()
+    }
+}
+
+impl Drop for ReleaseServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // Wake the accept loop so it sees the flag.
+        let _ = TcpStream::connect(&self.addr);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn serve(stream: TcpStream, shared: &Mutex<Shared>) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Drain the headers; the helper never sends a body.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+
+    let body = {
+        let mut shared = shared.lock().unwrap();
+        shared.requests.push(path.to_string());
+        shared.files.get(path).cloned()
+    };
+    let (status, body) = match (method, body) {
+        ("GET", Some(body)) => ("200 OK", body),
+        ("GET", None) => ("404 Not Found", b"not found".to_vec()),
+        _ => ("405 Method Not Allowed", Vec::new()),
+    };
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Length: {}\r\nContent-Type: application/octet-stream\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    stream.write_all(&body)?;
+    stream.flush()
+}
+
+/// A package as published: its bytes and the values a manifest carries.
+#[derive(Debug, Clone)]
+pub struct Release {
+    pub version: String,
+    pub package: Vec<u8>,
+    pub sha256: String,
+    pub signature: String,
+}
+
+/// A release server, a release signing key and a scratch directory.
+pub struct ReleaseFixture {
+    pub server: ReleaseServer,
+    pub dir: TempDir,
+    signing: SigningKey,
+}
+
+impl Default for ReleaseFixture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReleaseFixture {
+    pub fn new() -> Self {
+        Self {
+            server: ReleaseServer::start(),
+            dir: tempfile::tempdir().expect("fixture dir"),
+            signing: SigningKey::generate(&mut rand::rngs::OsRng),
+        }
+    }
+
+    /// The release public key, as the helper expects it in
+    /// [`RELEASE_PUBKEY_ENV`].
+    pub fn pubkey_b64(&self) -> String {
+        general_purpose::STANDARD.encode(self.signing.verifying_key().to_bytes())
+    }
+
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.path().join(name)
+    }
+
+    /// Build and sign a package holding `files` (relative path, contents).
+    pub fn build_release(&self, version: &str, files: &[(&str, &[u8])]) -> Release {
+        let package = tar_gz(files);
+        Release {
+            version: version.into(),
+            sha256: hex::encode(Sha256::digest(&package)),
+            signature: general_purpose::STANDARD.encode(self.signing.sign(&package).to_bytes()),
+            package,
+        }
+    }
+
+    /// Publish `release` at `/packages/<version>.tar.gz` and its manifest at
+    /// `/manifests/<version>.json`, and save the manifest locally. Returns
+    /// the local manifest path, which is what `stage --manifest` takes.
+    pub fn publish(&self, release: &Release, revoked: bool) -> PathBuf {
+        let package_path = format!("/packages/{}.tar.gz", release.version);
+        self.server.publish(&package_path, release.package.clone());
+        self.publish_manifest(release, &self.server.url(&package_path), revoked)
+    }
+
+    /// Publish a manifest for `release` pointing at `download_url`, which
+    /// need not serve the release — for testing mismatches.
+    pub fn publish_manifest(
+        &self,
+        release: &Release,
+        download_url: &str,
+        revoked: bool,
+    ) -> PathBuf {
+        let manifest = serde_json::json!({
+            "version": release.version,
+            "download_url": download_url,
+            "sha256": release.sha256,
+            "signature": release.signature,
+            "revoked": revoked,
+        });
+        let body = serde_json::to_vec_pretty(&manifest).unwrap();
+        self.server.publish(
+            &format!("/manifests/{}.json", release.version),
+            body.clone(),
+        );
+        let local = self.path(&format!("manifest-{}.json", release.version));
+        std::fs::write(&local, body).unwrap();
+        local
+    }
+
+    /// Write a version file pinning `updater_bin`'s hash, as shipped next to
+    /// the installed helper.
+    pub fn version_file(&self, updater_bin: &Path) -> PathBuf {
+        let hash = hex::encode(Sha256::digest(std::fs::read(updater_bin).unwrap()));
+        let path = self.path("version.json");
+        let body = serde_json::json!({
+            "version": "fixture",
+            "updater_sha256": hash,
+            "release_pubkey": self.pubkey_b64(),
+        });
+        std::fs::write(&path, body.to_string()).unwrap();
+        path
+    }
+
+    /// A command for `updater_bin` that trusts this fixture's release key
+    /// and reaches the server directly.
+    pub fn command(&self, updater_bin: &Path) -> Command {
+        let mut cmd = Command::new(updater_bin);
+        cmd.env(RELEASE_PUBKEY_ENV, self.pubkey_b64())
+            .env("NO_PROXY", "127.0.0.1")
+            .env_remove("HTTP_PROXY")
+            .env_remove("http_proxy");
+        cmd
+    }
+}
+
+/// gzip'd tarball of `files`.
+pub fn tar_gz(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let enc = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    let mut tar = tar::Builder::new(enc);
+    for (name, contents) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        tar.append_data(&mut header, name, *contents).unwrap();
+    }
+    tar.into_inner().unwrap().finish().unwrap()
+}
+
+/// Self-test commands for `post-check`, which splits on whitespace.
+pub fn passing_cmd() -> &'static str {
+    if cfg!(windows) {
+        "cmd /C exit 0"
+    } else {
+        "true"
+    }
+}
+
+pub fn failing_cmd() -> &'static str {
+    if cfg!(windows) {
+        "cmd /C exit 1"
+    } else {
+        "false"
+    }
+}
@@ -0,0 +1,195 @@
+//! End-to-end update flows against a local release server.
+//!
+//! Each test publishes signed releases on an `updater_fixtures` server and
+//! drives the real helper binary through stage → install → post-check, the
+//! way the service does.
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+use updater_fixtures::{failing_cmd, passing_cmd, ReleaseFixture};
+
+fn updater(fixture: &ReleaseFixture) -> Command {
+    Command::from_std(fixture.command(assert_cmd::cargo::cargo_bin!("updater-helper")))
+}
+
+fn stage(fixture: &ReleaseFixture, manifest: &Path) -> PathBuf {
+    let out = fixture.path("staged.tar.gz");
+    let stdout = updater(fixture)
+        .arg("stage")
+        .arg("--manifest")
+        .arg(manifest)
+        .arg("--output")
+        .arg(&out)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    assert_eq!(
+        String::from_utf8_lossy(&stdout).trim(),
+        out.display().to_string()
+    );
+    out
+}
+
+/// Install `package`; returns the backup manifest of what it replaced.
+fn install(fixture: &ReleaseFixture, package: &Path) -> String {
+    let version_file = fixture.version_file(assert_cmd::cargo::cargo_bin!("updater-helper"));
+    let stdout = updater(fixture)
+        .arg("install")
+        .arg("--package")
+        .arg(package)
+        .arg("--install-dir")
+        .arg(fixture.path("install"))
+        .arg("--backup-dir")
+        .arg(fixture.path("backup"))
+        .arg("--version-file")
+        .arg(version_file)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    String::from_utf8_lossy(&stdout).trim().to_string()
+}
+
+fn post_check(
+    fixture: &ReleaseFixture,
+    test_cmd: &str,
+    backup_manifest: &str,
+) -> assert_cmd::assert::Assert {
+    updater(fixture)
+        .arg("post-check")
+        .arg("--test-cmd")
+        .arg(test_cmd)
+        .arg("--backup-manifest")
+        .arg(backup_manifest)
+        .arg("--install-dir")
+        .arg(fixture.path("install"))
+        .assert()
+}
+
+fn prepare_dirs(fixture: &ReleaseFixture) {
+    fs::create_dir_all(fixture.path("install")).unwrap();
+    fs::create_dir_all(fixture.path("backup")).unwrap();
+}
+
+#[test]
+fn release_is_staged_over_http_installed_and_checked() {
+    let fixture = ReleaseFixture::new();
+    prepare_dirs(&fixture);
+    let release = fixture.build_release(
+        "2.1.0",
+        &[("bin/guard", b"guard 2.1.0"), ("VERSION", b"2.1.0")],
+    );
+    let manifest = fixture.publish(&release, false);
+
+    let staged = stage(&fixture, &manifest);
+    assert_eq!(fs::read(&staged).unwrap(), release.package);
+    assert_eq!(fixture.server.requests(), vec!["/packages/2.1.0.tar.gz"]);
+
+    let backup = install(&fixture, &staged);
+    post_check(&fixture, passing_cmd(), &backup).success();
+    let install_dir = fixture.path("install");
+    assert_eq!(
+        fs::read(install_dir.join("bin/guard")).unwrap(),
+        b"guard 2.1.0"
+    );
+    assert_eq!(fs::read(install_dir.join("VERSION")).unwrap(), b"2.1.0");
+}
+
+#[test]
+fn failed_post_check_rolls_back_to_previous_release() {
+    let fixture = ReleaseFixture::new();
+    prepare_dirs(&fixture);
+    let old = fixture.build_release("2.0.0", &[("VERSION", b"2.0.0")]);
+    let new = fixture.build_release("2.1.0", &[("VERSION", b"2.1.0")]);
+
+    let staged = stage(&fixture, &fixture.publish(&old, false));
+    install(&fixture, &staged);
+    // Backups are named to the second; keep the two installs apart.
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    let staged = stage(&fixture, &fixture.publish(&new, false));
+    let backup = install(&fixture, &staged);
+    assert_eq!(fs::read(fixture.path("install/VERSION")).unwrap(), b"2.1.0");
+
+    post_check(&fixture, failing_cmd(), &backup)
+        .failure()
+        .stderr(predicate::str::contains("rolled back"));
+    assert_eq!(fs::read(fixture.path("install/VERSION")).unwrap(), b"2.0.0");
+}
+
+#[test]
+fn revoked_release_is_refused_before_download() {
+    let fixture = ReleaseFixture::new();
+    let release = fixture.build_release("2.1.0", &[("VERSION", b"2.1.0")]);
+    let manifest = fixture.publish(&release, true);
+
+    updater(&fixture)
+        .arg("stage")
+        .arg("--manifest")
+        .arg(manifest)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("revoked"));
+    assert!(fixture.server.requests().is_empty());
+}
+
+#[test]
+fn package_swapped_on_server_fails_hash_check() {
+    let fixture = ReleaseFixture::new();
+    let release = fixture.build_release("2.1.0", &[("VERSION", b"2.1.0")]);
+    let manifest = fixture.publish(&release, false);
+    let evil = fixture.build_release("2.1.0", &[("VERSION", b"evil")]);
+    fixture
+        .server
+        .publish("/packages/2.1.0.tar.gz", evil.package);
+
+    updater(&fixture)
+        .arg("stage")
+        .arg("--manifest")
+        .arg(manifest)
+        .arg("--output")
+        .arg(fixture.path("staged.tar.gz"))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("hash mismatch"));
+}
+
+#[test]
+fn release_signed_by_another_key_is_refused() {
+    let fixture = ReleaseFixture::new();
+    let other = ReleaseFixture::new();
+    let release = other.build_release("2.1.0", &[("VERSION", b"2.1.0")]);
+    let manifest = fixture.publish(&release, false);
+
+    updater(&fixture)
+        .arg("stage")
+        .arg("--manifest")
+        .arg(manifest)
+        .arg("--output")
+        .arg(fixture.path("staged.tar.gz"))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("signature"));
+}
+
+#[test]
+fn missing_package_fails_download() {
+    let fixture = ReleaseFixture::new();
+    let release = fixture.build_release("2.1.0", &[("VERSION", b"2.1.0")]);
+    let manifest = fixture.publish(&release, false);
+    fixture.server.unpublish("/packages/2.1.0.tar.gz");
+
+    updater(&fixture)
+        .arg("stage")
+        .arg("--manifest")
+        .arg(manifest)
+        .arg("--output")
+        .arg(fixture.path("staged.tar.gz"))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("404"));
+}
@@ -191,7 +191,12 @@ async fn get_settings() -> Result<GuardSettings, String> {
 
 #[tauri::command]
 async fn update_settings(settings: GuardSettings) -> Result<(), String> {
-    match ipc_settings_request(IpcRequest::UpdateSettings { settings }).await? {
+    match ipc_settings_request(IpcRequest::UpdateSettings {
+        settings,
+        note: Some("desktop".into()),
+    })
+    .await?
+    {
         IpcResponse::SettingsUpdated => Ok(()),
         _ => Err("Unexpected IPC response".to_string()),
     }
@@ -219,6 +224,15 @@ async fn get_events() -> Result<serde_json::Value, String> {
                 let data = e.get("data").cloned().unwrap_or(serde_json::json!({}));
                 let seq = e.get("seq").and_then(|v| v.as_u64()).unwrap_or(0);
                 let hash = e.get("hash").and_then(|v| v.as_str()).unwrap_or("").to_string();
+                let notes: Vec<serde_json::Value> = e.get("notes")
+                    .and_then(|v| v.as_array())
+                    .map(|notes| notes.iter().map(|n| serde_json::json!({
+                        "seq": n.get("seq").cloned().unwrap_or_default(),
+                        "timestamp": n.get("timestamp").cloned().unwrap_or_default(),
+                        "note": n["data"].get("note").cloned().unwrap_or_default(),
+                        "author": n["data"].get("author").cloned().unwrap_or_default(),
+                    })).collect())
+                    .unwrap_or_default();
                 
                 // Build detail string from data
                 let detail = if let Some(obj) = data.as_object() {
@@ -255,6 +269,7 @@ async fn get_events() -> Result<serde_json::Value, String> {
                     "detail": detail,
                     "seq": seq,
                     "hash": hash,
+                    "notes": notes,
                 })
             }).collect();
             Ok(serde_json::json!({ "events": transformed }))
@@ -447,9 +462,13 @@ async fn update_rollback(_backup_manifest: String) -> Result<serde_json::Value,
 #[tauri::command]
 async fn create_baseline() -> Result<serde_json::Value, String> {
     match ipc_settings_request(IpcRequest::BaselineCreate).await {
-        Ok(IpcResponse::BaselineCreated { entries }) => {
-            Ok(serde_json::json!({ "entries": entries }))
-        }
+        Ok(IpcResponse::BaselineCreated {
+            entries,
+            pending_countersign,
+        }) => Ok(serde_json::json!({
+            "entries": entries,
+            "pending_countersign": pending_countersign,
+        })),
         Ok(_) => Err("Unexpected response".into()),
         Err(e) => Err(format!("Failed to create baseline: {}", e)),
     }
@@ -466,6 +485,17 @@ async fn verify_baseline() -> Result<serde_json::Value, String> {
     }
 }
 
+#[tauri::command]
+async fn estimate_path(path: String) -> Result<serde_json::Value, String> {
+    match ipc_settings_request(IpcRequest::EstimatePath { path }).await {
+        Ok(IpcResponse::PathEstimate { estimate }) => {
+            serde_json::to_value(estimate).map_err(|e| e.to_string())
+        }
+        Ok(_) => Err("Unexpected response".into()),
+        Err(e) => Err(format!("Failed to estimate path: {}", e)),
+    }
+}
+
 #[tauri::command]
 async fn send_crash_report(report: serde_json::Value) -> Result<serde_json::Value, String> {
     let platform_url = std::env::var("DARKLOCK_PLATFORM_URL")
@@ -571,6 +601,7 @@ fn main() {
             lock_vault,
             create_baseline,
             verify_baseline,
+            estimate_path,
             send_crash_report
         ])
         .run(tauri::generate_context!())
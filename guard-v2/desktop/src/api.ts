@@ -1,5 +1,6 @@
 import { invoke } from "@tauri-apps/api/core";
 import type { GuardSettings } from "./state/settings";
+import type { PathEstimate } from "./types";
 
 export const getSettings = () => invoke<GuardSettings>("get_settings");
 
@@ -9,3 +10,5 @@ export const updateSettings = (settings: GuardSettings) =>
 export const createBaseline = () => invoke<{ entries: number }>("create_baseline");
 
 export const verifyBaseline = () => invoke<{ valid: boolean; detail: any }>("verify_baseline");
+
+export const estimatePath = (path: string) => invoke<PathEstimate>("estimate_path", { path });
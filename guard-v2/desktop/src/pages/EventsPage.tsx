@@ -87,8 +87,8 @@ const EventsPage: React.FC = () => {
   };
 
   const exportEvents = () => {
-    const csv = ['Timestamp,Severity,Event,Detail,Data',
-      ...filtered.map(e => `"${e.timestamp}","${e.severity}","${e.event_type}","${e.detail || ''}","${JSON.stringify(e.data || {}).replace(/"/g, '""')}"`)
+    const csv = ['Timestamp,Severity,Event,Detail,Data,Notes',
+      ...filtered.map(e => `"${e.timestamp}","${e.severity}","${e.event_type}","${e.detail || ''}","${JSON.stringify(e.data || {}).replace(/"/g, '""')}","${(e.notes || []).map(n => `${n.author}: ${n.note}`).join('; ').replace(/"/g, '""')}"`)
     ].join('\n');
     const blob = new Blob([csv], { type: 'text/csv' });
     const a = document.createElement('a');
@@ -222,6 +222,18 @@ const EventsPage: React.FC = () => {
                             <span className="text-text-primary font-mono ml-2 text-[10px]">{event.hash}</span>
                           </div>
                         )}
+                        {event.notes && event.notes.length > 0 && (
+                          <div className="pt-2 border-t border-white/5 space-y-1">
+                            <span className="text-text-muted block">Notes:</span>
+                            {event.notes.map(n => (
+                              <div key={n.seq} className="text-text-primary">
+                                <span className="text-text-muted font-mono">{formatDate(n.timestamp)}</span>
+                                <span className="text-text-secondary ml-2">{n.author}:</span>
+                                <span className="ml-1">{n.note}</span>
+                              </div>
+                            ))}
+                          </div>
+                        )}
                       </div>
                     </div>
                   )}
@@ -1,7 +1,8 @@
 import React, { useEffect, useState } from 'react';
 import { useService } from '../state/service';
-import { getSettings, updateSettings, createBaseline, verifyBaseline } from '../api';
+import { getSettings, updateSettings, createBaseline, verifyBaseline, estimatePath } from '../api';
 import type { GuardSettings } from '../state/settings';
+import type { PathEstimate } from '../types';
 import { Shield, Lock, Unlock, Fingerprint, Eye, EyeOff, ToggleLeft, ToggleRight, AlertTriangle, FileKey, FolderLock, ShieldCheck, X, RefreshCw, CheckCircle2 } from 'lucide-react';
 import { open } from '@tauri-apps/plugin-dialog';
 
@@ -36,6 +37,17 @@ const Section: React.FC<React.PropsWithChildren<{ title: string; icon: React.Ele
   </div>
 );
 
+const formatBytes = (bytes: number) => {
+  const units = ['B', 'KB', 'MB', 'GB', 'TB'];
+  let value = bytes;
+  let unit = 0;
+  while (value >= 1024 && unit < units.length - 1) { value /= 1024; unit++; }
+  return `${value.toFixed(unit === 0 ? 0 : 1)} ${units[unit]}`;
+};
+
+const formatDuration = (secs: number) =>
+  secs < 1 ? 'under a second' : secs < 90 ? `${Math.round(secs)} s` : `${Math.round(secs / 60)} min`;
+
 const ProtectionPage: React.FC = () => {
   const { serviceAvailable, status } = useService();
   const [settings, setSettings] = useState<GuardSettings | null>(null);
@@ -45,6 +57,8 @@ const ProtectionPage: React.FC = () => {
   const [baselineCreating, setBaselineCreating] = useState(false);
   const [baselineVerifying, setBaselineVerifying] = useState(false);
   const [baselineInfo, setBaselineInfo] = useState<any>(null);
+  const [estimating, setEstimating] = useState(false);
+  const [estimate, setEstimate] = useState<PathEstimate | null>(null);
 
   useEffect(() => {
     if (!serviceAvailable) return;
@@ -188,7 +202,16 @@ const ProtectionPage: React.FC = () => {
                     setError('Directory already in the list');
                     setTimeout(() => setError(null), 2000);
                   } else {
-                    await save({ ...settings, protection: { ...settings.protection, protected_paths: [...paths, selected] } }, 'Directory added');
+                    setEstimating(true);
+                    try {
+                      setEstimate(await estimatePath(selected));
+                    } catch (e) {
+                      // Services without EstimatePath: add without a preview.
+                      console.warn('Path estimate unavailable:', e);
+                      await save({ ...settings, protection: { ...settings.protection, protected_paths: [...paths, selected] } }, 'Directory added');
+                    } finally {
+                      setEstimating(false);
+                    }
                   }
                 }
               } catch (e: any) {
@@ -197,16 +220,76 @@ const ProtectionPage: React.FC = () => {
                 setTimeout(() => setError(null), 3000);
               }
             }}
-            disabled={disabled}
+            disabled={disabled || estimating || !!estimate}
             className={`mt-3 px-4 py-2 rounded-lg text-xs font-medium transition-all border ${
-              disabled 
+              disabled || estimating || estimate 
                 ? 'text-text-muted bg-bg-secondary/30 border-white/5 cursor-not-allowed opacity-50' 
                 : 'text-accent-primary bg-accent-primary/10 border-accent-primary/30 hover:bg-accent-primary/20 hover:border-accent-primary/50 cursor-pointer'
             }`}
           >
-            + Add Directory
+            {estimating ? 'Estimating…' : '+ Add Directory'}
           </button>
 
+          {/* Pre-scan estimate for the picked directory */}
+          {estimate && settings && (
+            <div className="mt-3 p-4 rounded-lg bg-bg-secondary/50 border border-white/5 space-y-3">
+              <p className="text-xs font-mono text-text-secondary truncate">{estimate.path}</p>
+              <div className="grid grid-cols-2 sm:grid-cols-4 gap-3">
+                {[
+                  { label: 'Files', value: estimate.files.toLocaleString() },
+                  { label: 'Size', value: formatBytes(estimate.total_bytes) },
+                  { label: 'Baseline', value: formatDuration(estimate.estimated_baseline_secs) },
+                  { label: 'Watches', value: `${estimate.watches.toLocaleString()} / ${Math.max(estimate.watch_budget - estimate.watches_in_use, 0).toLocaleString()} free` },
+                ].map((item) => (
+                  <div key={item.label}>
+                    <p className="text-[11px] text-text-muted uppercase tracking-wider">{item.label}</p>
+                    <p className="text-sm font-mono text-text-primary mt-0.5">{item.value}</p>
+                  </div>
+                ))}
+              </div>
+              {estimate.covered_by && (
+                <p className="text-xs text-semantic-warning">Already covered by protected directory {estimate.covered_by}.</p>
+              )}
+              {estimate.exceeds_watch_budget && (
+                <p className="text-xs text-semantic-warning">Exceeds the watch budget: some directories will be polled instead of watched in real time.</p>
+              )}
+              {estimate.unreadable > 0 && (
+                <p className="text-xs text-text-muted">{estimate.unreadable.toLocaleString()} entries could not be read.</p>
+              )}
+              {estimate.suggested_exclusions.length > 0 && (
+                <div>
+                  <p className="text-[11px] text-text-muted uppercase tracking-wider mb-1">Consider excluding</p>
+                  {estimate.suggested_exclusions.map((s) => (
+                    <div key={s.path} className="flex items-center justify-between text-xs py-1">
+                      <span className="font-mono text-text-secondary truncate flex-1">{s.path}</span>
+                      <span className="text-text-muted ml-3">{s.reason} · {s.files.toLocaleString()} files, {formatBytes(s.total_bytes)}</span>
+                    </div>
+                  ))}
+                </div>
+              )}
+              <div className="flex gap-2">
+                <button
+                  onClick={async () => {
+                    const paths = settings.protection.protected_paths || [];
+                    const path = estimate.path;
+                    setEstimate(null);
+                    await save({ ...settings, protection: { ...settings.protection, protected_paths: [...paths, path] } }, 'Directory added');
+                  }}
+                  disabled={disabled}
+                  className="px-3 py-1.5 rounded-lg text-xs font-medium text-accent-primary bg-accent-primary/10 border border-accent-primary/30 hover:bg-accent-primary/20 disabled:opacity-50"
+                >
+                  Protect Directory
+                </button>
+                <button
+                  onClick={() => setEstimate(null)}
+                  className="px-3 py-1.5 rounded-lg text-xs font-medium text-text-muted border border-white/10 hover:text-text-primary"
+                >
+                  Cancel
+                </button>
+              </div>
+            </div>
+          )}
+
           {/* Baseline Management */}
           {(settings?.protection.protected_paths?.length || 0) > 0 && (
             <div className="mt-4 pt-4 border-t border-white/5 space-y-2">
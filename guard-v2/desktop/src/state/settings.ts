@@ -1,5 +1,7 @@
 export type SecurityMode = "Normal" | "Strict";
 
+export type SymlinkPolicy = "follow" | "no_follow" | "protect_link";
+
 export interface StrictModeSettings {
   require_password_for_settings: boolean;
   require_password_for_protection_changes: boolean;
@@ -16,6 +18,7 @@ export interface GuardSettings {
     baseline_locked: boolean;
     protected_paths: string[];
     quarantine_enabled: boolean;
+    symlink_policy?: SymlinkPolicy;
   };
   performance: {
     max_cpu_percent: number;
@@ -27,6 +27,15 @@ export type EventEntry = {
   detail?: string;
   seq?: number;
   hash?: string;
+  notes?: EventNote[];
+};
+
+/** Operator note logged as its own EVENT_NOTE entry. */
+export type EventNote = {
+  seq: number;
+  timestamp: string;
+  note: string;
+  author: string;
 };
 
 export type UpdateInfo = {
@@ -65,3 +74,29 @@ export type SystemMetrics = {
   memory_total_mb: number;
   memory_percent: number;
 };
+
+/** Directory the service suggests leaving out of protection. */
+export type SuggestedExclusion = {
+  path: string;
+  reason: string;
+  files: number;
+  total_bytes: number;
+};
+
+/** Cost of protecting a path, from the service's `EstimatePath`. */
+export type PathEstimate = {
+  path: string;
+  files: number;
+  directories: number;
+  total_bytes: number;
+  unreadable: number;
+  hash_rate_bytes_per_sec: number;
+  sampled_bytes: number;
+  estimated_baseline_secs: number;
+  watches: number;
+  watches_in_use: number;
+  watch_budget: number;
+  exceeds_watch_budget: boolean;
+  covered_by?: string | null;
+  suggested_exclusions: SuggestedExclusion[];
+};